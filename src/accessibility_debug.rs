@@ -0,0 +1,303 @@
+//! Hidden diagnostic overlay for "nothing previews in this view" reports.
+//!
+//! Started with `--debug-accessibility`, the app shows a small panel next to
+//! the cursor, updated several times a second, with what the Explorer hook
+//! sees there: the class of the window under the cursor and the MSAA role,
+//! name and value of the accessible element at that point. Previews work as
+//! usual alongside it. The panel ignores the mouse, so it never becomes the
+//! element it describes.
+
+use crate::overlay;
+use crate::preview_window::rgba_to_bgra;
+use crate::RUNNING;
+use std::ffi::OsString;
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, SIZE, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, SelectObject, AC_SRC_ALPHA,
+    AC_SRC_OVER, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLENDFUNCTION, DIB_RGB_COLORS,
+};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Variant::VARIANT;
+use windows::Win32::UI::Accessibility::{AccessibleObjectFromPoint, GetRoleTextW, IAccessible};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClassNameW, GetCursorPos,
+    PeekMessageW, RegisterClassExW, SetWindowPos, ShowWindow, TranslateMessage,
+    UpdateLayeredWindow, WindowFromPoint, HWND_TOPMOST, MSG, PM_REMOVE, SWP_NOACTIVATE, SWP_NOMOVE,
+    SWP_NOSIZE, SW_SHOWNOACTIVATE, ULW_ALPHA, WNDCLASSEXW, WS_EX_LAYERED, WS_EX_NOACTIVATE,
+    WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+};
+
+const PANEL_CLASS: PCWSTR = w!("RustHoverPreviewAccessibilityDebug");
+const REFRESH_INTERVAL: Duration = Duration::from_millis(150);
+/// Where the panel sits relative to the cursor hotspot.
+const CURSOR_OFFSET: (i32, i32) = (16, 24);
+/// Longest value shown per field; element values can be whole documents.
+const MAX_FIELD_CHARS: usize = 72;
+/// Approximate advance of the panel's monospace font, for sizing the panel.
+const PANEL_CHAR_PX: u32 = 8;
+const PANEL_LINE_PX: u32 = 17;
+const PANEL_PADDING_PX: u32 = 14;
+
+/// Whether the app was started with `--debug-accessibility`.
+pub fn requested(args: impl IntoIterator<Item = OsString>) -> bool {
+    args.into_iter()
+        .skip(1)
+        .any(|arg| arg == "--debug-accessibility")
+}
+
+/// Follow the cursor with the panel until the app exits.
+pub fn run() {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        if let Some(panel) = create_panel() {
+            let mut shown: Vec<String> = Vec::new();
+            while RUNNING.load(Ordering::Acquire) {
+                pump_messages();
+                let mut cursor = POINT::default();
+                if GetCursorPos(&mut cursor).is_ok() {
+                    let lines = describe_point(cursor);
+                    if lines != shown {
+                        paint_panel(panel, &lines);
+                        shown = lines;
+                    }
+                    move_panel(panel, cursor);
+                }
+                std::thread::sleep(REFRESH_INTERVAL);
+            }
+            let _ = DestroyWindow(panel);
+        }
+        CoUninitialize();
+    }
+}
+
+/// The panel's lines for the element at `point`.
+unsafe fn describe_point(point: POINT) -> Vec<String> {
+    let hwnd = WindowFromPoint(point);
+    let mut class = [0u16; 256];
+    let len = GetClassNameW(hwnd, &mut class).max(0) as usize;
+    let class = String::from_utf16_lossy(&class[..len]);
+    let mut lines = vec![field(
+        "window",
+        &format!("{} ({:#x})", class, hwnd.0 as usize),
+    )];
+
+    let mut accessible: Option<IAccessible> = None;
+    let mut child = VARIANT::default();
+    if AccessibleObjectFromPoint(point, &mut accessible, &mut child).is_err() {
+        lines.push(field("role", "(no accessible object)"));
+        return lines;
+    }
+    let Some(accessible) = accessible else {
+        lines.push(field("role", "(no accessible object)"));
+        return lines;
+    };
+
+    let role = accessible
+        .get_accRole(&child)
+        .ok()
+        .and_then(|role| i32::try_from(&role).ok())
+        .map(|role| role_text(role as u32))
+        .unwrap_or_default();
+    let child_id = i32::try_from(&child).unwrap_or(0);
+    lines.push(field("role", &format!("{} (child {})", role, child_id)));
+    let name = accessible.get_accName(&child).map(|name| name.to_string());
+    lines.push(field("name", &name.unwrap_or_default()));
+    let value = accessible
+        .get_accValue(&child)
+        .map(|value| value.to_string());
+    lines.push(field("value", &value.unwrap_or_default()));
+    lines
+}
+
+unsafe fn role_text(role: u32) -> String {
+    let mut text = [0u16; 128];
+    let len = GetRoleTextW(role, Some(&mut text)) as usize;
+    String::from_utf16_lossy(&text[..len.min(text.len())])
+}
+
+/// `label: value` on one line, with the value shortened to fit the panel.
+fn field(label: &str, value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    let value = if value.is_empty() {
+        "(empty)".to_string()
+    } else {
+        value
+    };
+    let mut line = format!("{:<6} ", format!("{}:", label));
+    if value.chars().count() > MAX_FIELD_CHARS {
+        line.extend(value.chars().take(MAX_FIELD_CHARS - 3));
+        line.push_str("...");
+    } else {
+        line.push_str(&value);
+    }
+    line
+}
+
+unsafe extern "system" fn panel_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+unsafe fn create_panel() -> Option<HWND> {
+    let hinstance = GetModuleHandleW(None).ok()?;
+    let wc = WNDCLASSEXW {
+        cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+        lpfnWndProc: Some(panel_proc),
+        hInstance: hinstance.into(),
+        lpszClassName: PANEL_CLASS,
+        ..Default::default()
+    };
+    RegisterClassExW(&wc);
+
+    // Layered and transparent, so the mouse and hit-testing pass through it.
+    CreateWindowExW(
+        WS_EX_LAYERED | WS_EX_TRANSPARENT | WS_EX_TOOLWINDOW | WS_EX_TOPMOST | WS_EX_NOACTIVATE,
+        PANEL_CLASS,
+        w!("Accessibility Debug"),
+        WS_POPUP,
+        0,
+        0,
+        1,
+        1,
+        None,
+        None,
+        hinstance,
+        None,
+    )
+    .ok()
+}
+
+unsafe fn pump_messages() {
+    let mut msg = MSG::default();
+    while PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+        let _ = TranslateMessage(&msg);
+        DispatchMessageW(&msg);
+    }
+}
+
+unsafe fn move_panel(panel: HWND, cursor: POINT) {
+    let position = POINT {
+        x: cursor.x + CURSOR_OFFSET.0,
+        y: cursor.y + CURSOR_OFFSET.1,
+    };
+    let blend = BLENDFUNCTION {
+        BlendOp: AC_SRC_OVER as u8,
+        BlendFlags: 0,
+        SourceConstantAlpha: 230,
+        AlphaFormat: AC_SRC_ALPHA as u8,
+    };
+    let _ = UpdateLayeredWindow(
+        panel,
+        None,
+        Some(&position),
+        None,
+        None,
+        None,
+        COLORREF(0),
+        Some(&blend),
+        ULW_ALPHA,
+    );
+    // Stay above the preview window, which is topmost too.
+    let _ = SetWindowPos(
+        panel,
+        HWND_TOPMOST,
+        0,
+        0,
+        0,
+        0,
+        SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+    );
+}
+
+/// Render `lines` into the panel and show it.
+unsafe fn paint_panel(panel: HWND, lines: &[String]) {
+    let longest = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0) as u32;
+    let width = longest * PANEL_CHAR_PX + PANEL_PADDING_PX * 2;
+    let height = lines.len() as u32 * PANEL_LINE_PX + PANEL_PADDING_PX * 2;
+    let Some(image) = overlay::render_text_panel(width, height, lines) else {
+        return;
+    };
+    let pixels = rgba_to_bgra(image.as_raw());
+
+    let bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mem_dc = CreateCompatibleDC(None);
+    if mem_dc.0.is_null() {
+        return;
+    }
+    let mut bits: *mut core::ffi::c_void = ptr::null_mut();
+    let Ok(bitmap) = CreateDIBSection(mem_dc, &bmi, DIB_RGB_COLORS, &mut bits, None, 0) else {
+        let _ = DeleteDC(mem_dc);
+        return;
+    };
+    if !bits.is_null() {
+        ptr::copy_nonoverlapping(pixels.as_ptr(), bits as *mut u8, pixels.len());
+        let old_bitmap = SelectObject(mem_dc, bitmap);
+        let size = SIZE {
+            cx: width as i32,
+            cy: height as i32,
+        };
+        let source = POINT::default();
+        let blend = BLENDFUNCTION {
+            BlendOp: AC_SRC_OVER as u8,
+            BlendFlags: 0,
+            SourceConstantAlpha: 230,
+            AlphaFormat: AC_SRC_ALPHA as u8,
+        };
+        let _ = UpdateLayeredWindow(
+            panel,
+            None,
+            None,
+            Some(&size),
+            mem_dc,
+            Some(&source),
+            COLORREF(0),
+            Some(&blend),
+            ULW_ALPHA,
+        );
+        SelectObject(mem_dc, old_bitmap);
+        let _ = ShowWindow(panel, SW_SHOWNOACTIVATE);
+    }
+    let _ = DeleteObject(bitmap);
+    let _ = DeleteDC(mem_dc);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_labelled_on_one_shortened_line() {
+        assert_eq!(field("name", "photo.jpg"), "name:  photo.jpg");
+        assert_eq!(field("value", ""), "value: (empty)");
+        assert_eq!(field("name", "a\r\nb"), "name:  a  b");
+        let long = field("value", &"x".repeat(200));
+        assert_eq!(long.chars().count(), 7 + MAX_FIELD_CHARS);
+        assert!(long.ends_with("..."));
+        assert!(requested(["app".into(), "--debug-accessibility".into()]));
+        assert!(!requested(["--debug-accessibility".into()]));
+    }
+}
@@ -0,0 +1,224 @@
+//! Markup for pinned previews: arrows, rectangles, freehand strokes and text.
+//!
+//! Shapes are kept as geometry in pinned-frame pixels and rasterized onto a
+//! pixel buffer at a given scale, so saved and copied images get strokes drawn
+//! at the original's resolution instead of upscaled from the pinned frame.
+
+use crate::overlay;
+
+/// Annotation colour (#e5392b) in BGRA order; see [`rgba`] for RGBA buffers.
+pub const COLOR_BGRA: [u8; 4] = [0x2b, 0x39, 0xe5, 0xff];
+/// Stroke width and text size at pinned-frame scale.
+const STROKE_PX: f32 = 3.0;
+const ARROW_HEAD_PX: f32 = 14.0;
+const TEXT_PX: u32 = 20;
+const TEXT_FONT_FACE: &str = "Segoe UI";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Redact,
+    Arrow,
+    Rectangle,
+    Freehand,
+    Text,
+}
+
+impl Tool {
+    /// Tool selected by a letter key: R, A, B (box), D (draw) or T.
+    pub fn from_key(key: char) -> Option<Self> {
+        match key.to_ascii_lowercase() {
+            'r' => Some(Tool::Redact),
+            'a' => Some(Tool::Arrow),
+            'b' => Some(Tool::Rectangle),
+            'd' => Some(Tool::Freehand),
+            't' => Some(Tool::Text),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tool::Redact => "redact",
+            Tool::Arrow => "arrow",
+            Tool::Rectangle => "box",
+            Tool::Freehand => "draw",
+            Tool::Text => "text",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Arrow { from: (i32, i32), to: (i32, i32) },
+    Rectangle { from: (i32, i32), to: (i32, i32) },
+    Freehand(Vec<(i32, i32)>),
+    Text { at: (i32, i32), text: String },
+}
+
+impl Annotation {
+    /// Draw onto a 4-byte-per-pixel `width`x`height` buffer whose pixels are
+    /// `scale` times the pinned frame's, in `color` (given in buffer order).
+    pub fn draw(&self, pixels: &mut [u8], width: u32, height: u32, scale: f32, color: [u8; 4]) {
+        let at = |(x, y): (i32, i32)| (x as f32 * scale, y as f32 * scale);
+        let radius = (STROKE_PX * scale / 2.0).max(0.5);
+        let mut canvas = Canvas {
+            pixels,
+            width,
+            height,
+            color,
+        };
+        match self {
+            Annotation::Arrow { from, to } => {
+                let (from, to) = (at(*from), at(*to));
+                canvas.line(from, to, radius);
+                let angle = (from.1 - to.1).atan2(from.0 - to.0);
+                let head = ARROW_HEAD_PX * scale;
+                for side in [-0.45f32, 0.45] {
+                    let tip = (
+                        to.0 + head * (angle + side).cos(),
+                        to.1 + head * (angle + side).sin(),
+                    );
+                    canvas.line(to, tip, radius);
+                }
+            }
+            Annotation::Rectangle { from, to } => {
+                let (a, b) = (at(*from), at(*to));
+                canvas.line((a.0, a.1), (b.0, a.1), radius);
+                canvas.line((b.0, a.1), (b.0, b.1), radius);
+                canvas.line((b.0, b.1), (a.0, b.1), radius);
+                canvas.line((a.0, b.1), (a.0, a.1), radius);
+            }
+            Annotation::Freehand(points) => {
+                if let [only] = points.as_slice() {
+                    canvas.disc(at(*only), radius);
+                }
+                for pair in points.windows(2) {
+                    canvas.line(at(pair[0]), at(pair[1]), radius);
+                }
+            }
+            Annotation::Text { at: origin, text } => {
+                canvas.text(at(*origin), text, ((TEXT_PX as f32 * scale) as u32).max(8));
+            }
+        }
+    }
+}
+
+/// `color` converted between BGRA and RGBA order.
+pub fn rgba(color: [u8; 4]) -> [u8; 4] {
+    [color[2], color[1], color[0], color[3]]
+}
+
+struct Canvas<'a> {
+    pixels: &'a mut [u8],
+    width: u32,
+    height: u32,
+    color: [u8; 4],
+}
+
+impl Canvas<'_> {
+    /// Blend `color` over the pixel at `(x, y)` with `coverage` (0-255).
+    fn blend(&mut self, x: i32, y: i32, coverage: u32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || coverage == 0 {
+            return;
+        }
+        let offset = (y as usize * self.width as usize + x as usize) * 4;
+        let px = &mut self.pixels[offset..offset + 4];
+        for (channel, target) in px.iter_mut().zip(self.color.iter()).take(3) {
+            let mixed = *channel as u32 * (255 - coverage) + *target as u32 * coverage;
+            *channel = (mixed / 255) as u8;
+        }
+        px[3] = px[3].max(coverage as u8);
+    }
+
+    fn disc(&mut self, (cx, cy): (f32, f32), radius: f32) {
+        let reach = radius.ceil() as i32;
+        let (x0, y0) = (cx.round() as i32, cy.round() as i32);
+        for y in y0 - reach..=y0 + reach {
+            for x in x0 - reach..=x0 + reach {
+                let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.blend(x, y, 255);
+                }
+            }
+        }
+    }
+
+    /// A round-capped line, stamped as discs at sub-radius spacing.
+    fn line(&mut self, from: (f32, f32), to: (f32, f32), radius: f32) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = (dx * dx + dy * dy).sqrt();
+        let steps = (length / (radius / 2.0).max(0.5)).ceil().max(1.0) as u32;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            self.disc((from.0 + dx * t, from.1 + dy * t), radius);
+        }
+    }
+
+    fn text(&mut self, (x, y): (f32, f32), text: &str, font_px: u32) {
+        if text.is_empty() {
+            return;
+        }
+        let line_height = font_px + font_px / 3;
+        let mask_width = (text.chars().count() as u32 * font_px).max(1);
+        let lines = [text.to_string()];
+        let Some(mask) = overlay::render_text_mask(
+            mask_width,
+            line_height,
+            &lines,
+            TEXT_FONT_FACE,
+            font_px,
+            line_height,
+            0,
+        ) else {
+            return;
+        };
+        let (left, top) = (x.round() as i32, y.round() as i32);
+        for (index, coverage) in mask.iter().enumerate() {
+            let mx = (index % mask_width as usize) as i32;
+            let my = (index / mask_width as usize) as i32;
+            self.blend(left + mx, top + my, *coverage as u32);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let offset = ((y * width + x) * 4) as usize;
+        [
+            pixels[offset],
+            pixels[offset + 1],
+            pixels[offset + 2],
+            pixels[offset + 3],
+        ]
+    }
+
+    #[test]
+    fn shapes_are_scaled_onto_the_target_buffer() {
+        let (width, height) = (40u32, 40u32);
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let rectangle = Annotation::Rectangle {
+            from: (2, 2),
+            to: (8, 8),
+        };
+        rectangle.draw(&mut pixels, width, height, 4.0, COLOR_BGRA);
+
+        assert_eq!(pixel(&pixels, width, 8, 20), COLOR_BGRA);
+        assert_eq!(pixel(&pixels, width, 32, 32), COLOR_BGRA);
+        assert_eq!(pixel(&pixels, width, 20, 20), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn freehand_dots_and_tools_by_key() {
+        let mut pixels = vec![0u8; 10 * 10 * 4];
+        Annotation::Freehand(vec![(5, 5)]).draw(&mut pixels, 10, 10, 1.0, COLOR_BGRA);
+        assert_eq!(pixel(&pixels, 10, 5, 5), COLOR_BGRA);
+        assert_eq!(pixel(&pixels, 10, 0, 0), [0, 0, 0, 0]);
+
+        assert_eq!(Tool::from_key('B'), Some(Tool::Rectangle));
+        assert_eq!(Tool::from_key('x'), None);
+        assert_eq!(rgba(COLOR_BGRA), [0xe5, 0x39, 0x2b, 0xff]);
+    }
+}
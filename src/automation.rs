@@ -0,0 +1,189 @@
+//! Read-only information about the current preview for screen readers and
+//! automation tools.
+//!
+//! With `share_preview_info` on, the preview window's title is the path of the
+//! previewed file, which UI Automation reports as the window's Name (and
+//! raises a name-changed event for), and the named shared memory
+//! `Local\RustHoverPreview.Current` describes the preview. The mapping starts
+//! with two little-endian `u32`s: a sequence number that is odd while an
+//! update is being written, and the length in UTF-16 units of the text that
+//! follows. The text is one `key=value` per line:
+//!
+//! ```text
+//! state=showing
+//! path=C:\Photos\beach.jpg
+//! type=image
+//! width=640
+//! height=480
+//! size=2483121
+//! modified=2024-05-01T09:30:12Z
+//! ```
+//!
+//! `width` and `height` are the preview's, not the file's. Without a preview
+//! the text is `state=hidden`. Readers copy the text and retry when the
+//! sequence number was odd or changed meanwhile. Only the preview thread writes.
+
+use crate::CONFIG;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HWND, INVALID_HANDLE_VALUE};
+use windows::Win32::System::Memory::{
+    CreateFileMappingW, MapViewOfFile, FILE_MAP_WRITE, PAGE_READWRITE,
+};
+use windows::Win32::UI::WindowsAndMessaging::SetWindowTextW;
+
+const MAPPING_NAME: PCWSTR = w!("Local\\RustHoverPreview.Current");
+const MAPPING_BYTES: usize = 64 * 1024;
+/// Sequence number and text length.
+const HEADER_BYTES: usize = 8;
+const DEFAULT_TITLE: PCWSTR = w!("Preview");
+
+/// Address of the mapped view, once created. The mapping stays open for the
+/// rest of the session.
+static VIEW: Mutex<Option<usize>> = Mutex::new(None);
+
+pub struct PreviewInfo {
+    pub path: PathBuf,
+    /// `image`, `animation` or `video`.
+    pub kind: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Publish the preview now on screen, or that none is when `info` is `None`.
+pub fn publish(hwnd: HWND, info: Option<&PreviewInfo>) {
+    let enabled = CONFIG.lock().map(|c| c.share_preview_info).unwrap_or(false);
+    let info = info.filter(|_| enabled);
+    unsafe {
+        let _ = match info {
+            Some(info) => SetWindowTextW(hwnd, &HSTRING::from(info.path.as_os_str())),
+            None => SetWindowTextW(hwnd, DEFAULT_TITLE),
+        };
+    }
+    let Ok(mut view) = VIEW.lock() else {
+        return;
+    };
+    if view.is_none() && enabled {
+        *view = create_view();
+    }
+    if let Some(address) = *view {
+        let text = match info {
+            Some(info) => {
+                let metadata = std::fs::metadata(&info.path).ok();
+                let size = metadata.as_ref().map(|m| m.len());
+                let modified = metadata.and_then(|m| m.modified().ok());
+                info_text(info, size, modified)
+            }
+            None => "state=hidden\n".to_string(),
+        };
+        unsafe { write_text(address, &text) };
+    }
+}
+
+fn create_view() -> Option<usize> {
+    unsafe {
+        let mapping = CreateFileMappingW(
+            INVALID_HANDLE_VALUE,
+            None,
+            PAGE_READWRITE,
+            0,
+            MAPPING_BYTES as u32,
+            MAPPING_NAME,
+        )
+        .ok()?;
+        let view = MapViewOfFile(mapping, FILE_MAP_WRITE, 0, 0, MAPPING_BYTES);
+        (!view.Value.is_null()).then_some(view.Value as usize)
+    }
+}
+
+/// Replace the text in the view at `address`, bracketed by sequence bumps.
+unsafe fn write_text(address: usize, text: &str) {
+    let sequence = &*(address as *const AtomicU32);
+    let length = &*((address + 4) as *const AtomicU32);
+    let capacity = (MAPPING_BYTES - HEADER_BYTES) / 2;
+    let units: Vec<u16> = text.encode_utf16().take(capacity).collect();
+
+    sequence.fetch_add(1, Ordering::AcqRel);
+    std::ptr::copy_nonoverlapping(
+        units.as_ptr(),
+        (address + HEADER_BYTES) as *mut u16,
+        units.len(),
+    );
+    length.store(units.len() as u32, Ordering::Relaxed);
+    sequence.fetch_add(1, Ordering::Release);
+}
+
+fn info_text(info: &PreviewInfo, size: Option<u64>, modified: Option<SystemTime>) -> String {
+    let mut text = format!(
+        "state=showing\npath={}\ntype={}\nwidth={}\nheight={}\n",
+        // Keep a path with a line break in its name on one line.
+        info.path.display().to_string().replace(['\r', '\n'], " "),
+        info.kind,
+        info.width,
+        info.height,
+    );
+    if let Some(size) = size {
+        text.push_str(&format!("size={}\n", size));
+    }
+    if let Some(seconds) = modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_secs())
+    {
+        text.push_str(&format!("modified={}\n", utc_timestamp(seconds)));
+    }
+    text
+}
+
+/// ISO 8601 UTC time for seconds since the Unix epoch.
+fn utc_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let of_day = seconds % 86_400;
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        of_day / 3_600,
+        of_day / 60 % 60,
+        of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn info_text_lists_known_fields() {
+        let info = PreviewInfo {
+            path: PathBuf::from("C:\\Photos\\beach.jpg"),
+            kind: "image",
+            width: 640,
+            height: 480,
+        };
+        let modified = UNIX_EPOCH + Duration::from_secs(1_714_555_812);
+        assert_eq!(
+            info_text(&info, Some(2_483_121), Some(modified)),
+            "state=showing\npath=C:\\Photos\\beach.jpg\ntype=image\nwidth=640\nheight=480\n\
+             size=2483121\nmodified=2024-05-01T09:30:12Z\n"
+        );
+        assert!(!info_text(&info, None, None).contains("size="));
+        assert_eq!(utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+    }
+}
@@ -0,0 +1,350 @@
+//! `--bench <folder>`: decode every previewable file in a folder with each
+//! decoder and resize filter that applies to it, and report the timings.
+//!
+//! Every configuration runs `RUNS` times per file at a `PREVIEW_SIZE` preview
+//! and its median is reported, followed by totals per configuration. Files are
+//! read once before timing so the first configuration does not also pay for
+//! the disk. The report is printed to the console the app was started from
+//! and written to `bench.txt` next to `config.ini`; without a console, that
+//! file is opened instead.
+//!
+//! Configurations:
+//! - `preview`: the provider's load as the preview thread runs it, up to the
+//!   first frame it can show (not for videos, whose load starts playback)
+//! - `image`: full decode with the `image` crate
+//! - `jpeg-dct`: JPEG decoded at 1/2, 1/4 or 1/8 scale in the DCT
+//! - `exif-thumbnail`, `shell-cache`: the progressive first-pass stand-ins
+//! - `resize-*`: scaling the full decode to the preview with each filter
+//! - `gif`, `webp`: decoding every frame of an animation
+//! - `mediafoundation`, `ffmpeg`: decoding a video's first frame
+//! - `ffprobe`, `mediafoundation-probe`: reading a video's stream details
+
+use crate::config::AppConfig;
+use crate::first_pass;
+use crate::media_foundation;
+use crate::ocr;
+use crate::preview_window::{self, MediaData};
+use crate::providers::{self, LoadConstraints};
+use crate::video;
+use image::imageops::FilterType;
+use image::GenericImageView;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use windows::core::w;
+use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+const RUNS: usize = 3;
+const PREVIEW_SIZE: (u32, u32) = (800, 600);
+const REPORT_FILE: &str = "bench.txt";
+const RESIZE_FILTERS: [(&str, FilterType); 4] = [
+    ("resize-nearest", FilterType::Nearest),
+    ("resize-triangle", FilterType::Triangle),
+    ("resize-catmullrom", FilterType::CatmullRom),
+    ("resize-lanczos3", FilterType::Lanczos3),
+];
+
+/// Median milliseconds per configuration for one file; `None` where the
+/// configuration could not decode it.
+struct FileTimings {
+    name: String,
+    bytes: u64,
+    timings: Vec<(&'static str, Option<f64>)>,
+}
+
+/// The folder after `--bench`, when the app was started to benchmark.
+pub fn folder_argument(args: impl IntoIterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.into_iter().skip(1);
+    args.find(|arg| *arg == "--bench")?;
+    args.next().map(PathBuf::from)
+}
+
+/// Benchmark the files in `folder` and publish the report.
+pub fn run(folder: &Path) {
+    let console = unsafe { AttachConsole(ATTACH_PARENT_PROCESS).is_ok() };
+    let mut files: Vec<PathBuf> = std::fs::read_dir(folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.is_file() && providers::is_supported(path))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+
+    let mut results = Vec::new();
+    for (index, path) in files.iter().enumerate() {
+        if console {
+            eprint!("\r{}/{} {}", index + 1, files.len(), file_name(path));
+        }
+        let timings = ocr::with_com(|| Some(bench_file(path))).unwrap_or_default();
+        results.push(FileTimings {
+            name: file_name(path),
+            bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            timings,
+        });
+    }
+    if console {
+        eprintln!();
+    }
+
+    let report = format_report(folder, &results);
+    if console {
+        let _ = std::io::stdout().write_all(report.as_bytes());
+    }
+    let Some(report_path) =
+        AppConfig::config_path().and_then(|config| Some(config.parent()?.join(REPORT_FILE)))
+    else {
+        return;
+    };
+    if std::fs::write(&report_path, &report).is_ok() {
+        if console {
+            println!("Saved to {}", report_path.display());
+        } else {
+            unsafe { preview_window::shell_execute(w!("open"), report_path.as_os_str(), None) };
+        }
+    }
+}
+
+fn bench_file(path: &PathBuf) -> Vec<(&'static str, Option<f64>)> {
+    // Warm the file cache so the first configuration is not the only one
+    // reading from disk.
+    let _ = std::fs::read(path);
+    let (max_width, max_height) = PREVIEW_SIZE;
+    let mut timings = Vec::new();
+    let is_video = providers::is_video(path);
+
+    if is_video {
+        let (width, height) = preview_window::video_media_dimensions(path)
+            .map(|(w, h)| preview_window::scale_dimensions(w, h, max_width, max_height))
+            .unwrap_or(PREVIEW_SIZE);
+        let cancel = AtomicBool::new(false);
+        if media_foundation::can_decode(path) {
+            timings.push((
+                "mediafoundation",
+                time(|| {
+                    media_foundation::extract_frames(path, &[0.0], (width, height), &cancel)
+                        .pop()
+                        .flatten()
+                }),
+            ));
+            timings.push((
+                "mediafoundation-probe",
+                time(|| media_foundation::probe(path)),
+            ));
+        }
+        let filter = preview_window::ffmpeg_scale_filter(width, height);
+        timings.push((
+            "ffmpeg",
+            time(|| video::extract_ffmpeg_frame(path, &filter, 0.0, (width, height), &cancel)),
+        ));
+        timings.push(("ffprobe", time(|| video::probe_info(path))));
+    } else {
+        timings.push(("preview", time(|| load_preview(path))));
+        if providers::has_extension(path, &["gif"]) {
+            timings.push(("gif", time(|| decode_gif(path))));
+        } else if providers::has_extension(path, &["webp"]) {
+            timings.push(("webp", time(|| decode_webp(path))));
+        }
+    }
+
+    if !is_video && providers::is_image(path) {
+        let is_jpeg = providers::has_extension(path, &["jpg", "jpeg", "jpe", "jfif"]);
+        let full = image::open(path).ok();
+        timings.push(("image", time(|| image::open(path).ok())));
+        if is_jpeg {
+            timings.push((
+                "jpeg-dct",
+                time(|| preview_window::decode_scaled_jpeg(path, max_width, max_height)),
+            ));
+            timings.push(("exif-thumbnail", time(|| first_pass::exif_thumbnail(path))));
+        }
+        if let Some(img) = full {
+            let (width, height) = img.dimensions();
+            let (target_width, target_height) =
+                preview_window::scale_dimensions(width, height, max_width, max_height);
+            timings.push((
+                "shell-cache",
+                time(|| first_pass::shell_cached(path, target_width, target_height)),
+            ));
+            for (name, filter) in RESIZE_FILTERS {
+                timings.push((
+                    name,
+                    time(|| Some(img.resize_exact(target_width, target_height, filter))),
+                ));
+            }
+        }
+    }
+    timings
+}
+
+/// Time a provider load, then cancel whatever it left running in the
+/// background.
+fn load_preview(path: &PathBuf) -> Option<MediaData> {
+    let provider = providers::find(path)?;
+    let (max_width, max_height) = PREVIEW_SIZE;
+    let constraints = LoadConstraints {
+        max_width,
+        max_height,
+        cancel: Arc::new(AtomicBool::new(false)),
+    };
+    let media = provider.load(path, &constraints);
+    constraints.cancel.store(true, Ordering::Release);
+    media
+}
+
+fn decode_gif(path: &Path) -> Option<usize> {
+    let mut decoder = preview_window::open_gif_decoder(path)?;
+    let mut frames = 0;
+    while decoder.read_next_frame().ok()?.is_some() {
+        frames += 1;
+    }
+    (frames > 0).then_some(frames)
+}
+
+fn decode_webp(path: &Path) -> Option<usize> {
+    let buffer = std::fs::read(path).ok()?;
+    let decoder = preview_window::open_webp_decoder(&buffer)?;
+    let frames = decoder.into_iter().count();
+    (frames > 0).then_some(frames)
+}
+
+/// Median milliseconds of `RUNS` runs, or `None` when a run fails.
+fn time<T>(mut run: impl FnMut() -> Option<T>) -> Option<f64> {
+    let mut samples = Vec::with_capacity(RUNS);
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        let result = run()?;
+        samples.push(started.elapsed().as_secs_f64() * 1000.0);
+        drop(result);
+    }
+    median(&mut samples)
+}
+
+fn median(samples: &mut [f64]) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let middle = samples.len() / 2;
+    Some(if samples.len() % 2 == 0 {
+        (samples[middle - 1] + samples[middle]) / 2.0
+    } else {
+        samples[middle]
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn format_report(folder: &Path, results: &[FileTimings]) -> String {
+    let mut report = String::new();
+    let _ = writeln!(
+        report,
+        "Decoder benchmark of {} ({} files, {}x{} preview, median of {} runs, ms)\n",
+        folder.display(),
+        results.len(),
+        PREVIEW_SIZE.0,
+        PREVIEW_SIZE.1,
+        RUNS
+    );
+    // Configurations in first-seen order, with the files they decoded and
+    // their total time.
+    let mut totals: Vec<(&str, usize, f64, usize)> = Vec::new();
+    for file in results {
+        let _ = writeln!(
+            report,
+            "{} ({:.1} MB)",
+            file.name,
+            file.bytes as f64 / (1024.0 * 1024.0)
+        );
+        for &(config, ms) in &file.timings {
+            let _ = match ms {
+                Some(ms) => writeln!(report, "  {:<22}{:>10.1}", config, ms),
+                None => writeln!(report, "  {:<22}{:>10}", config, "failed"),
+            };
+            let index = match totals.iter().position(|total| total.0 == config) {
+                Some(index) => index,
+                None => {
+                    totals.push((config, 0, 0.0, 0));
+                    totals.len() - 1
+                }
+            };
+            match ms {
+                Some(ms) => {
+                    totals[index].1 += 1;
+                    totals[index].2 += ms;
+                }
+                None => totals[index].3 += 1,
+            }
+        }
+    }
+    let _ = writeln!(
+        report,
+        "\n{:<24}{:>7}{:>8}{:>12}{:>10}",
+        "configuration", "files", "failed", "total", "mean"
+    );
+    for (config, files, total, failed) in totals {
+        let mean = if files > 0 { total / files as f64 } else { 0.0 };
+        let _ = writeln!(
+            report,
+            "{:<24}{:>7}{:>8}{:>12.1}{:>10.1}",
+            config, files, failed, total, mean
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench_argument_takes_the_following_folder() {
+        let args = |list: &[&str]| list.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            folder_argument(args(&["app.exe", "--bench", "C:\\Media"])),
+            Some(PathBuf::from("C:\\Media"))
+        );
+        assert_eq!(folder_argument(args(&["app.exe", "--bench"])), None);
+        assert_eq!(folder_argument(args(&["app.exe"])), None);
+    }
+
+    #[test]
+    fn report_totals_each_configuration() {
+        assert_eq!(median(&mut [5.0, 1.0, 3.0]), Some(3.0));
+        assert_eq!(median(&mut [4.0, 2.0]), Some(3.0));
+        assert_eq!(median(&mut []), None);
+
+        let results = [
+            FileTimings {
+                name: "a.jpg".into(),
+                bytes: 1024 * 1024,
+                timings: vec![("image", Some(10.0)), ("jpeg-dct", Some(2.0))],
+            },
+            FileTimings {
+                name: "b.jpg".into(),
+                bytes: 0,
+                timings: vec![("image", Some(30.0)), ("jpeg-dct", None)],
+            },
+        ];
+        let report = format_report(Path::new("C:\\Media"), &results);
+        assert!(report.contains("a.jpg (1.0 MB)"));
+        assert!(report.contains(&format!("  {:<22}{:>10}", "jpeg-dct", "failed")));
+        assert!(report.contains(&format!(
+            "{:<24}{:>7}{:>8}{:>12.1}{:>10.1}",
+            "image", 2, 0, 40.0, 20.0
+        )));
+        assert!(report.contains(&format!(
+            "{:<24}{:>7}{:>8}{:>12.1}{:>10.1}",
+            "jpeg-dct", 1, 1, 2.0, 2.0
+        )));
+    }
+}
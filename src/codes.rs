@@ -0,0 +1,171 @@
+//! QR code and barcode detection for still previews and pinned windows,
+//! through `rxing` (a port of ZXing).
+//!
+//! With `detect_codes` on, image previews list what they contain in a bar
+//! along the bottom. Pinned windows can copy the decoded text or open a
+//! decoded web address; other schemes are never opened.
+
+use image::{GrayImage, RgbaImage};
+
+/// Larger images are scanned downscaled; codes that survive this are still
+/// readable and scanning stays fast.
+const MAX_SCAN_SIDE: u32 = 2048;
+const MAX_BAND_LINES: usize = 3;
+const MAX_BAND_CHARS: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedCode {
+    /// Short format name, e.g. `QR` or `EAN 13`.
+    pub format: String,
+    pub text: String,
+}
+
+/// Codes in a BGRA frame as decoded for the preview window.
+pub fn detect_bgra(pixels: &[u8], width: u32, height: u32) -> Vec<DetectedCode> {
+    detect_luma(
+        luma_on_white(pixels, width, height, [2, 1, 0]),
+        width,
+        height,
+    )
+}
+
+/// Codes in an RGBA image, e.g. a pinned window's edited image.
+pub fn detect_rgba(image: &RgbaImage) -> Vec<DetectedCode> {
+    let (width, height) = image.dimensions();
+    detect_luma(
+        luma_on_white(image.as_raw(), width, height, [0, 1, 2]),
+        width,
+        height,
+    )
+}
+
+fn detect_luma(luma: Vec<u8>, width: u32, height: u32) -> Vec<DetectedCode> {
+    let (luma, width, height) = if width.max(height) > MAX_SCAN_SIDE {
+        let Some(gray) = GrayImage::from_raw(width, height, luma) else {
+            return Vec::new();
+        };
+        let scale = MAX_SCAN_SIDE as f32 / width.max(height) as f32;
+        let scaled = image::imageops::resize(
+            &gray,
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        let (width, height) = scaled.dimensions();
+        (scaled.into_raw(), width, height)
+    } else {
+        (luma, width, height)
+    };
+
+    let Ok(results) = rxing::helpers::detect_multiple_in_luma(luma, width, height) else {
+        return Vec::new();
+    };
+    let mut codes: Vec<DetectedCode> = Vec::new();
+    for result in results {
+        let code = DetectedCode {
+            format: format_label(&format!("{:?}", result.getBarcodeFormat())),
+            text: result.getText().to_string(),
+        };
+        if !code.text.is_empty() && !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+/// `QR_CODE` -> `QR`, `EAN_13` -> `EAN 13`.
+fn format_label(format: &str) -> String {
+    format.trim_end_matches("_CODE").replace('_', " ")
+}
+
+/// Lines for the preview's caption band, one per code.
+pub fn band_lines(codes: &[DetectedCode]) -> Vec<String> {
+    let mut lines: Vec<String> = codes
+        .iter()
+        .take(MAX_BAND_LINES)
+        .map(|code| {
+            let text = code.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            let mut line = format!("{}: {}", code.format, text);
+            if line.chars().count() > MAX_BAND_CHARS {
+                line = line.chars().take(MAX_BAND_CHARS - 1).collect();
+                line.push('…');
+            }
+            line
+        })
+        .collect();
+    if codes.len() > MAX_BAND_LINES {
+        lines.push(format!("+{} more", codes.len() - MAX_BAND_LINES));
+    }
+    lines
+}
+
+/// The first decoded `http`/`https` address, the only kind that is opened.
+pub fn first_web_url(codes: &[DetectedCode]) -> Option<&str> {
+    codes.iter().map(|code| code.text.trim()).find(|text| {
+        let lower = text.to_ascii_lowercase();
+        (lower.starts_with("https://") || lower.starts_with("http://"))
+            && !text.chars().any(char::is_whitespace)
+    })
+}
+
+/// Gray levels with transparency composited over white; `channels` gives the
+/// red, green and blue byte offsets within each pixel.
+fn luma_on_white(pixels: &[u8], width: u32, height: u32, channels: [usize; 3]) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .take(width as usize * height as usize)
+        .map(|px| {
+            let [r, g, b] = channels.map(|channel| px[channel] as u32);
+            let gray = (r * 299 + g * 587 + b * 114) / 1000;
+            let alpha = px[3] as u32;
+            ((gray * alpha + 255 * (255 - alpha)) / 255) as u8
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(format: &str, text: &str) -> DetectedCode {
+        DetectedCode {
+            format: format.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn only_web_addresses_are_opened() {
+        let codes = [
+            code("QR", "WIFI:S:home;T:WPA;P:secret;;"),
+            code("QR", "file:///C:/Windows/notepad.exe"),
+            code("QR", " https://example.com/ticket?id=42 "),
+        ];
+        assert_eq!(
+            first_web_url(&codes),
+            Some("https://example.com/ticket?id=42")
+        );
+        assert_eq!(first_web_url(&codes[..2]), None);
+        assert_eq!(format_label("QR_CODE"), "QR");
+        assert_eq!(format_label("EAN_13"), "EAN 13");
+    }
+
+    #[test]
+    fn band_lines_are_flattened_and_capped() {
+        let long = "x".repeat(200);
+        let codes = [
+            code("QR", "line one\nline two"),
+            code("CODE 128", &long),
+            code("EAN 13", "4006381333931"),
+            code("QR", "extra"),
+        ];
+        let lines = band_lines(&codes);
+        assert_eq!(lines[0], "QR: line one line two");
+        assert_eq!(lines[1].chars().count(), MAX_BAND_CHARS);
+        assert_eq!(lines[3], "+1 more");
+        assert_eq!(
+            luma_on_white(&[0, 0, 0, 0, 255, 255, 255, 255], 2, 1, [2, 1, 0]),
+            vec![255, 255]
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Output color management for wide-gamut displays.
+//!
+//! Decoded previews are sRGB. With `color_management` on, each composed frame
+//! is converted from sRGB to the ICC profile Windows has associated with the
+//! monitor the preview is on, as color-managed viewers do, so saturated colors
+//! are not stretched to the panel's wider gamut. Monitors without a profile, or
+//! with the standard sRGB one, are left alone.
+//!
+//! Transforms are built once per monitor and dropped when the display
+//! configuration changes. In-process videos are not converted.
+
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, MAX_PATH};
+use windows::Win32::Graphics::Gdi::{
+    CreateDCW, DeleteDC, GetMonitorInfoW, MonitorFromWindow, HMONITOR, MONITORINFO, MONITORINFOEXW,
+    MONITOR_DEFAULTTONEAREST,
+};
+use windows::Win32::Storage::FileSystem::{FILE_SHARE_READ, OPEN_EXISTING};
+use windows::Win32::UI::ColorSystem::{
+    BM_xRGBQUADS, CloseColorProfile, CreateMultiProfileTransform, DeleteColorTransform,
+    GetICMProfileW, GetStandardColorSpaceProfileW, OpenColorProfileW, TranslateBitmapBits,
+    BEST_MODE, INDEX_DONT_CARE, INTENT_RELATIVE_COLORIMETRIC, PROFILE, PROFILE_FILENAME,
+    PROFILE_READ,
+};
+
+/// `LCS_sRGB`, the standard sRGB color space ('sRGB').
+const LCS_SRGB: u32 = 0x7352_4742;
+
+/// Transforms by monitor handle; `None` for monitors that need none.
+static TRANSFORMS: Lazy<Mutex<HashMap<isize, Option<Transform>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+struct Transform(isize);
+
+impl Drop for Transform {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DeleteColorTransform(self.0);
+        }
+    }
+}
+
+pub fn enabled() -> bool {
+    CONFIG.lock().map(|c| c.color_management).unwrap_or(true)
+}
+
+/// Convert a composed BGRA frame for the monitor `hwnd` is on. Alpha is kept;
+/// premultiplied colors of translucent pixels are converted as they are,
+/// which is close enough for edges.
+pub fn apply(hwnd: HWND, pixels: &mut [u8], width: u32, height: u32) {
+    if width == 0 || height == 0 || !enabled() {
+        return;
+    }
+    let monitor = unsafe { MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST) };
+    let Ok(mut transforms) = TRANSFORMS.lock() else {
+        return;
+    };
+    let transform = transforms
+        .entry(monitor.0 as isize)
+        .or_insert_with(|| unsafe { monitor_transform(monitor) });
+    let Some(transform) = transform else {
+        return;
+    };
+
+    let stride = width * 4;
+    let mut converted = vec![0u8; pixels.len()];
+    let translated = unsafe {
+        TranslateBitmapBits(
+            transform.0,
+            pixels.as_ptr() as *const _,
+            BM_xRGBQUADS,
+            width,
+            height,
+            stride,
+            converted.as_mut_ptr() as *mut _,
+            BM_xRGBQUADS,
+            stride,
+            None,
+            LPARAM(0),
+        )
+    };
+    if !translated.as_bool() {
+        return;
+    }
+    copy_colors(pixels, &converted);
+}
+
+/// Forget the transforms, for when monitors or their profiles change.
+pub fn reset() {
+    if let Ok(mut transforms) = TRANSFORMS.lock() {
+        transforms.clear();
+    }
+}
+
+/// Take the color channels from `converted`, keeping the alpha of `pixels`.
+fn copy_colors(pixels: &mut [u8], converted: &[u8]) {
+    for (px, converted) in pixels.chunks_exact_mut(4).zip(converted.chunks_exact(4)) {
+        px[..3].copy_from_slice(&converted[..3]);
+    }
+}
+
+/// Whether a profile path names the standard sRGB profile.
+fn is_same_profile(profile: &str, srgb: &str) -> bool {
+    let file_name = |path: &str| path.rsplit(['\\', '/']).next().unwrap_or("").to_lowercase();
+    file_name(profile) == file_name(srgb)
+}
+
+unsafe fn monitor_transform(monitor: HMONITOR) -> Option<Transform> {
+    let profile = monitor_profile(monitor)?;
+    let srgb = standard_srgb_profile()?;
+    if is_same_profile(&profile, &srgb) {
+        return None;
+    }
+
+    let source = open_profile(&srgb)?;
+    let Some(target) = open_profile(&profile) else {
+        let _ = CloseColorProfile(source);
+        return None;
+    };
+    let transform = CreateMultiProfileTransform(
+        &[source, target],
+        &[INTENT_RELATIVE_COLORIMETRIC],
+        BEST_MODE,
+        INDEX_DONT_CARE,
+    );
+    let _ = CloseColorProfile(source);
+    let _ = CloseColorProfile(target);
+    (transform != 0).then_some(Transform(transform))
+}
+
+/// Path of the ICC profile associated with a monitor.
+unsafe fn monitor_profile(monitor: HMONITOR) -> Option<String> {
+    let mut info = MONITORINFOEXW {
+        monitorInfo: MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFOEXW>() as u32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    if !GetMonitorInfoW(monitor, &mut info.monitorInfo).as_bool() {
+        return None;
+    }
+
+    let device = PCWSTR(info.szDevice.as_ptr());
+    let hdc = CreateDCW(device, device, PCWSTR::null(), None);
+    if hdc.0.is_null() {
+        return None;
+    }
+    let mut buffer = [0u16; MAX_PATH as usize];
+    let mut size = buffer.len() as u32;
+    let found = GetICMProfileW(hdc, &mut size, PWSTR(buffer.as_mut_ptr())).as_bool();
+    let _ = DeleteDC(hdc);
+    found.then(|| wide_to_string(&buffer))
+}
+
+unsafe fn standard_srgb_profile() -> Option<String> {
+    let mut buffer = [0u16; MAX_PATH as usize];
+    let mut size = (buffer.len() * 2) as u32;
+    GetStandardColorSpaceProfileW(
+        PCWSTR::null(),
+        LCS_SRGB,
+        PWSTR(buffer.as_mut_ptr()),
+        &mut size,
+    )
+    .as_bool()
+    .then(|| wide_to_string(&buffer))
+}
+
+unsafe fn open_profile(path: &str) -> Option<isize> {
+    let path: Vec<u16> = path.encode_utf16().chain(Some(0)).collect();
+    let profile = PROFILE {
+        dwType: PROFILE_FILENAME,
+        pProfileData: path.as_ptr() as *mut _,
+        cbDataSize: (path.len() * 2) as u32,
+    };
+    let handle = OpenColorProfileW(&profile, PROFILE_READ, FILE_SHARE_READ.0, OPEN_EXISTING.0);
+    (handle != 0).then_some(handle)
+}
+
+fn wide_to_string(buffer: &[u16]) -> String {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_is_recognised_by_file_name_and_alpha_is_kept() {
+        let srgb = "C:\\Windows\\system32\\spool\\drivers\\color\\sRGB Color Space Profile.icm";
+        assert!(is_same_profile(
+            "C:\\Profiles\\SRGB COLOR SPACE PROFILE.ICM",
+            srgb
+        ));
+        assert!(!is_same_profile("C:\\Profiles\\Display P3.icc", srgb));
+
+        let mut pixels = [10, 20, 30, 128, 40, 50, 60, 0];
+        copy_colors(&mut pixels, &[11, 22, 33, 0, 44, 55, 66, 255]);
+        assert_eq!(pixels, [11, 22, 33, 128, 44, 55, 66, 0]);
+    }
+}
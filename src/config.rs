@@ -7,6 +7,35 @@ const CONFIG_SECTION: &str = "settings";
 pub const DEFAULT_WEBP_PLAYBACK_FPS: u32 = 90;
 pub const MAX_WEBP_PLAYBACK_FPS: u32 = 90;
 
+/// Default `image_extensions` and `video_extensions`.
+pub const DEFAULT_IMAGE_EXTENSIONS: &str = "jpg,jpeg,jpe,jfif,png,gif,bmp,ico,tiff,tif,webp";
+pub const DEFAULT_VIDEO_EXTENSIONS: &str = "mp4,webm,mkv,avi,mov,wmv,flv,m4v";
+
+pub const DEFAULT_VIDEO_SPEED: f32 = 1.0;
+pub const MIN_VIDEO_SPEED: f32 = 0.25;
+pub const MAX_VIDEO_SPEED: f32 = 4.0;
+
+/// Smallest `animation_memory_mb` accepted, enough for a few large frames.
+const MIN_ANIMATION_MEMORY_MB: u32 = 16;
+/// Smallest nonzero `memory_cap_mb` accepted, enough for one large preview.
+const MIN_MEMORY_CAP_MB: u32 = 64;
+/// Longest `crossfade_ms` accepted; longer fades get in the way of browsing.
+const MAX_CROSSFADE_MS: u32 = 1000;
+/// Longest `fade_ms` accepted.
+const MAX_FADE_MS: u32 = 1000;
+/// Least `opacity` accepted, so a preview never turns invisible.
+const MIN_OPACITY_PERCENT: u32 = 10;
+/// Smallest `panel_width` and `panel_height` accepted.
+const MIN_PANEL_SIZE_PX: u32 = 64;
+
+pub fn sanitize_video_speed(value: f32) -> f32 {
+    if value.is_finite() && value > 0.0 {
+        value.clamp(MIN_VIDEO_SPEED, MAX_VIDEO_SPEED)
+    } else {
+        DEFAULT_VIDEO_SPEED
+    }
+}
+
 pub fn sanitize_webp_playback_fps(value: u32) -> u32 {
     match value {
         0 => DEFAULT_WEBP_PLAYBACK_FPS,
@@ -15,12 +44,34 @@ pub fn sanitize_webp_playback_fps(value: u32) -> u32 {
     }
 }
 
+/// `#rrggbb` (the `#` is optional) as red, green and blue.
+fn parse_hex_color(value: &str) -> Option<[u8; 3]> {
+    let hex = value.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |at: usize| u8::from_str_radix(&hex[at..at + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn hex_color([red, green, blue]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", red, green, blue)
+}
+
+/// Executable paths are often pasted from Explorer's "Copy as path", which
+/// wraps them in quotes.
+fn unquote_path(value: &str) -> String {
+    value.trim().trim_matches('"').trim().to_string()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransparentBackground {
     Transparent,
     Black,
     White,
     Checkerboard,
+    /// The solid `matte_color`.
+    Matte,
 }
 
 impl TransparentBackground {
@@ -30,6 +81,7 @@ impl TransparentBackground {
             Self::Black => "black",
             Self::White => "white",
             Self::Checkerboard => "checkerboard",
+            Self::Matte => "matte",
         }
     }
 
@@ -39,6 +91,229 @@ impl TransparentBackground {
             "black" => Some(Self::Black),
             "white" => Some(Self::White),
             "checkerboard" => Some(Self::Checkerboard),
+            "matte" => Some(Self::Matte),
+            _ => None,
+        }
+    }
+}
+
+/// How dates shown in previews are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    /// The short date and time formats of the Windows regional settings.
+    Locale,
+    /// ISO 8601, such as `2024-05-01 14:30`.
+    Iso,
+}
+
+impl DateFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Locale => "locale",
+            Self::Iso => "iso",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "locale" => Some(Self::Locale),
+            "iso" | "iso8601" => Some(Self::Iso),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoBackend {
+    /// Media Foundation for the formats it handles natively, `ffmpeg` for the rest.
+    Auto,
+    /// Decode with `ffmpeg` and draw frames in the preview window.
+    Ffmpeg,
+    /// Decode with Windows Media Foundation, falling back to `ffmpeg` for
+    /// files it cannot open.
+    MediaFoundation,
+    /// Legacy external `ffplay` window.
+    Ffplay,
+    /// `mpv` rendering into a window of our own through `--wid`.
+    Mpv,
+}
+
+impl VideoBackend {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Ffmpeg => "ffmpeg",
+            Self::MediaFoundation => "mediafoundation",
+            Self::Ffplay => "ffplay",
+            Self::Mpv => "mpv",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "ffmpeg" => Some(Self::Ffmpeg),
+            "mediafoundation" | "mf" => Some(Self::MediaFoundation),
+            "ffplay" => Some(Self::Ffplay),
+            "mpv" => Some(Self::Mpv),
+            _ => None,
+        }
+    }
+}
+
+/// Where on the work area the fixed panel (`fixed_panel`) is docked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelDock {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl PanelDock {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TopLeft => "top_left",
+            Self::Top => "top",
+            Self::TopRight => "top_right",
+            Self::Left => "left",
+            Self::Right => "right",
+            Self::BottomLeft => "bottom_left",
+            Self::Bottom => "bottom",
+            Self::BottomRight => "bottom_right",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "top_left" => Some(Self::TopLeft),
+            "top" => Some(Self::Top),
+            "top_right" => Some(Self::TopRight),
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            "bottom_left" => Some(Self::BottomLeft),
+            "bottom" => Some(Self::Bottom),
+            "bottom_right" => Some(Self::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+/// The priority class the app runs at (`process_priority`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessPriority {
+    Idle,
+    BelowNormal,
+    Normal,
+    AboveNormal,
+}
+
+impl ProcessPriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::BelowNormal => "below_normal",
+            Self::Normal => "normal",
+            Self::AboveNormal => "above_normal",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "idle" => Some(Self::Idle),
+            "below_normal" => Some(Self::BelowNormal),
+            "normal" => Some(Self::Normal),
+            "above_normal" => Some(Self::AboveNormal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClickAction {
+    /// Clicks do nothing; the preview closes when the cursor reaches it.
+    Nothing,
+    /// Open the file with its default program.
+    Open,
+    /// Open the containing folder with the file selected.
+    OpenFolder,
+    /// Copy the file's full path to the clipboard.
+    CopyPath,
+    /// Move the preview into a pinned window.
+    Pin,
+    /// Close the preview.
+    Close,
+    /// Show the next previewable file in the folder.
+    Next,
+    /// Show the previous previewable file in the folder.
+    Previous,
+    /// Copy the frame on screen to the clipboard.
+    CopyImage,
+    /// Show Windows' "Open with" dialog for the file.
+    OpenWith,
+}
+
+impl ClickAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Nothing => "nothing",
+            Self::Open => "open",
+            Self::OpenFolder => "folder",
+            Self::CopyPath => "copy_path",
+            Self::Pin => "pin",
+            Self::Close => "close",
+            Self::Next => "next",
+            Self::Previous => "previous",
+            Self::CopyImage => "copy_image",
+            Self::OpenWith => "open_with",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "nothing" | "none" | "" => Some(Self::Nothing),
+            "open" => Some(Self::Open),
+            "folder" | "open_folder" => Some(Self::OpenFolder),
+            "copy_path" | "copy" => Some(Self::CopyPath),
+            "pin" => Some(Self::Pin),
+            "close" => Some(Self::Close),
+            "next" => Some(Self::Next),
+            "previous" | "prev" => Some(Self::Previous),
+            "copy_image" => Some(Self::CopyImage),
+            "open_with" | "openwith" => Some(Self::OpenWith),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoPreviewMode {
+    /// Live playback.
+    Play,
+    /// A 3x3 grid of frames sampled across the duration.
+    Storyboard,
+    /// A single frame taken at `video_static_seek_percent` of the duration.
+    Static,
+}
+
+impl VideoPreviewMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Play => "play",
+            Self::Storyboard => "storyboard",
+            Self::Static => "static",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "play" => Some(Self::Play),
+            "storyboard" => Some(Self::Storyboard),
+            "static" | "still" => Some(Self::Static),
             _ => None,
         }
     }
@@ -50,14 +325,139 @@ pub struct AppConfig {
     pub run_at_startup: bool,
     pub hover_delay_ms: u64,
     pub preview_enabled: bool,
+    /// Whether videos are previewed at all.
+    pub preview_videos: bool,
+    /// Whether GIFs and WebPs animate; false shows their first frame.
+    pub preview_animations: bool,
+    /// Whether video previews play their sound at `video_volume`.
+    pub play_video_audio: bool,
+    /// Extensions previewed as images and as videos, separated by commas.
+    pub image_extensions: String,
+    pub video_extensions: String,
+    /// Glob patterns of paths never previewed, separated by semicolons.
+    pub excluded_paths: String,
+    /// Whether files on network shares and mapped network drives are previewed.
+    pub preview_network_drives: bool,
+    /// Added to `hover_delay_ms` for files on network drives.
+    pub network_hover_delay_ms: u64,
+    /// How long a network share may take to answer before it is skipped for a
+    /// while; 0 waits for it as long as it takes.
+    pub network_timeout_ms: u64,
     pub enable_off_trigger_key: bool,
     pub off_trigger_key: String,
+    pub pin_key: String,
+    pub favorite_key: String,
+    pub pause_key: String,
+    /// Held to show the camera details panel over photo previews.
+    pub exif_key: String,
+    pub ocr_language: String,
     pub confirm_file_type: bool,
+    pub detect_codes: bool,
+    pub show_resolution: bool,
+    /// Show the file's name, pixel size, file size and modified date along
+    /// the bottom of previews.
+    pub metadata_bar: bool,
+    /// How dates in previews are written, always in the local time zone.
+    pub date_format: DateFormat,
+    /// Chart the red, green, blue and luma levels of still images in the
+    /// preview's top-left corner.
+    pub show_histogram: bool,
+    /// Draw a thin bar along the bottom of animations showing the frame on
+    /// screen and, while streaming, how much has been decoded.
+    pub animation_progress_bar: bool,
+    pub zoom_pan: bool,
+    /// Preview an Explorer window's selected file while its taskbar
+    /// thumbnail is hovered.
+    pub taskbar_thumbnails: bool,
+    /// Draw Explorer's preview pane for the formats in
+    /// `preview_handler::PANE_EXTENSIONS`.
+    pub preview_pane: bool,
+    /// Draw Explorer's thumbnails for the formats in
+    /// `preview_handler::THUMBNAIL_EXTENSIONS`.
+    pub explorer_thumbnails: bool,
     pub follow_cursor: bool,
+    /// Place mouse previews beside the hovered item rather than the cursor.
+    pub anchor_to_item: bool,
+    /// Show every preview in a panel of `panel_width`x`panel_height` docked at
+    /// `panel_dock`, instead of beside the cursor or item.
+    pub fixed_panel: bool,
+    pub panel_dock: PanelDock,
+    pub panel_width: u32,
+    pub panel_height: u32,
+    pub crossfade_ms: u32,
+    pub opacity: u32,
+    pub fade_ms: u32,
+    pub share_preview_info: bool,
+    pub trace_previews: bool,
+    /// Extra accessible names to ignore on hover, separated by semicolons.
+    pub container_names: String,
     pub same_file_rehover_delay_ms: u64,
+    /// How far the cursor may drift, in pixels along either axis, and still
+    /// count as resting on the same item.
+    pub dwell_radius_px: u32,
+    /// Cursor speed in pixels per second above which the cursor has not yet
+    /// settled, however little it moved; 0 for no limit.
+    pub dwell_max_speed_px_per_s: u32,
     pub webp_playback_fps: u32,
+    pub thumbnail_cache_mb: u32,
+    pub memory_cache_entries: u32,
+    pub animation_memory_mb: u32,
+    /// Memory the previews may hold in all, the one shown, the memory cache
+    /// and streaming buffers, before cached previews are dropped; 0 for no cap.
+    pub memory_cap_mb: u32,
+    /// Stop animations on their final frame once they have played as many
+    /// times as the file asks; false loops every animation forever.
+    pub respect_loop_count: bool,
+    pub high_load_cpu_percent: u32,
+    pub process_priority: ProcessPriority,
+    /// Run decodes on the efficiency cores of a hybrid CPU only.
+    pub decode_on_efficiency_cores: bool,
+    pub prefetch_neighbors: bool,
+    /// Record the folders previewed most and decode their newest stills into
+    /// the thumbnail cache at startup.
+    pub warm_start: bool,
+    pub blur_explicit: bool,
+    pub nsfw_model: String,
+    pub nsfw_classes: String,
+    pub nsfw_threshold: f32,
+    pub blur_folders: String,
+    pub reveal_key: String,
+    pub click_action_image: ClickAction,
+    pub click_action_animation: ClickAction,
+    pub click_action_video: ClickAction,
+    pub mouse_middle: ClickAction,
+    pub mouse_right: ClickAction,
+    pub mouse_x1: ClickAction,
+    pub mouse_x2: ClickAction,
     pub transparent_background: TransparentBackground,
+    pub color_management: bool,
+    /// Red, green and blue of the `matte` background.
+    pub matte_color: [u8; 3],
+    /// WAV file played when a preview appears; empty plays nothing.
+    pub show_sound: String,
+    /// WAV file played when a preview goes; empty plays nothing.
+    pub hide_sound: String,
+    /// Volume of the sound cues, 0-100.
+    pub sound_volume: u32,
     pub video_volume: u32,
+    pub video_backend: VideoBackend,
+    pub video_hover_scrub: bool,
+    pub video_preview_mode: VideoPreviewMode,
+    pub video_static_seek_percent: u32,
+    pub video_speed: f32,
+    pub video_loop: bool,
+    pub video_resume: bool,
+    pub video_player_keep_alive_ms: u64,
+    pub video_audio_track: u32,
+    pub video_subtitles: bool,
+    pub video_info_overlay: bool,
+    pub ffmpeg_path: String,
+    pub ffprobe_path: String,
+    pub ffplay_path: String,
+    pub ffplay_extra_args: String,
+    pub mpv_path: String,
+    pub ffmpeg_startup_check: bool,
+    pub dicom_redact_patient_data: bool,
 }
 
 impl Default for AppConfig {
@@ -67,14 +467,97 @@ impl Default for AppConfig {
             run_at_startup: true,
             hover_delay_ms: 0,
             preview_enabled: true,
+            preview_videos: true,
+            preview_animations: true,
+            play_video_audio: true,
+            image_extensions: DEFAULT_IMAGE_EXTENSIONS.to_string(),
+            video_extensions: DEFAULT_VIDEO_EXTENSIONS.to_string(),
+            excluded_paths: String::new(),
+            preview_network_drives: true,
+            network_hover_delay_ms: 500,
+            network_timeout_ms: 1500,
             enable_off_trigger_key: true,
             off_trigger_key: "alt".to_string(),
+            pin_key: "f8".to_string(),
+            favorite_key: "f9".to_string(),
+            pause_key: "space".to_string(),
+            exif_key: "i".to_string(),
+            ocr_language: String::new(),
             confirm_file_type: false,
+            detect_codes: false,
+            show_resolution: true,
+            metadata_bar: false,
+            date_format: DateFormat::Locale,
+            show_histogram: false,
+            animation_progress_bar: true,
+            zoom_pan: false,
+            taskbar_thumbnails: false,
+            preview_pane: false,
+            explorer_thumbnails: false,
             follow_cursor: false,
+            anchor_to_item: false,
+            fixed_panel: false,
+            panel_dock: PanelDock::BottomRight,
+            panel_width: 480,
+            panel_height: 360,
+            crossfade_ms: 100,
+            opacity: 100,
+            fade_ms: 120,
+            share_preview_info: false,
+            trace_previews: false,
+            container_names: String::new(),
             same_file_rehover_delay_ms: 750,
+            dwell_radius_px: 5,
+            dwell_max_speed_px_per_s: 0,
             webp_playback_fps: DEFAULT_WEBP_PLAYBACK_FPS,
-            transparent_background: TransparentBackground::Black,
+            thumbnail_cache_mb: 256,
+            memory_cache_entries: 8,
+            animation_memory_mb: 256,
+            memory_cap_mb: 1024,
+            respect_loop_count: true,
+            high_load_cpu_percent: 85,
+            process_priority: ProcessPriority::BelowNormal,
+            decode_on_efficiency_cores: false,
+            prefetch_neighbors: true,
+            warm_start: false,
+            blur_explicit: false,
+            nsfw_model: String::new(),
+            nsfw_classes: "1".to_string(),
+            nsfw_threshold: 0.6,
+            blur_folders: String::new(),
+            reveal_key: "shift".to_string(),
+            click_action_image: ClickAction::Nothing,
+            click_action_animation: ClickAction::Nothing,
+            click_action_video: ClickAction::Nothing,
+            mouse_middle: ClickAction::Nothing,
+            mouse_right: ClickAction::Nothing,
+            mouse_x1: ClickAction::Nothing,
+            mouse_x2: ClickAction::Nothing,
+            transparent_background: TransparentBackground::Transparent,
+            matte_color: [0x80, 0x80, 0x80],
+            color_management: true,
+            show_sound: String::new(),
+            hide_sound: String::new(),
+            sound_volume: 50,
             video_volume: 0, // Mute by default
+            video_backend: VideoBackend::Auto,
+            video_hover_scrub: true,
+            video_preview_mode: VideoPreviewMode::Play,
+            video_static_seek_percent: 10,
+            video_speed: DEFAULT_VIDEO_SPEED,
+            video_loop: true,
+            video_resume: true,
+            video_player_keep_alive_ms: 3000,
+            video_audio_track: 0,
+            video_subtitles: false,
+            video_info_overlay: true,
+            ffmpeg_path: String::new(),
+            ffprobe_path: String::new(),
+            ffplay_path: String::new(),
+            ffplay_extra_args: String::new(),
+            mpv_path: String::new(),
+            ffmpeg_startup_check: true,
+            dicom_redact_patient_data: false,
         }
     }
 }
@@ -134,6 +617,51 @@ impl AppConfig {
                 "preview_enabled",
                 Some(self.preview_enabled.to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "preview_videos",
+                Some(self.preview_videos.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "preview_animations",
+                Some(self.preview_animations.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "play_video_audio",
+                Some(self.play_video_audio.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "image_extensions",
+                Some(self.image_extensions.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_extensions",
+                Some(self.video_extensions.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "excluded_paths",
+                Some(self.excluded_paths.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "preview_network_drives",
+                Some(self.preview_network_drives.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "network_hover_delay_ms",
+                Some(self.network_hover_delay_ms.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "network_timeout_ms",
+                Some(self.network_timeout_ms.to_string()),
+            );
             ini.set(
                 CONFIG_SECTION,
                 "enable_off_trigger_key",
@@ -144,36 +672,350 @@ impl AppConfig {
                 "off_trigger_key",
                 Some(self.off_trigger_key.clone()),
             );
+            ini.set(CONFIG_SECTION, "pin_key", Some(self.pin_key.clone()));
+            ini.set(
+                CONFIG_SECTION,
+                "favorite_key",
+                Some(self.favorite_key.clone()),
+            );
+            ini.set(CONFIG_SECTION, "pause_key", Some(self.pause_key.clone()));
+            ini.set(CONFIG_SECTION, "exif_key", Some(self.exif_key.clone()));
+            ini.set(
+                CONFIG_SECTION,
+                "ocr_language",
+                Some(self.ocr_language.clone()),
+            );
             ini.set(
                 CONFIG_SECTION,
                 "confirm_file_type",
                 Some(self.confirm_file_type.to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "detect_codes",
+                Some(self.detect_codes.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "show_resolution",
+                Some(self.show_resolution.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "metadata_bar",
+                Some(self.metadata_bar.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "date_format",
+                Some(self.date_format.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "show_histogram",
+                Some(self.show_histogram.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "animation_progress_bar",
+                Some(self.animation_progress_bar.to_string()),
+            );
+            ini.set(CONFIG_SECTION, "zoom_pan", Some(self.zoom_pan.to_string()));
+            ini.set(
+                CONFIG_SECTION,
+                "taskbar_thumbnails",
+                Some(self.taskbar_thumbnails.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "preview_pane",
+                Some(self.preview_pane.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "explorer_thumbnails",
+                Some(self.explorer_thumbnails.to_string()),
+            );
             ini.set(
                 CONFIG_SECTION,
                 "follow_cursor",
                 Some(self.follow_cursor.to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "anchor_to_item",
+                Some(self.anchor_to_item.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "fixed_panel",
+                Some(self.fixed_panel.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "panel_dock",
+                Some(self.panel_dock.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "panel_width",
+                Some(self.panel_width.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "panel_height",
+                Some(self.panel_height.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "crossfade_ms",
+                Some(self.crossfade_ms.to_string()),
+            );
+            ini.set(CONFIG_SECTION, "opacity", Some(self.opacity.to_string()));
+            ini.set(CONFIG_SECTION, "fade_ms", Some(self.fade_ms.to_string()));
+            ini.set(
+                CONFIG_SECTION,
+                "share_preview_info",
+                Some(self.share_preview_info.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "trace_previews",
+                Some(self.trace_previews.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "container_names",
+                Some(self.container_names.clone()),
+            );
             ini.set(
                 CONFIG_SECTION,
                 "same_file_rehover_delay_ms",
                 Some(self.same_file_rehover_delay_ms.to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "dwell_radius_px",
+                Some(self.dwell_radius_px.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "dwell_max_speed_px_per_s",
+                Some(self.dwell_max_speed_px_per_s.to_string()),
+            );
             ini.set(
                 CONFIG_SECTION,
                 "webp_playback_fps",
                 Some(sanitize_webp_playback_fps(self.webp_playback_fps).to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "thumbnail_cache_mb",
+                Some(self.thumbnail_cache_mb.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "memory_cache_entries",
+                Some(self.memory_cache_entries.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "animation_memory_mb",
+                Some(self.animation_memory_mb.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "memory_cap_mb",
+                Some(self.memory_cap_mb.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "respect_loop_count",
+                Some(self.respect_loop_count.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "high_load_cpu_percent",
+                Some(self.high_load_cpu_percent.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "process_priority",
+                Some(self.process_priority.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "decode_on_efficiency_cores",
+                Some(self.decode_on_efficiency_cores.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "prefetch_neighbors",
+                Some(self.prefetch_neighbors.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "warm_start",
+                Some(self.warm_start.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "blur_explicit",
+                Some(self.blur_explicit.to_string()),
+            );
+            ini.set(CONFIG_SECTION, "nsfw_model", Some(self.nsfw_model.clone()));
+            ini.set(
+                CONFIG_SECTION,
+                "nsfw_classes",
+                Some(self.nsfw_classes.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "nsfw_threshold",
+                Some(self.nsfw_threshold.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "blur_folders",
+                Some(self.blur_folders.clone()),
+            );
+            ini.set(CONFIG_SECTION, "reveal_key", Some(self.reveal_key.clone()));
+            ini.set(
+                CONFIG_SECTION,
+                "click_action_image",
+                Some(self.click_action_image.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "click_action_animation",
+                Some(self.click_action_animation.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "click_action_video",
+                Some(self.click_action_video.as_str().to_string()),
+            );
+            for (key, action) in [
+                ("mouse_middle", self.mouse_middle),
+                ("mouse_right", self.mouse_right),
+                ("mouse_x1", self.mouse_x1),
+                ("mouse_x2", self.mouse_x2),
+            ] {
+                ini.set(CONFIG_SECTION, key, Some(action.as_str().to_string()));
+            }
             ini.set(
                 CONFIG_SECTION,
                 "transparent_background",
                 Some(self.transparent_background.as_str().to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "matte_color",
+                Some(hex_color(self.matte_color)),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "color_management",
+                Some(self.color_management.to_string()),
+            );
+            ini.set(CONFIG_SECTION, "show_sound", Some(self.show_sound.clone()));
+            ini.set(CONFIG_SECTION, "hide_sound", Some(self.hide_sound.clone()));
+            ini.set(
+                CONFIG_SECTION,
+                "sound_volume",
+                Some(self.sound_volume.to_string()),
+            );
             ini.set(
                 CONFIG_SECTION,
                 "video_volume",
                 Some(self.video_volume.to_string()),
             );
+            ini.set(
+                CONFIG_SECTION,
+                "video_backend",
+                Some(self.video_backend.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_hover_scrub",
+                Some(self.video_hover_scrub.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_preview_mode",
+                Some(self.video_preview_mode.as_str().to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_static_seek_percent",
+                Some(self.video_static_seek_percent.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_speed",
+                Some(sanitize_video_speed(self.video_speed).to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_loop",
+                Some(self.video_loop.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_resume",
+                Some(self.video_resume.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_player_keep_alive_ms",
+                Some(self.video_player_keep_alive_ms.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_audio_track",
+                Some(self.video_audio_track.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_subtitles",
+                Some(self.video_subtitles.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "video_info_overlay",
+                Some(self.video_info_overlay.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "ffmpeg_path",
+                Some(self.ffmpeg_path.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "ffprobe_path",
+                Some(self.ffprobe_path.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "ffplay_path",
+                Some(self.ffplay_path.clone()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "ffplay_extra_args",
+                Some(self.ffplay_extra_args.clone()),
+            );
+            ini.set(CONFIG_SECTION, "mpv_path", Some(self.mpv_path.clone()));
+            ini.set(
+                CONFIG_SECTION,
+                "ffmpeg_startup_check",
+                Some(self.ffmpeg_startup_check.to_string()),
+            );
+            ini.set(
+                CONFIG_SECTION,
+                "dicom_redact_patient_data",
+                Some(self.dicom_redact_patient_data.to_string()),
+            );
             let _ = ini.write(path.to_string_lossy().as_ref());
         }
     }
@@ -188,6 +1030,34 @@ impl AppConfig {
         if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "preview_enabled") {
             self.preview_enabled = value;
         }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "preview_videos") {
+            self.preview_videos = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "preview_animations") {
+            self.preview_animations = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "play_video_audio") {
+            self.play_video_audio = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "image_extensions") {
+            self.image_extensions = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "video_extensions") {
+            self.video_extensions = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "excluded_paths") {
+            self.excluded_paths = value.trim().to_string();
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "preview_network_drives") {
+            self.preview_network_drives = value;
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "network_hover_delay_ms") {
+            self.network_hover_delay_ms = value;
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "network_timeout_ms") {
+            // 0 turns the timeout off.
+            self.network_timeout_ms = value;
+        }
         if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "enable_off_trigger_key") {
             self.enable_off_trigger_key = value;
         }
@@ -197,29 +1067,316 @@ impl AppConfig {
                 self.off_trigger_key = value.to_string();
             }
         }
+        if let Some(value) = ini.get(CONFIG_SECTION, "pin_key") {
+            // Empty disables pinning.
+            self.pin_key = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "favorite_key") {
+            // Empty disables starring favorites.
+            self.favorite_key = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "pause_key") {
+            // Empty disables pausing animations.
+            self.pause_key = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "exif_key") {
+            // Empty disables the camera details panel.
+            self.exif_key = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "ocr_language") {
+            // Empty uses the user's profile languages.
+            self.ocr_language = value.trim().to_string();
+        }
         if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "confirm_file_type") {
             self.confirm_file_type = value;
         }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "detect_codes") {
+            self.detect_codes = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "show_resolution") {
+            self.show_resolution = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "metadata_bar") {
+            self.metadata_bar = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "date_format") {
+            if let Some(format) = DateFormat::from_str(&value) {
+                self.date_format = format;
+            }
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "show_histogram") {
+            self.show_histogram = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "animation_progress_bar") {
+            self.animation_progress_bar = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "zoom_pan") {
+            self.zoom_pan = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "taskbar_thumbnails") {
+            self.taskbar_thumbnails = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "preview_pane") {
+            self.preview_pane = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "explorer_thumbnails") {
+            self.explorer_thumbnails = value;
+        }
         if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "follow_cursor") {
             self.follow_cursor = value;
         }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "anchor_to_item") {
+            self.anchor_to_item = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "fixed_panel") {
+            self.fixed_panel = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "panel_dock") {
+            if let Some(dock) = PanelDock::from_str(&value) {
+                self.panel_dock = dock;
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "panel_width") {
+            if let Ok(value) = u32::try_from(value) {
+                self.panel_width = value.max(MIN_PANEL_SIZE_PX);
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "panel_height") {
+            if let Ok(value) = u32::try_from(value) {
+                self.panel_height = value.max(MIN_PANEL_SIZE_PX);
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "crossfade_ms") {
+            // 0 swaps previews without a fade.
+            if let Ok(value) = u32::try_from(value) {
+                self.crossfade_ms = value.min(MAX_CROSSFADE_MS);
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "opacity") {
+            if let Ok(value) = u32::try_from(value) {
+                self.opacity = value.clamp(MIN_OPACITY_PERCENT, 100);
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "fade_ms") {
+            // 0 shows and hides previews at once.
+            if let Ok(value) = u32::try_from(value) {
+                self.fade_ms = value.min(MAX_FADE_MS);
+            }
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "share_preview_info") {
+            self.share_preview_info = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "trace_previews") {
+            self.trace_previews = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "container_names") {
+            self.container_names = value.trim().to_string();
+        }
         if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "same_file_rehover_delay_ms") {
             self.same_file_rehover_delay_ms = value;
         }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "dwell_radius_px") {
+            if let Ok(value) = u32::try_from(value) {
+                self.dwell_radius_px = value;
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "dwell_max_speed_px_per_s") {
+            if let Ok(value) = u32::try_from(value) {
+                self.dwell_max_speed_px_per_s = value;
+            }
+        }
         if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "webp_playback_fps") {
             if let Ok(value) = u32::try_from(value) {
                 self.webp_playback_fps = sanitize_webp_playback_fps(value);
             }
         }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "thumbnail_cache_mb") {
+            // 0 disables the cache.
+            self.thumbnail_cache_mb = u32::try_from(value).unwrap_or(u32::MAX);
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "memory_cache_entries") {
+            // 0 disables the cache.
+            self.memory_cache_entries = u32::try_from(value).unwrap_or(u32::MAX);
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "animation_memory_mb") {
+            self.animation_memory_mb = u32::try_from(value)
+                .unwrap_or(u32::MAX)
+                .max(MIN_ANIMATION_MEMORY_MB);
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "memory_cap_mb") {
+            // 0 turns the cap off.
+            self.memory_cap_mb = match u32::try_from(value).unwrap_or(u32::MAX) {
+                0 => 0,
+                value => value.max(MIN_MEMORY_CAP_MB),
+            };
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "respect_loop_count") {
+            self.respect_loop_count = value;
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "high_load_cpu_percent") {
+            // 0 turns adaptive quality off.
+            if let Ok(value) = u32::try_from(value) {
+                self.high_load_cpu_percent = value.min(100);
+            }
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "process_priority") {
+            if let Some(priority) = ProcessPriority::from_str(&value) {
+                self.process_priority = priority;
+            }
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "decode_on_efficiency_cores") {
+            self.decode_on_efficiency_cores = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "prefetch_neighbors") {
+            self.prefetch_neighbors = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "warm_start") {
+            self.warm_start = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "blur_explicit") {
+            self.blur_explicit = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "nsfw_model") {
+            self.nsfw_model = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "nsfw_classes") {
+            self.nsfw_classes = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "nsfw_threshold") {
+            if let Ok(value) = value.trim().parse::<f32>() {
+                if value.is_finite() {
+                    self.nsfw_threshold = value.clamp(0.0, 1.0);
+                }
+            }
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "blur_folders") {
+            self.blur_folders = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "reveal_key") {
+            self.reveal_key = value.trim().to_string();
+        }
+        for (key, action) in [
+            ("click_action_image", &mut self.click_action_image),
+            ("click_action_animation", &mut self.click_action_animation),
+            ("click_action_video", &mut self.click_action_video),
+            ("mouse_middle", &mut self.mouse_middle),
+            ("mouse_right", &mut self.mouse_right),
+            ("mouse_x1", &mut self.mouse_x1),
+            ("mouse_x2", &mut self.mouse_x2),
+        ] {
+            let parsed = ini
+                .get(CONFIG_SECTION, key)
+                .and_then(|value| ClickAction::from_str(&value));
+            if let Some(parsed) = parsed {
+                *action = parsed;
+            }
+        }
         if let Some(value) = ini.get(CONFIG_SECTION, "transparent_background") {
             if let Some(background) = TransparentBackground::from_str(&value) {
                 self.transparent_background = background;
             }
         }
+        if let Some(value) = ini.get(CONFIG_SECTION, "matte_color") {
+            if let Some(color) = parse_hex_color(&value) {
+                self.matte_color = color;
+            }
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "color_management") {
+            self.color_management = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "show_sound") {
+            self.show_sound = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "hide_sound") {
+            self.hide_sound = value.trim().to_string();
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "sound_volume") {
+            self.sound_volume = value.min(100) as u32;
+        }
         if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "video_volume") {
             if let Ok(value) = u32::try_from(value) {
                 self.video_volume = value;
             }
         }
+        if let Some(value) = ini.get(CONFIG_SECTION, "video_backend") {
+            if let Some(backend) = VideoBackend::from_str(&value) {
+                self.video_backend = backend;
+            }
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "video_hover_scrub") {
+            self.video_hover_scrub = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "video_preview_mode") {
+            if let Some(mode) = VideoPreviewMode::from_str(&value) {
+                self.video_preview_mode = mode;
+            }
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "video_static_seek_percent") {
+            if let Ok(value) = u32::try_from(value) {
+                self.video_static_seek_percent = value.min(100);
+            }
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "video_speed") {
+            if let Ok(value) = value
+                .trim()
+                .trim_end_matches(|c| c == 'x' || c == 'X')
+                .parse::<f32>()
+            {
+                self.video_speed = sanitize_video_speed(value);
+            }
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "video_loop") {
+            self.video_loop = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "video_resume") {
+            self.video_resume = value;
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "video_player_keep_alive_ms") {
+            self.video_player_keep_alive_ms = value;
+        }
+        if let Ok(Some(value)) = ini.getuint(CONFIG_SECTION, "video_audio_track") {
+            self.video_audio_track = u32::try_from(value).unwrap_or(0);
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "video_subtitles") {
+            self.video_subtitles = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "video_info_overlay") {
+            self.video_info_overlay = value;
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "ffmpeg_path") {
+            self.ffmpeg_path = unquote_path(&value);
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "ffprobe_path") {
+            self.ffprobe_path = unquote_path(&value);
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "ffplay_path") {
+            self.ffplay_path = unquote_path(&value);
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "ffplay_extra_args") {
+            self.ffplay_extra_args = value.trim().to_string();
+        }
+        if let Some(value) = ini.get(CONFIG_SECTION, "mpv_path") {
+            self.mpv_path = unquote_path(&value);
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "ffmpeg_startup_check") {
+            self.ffmpeg_startup_check = value;
+        }
+        if let Ok(Some(value)) = ini.getboolcoerce(CONFIG_SECTION, "dicom_redact_patient_data") {
+            self.dicom_redact_patient_data = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors_round_trip_with_or_without_hash() {
+        assert_eq!(parse_hex_color("#1E90ff"), Some([0x1e, 0x90, 0xff]));
+        assert_eq!(parse_hex_color(" 000000 "), Some([0, 0, 0]));
+        assert_eq!(parse_hex_color("#fff"), None);
+        assert_eq!(parse_hex_color("#12345g"), None);
+        assert_eq!(hex_color([0x1e, 0x90, 0xff]), "#1e90ff");
     }
 }
@@ -0,0 +1,219 @@
+//! Applying edits to `config.ini` as soon as they are saved.
+//!
+//! The folder holding `config.ini` is watched with `ReadDirectoryChangesW`.
+//! When the file is written, or replaced by renaming another file over it as
+//! some editors save, the settings are read again and the ones acted on only
+//! when they change (`run_at_startup`, the preview pane and thumbnail
+//! registrations, process priority) are applied. Everything else is read from
+//! `CONFIG` where it is used, so it takes effect with the next preview or the
+//! next time the tray menu opens.
+
+use crate::config::AppConfig;
+use crate::preview_handler;
+use crate::priority;
+use crate::startup;
+use crate::{CONFIG, RUNNING};
+use std::os::windows::ffi::OsStrExt;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, ReadDirectoryChangesW, FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED,
+    FILE_LIST_DIRECTORY, FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE,
+    FILE_NOTIFY_CHANGE_SIZE, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
+use windows::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+use windows::Win32::System::IO::{CancelIoEx, GetOverlappedResult, OVERLAPPED};
+
+/// How often the watch wakes up to notice the app closing.
+const WAKE_INTERVAL_MS: u32 = 500;
+/// Time given an editor to finish writing before the file is read.
+const SETTLE_DELAY: Duration = Duration::from_millis(150);
+/// Wait before watching again when the folder cannot be watched, such as
+/// before the first save creates it.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+const CHANGE_BUFFER_BYTES: usize = 4096;
+
+/// Watch `config.ini` until the app closes.
+pub fn run() {
+    while RUNNING.load(Ordering::Acquire) {
+        if watch().is_none() && RUNNING.load(Ordering::Acquire) {
+            std::thread::sleep(RETRY_INTERVAL);
+        }
+    }
+}
+
+/// Watch the folder of `config.ini` until the app closes (`Some`) or the
+/// watch fails (`None`).
+fn watch() -> Option<()> {
+    let config_path = AppConfig::config_path()?;
+    let file_name = config_path.file_name()?.to_string_lossy().into_owned();
+    let folder: Vec<u16> = config_path
+        .parent()?
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    unsafe {
+        let directory = CreateFileW(
+            PCWSTR(folder.as_ptr()),
+            FILE_LIST_DIRECTORY.0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            None,
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED,
+            HANDLE::default(),
+        )
+        .ok()?;
+        let Ok(event) = CreateEventW(None, true, false, PCWSTR::null()) else {
+            let _ = CloseHandle(directory);
+            return None;
+        };
+        let watched = watch_changes(directory, event, &file_name);
+        let _ = CloseHandle(event);
+        let _ = CloseHandle(directory);
+        watched
+    }
+}
+
+unsafe fn watch_changes(directory: HANDLE, event: HANDLE, file_name: &str) -> Option<()> {
+    // ReadDirectoryChangesW needs a DWORD-aligned buffer.
+    let mut buffer = vec![0u32; CHANGE_BUFFER_BYTES / 4];
+    loop {
+        let mut overlapped = OVERLAPPED {
+            hEvent: event,
+            ..Default::default()
+        };
+        ReadDirectoryChangesW(
+            directory,
+            buffer.as_mut_ptr() as *mut _,
+            CHANGE_BUFFER_BYTES as u32,
+            false,
+            FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_LAST_WRITE | FILE_NOTIFY_CHANGE_SIZE,
+            None,
+            Some(&mut overlapped as *mut _),
+            None,
+        )
+        .ok()?;
+
+        let mut bytes = 0u32;
+        while WaitForSingleObject(event, WAKE_INTERVAL_MS) != WAIT_OBJECT_0 {
+            if !RUNNING.load(Ordering::Acquire) {
+                // The buffer must outlive the cancelled read.
+                let _ = CancelIoEx(directory, Some(&overlapped as *const _));
+                let _ = GetOverlappedResult(directory, &overlapped, &mut bytes, true);
+                return Some(());
+            }
+        }
+        GetOverlappedResult(directory, &overlapped, &mut bytes, false).ok()?;
+
+        let records = std::slice::from_raw_parts(buffer.as_ptr() as *const u8, bytes as usize);
+        // No records means more changed than the buffer holds, which may
+        // include the config.
+        if bytes == 0
+            || changed_names(records)
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(file_name))
+        {
+            std::thread::sleep(SETTLE_DELAY);
+            reload();
+        }
+    }
+}
+
+/// Read `config.ini` again and apply the settings acted on when they change.
+fn reload() {
+    let Ok(mut config) = CONFIG.lock() else {
+        return;
+    };
+    let before = (
+        config.run_at_startup,
+        config.preview_pane,
+        config.explorer_thumbnails,
+    );
+    config.reload_from_disk();
+    let (run_at_startup, preview_pane, explorer_thumbnails) = (
+        config.run_at_startup,
+        config.preview_pane,
+        config.explorer_thumbnails,
+    );
+    drop(config);
+
+    if run_at_startup != before.0 {
+        if run_at_startup {
+            startup::enable_startup();
+        } else {
+            startup::disable_startup();
+        }
+    }
+    if preview_pane != before.1 {
+        if preview_pane {
+            preview_handler::register();
+        } else {
+            preview_handler::unregister();
+        }
+    }
+    if explorer_thumbnails != before.2 {
+        if explorer_thumbnails {
+            preview_handler::register_thumbnails();
+        } else {
+            preview_handler::unregister_thumbnails();
+        }
+    }
+    priority::apply_process_priority();
+}
+
+/// The file names in a buffer of `FILE_NOTIFY_INFORMATION` records.
+fn changed_names(records: &[u8]) -> Vec<String> {
+    let field = |at: usize| -> Option<usize> {
+        let bytes = records.get(at..at + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let mut names = Vec::new();
+    let mut offset = 0;
+    // Each record: next record offset, action, name length in bytes, name.
+    while let (Some(next), Some(name_len)) = (field(offset), field(offset + 8)) {
+        let Some(name) = records.get(offset + 12..offset + 12 + name_len) else {
+            break;
+        };
+        let units: Vec<u16> = name
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        names.push(String::from_utf16_lossy(&units));
+        if next == 0 {
+            break;
+        }
+        offset += next;
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(next: u32, name: &str) -> Vec<u8> {
+        let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut record = Vec::new();
+        record.extend(next.to_le_bytes());
+        record.extend(3u32.to_le_bytes());
+        record.extend((name.len() as u32).to_le_bytes());
+        record.extend(name);
+        record
+    }
+
+    #[test]
+    fn change_records_are_followed_to_the_last() {
+        let mut records = record(36, "config.ini~");
+        records.resize(36, 0);
+        records.extend(record(0, "Config.ini"));
+        assert_eq!(changed_names(&records), ["config.ini~", "Config.ini"]);
+
+        // A record cut short ends the list.
+        let cut = &records[..40];
+        assert_eq!(changed_names(cut), ["config.ini~"]);
+        assert!(changed_names(&[]).is_empty());
+    }
+}
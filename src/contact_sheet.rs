@@ -0,0 +1,349 @@
+//! Contact sheets of the preview history or the favorites.
+//!
+//! Ctrl+S in a gallery window asks where to save a contact sheet of its files:
+//! one image with a grid of thumbnails, each captioned with its file name, for
+//! sharing a review selection. It is saved as a PNG, or as a one-page PDF when
+//! the name ends in `.pdf`. Thumbnails are read from the shell at sheet size;
+//! entries that were blurred, and files the shell has no thumbnail for, use the
+//! gallery's own.
+
+use crate::first_pass;
+use crate::gallery::Thumbnail;
+use crate::overlay;
+use crate::preview_window;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use windows::core::{w, HSTRING};
+use windows::Win32::Foundation::HWND;
+use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_INPROC_SERVER};
+use windows::Win32::UI::Shell::Common::COMDLG_FILTERSPEC;
+use windows::Win32::UI::Shell::{FileSaveDialog, IFileSaveDialog, SIGDN_FILESYSPATH};
+
+const SHEET_COLUMNS: u32 = 5;
+const CELL_SIDE: u32 = 240;
+const CELL_GAP: u32 = 16;
+const LABEL_HEIGHT: u32 = 24;
+const TITLE_HEIGHT: u32 = 40;
+const MARGIN: u32 = 24;
+const FONT_FACE: &str = "Segoe UI";
+const LABEL_FONT_PX: u32 = 14;
+const TITLE_FONT_PX: u32 = 20;
+/// File names longer than this are shortened in the middle.
+const MAX_LABEL_CHARS: usize = 34;
+const TEXT_GRAY: u8 = 0x20;
+const PLACEHOLDER_GRAY: u8 = 0xE0;
+const JPEG_QUALITY: u8 = 90;
+
+/// A file to put on the sheet, with the gallery's thumbnail of it.
+pub struct SheetEntry {
+    pub path: PathBuf,
+    pub thumbnail: Option<Thumbnail>,
+    /// Use `thumbnail` even when the shell has a sharper one.
+    pub keep_thumbnail: bool,
+}
+
+/// Ask where to save a contact sheet of `entries`, then compose and save it in
+/// the background and show the result in its folder.
+pub fn export(owner: HWND, title: &str, entries: Vec<SheetEntry>) {
+    if entries.is_empty() {
+        return;
+    }
+    let Some(target) = (unsafe { ask_save_path(owner) }) else {
+        return;
+    };
+    let title = format!("{} ({} files)", title, entries.len());
+    std::thread::spawn(move || {
+        let sheet = compose(&title, &entries);
+        if save(&sheet, &target).is_some() {
+            unsafe { preview_window::show_in_folder(&target) };
+        }
+    });
+}
+
+unsafe fn ask_save_path(owner: HWND) -> Option<PathBuf> {
+    let dialog: IFileSaveDialog =
+        CoCreateInstance(&FileSaveDialog, None, CLSCTX_INPROC_SERVER).ok()?;
+    let types = [
+        COMDLG_FILTERSPEC {
+            pszName: w!("PNG image"),
+            pszSpec: w!("*.png"),
+        },
+        COMDLG_FILTERSPEC {
+            pszName: w!("PDF document"),
+            pszSpec: w!("*.pdf"),
+        },
+    ];
+    dialog.SetFileTypes(&types).ok()?;
+    dialog.SetDefaultExtension(w!("png")).ok()?;
+    dialog.SetFileName(&HSTRING::from("contact-sheet")).ok()?;
+    dialog.Show(owner).ok()?;
+    let name = dialog
+        .GetResult()
+        .ok()?
+        .GetDisplayName(SIGDN_FILESYSPATH)
+        .ok()?;
+    let path = name.to_string().ok().map(PathBuf::from);
+    CoTaskMemFree(Some(name.0 as *const _));
+    path
+}
+
+fn compose(title: &str, entries: &[SheetEntry]) -> RgbaImage {
+    let (width, height) = sheet_size(entries.len());
+    let mut sheet = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+    let title_box = (width - 2 * MARGIN, TITLE_HEIGHT);
+    draw_text(
+        &mut sheet,
+        (MARGIN, MARGIN),
+        title_box,
+        title,
+        TITLE_FONT_PX,
+    );
+
+    for (index, entry) in entries.iter().enumerate() {
+        let (left, top) = cell_origin(index);
+        match cell_image(entry) {
+            Some(image) => {
+                let x = left + (CELL_SIDE - image.width()) / 2;
+                let y = top + (CELL_SIDE - image.height()) / 2;
+                image::imageops::overlay(&mut sheet, &image, x as i64, y as i64);
+            }
+            None => {
+                let placeholder = RgbaImage::from_pixel(
+                    CELL_SIDE,
+                    CELL_SIDE,
+                    Rgba([PLACEHOLDER_GRAY, PLACEHOLDER_GRAY, PLACEHOLDER_GRAY, 255]),
+                );
+                image::imageops::replace(&mut sheet, &placeholder, left as i64, top as i64);
+            }
+        }
+        let name = entry
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let label = shorten_name(&name, MAX_LABEL_CHARS);
+        let label_box = (CELL_SIDE, LABEL_HEIGHT);
+        draw_text(
+            &mut sheet,
+            (left, top + CELL_SIDE),
+            label_box,
+            &label,
+            LABEL_FONT_PX,
+        );
+    }
+    sheet
+}
+
+/// The entry's picture scaled to fit a cell, in RGBA.
+fn cell_image(entry: &SheetEntry) -> Option<RgbaImage> {
+    let shell = (!entry.keep_thumbnail)
+        .then(|| first_pass::shell_thumbnail(&entry.path, CELL_SIDE, CELL_SIDE))
+        .flatten();
+    let image = match shell {
+        Some(image) => image,
+        None => {
+            let thumbnail = entry.thumbnail.as_ref()?;
+            let rgba = preview_window::rgba_to_bgra(&thumbnail.pixels);
+            let (width, height) = (thumbnail.width, thumbnail.height);
+            DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, rgba)?)
+        }
+    };
+    let (width, height) = fit_cell(image.width(), image.height());
+    Some(
+        image
+            .resize_exact(width, height, image::imageops::FilterType::Triangle)
+            .to_rgba8(),
+    )
+}
+
+/// Dark text over the sheet's white, left-aligned in the given box.
+fn draw_text(
+    sheet: &mut RgbaImage,
+    (left, top): (u32, u32),
+    (width, height): (u32, u32),
+    text: &str,
+    font_px: u32,
+) {
+    let line_height = font_px + font_px / 4;
+    let padding = height.saturating_sub(line_height) / 2;
+    let lines = [text.to_string()];
+    let Some(mask) = overlay::render_text_mask(
+        width,
+        height,
+        &lines,
+        FONT_FACE,
+        font_px,
+        line_height,
+        padding,
+    ) else {
+        return;
+    };
+    for (index, coverage) in mask.iter().enumerate() {
+        let (x, y) = (left + index as u32 % width, top + index as u32 / width);
+        if x >= sheet.width() || y >= sheet.height() {
+            continue;
+        }
+        let text = *coverage as u32;
+        for channel in sheet.get_pixel_mut(x, y).0.iter_mut().take(3) {
+            *channel = ((*channel as u32 * (255 - text) + TEXT_GRAY as u32 * text) / 255) as u8;
+        }
+    }
+}
+
+fn save(sheet: &RgbaImage, target: &Path) -> Option<()> {
+    let rgb = DynamicImage::ImageRgba8(sheet.clone()).to_rgb8();
+    let is_pdf = target
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+    if !is_pdf {
+        return rgb.save_with_format(target, ImageFormat::Png).ok();
+    }
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode_image(&rgb)
+        .ok()?;
+    std::fs::write(target, pdf_with_jpeg(&jpeg, sheet.width(), sheet.height())).ok()
+}
+
+fn sheet_size(count: usize) -> (u32, u32) {
+    let columns = SHEET_COLUMNS.min(count.max(1) as u32);
+    let rows = (count.max(1) as u32 + SHEET_COLUMNS - 1) / SHEET_COLUMNS;
+    let width = 2 * MARGIN + columns * CELL_SIDE + (columns - 1) * CELL_GAP;
+    let height = 2 * MARGIN + TITLE_HEIGHT + rows * (CELL_SIDE + LABEL_HEIGHT + CELL_GAP);
+    // Wide enough for the title even with a single file.
+    (width.max(2 * MARGIN + 2 * CELL_SIDE), height)
+}
+
+/// Top-left corner of cell `index`'s picture.
+fn cell_origin(index: usize) -> (u32, u32) {
+    let (row, column) = (index as u32 / SHEET_COLUMNS, index as u32 % SHEET_COLUMNS);
+    (
+        MARGIN + column * (CELL_SIDE + CELL_GAP),
+        MARGIN + TITLE_HEIGHT + row * (CELL_SIDE + LABEL_HEIGHT + CELL_GAP),
+    )
+}
+
+/// Size of a `width`x`height` picture scaled up or down to fit a cell.
+fn fit_cell(width: u32, height: u32) -> (u32, u32) {
+    let scale = CELL_SIDE as f32 / width.max(height).max(1) as f32;
+    (
+        ((width as f32 * scale).round() as u32).clamp(1, CELL_SIDE),
+        ((height as f32 * scale).round() as u32).clamp(1, CELL_SIDE),
+    )
+}
+
+/// `name` shortened to `max` characters by cutting its middle, so the start and
+/// the extension stay readable.
+fn shorten_name(name: &str, max: usize) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    if chars.len() <= max {
+        return name.to_string();
+    }
+    let tail = (max - 1) / 2;
+    let head = max - 1 - tail;
+    let mut short: String = chars[..head].iter().collect();
+    short.push('…');
+    short.extend(&chars[chars.len() - tail..]);
+    short
+}
+
+/// A one-page PDF showing a JPEG at 96 dpi.
+fn pdf_with_jpeg(jpeg: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (page_width, page_height) = (width as f32 * 0.75, height as f32 * 0.75);
+    let content = format!(
+        "q {:.2} 0 0 {:.2} 0 0 cm /Sheet Do Q",
+        page_width, page_height
+    );
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /XObject << /Sheet 4 0 R >> >> /Contents 5 0 R >>",
+            page_width, page_height
+        )
+        .into_bytes(),
+    ];
+    let mut image = format!(
+        "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB \
+         /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+        width,
+        height,
+        jpeg.len()
+    )
+    .into_bytes();
+    image.extend_from_slice(jpeg);
+    image.extend_from_slice(b"\nendstream");
+    objects.push(image);
+    objects.push(
+        format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            content.len(),
+            content
+        )
+        .into_bytes(),
+    );
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    let count = objects.len() + 1;
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", count).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            count, xref
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheet_layout_and_labels() {
+        let (width, height) = sheet_size(7);
+        assert_eq!(width, 2 * MARGIN + 5 * CELL_SIDE + 4 * CELL_GAP);
+        let row_height = CELL_SIDE + LABEL_HEIGHT + CELL_GAP;
+        assert_eq!(height, 2 * MARGIN + TITLE_HEIGHT + 2 * row_height);
+        assert_eq!(sheet_size(1).0, 2 * MARGIN + 2 * CELL_SIDE);
+        let second_row = MARGIN + TITLE_HEIGHT + row_height;
+        assert_eq!(cell_origin(6), (MARGIN + CELL_SIDE + CELL_GAP, second_row));
+
+        assert_eq!(fit_cell(120, 60), (CELL_SIDE, CELL_SIDE / 2));
+        assert_eq!(fit_cell(4000, 8000), (CELL_SIDE / 2, CELL_SIDE));
+
+        assert_eq!(shorten_name("beach.jpg", 12), "beach.jpg");
+        assert_eq!(shorten_name("holiday-2026-beach.jpg", 12), "holida…h.jpg");
+    }
+
+    #[test]
+    fn pdf_cross_references_point_at_objects() {
+        // Any ASCII payload keeps byte offsets and string offsets equal.
+        let pdf = pdf_with_jpeg(b"jpeg", 4, 2);
+        let text = String::from_utf8(pdf).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.contains("/MediaBox [0 0 3.00 1.50]"));
+        let xref = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref: usize = text[xref..].lines().next().unwrap().parse().unwrap();
+        assert!(text[xref..].starts_with("xref\n0 6\n"));
+        for (index, line) in text[xref..].lines().skip(3).take(5).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+    }
+}
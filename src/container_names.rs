@@ -0,0 +1,188 @@
+//! Telling Explorer's own elements from files under the cursor.
+//!
+//! Hovering a view's background, a column header or a group reports the
+//! element's name the same way a file item does. The element's accessibility
+//! role decides first: list items and rows are files whatever they are called,
+//! and lists, headers, groups, panes and the like never are. Only elements
+//! without a telling role, such as text cells, are judged by name.
+//!
+//! Names are localized with Windows, so the built-in list in
+//! `assets/container_names.txt` has a section per UI language; the shared and
+//! English sections are always loaded, plus the one for the user's UI
+//! language. `container_names` in the config adds more, separated by
+//! semicolons.
+
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use windows::Win32::Globalization::{GetUserDefaultUILanguage, LCIDToLocaleName};
+use windows::Win32::UI::Accessibility::{
+    UIA_DataGridControlTypeId, UIA_DataItemControlTypeId, UIA_GroupControlTypeId,
+    UIA_HeaderControlTypeId, UIA_HeaderItemControlTypeId, UIA_ListControlTypeId,
+    UIA_ListItemControlTypeId, UIA_MenuBarControlTypeId, UIA_PaneControlTypeId,
+    UIA_ScrollBarControlTypeId, UIA_StatusBarControlTypeId, UIA_TableControlTypeId,
+    UIA_TitleBarControlTypeId, UIA_ToolBarControlTypeId, UIA_WindowControlTypeId,
+    ROLE_SYSTEM_CLIENT, ROLE_SYSTEM_COLUMNHEADER, ROLE_SYSTEM_GROUPING, ROLE_SYSTEM_LIST,
+    ROLE_SYSTEM_LISTITEM, ROLE_SYSTEM_MENUBAR, ROLE_SYSTEM_PANE, ROLE_SYSTEM_ROW,
+    ROLE_SYSTEM_ROWHEADER, ROLE_SYSTEM_SCROLLBAR, ROLE_SYSTEM_STATUSBAR, ROLE_SYSTEM_TABLE,
+    ROLE_SYSTEM_TITLEBAR, ROLE_SYSTEM_TOOLBAR, ROLE_SYSTEM_WINDOW, UIA_CONTROLTYPE_ID,
+};
+
+const BUILT_IN: &str = include_str!("../assets/container_names.txt");
+/// `LOCALE_NAME_MAX_LENGTH`.
+const MAX_LOCALE_NAME: usize = 85;
+
+/// What an element's accessibility role says about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// A file's item or row: its name is the file's.
+    Item,
+    /// Part of the view around the files, never a file.
+    Container,
+    /// No role, or one either could have, such as text; the name decides.
+    Unknown,
+}
+
+impl Role {
+    /// Classify an MSAA role (`ROLE_SYSTEM_*`).
+    pub fn from_msaa(role: u32) -> Self {
+        match role {
+            ROLE_SYSTEM_LISTITEM | ROLE_SYSTEM_ROW => Role::Item,
+            ROLE_SYSTEM_LIST
+            | ROLE_SYSTEM_TABLE
+            | ROLE_SYSTEM_COLUMNHEADER
+            | ROLE_SYSTEM_ROWHEADER
+            | ROLE_SYSTEM_GROUPING
+            | ROLE_SYSTEM_PANE
+            | ROLE_SYSTEM_CLIENT
+            | ROLE_SYSTEM_WINDOW
+            | ROLE_SYSTEM_SCROLLBAR
+            | ROLE_SYSTEM_TOOLBAR
+            | ROLE_SYSTEM_STATUSBAR
+            | ROLE_SYSTEM_TITLEBAR
+            | ROLE_SYSTEM_MENUBAR => Role::Container,
+            _ => Role::Unknown,
+        }
+    }
+
+    /// Classify a UI Automation control type.
+    pub fn from_uia(control_type: UIA_CONTROLTYPE_ID) -> Self {
+        match control_type {
+            UIA_ListItemControlTypeId | UIA_DataItemControlTypeId => Role::Item,
+            UIA_ListControlTypeId
+            | UIA_TableControlTypeId
+            | UIA_DataGridControlTypeId
+            | UIA_HeaderControlTypeId
+            | UIA_HeaderItemControlTypeId
+            | UIA_GroupControlTypeId
+            | UIA_PaneControlTypeId
+            | UIA_WindowControlTypeId
+            | UIA_ScrollBarControlTypeId
+            | UIA_ToolBarControlTypeId
+            | UIA_StatusBarControlTypeId
+            | UIA_TitleBarControlTypeId
+            | UIA_MenuBarControlTypeId => Role::Container,
+            _ => Role::Unknown,
+        }
+    }
+}
+
+/// Check if an element with this role and name is part of Explorer's UI
+/// rather than a file.
+pub fn is_container(role: Role, name: &str) -> bool {
+    match role {
+        Role::Item => name.trim().is_empty(),
+        Role::Container => true,
+        Role::Unknown => is_container_name(name),
+    }
+}
+
+/// Built-in names for the UI language, lowercased.
+static NAMES: Lazy<HashSet<String>> =
+    Lazy::new(|| parse_names(BUILT_IN, &ui_language()).into_iter().collect());
+
+/// Check if a name is a container/UI element name rather than an actual file
+fn is_container_name(name: &str) -> bool {
+    let name = name.trim();
+    if name.is_empty() {
+        return true;
+    }
+    let name = name.to_lowercase();
+    if NAMES.contains(&name) {
+        return true;
+    }
+    CONFIG
+        .lock()
+        .map(|c| {
+            c.container_names
+                .split(';')
+                .any(|extra| extra.trim().to_lowercase() == name)
+        })
+        .unwrap_or(false)
+}
+
+/// The names in the shared, English and `language` sections, lowercased.
+fn parse_names(data: &str, language: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut wanted = false;
+    for line in data.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let section = section.trim();
+            wanted = section == "*"
+                || section.eq_ignore_ascii_case("en")
+                || section.eq_ignore_ascii_case(language);
+            continue;
+        }
+        if wanted {
+            names.push(line.to_lowercase());
+        }
+    }
+    names
+}
+
+/// The ISO 639-1 code of the Windows display language, such as `de`.
+fn ui_language() -> String {
+    let mut buffer = [0u16; MAX_LOCALE_NAME];
+    let len = unsafe { LCIDToLocaleName(GetUserDefaultUILanguage() as u32, Some(&mut buffer), 0) };
+    // The length includes the terminating null.
+    let len = (len.max(1) - 1) as usize;
+    let locale = String::from_utf16_lossy(&buffer[..len.min(buffer.len())]);
+    locale.split('-').next().unwrap_or("").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_english_and_ui_language_sections_are_loaded() {
+        let data = "# comment\n[*]\nShellView\n[en]\nItems View\n[de]\n Größe \n[fr]\nTaille\n";
+        assert_eq!(
+            parse_names(data, "de"),
+            ["shellview", "items view", "größe"]
+        );
+        assert_eq!(parse_names(data, "en"), ["shellview", "items view"]);
+
+        let built_in = parse_names(BUILT_IN, "de");
+        assert!(built_in.contains(&"elemente ansicht".to_string()));
+        assert!(built_in.contains(&"date modified".to_string()));
+        assert!(!built_in.contains(&"taille".to_string()));
+    }
+
+    #[test]
+    fn roles_decide_before_names() {
+        assert_eq!(Role::from_msaa(ROLE_SYSTEM_LISTITEM), Role::Item);
+        assert_eq!(Role::from_msaa(ROLE_SYSTEM_COLUMNHEADER), Role::Container);
+        assert_eq!(Role::from_uia(UIA_DataItemControlTypeId), Role::Item);
+        assert_eq!(Role::from_uia(UIA_HeaderItemControlTypeId), Role::Container);
+
+        // A file may be called anything a column is.
+        assert!(!is_container(Role::Item, "Name"));
+        assert!(is_container(Role::Item, " "));
+        assert!(is_container(Role::Container, "photo.jpg"));
+        assert!(is_container(Role::Unknown, ""));
+    }
+}
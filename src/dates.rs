@@ -0,0 +1,221 @@
+//! Dates shown in previews, such as a file's modified time in the details bar,
+//! a certificate's validity and a photo's capture time, in the local time zone
+//! where the moment is known.
+//!
+//! `date_format=locale` writes them in the short date and time formats of the
+//! Windows regional settings, as Explorer's Date modified column does, and
+//! `date_format=iso` as ISO 8601 (`2024-05-01 14:30`) whatever the region.
+//! Dates files record without a time zone, such as a DICOM study date, are
+//! written as they are.
+
+use crate::config::DateFormat;
+use crate::CONFIG;
+use std::time::{Duration, SystemTime};
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{FILETIME, SYSTEMTIME};
+use windows::Win32::Globalization::{
+    GetDateFormatEx, GetTimeFormatEx, DATE_SHORTDATE, TIME_NOSECONDS,
+};
+use windows::Win32::System::Time::{FileTimeToSystemTime, SystemTimeToTzSpecificLocalTime};
+
+/// FILETIME ticks (100 ns since 1601) at the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+/// Days from 0000-03-01 to 1970-01-01 in the proleptic Gregorian calendar.
+const UNIX_EPOCH_DAYS: i64 = 719_468;
+
+/// A date, with the time of day when there is one, as a file records it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CivilTime {
+    pub year: u16,
+    pub month: u16,
+    pub day: u16,
+    /// Hour and minute.
+    pub time: Option<(u16, u16)>,
+}
+
+impl CivilTime {
+    /// `None` unless the month, day, hour and minute are in range.
+    pub fn new(year: u16, month: u16, day: u16, time: Option<(u16, u16)>) -> Option<Self> {
+        let time_valid = time.map_or(true, |(hour, minute)| hour < 24 && minute < 60);
+        ((1..=12).contains(&month) && (1..=31).contains(&day) && time_valid).then_some(Self {
+            year,
+            month,
+            day,
+            time,
+        })
+    }
+
+    /// The moment this is, taking it to be `utc_offset_minutes` ahead of UTC.
+    /// `None` before 1970.
+    pub fn to_system_time(self, utc_offset_minutes: i32) -> Option<SystemTime> {
+        let (hour, minute) = self.time.unwrap_or((0, 0));
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        let minutes = days * 24 * 60 + hour as i64 * 60 + minute as i64;
+        let secs = (minutes - utc_offset_minutes as i64) * 60;
+        let secs = u64::try_from(secs).ok()?;
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    fn system_time(self) -> SYSTEMTIME {
+        let (hour, minute) = self.time.unwrap_or((0, 0));
+        SYSTEMTIME {
+            wYear: self.year,
+            wMonth: self.month,
+            wDay: self.day,
+            wHour: hour,
+            wMinute: minute,
+            ..Default::default()
+        }
+    }
+}
+
+/// `time` in the local time zone, written as `date_format` asks.
+pub fn format_local(time: SystemTime) -> Option<String> {
+    Some(format_system_time(&local_system_time(time)?, true))
+}
+
+/// `time` written as `date_format` asks without moving it to another time
+/// zone, for dates and times recorded in none.
+pub fn format_civil(time: CivilTime) -> String {
+    format_system_time(&time.system_time(), time.time.is_some())
+}
+
+fn format_system_time(local: &SYSTEMTIME, with_time: bool) -> String {
+    let format = CONFIG
+        .lock()
+        .map(|c| c.date_format)
+        .unwrap_or(DateFormat::Locale);
+    match format {
+        DateFormat::Locale => {
+            locale_date_time(local, with_time).unwrap_or_else(|| iso_date_time(local, with_time))
+        }
+        DateFormat::Iso => iso_date_time(local, with_time),
+    }
+}
+
+fn local_system_time(time: SystemTime) -> Option<SYSTEMTIME> {
+    let ticks = filetime_ticks(time)?;
+    let filetime = FILETIME {
+        dwLowDateTime: ticks as u32,
+        dwHighDateTime: (ticks >> 32) as u32,
+    };
+    let mut utc = SYSTEMTIME::default();
+    let mut local = SYSTEMTIME::default();
+    unsafe {
+        FileTimeToSystemTime(&filetime, &mut utc).ok()?;
+        SystemTimeToTzSpecificLocalTime(None, &utc, &mut local).ok()?;
+    }
+    Some(local)
+}
+
+/// The user's short date and, `with_time`, time without seconds, such as
+/// `5/1/2024 2:30 PM`.
+fn locale_date_time(local: &SYSTEMTIME, with_time: bool) -> Option<String> {
+    let mut date = [0u16; 80];
+    let date_len = unsafe {
+        GetDateFormatEx(
+            PCWSTR::null(),
+            DATE_SHORTDATE,
+            Some(local as *const _),
+            PCWSTR::null(),
+            Some(&mut date),
+            PCWSTR::null(),
+        )
+    };
+    // Both lengths count the terminating NUL.
+    if date_len <= 1 {
+        return None;
+    }
+    let date = String::from_utf16_lossy(&date[..date_len as usize - 1]);
+    if !with_time {
+        return Some(date);
+    }
+
+    let mut time = [0u16; 80];
+    let time_len = unsafe {
+        GetTimeFormatEx(
+            PCWSTR::null(),
+            TIME_NOSECONDS,
+            Some(local as *const _),
+            PCWSTR::null(),
+            Some(&mut time),
+        )
+    };
+    if time_len <= 1 {
+        return None;
+    }
+    Some(format!(
+        "{} {}",
+        date,
+        String::from_utf16_lossy(&time[..time_len as usize - 1])
+    ))
+}
+
+fn iso_date_time(local: &SYSTEMTIME, with_time: bool) -> String {
+    let date = format!("{:04}-{:02}-{:02}", local.wYear, local.wMonth, local.wDay);
+    if !with_time {
+        return date;
+    }
+    format!("{} {:02}:{:02}", date, local.wHour, local.wMinute)
+}
+
+/// `time` as FILETIME ticks; `None` before 1970.
+fn filetime_ticks(time: SystemTime) -> Option<u64> {
+    let since_unix = time.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+    Some(since_unix.as_nanos() as u64 / 100 + FILETIME_UNIX_EPOCH)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Counting from March puts the leap day at the end of the year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - UNIX_EPOCH_DAYS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iso_dates_are_zero_padded_to_the_minute() {
+        let local = SYSTEMTIME {
+            wYear: 2024,
+            wMonth: 5,
+            wDay: 1,
+            wHour: 9,
+            wMinute: 5,
+            wSecond: 59,
+            ..Default::default()
+        };
+        assert_eq!(iso_date_time(&local, true), "2024-05-01 09:05");
+        assert_eq!(iso_date_time(&local, false), "2024-05-01");
+
+        let leap_day = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_214_300);
+        let ticks = FILETIME_UNIX_EPOCH + 1_709_214_300 * 10_000_000;
+        assert_eq!(filetime_ticks(leap_day), Some(ticks));
+        let before_1970 = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+        assert_eq!(filetime_ticks(before_1970), None);
+    }
+
+    #[test]
+    fn civil_times_are_placed_by_their_utc_offset() {
+        let leap_day = CivilTime::new(2024, 2, 29, Some((13, 45))).unwrap();
+        let moment = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_214_300);
+        assert_eq!(leap_day.to_system_time(0), Some(moment));
+        let two_hours = Duration::from_secs(2 * 60 * 60);
+        assert_eq!(leap_day.to_system_time(120), Some(moment - two_hours));
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+
+        assert!(CivilTime::new(1969, 12, 31, None)
+            .unwrap()
+            .to_system_time(0)
+            .is_none());
+        assert!(CivilTime::new(2024, 13, 1, None).is_none());
+        assert!(CivilTime::new(2024, 1, 1, Some((24, 0))).is_none());
+    }
+}
@@ -0,0 +1,85 @@
+//! Locations never previewed (`excluded_paths`), such as confidential folders
+//! or slow network shares.
+//!
+//! Patterns are separated by semicolons and matched against the whole path of
+//! the file about to be previewed, ignoring case and reading `/` as `\`. `*`
+//! matches any run of characters, separators included, and `?` any one
+//! character. A pattern that matches a folder also covers everything in it, so
+//! `C:\Work\Confidential` and `C:\Work\Confidential\*` are the same.
+
+use crate::CONFIG;
+use std::path::Path;
+
+/// Whether `path` matches one of the `excluded_paths`.
+pub fn is_excluded(path: &Path) -> bool {
+    let patterns = CONFIG
+        .lock()
+        .map(|c| c.excluded_paths.clone())
+        .unwrap_or_default();
+    let path = normalize(&path.to_string_lossy());
+    patterns
+        .split(';')
+        .map(|pattern| pattern.trim().trim_end_matches(['\\', '/']))
+        .filter(|pattern| !pattern.is_empty())
+        .any(|pattern| {
+            let pattern = normalize(pattern);
+            let contents: Vec<char> = pattern.iter().copied().chain(['\\', '*']).collect();
+            glob_matches(&pattern, &path) || glob_matches(&contents, &path)
+        })
+}
+
+fn normalize(text: &str) -> Vec<char> {
+    text.to_lowercase()
+        .chars()
+        .map(|c| if c == '/' { '\\' } else { c })
+        .collect()
+}
+
+/// Whether all of `text` matches `pattern`, where `*` matches any run of
+/// characters and `?` any one.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // The last `*` seen and where in `text` its run currently ends.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the `*` take one more character and try again.
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        glob_matches(&normalize(pattern), &normalize(text))
+    }
+
+    #[test]
+    fn globs_match_whole_paths_ignoring_case_and_slashes() {
+        assert!(matches("\\\\nas\\*", "\\\\NAS\\photos\\2024\\a.jpg"));
+        assert!(matches("C:/Work/*/s?.png", "c:\\work\\q3\\s1.png"));
+        assert!(matches("*.mkv", "D:\\films\\a.b.mkv"));
+        assert!(!matches("*.mkv", "D:\\films\\a.mkv.jpg"));
+        assert!(!matches("C:\\Work\\*", "C:\\Workshop\\a.jpg"));
+        assert!(!matches("C:\\Work\\s?.png", "C:\\Work\\s.png"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Camera details from a photo's EXIF block, shown while `exif_key` is held.
+//!
+//! JPEGs carry EXIF in an APP1 segment near their start, and TIFF-based raw
+//! files (DNG, CR2, NEF, ARW and the like) are a TIFF structure themselves.
+//! Only what is worth a glance is read: the camera, the lens, the exposure,
+//! when the photo was taken and whether a GPS location was recorded.
+
+use crate::dates::{self, CivilTime};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Bytes read from a file's start to find its EXIF block: an APP1 segment of
+/// at most 64 KB, with as much again for the segments some cameras put first.
+pub(crate) const READ_LIMIT: u64 = 128 * 1024;
+
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_EXPOSURE_TIME: u16 = 0x829A;
+const TAG_F_NUMBER: u16 = 0x829D;
+const TAG_ISO: u16 = 0x8827;
+const TAG_FOCAL_LENGTH: u16 = 0x920A;
+const TAG_LENS_MODEL: u16 = 0xA434;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_GPS_LATITUDE: u16 = 0x0002;
+
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+const TYPE_RATIONAL: u16 = 5;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CameraDetails {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    /// Seconds, as a fraction.
+    pub exposure_time: Option<(u32, u32)>,
+    pub f_number: Option<(u32, u32)>,
+    pub iso: Option<u32>,
+    /// Millimetres, as a fraction.
+    pub focal_length: Option<(u32, u32)>,
+    /// When the photo was taken, on the camera's clock.
+    pub taken: Option<CivilTime>,
+    /// How far the camera's clock was ahead of UTC, in minutes.
+    pub taken_utc_offset: Option<i32>,
+    pub has_gps: bool,
+}
+
+/// The camera details of a JPEG or TIFF-based file, if it has an EXIF block.
+pub fn read(path: &Path) -> Option<CameraDetails> {
+    let mut head = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(READ_LIMIT)
+        .read_to_end(&mut head)
+        .ok()?;
+    parse(&head)
+}
+
+/// The TIFF structure holding the EXIF data of a file's first bytes: the
+/// APP1 segment of a JPEG, or the whole of a TIFF-based file.
+pub fn tiff_block(data: &[u8]) -> Option<&[u8]> {
+    if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        return Some(data);
+    }
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    loop {
+        let marker = *data.get(pos + 1)?;
+        if *data.get(pos)? != 0xFF || marker == 0xDA {
+            return None;
+        }
+        let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + length)?;
+        if marker == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            return Some(&segment[6..]);
+        }
+        pos += 2 + length;
+    }
+}
+
+fn parse(data: &[u8]) -> Option<CameraDetails> {
+    let tiff = Tiff::new(tiff_block(data)?)?;
+    let mut details = CameraDetails::default();
+    let ifd0 = tiff.u32_at(4)? as usize;
+    let (mut exif_ifd, mut gps_ifd) = (None, None);
+    for entry in tiff.entries(ifd0) {
+        match tiff.u16_at(entry)? {
+            TAG_MAKE => details.make = tiff.ascii(entry),
+            TAG_MODEL => details.model = tiff.ascii(entry),
+            TAG_EXIF_IFD => exif_ifd = tiff.number(entry),
+            TAG_GPS_IFD => gps_ifd = tiff.number(entry),
+            _ => {}
+        }
+    }
+    if let Some(exif_ifd) = exif_ifd.filter(|&offset| offset != 0) {
+        for entry in tiff.entries(exif_ifd as usize) {
+            match tiff.u16_at(entry)? {
+                TAG_EXPOSURE_TIME => details.exposure_time = tiff.rational(entry),
+                TAG_F_NUMBER => details.f_number = tiff.rational(entry),
+                TAG_ISO => details.iso = tiff.number(entry),
+                TAG_FOCAL_LENGTH => details.focal_length = tiff.rational(entry),
+                TAG_LENS_MODEL => details.lens = tiff.ascii(entry),
+                TAG_DATE_TIME_ORIGINAL => {
+                    details.taken = tiff.ascii(entry).and_then(|text| parse_date_time(&text))
+                }
+                TAG_OFFSET_TIME_ORIGINAL => {
+                    details.taken_utc_offset =
+                        tiff.ascii(entry).and_then(|text| parse_utc_offset(&text))
+                }
+                _ => {}
+            }
+        }
+    }
+    // Some cameras write a GPS directory without a fix in it.
+    if let Some(gps_ifd) = gps_ifd.filter(|&offset| offset != 0) {
+        details.has_gps = tiff
+            .entries(gps_ifd as usize)
+            .any(|entry| tiff.u16_at(entry) == Some(TAG_GPS_LATITUDE));
+    }
+    Some(details)
+}
+
+impl CameraDetails {
+    /// The details as lines of text, such as `Canon EOS R5`,
+    /// `RF24-105mm F4 L IS USM`, `1/250 s  ·  f/4.0  ·  ISO 400  ·  50 mm`,
+    /// `Taken 5/1/2024 2:30 PM` and `GPS location recorded`.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        lines.extend(self.camera());
+        lines.extend(self.lens.clone());
+        let exposure: Vec<String> = [
+            self.exposure_time.and_then(format_exposure_time),
+            self.f_number
+                .and_then(fraction)
+                .map(|f| format!("f/{:.1}", f)),
+            self.iso.map(|iso| format!("ISO {}", iso)),
+            self.focal_length
+                .and_then(fraction)
+                .map(|mm| format!("{} mm", trim_decimal(mm))),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !exposure.is_empty() {
+            lines.push(exposure.join("  \u{b7}  "));
+        }
+        lines.extend(self.taken().map(|taken| format!("Taken {}", taken)));
+        lines.push(
+            if self.has_gps {
+                "GPS location recorded"
+            } else {
+                "No GPS location"
+            }
+            .to_string(),
+        );
+        lines
+    }
+
+    /// When the photo was taken: in the local time zone when the camera
+    /// recorded its offset from UTC, otherwise as its clock showed.
+    fn taken(&self) -> Option<String> {
+        let taken = self.taken?;
+        let local = self
+            .taken_utc_offset
+            .and_then(|offset| taken.to_system_time(offset))
+            .and_then(dates::format_local);
+        Some(local.unwrap_or_else(|| dates::format_civil(taken)))
+    }
+
+    /// The model, led by the make unless the model already names it, as in
+    /// `NIKON CORPORATION` and `NIKON Z 6`.
+    fn camera(&self) -> Option<String> {
+        match (&self.make, &self.model) {
+            (Some(make), Some(model)) => {
+                let brand = make.split_whitespace().next().unwrap_or("").to_lowercase();
+                if model.to_lowercase().starts_with(&brand) {
+                    Some(model.clone())
+                } else {
+                    Some(format!("{} {}", make, model))
+                }
+            }
+            (make, model) => model.clone().or_else(|| make.clone()),
+        }
+    }
+}
+
+/// `2024:05:01 14:30:59`, the EXIF date and time format.
+fn parse_date_time(text: &str) -> Option<CivilTime> {
+    let (date, time) = text.trim().split_once(' ')?;
+    let mut date = date.split(':').map(|part| part.parse::<u16>().ok());
+    let mut time = time.split(':').map(|part| part.parse::<u16>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (hour, minute) = (time.next()??, time.next()??);
+    CivilTime::new(year, month, day, Some((hour, minute)))
+}
+
+/// `+02:00` or `-05:30` as minutes ahead of UTC.
+fn parse_utc_offset(text: &str) -> Option<i32> {
+    let text = text.trim();
+    let sign = match text.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = text[1..].split_once(':')?;
+    Some(sign * (hours.parse::<i32>().ok()? * 60 + minutes.parse::<i32>().ok()?))
+}
+
+/// `1/250 s` for short exposures, `2 s` or `1.3 s` for long ones.
+fn format_exposure_time((numerator, denominator): (u32, u32)) -> Option<String> {
+    let seconds = fraction((numerator, denominator))?;
+    if seconds <= 0.0 {
+        return None;
+    }
+    if seconds >= 0.5 {
+        return Some(format!("{} s", trim_decimal(seconds)));
+    }
+    Some(format!("1/{} s", (1.0 / seconds).round() as u32))
+}
+
+fn fraction((numerator, denominator): (u32, u32)) -> Option<f64> {
+    (denominator != 0).then(|| numerator as f64 / denominator as f64)
+}
+
+/// One decimal, dropped when it is zero.
+fn trim_decimal(value: f64) -> String {
+    let text = format!("{:.1}", value);
+    text.strip_suffix(".0").map(str::to_string).unwrap_or(text)
+}
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Tiff {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = [*self.data.get(offset)?, *self.data.get(offset + 1)?];
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// Offsets of the 12-byte entries of the directory at `ifd`.
+    fn entries(&self, ifd: usize) -> impl Iterator<Item = usize> {
+        let count = self.u16_at(ifd).unwrap_or(0) as usize;
+        (0..count).map(move |index| ifd + 2 + index * 12)
+    }
+
+    /// Where an entry's value is: in the entry when it fits in 4 bytes,
+    /// otherwise at the offset the entry holds.
+    fn value_offset(&self, entry: usize, size: usize) -> Option<usize> {
+        let count = self.u32_at(entry + 4)? as usize;
+        if count.checked_mul(size)? <= 4 {
+            Some(entry + 8)
+        } else {
+            Some(self.u32_at(entry + 8)? as usize)
+        }
+    }
+
+    fn ascii(&self, entry: usize) -> Option<String> {
+        if self.u16_at(entry + 2)? != TYPE_ASCII {
+            return None;
+        }
+        let count = self.u32_at(entry + 4)? as usize;
+        let start = self.value_offset(entry, 1)?;
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        let text = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// A SHORT or LONG value.
+    fn number(&self, entry: usize) -> Option<u32> {
+        match self.u16_at(entry + 2)? {
+            TYPE_SHORT => Some(self.u16_at(entry + 8)? as u32),
+            TYPE_LONG => self.u32_at(entry + 8),
+            _ => None,
+        }
+    }
+
+    fn rational(&self, entry: usize) -> Option<(u32, u32)> {
+        if self.u16_at(entry + 2)? != TYPE_RATIONAL {
+            return None;
+        }
+        let offset = self.value_offset(entry, 8)?;
+        Some((self.u32_at(offset)?, self.u32_at(offset + 4)?))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A JPEG head with `tiff` as its EXIF block, after an APP0 segment and
+    /// followed by the start of the scan.
+    pub(crate) fn jpeg_with_exif(tiff: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00];
+        data.extend_from_slice(&[0xFF, 0xE1]);
+        data.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+        data.extend_from_slice(b"Exif\0\0");
+        data.extend_from_slice(tiff);
+        data.extend_from_slice(&[0xFF, 0xDA]);
+        data
+    }
+
+    /// A little-endian EXIF block: make and model in IFD0, exposure and lens
+    /// in the EXIF directory, and a GPS directory with a latitude.
+    fn camera_tiff() -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        let push_u16 =
+            |tiff: &mut Vec<u8>, value: u16| tiff.extend_from_slice(&value.to_le_bytes());
+        let push_u32 =
+            |tiff: &mut Vec<u8>, value: u32| tiff.extend_from_slice(&value.to_le_bytes());
+        let entry = |tiff: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32| {
+            push_u16(tiff, tag);
+            push_u16(tiff, kind);
+            push_u32(tiff, count);
+            push_u32(tiff, value);
+        };
+        // IFD0 at 8: 4 entries, then the data area at 8 + 2 + 48 + 4 = 62.
+        push_u32(&mut tiff, 8);
+        push_u16(&mut tiff, 4);
+        entry(&mut tiff, TAG_MAKE, TYPE_ASCII, 6, 62);
+        entry(&mut tiff, TAG_MODEL, TYPE_ASCII, 13, 68);
+        entry(&mut tiff, TAG_EXIF_IFD, TYPE_LONG, 1, 82);
+        entry(&mut tiff, TAG_GPS_IFD, TYPE_LONG, 1, 172);
+        push_u32(&mut tiff, 0);
+        tiff.extend_from_slice(b"Canon\0");
+        tiff.extend_from_slice(b"Canon EOS R5\0\0");
+        // EXIF directory at 82: 5 entries, rationals from 82 + 2 + 60 + 4 = 148.
+        assert_eq!(tiff.len(), 82);
+        push_u16(&mut tiff, 5);
+        entry(&mut tiff, TAG_EXPOSURE_TIME, TYPE_RATIONAL, 1, 148);
+        entry(&mut tiff, TAG_F_NUMBER, TYPE_RATIONAL, 1, 156);
+        entry(&mut tiff, TAG_ISO, TYPE_SHORT, 1, 400);
+        entry(&mut tiff, TAG_FOCAL_LENGTH, TYPE_RATIONAL, 1, 164);
+        entry(
+            &mut tiff,
+            TAG_LENS_MODEL,
+            TYPE_ASCII,
+            4,
+            u32::from_le_bytes(*b"RF5\0"),
+        );
+        push_u32(&mut tiff, 0);
+        push_u32(&mut tiff, 10);
+        push_u32(&mut tiff, 2500);
+        push_u32(&mut tiff, 4);
+        push_u32(&mut tiff, 1);
+        push_u32(&mut tiff, 50);
+        push_u32(&mut tiff, 1);
+        // GPS directory at 172 with only a latitude entry.
+        assert_eq!(tiff.len(), 172);
+        push_u16(&mut tiff, 1);
+        entry(&mut tiff, TAG_GPS_LATITUDE, TYPE_RATIONAL, 3, 0);
+        push_u32(&mut tiff, 0);
+        tiff
+    }
+
+    #[test]
+    fn camera_details_are_read_from_the_exif_block() {
+        let data = jpeg_with_exif(&camera_tiff());
+        let details = parse(&data).unwrap();
+        assert_eq!(details.make.as_deref(), Some("Canon"));
+        assert_eq!(details.model.as_deref(), Some("Canon EOS R5"));
+        assert_eq!(details.lens.as_deref(), Some("RF5"));
+        assert_eq!(details.exposure_time, Some((10, 2500)));
+        assert_eq!(details.f_number, Some((4, 1)));
+        assert_eq!(details.iso, Some(400));
+        assert_eq!(details.focal_length, Some((50, 1)));
+        assert!(details.has_gps);
+        assert_eq!(
+            details.lines(),
+            [
+                "Canon EOS R5",
+                "RF5",
+                "1/250 s  \u{b7}  f/4.0  \u{b7}  ISO 400  \u{b7}  50 mm",
+                "GPS location recorded",
+            ]
+        );
+
+        assert!(parse(b"\x89PNG").is_none());
+        assert!(parse(&data[..20]).is_none());
+    }
+
+    #[test]
+    fn exposure_and_camera_are_written_for_reading() {
+        assert_eq!(format_exposure_time((1, 8000)).as_deref(), Some("1/8000 s"));
+        assert_eq!(format_exposure_time((2, 1)).as_deref(), Some("2 s"));
+        assert_eq!(format_exposure_time((13, 10)).as_deref(), Some("1.3 s"));
+        assert_eq!(format_exposure_time((0, 1)), None);
+
+        assert_eq!(
+            parse_date_time("2024:05:01 14:30:59"),
+            CivilTime::new(2024, 5, 1, Some((14, 30)))
+        );
+        assert_eq!(parse_date_time("0000:00:00 00:00:00"), None);
+        assert_eq!(parse_utc_offset("+02:00"), Some(120));
+        assert_eq!(parse_utc_offset("-05:30"), Some(-330));
+        assert_eq!(parse_utc_offset("   "), None);
+
+        let details = CameraDetails {
+            make: Some("FUJIFILM".to_string()),
+            model: Some("X-T4".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(details.lines(), ["FUJIFILM X-T4", "No GPS location"]);
+    }
+}
@@ -1,26 +1,38 @@
+use crate::container_names::{self, Role};
+use crate::excluded_paths;
+use crate::layout::ScreenBounds;
+use crate::network_drives;
 use crate::preview_window::{
-    hide_preview, is_cursor_over_image_preview, is_cursor_over_video_preview, show_preview,
-    show_preview_keyboard,
+    favorite_preview, hide_preview, is_animation_paused, is_cursor_over_clickable_preview,
+    is_cursor_over_image_preview, is_cursor_over_scrubbable_preview, is_cursor_over_video_preview,
+    pause_or_resume_animation, pin_preview, set_exif_held, set_reveal_held, show_preview,
+    show_preview_at_item, show_preview_keyboard, step_animation,
 };
+use crate::providers;
+use crate::shell_access;
+use crate::trace;
 use crate::{CONFIG, RUNNING};
 use once_cell::sync::Lazy;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
-use std::os::windows::ffi::OsStringExt;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::path::{Path, PathBuf};
-use std::sync::{atomic::Ordering, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
-use windows::core::{Interface, VARIANT};
-use windows::Win32::Foundation::{BOOL, HWND, LPARAM, POINT, RECT};
-use windows::Win32::Graphics::Gdi::ScreenToClient;
+use windows::core::{Interface, PCWSTR, VARIANT};
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, MAX_PATH, POINT, RECT};
+use windows::Win32::Graphics::Gdi::{
+    GetMonitorInfoW, MonitorFromWindow, ScreenToClient, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+};
 use windows::Win32::System::Com::{
-    CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, IServiceProvider, CLSCTX_ALL,
-    COINIT_APARTMENTTHREADED,
+    CoCreateInstance, CoInitializeEx, CoTaskMemFree, CoUninitialize, IPersistFile,
+    IServiceProvider, CLSCTX_ALL, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED, STGM_READ,
 };
 use windows::Win32::System::Variant::VariantClear;
 use windows::Win32::UI::Accessibility::{
     CUIAutomation, IUIAutomation, IUIAutomationElement, IUIAutomationLegacyIAccessiblePattern,
-    UIA_DataItemControlTypeId, UIA_LegacyIAccessiblePatternId, UIA_ListItemControlTypeId,
+    UIA_LegacyIAccessiblePatternId,
 };
 use windows::Win32::UI::Input::KeyboardAndMouse::{
     GetAsyncKeyState, VK_DOWN, VK_END, VK_HOME, VK_LEFT, VK_NEXT, VK_PRIOR, VK_RIGHT, VK_UP,
@@ -28,23 +40,19 @@ use windows::Win32::UI::Input::KeyboardAndMouse::{
 };
 use windows::Win32::UI::Shell::{
     IFolderView, INameSpaceTreeControl, IPersistFolder2, IShellBrowser, IShellFolder,
-    IShellFolderViewDual, IShellItem, IShellView, IShellWindows, SHCreateItemFromIDList,
-    SHCreateItemWithParent, SID_STopLevelBrowser, ShellWindows, SIGDN_DESKTOPABSOLUTEPARSING,
+    IShellFolderViewDual, IShellItem, IShellLinkW, IShellView, IShellWindows,
+    SHCreateItemFromIDList, SHCreateItemWithParent, SID_STopLevelBrowser, ShellLink, ShellWindows,
+    SIGDN_DESKTOPABSOLUTEPARSING, SLGP_UNCPRIORITY, SVGIO_SELECTION,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
-    EnumWindows, GetClassNameW, GetCursorPos, GetForegroundWindow, GetSystemMetrics,
-    GetWindowPlacement, GetWindowRect, GetWindowThreadProcessId, IsIconic, IsWindowVisible,
-    WindowFromPoint, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
-    SW_SHOWMAXIMIZED, WINDOWPLACEMENT,
+    EnumChildWindows, EnumWindows, GetClassNameW, GetCursorPos, GetForegroundWindow, GetParent,
+    GetShellWindow, GetSystemMetrics, GetWindowPlacement, GetWindowRect, GetWindowTextW,
+    GetWindowThreadProcessId, IsIconic, IsWindowVisible, WindowFromPoint, SM_CXVIRTUALSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SW_SHOWMAXIMIZED, WINDOWPLACEMENT,
 };
 
-// Supported image extensions
-const IMAGE_EXTENSIONS: &[&str] = &[
-    "jpg", "jpeg", "jpe", "jfif", "png", "gif", "bmp", "ico", "tiff", "tif", "webp",
-];
-
-// Supported video extensions
-const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "mkv", "avi", "mov", "wmv", "flv", "m4v"];
+// Shell shortcuts are hover candidates; they preview their target when it is media.
+const SHORTCUT_EXTENSIONS: &[&str] = &["lnk"];
 
 struct FolderMediaIndex {
     built_at: Instant,
@@ -113,10 +121,14 @@ const EXPLORER_PROBE_SLOW_MS: u64 = 700;
 const EXPLORER_WINDOW_CACHE_TTL_MS: u64 = 1000;
 const FOLDER_INDEX_CACHE_MAX_ENTRIES: usize = 16;
 const EXPLORER_REAL_FOLDER_CACHE_MAX_ENTRIES: usize = 256;
+const SHORTCUT_TARGET_CACHE_TTL_MS: u64 = 5000;
+const SHORTCUT_TARGET_CACHE_MAX_ENTRIES: usize = 256;
 const SEARCH_ROOT_CACHE_MAX_ENTRIES: usize = 8;
 const FOLDER_PROBE_MS: u64 = 200;
 const IDLE_FOLDER_PROBE_MS: u64 = 750;
 const DISPLAY_CHANGE_BACKOFF_MS: u64 = 1500;
+/// Time given a restarted Explorer to open its windows again.
+const SHELL_RESTART_BACKOFF_MS: u64 = 2000;
 const KEYBOARD_FOCUS_INPUT_GRACE_MS: u64 = 500;
 const HOVER_RESOLVER_INPUT_GRACE_MS: u64 = 1500;
 const VK_BACK_CODE: i32 = 0x08;
@@ -140,9 +152,26 @@ static SEARCH_ROOT_INDEX_BUILDING: Lazy<Mutex<HashSet<String>>> =
     Lazy::new(|| Mutex::new(HashSet::new()));
 static EXPLORER_LAST_REAL_FOLDERS: Lazy<Mutex<HashMap<isize, String>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
+static SHORTCUT_TARGET_CACHE: Lazy<Mutex<HashMap<PathBuf, (Option<PathBuf>, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 static EXPLORER_WINDOW_CACHE: Lazy<Mutex<HashMap<isize, (bool, Instant)>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
+/// Set by the tray when the taskbar is created again, as Explorer does when it
+/// restarts.
+static SHELL_RESTARTED: AtomicBool = AtomicBool::new(false);
+
+/// Note that Explorer restarted, so the hook drops what it knew of the old one.
+pub fn shell_restarted() {
+    SHELL_RESTARTED.store(true, Ordering::Release);
+}
+
+/// Whether the shell's desktop window, last seen as `last`, went away or was
+/// replaced. Zero means there was none.
+fn shell_window_replaced(last: isize, current: isize) -> bool {
+    last != 0 && current != last
+}
+
 fn clear_shell_view_probe_caches() {
     if let Ok(mut cache) = SHELL_VIEW_MEDIA_INDEX.lock() {
         cache.clear();
@@ -211,6 +240,24 @@ fn should_probe_stationary_hover(already_probed: bool) -> bool {
     !already_probed
 }
 
+/// Whether the cursor has strayed more than `dwell_radius` pixels from where
+/// it came to rest, along either axis.
+fn left_dwell_radius(rest: POINT, cursor: POINT, dwell_radius: u32) -> bool {
+    let dwell_radius = dwell_radius.min(i32::MAX as u32) as i32;
+    (cursor.x - rest.x).abs() > dwell_radius || (cursor.y - rest.y).abs() > dwell_radius
+}
+
+/// Whether the cursor travelled faster than `max_speed` pixels per second
+/// between two polls. A `max_speed` of 0 is no limit.
+fn cursor_too_fast(previous: POINT, cursor: POINT, elapsed: Duration, max_speed: u32) -> bool {
+    if max_speed == 0 || elapsed.is_zero() {
+        return false;
+    }
+    let dx = (cursor.x - previous.x) as f64;
+    let dy = (cursor.y - previous.y) as f64;
+    (dx * dx + dy * dy).sqrt() / elapsed.as_secs_f64() > max_speed as f64
+}
+
 fn is_jpeg_extension(ext: &str) -> bool {
     matches!(ext, "jpg" | "jpeg" | "jpe" | "jfif")
 }
@@ -374,24 +421,98 @@ fn lookup_media_in_folder_index(
 }
 
 fn is_image_file(path: &PathBuf) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
-        .unwrap_or(false)
+    providers::is_image(path)
 }
 
 fn is_video_file(path: &PathBuf) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
-        .unwrap_or(false)
+    providers::is_video(path)
+}
+
+fn is_shortcut_file(path: &PathBuf) -> bool {
+    providers::has_extension(path, SHORTCUT_EXTENSIONS)
+}
+
+/// Anything a registered preview provider can render.
+fn is_previewable_file(path: &PathBuf) -> bool {
+    is_image_file(path) || is_video_file(path) || providers::is_supported(path)
 }
 
+/// Hover candidate check. Shortcuts pass here and are resolved to their target
+/// by `resolve_preview_target` right before a preview is requested; shortcuts
+/// without a previewable target show the shortcut's own summary.
 fn is_media_file(path: &PathBuf) -> bool {
-    is_image_file(path) || is_video_file(path)
+    is_previewable_file(path) || is_shortcut_file(path)
+}
+
+fn read_shortcut_target(path: &Path) -> Option<PathBuf> {
+    unsafe {
+        let shell_link: IShellLinkW =
+            CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER).ok()?;
+        let persist_file = shell_link.cast::<IPersistFile>().ok()?;
+
+        let wide_path: Vec<u16> = path
+            .as_os_str()
+            .encode_wide()
+            .chain(std::iter::once(0))
+            .collect();
+        persist_file
+            .Load(PCWSTR(wide_path.as_ptr()), STGM_READ)
+            .ok()?;
+
+        // GetPath only reads the stored link data and never touches the target;
+        // whether the target exists is checked by the caller.
+        let mut buffer = [0u16; MAX_PATH as usize];
+        shell_link
+            .GetPath(&mut buffer, std::ptr::null_mut(), SLGP_UNCPRIORITY.0 as u32)
+            .ok()?;
+
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        if len == 0 {
+            return None;
+        }
+
+        Some(PathBuf::from(OsString::from_wide(&buffer[..len])))
+    }
+}
+
+fn resolve_shortcut_target(path: &PathBuf) -> Option<PathBuf> {
+    if let Ok(cache) = SHORTCUT_TARGET_CACHE.lock() {
+        if let Some((target, cached_at)) = cache.get(path) {
+            if cached_at.elapsed() <= Duration::from_millis(SHORTCUT_TARGET_CACHE_TTL_MS) {
+                return target.clone();
+            }
+        }
+    }
+
+    let target = read_shortcut_target(path)
+        .filter(is_previewable_file)
+        .and_then(normalize_existing_path);
+
+    if let Ok(mut cache) = SHORTCUT_TARGET_CACHE.lock() {
+        cache.retain(|_, (_, cached_at)| {
+            cached_at.elapsed() <= Duration::from_millis(SHORTCUT_TARGET_CACHE_TTL_MS)
+        });
+        if cache.len() >= SHORTCUT_TARGET_CACHE_MAX_ENTRIES {
+            cache.clear();
+        }
+        cache.insert(path.clone(), (target.clone(), Instant::now()));
+    }
+
+    target
+}
+
+/// Maps a resolved hover candidate to the file that should actually be previewed.
+fn resolve_preview_target(path: PathBuf) -> Option<PathBuf> {
+    if is_shortcut_file(&path) {
+        // Shortcuts to anything else are summarized by the shortcut provider.
+        return resolve_shortcut_target(&path).or(Some(path));
+    }
+
+    Some(path)
 }
 
-fn same_path(a: &PathBuf, b: &PathBuf) -> bool {
+/// Windows paths compare case-insensitively.
+pub(crate) fn same_path(a: &Path, b: &Path) -> bool {
     a == b
         || a.to_string_lossy()
             .eq_ignore_ascii_case(&b.to_string_lossy())
@@ -517,7 +638,7 @@ fn resolve_media_path_candidate(text: &str) -> Option<PathBuf> {
     }
 
     let path = PathBuf::from(normalized);
-    if path.exists() && is_media_file(&path) {
+    if path_exists(&path) && is_media_file(&path) {
         Some(path)
     } else {
         None
@@ -688,7 +809,12 @@ fn get_all_explorer_folders() -> Vec<(HWND, String)> {
         }
     }
 
-    let mut result: Vec<(isize, String)> = Vec::new();
+    // Without `ShellWindows`, fall back to what the address bars show.
+    let mut result: Vec<(isize, String)> = if shell_access::shell_windows_available() {
+        Vec::new()
+    } else {
+        get_explorer_folders_from_address_bars()
+    };
 
     unsafe {
         if let Ok(shell_windows) =
@@ -735,6 +861,80 @@ fn get_all_explorer_folders() -> Vec<(HWND, String)> {
         .collect()
 }
 
+/// Each Explorer window's folder, read from its address bar's window text
+/// rather than `ShellWindows`, for when COM cannot reach Explorer.
+fn get_explorer_folders_from_address_bars() -> Vec<(isize, String)> {
+    let mut windows: Vec<isize> = Vec::new();
+    unsafe {
+        let _ = EnumWindows(
+            Some(collect_explorer_windows_callback),
+            LPARAM(&mut windows as *mut Vec<isize> as isize),
+        );
+    }
+    windows
+        .into_iter()
+        .filter_map(|hwnd| {
+            let text = address_bar_text(HWND(hwnd as *mut _))?;
+            let folder = folder_from_address_text(&text)?;
+            Path::new(folder)
+                .is_dir()
+                .then(|| (hwnd, folder.to_string()))
+        })
+        .collect()
+}
+
+unsafe extern "system" fn collect_explorer_windows_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let windows = &mut *(lparam.0 as *mut Vec<isize>);
+    if explorer_browser_class_matches(hwnd) {
+        windows.push(hwnd.0 as isize);
+    }
+    BOOL(1)
+}
+
+/// The window text of an Explorer window's address bar, such as
+/// `Address: C:\Photos`.
+fn address_bar_text(explorer: HWND) -> Option<String> {
+    let mut toolbar: Option<isize> = None;
+    unsafe {
+        let _ = EnumChildWindows(
+            explorer,
+            Some(find_address_bar_callback),
+            LPARAM(&mut toolbar as *mut Option<isize> as isize),
+        );
+        let mut text = [0u16; 1024];
+        let len = GetWindowTextW(HWND(toolbar? as *mut _), &mut text).max(0) as usize;
+        Some(String::from_utf16_lossy(&text[..len]))
+    }
+}
+
+unsafe extern "system" fn find_address_bar_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let found = &mut *(lparam.0 as *mut Option<isize>);
+    let in_breadcrumb = GetParent(hwnd)
+        .map(|parent| window_class(parent) == "Breadcrumb Parent")
+        .unwrap_or(false);
+    if in_breadcrumb && window_class(hwnd) == "ToolbarWindow32" {
+        *found = Some(hwnd.0 as isize);
+        return BOOL(0);
+    }
+    BOOL(1)
+}
+
+fn window_class(hwnd: HWND) -> String {
+    let mut class = [0u16; 64];
+    let len = unsafe { GetClassNameW(hwnd, &mut class) }.max(0) as usize;
+    String::from_utf16_lossy(&class[..len])
+}
+
+/// The folder in an address bar's text, whatever language its label is in:
+/// everything from the first drive letter or UNC prefix on.
+fn folder_from_address_text(text: &str) -> Option<&str> {
+    let (start, _) = text.char_indices().find(|&(i, c)| {
+        (c.is_ascii_alphabetic() && text[i + 1..].starts_with(":\\"))
+            || text[i..].starts_with("\\\\")
+    })?;
+    Some(text[start..].trim_end())
+}
+
 fn get_explorer_hwnd_under_cursor_or_foreground() -> Option<HWND> {
     unsafe {
         let folders = get_all_explorer_folders();
@@ -1047,8 +1247,15 @@ fn point_in_rect(point: &POINT, rect: &RECT) -> bool {
     point.x >= rect.left && point.x < rect.right && point.y >= rect.top && point.y < rect.bottom
 }
 
+/// Whether `path` exists. A file on a network drive is only looked for once
+/// `network_drives` lets its share through, so an offline share times out
+/// rather than stalling the hook and is left alone when not previewed.
+fn path_exists(path: &Path) -> bool {
+    network_drives::is_previewable(path) && path.exists()
+}
+
 fn normalize_existing_path(path: PathBuf) -> Option<PathBuf> {
-    if !path.exists() {
+    if !path_exists(&path) {
         return None;
     }
 
@@ -1056,7 +1263,7 @@ fn normalize_existing_path(path: PathBuf) -> Option<PathBuf> {
 }
 
 fn normalize_media_path(path: PathBuf) -> Option<PathBuf> {
-    if !path.exists() || !is_media_file(&path) {
+    if !path_exists(&path) || !is_media_file(&path) {
         return None;
     }
 
@@ -1206,6 +1413,75 @@ fn get_focused_shell_view_media_path(item: &FocusedItemInfo) -> Option<PathBuf>
     None
 }
 
+/// The selected media file of the Explorer window titled `title`, for
+/// previews that start outside the window, such as from its taskbar thumbnail.
+pub fn get_selected_media_file_in_window(title: &str) -> Option<PathBuf> {
+    unsafe {
+        let shell_windows =
+            CoCreateInstance::<_, IShellWindows>(&ShellWindows, None, CLSCTX_ALL).ok()?;
+        let count = shell_windows.Count().ok()?;
+
+        for i in 0..count {
+            let variant = VARIANT::from(i);
+            let disp = match shell_windows.Item(&variant) {
+                Ok(disp) => disp,
+                Err(_) => continue,
+            };
+            let browser = match disp.cast::<windows::Win32::UI::Shell::IWebBrowser2>() {
+                Ok(browser) => browser,
+                Err(_) => continue,
+            };
+            let browser_hwnd = match browser.HWND() {
+                Ok(browser_hwnd) => HWND(browser_hwnd.0 as *mut _),
+                Err(_) => continue,
+            };
+            let mut text = [0u16; 512];
+            let len = GetWindowTextW(browser_hwnd, &mut text).max(0) as usize;
+            if !titles_match(&String::from_utf16_lossy(&text[..len]), title) {
+                continue;
+            }
+
+            let service_provider = browser.cast::<IServiceProvider>().ok()?;
+            let shell_browser: IShellBrowser =
+                service_provider.QueryService(&SID_STopLevelBrowser).ok()?;
+            let shell_view = shell_browser.QueryActiveShellView().ok()?;
+            let folder_view = shell_view.cast::<IFolderView>().ok()?;
+            if folder_view.ItemCount(SVGIO_SELECTION).unwrap_or(0) == 0 {
+                return None;
+            }
+            let item_index = folder_view
+                .GetSelectionMarkedItem()
+                .or_else(|_| folder_view.GetFocusedItem())
+                .ok()?;
+            let pidl = match folder_view.Item(item_index) {
+                Ok(pidl) if !pidl.is_null() => pidl,
+                _ => return None,
+            };
+            let shell_item = shell_item_from_view_pidl(&folder_view, pidl);
+            CoTaskMemFree(Some(pidl as *const core::ffi::c_void));
+            return shell_item.and_then(|shell_item| shell_item_to_media_path(&shell_item));
+        }
+    }
+
+    None
+}
+
+/// Whether a window titled `window_title` is the one a taskbar thumbnail
+/// calls `name`. Either may carry the app's name after the folder's, as in
+/// `Pictures - File Explorer`.
+fn titles_match(window_title: &str, name: &str) -> bool {
+    let (window_title, name) = (window_title.trim(), name.trim());
+    if window_title.is_empty() || name.is_empty() {
+        return false;
+    }
+    let extends = |long: &str, short: &str| {
+        long.strip_prefix(short)
+            .and_then(|rest| rest.strip_prefix(" - "))
+            .is_some()
+    };
+    window_title == name || extends(window_title, name) || extends(name, window_title)
+}
+
 fn build_shell_view_media_index(view_hwnd_key: isize) -> Option<ShellViewMediaIndex> {
     let mut by_display_name = HashMap::new();
     let mut by_file_name = HashMap::new();
@@ -1270,7 +1546,7 @@ fn build_shell_view_media_index(view_hwnd_key: isize) -> Option<ShellViewMediaIn
                     Err(_) => continue,
                 };
                 let path = PathBuf::from(path_str);
-                if !path.exists() || !is_media_file(&path) {
+                if !path_exists(&path) || !is_media_file(&path) {
                     continue;
                 }
 
@@ -1361,7 +1637,7 @@ fn build_legacy_search_shell_view_media_index(
                     Err(_) => continue,
                 };
                 let path = PathBuf::from(path_str);
-                if !path.exists() || !is_media_file(&path) {
+                if !path_exists(&path) || !is_media_file(&path) {
                     continue;
                 }
 
@@ -1536,7 +1812,7 @@ fn add_search_index_path(index: &mut SearchRootMediaIndex, path: &PathBuf) {
 
 fn build_search_root_media_index(root: &str) -> Option<SearchRootMediaIndex> {
     let root_path = PathBuf::from(root);
-    if !root_path.is_dir() {
+    if !network_drives::is_previewable(&root_path) || !root_path.is_dir() {
         return None;
     }
 
@@ -1786,48 +2062,9 @@ fn get_current_explorer_folder() -> Option<String> {
     None
 }
 
-/// Names that indicate container elements, not actual files
-const CONTAINER_NAMES: &[&str] = &[
-    "Items View",
-    "Folder View",
-    "Shell Folder View",
-    "ShellView",
-    "UIItemsView",
-    "DirectUIHWND",
-    "Search Results",
-    "File list",
-    "Name",
-    "Date modified",
-    "Type",
-    "Size",
-    "Date",
-    "Date created",
-    "Details",
-    "List",
-    "Content",
-    "Tiles",
-    "Large icons",
-    "Medium icons",
-    "Small icons",
-    "Extra large icons",
-    "Item",
-    "Group",
-    "Header",
-];
-
 /// Patterns that suggest a value might be a folder path rather than a file
 const FOLDER_PATTERNS: &[&str] = &["search-ms:", "shell:", "::{"];
 
-/// Check if a name is a container/UI element name rather than an actual file
-fn is_container_name(name: &str) -> bool {
-    if name.is_empty() {
-        return true;
-    }
-    CONTAINER_NAMES
-        .iter()
-        .any(|&c| name.eq_ignore_ascii_case(c))
-}
-
 /// Check if a value looks like a valid file path (not a shell special path)
 fn is_valid_file_path(s: &str) -> bool {
     if s.is_empty() {
@@ -1853,6 +2090,21 @@ enum AccessibilityResult {
     FullPath(PathBuf),
 }
 
+/// Check if the element `variant` of `acc` is part of Explorer's UI rather
+/// than a file, by its MSAA role and, failing that, its name.
+fn is_container_element(
+    acc: &windows::Win32::UI::Accessibility::IAccessible,
+    variant: &VARIANT,
+    name: &str,
+) -> bool {
+    let role = unsafe { acc.get_accRole(variant) }
+        .ok()
+        .and_then(|role| i32::try_from(&role).ok())
+        .map(|role| Role::from_msaa(role as u32))
+        .unwrap_or(Role::Unknown);
+    container_names::is_container(role, name)
+}
+
 /// Check whether an accessibility element/child variant contains the current cursor point.
 /// This prevents fallbacks from returning focused/default items that are not truly hovered.
 fn is_variant_under_cursor(
@@ -1922,7 +2174,7 @@ fn get_item_under_cursor() -> Option<AccessibilityResult> {
                 if is_variant_under_cursor(acc, &child_variant, &cursor_pos) {
                     if let Ok(name) = acc.get_accName(&child_variant) {
                         let name_str = name.to_string();
-                        if !is_container_name(&name_str) {
+                        if !is_container_element(acc, &child_variant, &name_str) {
                             if let Some(path) = resolve_media_path_from_text(&name_str) {
                                 return Some(AccessibilityResult::FullPath(path));
                             }
@@ -1936,7 +2188,7 @@ fn get_item_under_cursor() -> Option<AccessibilityResult> {
                 if is_variant_under_cursor(acc, &default_variant, &cursor_pos) {
                     if let Ok(name) = acc.get_accName(&default_variant) {
                         let name_str = name.to_string();
-                        if !is_container_name(&name_str) {
+                        if !is_container_element(acc, &default_variant, &name_str) {
                             if let Some(path) = resolve_media_path_from_text(&name_str) {
                                 return Some(AccessibilityResult::FullPath(path));
                             }
@@ -1954,7 +2206,9 @@ fn get_item_under_cursor() -> Option<AccessibilityResult> {
                 if is_variant_under_cursor(acc, &child_variant, &cursor_pos) {
                     if let Ok(help) = acc.get_accHelp(&child_variant) {
                         let help_str = help.to_string();
-                        if !help_str.is_empty() && !is_container_name(&help_str) {
+                        if !help_str.is_empty()
+                            && !is_container_element(acc, &child_variant, &help_str)
+                        {
                             return Some(AccessibilityResult::FileName(help_str));
                         }
                     }
@@ -2003,7 +2257,7 @@ fn try_get_item_from_parent(
                 if is_variant_under_cursor(&parent_acc, &default_variant, cursor_pos) {
                     if let Ok(name) = parent_acc.get_accName(&default_variant) {
                         let name_str = name.to_string();
-                        if !is_container_name(&name_str) {
+                        if !is_container_element(&parent_acc, &default_variant, &name_str) {
                             if let Some(path) = resolve_media_path_from_text(&name_str) {
                                 return Some(AccessibilityResult::FullPath(path));
                             }
@@ -2038,7 +2292,7 @@ fn try_get_item_from_parent(
                     if is_variant_under_cursor(acc, &focus, cursor_pos) {
                         if let Ok(name) = acc.get_accName(&focus) {
                             let name_str = name.to_string();
-                            if !is_container_name(&name_str) {
+                            if !is_container_element(acc, &focus, &name_str) {
                                 return Some(AccessibilityResult::FileName(name_str));
                             }
                         }
@@ -2082,7 +2336,7 @@ fn try_get_focused_child(
                     if (state_val & 0x80) != 0 || (state_val & 0x4) != 0 {
                         if let Ok(name) = acc.get_accName(&child_var) {
                             let name_str = name.to_string();
-                            if !is_container_name(&name_str) {
+                            if !is_container_element(acc, &child_var, &name_str) {
                                 if let Some(path) = resolve_media_path_from_text(&name_str) {
                                     return Some(AccessibilityResult::FullPath(path));
                                 }
@@ -2128,7 +2382,7 @@ fn try_deep_parent_search(
                     if is_variant_under_cursor(&parent_acc, &default_variant, cursor_pos) {
                         if let Ok(name) = parent_acc.get_accName(&default_variant) {
                             let name_str = name.to_string();
-                            if !is_container_name(&name_str) {
+                            if !is_container_element(&parent_acc, &default_variant, &name_str) {
                                 if let Some(path) = resolve_media_path_from_text(&name_str) {
                                     return Some(AccessibilityResult::FullPath(path));
                                 }
@@ -2172,6 +2426,9 @@ fn find_media_in_folder(folder: &str, item_name: &str) -> Option<PathBuf> {
     }
 
     let folder_path = PathBuf::from(folder);
+    if !network_drives::is_previewable(&folder_path) {
+        return None;
+    }
     let folder_key = folder_path.to_string_lossy().into_owned();
 
     // First try: item_name as-is
@@ -2180,6 +2437,13 @@ fn find_media_in_folder(folder: &str, item_name: &str) -> Option<PathBuf> {
         return Some(full_path);
     }
 
+    // Explorer hides the .lnk extension, so a shortcut named "photo.jpg.lnk"
+    // is labelled "photo.jpg".
+    let shortcut_path = folder_path.join(format!("{}.lnk", item_name));
+    if shortcut_path.is_file() {
+        return Some(shortcut_path);
+    }
+
     // JPEG extension aliases can differ between Explorer labels and on-disk names.
     // Try sibling JPEG aliases before consulting the folder index.
     if let Some(item_ext) = Path::new(item_name)
@@ -2207,9 +2471,9 @@ fn find_media_in_folder(folder: &str, item_name: &str) -> Option<PathBuf> {
     lookup_media_in_folder_index(&folder_path, &folder_key, item_name)
 }
 
-fn accessibility_result_from_name(name: String) -> Option<AccessibilityResult> {
+fn accessibility_result_from_name(name: String, role: Role) -> Option<AccessibilityResult> {
     let name = name.trim().to_string();
-    if name.is_empty() || is_container_name(&name) {
+    if name.is_empty() || container_names::is_container(role, &name) {
         return None;
     }
 
@@ -2224,6 +2488,10 @@ fn accessibility_result_from_legacy_pattern(
     pattern: &IUIAutomationLegacyIAccessiblePattern,
 ) -> Option<AccessibilityResult> {
     unsafe {
+        let role = pattern
+            .CurrentRole()
+            .map(Role::from_msaa)
+            .unwrap_or(Role::Unknown);
         for candidate in [
             pattern.CurrentValue().ok().map(|s| s.to_string()),
             pattern.CurrentDescription().ok().map(|s| s.to_string()),
@@ -2232,7 +2500,7 @@ fn accessibility_result_from_legacy_pattern(
         .into_iter()
         .flatten()
         {
-            if let Some(result) = accessibility_result_from_name(candidate) {
+            if let Some(result) = accessibility_result_from_name(candidate, role) {
                 return Some(result);
             }
         }
@@ -2245,16 +2513,21 @@ fn accessibility_result_from_uia_element(
     element: &IUIAutomationElement,
 ) -> Option<AccessibilityResult> {
     unsafe {
+        let role = element
+            .CurrentControlType()
+            .map(Role::from_uia)
+            .unwrap_or(Role::Unknown);
+        // Headers, groups and the view itself have no file behind them.
+        if role == Role::Container {
+            return None;
+        }
         if let Ok(name) = element.CurrentName() {
-            if let Some(result) = accessibility_result_from_name(name.to_string()) {
+            if let Some(result) = accessibility_result_from_name(name.to_string(), role) {
                 return Some(result);
             }
         }
 
-        let control_type = element.CurrentControlType().ok();
-        if control_type == Some(UIA_ListItemControlTypeId)
-            || control_type == Some(UIA_DataItemControlTypeId)
-        {
+        if role == Role::Item {
             if let Ok(pattern) = element
                 .GetCurrentPatternAs::<IUIAutomationLegacyIAccessiblePattern>(
                     UIA_LegacyIAccessiblePatternId,
@@ -2300,6 +2573,89 @@ fn get_item_under_cursor_uia(automation: &IUIAutomation) -> Option<Accessibility
     None
 }
 
+/// The on-screen bounds of the list item or row under the cursor, which
+/// previews keep clear of. UI Automation finds the item among the elements from the
+/// cursor up; without it, MSAA's location of the hovered child is used.
+fn hovered_item_bounds(
+    automation: Option<&IUIAutomation>,
+    cursor_pos: &POINT,
+) -> Option<ScreenBounds> {
+    let rect = automation
+        .and_then(|automation| hovered_item_rect_uia(automation, cursor_pos))
+        .or_else(|| hovered_item_rect_msaa(cursor_pos))?;
+    Some(ScreenBounds {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    })
+}
+
+fn hovered_item_rect_uia(automation: &IUIAutomation, cursor_pos: &POINT) -> Option<RECT> {
+    unsafe {
+        let mut element = automation.ElementFromPoint(*cursor_pos).ok()?;
+        let walker = automation.ControlViewWalker().ok()?;
+
+        // From a details-view cell up to its row, or an icon's label up to
+        // the icon.
+        for _ in 0..8 {
+            let role = element
+                .CurrentControlType()
+                .map(Role::from_uia)
+                .unwrap_or(Role::Unknown);
+            match role {
+                Role::Item => {
+                    let rect = element.CurrentBoundingRectangle().ok()?;
+                    return rect_contains(&rect, cursor_pos).then_some(rect);
+                }
+                Role::Container => return None,
+                Role::Unknown => {}
+            }
+            element = walker.GetParentElement(&element).ok()?;
+        }
+    }
+
+    None
+}
+
+fn hovered_item_rect_msaa(cursor_pos: &POINT) -> Option<RECT> {
+    unsafe {
+        let mut accessible: Option<windows::Win32::UI::Accessibility::IAccessible> = None;
+        let mut child_variant = VARIANT::default();
+        windows::Win32::UI::Accessibility::AccessibleObjectFromPoint(
+            *cursor_pos,
+            &mut accessible,
+            &mut child_variant,
+        )
+        .ok()?;
+        let acc = accessible?;
+        let role = acc
+            .get_accRole(&child_variant)
+            .ok()
+            .and_then(|role| i32::try_from(&role).ok())
+            .map(|role| Role::from_msaa(role as u32))
+            .unwrap_or(Role::Unknown);
+        if role != Role::Item {
+            return None;
+        }
+
+        let (mut left, mut top, mut width, mut height) = (0, 0, 0, 0);
+        acc.accLocation(&mut left, &mut top, &mut width, &mut height, &child_variant)
+            .ok()?;
+        let rect = RECT {
+            left,
+            top,
+            right: left.saturating_add(width),
+            bottom: top.saturating_add(height),
+        };
+        rect_contains(&rect, cursor_pos).then_some(rect)
+    }
+}
+
+fn rect_contains(rect: &RECT, point: &POINT) -> bool {
+    rect.left <= point.x && point.x < rect.right && rect.top <= point.y && point.y < rect.bottom
+}
+
 fn get_accessibility_item_under_cursor(
     automation: Option<&IUIAutomation>,
 ) -> Option<AccessibilityResult> {
@@ -2342,7 +2698,7 @@ fn get_file_under_cursor_normal(
 
             let potential_path = PathBuf::from(&item_name);
             if potential_path.is_absolute()
-                && potential_path.exists()
+                && path_exists(&potential_path)
                 && is_media_file(&potential_path)
             {
                 return Some(potential_path);
@@ -2449,7 +2805,13 @@ fn get_file_under_cursor_checked(
     slow_probe_count: &mut u32,
 ) -> Option<PathBuf> {
     let started = Instant::now();
-    let result = get_file_under_cursor(automation, hints);
+    let result = get_file_under_cursor(automation, hints)
+        .and_then(resolve_preview_target)
+        .filter(|path| {
+            providers::is_type_enabled(path)
+                && !excluded_paths::is_excluded(path)
+                && network_drives::is_previewable(path)
+        });
 
     if started.elapsed() >= Duration::from_millis(EXPLORER_PROBE_SLOW_MS) {
         *slow_probe_count = slow_probe_count.saturating_add(1);
@@ -2483,7 +2845,7 @@ fn is_window_maximized(hwnd: HWND) -> bool {
     false
 }
 
-/// Check if a window is fullscreen (covers entire screen)
+/// Check if a window is fullscreen (covers the entire monitor it is on)
 fn is_window_fullscreen(hwnd: HWND) -> bool {
     unsafe {
         let mut window_rect = RECT::default();
@@ -2491,19 +2853,20 @@ fn is_window_fullscreen(hwnd: HWND) -> bool {
             return false;
         }
 
-        // Get screen dimensions
-        let screen_width = windows::Win32::UI::WindowsAndMessaging::GetSystemMetrics(
-            windows::Win32::UI::WindowsAndMessaging::SM_CXSCREEN,
-        );
-        let screen_height = windows::Win32::UI::WindowsAndMessaging::GetSystemMetrics(
-            windows::Win32::UI::WindowsAndMessaging::SM_CYSCREEN,
-        );
-
-        // Check if window covers entire screen (with small tolerance for borders)
-        let width = window_rect.right - window_rect.left;
-        let height = window_rect.bottom - window_rect.top;
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return false;
+        }
+        let screen = info.rcMonitor;
 
-        width >= screen_width && height >= screen_height
+        window_rect.left <= screen.left
+            && window_rect.top <= screen.top
+            && window_rect.right >= screen.right
+            && window_rect.bottom >= screen.bottom
     }
 }
 
@@ -2623,16 +2986,33 @@ fn get_explorer_state() -> ExplorerState {
 
 /// Detect whether the user is actively navigating Explorer with keyboard.
 /// We treat both current-down and "pressed since last check" states as input.
+/// Left and Right step a paused animation instead.
 fn is_keyboard_navigation_input_detected() -> bool {
+    let stepping = is_animation_paused();
     unsafe {
         let navigation_keys = [
             VK_UP, VK_DOWN, VK_LEFT, VK_RIGHT, VK_HOME, VK_END, VK_PRIOR, VK_NEXT,
         ];
 
-        navigation_keys.iter().any(|&key| {
-            let state = GetAsyncKeyState(key.0 as i32) as u16;
-            is_pressed_or_down_state(state)
-        })
+        navigation_keys
+            .iter()
+            .filter(|&&key| !(stepping && (key == VK_LEFT || key == VK_RIGHT)))
+            .any(|&key| {
+                let state = GetAsyncKeyState(key.0 as i32) as u16;
+                is_pressed_or_down_state(state)
+            })
+    }
+}
+
+/// -1 while Left is held, 1 while Right is, otherwise 0.
+fn step_key_direction() -> i32 {
+    match (
+        is_key_down(VK_LEFT.0 as i32),
+        is_key_down(VK_RIGHT.0 as i32),
+    ) {
+        (true, false) => -1,
+        (false, true) => 1,
+        _ => 0,
     }
 }
 
@@ -2708,7 +3088,8 @@ fn is_pressed_or_down_state(state: u16) -> bool {
     (state & 0x8000) != 0 || (state & 0x0001) != 0
 }
 
-fn off_trigger_key_to_vk(key: &str) -> Option<i32> {
+/// Virtual-key code for a key name from `config.ini` (`alt`, `f8`, `q`, ...).
+fn hotkey_to_vk(key: &str) -> Option<i32> {
     let key = key.trim().to_ascii_lowercase();
     let vk = match key.as_str() {
         "alt" | "menu" => 0x12,
@@ -2761,8 +3142,8 @@ fn off_trigger_key_to_vk(key: &str) -> Option<i32> {
     Some(vk)
 }
 
-fn is_off_trigger_key_down(key: &str) -> bool {
-    let Some(vk) = off_trigger_key_to_vk(key) else {
+fn is_hotkey_down(key: &str) -> bool {
+    let Some(vk) = hotkey_to_vk(key) else {
         return false;
     };
 
@@ -2901,7 +3282,11 @@ fn get_focused_explorer_item(automation: &IUIAutomation) -> Option<FocusedItemIn
 
         // Get the element name (this is the filename in Explorer)
         let name = focused.CurrentName().ok()?.to_string();
-        if name.is_empty() || is_container_name(&name) {
+        let role = focused
+            .CurrentControlType()
+            .map(Role::from_uia)
+            .unwrap_or(Role::Unknown);
+        if name.is_empty() || container_names::is_container(role, &name) {
             return None;
         }
 
@@ -2914,7 +3299,7 @@ fn get_focused_explorer_item(automation: &IUIAutomation) -> Option<FocusedItemIn
         // Check if name is a full path (can happen in search results)
         if is_valid_file_path(&name) {
             let path = PathBuf::from(&name);
-            if path.exists() && is_media_file(&path) {
+            if path_exists(&path) && is_media_file(&path) {
                 return Some(FocusedItemInfo {
                     result: AccessibilityResult::FullPath(path),
                     rect,
@@ -2930,6 +3315,16 @@ fn get_focused_explorer_item(automation: &IUIAutomation) -> Option<FocusedItemIn
 }
 
 fn resolve_focused_item_to_path(item: &FocusedItemInfo) -> Option<PathBuf> {
+    resolve_focused_item_candidate(item)
+        .and_then(resolve_preview_target)
+        .filter(|path| {
+            providers::is_type_enabled(path)
+                && !excluded_paths::is_excluded(path)
+                && network_drives::is_previewable(path)
+        })
+}
+
+fn resolve_focused_item_candidate(item: &FocusedItemInfo) -> Option<PathBuf> {
     match &item.result {
         AccessibilityResult::FullPath(path) => {
             if is_media_file(path) {
@@ -2948,7 +3343,7 @@ fn resolve_focused_item_to_path(item: &FocusedItemInfo) -> Option<PathBuf> {
             // Try as a potential full path
             let potential_path = PathBuf::from(item_name);
             if potential_path.is_absolute()
-                && potential_path.exists()
+                && path_exists(&potential_path)
                 && is_media_file(&potential_path)
             {
                 return Some(potential_path);
@@ -2998,19 +3393,22 @@ fn resolve_focused_item_to_path(item: &FocusedItemInfo) -> Option<PathBuf> {
 
 /// Main loop for explorer hook
 pub fn run_explorer_hook() {
-    unsafe {
-        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
-    }
+    let com_init = unsafe { CoInitializeEx(None, COINIT_APARTMENTTHREADED) };
 
     // Create UI Automation instance for keyboard focus detection (cached for the lifetime of the loop)
-    let uia: Option<IUIAutomation> =
+    let mut uia: Option<IUIAutomation> =
         unsafe { CoCreateInstance(&CUIAutomation, None, CLSCTX_ALL).ok() };
+    // Note what of COM is missing, so the tray can say so and folders can
+    // come from address bars instead.
+    shell_access::check(com_init, uia.is_some());
 
     let mut last_file: Option<PathBuf> = None;
     let mut suppressed_hover_file: Option<PathBuf> = None;
     let mut suppressed_hover_started_at: Option<Instant> = None;
     let mut hover_start: Option<Instant> = None;
     let mut last_cursor_pos = POINT::default();
+    // Where the cursor was at the previous poll, for its speed.
+    let mut last_polled_cursor: Option<(POINT, Instant)> = None;
 
     // Keyboard hover state
     let mut keyboard_file: Option<PathBuf> = None;
@@ -3065,15 +3463,71 @@ pub fn run_explorer_hook() {
                 c.hover_delay_ms,
                 c.enable_off_trigger_key,
                 c.off_trigger_key.clone(),
+                c.pin_key.clone(),
+                c.favorite_key.clone(),
                 c.same_file_rehover_delay_ms,
+                c.reveal_key.clone(),
+                c.pause_key.clone(),
+                c.exif_key.clone(),
+                c.dwell_radius_px,
+                c.dwell_max_speed_px_per_s,
             )
         })
-        .unwrap_or((true, 0, true, "alt".to_string(), 750));
+        .unwrap_or((
+            true,
+            0,
+            true,
+            "alt".to_string(),
+            "f8".to_string(),
+            "f9".to_string(),
+            750,
+            "shift".to_string(),
+            "space".to_string(),
+            "i".to_string(),
+            5,
+            0,
+        ));
+    let mut pin_key_was_down = false;
+    let mut favorite_key_was_down = false;
+    let mut pause_key_was_down = false;
+    let mut step_key_was = 0;
     let mut slow_explorer_probe_count = 0u32;
     let mut explorer_probe_backoff_until: Option<Instant> = None;
     let mut last_display_signature = current_display_signature();
+    let mut last_shell_window = unsafe { GetShellWindow().0 as isize };
 
     while RUNNING.load(Ordering::SeqCst) {
+        // Explorer restarted: its windows, their views and the UI Automation
+        // connection to them are gone, so start over as if just launched.
+        let shell_window = unsafe { GetShellWindow().0 as isize };
+        let shell_replaced = shell_window_replaced(last_shell_window, shell_window);
+        last_shell_window = shell_window;
+        if SHELL_RESTARTED.swap(false, Ordering::AcqRel) || shell_replaced {
+            clear_shell_view_probe_caches();
+            hide_preview();
+            uia = unsafe { CoCreateInstance(&CUIAutomation, None, CLSCTX_ALL).ok() };
+            shell_access::check(com_init, uia.is_some());
+            last_file = None;
+            keyboard_file = None;
+            is_keyboard_hover = false;
+            last_focused_name = None;
+            suppressed_hover_file = None;
+            suppressed_hover_started_at = None;
+            stationary_search_miss_started_at = None;
+            hover_start = None;
+            video_hover_guard_until = None;
+            stationary_hover_probe_done = false;
+            suspend_preview_until_user_input = true;
+            allow_keyboard_preview_on_first_observation = false;
+            folder_change_time = Some(Instant::now());
+            suspended_initial_focus = None;
+            hover_resolver_hints = HoverResolverHints::default();
+            last_cursor_location = None;
+            slow_explorer_probe_count = 0;
+            explorer_probe_backoff_until =
+                Some(Instant::now() + Duration::from_millis(SHELL_RESTART_BACKOFF_MS));
+        }
+
         if let Some(display_signature) = current_display_signature() {
             if display_signature_changed(last_display_signature, display_signature) {
                 last_display_signature = Some(display_signature);
@@ -3147,7 +3601,14 @@ pub fn run_explorer_hook() {
                 config.hover_delay_ms,
                 config.enable_off_trigger_key,
                 config.off_trigger_key.clone(),
+                config.pin_key.clone(),
+                config.favorite_key.clone(),
                 config.same_file_rehover_delay_ms,
+                config.reveal_key.clone(),
+                config.pause_key.clone(),
+                config.exif_key.clone(),
+                config.dwell_radius_px,
+                config.dwell_max_speed_px_per_s,
             );
         }
 
@@ -3156,11 +3617,47 @@ pub fn run_explorer_hook() {
             hover_delay_ms,
             enable_off_trigger_key,
             off_trigger_key,
+            pin_key,
+            favorite_key,
             same_file_rehover_delay_ms,
+            reveal_key,
+            pause_key,
+            exif_key,
+            dwell_radius_px,
+            dwell_max_speed_px_per_s,
         ) = config_snapshot.clone();
 
-        let off_trigger_active =
-            enable_off_trigger_key && is_off_trigger_key_down(&off_trigger_key);
+        // Pin on the key press only, not for as long as it is held.
+        let pin_key_down = !pin_key.is_empty() && is_hotkey_down(&pin_key);
+        let preview_active = last_file.is_some() || keyboard_file.is_some();
+        if pin_key_down && !pin_key_was_down && preview_active {
+            pin_preview();
+        }
+        pin_key_was_down = pin_key_down;
+        let favorite_key_down = !favorite_key.is_empty() && is_hotkey_down(&favorite_key);
+        if favorite_key_down && !favorite_key_was_down && preview_active {
+            favorite_preview();
+        }
+        favorite_key_was_down = favorite_key_down;
+        set_reveal_held(!reveal_key.is_empty() && is_hotkey_down(&reveal_key));
+        set_exif_held(!exif_key.is_empty() && is_hotkey_down(&exif_key));
+        let pause_key_down = !pause_key.is_empty() && is_hotkey_down(&pause_key);
+        if pause_key_down && !pause_key_was_down && preview_active {
+            pause_or_resume_animation();
+        }
+        pause_key_was_down = pause_key_down;
+        // While an animation is paused, Left and Right step it a frame per press.
+        let step_key = if is_animation_paused() {
+            step_key_direction()
+        } else {
+            0
+        };
+        if step_key != 0 && step_key != step_key_was && preview_active {
+            step_animation(step_key);
+        }
+        step_key_was = step_key;
+
+        let off_trigger_active = enable_off_trigger_key && is_hotkey_down(&off_trigger_key);
 
         if off_trigger_active {
             if last_file.is_some() || keyboard_file.is_some() {
@@ -3274,8 +3771,16 @@ pub fn run_explorer_hook() {
             }
 
             let loop_now = Instant::now();
-            let moved = (cursor_pos.x - last_cursor_pos.x).abs() > 5
-                || (cursor_pos.y - last_cursor_pos.y).abs() > 5;
+            // Still travelling quickly counts as moving even within the dwell
+            // radius, so a preview waits for the cursor to settle.
+            let too_fast = last_polled_cursor
+                .map(|(previous, polled_at)| {
+                    let elapsed = loop_now.duration_since(polled_at);
+                    cursor_too_fast(previous, cursor_pos, elapsed, dwell_max_speed_px_per_s)
+                })
+                .unwrap_or(false);
+            last_polled_cursor = Some((cursor_pos, loop_now));
+            let moved = left_dwell_radius(last_cursor_pos, cursor_pos, dwell_radius_px) || too_fast;
             let explorer_navigation_shortcut_input = is_explorer_navigation_shortcut_detected();
             let keyboard_navigation_input =
                 explorer_navigation_shortcut_input || is_keyboard_navigation_input_detected();
@@ -3310,6 +3815,16 @@ pub fn run_explorer_hook() {
                 continue;
             }
 
+            // An in-process video preview under the cursor is being scrubbed by
+            // the preview thread, or a preview with a click action is waiting
+            // for a click; keep it open and leave hover state untouched.
+            if (is_cursor_over_scrubbable_preview() || is_cursor_over_clickable_preview())
+                && !suppress_preview_until_cursor_leaves_preview
+            {
+                last_cursor_pos = cursor_pos;
+                continue;
+            }
+
             // Close as soon as the cursor touches the preview window. Keep
             // suppressing preview until the cursor leaves so a delayed spinner
             // or background load result cannot resurrect a stuck preview under
@@ -3684,11 +4199,13 @@ pub fn run_explorer_hook() {
                     stationary_hover_probe_done = true;
 
                     // Try to get file under cursor
+                    let resolve_started = Instant::now();
                     if let Some(file_path) = get_file_under_cursor_checked(
                         uia.as_ref(),
                         &hover_resolver_hints,
                         &mut slow_explorer_probe_count,
                     ) {
+                        trace::resolved(&file_path, resolve_started.elapsed());
                         if !last_file
                             .as_ref()
                             .map(|last| same_path(last, &file_path))
@@ -3709,6 +4226,12 @@ pub fn run_explorer_hook() {
                                     continue;
                                 }
                             }
+                            let network_delay = network_drives::extra_hover_delay(&file_path);
+                            if start.elapsed() < hover_delay + network_delay {
+                                // Ask again once the longer delay is over.
+                                stationary_hover_probe_done = false;
+                                continue;
+                            }
                             suppressed_hover_file = None;
                             suppressed_hover_started_at = None;
                             stationary_search_miss_started_at = None;
@@ -3721,7 +4244,15 @@ pub fn run_explorer_hook() {
                             } else {
                                 None
                             };
-                            show_preview(&file_path, cursor_pos.x, cursor_pos.y);
+                            match hovered_item_bounds(uia.as_ref(), &cursor_pos) {
+                                Some(item) => show_preview_at_item(
+                                    &file_path,
+                                    cursor_pos.x,
+                                    cursor_pos.y,
+                                    item,
+                                ),
+                                None => show_preview(&file_path, cursor_pos.x, cursor_pos.y),
+                            }
                         }
                     } else {
                         let search_view_active =
@@ -3814,6 +4345,14 @@ mod tests {
         assert!(!display_signature_changed(None, changed));
     }
 
+    #[test]
+    fn shell_restart_is_a_new_shell_window() {
+        assert!(shell_window_replaced(0x10010, 0x20020));
+        assert!(shell_window_replaced(0x10010, 0));
+        assert!(!shell_window_replaced(0x10010, 0x10010));
+        assert!(!shell_window_replaced(0, 0x20020));
+    }
+
     #[test]
     fn keyboard_focus_probe_requires_recent_navigation_input() {
         assert!(!should_probe_keyboard_focus(None));
@@ -3848,6 +4387,21 @@ mod tests {
         assert!(!should_probe_stationary_hover(true));
     }
 
+    #[test]
+    fn cursor_settles_within_dwell_radius_below_speed_limit() {
+        let rest = POINT { x: 100, y: 100 };
+        assert!(!left_dwell_radius(rest, POINT { x: 105, y: 95 }, 5));
+        assert!(left_dwell_radius(rest, POINT { x: 106, y: 100 }, 5));
+        assert!(left_dwell_radius(rest, POINT { x: 100, y: 101 }, 0));
+
+        let step = POINT { x: 103, y: 104 };
+        let poll = Duration::from_millis(10);
+        assert!(cursor_too_fast(rest, step, poll, 400));
+        assert!(!cursor_too_fast(rest, step, poll, 500));
+        assert!(!cursor_too_fast(rest, step, poll, 0));
+        assert!(!cursor_too_fast(rest, step, Duration::ZERO, 400));
+    }
+
     #[test]
     fn explorer_navigation_shortcut_keys_cover_windows_11_navigation() {
         assert!(is_explorer_navigation_shortcut_key(
@@ -3916,4 +4470,23 @@ mod tests {
             Some("url:file:///C:/Users/HDP")
         );
     }
+
+    #[test]
+    fn taskbar_thumbnail_names_match_window_titles_with_or_without_the_app_name() {
+        assert!(titles_match("Pictures", " Pictures "));
+        assert!(titles_match("Pictures - File Explorer", "Pictures"));
+        assert!(titles_match("Pictures", "Pictures - File Explorer"));
+        assert!(!titles_match("Pictures 2024", "Pictures"));
+        assert!(!titles_match("Pictures", "Pictures-old"));
+        assert!(!titles_match("", ""));
+    }
+
+    #[test]
+    fn address_bar_text_gives_the_folder_whatever_the_label() {
+        let folder = folder_from_address_text;
+        assert_eq!(folder("Address: C:\\Photos"), Some("C:\\Photos"));
+        assert_eq!(folder("Adresse : D:\\"), Some("D:\\"));
+        assert_eq!(folder("Address: \\\\nas\\share "), Some("\\\\nas\\share"));
+        assert_eq!(folder("Address: This PC"), None);
+    }
 }
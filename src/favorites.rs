@@ -0,0 +1,159 @@
+//! Favorites starred from previews.
+//!
+//! Pressing `favorite_key` while a preview is showing stars its file, or
+//! unstars it if it already was. "Favorites..." in the tray menu opens a
+//! gallery window listing them newest first; thumbnails can be dragged out
+//! into Explorer or any other drop target. The list is kept in
+//! `favorites.txt` next to `config.ini`, one path per line.
+//!
+//! Favorites starred in this session keep the frame they were starred from as
+//! their thumbnail. Those loaded from disk get the shell's thumbnail, read on a
+//! background thread when the window first opens.
+
+use crate::config::AppConfig;
+use crate::explorer_hook::same_path;
+use crate::first_pass;
+use crate::gallery::{self, Entry, Gallery, Thumbnail, THUMB_SIDE};
+use crate::preview_window;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+const FAVORITES_FILE: &str = "favorites.txt";
+
+pub static FAVORITES: Lazy<Mutex<VecDeque<Entry>>> = Lazy::new(|| {
+    let paths = favorites_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|text| parse(&text))
+        .unwrap_or_default();
+    Mutex::new(
+        paths
+            .into_iter()
+            .map(|path| Entry {
+                path,
+                thumbnail: None,
+                blurred: false,
+            })
+            .collect(),
+    )
+});
+static LOADING_THUMBNAILS: AtomicBool = AtomicBool::new(false);
+
+/// Open the favorites window.
+pub fn open() {
+    load_missing_thumbnails();
+    gallery::open(Gallery::Favorites);
+}
+
+/// Star a previewed file, or unstar it when it already is. Returns whether it
+/// is now a favorite.
+pub fn toggle(entry: Entry) -> bool {
+    let Ok(mut favorites) = FAVORITES.lock() else {
+        return false;
+    };
+    let count = favorites.len();
+    favorites.retain(|existing| !same_path(&existing.path, &entry.path));
+    let starred = favorites.len() == count;
+    if starred {
+        favorites.push_front(entry);
+    }
+    save(&favorites);
+    drop(favorites);
+    gallery::changed(Gallery::Favorites);
+    starred
+}
+
+/// Unstar `path`.
+pub fn remove(path: &Path) {
+    if let Ok(mut favorites) = FAVORITES.lock() {
+        favorites.retain(|entry| !same_path(&entry.path, path));
+        save(&favorites);
+    }
+    gallery::changed(Gallery::Favorites);
+}
+
+/// Read the shell's thumbnail for favorites that have none yet.
+fn load_missing_thumbnails() {
+    if LOADING_THUMBNAILS.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    std::thread::spawn(|| {
+        let missing: Vec<PathBuf> = FAVORITES
+            .lock()
+            .map(|favorites| {
+                favorites
+                    .iter()
+                    .filter(|entry| entry.thumbnail.is_none())
+                    .map(|entry| entry.path.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let side = THUMB_SIDE as u32;
+        for path in missing {
+            let Some(image) = first_pass::shell_thumbnail(&path, side, side) else {
+                continue;
+            };
+            let image = image.to_rgba8();
+            let (width, height) = image.dimensions();
+            let pixels = preview_window::rgba_to_bgra(image.as_raw());
+            let thumbnail = Thumbnail::from_frame(&pixels, width, height);
+            if let Ok(mut favorites) = FAVORITES.lock() {
+                if let Some(entry) = favorites.iter_mut().find(|entry| entry.path == path) {
+                    entry.thumbnail = thumbnail;
+                }
+            }
+            gallery::changed(Gallery::Favorites);
+        }
+        LOADING_THUMBNAILS.store(false, Ordering::Release);
+    });
+}
+
+fn favorites_path() -> Option<PathBuf> {
+    Some(AppConfig::config_path()?.parent()?.join(FAVORITES_FILE))
+}
+
+fn save(favorites: &VecDeque<Entry>) {
+    let Some(path) = favorites_path() else {
+        return;
+    };
+    let text: String = favorites
+        .iter()
+        .map(|entry| format!("{}\n", entry.path.display()))
+        .collect();
+    if let Some(folder) = path.parent() {
+        let _ = std::fs::create_dir_all(folder);
+    }
+    let _ = std::fs::write(path, text);
+}
+
+/// Paths in `favorites.txt`, skipping blank lines and repeats.
+fn parse(text: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let path = PathBuf::from(line);
+        if !paths.iter().any(|existing| same_path(existing, &path)) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn favorites_file_skips_blanks_and_repeats() {
+        let text = "C:\\Photos\\a.jpg\r\n\r\n  D:\\b.png \nc:\\photos\\A.JPG\n";
+        assert_eq!(
+            parse(text),
+            vec![
+                PathBuf::from("C:\\Photos\\a.jpg"),
+                PathBuf::from("D:\\b.png")
+            ]
+        );
+        assert!(parse("").is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+//! The decoders exported as a C ABI, for file managers written in other
+//! languages.
+//!
+//! The crate also builds as `rust_hover_preview.dll`, which exports:
+//!
+//! ```c
+//! int32_t hp_load_preview(const wchar_t *path, uint32_t max_w, uint32_t max_h, HpFrame *out_buf);
+//! void    hp_free_preview(HpFrame *frame);
+//! ```
+//!
+//! `HpFrame` is the struct plugins return (see `providers/plugin.rs`).
+//! `hp_load_preview` decodes the first frame of `path` through the same
+//! provider registry as the app, plugins included, scaled to fit `max_w` x
+//! `max_h`, and returns non-zero on success with `out_buf` holding RGBA8
+//! pixels owned by the DLL. Every frame it fills is handed back to
+//! `hp_free_preview`. The app's `config.ini` applies, and slow stills share
+//! its thumbnail cache, so a file previewed by either is quick for the other.
+
+use crate::preview_window;
+use crate::providers::{self, HpFrame};
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::sync::Once;
+
+static PLUGINS: Once = Once::new();
+
+/// Decode `path` into `out_buf`; non-zero on success.
+///
+/// # Safety
+///
+/// `path` must be null or a NUL-terminated UTF-16 string, and `out_buf` null
+/// or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn hp_load_preview(
+    path: *const u16,
+    max_w: u32,
+    max_h: u32,
+    out_buf: *mut HpFrame,
+) -> i32 {
+    if path.is_null() || out_buf.is_null() || max_w == 0 || max_h == 0 {
+        return 0;
+    }
+    let len = (0..).take_while(|&i| *path.add(i) != 0).count();
+    let path = PathBuf::from(OsString::from_wide(std::slice::from_raw_parts(path, len)));
+    PLUGINS.call_once(providers::register_plugins);
+
+    // A panic must not unwind into the caller.
+    let loaded = std::panic::catch_unwind(|| {
+        let media = preview_window::load_hosted_media(&path, max_w, max_h)?;
+        let (bgra, width, height) = media.shown_frame();
+        // Swapping red and blue works both ways.
+        Some((preview_window::rgba_to_bgra(bgra), width, height))
+    })
+    .ok()
+    .flatten();
+    let Some((rgba, width, height)) = loaded else {
+        return 0;
+    };
+
+    *out_buf = HpFrame {
+        width,
+        height,
+        stride: width * 4,
+        pixels: Box::into_raw(rgba.into_boxed_slice()) as *mut u8,
+        user_data: std::ptr::null_mut(),
+    };
+    1
+}
+
+/// Free the pixels of a frame filled by `hp_load_preview`.
+///
+/// # Safety
+///
+/// `frame` must be null, zeroed, or filled by `hp_load_preview` and not freed
+/// since.
+#[no_mangle]
+pub unsafe extern "C" fn hp_free_preview(frame: *mut HpFrame) {
+    let Some(frame) = frame.as_mut() else {
+        return;
+    };
+    if frame.pixels.is_null() {
+        return;
+    }
+    let len = frame.stride as usize * frame.height as usize;
+    let pixels = std::ptr::slice_from_raw_parts_mut(frame.pixels, len);
+    drop(Box::from_raw(pixels));
+    frame.pixels = std::ptr::null_mut();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_frame() -> HpFrame {
+        HpFrame {
+            width: 0,
+            height: 0,
+            stride: 0,
+            pixels: std::ptr::null_mut(),
+            user_data: std::ptr::null_mut(),
+        }
+    }
+
+    #[test]
+    fn missing_arguments_fail_without_touching_the_frame() {
+        let path: Vec<u16> = "C:\\a.png\0".encode_utf16().collect();
+        let mut frame = empty_frame();
+        unsafe {
+            assert_eq!(hp_load_preview(std::ptr::null(), 64, 64, &mut frame), 0);
+            assert_eq!(hp_load_preview(path.as_ptr(), 0, 64, &mut frame), 0);
+            let no_frame = std::ptr::null_mut();
+            assert_eq!(hp_load_preview(path.as_ptr(), 64, 64, no_frame), 0);
+            assert!(frame.pixels.is_null());
+
+            // Freeing nothing is allowed.
+            hp_free_preview(&mut frame);
+            hp_free_preview(std::ptr::null_mut());
+        }
+    }
+}
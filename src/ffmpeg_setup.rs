@@ -0,0 +1,201 @@
+//! Detecting missing FFmpeg tools and installing a static build.
+//!
+//! The tray checks for `ffmpeg`, `ffprobe` and `ffplay` at startup and offers
+//! to install them. Installing downloads the gyan.dev "essentials" build with
+//! the `curl.exe` and `tar.exe` that ship with Windows 10 and later, checks it
+//! against the SHA-256 gyan.dev publishes with `certutil.exe`, unpacks it next
+//! to `config.ini` and points the `*_path` settings at its `bin` folder, so
+//! nothing has to be added to `PATH` and no restart is needed.
+
+use crate::config::AppConfig;
+use crate::video::{self, CREATE_NO_WINDOW};
+use crate::CONFIG;
+use std::fs;
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const DOWNLOAD_PAGE_URL: &str = "https://ffmpeg.org/download.html";
+const STATIC_BUILD_URL: &str = "https://www.gyan.dev/ffmpeg/builds/ffmpeg-release-essentials.zip";
+const STATIC_BUILD_SHA256_URL: &str =
+    "https://www.gyan.dev/ffmpeg/builds/ffmpeg-release-essentials.zip.sha256";
+const INSTALL_DIR_NAME: &str = "ffmpeg";
+
+/// Names of the FFmpeg tools that cannot be started with the current settings.
+pub fn missing_tools() -> Vec<&'static str> {
+    [
+        ("ffmpeg", video::ffmpeg_command()),
+        ("ffprobe", video::ffprobe_command()),
+        ("ffplay", video::ffplay_command()),
+    ]
+    .into_iter()
+    .filter_map(|(name, command)| (!runs(command)).then_some(name))
+    .collect()
+}
+
+/// "ffprobe", "ffprobe and ffplay", "ffmpeg, ffprobe and ffplay".
+pub fn list_tools(tools: &[&str]) -> String {
+    match tools.split_last() {
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn runs(mut command: Command) -> bool {
+    command
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Download, verify and unpack a static build, then point the tool paths at
+/// it. Blocks for the whole download; returns the `bin` folder on success and
+/// `None`, with the archive deleted, when it does not match its checksum.
+pub fn install() -> Option<PathBuf> {
+    let install_dir = AppConfig::config_path()?.parent()?.join(INSTALL_DIR_NAME);
+    fs::create_dir_all(&install_dir).ok()?;
+    let archive = install_dir.join("ffmpeg-release-essentials.zip");
+
+    let downloaded = hidden_command("curl.exe")
+        .args(["--location", "--fail", "--silent", "--output"])
+        .arg(&archive)
+        .arg(STATIC_BUILD_URL)
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    let verified = downloaded
+        && match (published_sha256(), file_sha256(&archive)) {
+            (Some(expected), Some(actual)) => expected == actual,
+            _ => false,
+        };
+    let extracted = verified
+        && hidden_command("tar.exe")
+            .arg("-xf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&install_dir)
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false);
+    let _ = fs::remove_file(&archive);
+    if !extracted {
+        return None;
+    }
+
+    let bin = find_bin_dir(&install_dir)?;
+    configure(&bin);
+    Some(bin)
+}
+
+/// The SHA-256 gyan.dev publishes next to the build.
+fn published_sha256() -> Option<String> {
+    let output = hidden_command("curl.exe")
+        .args(["--location", "--fail", "--silent"])
+        .arg(STATIC_BUILD_SHA256_URL)
+        .stdout(Stdio::piped())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(sha256_in)
+}
+
+/// The SHA-256 of `path`, from the `certutil.exe` that ships with Windows.
+fn file_sha256(path: &Path) -> Option<String> {
+    let output = hidden_command("certutil.exe")
+        .arg("-hashfile")
+        .arg(path)
+        .arg("SHA256")
+        .stdout(Stdio::piped())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(sha256_in)
+}
+
+/// The lowercase hex digest a line starts with or consists of. Checksum files
+/// may follow it with the file name; older `certutil` versions put spaces
+/// between its bytes.
+fn sha256_in(line: &str) -> Option<String> {
+    let is_digest = |hex: &str| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit());
+    let first = line.split_whitespace().next()?;
+    if is_digest(first) {
+        return Some(first.to_ascii_lowercase());
+    }
+    let joined: String = line.split_whitespace().collect();
+    is_digest(&joined).then(|| joined.to_ascii_lowercase())
+}
+
+fn hidden_command(program: &str) -> Command {
+    let mut command = Command::new(program);
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+/// The archive unpacks to a versioned folder (`ffmpeg-7.1-essentials_build`);
+/// use the newest one that has the tools.
+fn find_bin_dir(install_dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(install_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("bin"))
+        .filter(|bin| bin.join("ffmpeg.exe").is_file())
+        .max_by_key(|bin| fs::metadata(bin).and_then(|meta| meta.modified()).ok())
+}
+
+fn configure(bin: &Path) {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.ffmpeg_path = bin.join("ffmpeg.exe").to_string_lossy().to_string();
+        config.ffprobe_path = bin.join("ffprobe.exe").to_string_lossy().to_string();
+        config.ffplay_path = bin.join("ffplay.exe").to_string_lossy().to_string();
+        config.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_lists_read_naturally() {
+        assert_eq!(list_tools(&[]), "");
+        assert_eq!(list_tools(&["ffprobe"]), "ffprobe");
+        assert_eq!(list_tools(&["ffprobe", "ffplay"]), "ffprobe and ffplay");
+        assert_eq!(
+            list_tools(&["ffmpeg", "ffprobe", "ffplay"]),
+            "ffmpeg, ffprobe and ffplay"
+        );
+    }
+
+    #[test]
+    fn digests_are_read_from_checksum_files_and_certutil() {
+        let digest = "ab".repeat(32);
+        assert_eq!(sha256_in(&digest.to_uppercase()), Some(digest.clone()));
+        let listed = format!("{digest}  ffmpeg-release-essentials.zip");
+        assert_eq!(sha256_in(&listed), Some(digest.clone()));
+        let spaced = ["ab"; 32].join(" ");
+        assert_eq!(sha256_in(&spaced), Some(digest));
+        assert_eq!(sha256_in("SHA256 hash of ffmpeg.zip:"), None);
+        assert_eq!(
+            sha256_in("CertUtil: -hashfile command completed successfully."),
+            None
+        );
+    }
+}
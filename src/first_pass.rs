@@ -0,0 +1,225 @@
+//! Cheap stand-ins for a large still, shown while its full decode runs.
+//!
+//! Photos usually carry a small JPEG thumbnail in their EXIF block, and files
+//! Explorer has already shown have a bitmap in the Windows thumbnail cache.
+//! Either can be read in a few milliseconds. Both are only used when their
+//! aspect ratio matches the image, since some cameras pad EXIF thumbnails to
+//! 4:3.
+
+use crate::{exif, ocr};
+use image::{DynamicImage, RgbaImage};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use windows::core::HSTRING;
+use windows::Win32::Foundation::SIZE;
+use windows::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, DeleteDC, DeleteObject, GetDIBits, GetObjectW, BITMAP, BITMAPINFO,
+    BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+};
+use windows::Win32::UI::Shell::{
+    IShellItemImageFactory, SHCreateItemFromParsingName, SIIGBF, SIIGBF_INCACHEONLY,
+    SIIGBF_THUMBNAILONLY,
+};
+
+/// Relative difference in aspect ratio a stand-in may have.
+const ASPECT_TOLERANCE: f32 = 0.03;
+
+/// The embedded EXIF thumbnail of a JPEG file.
+pub fn exif_thumbnail(path: &Path) -> Option<DynamicImage> {
+    let mut head = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(exif::READ_LIMIT)
+        .read_to_end(&mut head)
+        .ok()?;
+    image::load_from_memory_with_format(exif_thumbnail_bytes(&head)?, image::ImageFormat::Jpeg).ok()
+}
+
+/// The Windows thumbnail cache's bitmap for `path`, if Explorer already made
+/// one. Never asks a thumbnail handler to render.
+pub fn shell_cached(path: &Path, width: u32, height: u32) -> Option<DynamicImage> {
+    shell_image(
+        path,
+        width,
+        height,
+        SIIGBF_THUMBNAILONLY | SIIGBF_INCACHEONLY,
+    )
+}
+
+/// The shell's thumbnail for `path`, rendered by its thumbnail handler when
+/// the cache has none.
+pub fn shell_thumbnail(path: &Path, width: u32, height: u32) -> Option<DynamicImage> {
+    shell_image(path, width, height, SIIGBF_THUMBNAILONLY)
+}
+
+fn shell_image(path: &Path, width: u32, height: u32, flags: SIIGBF) -> Option<DynamicImage> {
+    ocr::with_com(|| unsafe {
+        let item: IShellItemImageFactory =
+            SHCreateItemFromParsingName(&HSTRING::from(path.as_os_str()), None).ok()?;
+        let size = SIZE {
+            cx: width as i32,
+            cy: height as i32,
+        };
+        let bitmap = item.GetImage(size, flags).ok()?;
+
+        let mut info = BITMAP::default();
+        let read = GetObjectW(
+            bitmap,
+            std::mem::size_of::<BITMAP>() as i32,
+            Some(&mut info as *mut BITMAP as *mut _),
+        );
+        let (width, height) = (info.bmWidth.max(0) as u32, info.bmHeight.unsigned_abs());
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        let mut header = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width as i32,
+                biHeight: -(height as i32),
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let dc = CreateCompatibleDC(None);
+        let copied = read != 0
+            && !pixels.is_empty()
+            && GetDIBits(
+                dc,
+                bitmap,
+                0,
+                height,
+                Some(pixels.as_mut_ptr() as *mut _),
+                &mut header,
+                DIB_RGB_COLORS,
+            ) != 0;
+        let _ = DeleteDC(dc);
+        let _ = DeleteObject(bitmap);
+        if !copied {
+            return None;
+        }
+        RgbaImage::from_raw(width, height, bgra_to_opaque_rgba(pixels))
+            .map(DynamicImage::ImageRgba8)
+    })
+}
+
+/// Whether a `candidate` image has the shape of an `original` one.
+pub fn same_aspect(candidate: (u32, u32), original: (u32, u32)) -> bool {
+    if candidate.1 == 0 || original.1 == 0 {
+        return false;
+    }
+    let candidate = candidate.0 as f32 / candidate.1 as f32;
+    let original = original.0 as f32 / original.1 as f32;
+    ((candidate - original) / original).abs() <= ASPECT_TOLERANCE
+}
+
+/// The JPEG thumbnail in the EXIF block (IFD1) of a JPEG file's first bytes.
+fn exif_thumbnail_bytes(data: &[u8]) -> Option<&[u8]> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    tiff_thumbnail(exif::tiff_block(data)?)
+}
+
+fn tiff_thumbnail(tiff: &[u8]) -> Option<&[u8]> {
+    let little_endian = match tiff.get(..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    };
+    let u32_at = |offset: usize| {
+        let bytes: [u8; 4] = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+
+    // IFD1, the thumbnail's directory, follows IFD0.
+    let ifd0 = u32_at(4)? as usize;
+    let ifd1 = u32_at(ifd0 + 2 + u16_at(ifd0)? as usize * 12)? as usize;
+    if ifd1 == 0 {
+        return None;
+    }
+    let (mut offset, mut length) = (None, None);
+    for index in 0..u16_at(ifd1)? as usize {
+        let entry = ifd1 + 2 + index * 12;
+        match u16_at(entry)? {
+            0x0201 => offset = Some(u32_at(entry + 8)? as usize),
+            0x0202 => length = Some(u32_at(entry + 8)? as usize),
+            _ => {}
+        }
+    }
+    let (offset, length) = (offset?, length?);
+    let thumbnail = tiff.get(offset..offset.checked_add(length)?)?;
+    thumbnail.starts_with(&[0xFF, 0xD8]).then_some(thumbnail)
+}
+
+/// BGRA from GDI to RGBA. Bitmaps without alpha report it as 0 everywhere.
+fn bgra_to_opaque_rgba(mut pixels: Vec<u8>) -> Vec<u8> {
+    let opaque = pixels.chunks_exact(4).all(|px| px[3] == 0);
+    for px in pixels.chunks_exact_mut(4) {
+        px.swap(0, 2);
+        if opaque {
+            px[3] = 255;
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exif::tests::jpeg_with_exif;
+
+    /// A little-endian EXIF block whose IFD1 points at `thumbnail`.
+    fn thumbnail_tiff(thumbnail: &[u8]) -> Vec<u8> {
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend_from_slice(&8u32.to_le_bytes());
+        // IFD0: no entries, IFD1 at 14.
+        tiff.extend_from_slice(&0u16.to_le_bytes());
+        tiff.extend_from_slice(&14u32.to_le_bytes());
+        // IFD1: offset and length of the thumbnail, stored at 44.
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        for (tag, value) in [(0x0201u16, 44u32), (0x0202, thumbnail.len() as u32)] {
+            tiff.extend_from_slice(&tag.to_le_bytes());
+            tiff.extend_from_slice(&4u16.to_le_bytes());
+            tiff.extend_from_slice(&1u32.to_le_bytes());
+            tiff.extend_from_slice(&value.to_le_bytes());
+        }
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+        tiff.extend_from_slice(thumbnail);
+        tiff
+    }
+
+    #[test]
+    fn exif_thumbnail_is_found_after_other_segments() {
+        let thumbnail = [0xFF, 0xD8, 1, 2, 3, 0xFF, 0xD9];
+        let data = jpeg_with_exif(&thumbnail_tiff(&thumbnail));
+        assert_eq!(exif_thumbnail_bytes(&data), Some(&thumbnail[..]));
+        let not_a_thumbnail = jpeg_with_exif(&thumbnail_tiff(&[1, 2, 3]));
+        assert_eq!(exif_thumbnail_bytes(&not_a_thumbnail), None);
+        assert_eq!(exif_thumbnail_bytes(&data[..20]), None);
+        assert_eq!(exif_thumbnail_bytes(b"\x89PNG"), None);
+    }
+
+    #[test]
+    fn stand_ins_must_match_the_aspect_ratio() {
+        assert!(same_aspect((160, 107), (6000, 4000)));
+        assert!(!same_aspect((160, 120), (6000, 4000)));
+        assert!(!same_aspect((160, 0), (6000, 4000)));
+        assert_eq!(bgra_to_opaque_rgba(vec![1, 2, 3, 0]), vec![3, 2, 1, 255]);
+        assert_eq!(bgra_to_opaque_rgba(vec![1, 2, 3, 9]), vec![3, 2, 1, 9]);
+    }
+}
@@ -0,0 +1,593 @@
+//! Thumbnail grid windows for the preview history and the favorites.
+//!
+//! Each window lists its collection newest first in a scrollable grid.
+//! Clicking a thumbnail opens the file, dragging it out drops the file
+//! wherever Explorer would (a folder, a chat, an editor), and right-clicking
+//! shows it in its folder. In the favorites, middle-clicking a thumbnail
+//! removes it. Ctrl+S saves a contact sheet of the window's files.
+//!
+//! Windows are created on the tray thread, whose message loop dispatches their
+//! messages. Other threads change a collection and call `changed`, which posts
+//! `WM_GALLERY_CHANGED` to its window instead of touching it directly.
+
+use crate::contact_sheet::{self, SheetEntry};
+use crate::favorites;
+use crate::history;
+use crate::preview_window;
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, DrawTextW, EndPaint, FillRect, GetStockObject, GetSysColorBrush, InvalidateRect,
+    SelectObject, SetBkMode, SetDIBitsToDevice, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+    COLOR_BTNFACE, COLOR_WINDOW, DEFAULT_GUI_FONT, DIB_RGB_COLORS, DT_CENTER, DT_END_ELLIPSIS,
+    DT_NOPREFIX, DT_SINGLELINE, DT_VCENTER, PAINTSTRUCT, TRANSPARENT,
+};
+use windows::Win32::System::Com::IDataObject;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Ole::{OleInitialize, DROPEFFECT_COPY, DROPEFFECT_LINK};
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetKeyState, VK_CONTROL, VK_ESCAPE};
+use windows::Win32::UI::Shell::{
+    BHID_DataObject, IShellItem, SHCreateItemFromParsingName, SHDoDragDrop,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyWindow, DragDetect, GetClientRect,
+    LoadCursorW, PostMessageW, RegisterClassExW, SetForegroundWindow, SetScrollInfo,
+    SetWindowTextW, ShowWindow, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT, IDC_HAND, SB_BOTTOM,
+    SB_LINEDOWN, SB_LINEUP, SB_PAGEDOWN, SB_PAGEUP, SB_THUMBTRACK, SB_TOP, SB_VERT,
+    SCROLLBAR_COMMAND, SCROLLINFO, SIF_PAGE, SIF_POS, SIF_RANGE, SW_SHOW, WINDOW_EX_STYLE,
+    WM_DESTROY, WM_ERASEBKGND, WM_KEYDOWN, WM_LBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEWHEEL, WM_PAINT,
+    WM_RBUTTONUP, WM_SIZE, WM_USER, WM_VSCROLL, WNDCLASSEXW, WS_OVERLAPPEDWINDOW, WS_VSCROLL,
+};
+
+const GALLERY_CLASS: PCWSTR = w!("RustHoverPreviewGallery");
+/// Posted to a window when its collection changes.
+const WM_GALLERY_CHANGED: u32 = WM_USER + 1;
+pub const THUMB_SIDE: i32 = 112;
+const CELL_PADDING: i32 = 8;
+const LABEL_HEIGHT: i32 = 20;
+const CELL_WIDTH: i32 = THUMB_SIDE + 2 * CELL_PADDING;
+const CELL_HEIGHT: i32 = THUMB_SIDE + LABEL_HEIGHT + 2 * CELL_PADDING;
+const MARGIN: i32 = 8;
+/// Initial size of a window, in cells.
+const DEFAULT_COLUMNS: i32 = 6;
+const DEFAULT_ROWS: i32 = 4;
+/// Pixels scrolled per wheel notch.
+const WHEEL_SCROLL_PX: i32 = CELL_HEIGHT / 2;
+
+static CLASS_REGISTERED: AtomicBool = AtomicBool::new(false);
+static WINDOWS: Lazy<Mutex<HashMap<isize, GalleryWindow>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gallery {
+    History,
+    Favorites,
+}
+
+impl Gallery {
+    fn entries(self) -> &'static Mutex<VecDeque<Entry>> {
+        match self {
+            Gallery::History => &history::HISTORY,
+            Gallery::Favorites => &favorites::FAVORITES,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Gallery::History => "Preview History",
+            Gallery::Favorites => "Favorites",
+        }
+    }
+
+    fn title(self) -> String {
+        let count = self
+            .entries()
+            .lock()
+            .map(|entries| entries.len())
+            .unwrap_or(0);
+        let hints = match self {
+            Gallery::History => {
+                "click to open, drag out to copy, right-click to show in folder, \
+                 Ctrl+S contact sheet"
+            }
+            Gallery::Favorites => {
+                "click to open, drag out to copy, right-click to show in folder, \
+                 middle-click to remove, Ctrl+S contact sheet"
+            }
+        };
+        format!("{} ({}) - {}", self.name(), count, hints)
+    }
+
+    /// The entries as they stand, for a contact sheet.
+    fn sheet_entries(self) -> Vec<SheetEntry> {
+        let Ok(entries) = self.entries().lock() else {
+            return Vec::new();
+        };
+        entries
+            .iter()
+            .map(|entry| SheetEntry {
+                path: entry.path.clone(),
+                thumbnail: entry.thumbnail.clone(),
+                keep_thumbnail: entry.blurred,
+            })
+            .collect()
+    }
+
+    fn empty_text(self) -> &'static str {
+        match self {
+            Gallery::History => "Nothing previewed yet",
+            Gallery::Favorites => "No favorites yet: press favorite_key while a preview shows",
+        }
+    }
+}
+
+/// A file in a gallery.
+pub struct Entry {
+    pub path: PathBuf,
+    pub thumbnail: Option<Thumbnail>,
+    /// The thumbnail was taken from a blurred preview, so it must not be
+    /// swapped for a sharp one.
+    pub blurred: bool,
+}
+
+/// BGRA pixels fitting a `THUMB_SIDE` square.
+#[derive(Clone)]
+pub struct Thumbnail {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Thumbnail {
+    /// The BGRA frame scaled to fit a `THUMB_SIDE` square.
+    pub fn from_frame(pixels: &[u8], width: u32, height: u32) -> Option<Self> {
+        let frame = image::ImageBuffer::<image::Rgba<u8>, &[u8]>::from_raw(width, height, pixels)?;
+        let scale = (THUMB_SIDE as f32 / width.max(height) as f32).min(1.0);
+        let small = image::imageops::thumbnail(
+            &frame,
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+        );
+        let (width, height) = small.dimensions();
+        Some(Thumbnail {
+            pixels: small.into_raw(),
+            width,
+            height,
+        })
+    }
+}
+
+struct GalleryWindow {
+    gallery: Gallery,
+    scroll: i32,
+}
+
+/// Open the window for `gallery`, or bring it forward when it is already open.
+pub fn open(gallery: Gallery) {
+    unsafe {
+        if let Some(existing) = window_of(gallery) {
+            let _ = SetForegroundWindow(existing);
+            return;
+        }
+        let Ok(hinstance) = GetModuleHandleW(None) else {
+            return;
+        };
+        if !CLASS_REGISTERED.swap(true, Ordering::AcqRel) {
+            let wc = WNDCLASSEXW {
+                cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+                style: CS_HREDRAW | CS_VREDRAW,
+                lpfnWndProc: Some(gallery_window_proc),
+                hInstance: hinstance.into(),
+                hCursor: LoadCursorW(None, IDC_HAND).unwrap_or_default(),
+                lpszClassName: GALLERY_CLASS,
+                ..Default::default()
+            };
+            RegisterClassExW(&wc);
+        }
+
+        let style = WS_OVERLAPPEDWINDOW | WS_VSCROLL;
+        let mut rect = RECT {
+            left: 0,
+            top: 0,
+            right: 2 * MARGIN + DEFAULT_COLUMNS * CELL_WIDTH,
+            bottom: 2 * MARGIN + DEFAULT_ROWS * CELL_HEIGHT,
+        };
+        let _ = AdjustWindowRectEx(&mut rect, style, false, WINDOW_EX_STYLE(0));
+        let title = wide(&gallery.title());
+        let Ok(hwnd) = CreateWindowExW(
+            WINDOW_EX_STYLE(0),
+            GALLERY_CLASS,
+            PCWSTR(title.as_ptr()),
+            style,
+            CW_USEDEFAULT,
+            CW_USEDEFAULT,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            None,
+            None,
+            hinstance,
+            None,
+        ) else {
+            return;
+        };
+        if let Ok(mut windows) = WINDOWS.lock() {
+            windows.insert(hwnd.0 as isize, GalleryWindow { gallery, scroll: 0 });
+        }
+        update_scrollbar(hwnd);
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        let _ = SetForegroundWindow(hwnd);
+    }
+}
+
+/// Tell the window of `gallery`, if open, that its entries changed.
+pub fn changed(gallery: Gallery) {
+    if let Some(hwnd) = window_of(gallery) {
+        unsafe {
+            let _ = PostMessageW(hwnd, WM_GALLERY_CHANGED, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+fn window_of(gallery: Gallery) -> Option<HWND> {
+    let windows = WINDOWS.lock().ok()?;
+    windows
+        .iter()
+        .find(|(_, window)| window.gallery == gallery)
+        .map(|(&hwnd, _)| HWND(hwnd as *mut _))
+}
+
+/// The gallery shown in `hwnd` and its scroll position.
+fn window_state(hwnd: HWND) -> Option<(Gallery, i32)> {
+    let windows = WINDOWS.lock().ok()?;
+    let window = windows.get(&(hwnd.0 as isize))?;
+    Some((window.gallery, window.scroll))
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn columns(client_width: i32) -> i32 {
+    ((client_width - 2 * MARGIN) / CELL_WIDTH).max(1)
+}
+
+fn content_height(count: usize, columns: i32) -> i32 {
+    let rows = (count as i32 + columns - 1) / columns;
+    2 * MARGIN + rows * CELL_HEIGHT
+}
+
+/// The cell of entry `index`, in client coordinates.
+fn cell_rect(index: usize, columns: i32, scroll: i32) -> RECT {
+    let (row, column) = (index as i32 / columns, index as i32 % columns);
+    let left = MARGIN + column * CELL_WIDTH;
+    let top = MARGIN + row * CELL_HEIGHT - scroll;
+    RECT {
+        left,
+        top,
+        right: left + CELL_WIDTH,
+        bottom: top + CELL_HEIGHT,
+    }
+}
+
+/// The entry under client point `(x, y)`.
+fn cell_at(x: i32, y: i32, scroll: i32, columns: i32, count: usize) -> Option<usize> {
+    let (x, y) = (x - MARGIN, y + scroll - MARGIN);
+    if x < 0 || y < 0 || x / CELL_WIDTH >= columns {
+        return None;
+    }
+    let index = ((y / CELL_HEIGHT) * columns + x / CELL_WIDTH) as usize;
+    (index < count).then_some(index)
+}
+
+unsafe fn client_size(hwnd: HWND) -> (i32, i32) {
+    let mut rect = RECT::default();
+    let _ = GetClientRect(hwnd, &mut rect);
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+/// Fit the scroll range to the entries and clamp the scroll position to it.
+unsafe fn update_scrollbar(hwnd: HWND) {
+    let Some((gallery, scroll)) = window_state(hwnd) else {
+        return;
+    };
+    let count = gallery
+        .entries()
+        .lock()
+        .map(|entries| entries.len())
+        .unwrap_or(0);
+    let (width, height) = client_size(hwnd);
+    let content = content_height(count, columns(width));
+    let scroll = scroll.min(content - height).max(0);
+    if let Ok(mut windows) = WINDOWS.lock() {
+        if let Some(window) = windows.get_mut(&(hwnd.0 as isize)) {
+            window.scroll = scroll;
+        }
+    }
+    let info = SCROLLINFO {
+        cbSize: std::mem::size_of::<SCROLLINFO>() as u32,
+        fMask: SIF_RANGE | SIF_PAGE | SIF_POS,
+        nMin: 0,
+        nMax: content - 1,
+        nPage: height.max(0) as u32,
+        nPos: scroll,
+        ..Default::default()
+    };
+    SetScrollInfo(hwnd, SB_VERT, &info, true);
+}
+
+unsafe fn scroll_to(hwnd: HWND, scroll: i32) {
+    if let Ok(mut windows) = WINDOWS.lock() {
+        if let Some(window) = windows.get_mut(&(hwnd.0 as isize)) {
+            window.scroll = scroll;
+        }
+    }
+    update_scrollbar(hwnd);
+    let _ = InvalidateRect(hwnd, None, false);
+}
+
+unsafe fn paint(hwnd: HWND) {
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut ps);
+    FillRect(hdc, &ps.rcPaint, GetSysColorBrush(COLOR_WINDOW));
+    SelectObject(hdc, GetStockObject(DEFAULT_GUI_FONT));
+    SetBkMode(hdc, TRANSPARENT);
+    let (width, height) = client_size(hwnd);
+    let columns = columns(width);
+    let Some((gallery, scroll)) = window_state(hwnd) else {
+        let _ = EndPaint(hwnd, &ps);
+        return;
+    };
+    if let Ok(entries) = gallery.entries().lock() {
+        if entries.is_empty() {
+            let mut text: Vec<u16> = gallery.empty_text().encode_utf16().collect();
+            let mut rect = RECT {
+                left: 0,
+                top: 0,
+                right: width,
+                bottom: height,
+            };
+            DrawTextW(
+                hdc,
+                &mut text,
+                &mut rect,
+                DT_CENTER | DT_VCENTER | DT_SINGLELINE,
+            );
+        }
+        for (index, entry) in entries.iter().enumerate() {
+            let cell = cell_rect(index, columns, scroll);
+            if cell.bottom < 0 || cell.top > height {
+                continue;
+            }
+            let thumb_left = cell.left + CELL_PADDING;
+            let thumb_top = cell.top + CELL_PADDING;
+            match &entry.thumbnail {
+                Some(thumbnail) => {
+                    let bmi = BITMAPINFO {
+                        bmiHeader: BITMAPINFOHEADER {
+                            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                            biWidth: thumbnail.width as i32,
+                            biHeight: -(thumbnail.height as i32),
+                            biPlanes: 1,
+                            biBitCount: 32,
+                            biCompression: BI_RGB.0,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    SetDIBitsToDevice(
+                        hdc,
+                        thumb_left + (THUMB_SIDE - thumbnail.width as i32) / 2,
+                        thumb_top + (THUMB_SIDE - thumbnail.height as i32) / 2,
+                        thumbnail.width,
+                        thumbnail.height,
+                        0,
+                        0,
+                        0,
+                        thumbnail.height,
+                        thumbnail.pixels.as_ptr() as *const _,
+                        &bmi,
+                        DIB_RGB_COLORS,
+                    );
+                }
+                None => {
+                    let placeholder = RECT {
+                        left: thumb_left,
+                        top: thumb_top,
+                        right: thumb_left + THUMB_SIDE,
+                        bottom: thumb_top + THUMB_SIDE,
+                    };
+                    FillRect(hdc, &placeholder, GetSysColorBrush(COLOR_BTNFACE));
+                }
+            }
+            let name = entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let mut label: Vec<u16> = name.encode_utf16().collect();
+            let mut label_rect = RECT {
+                left: cell.left + 2,
+                top: thumb_top + THUMB_SIDE,
+                right: cell.right - 2,
+                bottom: thumb_top + THUMB_SIDE + LABEL_HEIGHT,
+            };
+            DrawTextW(
+                hdc,
+                &mut label,
+                &mut label_rect,
+                DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_END_ELLIPSIS | DT_NOPREFIX,
+            );
+        }
+    }
+    let _ = EndPaint(hwnd, &ps);
+}
+
+fn point_from_lparam(lparam: LPARAM) -> POINT {
+    POINT {
+        x: (lparam.0 & 0xFFFF) as u16 as i16 as i32,
+        y: ((lparam.0 >> 16) & 0xFFFF) as u16 as i16 as i32,
+    }
+}
+
+/// The gallery and path of the entry under `point`.
+unsafe fn entry_at(hwnd: HWND, point: POINT) -> Option<(Gallery, PathBuf)> {
+    let (gallery, scroll) = window_state(hwnd)?;
+    let (width, _) = client_size(hwnd);
+    let entries = gallery.entries().lock().ok()?;
+    let index = cell_at(point.x, point.y, scroll, columns(width), entries.len())?;
+    Some((gallery, entries[index].path.clone()))
+}
+
+/// Let the file be dropped wherever Explorer files can be, through the
+/// shell's own data object for it.
+unsafe fn drag_out(hwnd: HWND, path: &Path) {
+    // Drag and drop needs OLE on this thread; repeated calls are harmless.
+    let _ = OleInitialize(None);
+    let Ok(item) =
+        SHCreateItemFromParsingName::<_, _, IShellItem>(&HSTRING::from(path.as_os_str()), None)
+    else {
+        return;
+    };
+    let Ok(data) = item.BindToHandler::<_, IDataObject>(None, &BHID_DataObject) else {
+        return;
+    };
+    let _ = SHDoDragDrop(hwnd, &data, None, DROPEFFECT_COPY | DROPEFFECT_LINK);
+}
+
+unsafe extern "system" fn gallery_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_PAINT => {
+            paint(hwnd);
+            LRESULT(0)
+        }
+        WM_ERASEBKGND => LRESULT(1),
+        WM_SIZE => {
+            update_scrollbar(hwnd);
+            LRESULT(0)
+        }
+        WM_GALLERY_CHANGED => {
+            if let Some((gallery, _)) = window_state(hwnd) {
+                let title = wide(&gallery.title());
+                let _ = SetWindowTextW(hwnd, PCWSTR(title.as_ptr()));
+            }
+            update_scrollbar(hwnd);
+            let _ = InvalidateRect(hwnd, None, false);
+            LRESULT(0)
+        }
+        WM_VSCROLL => {
+            let scroll = window_state(hwnd).map(|(_, scroll)| scroll).unwrap_or(0);
+            let (_, page) = client_size(hwnd);
+            let target = match SCROLLBAR_COMMAND((wparam.0 & 0xFFFF) as i32) {
+                SB_LINEUP => scroll - CELL_HEIGHT / 4,
+                SB_LINEDOWN => scroll + CELL_HEIGHT / 4,
+                SB_PAGEUP => scroll - page,
+                SB_PAGEDOWN => scroll + page,
+                SB_THUMBTRACK => ((wparam.0 >> 16) & 0xFFFF) as i32,
+                SB_TOP => 0,
+                SB_BOTTOM => i32::MAX,
+                _ => scroll,
+            };
+            scroll_to(hwnd, target);
+            LRESULT(0)
+        }
+        WM_MOUSEWHEEL => {
+            let delta = ((wparam.0 >> 16) & 0xFFFF) as u16 as i16 as i32;
+            let scroll = window_state(hwnd).map(|(_, scroll)| scroll).unwrap_or(0);
+            scroll_to(hwnd, scroll - delta * WHEEL_SCROLL_PX / 120);
+            LRESULT(0)
+        }
+        WM_LBUTTONDOWN => {
+            let point = point_from_lparam(lparam);
+            if let Some((_, path)) = entry_at(hwnd, point) {
+                // `DragDetect` returns once the cursor leaves the drag
+                // rectangle or the button is released, which is a click.
+                if DragDetect(hwnd, point).as_bool() {
+                    drag_out(hwnd, &path);
+                } else {
+                    preview_window::shell_execute(w!("open"), path.as_os_str(), None);
+                }
+            }
+            LRESULT(0)
+        }
+        WM_RBUTTONUP => {
+            if let Some((_, path)) = entry_at(hwnd, point_from_lparam(lparam)) {
+                preview_window::show_in_folder(&path);
+            }
+            LRESULT(0)
+        }
+        WM_MBUTTONUP => {
+            if let Some((Gallery::Favorites, path)) = entry_at(hwnd, point_from_lparam(lparam)) {
+                favorites::remove(&path);
+            }
+            LRESULT(0)
+        }
+        WM_KEYDOWN => {
+            let key = wparam.0 as u16;
+            if key == VK_ESCAPE.0 {
+                let _ = DestroyWindow(hwnd);
+            } else if key == u16::from(b'S') && GetKeyState(VK_CONTROL.0 as i32) < 0 {
+                if let Some((gallery, _)) = window_state(hwnd) {
+                    contact_sheet::export(hwnd, gallery.name(), gallery.sheet_entries());
+                }
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if let Ok(mut windows) = WINDOWS.lock() {
+                windows.remove(&(hwnd.0 as isize));
+            }
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_hit_testing_follows_the_scroll() {
+        assert_eq!(columns(2 * MARGIN + 3 * CELL_WIDTH + 10), 3);
+        assert_eq!(columns(0), 1);
+        assert_eq!(content_height(7, 3), 2 * MARGIN + 3 * CELL_HEIGHT);
+
+        assert_eq!(cell_at(MARGIN, MARGIN, 0, 3, 7), Some(0));
+        assert_eq!(
+            cell_at(MARGIN + CELL_WIDTH, MARGIN + CELL_HEIGHT, 0, 3, 7),
+            Some(4)
+        );
+        assert_eq!(
+            cell_at(MARGIN + CELL_WIDTH, MARGIN, CELL_HEIGHT, 3, 7),
+            Some(4)
+        );
+        assert_eq!(
+            cell_at(MARGIN + CELL_WIDTH, MARGIN, 2 * CELL_HEIGHT, 3, 7),
+            None
+        );
+        assert_eq!(cell_at(MARGIN + 3 * CELL_WIDTH, MARGIN, 0, 3, 7), None);
+        assert_eq!(cell_at(MARGIN - 1, MARGIN, 0, 3, 7), None);
+
+        let rect = cell_rect(4, 3, CELL_HEIGHT);
+        assert_eq!((rect.left, rect.top), (MARGIN + CELL_WIDTH, MARGIN));
+    }
+
+    #[test]
+    fn thumbnails_fit_the_cell() {
+        let thumbnail = Thumbnail::from_frame(&[0; 224 * 112 * 4], 224, 112).unwrap();
+        let side = THUMB_SIDE as u32;
+        assert_eq!((thumbnail.width, thumbnail.height), (side, side / 2));
+        assert_eq!(thumbnail.pixels.len(), (side * side / 2 * 4) as usize);
+        assert!(Thumbnail::from_frame(&[0; 3], 1, 1).is_none());
+    }
+}
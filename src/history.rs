@@ -0,0 +1,26 @@
+//! The session's preview history.
+//!
+//! Every preview that loads is remembered with a small thumbnail of its first
+//! frame (blurred when the preview was). "Preview History..." in the tray menu
+//! opens a gallery window listing them newest first. Nothing is written to
+//! disk; the history ends with the session.
+
+use crate::gallery::{self, Entry, Gallery};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Oldest entries beyond this are forgotten.
+const MAX_ENTRIES: usize = 500;
+
+pub static HISTORY: Lazy<Mutex<VecDeque<Entry>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Remember a preview as the newest entry.
+pub fn record(entry: Entry) {
+    if let Ok(mut history) = HISTORY.lock() {
+        history.retain(|existing| existing.path != entry.path);
+        history.push_front(entry);
+        history.truncate(MAX_ENTRIES);
+    }
+    gallery::changed(Gallery::History);
+}
@@ -0,0 +1,343 @@
+//! Image dimensions read straight from file headers.
+//!
+//! Laying out a preview needs only the image's size, so the common formats are
+//! measured from their size fields without setting up a decoder: JPEG (the
+//! first start-of-frame segment), PNG (`IHDR`), GIF (the logical screen), WebP
+//! (`VP8 `, `VP8L` or `VP8X`) and BMP. The format is recognised by its magic
+//! bytes, not the extension, so mislabeled files are measured too. Anything
+//! else is `None`, and callers fall back to the `image` crate.
+//!
+//! Animated WebP loop counts are read here too, since the decoder does not
+//! report them, and so are the frame counts of animations, which the decoders
+//! only know once every frame is decoded.
+
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Enough for every size field outside JPEG.
+const HEAD_BYTES: usize = 30;
+/// JPEG segments skipped before giving up on finding the frame header.
+const MAX_JPEG_SEGMENTS: usize = 1024;
+
+pub fn dimensions(path: &Path) -> Option<(u32, u32)> {
+    read_dimensions(&mut BufReader::new(File::open(path).ok()?))
+}
+
+fn read_dimensions<R: Read + Seek>(reader: &mut R) -> Option<(u32, u32)> {
+    let mut head = [0u8; HEAD_BYTES];
+    let len = read_up_to(reader, &mut head)?;
+    let head = &head[..len];
+    let dimensions = if head.starts_with(&[0xFF, 0xD8]) {
+        reader.seek(SeekFrom::Start(2)).ok()?;
+        jpeg_dimensions(reader)
+    } else if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        png_dimensions(head)
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some((u16_le(head, 6)? as u32, u16_le(head, 8)? as u32))
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP".as_slice()) {
+        webp_dimensions(head)
+    } else if head.starts_with(b"BM") {
+        bmp_dimensions(head)
+    } else {
+        None
+    };
+    dimensions.filter(|&(width, height)| width > 0 && height > 0)
+}
+
+/// Fill as much of `buffer` as the file has; the length read.
+fn read_up_to<R: Read>(reader: &mut R, buffer: &mut [u8]) -> Option<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]).ok()? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Some(filled)
+}
+
+fn png_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    if head.get(12..16) != Some(b"IHDR".as_slice()) {
+        return None;
+    }
+    Some((u32_be(head, 16)?, u32_be(head, 20)?))
+}
+
+fn webp_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    match head.get(12..16)? {
+        // Lossy: frame tag, start code, then 14-bit sizes.
+        b"VP8 " => {
+            if head.get(23..26) != Some([0x9D, 0x01, 0x2A].as_slice()) {
+                return None;
+            }
+            Some((
+                (u16_le(head, 26)? & 0x3FFF) as u32,
+                (u16_le(head, 28)? & 0x3FFF) as u32,
+            ))
+        }
+        // Lossless: signature byte, then 14-bit sizes minus one.
+        b"VP8L" => {
+            if *head.get(20)? != 0x2F {
+                return None;
+            }
+            let bits = u32::from_le_bytes(head.get(21..25)?.try_into().ok()?);
+            Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+        }
+        // Extended (animated, alpha, metadata): 24-bit canvas sizes minus one.
+        b"VP8X" => Some((u24_le(head, 24)? + 1, u24_le(head, 27)? + 1)),
+        _ => None,
+    }
+}
+
+/// The loop count in an animated WebP's `ANIM` chunk, where 0 means forever.
+/// `None` for anything else.
+pub fn webp_loop_count(data: &[u8]) -> Option<u16> {
+    let (_, at) = webp_chunks(data).find(|(fourcc, _)| *fourcc == b"ANIM")?;
+    // The background color comes first.
+    u16_le(data, at + 12)
+}
+
+/// The number of frames (`ANMF` chunks) in an animated WebP; `None` for
+/// anything else.
+pub fn webp_frame_count(data: &[u8]) -> Option<usize> {
+    let frames = webp_chunks(data)
+        .filter(|(fourcc, _)| *fourcc == b"ANMF")
+        .count();
+    Some(frames).filter(|&frames| frames > 0)
+}
+
+/// The fourcc and offset of each top-level chunk of a WebP file, up to the
+/// first one cut short. Nothing for other files.
+fn webp_chunks(data: &[u8]) -> impl Iterator<Item = (&[u8], usize)> {
+    let webp = data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP".as_slice());
+    let mut at = if webp { 12 } else { data.len() };
+    std::iter::from_fn(move || {
+        let fourcc = data.get(at..at + 4)?;
+        let size = u32::from_le_bytes(data.get(at + 4..at + 8)?.try_into().ok()?) as usize;
+        let chunk = at;
+        // Chunks are padded to an even size.
+        at = at.checked_add(8 + size + (size & 1))?;
+        Some((fourcc, chunk))
+    })
+}
+
+/// The number of frames in a GIF, counted by walking its blocks without
+/// decoding them. A file cut short counts the frames before the cut.
+pub fn gif_frame_count(path: &Path) -> Option<usize> {
+    count_gif_frames(&mut BufReader::new(File::open(path).ok()?))
+}
+
+fn count_gif_frames<R: Read>(reader: &mut R) -> Option<usize> {
+    let mut header = [0u8; 13];
+    reader.read_exact(&mut header).ok()?;
+    if !header.starts_with(b"GIF87a") && !header.starts_with(b"GIF89a") {
+        return None;
+    }
+    skip_gif_color_table(reader, header[10])?;
+
+    let mut frames = 0;
+    let mut block = [0u8; 1];
+    while reader.read_exact(&mut block).is_ok() {
+        let separator = block[0];
+        let complete = match separator {
+            // Extension: its label, then data sub-blocks.
+            0x21 => reader.read_exact(&mut block).is_ok() && skip_gif_sub_blocks(reader).is_some(),
+            // Image: descriptor, local color table, LZW code size, data.
+            0x2C => {
+                let mut descriptor = [0u8; 9];
+                reader.read_exact(&mut descriptor).is_ok()
+                    && skip_gif_color_table(reader, descriptor[8]).is_some()
+                    && reader.read_exact(&mut block).is_ok()
+                    && skip_gif_sub_blocks(reader).is_some()
+            }
+            _ => break,
+        };
+        if !complete {
+            break;
+        }
+        if separator == 0x2C {
+            frames += 1;
+        }
+    }
+    Some(frames)
+}
+
+/// Skip the color table a GIF screen or image descriptor's packed field
+/// announces, if any.
+fn skip_gif_color_table<R: Read>(reader: &mut R, packed: u8) -> Option<()> {
+    if packed & 0x80 == 0 {
+        return Some(());
+    }
+    skip_bytes(reader, 3 << ((packed & 0x07) + 1))
+}
+
+/// Skip GIF data sub-blocks up to and including the empty one ending them.
+fn skip_gif_sub_blocks<R: Read>(reader: &mut R) -> Option<()> {
+    let mut size = [0u8; 1];
+    loop {
+        reader.read_exact(&mut size).ok()?;
+        if size[0] == 0 {
+            return Some(());
+        }
+        skip_bytes(reader, size[0] as u64)?;
+    }
+}
+
+fn skip_bytes<R: Read>(reader: &mut R, count: u64) -> Option<()> {
+    let skipped = std::io::copy(&mut reader.take(count), &mut std::io::sink()).ok()?;
+    (skipped == count).then_some(())
+}
+
+fn bmp_dimensions(head: &[u8]) -> Option<(u32, u32)> {
+    let header_size = u32::from_le_bytes(head.get(14..18)?.try_into().ok()?);
+    if header_size == 12 {
+        return Some((u16_le(head, 18)? as u32, u16_le(head, 20)? as u32));
+    }
+    let width = i32::from_le_bytes(head.get(18..22)?.try_into().ok()?);
+    // Negative heights are top-down bitmaps.
+    let height = i32::from_le_bytes(head.get(22..26)?.try_into().ok()?);
+    Some((width.unsigned_abs(), height.unsigned_abs()))
+}
+
+/// Walk the segments after SOI to the first start-of-frame marker.
+fn jpeg_dimensions<R: Read + Seek>(reader: &mut R) -> Option<(u32, u32)> {
+    let mut byte = [0u8; 1];
+    for _ in 0..MAX_JPEG_SEGMENTS {
+        reader.read_exact(&mut byte).ok()?;
+        if byte[0] != 0xFF {
+            return None;
+        }
+        // Any number of fill bytes may precede the marker.
+        let mut marker = 0xFF;
+        while marker == 0xFF {
+            reader.read_exact(&mut byte).ok()?;
+            marker = byte[0];
+        }
+        match marker {
+            // Standalone markers carry no length.
+            0x01 | 0xD0..=0xD7 => continue,
+            // Scan data or the end before any frame header.
+            0xD9 | 0xDA => return None,
+            _ => {}
+        }
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length).ok()?;
+        let length = u16::from_be_bytes(length);
+        if length < 2 {
+            return None;
+        }
+        // SOF0-SOF15, except DHT, JPG and DAC which share the range.
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            let mut frame = [0u8; 5];
+            reader.read_exact(&mut frame).ok()?;
+            let height = u16::from_be_bytes([frame[1], frame[2]]) as u32;
+            let width = u16::from_be_bytes([frame[3], frame[4]]) as u32;
+            return Some((width, height));
+        }
+        reader.seek(SeekFrom::Current(length as i64 - 2)).ok()?;
+    }
+    None
+}
+
+fn u16_le(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u24_le(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 3)?;
+    Some(b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn u32_be(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn measure(bytes: &[u8]) -> Option<(u32, u32)> {
+        read_dimensions(&mut Cursor::new(bytes))
+    }
+
+    #[test]
+    fn jpeg_skips_segments_before_the_frame_header() {
+        let mut jpeg = vec![0xFF, 0xD8];
+        // APP1 with a 6-byte payload, then fill bytes before SOF2.
+        jpeg.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x08, 1, 2, 3, 4, 5, 6]);
+        jpeg.extend_from_slice(&[0xFF, 0xFF, 0xC2, 0x00, 0x11, 0x08, 0x02, 0xD0, 0x05, 0x00]);
+        assert_eq!(measure(&jpeg), Some((1280, 720)));
+        assert_eq!(measure(&[0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x08]), None);
+    }
+
+    #[test]
+    fn fixed_headers_are_read_from_their_size_fields() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        assert_eq!(measure(&png), Some((640, 480)));
+
+        assert_eq!(measure(b"GIF89a\x40\x01\xf0\x00"), Some((320, 240)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+        webp.extend_from_slice(&[0x7F, 0x07, 0x00, 0x37, 0x04, 0x00]);
+        assert_eq!(measure(&webp), Some((1920, 1080)));
+
+        let mut lossless = b"RIFF\0\0\0\0WEBPVP8L\0\0\0\0\x2f".to_vec();
+        lossless.extend_from_slice(&(99u32 | (49u32 << 14)).to_le_bytes());
+        assert_eq!(measure(&lossless), Some((100, 50)));
+
+        let mut bmp = b"BM".to_vec();
+        bmp.extend_from_slice(&[0; 12]);
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&800i32.to_le_bytes());
+        bmp.extend_from_slice(&(-600i32).to_le_bytes());
+        assert_eq!(measure(&bmp), Some((800, 600)));
+
+        assert_eq!(measure(b"not an image"), None);
+    }
+
+    #[test]
+    fn webp_loop_count_is_read_from_the_anim_chunk() {
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0".to_vec();
+        webp.extend_from_slice(&[0; 10]);
+        // An odd-sized chunk is padded before the next one.
+        webp.extend_from_slice(b"ICCP\x03\0\0\0abc\0");
+        webp.extend_from_slice(b"ANIM\x06\0\0\0\xff\xff\xff\xff\x03\x00");
+        assert_eq!(webp_loop_count(&webp), Some(3));
+
+        assert_eq!(webp_loop_count(&webp[..webp.len() - 8]), None);
+        assert_eq!(webp_loop_count(b"GIF89a\x40\x01\xf0\x00"), None);
+    }
+
+    #[test]
+    fn animation_frames_are_counted_without_decoding() {
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0".to_vec();
+        webp.extend_from_slice(&[0; 10]);
+        webp.extend_from_slice(b"ANIM\x06\0\0\0\0\0\0\0\0\0");
+        webp.extend_from_slice(b"ANMF\x01\0\0\0x\0ANMF\x02\0\0\0xy");
+        assert_eq!(webp_frame_count(&webp), Some(2));
+        assert_eq!(webp_frame_count(&webp[..webp.len() - 12]), Some(1));
+        assert_eq!(webp_frame_count(b"RIFF\0\0\0\0WEBPVP8 \0\0\0\0"), None);
+
+        // A global color table of two colors, a comment, and two images, the
+        // second with a local color table of two colors.
+        let mut gif = b"GIF89a\x01\0\x01\0\x80\0\0".to_vec();
+        gif.extend_from_slice(&[0; 6]);
+        gif.extend_from_slice(b"\x21\xfe\x02hi\0");
+        let image = b"\x2c\0\0\0\0\x01\0\x01\0";
+        gif.extend_from_slice(image);
+        gif.extend_from_slice(b"\0\x02\x02\x4c\x01\0");
+        gif.extend_from_slice(image);
+        gif.push(0x80);
+        gif.extend_from_slice(&[0; 6]);
+        gif.extend_from_slice(b"\x02\x02\x4c\x01\0\x3b");
+        let count = |bytes: &[u8]| count_gif_frames(&mut Cursor::new(bytes));
+        assert_eq!(count(&gif), Some(2));
+        // Cut inside the second image's data.
+        assert_eq!(count(&gif[..gif.len() - 3]), Some(1));
+        assert_eq!(count(b"not a gif at all"), None);
+    }
+}
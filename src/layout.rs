@@ -0,0 +1,654 @@
+//! Where previews and pinned windows go on screen.
+//!
+//! Pure geometry, kept apart from the window code so it can be tested on any
+//! monitor arrangement: screen coordinates run from the virtual screen's
+//! origin, which is negative for monitors left of or above the primary one.
+//! A preview sits beside the cursor or the selected item, in the corner of the
+//! monitor's work area with the most room (`follow_cursor`), or in the taller
+//! of the strips left and right of it, centered vertically. With
+//! `anchor_to_item`, mouse previews sit beside the hovered item instead,
+//! level with its top. Either way a mouse preview keeps clear of the hovered
+//! item, so it never hides the name being pointed at. With `fixed_panel`,
+//! every preview goes in a box of a set size docked to a corner or edge
+//! instead. The work area is that of the monitor the preview was requested
+//! on, not the primary one.
+
+use crate::config::PanelDock;
+use crate::preview_window::scale_dimensions;
+use windows::Win32::Foundation::RECT;
+
+/// Distance between the cursor and a preview placed beside it.
+const CURSOR_OFFSET: i32 = 20;
+/// Distance between a selected item and a preview placed beside it.
+const ITEM_GAP: i32 = 10;
+/// Space between the fixed panel and the edges of the work area.
+const PANEL_MARGIN: i32 = 12;
+/// Narrowest strip beside a hovered item worth anchoring a preview in.
+const MIN_ITEM_STRIP: i32 = 160;
+/// Corners around the anchor, as (left of it, above it), in order of
+/// preference when several fit the preview equally well.
+const CORNERS: [(bool, bool); 4] = [(false, false), (true, false), (false, true), (true, true)];
+
+/// Computed preview window layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreviewLayout {
+    pub pos_x: i32,
+    pub pos_y: i32,
+    pub max_width: u32,
+    pub max_height: u32,
+    pub preview_w: u32,
+    pub preview_h: u32,
+    /// The work area the preview was laid out in.
+    pub work_area: ScreenBounds,
+    /// Whether the preview is left of the anchor, so its right edge is the
+    /// one kept clear of it.
+    on_left: bool,
+    /// Whether the preview is above (`Some(true)`) or below the anchor, or
+    /// centered vertically in the work area (`None`).
+    on_top: Option<bool>,
+}
+
+impl PreviewLayout {
+    /// Where a window of `width` x `height` goes for this layout. Decoded
+    /// previews can come out a little larger or smaller than the probed size;
+    /// the edge facing the anchor stays put, and the window is kept on the
+    /// work area.
+    pub fn position_for(&self, width: i32, height: i32) -> (i32, i32) {
+        let work = self.work_area;
+        let x = if self.on_left {
+            self.pos_x + self.preview_w as i32 - width
+        } else {
+            self.pos_x
+        };
+        let y = match self.on_top {
+            Some(true) => self.pos_y + self.preview_h as i32 - height,
+            Some(false) => self.pos_y,
+            None => work.top + (work.height() - height) / 2,
+        };
+        // A window larger than the work area keeps its top-left corner on it.
+        (
+            x.min(work.right - width).max(work.left),
+            y.min(work.bottom - height).max(work.top),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenBounds {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+impl ScreenBounds {
+    pub fn height(self) -> i32 {
+        self.bottom - self.top
+    }
+}
+
+/// Compute preview layout for mouse hover (relative to cursor position)
+pub fn compute_mouse_layout(
+    cursor_x: i32,
+    cursor_y: i32,
+    orig_dims: (u32, u32),
+    follow_cursor: bool,
+    bounds: ScreenBounds,
+) -> Option<PreviewLayout> {
+    let cursor = ScreenBounds {
+        left: cursor_x,
+        top: cursor_y,
+        right: cursor_x,
+        bottom: cursor_y,
+    };
+    place_beside(cursor, CURSOR_OFFSET, orig_dims, follow_cursor, bounds)
+}
+
+/// Compute preview layout for mouse hover, kept clear of the hovered `item`
+/// as well as the cursor. When that leaves room for less than half the size
+/// the cursor alone allows, such as beside a details-view row spanning the
+/// monitor, the item is covered rather than the preview shrunk.
+pub fn compute_mouse_layout_around(
+    cursor_x: i32,
+    cursor_y: i32,
+    item: ScreenBounds,
+    orig_dims: (u32, u32),
+    follow_cursor: bool,
+    bounds: ScreenBounds,
+) -> Option<PreviewLayout> {
+    let unobstructed = compute_mouse_layout(cursor_x, cursor_y, orig_dims, follow_cursor, bounds);
+    let around = ScreenBounds {
+        left: item.left.min(cursor_x),
+        top: item.top.min(cursor_y),
+        right: item.right.max(cursor_x),
+        bottom: item.bottom.max(cursor_y),
+    };
+    let clear = place_beside(around, CURSOR_OFFSET, orig_dims, follow_cursor, bounds);
+    match (clear, unobstructed) {
+        (Some(clear), Some(unobstructed)) if clear.preview_w * 2 < unobstructed.preview_w => {
+            Some(unobstructed)
+        }
+        (Some(clear), _) => Some(clear),
+        (None, unobstructed) => unobstructed,
+    }
+}
+
+/// Compute preview layout for keyboard hover (relative to item bounding rect)
+/// Positions the preview so it doesn't block the selected file item
+pub fn compute_keyboard_layout(
+    item: ScreenBounds,
+    orig_dims: (u32, u32),
+    follow_cursor: bool,
+    bounds: ScreenBounds,
+) -> Option<PreviewLayout> {
+    place_beside(item, ITEM_GAP, orig_dims, follow_cursor, bounds)
+}
+
+/// Compute preview layout for a mouse hover anchored to the hovered item
+/// (`anchor_to_item`): right of the item and level with its top, or left of it
+/// when that side has room for a larger preview. `None` when neither side has
+/// a useful strip, such as beside a details-view row spanning the window.
+pub fn compute_item_layout(
+    item: ScreenBounds,
+    orig_dims: (u32, u32),
+    bounds: ScreenBounds,
+) -> Option<PreviewLayout> {
+    let (orig_w, orig_h) = (orig_dims.0 as f32, orig_dims.1 as f32);
+    let full_height = bounds.height();
+    let fit = |width: i32| (width as f32 / orig_w).min(full_height as f32 / orig_h);
+    let left = item.left - bounds.left - ITEM_GAP;
+    let right = bounds.right - item.right - ITEM_GAP;
+    let on_left = fit(left) > fit(right);
+    let avail_w = if on_left { left } else { right };
+    if avail_w < MIN_ITEM_STRIP || full_height <= 0 {
+        return None;
+    }
+
+    let max_width = avail_w as u32;
+    let max_height = full_height as u32;
+    let (preview_w, preview_h) = scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
+    if preview_w == 0 || preview_h == 0 {
+        return None;
+    }
+    let pos_x = if on_left {
+        item.left - ITEM_GAP - preview_w as i32
+    } else {
+        item.right + ITEM_GAP
+    };
+    let pos_y = item
+        .top
+        .min(bounds.bottom - preview_h as i32)
+        .max(bounds.top);
+
+    Some(PreviewLayout {
+        pos_x,
+        pos_y,
+        max_width,
+        max_height,
+        preview_w,
+        preview_h,
+        work_area: bounds,
+        on_left,
+        on_top: Some(false),
+    })
+}
+
+/// Compute preview layout for the fixed panel (`fixed_panel`): the preview
+/// fitted into `panel_size`, pushed into the corner or against the edge of the
+/// work area at `dock`, and centered along a docked edge.
+pub fn compute_panel_layout(
+    dock: PanelDock,
+    panel_size: (u32, u32),
+    orig_dims: (u32, u32),
+    bounds: ScreenBounds,
+) -> Option<PreviewLayout> {
+    let room_w = bounds.right - bounds.left - PANEL_MARGIN * 2;
+    let room_h = bounds.height() - PANEL_MARGIN * 2;
+    let max_width = (panel_size.0.min(i32::MAX as u32) as i32).min(room_w);
+    let max_height = (panel_size.1.min(i32::MAX as u32) as i32).min(room_h);
+    if max_width <= 0 || max_height <= 0 {
+        return None;
+    }
+    let (max_width, max_height) = (max_width as u32, max_height as u32);
+    let (preview_w, preview_h) = scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
+    if preview_w == 0 || preview_h == 0 {
+        return None;
+    }
+
+    // Each axis docks to its start, end, or neither (centered).
+    let (horizontal, vertical) = match dock {
+        PanelDock::TopLeft => (Some(false), Some(false)),
+        PanelDock::Top => (None, Some(false)),
+        PanelDock::TopRight => (Some(true), Some(false)),
+        PanelDock::Left => (Some(false), None),
+        PanelDock::Right => (Some(true), None),
+        PanelDock::BottomLeft => (Some(false), Some(true)),
+        PanelDock::Bottom => (None, Some(true)),
+        PanelDock::BottomRight => (Some(true), Some(true)),
+    };
+    let place = |start: i32, end: i32, size: u32, docked: Option<bool>| match docked {
+        Some(false) => start + PANEL_MARGIN,
+        Some(true) => end - PANEL_MARGIN - size as i32,
+        None => start + (end - start - size as i32) / 2,
+    };
+
+    Some(PreviewLayout {
+        pos_x: place(bounds.left, bounds.right, preview_w, horizontal),
+        pos_y: place(bounds.top, bounds.bottom, preview_h, vertical),
+        max_width,
+        max_height,
+        preview_w,
+        preview_h,
+        work_area: bounds,
+        // A preview decoded at another size keeps its docked edges.
+        on_left: horizontal == Some(true),
+        on_top: vertical,
+    })
+}
+
+/// Lay out a preview of `orig_dims` within `bounds`, `margin` pixels clear of
+/// `anchor`, shrunk to fit the room it is given but never enlarged.
+fn place_beside(
+    anchor: ScreenBounds,
+    margin: i32,
+    orig_dims: (u32, u32),
+    follow_cursor: bool,
+    bounds: ScreenBounds,
+) -> Option<PreviewLayout> {
+    let (orig_w, orig_h) = (orig_dims.0 as f32, orig_dims.1 as f32);
+    let fit =
+        |width: i32, height: i32| (width as f32 / orig_w).min(height as f32 / orig_h).min(1.0);
+    let left = anchor.left - bounds.left - margin;
+    let right = bounds.right - anchor.right - margin;
+    let above = anchor.top - bounds.top - margin;
+    let below = bounds.bottom - anchor.bottom - margin;
+
+    let (on_left, on_top, max_width, max_height) = if follow_cursor {
+        let mut best = None;
+        let mut best_scale: f32 = 0.0;
+        for (on_left, on_top) in CORNERS {
+            let avail_w = if on_left { left } else { right };
+            let avail_h = if on_top { above } else { below };
+            if avail_w <= 0 || avail_h <= 0 {
+                continue;
+            }
+            let scale = fit(avail_w, avail_h);
+            if scale > best_scale {
+                best_scale = scale;
+                best = Some((on_left, Some(on_top), avail_w, avail_h));
+            }
+        }
+        best?
+    } else {
+        let full_height = bounds.height();
+        let on_left = fit(left, full_height) > fit(right, full_height) && left > 0;
+        let avail_w = if on_left { left } else { right };
+        if avail_w <= 0 {
+            return None;
+        }
+        (on_left, None, avail_w, full_height)
+    };
+
+    let max_width = max_width.max(1) as u32;
+    let max_height = max_height.max(1) as u32;
+    let (preview_w, preview_h) = scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
+    let media_width = preview_w as i32;
+    let media_height = preview_h as i32;
+    if media_width <= 0 || media_height <= 0 {
+        return None;
+    }
+
+    let pos_x = if on_left {
+        anchor.left - margin - media_width
+    } else {
+        anchor.right + margin
+    };
+    let pos_y = match on_top {
+        Some(true) => anchor.top - margin - media_height,
+        Some(false) => anchor.bottom + margin,
+        None => bounds.top + (bounds.height() - media_height) / 2,
+    };
+
+    Some(PreviewLayout {
+        pos_x,
+        pos_y,
+        max_width,
+        max_height,
+        preview_w,
+        preview_h,
+        work_area: bounds,
+        on_left,
+        on_top,
+    })
+}
+
+/// Move `rect` the least distance that puts it inside `work`. A rect larger
+/// than `work` keeps its top-left corner (and so its title bar) inside.
+pub fn fit_in_work_area(rect: RECT, work: RECT) -> RECT {
+    let shift = |start: i32, end: i32, min: i32, max: i32| {
+        if end > max {
+            (max - end).max(min - start)
+        } else if start < min {
+            min - start
+        } else {
+            0
+        }
+    };
+    let dx = shift(rect.left, rect.right, work.left, work.right);
+    let dy = shift(rect.top, rect.bottom, work.top, work.bottom);
+    RECT {
+        left: rect.left + dx,
+        top: rect.top + dy,
+        right: rect.right + dx,
+        bottom: rect.bottom + dy,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(left: i32, top: i32, right: i32, bottom: i32) -> ScreenBounds {
+        ScreenBounds {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// `(pos_x, pos_y, preview_w, preview_h, max_width, max_height)`.
+    fn placed(layout: Option<PreviewLayout>) -> Option<(i32, i32, u32, u32, u32, u32)> {
+        layout.map(|l| {
+            (
+                l.pos_x,
+                l.pos_y,
+                l.preview_w,
+                l.preview_h,
+                l.max_width,
+                l.max_height,
+            )
+        })
+    }
+
+    /// 1920x1080 primary monitor with a 40 pixel taskbar at the bottom.
+    const PRIMARY: ScreenBounds = ScreenBounds {
+        left: 0,
+        top: 0,
+        right: 1920,
+        bottom: 1040,
+    };
+    /// The same monitor to the left of the primary one.
+    const LEFT_OF_PRIMARY: ScreenBounds = ScreenBounds {
+        left: -1920,
+        top: 0,
+        right: 0,
+        bottom: 1040,
+    };
+
+    #[test]
+    fn mouse_previews_take_the_roomiest_corner() {
+        // Room everywhere but up and left: bottom-right, at full size.
+        assert_eq!(
+            placed(compute_mouse_layout(100, 100, (800, 600), true, PRIMARY)),
+            Some((120, 120, 800, 600, 1800, 920))
+        );
+        // Near the taskbar in the bottom-right corner: top-left.
+        assert_eq!(
+            placed(compute_mouse_layout(1800, 1000, (800, 600), true, PRIMARY)),
+            Some((980, 380, 800, 600, 1780, 980))
+        );
+        // Room for the full size in every corner: bottom-right wins.
+        assert_eq!(
+            placed(compute_mouse_layout(1000, 520, (400, 300), true, PRIMARY)),
+            Some((1020, 540, 400, 300, 900, 500))
+        );
+    }
+
+    #[test]
+    fn mouse_previews_stay_on_monitors_at_negative_coordinates() {
+        // Shrunk to the 600 pixels below the cursor, and placed left of it.
+        assert_eq!(
+            placed(compute_mouse_layout(
+                -100,
+                420,
+                (1600, 1200),
+                true,
+                LEFT_OF_PRIMARY
+            )),
+            Some((-920, 440, 800, 600, 1800, 600))
+        );
+        // A portrait monitor above the primary one, in best-spot mode: the
+        // wider strip left of the cursor, centered vertically.
+        let portrait_above = bounds(0, -1920, 1080, 0);
+        assert_eq!(
+            placed(compute_mouse_layout(
+                700,
+                -1000,
+                (1000, 2000),
+                false,
+                portrait_above
+            )),
+            Some((0, -1640, 680, 1360, 680, 1920))
+        );
+    }
+
+    #[test]
+    fn mouse_previews_fit_portrait_monitors() {
+        // Portrait monitor right of the primary one, its top above it.
+        let portrait = bounds(1920, -400, 3000, 1520);
+        assert_eq!(
+            placed(compute_mouse_layout(
+                2000,
+                1400,
+                (900, 1600),
+                true,
+                portrait
+            )),
+            Some((2020, -220, 900, 1600, 980, 1780))
+        );
+        assert_eq!(
+            placed(compute_mouse_layout(
+                2900,
+                500,
+                (900, 1600),
+                false,
+                portrait
+            )),
+            Some((1980, -240, 900, 1600, 960, 1920))
+        );
+    }
+
+    #[test]
+    fn keyboard_previews_keep_clear_of_the_item() {
+        let item = bounds(-1500, 300, -1400, 400);
+        assert_eq!(
+            placed(compute_keyboard_layout(
+                item,
+                (400, 300),
+                true,
+                LEFT_OF_PRIMARY
+            )),
+            Some((-1390, 410, 400, 300, 1390, 630))
+        );
+        assert_eq!(
+            placed(compute_keyboard_layout(
+                item,
+                (400, 300),
+                false,
+                LEFT_OF_PRIMARY
+            )),
+            Some((-1390, 370, 400, 300, 1390, 1040))
+        );
+        // An item filling the monitor leaves no room at all.
+        let everywhere = bounds(-1920, 0, 0, 1040);
+        assert_eq!(
+            compute_keyboard_layout(everywhere, (400, 300), true, LEFT_OF_PRIMARY),
+            None
+        );
+        assert_eq!(
+            compute_keyboard_layout(everywhere, (400, 300), false, LEFT_OF_PRIMARY),
+            None
+        );
+    }
+
+    #[test]
+    fn mouse_previews_keep_clear_of_the_hovered_item() {
+        // The cursor on the left of a large icon: beside the icon, not over it.
+        let icon = bounds(100, 100, 300, 340);
+        assert_eq!(
+            placed(compute_mouse_layout_around(
+                120,
+                150,
+                icon,
+                (800, 600),
+                true,
+                PRIMARY
+            )),
+            Some((320, 360, 800, 600, 1600, 680))
+        );
+        // A details-view row leaving a narrow strip at its end: covering the
+        // row beats a quarter-size preview.
+        let row = bounds(0, 300, 1700, 320);
+        assert_eq!(
+            compute_mouse_layout_around(400, 310, row, (800, 600), true, PRIMARY),
+            compute_mouse_layout(400, 310, (800, 600), true, PRIMARY)
+        );
+    }
+
+    #[test]
+    fn item_previews_sit_beside_the_item_level_with_its_top() {
+        // An icon on the left of the monitor: right of it, at full size.
+        let icon = bounds(100, 300, 200, 420);
+        assert_eq!(
+            placed(compute_item_layout(icon, (800, 600), PRIMARY)),
+            Some((210, 300, 800, 600, 1710, 1040))
+        );
+        // Low on the screen: moved up so the preview stays on the work area.
+        let low = bounds(100, 900, 200, 1000);
+        assert_eq!(
+            placed(compute_item_layout(low, (800, 600), PRIMARY)),
+            Some((210, 440, 800, 600, 1710, 1040))
+        );
+        // A row near the right edge: left of it, shrunk to the room there.
+        let row = bounds(1000, 500, 1800, 520);
+        assert_eq!(
+            placed(compute_item_layout(row, (1600, 1200), PRIMARY)),
+            Some((1, 298, 989, 742, 990, 1040))
+        );
+        // A details-view row across the whole monitor leaves no strip.
+        let wide = bounds(10, 500, 1850, 520);
+        assert_eq!(compute_item_layout(wide, (800, 600), PRIMARY), None);
+    }
+
+    #[test]
+    fn panel_previews_dock_to_the_chosen_corner_or_edge() {
+        // Shrunk into a 480x360 panel in the bottom-right corner.
+        let layout = compute_panel_layout(PanelDock::BottomRight, (480, 360), (800, 600), PRIMARY);
+        assert_eq!(placed(layout), Some((1428, 668, 480, 360, 480, 360)));
+        // Larger when decoded: the bottom-right corner stays put.
+        assert_eq!(layout.unwrap().position_for(500, 370), (1408, 658));
+
+        // Centered along the top edge of a monitor left of the primary one.
+        assert_eq!(
+            placed(compute_panel_layout(
+                PanelDock::Top,
+                (480, 360),
+                (300, 200),
+                LEFT_OF_PRIMARY
+            )),
+            Some((-1110, 12, 300, 200, 480, 360))
+        );
+        // A panel larger than the work area is cut down to it.
+        let small = bounds(0, 0, 400, 300);
+        assert_eq!(
+            placed(compute_panel_layout(
+                PanelDock::Left,
+                (2000, 2000),
+                (1000, 1000),
+                small
+            )),
+            Some((12, 12, 276, 276, 376, 276))
+        );
+    }
+
+    #[test]
+    fn previews_respect_the_virtual_screen_origin() {
+        let virtual_screen = bounds(-3440, 0, 3840, 2160);
+        let mouse = compute_mouse_layout(-3300, 1000, (800, 600), true, virtual_screen);
+        let item = bounds(-3200, 900, -3000, 1100);
+        let keyboard = compute_keyboard_layout(item, (800, 600), true, virtual_screen);
+
+        for layout in [mouse, keyboard] {
+            let layout = layout.expect("layout should fit on the left monitor");
+            assert!(layout.pos_x >= virtual_screen.left);
+            assert!(layout.pos_x + layout.preview_w as i32 <= virtual_screen.right);
+            assert!(layout.pos_y >= virtual_screen.top);
+            assert!(layout.pos_y + layout.preview_h as i32 <= virtual_screen.bottom);
+        }
+    }
+
+    #[test]
+    fn resized_previews_keep_the_edge_facing_the_anchor() {
+        // Laid out left of the cursor on a monitor left of the primary one.
+        let layout = compute_mouse_layout(-100, 420, (1600, 1200), true, LEFT_OF_PRIMARY)
+            .expect("layout should fit left of the cursor");
+        assert_eq!((layout.pos_x, layout.pos_y), (-920, 440));
+        assert_eq!(layout.position_for(800, 600), (-920, 440));
+        // Wider than probed: the right edge stays 20 pixels from the cursor.
+        assert_eq!(layout.position_for(900, 600), (-1020, 440));
+        // Taller than the room below the cursor: moved up onto the work area.
+        assert_eq!(layout.position_for(800, 700), (-920, 340));
+
+        // Above the cursor on a monitor above the primary one.
+        let above = bounds(0, -1080, 1920, 0);
+        let layout = compute_mouse_layout(100, -100, (400, 300), true, above)
+            .expect("layout should fit above the cursor");
+        assert_eq!((layout.pos_x, layout.pos_y), (120, -420));
+        assert_eq!(layout.position_for(400, 350), (120, -470));
+
+        // Centered vertically in best-spot mode, and never off the work area.
+        let layout = compute_mouse_layout(-1800, 500, (400, 300), false, LEFT_OF_PRIMARY)
+            .expect("layout should fit right of the cursor");
+        assert_eq!((layout.pos_x, layout.pos_y), (-1780, 370));
+        assert_eq!(layout.position_for(400, 400), (-1780, 320));
+        assert_eq!(layout.position_for(2400, 1200), (-1920, 0));
+    }
+
+    #[test]
+    fn pinned_windows_are_moved_into_the_work_area() {
+        let work = RECT {
+            left: 0,
+            top: 0,
+            right: 1920,
+            bottom: 1040,
+        };
+        let rect = |left, top, right, bottom| RECT {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        let edges = |r: RECT| (r.left, r.top, r.right, r.bottom);
+        // Under the taskbar, and above the top edge.
+        assert_eq!(
+            edges(fit_in_work_area(rect(100, 900, 500, 1200), work)),
+            (100, 740, 500, 1040)
+        );
+        assert_eq!(
+            edges(fit_in_work_area(rect(-8, -30, 400, 300), work)),
+            (0, 0, 408, 330)
+        );
+        // Taller than the work area: the title bar stays visible.
+        assert_eq!(
+            edges(fit_in_work_area(rect(10, 50, 300, 1250), work)),
+            (10, 0, 300, 1200)
+        );
+        // On a monitor left of and above the primary one.
+        let upper_left = rect(-1920, -1080, 0, 0);
+        assert_eq!(
+            edges(fit_in_work_area(rect(-300, -200, 100, 100), upper_left)),
+            (-400, -300, 0, 0)
+        );
+    }
+}
@@ -0,0 +1,172 @@
+mod accessibility_debug;
+mod annotation;
+mod automation;
+mod bench;
+mod codes;
+mod color_profile;
+mod config;
+mod config_watch;
+mod contact_sheet;
+mod container_names;
+mod dates;
+mod excluded_paths;
+mod exif;
+mod explorer_hook;
+mod favorites;
+mod ffi;
+mod ffmpeg_setup;
+mod first_pass;
+mod gallery;
+mod history;
+mod image_header;
+mod layout;
+mod media_foundation;
+mod network_drives;
+mod nsfw;
+mod ocr;
+mod overlay;
+mod pinned;
+mod prefetch;
+mod preview_handler;
+mod preview_window;
+mod priority;
+mod providers;
+mod shell_access;
+mod sound;
+mod startup;
+mod system_load;
+mod taskbar;
+mod thumbnail_cache;
+mod trace;
+mod tray;
+mod video;
+mod warm_start;
+mod zoom;
+
+use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED};
+use windows::Win32::UI::HiDpi::{
+    SetProcessDpiAwareness, SetProcessDpiAwarenessContext,
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, PROCESS_PER_MONITOR_DPI_AWARE,
+};
+
+// Global state
+pub static RUNNING: AtomicBool = AtomicBool::new(true);
+pub static CONFIG: Lazy<Mutex<config::AppConfig>> =
+    Lazy::new(|| Mutex::new(config::AppConfig::load()));
+
+/// Run the app, or the bench or preview handler server its arguments ask
+/// for, until it exits.
+pub fn run() {
+    // `--bench <folder>` times the decoders instead of starting the app.
+    if let Some(folder) = bench::folder_argument(std::env::args_os()) {
+        providers::register_plugins();
+        bench::run(&folder);
+        return;
+    }
+
+    // Explorer starts the app as the COM server of its preview pane and
+    // thumbnails.
+    if preview_handler::server_requested(std::env::args_os()) {
+        providers::register_plugins();
+        preview_handler::run_server();
+        return;
+    }
+
+    configure_dpi_awareness();
+    sync_startup_setting();
+    priority::apply_process_priority();
+
+    // Keep the preview pane and thumbnail registrations pointing at this
+    // copy of the app.
+    let (preview_pane, explorer_thumbnails) = CONFIG
+        .lock()
+        .map(|c| (c.preview_pane, c.explorer_thumbnails))
+        .unwrap_or((false, false));
+    if preview_pane {
+        preview_handler::register();
+    }
+    if explorer_thumbnails {
+        preview_handler::register_thumbnails();
+    }
+
+    // Initialize COM
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+    }
+
+    // Third-party providers must be registered before the hook starts matching files.
+    providers::register_plugins();
+
+    // Decode the newest stills of the most previewed folders ahead of use.
+    warm_start::start();
+
+    // Start the preview window in a separate thread
+    let preview_handle = std::thread::spawn(|| {
+        preview_window::run_preview_window();
+    });
+
+    // Apply edits to config.ini as soon as they are saved.
+    let config_watch_handle = std::thread::spawn(config_watch::run);
+
+    // Sample system load so previews can cut back during builds and renders.
+    let load_monitor_handle = std::thread::spawn(|| {
+        system_load::run_monitor();
+    });
+
+    // `--debug-accessibility` shows what the hook sees under the cursor.
+    let accessibility_debug_handle = accessibility_debug::requested(std::env::args_os())
+        .then(|| std::thread::spawn(accessibility_debug::run));
+
+    // Preview Explorer windows' selected files from their taskbar thumbnails.
+    let taskbar_handle = std::thread::spawn(taskbar::run);
+
+    // Start the explorer hook in a separate thread
+    let hook_handle = std::thread::spawn(|| {
+        explorer_hook::run_explorer_hook();
+    });
+
+    // Run the system tray (this blocks until exit)
+    tray::run_tray();
+
+    // Signal other threads to stop
+    RUNNING.store(false, Ordering::SeqCst);
+
+    // Wait for threads to finish (with timeout)
+    let _ = preview_handle.join();
+    let _ = hook_handle.join();
+    let _ = config_watch_handle.join();
+    let _ = load_monitor_handle.join();
+    let _ = taskbar_handle.join();
+    if let Some(handle) = accessibility_debug_handle {
+        let _ = handle.join();
+    }
+    warm_start::save();
+
+    // Cleanup COM
+    unsafe {
+        CoUninitialize();
+    }
+}
+
+fn configure_dpi_awareness() {
+    unsafe {
+        // Prefer per-monitor v2 to avoid DPI scaling artifacts on layered windows.
+        if SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2).is_err() {
+            let _ = SetProcessDpiAwareness(PROCESS_PER_MONITOR_DPI_AWARE);
+        }
+    }
+}
+
+fn sync_startup_setting() {
+    let should_enable_startup = CONFIG
+        .lock()
+        .map(|config| config.is_first_run && config.run_at_startup)
+        .unwrap_or(false);
+
+    if should_enable_startup {
+        startup::enable_startup();
+    }
+}
@@ -0,0 +1,420 @@
+//! Video decoding through Windows Media Foundation.
+//!
+//! `IMFSourceReader` with advanced video processing decodes, converts to RGB32
+//! and scales in one step, so MP4/MOV/WMV/AVI previews work on a stock Windows
+//! install without FFmpeg. Frames feed the same bounded queue as the ffmpeg
+//! pipe in `video.rs`.
+
+use crate::preview_window::ImageFrame;
+use crate::video::FrameQueue;
+use std::collections::VecDeque;
+use std::os::windows::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use windows::core::{GUID, PCWSTR, PROPVARIANT};
+use windows::Win32::Media::MediaFoundation::{
+    IMFMediaBuffer, IMFSample, IMFSourceReader, MFCreateAttributes, MFCreateMediaType,
+    MFCreateSourceReaderFromURL, MFMediaType_Video, MFShutdown, MFStartup, MFVideoFormat_RGB32,
+    MFSTARTUP_NOSOCKET, MF_MT_DEFAULT_STRIDE, MF_MT_FRAME_RATE, MF_MT_FRAME_SIZE, MF_MT_MAJOR_TYPE,
+    MF_MT_SUBTYPE, MF_PD_DURATION, MF_SOURCE_READERF_ENDOFSTREAM, MF_SOURCE_READER_ALL_STREAMS,
+    MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING, MF_SOURCE_READER_FIRST_VIDEO_STREAM,
+    MF_SOURCE_READER_MEDIASOURCE, MF_VERSION,
+};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+
+/// Containers Media Foundation decodes out of the box. MKV, WebM and FLV need
+/// optional Store codecs, so they stay on ffmpeg.
+pub const MF_VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mov", "wmv", "avi"];
+
+const MF_STREAM_BUFFER_FRAMES: usize = 8;
+const HUNDRED_NS_PER_SEC: f64 = 10_000_000.0;
+
+/// Keeps COM and Media Foundation started on this thread for the lifetime of
+/// the guard. Threads that already joined an STA keep it.
+struct MfRuntime {
+    com_initialized: bool,
+}
+
+impl MfRuntime {
+    fn start() -> Option<Self> {
+        unsafe {
+            let com_initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+            if MFStartup(MF_VERSION, MFSTARTUP_NOSOCKET).is_err() {
+                if com_initialized {
+                    CoUninitialize();
+                }
+                return None;
+            }
+            Some(MfRuntime { com_initialized })
+        }
+    }
+}
+
+impl Drop for MfRuntime {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = MFShutdown();
+            if self.com_initialized {
+                CoUninitialize();
+            }
+        }
+    }
+}
+
+fn first_video_stream() -> u32 {
+    MF_SOURCE_READER_FIRST_VIDEO_STREAM.0 as u32
+}
+
+fn split_u64(value: u64) -> (u32, u32) {
+    ((value >> 32) as u32, value as u32)
+}
+
+fn frame_rate_from_ratio(num: u32, den: u32) -> Option<f32> {
+    if num == 0 || den == 0 {
+        return None;
+    }
+    let rate = num as f32 / den as f32;
+    if rate.is_finite() && rate > 0.0 {
+        Some(rate)
+    } else {
+        None
+    }
+}
+
+fn open_reader(path: &PathBuf, output_size: Option<(u32, u32)>) -> Option<IMFSourceReader> {
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    unsafe {
+        let mut attributes = None;
+        MFCreateAttributes(&mut attributes, 1).ok()?;
+        let attributes = attributes?;
+        attributes
+            .SetUINT32(&MF_SOURCE_READER_ENABLE_ADVANCED_VIDEO_PROCESSING, 1)
+            .ok()?;
+
+        let reader = MFCreateSourceReaderFromURL(PCWSTR(wide.as_ptr()), &attributes).ok()?;
+        reader
+            .SetStreamSelection(MF_SOURCE_READER_ALL_STREAMS.0 as u32, false)
+            .ok()?;
+        reader.SetStreamSelection(first_video_stream(), true).ok()?;
+
+        if let Some((width, height)) = output_size {
+            let media_type = MFCreateMediaType().ok()?;
+            media_type
+                .SetGUID(&MF_MT_MAJOR_TYPE, &MFMediaType_Video)
+                .ok()?;
+            media_type
+                .SetGUID(&MF_MT_SUBTYPE, &MFVideoFormat_RGB32)
+                .ok()?;
+            media_type
+                .SetUINT64(&MF_MT_FRAME_SIZE, ((width as u64) << 32) | height as u64)
+                .ok()?;
+            reader
+                .SetCurrentMediaType(first_video_stream(), None, &media_type)
+                .ok()?;
+        }
+
+        Some(reader)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MfVideoInfo {
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: Option<f32>,
+    pub duration_secs: Option<f64>,
+}
+
+/// Native size, frame rate and duration of the first video stream.
+pub fn probe(path: &PathBuf) -> Option<MfVideoInfo> {
+    let _runtime = MfRuntime::start()?;
+    let reader = open_reader(path, None)?;
+    unsafe {
+        let native = reader.GetNativeMediaType(first_video_stream(), 0).ok()?;
+        let (width, height) = split_u64(native.GetUINT64(&MF_MT_FRAME_SIZE).ok()?);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let frame_rate = native
+            .GetUINT64(&MF_MT_FRAME_RATE)
+            .ok()
+            .map(split_u64)
+            .and_then(|(num, den)| frame_rate_from_ratio(num, den));
+        let duration_secs = reader
+            .GetPresentationAttribute(MF_SOURCE_READER_MEDIASOURCE.0 as u32, &MF_PD_DURATION)
+            .ok()
+            .and_then(|value| u64::try_from(&value).ok())
+            .map(|hundred_ns| hundred_ns as f64 / HUNDRED_NS_PER_SEC)
+            .filter(|secs| *secs > 0.0);
+        Some(MfVideoInfo {
+            width,
+            height,
+            frame_rate,
+            duration_secs,
+        })
+    }
+}
+
+/// Copy an RGB32 sample into an opaque BGRA frame of `width`x`height`.
+fn sample_to_frame(
+    sample: &IMFSample,
+    width: u32,
+    height: u32,
+    stride: i32,
+    delay_ms: u32,
+) -> Option<ImageFrame> {
+    let row_bytes = width as usize * 4;
+    let stride_abs = (stride.unsigned_abs() as usize).max(row_bytes);
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+
+    unsafe {
+        let buffer: IMFMediaBuffer = sample.ConvertToContiguousBuffer().ok()?;
+        let mut data = std::ptr::null_mut();
+        let mut length = 0u32;
+        buffer.Lock(&mut data, None, Some(&mut length)).ok()?;
+        if data.is_null() || (length as usize) < stride_abs * (height as usize - 1) + row_bytes {
+            let _ = buffer.Unlock();
+            return None;
+        }
+
+        let source = std::slice::from_raw_parts(data, length as usize);
+        for y in 0..height as usize {
+            // Negative stride means a bottom-up surface.
+            let row = if stride < 0 {
+                height as usize - 1 - y
+            } else {
+                y
+            };
+            let start = row * stride_abs;
+            pixels.extend_from_slice(&source[start..start + row_bytes]);
+        }
+        let _ = buffer.Unlock();
+    }
+
+    // RGB32 leaves the fourth byte undefined.
+    for px in pixels.chunks_exact_mut(4) {
+        px[3] = 255;
+    }
+
+    Some(ImageFrame {
+        pixels,
+        width,
+        height,
+        delay_ms,
+    })
+}
+
+fn seek(reader: &IMFSourceReader, position_secs: f64) -> bool {
+    let position = (position_secs.max(0.0) * HUNDRED_NS_PER_SEC) as i64;
+    unsafe {
+        reader
+            .SetCurrentPosition(&GUID::zeroed(), &PROPVARIANT::from(position))
+            .is_ok()
+    }
+}
+
+/// Row pitch of the RGB32 output; negative for bottom-up surfaces.
+fn current_stride(reader: &IMFSourceReader, width: u32) -> i32 {
+    unsafe {
+        reader
+            .GetCurrentMediaType(first_video_stream())
+            .and_then(|media_type| media_type.GetUINT32(&MF_MT_DEFAULT_STRIDE))
+            .map(|stride| stride as i32)
+            .unwrap_or((width * 4) as i32)
+    }
+}
+
+fn decode_loop(
+    reader: &IMFSourceReader,
+    queue: &FrameQueue,
+    width: u32,
+    height: u32,
+    delay_ms: u32,
+    looping: bool,
+    cancel: &AtomicBool,
+) {
+    let stride = current_stride(reader, width);
+
+    let mut empty_reads = 0u32;
+    while !cancel.load(Ordering::Acquire) {
+        let buffered = queue.lock().map(|q| q.len()).unwrap_or(0);
+        if buffered >= MF_STREAM_BUFFER_FRAMES {
+            std::thread::sleep(Duration::from_millis(4));
+            continue;
+        }
+
+        let mut flags = 0u32;
+        let mut sample: Option<IMFSample> = None;
+        let read = unsafe {
+            reader.ReadSample(
+                first_video_stream(),
+                0,
+                None,
+                Some(&mut flags),
+                None,
+                Some(&mut sample),
+            )
+        };
+        if read.is_err() {
+            break;
+        }
+
+        if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+            if !looping || !seek(reader, 0.0) {
+                break;
+            }
+            continue;
+        }
+
+        let Some(sample) = sample else {
+            // Stream ticks and gaps come back without a sample.
+            empty_reads += 1;
+            if empty_reads > 1000 {
+                break;
+            }
+            continue;
+        };
+        empty_reads = 0;
+
+        if let Some(frame) = sample_to_frame(&sample, width, height, stride, delay_ms) {
+            if let Ok(mut q) = queue.lock() {
+                q.push_back(Arc::new(frame));
+            }
+        }
+    }
+}
+
+/// Decode `path` on a background thread into `width`x`height` BGRA frames,
+/// starting `start_secs` into the file.
+///
+/// Like the ffmpeg stream, playback restarts at the end when `looping` is set
+/// and stops when `cancel` is set. Returns
+/// `None` when Media Foundation cannot open or scale the file, so the caller
+/// can fall back to ffmpeg.
+pub fn spawn_frame_stream(
+    path: &PathBuf,
+    width: u32,
+    height: u32,
+    frame_delay_ms: u32,
+    start_secs: f64,
+    looping: bool,
+    cancel: Arc<AtomicBool>,
+) -> Option<FrameQueue> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let queue: FrameQueue = Arc::new(Mutex::new(VecDeque::new()));
+    let queue_clone = Arc::clone(&queue);
+    let path = path.clone();
+    let (opened_tx, opened_rx) = std::sync::mpsc::channel();
+
+    std::thread::spawn(move || {
+        if let Some(runtime) = MfRuntime::start() {
+            let reader = open_reader(&path, Some((width, height)));
+            let _ = opened_tx.send(reader.is_some());
+            if let Some(reader) = reader {
+                if start_secs > 0.0 {
+                    seek(&reader, start_secs);
+                }
+                decode_loop(
+                    &reader,
+                    &queue_clone,
+                    width,
+                    height,
+                    frame_delay_ms,
+                    looping,
+                    &cancel,
+                );
+            }
+            drop(runtime);
+        } else {
+            let _ = opened_tx.send(false);
+        }
+    });
+
+    match opened_rx.recv() {
+        Ok(true) => Some(queue),
+        _ => None,
+    }
+}
+
+/// Decode single `width`x`height` frames at each of `times_secs`. Each seek
+/// lands on the nearest preceding keyframe, which is fine for thumbnails.
+/// Frames after `cancel` is set are skipped.
+pub fn extract_frames(
+    path: &PathBuf,
+    times_secs: &[f64],
+    (width, height): (u32, u32),
+    cancel: &AtomicBool,
+) -> Vec<Option<ImageFrame>> {
+    let Some(_runtime) = MfRuntime::start() else {
+        return times_secs.iter().map(|_| None).collect();
+    };
+    let Some(reader) = open_reader(path, Some((width, height))) else {
+        return times_secs.iter().map(|_| None).collect();
+    };
+    let stride = current_stride(&reader, width);
+
+    times_secs
+        .iter()
+        .map(|&at| {
+            if cancel.load(Ordering::Acquire) || !seek(&reader, at) {
+                return None;
+            }
+            read_next_sample(&reader)
+                .and_then(|sample| sample_to_frame(&sample, width, height, stride, 0))
+        })
+        .collect()
+}
+
+/// Next decoded sample, skipping gaps. `None` at end of stream or on error.
+fn read_next_sample(reader: &IMFSourceReader) -> Option<IMFSample> {
+    for _ in 0..1000 {
+        let mut flags = 0u32;
+        let mut sample: Option<IMFSample> = None;
+        unsafe {
+            reader
+                .ReadSample(
+                    first_video_stream(),
+                    0,
+                    None,
+                    Some(&mut flags),
+                    None,
+                    Some(&mut sample),
+                )
+                .ok()?;
+        }
+        if flags & MF_SOURCE_READERF_ENDOFSTREAM.0 as u32 != 0 {
+            return None;
+        }
+        if sample.is_some() {
+            return sample;
+        }
+    }
+    None
+}
+
+pub fn can_decode(path: &PathBuf) -> bool {
+    crate::providers::has_extension(path, MF_VIDEO_EXTENSIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packed_attributes_split_into_high_and_low_words() {
+        assert_eq!(split_u64((1920u64 << 32) | 1080), (1920, 1080));
+    }
+
+    #[test]
+    fn frame_rate_ratio_rejects_zero_terms() {
+        let ntsc = frame_rate_from_ratio(30000, 1001).expect("valid ratio");
+        assert!((ntsc - 29.97).abs() < 0.01);
+        assert_eq!(frame_rate_from_ratio(0, 1), None);
+        assert_eq!(frame_rate_from_ratio(25, 0), None);
+    }
+}
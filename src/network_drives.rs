@@ -0,0 +1,158 @@
+//! Files on network shares and mapped network drives, which can take seconds
+//! to answer when the share is slow or the drive is disconnected.
+//!
+//! A UNC path (`\\server\share\...`) or a drive letter Windows reports as
+//! remote counts as a network drive. With `preview_network_drives` off, such
+//! files are never previewed. Otherwise the hover waits `network_hover_delay_ms`
+//! longer for them, and before the hook touches a file its share is asked for
+//! the file's details on a thread of its own. A share that has not answered within
+//! `network_timeout_ms` is skipped for a minute instead of stalling the hook,
+//! and one that answered is not asked again for a few seconds.
+
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use windows::core::PCWSTR;
+use windows::Win32::Storage::FileSystem::GetDriveTypeW;
+
+/// `GetDriveTypeW` for a network drive.
+const DRIVE_REMOTE: u32 = 4;
+/// How long a share that did not answer in time is skipped.
+const UNREACHABLE_RETRY: Duration = Duration::from_secs(60);
+/// How long a share that answered is taken to still be there.
+const REACHABLE_RECHECK: Duration = Duration::from_secs(10);
+
+/// Whether each share root answered when last asked, and when that was.
+static ROOTS: Lazy<Mutex<HashMap<String, (bool, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether `path` is on a network share or a mapped network drive.
+pub fn is_remote(path: &Path) -> bool {
+    let Some(root) = volume_root(&path.to_string_lossy()) else {
+        return false;
+    };
+    if root.starts_with("\\\\") {
+        return true;
+    }
+    let wide: Vec<u16> = root.encode_utf16().chain(Some(0)).collect();
+    unsafe { GetDriveTypeW(PCWSTR(wide.as_ptr())) == DRIVE_REMOTE }
+}
+
+/// How much longer than `hover_delay_ms` a hover over `path` waits.
+pub fn extra_hover_delay(path: &Path) -> Duration {
+    let delay = CONFIG.lock().map(|c| c.network_hover_delay_ms).unwrap_or(0);
+    if delay == 0 || !is_remote(path) {
+        return Duration::ZERO;
+    }
+    Duration::from_millis(delay)
+}
+
+/// Whether `path` may be previewed: it is local, or network drives are
+/// previewed and its share answers in time.
+pub fn is_previewable(path: &Path) -> bool {
+    if !is_remote(path) {
+        return true;
+    }
+    let (enabled, timeout_ms) = CONFIG
+        .lock()
+        .map(|c| (c.preview_network_drives, c.network_timeout_ms))
+        .unwrap_or((true, 0));
+    share_answers(path, enabled, timeout_ms, |path| {
+        std::fs::metadata(path).is_ok()
+    })
+}
+
+/// Whether the share of the remote `path` may be used, asking it with `probe`
+/// only when network drives are previewed and it has not answered recently.
+fn share_answers(path: &Path, enabled: bool, timeout_ms: u64, probe: fn(&Path) -> bool) -> bool {
+    if !enabled {
+        return false;
+    }
+    if timeout_ms == 0 {
+        return true;
+    }
+    let Some(root) = volume_root(&path.to_string_lossy()) else {
+        return true;
+    };
+    let known = ROOTS
+        .lock()
+        .ok()
+        .and_then(|roots| roots.get(&root).copied());
+    match known {
+        Some((false, checked)) if checked.elapsed() < UNREACHABLE_RETRY => return false,
+        Some((true, checked)) if checked.elapsed() < REACHABLE_RECHECK => return true,
+        _ => {}
+    }
+
+    // The share is asked on a thread of its own, which is left to finish in
+    // its own time when the share does not answer.
+    let (tx, rx) = mpsc::channel();
+    let probed = path.to_path_buf();
+    std::thread::spawn(move || {
+        let _ = tx.send(probe(&probed));
+    });
+    let reachable = rx.recv_timeout(Duration::from_millis(timeout_ms)).is_ok();
+    if let Ok(mut roots) = ROOTS.lock() {
+        roots.insert(root, (reachable, Instant::now()));
+    }
+    reachable
+}
+
+/// The root of the volume `path` is on, with a trailing backslash: `C:\` or
+/// `\\server\share\`, lowercased so roots compare whatever the case.
+fn volume_root(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+    let path = if let Some(unc) = path.strip_prefix("\\\\?\\UNC\\") {
+        format!("\\\\{unc}")
+    } else {
+        path.strip_prefix("\\\\?\\").unwrap_or(&path).to_string()
+    };
+    if let Some(rest) = path.strip_prefix("\\\\") {
+        let mut parts = rest.split('\\').filter(|part| !part.is_empty());
+        let server = parts.next()?;
+        let share = parts.next()?;
+        return Some(format!("\\\\{server}\\{share}\\").to_lowercase());
+    }
+    let mut chars = path.chars();
+    match (chars.next(), chars.next()) {
+        (Some(letter), Some(':')) if letter.is_ascii_alphabetic() => {
+            Some(format!("{}:\\", letter.to_ascii_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn roots_are_drive_letters_or_server_shares() {
+        let root = |path: &str| volume_root(path).unwrap_or_default();
+        assert_eq!(root("C:\\Photos\\a.jpg"), "c:\\");
+        assert_eq!(root("z:/a.png"), "z:\\");
+        assert_eq!(root("\\\\NAS\\Photos\\2024\\a.jpg"), "\\\\nas\\photos\\");
+        assert_eq!(root("\\\\?\\UNC\\nas\\photos\\a.jpg"), "\\\\nas\\photos\\");
+        assert_eq!(root("\\\\?\\D:\\a.mp4"), "d:\\");
+        assert_eq!(root("\\\\nas"), "");
+        assert_eq!(root("a.jpg"), "");
+    }
+
+    #[test]
+    fn shares_are_not_asked_when_network_drives_are_off() {
+        static ASKED: AtomicBool = AtomicBool::new(false);
+        let probe = |_: &Path| {
+            ASKED.store(true, Ordering::SeqCst);
+            true
+        };
+        let path = Path::new("\\\\offline\\photos\\a.jpg");
+        assert!(!share_answers(path, false, 1000, probe));
+        assert!(share_answers(path, true, 0, probe));
+        assert!(!ASKED.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,264 @@
+//! Optional blurring of explicit still previews, scored on-device by an ONNX
+//! image classifier through Windows ML.
+//!
+//! No model ships with the app: `nsfw_model` names an ONNX classifier that
+//! takes RGB scaled to 0..1, laid out NCHW or NHWC. The outputs listed in
+//! `nsfw_classes` are summed, and a still scoring at least `nsfw_threshold`
+//! is shown blurred until `reveal_key` is held. With `blur_folders` set, only
+//! files under those folders are scored. A model that fails to load blurs
+//! nothing.
+
+use crate::ocr;
+use crate::CONFIG;
+use image::RgbaImage;
+use std::cell::RefCell;
+use std::path::Path;
+use windows::core::{Interface, HSTRING};
+use windows::AI::MachineLearning::{
+    LearningModel, LearningModelBinding, LearningModelSession, TensorFeatureDescriptor, TensorFloat,
+};
+
+/// Side the preview is shrunk to before being stretched back, which leaves
+/// only blurred shapes and colors.
+const BLUR_SIDE: u32 = 16;
+/// Input side assumed for models that leave their spatial dimensions free.
+const DEFAULT_INPUT_SIDE: u32 = 224;
+
+thread_local! {
+    /// Each loader thread keeps its own session for the configured model.
+    static CLASSIFIER: RefCell<Option<Classifier>> = RefCell::new(None);
+}
+
+/// Whether the BGRA still decoded for `path` should be shown blurred.
+pub fn should_blur(path: &Path, pixels: &[u8], width: u32, height: u32) -> bool {
+    let settings = CONFIG.lock().ok().and_then(|config| {
+        let model = config.nsfw_model.trim();
+        (config.blur_explicit && !model.is_empty()).then(|| {
+            (
+                model.to_string(),
+                parse_classes(&config.nsfw_classes),
+                config.nsfw_threshold,
+                config.blur_folders.clone(),
+            )
+        })
+    });
+    let Some((model, classes, threshold, folders)) = settings else {
+        return false;
+    };
+    if !in_folders(path, &folders) {
+        return false;
+    }
+    let outputs = ocr::with_com(|| {
+        CLASSIFIER.with(|cell| {
+            let mut classifier = cell.borrow_mut();
+            if classifier
+                .as_ref()
+                .map(|c| c.model_path != model)
+                .unwrap_or(true)
+            {
+                *classifier = Classifier::load(&model);
+            }
+            classifier.as_ref()?.run(pixels, width, height)
+        })
+    });
+    outputs
+        .map(|outputs| explicit_score(&outputs, &classes) >= threshold)
+        .unwrap_or(false)
+}
+
+/// A heavily blurred copy of a BGRA frame.
+pub fn blur_bgra(pixels: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+    let image = RgbaImage::from_raw(width, height, pixels.to_vec())?;
+    let scale = (BLUR_SIDE as f32 / width.max(height) as f32).min(1.0);
+    let small = image::imageops::resize(
+        &image,
+        ((width as f32 * scale) as u32).max(1),
+        ((height as f32 * scale) as u32).max(1),
+        image::imageops::FilterType::Triangle,
+    );
+    let blurred =
+        image::imageops::resize(&small, width, height, image::imageops::FilterType::Triangle);
+    Some(blurred.into_raw())
+}
+
+struct Classifier {
+    model_path: String,
+    session: LearningModelSession,
+    input: HSTRING,
+    output: HSTRING,
+    layout: InputLayout,
+}
+
+impl Classifier {
+    fn load(model_path: &str) -> Option<Self> {
+        let model = LearningModel::LoadFromFilePath(&HSTRING::from(model_path)).ok()?;
+        let input = model.InputFeatures().ok()?.GetAt(0).ok()?;
+        let output = model.OutputFeatures().ok()?.GetAt(0).ok()?;
+        let shape: Vec<i64> = input
+            .cast::<TensorFeatureDescriptor>()
+            .ok()?
+            .Shape()
+            .ok()?
+            .into_iter()
+            .collect();
+        Some(Classifier {
+            model_path: model_path.to_string(),
+            session: LearningModelSession::CreateFromModel(&model).ok()?,
+            input: input.Name().ok()?,
+            output: output.Name().ok()?,
+            layout: InputLayout::from_shape(&shape)?,
+        })
+    }
+
+    fn run(&self, pixels: &[u8], width: u32, height: u32) -> Option<Vec<f32>> {
+        let data = self.layout.tensor_data(pixels, width, height)?;
+        let tensor =
+            TensorFloat::CreateFromShapeArrayAndDataArray(&self.layout.shape(), &data).ok()?;
+        let binding = LearningModelBinding::CreateFromSession(&self.session).ok()?;
+        binding.Bind(&self.input, &tensor).ok()?;
+        let result = self.session.Evaluate(&binding, &HSTRING::new()).ok()?;
+        let scores = result
+            .Outputs()
+            .ok()?
+            .Lookup(&self.output)
+            .ok()?
+            .cast::<TensorFloat>()
+            .ok()?
+            .GetAsVectorView()
+            .ok()?;
+        Some(scores.into_iter().collect())
+    }
+}
+
+/// How a model wants its one image: channels first or last, and its size.
+#[derive(Debug, PartialEq)]
+struct InputLayout {
+    channels_first: bool,
+    width: u32,
+    height: u32,
+}
+
+impl InputLayout {
+    /// Free dimensions (`-1`) take `DEFAULT_INPUT_SIDE`; `None` unless the
+    /// shape has four dimensions with three color channels.
+    fn from_shape(shape: &[i64]) -> Option<Self> {
+        let side = |dim: i64| {
+            if dim > 0 {
+                dim as u32
+            } else {
+                DEFAULT_INPUT_SIDE
+            }
+        };
+        match shape {
+            [_, 3, height, width] => Some(InputLayout {
+                channels_first: true,
+                width: side(*width),
+                height: side(*height),
+            }),
+            [_, height, width, 3] => Some(InputLayout {
+                channels_first: false,
+                width: side(*width),
+                height: side(*height),
+            }),
+            _ => None,
+        }
+    }
+
+    fn shape(&self) -> [i64; 4] {
+        let (width, height) = (self.width as i64, self.height as i64);
+        if self.channels_first {
+            [1, 3, height, width]
+        } else {
+            [1, height, width, 3]
+        }
+    }
+
+    /// RGB values in 0..1 of the BGRA frame stretched to the input size.
+    fn tensor_data(&self, pixels: &[u8], width: u32, height: u32) -> Option<Vec<f32>> {
+        let image = RgbaImage::from_raw(width, height, pixels.to_vec())?;
+        let resized = image::imageops::resize(
+            &image,
+            self.width,
+            self.height,
+            image::imageops::FilterType::Triangle,
+        );
+        let plane = (self.width * self.height) as usize;
+        let mut data = vec![0.0f32; plane * 3];
+        for (index, px) in resized.pixels().enumerate() {
+            // BGRA in, RGB out.
+            for (channel, &value) in [px.0[2], px.0[1], px.0[0]].iter().enumerate() {
+                let slot = if self.channels_first {
+                    channel * plane + index
+                } else {
+                    index * 3 + channel
+                };
+                data[slot] = value as f32 / 255.0;
+            }
+        }
+        Some(data)
+    }
+}
+
+/// Output indices from a comma-separated list, e.g. `1,3`.
+fn parse_classes(value: &str) -> Vec<usize> {
+    value
+        .split(',')
+        .filter_map(|part| part.trim().parse().ok())
+        .collect()
+}
+
+fn explicit_score(outputs: &[f32], classes: &[usize]) -> f32 {
+    classes.iter().filter_map(|&index| outputs.get(index)).sum()
+}
+
+/// Whether `path` is under one of the semicolon-separated `folders`; an empty
+/// list covers every folder.
+fn in_folders(path: &Path, folders: &str) -> bool {
+    let path = path.to_string_lossy().replace('/', "\\").to_lowercase();
+    let mut folders = folders
+        .split(';')
+        .map(|folder| folder.trim().replace('/', "\\").to_lowercase())
+        .map(|folder| folder.trim_end_matches('\\').to_string())
+        .filter(|folder| !folder.is_empty())
+        .peekable();
+    if folders.peek().is_none() {
+        return true;
+    }
+    folders.any(|folder| {
+        path.strip_prefix(&folder)
+            .map(|rest| rest.starts_with('\\'))
+            .unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folders_match_whole_path_components() {
+        let path = Path::new("D:\\Shared\\Photos\\beach.jpg");
+        assert!(in_folders(path, ""));
+        assert!(in_folders(path, " ; "));
+        assert!(in_folders(path, "C:\\Work; d:/shared/"));
+        assert!(!in_folders(path, "D:\\Shared\\Pho"));
+        assert!(!in_folders(path, "C:\\Work"));
+    }
+
+    #[test]
+    fn model_outputs_and_layouts_are_read() {
+        assert_eq!(parse_classes("1, 3,x"), vec![1, 3]);
+        assert_eq!(explicit_score(&[0.0, 0.25, 0.0, 0.5], &[1, 3, 9]), 0.75);
+
+        let nchw = InputLayout::from_shape(&[-1, 3, 299, 299]).unwrap();
+        assert!(nchw.channels_first);
+        assert_eq!(nchw.shape(), [1, 3, 299, 299]);
+        let nhwc = InputLayout::from_shape(&[1, -1, -1, 3]).unwrap();
+        assert_eq!(nhwc.shape(), [1, 224, 224, 3]);
+        assert_eq!(InputLayout::from_shape(&[1, 1000]), None);
+
+        let data = nchw.tensor_data(&[255, 0, 0, 255], 1, 1).unwrap();
+        let plane = 299 * 299;
+        assert_eq!((data[0], data[2 * plane]), (0.0, 1.0));
+    }
+}
@@ -0,0 +1,162 @@
+//! Text recognition for pinned previews through `Windows.Media.Ocr`.
+//!
+//! The engine only reads languages whose OCR pack is installed (Settings >
+//! Time & language > Language). `ocr_language` picks one by BCP-47 tag; empty
+//! uses the first installed language from the user's profile.
+
+use image::RgbaImage;
+use windows::core::HSTRING;
+use windows::Globalization::Language;
+use windows::Graphics::Imaging::{BitmapPixelFormat, SoftwareBitmap};
+use windows::Media::Ocr::OcrEngine;
+use windows::Storage::Streams::DataWriter;
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+
+/// Text found in an image and the language it was read as.
+pub struct Recognized {
+    pub text: String,
+    pub language: String,
+}
+
+/// Tags of the installed OCR languages, e.g. `en-US`.
+pub fn available_languages() -> Vec<String> {
+    with_com(|| {
+        let languages = OcrEngine::AvailableRecognizerLanguages().ok()?;
+        Some(
+            languages
+                .into_iter()
+                .filter_map(|language| language.LanguageTag().ok())
+                .map(|tag| tag.to_string_lossy())
+                .collect(),
+        )
+    })
+    .unwrap_or_default()
+}
+
+/// The language after `current` in `available`, wrapping around; the first
+/// one when `current` is empty or not installed.
+pub fn next_language(current: &str, available: &[String]) -> Option<String> {
+    let index = available
+        .iter()
+        .position(|tag| tag.eq_ignore_ascii_case(current))
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    available.get(index % available.len().max(1)).cloned()
+}
+
+/// Recognize the text in `image`, one line of output per recognized line.
+/// `None` when no engine is available for `language`.
+pub fn recognize(image: &RgbaImage, language: &str) -> Option<Recognized> {
+    with_com(|| recognize_with_engine(image, language))
+}
+
+fn recognize_with_engine(image: &RgbaImage, language: &str) -> Option<Recognized> {
+    let engine = if language.is_empty() {
+        OcrEngine::TryCreateFromUserProfileLanguages().ok()?
+    } else {
+        let language = Language::CreateLanguage(&HSTRING::from(language)).ok()?;
+        OcrEngine::TryCreateFromLanguage(&language).ok()?
+    };
+    let language = engine
+        .RecognizerLanguage()
+        .and_then(|language| language.LanguageTag())
+        .map(|tag| tag.to_string_lossy())
+        .unwrap_or_default();
+
+    // The engine rejects images larger than its limit on either side.
+    let max_side = OcrEngine::MaxImageDimension().unwrap_or(2600).max(1);
+    let (width, height) = image.dimensions();
+    let scaled;
+    let image = if width > max_side || height > max_side {
+        let scale = max_side as f32 / width.max(height) as f32;
+        scaled = image::imageops::resize(
+            image,
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+            image::imageops::FilterType::Triangle,
+        );
+        &scaled
+    } else {
+        image
+    };
+
+    let writer = DataWriter::new().ok()?;
+    writer.WriteBytes(&bgra_on_white(image)).ok()?;
+    let buffer = writer.DetachBuffer().ok()?;
+    let bitmap = SoftwareBitmap::CreateCopyFromBuffer(
+        &buffer,
+        BitmapPixelFormat::Bgra8,
+        image.width() as i32,
+        image.height() as i32,
+    )
+    .ok()?;
+    let result = engine.RecognizeAsync(&bitmap).ok()?.get().ok()?;
+    let lines: Vec<String> = result
+        .Lines()
+        .ok()?
+        .into_iter()
+        .filter_map(|line| line.Text().ok())
+        .map(|text| text.to_string_lossy())
+        .collect();
+    Some(Recognized {
+        text: lines.join("\r\n"),
+        language,
+    })
+}
+
+/// BGRA pixels composited over white, so transparent areas behind dark text
+/// do not read as black.
+fn bgra_on_white(image: &RgbaImage) -> Vec<u8> {
+    let mut out = Vec::with_capacity(image.as_raw().len());
+    for px in image.pixels() {
+        let [r, g, b, a] = px.0;
+        let over_white = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        out.extend_from_slice(&[over_white(b), over_white(g), over_white(r), 255]);
+    }
+    out
+}
+
+/// Run `f` on a thread that has joined the multithreaded apartment.
+pub fn with_com<T>(f: impl FnOnce() -> Option<T>) -> Option<T> {
+    unsafe {
+        let com_initialized = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        let result = f();
+        if com_initialized {
+            CoUninitialize();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn languages_cycle_and_wrap() {
+        let available = vec!["en-US".to_string(), "de-DE".to_string()];
+        assert_eq!(next_language("", &available), Some("en-US".to_string()));
+        assert_eq!(
+            next_language("en-us", &available),
+            Some("de-DE".to_string())
+        );
+        assert_eq!(
+            next_language("de-DE", &available),
+            Some("en-US".to_string())
+        );
+        assert_eq!(
+            next_language("fr-FR", &available),
+            Some("en-US".to_string())
+        );
+        assert_eq!(next_language("en-US", &[]), None);
+    }
+
+    #[test]
+    fn transparency_is_flattened_onto_white() {
+        let image = RgbaImage::from_raw(2, 1, vec![0, 0, 0, 0, 10, 20, 30, 255]).unwrap();
+        assert_eq!(
+            bgra_on_white(&image),
+            vec![255, 255, 255, 255, 30, 20, 10, 255]
+        );
+    }
+}
@@ -0,0 +1,561 @@
+//! Text drawn on top of decoded preview frames.
+//!
+//! Text is rasterized with GDI into an offscreen DIB (white on black, grayscale
+//! antialiasing) and the result is used as a coverage mask, so captions blend
+//! into the straight-alpha BGRA frames the preview window already renders.
+//! The same mask drives the text panels used by text-only previews.
+//! Histograms are drawn the same way, from coverage built per channel, and
+//! so is the progress bar under animations.
+
+use crate::preview_window::ImageFrame;
+use std::ptr;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{COLORREF, SIZE};
+use windows::Win32::Graphics::Gdi::{
+    CreateCompatibleDC, CreateDIBSection, CreateFontW, DeleteDC, DeleteObject,
+    GetTextExtentPoint32W, SelectObject, SetBkMode, SetTextColor, TextOutW, ANTIALIASED_QUALITY,
+    BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET, DIB_RGB_COLORS,
+    OUT_DEFAULT_PRECIS, TRANSPARENT,
+};
+
+const CAPTION_FONT_FACE: &str = "Segoe UI";
+const CAPTION_BACKGROUND_ALPHA: u32 = 170;
+const MIN_CAPTION_FONT_PX: u32 = 11;
+const MAX_CAPTION_FONT_PX: u32 = 18;
+
+/// Space between a corner badge and the frame edges.
+const BADGE_MARGIN_PX: u32 = 6;
+
+/// Levels per channel in a histogram, each a column of the panel or more.
+const HISTOGRAM_BINS: usize = 64;
+const MIN_HISTOGRAM_WIDTH: u32 = 96;
+const MAX_HISTOGRAM_WIDTH: u32 = 256;
+/// How much a channel's bars brighten the darkened panel.
+const HISTOGRAM_FILL: u8 = 150;
+
+/// Thickness of the progress bar along the bottom of animations.
+const PROGRESS_BAR_PX: u32 = 3;
+/// How much the decoded part of the progress bar's track brightens it.
+const PROGRESS_DECODED_FILL: u8 = 90;
+
+const PANEL_FONT_FACE: &str = "Consolas";
+const PANEL_FONT_PX: u32 = 14;
+const PANEL_BACKGROUND_RGB: [u8; 3] = [0x1e, 0x1e, 0x1e];
+const PANEL_TEXT_RGB: [u8; 3] = [0xe6, 0xe6, 0xe6];
+
+/// Font size for captions on a frame of the given width.
+pub fn caption_font_px(frame_width: u32) -> u32 {
+    (frame_width / 40).clamp(MIN_CAPTION_FONT_PX, MAX_CAPTION_FONT_PX)
+}
+
+/// Rasterize `lines` into a `width`x`height` coverage mask (0 = background,
+/// 255 = text), one byte per pixel.
+pub fn render_text_mask(
+    width: u32,
+    height: u32,
+    lines: &[String],
+    face: &str,
+    font_px: u32,
+    line_height: u32,
+    padding: u32,
+) -> Option<Vec<u8>> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            biSizeImage: 0,
+            biXPelsPerMeter: 0,
+            biYPelsPerMeter: 0,
+            biClrUsed: 0,
+            biClrImportant: 0,
+        },
+        bmiColors: [Default::default()],
+    };
+
+    unsafe {
+        let mem_dc = CreateCompatibleDC(None);
+        if mem_dc.0.is_null() {
+            return None;
+        }
+
+        let mut bits: *mut core::ffi::c_void = ptr::null_mut();
+        let Ok(bitmap) = CreateDIBSection(mem_dc, &bmi, DIB_RGB_COLORS, &mut bits, None, 0) else {
+            let _ = DeleteDC(mem_dc);
+            return None;
+        };
+        if bits.is_null() {
+            let _ = DeleteObject(bitmap);
+            let _ = DeleteDC(mem_dc);
+            return None;
+        }
+
+        // DIB sections start zeroed, which is the black background we want.
+        let face: Vec<u16> = face.encode_utf16().chain(Some(0)).collect();
+        let old_bitmap = SelectObject(mem_dc, bitmap);
+        let font = CreateFontW(
+            -(font_px as i32),
+            0,
+            0,
+            0,
+            400,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            ANTIALIASED_QUALITY,
+            0,
+            PCWSTR(face.as_ptr()),
+        );
+        let old_font = SelectObject(mem_dc, font);
+        SetBkMode(mem_dc, TRANSPARENT);
+        SetTextColor(mem_dc, COLORREF(0x00FF_FFFF));
+
+        for (index, line) in lines.iter().enumerate() {
+            let wide: Vec<u16> = line.encode_utf16().collect();
+            let y = (padding + index as u32 * line_height) as i32;
+            let _ = TextOutW(mem_dc, padding as i32, y, &wide);
+        }
+
+        let pixel_count = width as usize * height as usize;
+        let bgra = std::slice::from_raw_parts(bits as *const u8, pixel_count * 4);
+        let mask = bgra.chunks_exact(4).map(|px| px[1]).collect();
+
+        SelectObject(mem_dc, old_font);
+        SelectObject(mem_dc, old_bitmap);
+        let _ = DeleteObject(font);
+        let _ = DeleteObject(bitmap);
+        let _ = DeleteDC(mem_dc);
+
+        Some(mask)
+    }
+}
+
+/// Blend a translucent band with white text along the bottom of `frame`.
+pub fn draw_caption(frame: &mut ImageFrame, lines: &[String]) {
+    if let Some(band) = CaptionBand::new(frame.width, frame.height, lines) {
+        band.draw(&mut frame.pixels, frame.width, frame.height);
+    }
+}
+
+/// A caption rasterized once for a frame width, for captions that stay on
+/// screen while the frames under them change (such as the video info bar).
+pub struct CaptionBand {
+    width: u32,
+    height: u32,
+    mask: Vec<u8>,
+}
+
+impl CaptionBand {
+    pub fn new(frame_width: u32, frame_height: u32, lines: &[String]) -> Option<Self> {
+        if lines.is_empty() || frame_width < 48 || frame_height < 32 {
+            return None;
+        }
+
+        let font_px = caption_font_px(frame_width);
+        let line_height = font_px + font_px / 4;
+        let padding = font_px / 2;
+        let height = (line_height * lines.len() as u32 + padding * 2).min(frame_height);
+        let mask = render_text_mask(
+            frame_width,
+            height,
+            lines,
+            CAPTION_FONT_FACE,
+            font_px,
+            line_height,
+            padding,
+        )?;
+        Some(CaptionBand {
+            width: frame_width,
+            height,
+            mask,
+        })
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Blend the band along the bottom of a BGRA frame of the width it was
+    /// rendered for; frames of another size are left alone.
+    pub fn draw(&self, pixels: &mut [u8], width: u32, height: u32) {
+        self.draw_raised(pixels, width, height, 0);
+    }
+
+    /// Blend the band `raise` pixels above the bottom of the frame, such as
+    /// above another band.
+    pub fn draw_raised(&self, pixels: &mut [u8], width: u32, height: u32, raise: u32) {
+        if width != self.width || height < self.height + raise {
+            return;
+        }
+
+        let top = (height - self.height - raise) as usize;
+        let width = width as usize;
+        for (index, coverage) in self.mask.iter().enumerate() {
+            let offset = ((top + index / width) * width + index % width) * 4;
+            let Some(px) = pixels.get_mut(offset..offset + 4) else {
+                break;
+            };
+            blend_caption_pixel(px, *coverage);
+        }
+    }
+}
+
+/// A single line of text in a small translucent box, rasterized once and drawn
+/// in the top-right corner of each frame (such as the resolution badge).
+pub struct Badge {
+    frame_width: u32,
+    width: u32,
+    height: u32,
+    mask: Vec<u8>,
+}
+
+impl Badge {
+    /// A badge with text a notch smaller than captions on the same frame.
+    pub fn new(frame_width: u32, frame_height: u32, text: &str) -> Option<Self> {
+        let font_px = caption_font_px(frame_width)
+            .saturating_sub(2)
+            .max(MIN_CAPTION_FONT_PX);
+        let padding = font_px / 3;
+        let text_width = text_width(text, CAPTION_FONT_FACE, font_px)?;
+        let width = text_width + padding * 2;
+        let height = font_px + font_px / 4 + padding * 2;
+        if width + BADGE_MARGIN_PX * 2 > frame_width || height + BADGE_MARGIN_PX * 2 > frame_height
+        {
+            return None;
+        }
+        let lines = [text.to_string()];
+        let mask = render_text_mask(
+            width,
+            height,
+            &lines,
+            CAPTION_FONT_FACE,
+            font_px,
+            font_px,
+            padding,
+        )?;
+        Some(Badge {
+            frame_width,
+            width,
+            height,
+            mask,
+        })
+    }
+
+    /// Blend the badge into the top-right corner of a BGRA frame of the width
+    /// it was made for; frames of another size are left alone.
+    pub fn draw(&self, pixels: &mut [u8], width: u32, height: u32) {
+        if width != self.frame_width || height < self.height + BADGE_MARGIN_PX * 2 {
+            return;
+        }
+
+        let left = (width - self.width - BADGE_MARGIN_PX) as usize;
+        let top = BADGE_MARGIN_PX as usize;
+        let (frame_width, badge_width) = (width as usize, self.width as usize);
+        for (index, coverage) in self.mask.iter().enumerate() {
+            let (x, y) = (left + index % badge_width, top + index / badge_width);
+            let offset = (y * frame_width + x) * 4;
+            let Some(px) = pixels.get_mut(offset..offset + 4) else {
+                break;
+            };
+            blend_caption_pixel(px, *coverage);
+        }
+    }
+}
+
+/// Counts of red, green, blue and luma (Rec. 709) levels in a BGRA frame, in
+/// that order, in `HISTOGRAM_BINS` bins each. Fully transparent pixels are
+/// not counted.
+pub fn histogram(bgra: &[u8]) -> [[u32; HISTOGRAM_BINS]; 4] {
+    let mut counts = [[0u32; HISTOGRAM_BINS]; 4];
+    let bin = |level: u32| level as usize * HISTOGRAM_BINS / 256;
+    for px in bgra.chunks_exact(4).filter(|px| px[3] != 0) {
+        let (b, g, r) = (px[0] as u32, px[1] as u32, px[2] as u32);
+        let luma = (r * 2126 + g * 7152 + b * 722) / 10_000;
+        counts[0][bin(r)] += 1;
+        counts[1][bin(g)] += 1;
+        counts[2][bin(b)] += 1;
+        counts[3][bin(luma)] += 1;
+    }
+    counts
+}
+
+/// A histogram of a frame in a translucent box in its top-left corner: red,
+/// green and blue bars, adding up to grey where they overlap, under a white
+/// luma outline.
+pub struct HistogramPanel {
+    frame_width: u32,
+    width: u32,
+    height: u32,
+    /// Per pixel, which bars cover it: bits 0-2 for blue, green and red in
+    /// frame order, bit 3 for the luma outline.
+    coverage: Vec<u8>,
+}
+
+impl HistogramPanel {
+    /// A panel for a frame of `frame_width`x`frame_height`, a quarter of its
+    /// width across, or `None` when the frame is too small to hold one.
+    pub fn new(bgra: &[u8], frame_width: u32, frame_height: u32) -> Option<Self> {
+        let width = (frame_width / 4).clamp(MIN_HISTOGRAM_WIDTH, MAX_HISTOGRAM_WIDTH);
+        let height = width / 2;
+        if width + BADGE_MARGIN_PX * 2 > frame_width || height + BADGE_MARGIN_PX * 2 > frame_height
+        {
+            return None;
+        }
+        let counts = histogram(bgra);
+        let peak = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+        // Bar heights per column: red, green, blue, luma.
+        let columns: Vec<[u32; 4]> = (0..width as usize)
+            .map(|x| {
+                let bin = x * HISTOGRAM_BINS / width as usize;
+                counts.map(|channel| (channel[bin] as u64 * height as u64 / peak as u64) as u32)
+            })
+            .collect();
+        let mut coverage = vec![0u8; width as usize * height as usize];
+        for (index, cell) in coverage.iter_mut().enumerate() {
+            let [red, green, blue, luma] = columns[index % width as usize];
+            let rise = height - (index / width as usize) as u32;
+            *cell = (blue >= rise) as u8
+                | ((green >= rise) as u8) << 1
+                | ((red >= rise) as u8) << 2
+                | ((luma == rise || (luma > 0 && luma + 1 == rise)) as u8) << 3;
+        }
+        Some(HistogramPanel {
+            frame_width,
+            width,
+            height,
+            coverage,
+        })
+    }
+
+    /// Blend the panel into the top-left corner of a BGRA frame of the width
+    /// it was made for; frames of another size are left alone.
+    pub fn draw(&self, pixels: &mut [u8], width: u32, height: u32) {
+        if width != self.frame_width || height < self.height + BADGE_MARGIN_PX * 2 {
+            return;
+        }
+
+        let margin = BADGE_MARGIN_PX as usize;
+        let (frame_width, panel_width) = (width as usize, self.width as usize);
+        for (index, cell) in self.coverage.iter().enumerate() {
+            let (x, y) = (margin + index % panel_width, margin + index / panel_width);
+            let offset = (y * frame_width + x) * 4;
+            let Some(px) = pixels.get_mut(offset..offset + 4) else {
+                break;
+            };
+            blend_histogram_pixel(px, *cell);
+        }
+    }
+}
+
+/// Darken `px` like a caption band, then brighten the channels whose bars
+/// cover it, or whiten it under the luma outline.
+fn blend_histogram_pixel(px: &mut [u8], coverage: u8) {
+    blend_caption_pixel(px, 0);
+    if coverage & 0b1000 != 0 {
+        px[..3].fill(255);
+        return;
+    }
+    for (channel, value) in px.iter_mut().take(3).enumerate() {
+        if coverage & (1 << channel) != 0 {
+            *value = value.saturating_add(HISTOGRAM_FILL);
+        }
+    }
+}
+
+/// Draw a thin bar along the bottom of a BGRA frame, white as far as
+/// `played` and a darkened track after that. While the animation is still
+/// decoding, the track is lighter as far as `decoded`. Both are fractions of
+/// the whole animation, from 0 to 1.
+pub fn draw_progress_bar(
+    pixels: &mut [u8],
+    width: u32,
+    height: u32,
+    played: f32,
+    decoded: Option<f32>,
+) {
+    if width < 48 || height < 32 {
+        return;
+    }
+    let reach = |fraction: f32| (fraction.clamp(0.0, 1.0) * width as f32).round() as usize;
+    let played = reach(played);
+    let decoded = decoded.map(reach).unwrap_or(0);
+
+    let width = width as usize;
+    let top = (height - PROGRESS_BAR_PX) as usize;
+    for y in top..height as usize {
+        for x in 0..width {
+            let offset = (y * width + x) * 4;
+            let Some(px) = pixels.get_mut(offset..offset + 4) else {
+                return;
+            };
+            if x < played {
+                px.fill(255);
+                continue;
+            }
+            blend_caption_pixel(px, 0);
+            if x < decoded {
+                for channel in px.iter_mut().take(3) {
+                    *channel = channel.saturating_add(PROGRESS_DECODED_FILL);
+                }
+            }
+        }
+    }
+}
+
+/// Width of `text` set in `face` at `font_px`.
+fn text_width(text: &str, face: &str, font_px: u32) -> Option<u32> {
+    let face: Vec<u16> = face.encode_utf16().chain(Some(0)).collect();
+    let wide: Vec<u16> = text.encode_utf16().collect();
+    unsafe {
+        let mem_dc = CreateCompatibleDC(None);
+        if mem_dc.0.is_null() {
+            return None;
+        }
+        let font = CreateFontW(
+            -(font_px as i32),
+            0,
+            0,
+            0,
+            400,
+            0,
+            0,
+            0,
+            DEFAULT_CHARSET,
+            OUT_DEFAULT_PRECIS,
+            CLIP_DEFAULT_PRECIS,
+            ANTIALIASED_QUALITY,
+            0,
+            PCWSTR(face.as_ptr()),
+        );
+        let old_font = SelectObject(mem_dc, font);
+        let mut size = SIZE::default();
+        let measured = GetTextExtentPoint32W(mem_dc, &wide, &mut size).as_bool();
+        SelectObject(mem_dc, old_font);
+        let _ = DeleteObject(font);
+        let _ = DeleteDC(mem_dc);
+        measured.then_some(size.cx.max(0) as u32)
+    }
+}
+
+/// Render `lines` as light monospace text on an opaque dark panel. Lines that
+/// do not fit are clipped.
+pub fn render_text_panel(width: u32, height: u32, lines: &[String]) -> Option<image::RgbaImage> {
+    let font_px = PANEL_FONT_PX;
+    let line_height = font_px + font_px / 4;
+    let padding = font_px;
+    let mask = render_text_mask(
+        width,
+        height,
+        lines,
+        PANEL_FONT_FACE,
+        font_px,
+        line_height,
+        padding,
+    )?;
+
+    let mut rgba = Vec::with_capacity(mask.len() * 4);
+    for coverage in mask {
+        let text = coverage as u32;
+        for (background, foreground) in PANEL_BACKGROUND_RGB.iter().zip(PANEL_TEXT_RGB.iter()) {
+            let mixed = (*background as u32 * (255 - text) + *foreground as u32 * text) / 255;
+            rgba.push(mixed as u8);
+        }
+        rgba.push(255);
+    }
+    image::RgbaImage::from_raw(width, height, rgba)
+}
+
+/// Darken `px` by the caption band and then lay white text over it.
+fn blend_caption_pixel(px: &mut [u8], coverage: u8) {
+    let band = CAPTION_BACKGROUND_ALPHA;
+    let text = coverage as u32;
+    for channel in px.iter_mut().take(3) {
+        let darkened = *channel as u32 * (255 - band) / 255;
+        *channel = ((darkened * (255 - text) + 255 * text) / 255) as u8;
+    }
+    let alpha = px[3] as u32;
+    px[3] = (alpha + band * (255 - alpha) / 255).max(text) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caption_font_scales_with_width_within_bounds() {
+        assert_eq!(caption_font_px(100), MIN_CAPTION_FONT_PX);
+        assert_eq!(caption_font_px(600), 15);
+        assert_eq!(caption_font_px(4000), MAX_CAPTION_FONT_PX);
+    }
+
+    #[test]
+    fn caption_pixel_blend_darkens_background_and_whitens_text() {
+        let mut background = [200u8, 200, 200, 0];
+        blend_caption_pixel(&mut background, 0);
+        assert!(background[0] < 200);
+        assert_eq!(background[3] as u32, CAPTION_BACKGROUND_ALPHA);
+
+        let mut text = [0u8, 0, 0, 255];
+        blend_caption_pixel(&mut text, 255);
+        assert_eq!(text, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn histogram_counts_levels_of_visible_pixels() {
+        // Pure red, mid grey, and a transparent pixel that is not counted.
+        let pixels = [0u8, 0, 255, 255, 128, 128, 128, 255, 9, 9, 9, 0];
+        let counts = histogram(&pixels);
+        assert_eq!(counts[0][HISTOGRAM_BINS - 1], 1);
+        assert_eq!(counts[0][HISTOGRAM_BINS / 2], 1);
+        assert_eq!(counts[1][0], 1);
+        assert_eq!(counts[2][0], 1);
+        // Red is dark in luma: 255 * 0.2126 = 54.
+        assert_eq!(counts[3][54 * HISTOGRAM_BINS / 256], 1);
+        assert_eq!(counts.iter().flatten().sum::<u32>(), 8);
+    }
+
+    #[test]
+    fn histogram_panel_fills_the_top_left_corner_only() {
+        let (width, height) = (400u32, 300u32);
+        let frame = vec![255u8; width as usize * height as usize * 4];
+        let panel = HistogramPanel::new(&frame, width, height).unwrap();
+        assert_eq!((panel.width, panel.height), (100, 50));
+
+        let mut pixels = vec![0u8; frame.len()];
+        panel.draw(&mut pixels, width, height);
+        let alpha_at = |x: u32, y: u32| pixels[((y * width + x) * 4 + 3) as usize];
+        assert_ne!(alpha_at(BADGE_MARGIN_PX, BADGE_MARGIN_PX), 0);
+        assert_eq!(alpha_at(BADGE_MARGIN_PX + 100, BADGE_MARGIN_PX), 0);
+        assert_eq!(alpha_at(BADGE_MARGIN_PX, BADGE_MARGIN_PX + 50), 0);
+
+        assert!(HistogramPanel::new(&frame, 90, 60).is_none());
+    }
+
+    #[test]
+    fn progress_bar_shows_played_then_decoded_then_track() {
+        let (width, height) = (100u32, 40u32);
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        draw_progress_bar(&mut pixels, width, height, 0.25, Some(0.5));
+        let at = |x: u32, y: u32| {
+            let offset = ((y * width + x) * 4) as usize;
+            [pixels[offset], pixels[offset + 3]]
+        };
+        let (bottom, track) = (height - 1, CAPTION_BACKGROUND_ALPHA as u8);
+        assert_eq!(at(0, bottom), [255, 255]);
+        assert_eq!(at(24, height - PROGRESS_BAR_PX), [255, 255]);
+        assert_eq!(at(25, bottom), [PROGRESS_DECODED_FILL, track]);
+        assert_eq!(at(50, bottom), [0, track]);
+        // Only the bottom rows are touched.
+        assert_eq!(at(0, height - PROGRESS_BAR_PX - 1), [0, 0]);
+    }
+}
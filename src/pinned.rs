@@ -0,0 +1,898 @@
+//! Pinned previews.
+//!
+//! Pressing `pin_key` while a preview is showing copies its current frame into
+//! a regular, movable window that stays open after the cursor moves on. Pinned
+//! windows double as a small markup tool. Letter keys pick what a drag does:
+//! R pixelates the region (redaction, the default), A draws an arrow, B a box,
+//! D a freehand stroke, and T places text typed until Enter. Ctrl+Z undoes the
+//! last edit. Shift+drag selects a crop.
+//!
+//! Ctrl+S saves the edited image next to the original as `<name>-redacted.png`,
+//! Enter saves the crop as `<name>-crop.png`, and Ctrl+C copies the edited
+//! image (or the crop) to the clipboard. O reads the text in the image (or the
+//! crop) with Windows OCR and copies it instead, and L switches the OCR
+//! language between those installed. Q copies the content of the QR codes and
+//! barcodes in the image, and U opens the first web address among them.
+//! Results are built from the
+//! full-resolution original when the `image` crate can decode it, otherwise
+//! from the pinned frame; the original is never written. Esc closes the window.
+//!
+//! Windows are created on the preview thread, whose message loop also
+//! dispatches their messages. Window state lives in `PINNED`; the lock is never
+//! held across calls that can send messages back to `pinned_window_proc`.
+
+use crate::annotation::{self, Annotation, Tool};
+use crate::codes;
+use crate::layout;
+use crate::ocr;
+use crate::preview_window::{self, ImageFrame};
+use crate::tray;
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HANDLE, HGLOBAL, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, DrawFocusRect, EndPaint, GetMonitorInfoW, InvalidateRect, MonitorFromRect,
+    SetDIBitsToDevice, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, MONITORINFO,
+    MONITOR_DEFAULTTONEAREST, PAINTSTRUCT,
+};
+use windows::Win32::System::DataExchange::{
+    CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Memory::{
+    GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE,
+};
+use windows::Win32::System::Ole::{CF_DIB, CF_UNICODETEXT};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyState, ReleaseCapture, SetCapture, VK_BACK, VK_CONTROL, VK_ESCAPE, VK_RETURN,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyWindow, LoadCursorW,
+    RegisterClassExW, SetForegroundWindow, SetWindowTextW, ShowWindow, CS_HREDRAW, CS_VREDRAW,
+    IDC_CROSS, SW_SHOW, WM_CHAR, WM_DESTROY, WM_ERASEBKGND, WM_KEYDOWN, WM_LBUTTONDOWN,
+    WM_LBUTTONUP, WM_MOUSEMOVE, WM_PAINT, WNDCLASSEXW, WS_CAPTION, WS_EX_TOOLWINDOW, WS_EX_TOPMOST,
+    WS_POPUP, WS_SYSMENU,
+};
+
+const PINNED_CLASS: PCWSTR = w!("RustHoverPreviewPinned");
+const HELP_TEXT: &str = "R/A/B/D/T tools, Shift+drag crop, Enter save crop, Ctrl+S save, \
+    Ctrl+C copy, O copy text, Q copy codes, U open code URL, Ctrl+Z undo, Esc close";
+/// `MK_SHIFT` in the mouse message `wParam`.
+const MOUSE_SHIFT_DOWN: usize = 0x0004;
+/// Drags smaller than this are treated as clicks.
+const MIN_REDACT_PX: i32 = 4;
+
+static CLASS_REGISTERED: AtomicBool = AtomicBool::new(false);
+static PINNED: Lazy<Mutex<HashMap<isize, PinnedWindow>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Region in frame pixels: left, top, right, bottom (exclusive).
+type Region = (u32, u32, u32, u32);
+
+/// One undoable change, in pinned-frame pixels.
+enum Edit {
+    Redact(Region),
+    Mark(Annotation),
+}
+
+struct PinnedWindow {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    /// The frame as pinned, straight BGRA.
+    original: Vec<u8>,
+    /// `original` with `edits` applied; what gets painted.
+    shown: Vec<u8>,
+    edits: Vec<Edit>,
+    tool: Tool,
+    /// Selected crop, kept until replaced by another Shift+drag.
+    crop: Option<Region>,
+    drag: Option<Drag>,
+    /// Text being typed with the text tool, and where it goes.
+    typing: Option<((i32, i32), String)>,
+}
+
+struct Drag {
+    start: (i32, i32),
+    end: (i32, i32),
+    /// Selecting a crop rather than using the current tool.
+    crop: bool,
+    /// Points of a freehand stroke so far.
+    points: Vec<(i32, i32)>,
+}
+
+impl PinnedWindow {
+    fn title(&self) -> String {
+        window_title(
+            &self.path,
+            &format!("{} | {}", self.tool.label(), HELP_TEXT),
+        )
+    }
+
+    /// The shape the current drag would add, drawn live while dragging.
+    fn drag_mark(&self) -> Option<Annotation> {
+        let drag = self.drag.as_ref().filter(|drag| !drag.crop)?;
+        match self.tool {
+            Tool::Arrow => Some(Annotation::Arrow {
+                from: drag.start,
+                to: drag.end,
+            }),
+            Tool::Rectangle => Some(Annotation::Rectangle {
+                from: drag.start,
+                to: drag.end,
+            }),
+            Tool::Freehand => Some(Annotation::Freehand(drag.points.clone())),
+            Tool::Redact | Tool::Text => None,
+        }
+    }
+
+    fn typing_mark(&self) -> Option<Annotation> {
+        let (at, text) = self.typing.clone()?;
+        Some(Annotation::Text { at, text })
+    }
+
+    fn redraw(&mut self) {
+        let mut shown = self.original.clone();
+        let pending: Vec<Annotation> = self
+            .drag_mark()
+            .into_iter()
+            .chain(self.typing_mark())
+            .collect();
+        let size = (self.width, self.height);
+        let color = annotation::COLOR_BGRA;
+        apply_edits(&mut shown, size, size, &self.edits, pending.iter(), color);
+        self.shown = shown;
+    }
+
+    /// Finish text entry, keeping the text as an edit unless it is empty.
+    fn commit_typing(&mut self) {
+        if let Some((at, text)) = self.typing.take() {
+            if !text.is_empty() {
+                self.edits.push(Edit::Mark(Annotation::Text { at, text }));
+            }
+        }
+    }
+}
+
+/// Apply `edits` and then `pending` marks, all in `frame`-sized coordinates, to
+/// a buffer of `size` pixels.
+fn apply_edits<'a>(
+    pixels: &mut [u8],
+    frame: (u32, u32),
+    size: (u32, u32),
+    edits: &[Edit],
+    pending: impl Iterator<Item = &'a Annotation>,
+    color: [u8; 4],
+) {
+    let block = pixelation_block(size.0, size.1);
+    let scale = size.0 as f32 / frame.0 as f32;
+    for edit in edits {
+        match edit {
+            Edit::Redact(region) => {
+                let region = scale_region(*region, frame, size);
+                pixelate(pixels, size.0, size.1, region, block);
+            }
+            Edit::Mark(mark) => mark.draw(pixels, size.0, size.1, scale, color),
+        }
+    }
+    for mark in pending {
+        mark.draw(pixels, size.0, size.1, scale, color);
+    }
+}
+
+/// Open a pinned window showing `frame` with its client area at `(x, y)`.
+pub fn open(path: &Path, frame: &ImageFrame, x: i32, y: i32) {
+    if frame.width == 0 || frame.height == 0 {
+        return;
+    }
+
+    unsafe {
+        let Ok(hinstance) = GetModuleHandleW(None) else {
+            return;
+        };
+        if !CLASS_REGISTERED.swap(true, Ordering::AcqRel) {
+            let wc = WNDCLASSEXW {
+                cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+                style: CS_HREDRAW | CS_VREDRAW,
+                lpfnWndProc: Some(pinned_window_proc),
+                hInstance: hinstance.into(),
+                hCursor: LoadCursorW(None, IDC_CROSS).unwrap_or_default(),
+                lpszClassName: PINNED_CLASS,
+                ..Default::default()
+            };
+            RegisterClassExW(&wc);
+        }
+
+        let style = WS_POPUP | WS_CAPTION | WS_SYSMENU;
+        let ex_style = WS_EX_TOPMOST | WS_EX_TOOLWINDOW;
+        let mut rect = RECT {
+            left: x,
+            top: y,
+            right: x + frame.width as i32,
+            bottom: y + frame.height as i32,
+        };
+        let _ = AdjustWindowRectEx(&mut rect, style, false, ex_style);
+        // The frame added around the preview must not end up under the
+        // taskbar or off the top of the monitor.
+        let monitor = MonitorFromRect(&rect, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if GetMonitorInfoW(monitor, &mut info).as_bool() {
+            rect = layout::fit_in_work_area(rect, info.rcWork);
+        }
+
+        let window = PinnedWindow {
+            path: path.to_path_buf(),
+            width: frame.width,
+            height: frame.height,
+            original: frame.pixels.clone(),
+            shown: frame.pixels.clone(),
+            edits: Vec::new(),
+            tool: Tool::Redact,
+            crop: None,
+            drag: None,
+            typing: None,
+        };
+        let title = wide(&window.title());
+        let Ok(hwnd) = CreateWindowExW(
+            ex_style,
+            PINNED_CLASS,
+            PCWSTR(title.as_ptr()),
+            style,
+            rect.left,
+            rect.top,
+            rect.right - rect.left,
+            rect.bottom - rect.top,
+            None,
+            None,
+            hinstance,
+            None,
+        ) else {
+            return;
+        };
+
+        if let Ok(mut pinned) = PINNED.lock() {
+            pinned.insert(hwnd.0 as isize, window);
+        }
+
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        let _ = SetForegroundWindow(hwnd);
+    }
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn window_title(path: &Path, status: &str) -> String {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{} - {}", name, status)
+}
+
+fn point_from_lparam(lparam: LPARAM) -> (i32, i32) {
+    let x = (lparam.0 & 0xFFFF) as u16 as i16 as i32;
+    let y = ((lparam.0 >> 16) & 0xFFFF) as u16 as i16 as i32;
+    (x, y)
+}
+
+/// Drag corners to a region clamped to the frame, or `None` for tiny drags.
+fn drag_region(start: (i32, i32), end: (i32, i32), width: u32, height: u32) -> Option<Region> {
+    let clamp_x = |v: i32| v.clamp(0, width as i32) as u32;
+    let clamp_y = |v: i32| v.clamp(0, height as i32) as u32;
+    let (left, right) = (clamp_x(start.0.min(end.0)), clamp_x(start.0.max(end.0)));
+    let (top, bottom) = (clamp_y(start.1.min(end.1)), clamp_y(start.1.max(end.1)));
+    let big_enough =
+        (right - left) as i32 >= MIN_REDACT_PX && (bottom - top) as i32 >= MIN_REDACT_PX;
+    big_enough.then_some((left, top, right, bottom))
+}
+
+/// Mosaic cell size; large enough that text in the cell cannot be recovered.
+fn pixelation_block(width: u32, height: u32) -> u32 {
+    (width.max(height) / 48).max(8)
+}
+
+/// Replace each `block`-sized cell of `region` with its average colour. Works
+/// on any 4-byte pixel layout since every channel is averaged independently.
+fn pixelate(pixels: &mut [u8], width: u32, height: u32, region: Region, block: u32) {
+    let (left, top, right, bottom) = region;
+    let (right, bottom) = (right.min(width), bottom.min(height));
+    let block = block.max(1);
+
+    let mut cell_y = top;
+    while cell_y < bottom {
+        let cell_bottom = (cell_y + block).min(bottom);
+        let mut cell_x = left;
+        while cell_x < right {
+            let cell_right = (cell_x + block).min(right);
+
+            let mut sums = [0u64; 4];
+            for y in cell_y..cell_bottom {
+                for x in cell_x..cell_right {
+                    let offset = (y as usize * width as usize + x as usize) * 4;
+                    for (sum, value) in sums.iter_mut().zip(&pixels[offset..offset + 4]) {
+                        *sum += *value as u64;
+                    }
+                }
+            }
+            let count = ((cell_bottom - cell_y) * (cell_right - cell_x)) as u64;
+            let average = sums.map(|sum| (sum / count) as u8);
+            for y in cell_y..cell_bottom {
+                for x in cell_x..cell_right {
+                    let offset = (y as usize * width as usize + x as usize) * 4;
+                    pixels[offset..offset + 4].copy_from_slice(&average);
+                }
+            }
+
+            cell_x = cell_right;
+        }
+        cell_y = cell_bottom;
+    }
+}
+
+/// `region` of a `from`-sized frame mapped onto a `to`-sized image, rounded
+/// outwards so scaling never leaves an unredacted sliver.
+fn scale_region(region: Region, from: (u32, u32), to: (u32, u32)) -> Region {
+    let scale_down = |v: u32, from: u32, to: u32| (v as u64 * to as u64 / from as u64) as u32;
+    let scale_up = |v: u32, from: u32, to: u32| {
+        ((v as u64 * to as u64 + from as u64 - 1) / from as u64).min(to as u64) as u32
+    };
+    (
+        scale_down(region.0, from.0, to.0),
+        scale_down(region.1, from.1, to.1),
+        scale_up(region.2, from.0, to.0),
+        scale_up(region.3, from.1, to.1),
+    )
+}
+
+/// `<stem>-<suffix>.png` next to `path`, numbered if that name is taken.
+fn copy_path(path: &Path, suffix: &str, exists: impl Fn(&Path) -> bool) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "preview".to_string());
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut candidate = dir.join(format!("{}-{}.png", stem, suffix));
+    let mut n = 2;
+    while exists(&candidate) {
+        candidate = dir.join(format!("{}-{}-{}.png", stem, suffix, n));
+        n += 1;
+    }
+    candidate
+}
+
+/// The edited image at the original's resolution, cut down to `crop` if given.
+fn edited_image(window: &PinnedWindow, crop: Option<Region>) -> Option<image::RgbaImage> {
+    let mut image = match image::open(&window.path) {
+        Ok(original) => original.to_rgba8(),
+        Err(_) => {
+            let rgba: Vec<u8> = window
+                .original
+                .chunks_exact(4)
+                .flat_map(|px| [px[2], px[1], px[0], px[3]])
+                .collect();
+            image::RgbaImage::from_raw(window.width, window.height, rgba)?
+        }
+    };
+
+    let size = image.dimensions();
+    let frame = (window.width, window.height);
+    let color = annotation::rgba(annotation::COLOR_BGRA);
+    let pending = window.typing_mark();
+    apply_edits(
+        &mut image,
+        frame,
+        size,
+        &window.edits,
+        pending.iter(),
+        color,
+    );
+    if let Some(crop) = crop {
+        let (left, top, right, bottom) = scale_region(crop, frame, size);
+        image = image::imageops::crop_imm(&image, left, top, right - left, bottom - top).to_image();
+    }
+    Some(image)
+}
+
+/// Save the edited image, cut down to `crop` if given, as `<stem>-<suffix>.png`.
+fn save_copy(window: &PinnedWindow, suffix: &str, crop: Option<Region>) -> Option<PathBuf> {
+    let image = edited_image(window, crop)?;
+    let target = copy_path(&window.path, suffix, |path| path.exists());
+    image.save(&target).ok()?;
+    Some(target)
+}
+
+/// Window title after a save attempt.
+fn save_status(window: &PinnedWindow, saved: Option<PathBuf>, what: &str) -> String {
+    let status = match saved {
+        Some(target) => format!(
+            "saved {}",
+            target
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        ),
+        None => format!("could not save the {}", what),
+    };
+    window_title(&window.path, &status)
+}
+
+/// Put `image` on the clipboard as a 32-bit `CF_DIB`.
+pub unsafe fn copy_to_clipboard(hwnd: HWND, image: &image::RgbaImage) -> bool {
+    let (width, height) = image.dimensions();
+    let header = BITMAPINFOHEADER {
+        biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+        biWidth: width as i32,
+        // Bottom-up rows, which every clipboard consumer understands.
+        biHeight: height as i32,
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: BI_RGB.0,
+        ..Default::default()
+    };
+    let header_size = std::mem::size_of::<BITMAPINFOHEADER>();
+    let row_bytes = width as usize * 4;
+    let Ok(memory) = GlobalAlloc(GMEM_MOVEABLE, header_size + row_bytes * height as usize) else {
+        return false;
+    };
+    let target = GlobalLock(memory) as *mut u8;
+    if target.is_null() {
+        let _ = GlobalFree(memory);
+        return false;
+    }
+    std::ptr::copy_nonoverlapping(
+        &header as *const BITMAPINFOHEADER as *const u8,
+        target,
+        header_size,
+    );
+    let bits = std::slice::from_raw_parts_mut(target.add(header_size), row_bytes * height as usize);
+    for (y, row) in image.rows().enumerate() {
+        let offset = (height as usize - 1 - y) * row_bytes;
+        for (x, px) in row.enumerate() {
+            let [r, g, b, a] = px.0;
+            bits[offset + x * 4..offset + x * 4 + 4].copy_from_slice(&[b, g, r, a]);
+        }
+    }
+    let _ = GlobalUnlock(memory);
+    set_clipboard(hwnd, CF_DIB.0 as u32, memory)
+}
+
+/// Put `text` on the clipboard as `CF_UNICODETEXT`.
+pub unsafe fn copy_text_to_clipboard(hwnd: HWND, text: &str) -> bool {
+    let text = wide(text);
+    let Ok(memory) = GlobalAlloc(GMEM_MOVEABLE, text.len() * 2) else {
+        return false;
+    };
+    let target = GlobalLock(memory) as *mut u16;
+    if target.is_null() {
+        let _ = GlobalFree(memory);
+        return false;
+    }
+    std::ptr::copy_nonoverlapping(text.as_ptr(), target, text.len());
+    let _ = GlobalUnlock(memory);
+    set_clipboard(hwnd, CF_UNICODETEXT.0 as u32, memory)
+}
+
+unsafe fn set_clipboard(hwnd: HWND, format: u32, memory: HGLOBAL) -> bool {
+    if OpenClipboard(hwnd).is_err() {
+        let _ = GlobalFree(memory);
+        return false;
+    }
+    let _ = EmptyClipboard();
+    // The clipboard owns the memory once SetClipboardData succeeds.
+    let copied = SetClipboardData(format, HANDLE(memory.0)).is_ok();
+    if !copied {
+        let _ = GlobalFree(memory);
+    }
+    let _ = CloseClipboard();
+    copied
+}
+
+/// Read the text in `image` on a worker thread and copy it to the clipboard,
+/// reporting the outcome in the window title.
+fn copy_text_in_background(hwnd: HWND, path: PathBuf, image: image::RgbaImage) {
+    let language = CONFIG
+        .lock()
+        .map(|config| config.ocr_language.clone())
+        .unwrap_or_default();
+    let hwnd = hwnd.0 as isize;
+    std::thread::spawn(move || {
+        let hwnd = HWND(hwnd as *mut _);
+        let status = match ocr::recognize(&image, &language) {
+            Some(found) if found.text.trim().is_empty() => {
+                format!("no text found ({})", found.language)
+            }
+            Some(found) => {
+                let lines = found.text.lines().count();
+                if unsafe { copy_text_to_clipboard(hwnd, &found.text) } {
+                    format!("copied {} line(s) of text ({})", lines, found.language)
+                } else {
+                    "could not copy to the clipboard".to_string()
+                }
+            }
+            None if language.is_empty() => "no OCR language is installed".to_string(),
+            None => format!("OCR for {} is not installed", language),
+        };
+        // The window may have been closed while the text was read.
+        unsafe { set_title(hwnd, &window_title(&path, &status)) };
+    });
+}
+
+/// Decode the QR codes and barcodes in `image` on a worker thread, then copy
+/// their content to the clipboard or, with `open_url`, open the first web
+/// address among them.
+fn scan_codes_in_background(hwnd: HWND, path: PathBuf, image: image::RgbaImage, open_url: bool) {
+    let hwnd = hwnd.0 as isize;
+    std::thread::spawn(move || {
+        let hwnd = HWND(hwnd as *mut _);
+        let found = codes::detect_rgba(&image);
+        let status = if found.is_empty() {
+            "no QR code or barcode found".to_string()
+        } else if open_url {
+            match codes::first_web_url(&found) {
+                Some(url) => {
+                    tray::open_url(url);
+                    format!("opened {}", url)
+                }
+                None => "no web address in the codes found".to_string(),
+            }
+        } else {
+            let text: Vec<&str> = found.iter().map(|code| code.text.as_str()).collect();
+            if unsafe { copy_text_to_clipboard(hwnd, &text.join("\r\n")) } {
+                format!("copied {} code(s)", found.len())
+            } else {
+                "could not copy to the clipboard".to_string()
+            }
+        };
+        // The window may have been closed while the image was scanned.
+        unsafe { set_title(hwnd, &window_title(&path, &status)) };
+    });
+}
+
+/// Switch `ocr_language` to the next installed OCR language.
+fn cycle_ocr_language() -> Option<String> {
+    let available = ocr::available_languages();
+    let mut config = CONFIG.lock().ok()?;
+    let next = ocr::next_language(&config.ocr_language, &available)?;
+    config.ocr_language = next.clone();
+    config.save();
+    Some(next)
+}
+
+fn focus_rect((start, end): ((i32, i32), (i32, i32))) -> RECT {
+    RECT {
+        left: start.0.min(end.0),
+        top: start.1.min(end.1),
+        right: start.0.max(end.0),
+        bottom: start.1.max(end.1),
+    }
+}
+
+unsafe fn set_title(hwnd: HWND, title: &str) {
+    let title = wide(title);
+    let _ = SetWindowTextW(hwnd, PCWSTR(title.as_ptr()));
+}
+
+unsafe fn paint(hwnd: HWND) {
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut ps);
+    if let Ok(pinned) = PINNED.lock() {
+        if let Some(window) = pinned.get(&(hwnd.0 as isize)) {
+            // The window has no per-pixel alpha, so transparent areas are
+            // flattened rather than painted with whatever color they hold.
+            let pixels =
+                preview_window::opaque_preview_pixels(&window.shown, window.width, window.height);
+            let bmi = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: window.width as i32,
+                    biHeight: -(window.height as i32),
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            SetDIBitsToDevice(
+                hdc,
+                0,
+                0,
+                window.width,
+                window.height,
+                0,
+                0,
+                0,
+                window.height,
+                pixels.as_ptr() as *const _,
+                &bmi,
+                DIB_RGB_COLORS,
+            );
+            if let Some((left, top, right, bottom)) = window.crop {
+                let corners = ((left as i32, top as i32), (right as i32, bottom as i32));
+                let _ = DrawFocusRect(hdc, &focus_rect(corners));
+            }
+            // Redactions and crops are outlined while dragging; shapes draw live.
+            if let Some(drag) = &window.drag {
+                if drag.crop || window.tool == Tool::Redact {
+                    let _ = DrawFocusRect(hdc, &focus_rect((drag.start, drag.end)));
+                }
+            }
+        }
+    }
+    let _ = EndPaint(hwnd, &ps);
+}
+
+unsafe extern "system" fn pinned_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    let key = hwnd.0 as isize;
+    match msg {
+        WM_PAINT => {
+            paint(hwnd);
+            LRESULT(0)
+        }
+        WM_ERASEBKGND => LRESULT(1),
+        WM_LBUTTONDOWN => {
+            let point = point_from_lparam(lparam);
+            if let Ok(mut pinned) = PINNED.lock() {
+                if let Some(window) = pinned.get_mut(&key) {
+                    window.commit_typing();
+                    let crop = wparam.0 & MOUSE_SHIFT_DOWN != 0;
+                    if window.tool == Tool::Text && !crop {
+                        window.typing = Some((point, String::new()));
+                    } else {
+                        window.drag = Some(Drag {
+                            start: point,
+                            end: point,
+                            crop,
+                            points: vec![point],
+                        });
+                    }
+                    window.redraw();
+                }
+            }
+            SetCapture(hwnd);
+            let _ = InvalidateRect(hwnd, None, false);
+            LRESULT(0)
+        }
+        WM_MOUSEMOVE => {
+            let mut dragging = false;
+            if let Ok(mut pinned) = PINNED.lock() {
+                if let Some(window) = pinned.get_mut(&key) {
+                    if let Some(drag) = window.drag.as_mut() {
+                        let point = point_from_lparam(lparam);
+                        drag.end = point;
+                        if window.tool == Tool::Freehand {
+                            drag.points.push(point);
+                        }
+                        dragging = true;
+                    }
+                    if dragging && window.tool != Tool::Redact {
+                        window.redraw();
+                    }
+                }
+            }
+            if dragging {
+                let _ = InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+        WM_LBUTTONUP => {
+            if let Ok(mut pinned) = PINNED.lock() {
+                if let Some(window) = pinned.get_mut(&key) {
+                    let mark = window.drag_mark();
+                    if let Some(drag) = window.drag.take() {
+                        let end = point_from_lparam(lparam);
+                        let region = drag_region(drag.start, end, window.width, window.height);
+                        if drag.crop {
+                            window.crop = region;
+                        } else if window.tool == Tool::Redact {
+                            window.edits.extend(region.map(Edit::Redact));
+                        } else if let Some(mark) =
+                            mark.filter(|_| region.is_some() || window.tool == Tool::Freehand)
+                        {
+                            window.edits.push(Edit::Mark(mark));
+                        }
+                        window.redraw();
+                    }
+                }
+            }
+            let _ = ReleaseCapture();
+            let _ = InvalidateRect(hwnd, None, false);
+            LRESULT(0)
+        }
+        WM_CHAR => {
+            let typed = char::from_u32(wparam.0 as u32).filter(|c| !c.is_control());
+            if let Ok(mut pinned) = PINNED.lock() {
+                if let Some(window) = pinned.get_mut(&key) {
+                    if let (Some((_, text)), Some(typed)) = (window.typing.as_mut(), typed) {
+                        text.push(typed);
+                        window.redraw();
+                    }
+                }
+            }
+            let _ = InvalidateRect(hwnd, None, false);
+            LRESULT(0)
+        }
+        WM_KEYDOWN => {
+            let ctrl = GetKeyState(VK_CONTROL.0 as i32) < 0;
+            let vk = wparam.0 as u16;
+            let typing = PINNED
+                .lock()
+                .ok()
+                .map(|pinned| {
+                    pinned
+                        .get(&key)
+                        .map(|w| w.typing.is_some())
+                        .unwrap_or(false)
+                })
+                .unwrap_or(false);
+
+            if typing && (vk == VK_RETURN.0 || vk == VK_ESCAPE.0 || vk == VK_BACK.0) {
+                // Keys that edit the text being typed; letters arrive as WM_CHAR.
+                if let Ok(mut pinned) = PINNED.lock() {
+                    if let Some(window) = pinned.get_mut(&key) {
+                        if vk == VK_RETURN.0 {
+                            window.commit_typing();
+                        } else if vk == VK_ESCAPE.0 {
+                            window.typing = None;
+                        } else if let Some((_, text)) = window.typing.as_mut() {
+                            text.pop();
+                        }
+                        window.redraw();
+                    }
+                }
+                let _ = InvalidateRect(hwnd, None, false);
+            } else if vk == VK_ESCAPE.0 {
+                let _ = DestroyWindow(hwnd);
+            } else if ctrl && vk == u16::from(b'Z') {
+                if let Ok(mut pinned) = PINNED.lock() {
+                    if let Some(window) = pinned.get_mut(&key) {
+                        window.typing = None;
+                        window.edits.pop();
+                        window.redraw();
+                    }
+                }
+                let _ = InvalidateRect(hwnd, None, false);
+            } else if ctrl && vk == u16::from(b'C') {
+                let image = PINNED.lock().ok().and_then(|pinned| {
+                    let window = pinned.get(&key)?;
+                    Some((edited_image(window, window.crop), window.path.clone()))
+                });
+                if let Some((image, path)) = image {
+                    let copied = image
+                        .map(|image| copy_to_clipboard(hwnd, &image))
+                        .unwrap_or(false);
+                    let status = if copied {
+                        "copied to the clipboard"
+                    } else {
+                        "could not copy to the clipboard"
+                    };
+                    set_title(hwnd, &window_title(&path, status));
+                }
+            } else if (ctrl && vk == u16::from(b'S')) || vk == VK_RETURN.0 {
+                let title = PINNED.lock().ok().and_then(|pinned| {
+                    let window = pinned.get(&key)?;
+                    if vk == VK_RETURN.0 {
+                        let crop = window.crop?;
+                        let saved = save_copy(window, "crop", Some(crop));
+                        Some(save_status(window, saved, "crop"))
+                    } else {
+                        let saved = save_copy(window, "redacted", None);
+                        Some(save_status(window, saved, "redacted copy"))
+                    }
+                });
+                if let Some(title) = title {
+                    set_title(hwnd, &title);
+                }
+            } else if !ctrl && !typing && vk == u16::from(b'O') {
+                let job = PINNED.lock().ok().and_then(|pinned| {
+                    let window = pinned.get(&key)?;
+                    Some((edited_image(window, window.crop)?, window.path.clone()))
+                });
+                if let Some((image, path)) = job {
+                    set_title(hwnd, &window_title(&path, "reading text..."));
+                    copy_text_in_background(hwnd, path, image);
+                }
+            } else if !ctrl && !typing && (vk == u16::from(b'Q') || vk == u16::from(b'U')) {
+                let job = PINNED.lock().ok().and_then(|pinned| {
+                    let window = pinned.get(&key)?;
+                    Some((edited_image(window, window.crop)?, window.path.clone()))
+                });
+                if let Some((image, path)) = job {
+                    set_title(hwnd, &window_title(&path, "scanning for codes..."));
+                    scan_codes_in_background(hwnd, path, image, vk == u16::from(b'U'));
+                }
+            } else if !ctrl && !typing && vk == u16::from(b'L') {
+                let path = PINNED
+                    .lock()
+                    .ok()
+                    .and_then(|pinned| pinned.get(&key).map(|window| window.path.clone()));
+                if let Some(path) = path {
+                    let status = match cycle_ocr_language() {
+                        Some(language) => format!("OCR language {}", language),
+                        None => "no OCR language is installed".to_string(),
+                    };
+                    set_title(hwnd, &window_title(&path, &status));
+                }
+            } else if !ctrl && !typing {
+                let title = char::from_u32(vk as u32)
+                    .and_then(Tool::from_key)
+                    .and_then(|tool| {
+                        let mut pinned = PINNED.lock().ok()?;
+                        let window = pinned.get_mut(&key)?;
+                        window.tool = tool;
+                        Some(window.title())
+                    });
+                if let Some(title) = title {
+                    set_title(hwnd, &title);
+                }
+            }
+            LRESULT(0)
+        }
+        WM_DESTROY => {
+            if let Ok(mut pinned) = PINNED.lock() {
+                pinned.remove(&key);
+            }
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixelation_averages_each_cell() {
+        // 4x1 image, two 2-pixel cells.
+        let mut pixels = vec![
+            0, 0, 0, 255, 100, 100, 100, 255, 10, 20, 30, 255, 30, 40, 50, 255,
+        ];
+        pixelate(&mut pixels, 4, 1, (0, 0, 4, 1), 2);
+        assert_eq!(&pixels[..8], &[50, 50, 50, 255, 50, 50, 50, 255]);
+        assert_eq!(&pixels[8..], &[20, 30, 40, 255, 20, 30, 40, 255]);
+    }
+
+    #[test]
+    fn drags_become_clamped_regions() {
+        assert_eq!(
+            drag_region((50, 40), (-5, 10), 100, 80),
+            Some((0, 10, 50, 40))
+        );
+        assert_eq!(drag_region((10, 10), (12, 30), 100, 80), None);
+        assert_eq!(
+            scale_region((10, 10, 25, 25), (100, 100), (1000, 333)),
+            (100, 33, 250, 84)
+        );
+    }
+
+    #[test]
+    fn redacted_copies_do_not_overwrite() {
+        let path = Path::new("C:\\shots\\login.jpg");
+        let taken = [PathBuf::from("C:\\shots\\login-redacted.png")];
+        assert_eq!(
+            copy_path(path, "redacted", |candidate| taken
+                .iter()
+                .any(|t| t == candidate)),
+            PathBuf::from("C:\\shots\\login-redacted-2.png")
+        );
+        assert_eq!(
+            copy_path(path, "crop", |_| false),
+            PathBuf::from("C:\\shots\\login-crop.png")
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Prefetching the files next to the previewed one.
+//!
+//! After a still or animated preview loads, the next and previous previewable
+//! files in its folder (in Explorer's default name order) are decoded on a
+//! low-priority thread into the in-memory media cache, so stepping through a
+//! folder shows each preview without waiting for its decode. Starting a new
+//! preview cancels the prefetch in flight. Videos are never prefetched, and
+//! nothing is while the system is under heavy load.
+
+use crate::preview_window;
+use crate::priority;
+use crate::providers;
+use crate::system_load;
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use std::fs;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use windows::core::PCWSTR;
+use windows::Win32::System::Threading::{
+    GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_LOWEST,
+};
+use windows::Win32::UI::Shell::StrCmpLogicalW;
+
+/// Folders with more entries than this are not listed for prefetching.
+const MAX_LISTED_ENTRIES: usize = 20_000;
+
+static SENDER: Lazy<Mutex<Option<Sender<PrefetchRequest>>>> = Lazy::new(|| Mutex::new(None));
+static CURRENT_CANCEL: Lazy<Mutex<Option<Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(None));
+
+struct PrefetchRequest {
+    path: PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Previewable files of one folder in name order, as of its modification time.
+struct FolderListing {
+    folder: PathBuf,
+    modified: Option<SystemTime>,
+    files: Vec<PathBuf>,
+}
+
+impl FolderListing {
+    fn read(folder: &Path) -> Option<Self> {
+        Some(FolderListing {
+            folder: folder.to_path_buf(),
+            modified: fs::metadata(folder).ok()?.modified().ok(),
+            files: list_folder(folder, false)?,
+        })
+    }
+
+    fn is_current(&self, folder: &Path) -> bool {
+        self.folder == folder
+            && fs::metadata(folder)
+                .ok()
+                .and_then(|meta| meta.modified().ok())
+                == self.modified
+    }
+}
+
+/// Prefetch the neighbours of `path`, decoded to fit the same bounds.
+pub fn request(path: &PathBuf, max_width: u32, max_height: u32) {
+    let enabled = CONFIG
+        .lock()
+        .map(|config| config.prefetch_neighbors && config.memory_cache_entries > 0)
+        .unwrap_or(false);
+    if !enabled || system_load::is_high() || providers::is_video(path) {
+        return;
+    }
+
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    if let Ok(mut current) = CURRENT_CANCEL.lock() {
+        if let Some(previous) = current.replace(Arc::clone(&cancel_flag)) {
+            previous.store(true, Ordering::Release);
+        }
+    }
+    if let Ok(mut sender) = SENDER.lock() {
+        let _ = sender
+            .get_or_insert_with(spawn_worker)
+            .send(PrefetchRequest {
+                path: path.clone(),
+                max_width,
+                max_height,
+                cancel: cancel_flag,
+            });
+    }
+}
+
+/// Stop the prefetch in flight so a new preview gets the CPU.
+pub fn cancel() {
+    if let Ok(mut current) = CURRENT_CANCEL.lock() {
+        if let Some(flag) = current.take() {
+            flag.store(true, Ordering::Release);
+        }
+    }
+}
+
+fn spawn_worker() -> Sender<PrefetchRequest> {
+    let (tx, rx) = channel::<PrefetchRequest>();
+    std::thread::spawn(move || {
+        unsafe {
+            let _ = SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_LOWEST);
+        }
+        let mut listing: Option<FolderListing> = None;
+        while let Ok(mut request) = rx.recv() {
+            // Only the newest preview's neighbours matter.
+            while let Ok(newer) = rx.try_recv() {
+                request = newer;
+            }
+            priority::use_decode_cores();
+            let Some(folder) = request.path.parent() else {
+                continue;
+            };
+            if !listing
+                .as_ref()
+                .map(|l| l.is_current(folder))
+                .unwrap_or(false)
+            {
+                listing = FolderListing::read(folder);
+            }
+            let Some(files) = listing.as_ref().map(|listing| &listing.files) else {
+                continue;
+            };
+            for neighbour in neighbours(files, &request.path) {
+                if request.cancel.load(Ordering::Acquire) {
+                    break;
+                }
+                preview_window::prefetch_media(
+                    neighbour,
+                    request.max_width,
+                    request.max_height,
+                    &request.cancel,
+                );
+            }
+        }
+    });
+    tx
+}
+
+/// The previewable file after (or before) `path` in its folder, videos
+/// included, for stepping through a folder from the preview.
+pub fn sibling(path: &Path, forward: bool) -> Option<PathBuf> {
+    let files = list_folder(path.parent()?, true)?;
+    let index = files.iter().position(|file| same_file(file, path))?;
+    let sibling = if forward {
+        files.get(index + 1)
+    } else {
+        index.checked_sub(1).and_then(|i| files.get(i))
+    };
+    sibling.cloned()
+}
+
+/// Previewable files of `folder` in Explorer's name order.
+fn list_folder(folder: &Path, include_videos: bool) -> Option<Vec<PathBuf>> {
+    let mut named: Vec<(Vec<u16>, PathBuf)> = fs::read_dir(folder)
+        .ok()?
+        .take(MAX_LISTED_ENTRIES)
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            providers::is_supported(path) && (include_videos || !providers::is_video(path))
+        })
+        .map(|path| {
+            let name = path
+                .file_name()
+                .map(|name| name.encode_wide().chain(std::iter::once(0)).collect())
+                .unwrap_or_else(|| vec![0]);
+            (name, path)
+        })
+        .collect();
+    // Explorer's "sort by name", which orders "2.jpg" before "10.jpg".
+    named.sort_by(|(a, _), (b, _)| unsafe {
+        StrCmpLogicalW(PCWSTR(a.as_ptr()), PCWSTR(b.as_ptr())).cmp(&0)
+    });
+    Some(named.into_iter().map(|(_, path)| path).collect())
+}
+
+fn same_file(file: &Path, path: &Path) -> bool {
+    file.as_os_str()
+        .to_string_lossy()
+        .eq_ignore_ascii_case(&path.as_os_str().to_string_lossy())
+}
+
+/// The files after and before `path` in `files`, next first.
+fn neighbours<'a>(files: &'a [PathBuf], path: &Path) -> Vec<&'a PathBuf> {
+    let Some(index) = files.iter().position(|file| same_file(file, path)) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    if let Some(next) = files.get(index + 1) {
+        found.push(next);
+    }
+    if let Some(previous) = index.checked_sub(1).and_then(|i| files.get(i)) {
+        found.push(previous);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbours_are_next_then_previous() {
+        let files: Vec<PathBuf> = ["a.jpg", "b.png", "c.gif"]
+            .iter()
+            .map(|name| Path::new("C:\\Pictures").join(name))
+            .collect();
+        let path = Path::new("C:\\pictures\\B.PNG");
+        assert_eq!(neighbours(&files, path), vec![&files[2], &files[0]]);
+        assert_eq!(neighbours(&files, &files[2]), vec![&files[1]]);
+        assert!(neighbours(&files, Path::new("C:\\Pictures\\z.jpg")).is_empty());
+    }
+}
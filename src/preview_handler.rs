@@ -0,0 +1,795 @@
+//! Explorer's preview pane and thumbnails drawn by the app's own decoders.
+//!
+//! With `preview_pane` enabled, the app registers itself for the current user
+//! as the shell preview handler of formats Windows has no preview for
+//! (`PANE_EXTENSIONS`), as a COM local server: Explorer's preview host starts
+//! `rust-hover-preview.exe --preview-handler` and asks it for a handler per
+//! file. The handler decodes the file through the provider registry, plugins
+//! included, fitted to the pane, and plays animations in a child window of the
+//! pane. The server process has no tray icon or hook and exits when its last
+//! handler is released.
+//!
+//! With `explorer_thumbnails` enabled, the same server also provides the
+//! thumbnails Explorer shows in icon and tile views for
+//! `THUMBNAIL_EXTENSIONS`. Every thumbnail size Explorer asks for is cut
+//! from one decode at `THUMBNAIL_DECODE_SIZE`, which slow formats keep in the
+//! disk cache, so a file is decoded once rather than once per size.
+
+use crate::preview_window::{self, MediaData};
+use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use windows::core::{implement, w, Error, IUnknown, Interface, Result, GUID, PCWSTR};
+use windows::Win32::Foundation::{
+    BOOL, CLASS_E_NOAGGREGATION, E_FAIL, E_UNEXPECTED, HWND, LPARAM, LRESULT, RECT, S_FALSE, WPARAM,
+};
+use windows::Win32::Graphics::Gdi::{
+    BeginPaint, CreateDIBSection, EndPaint, FillRect, GetSysColorBrush, InvalidateRect,
+    SetStretchBltMode, StretchDIBits, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, COLOR_WINDOW,
+    DIB_RGB_COLORS, HALFTONE, HBITMAP, PAINTSTRUCT, SRCCOPY,
+};
+use windows::Win32::System::Com::{
+    CoAddRefServerProcess, CoInitializeEx, CoRegisterClassObject, CoReleaseServerProcess,
+    CoResumeClassObjects, CoRevokeClassObject, CoUninitialize, IClassFactory, IClassFactory_Impl,
+    CLSCTX_LOCAL_SERVER, COINIT_APARTMENTTHREADED, REGCLS_MULTIPLEUSE, REGCLS_SUSPENDED,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Ole::{
+    IObjectWithSite, IObjectWithSite_Impl, IOleWindow, IOleWindow_Impl,
+};
+use windows::Win32::System::Registry::{
+    RegCloseKey, RegCreateKeyExW, RegDeleteTreeW, RegDeleteValueW, RegGetValueW, RegOpenKeyExW,
+    RegSetValueExW, HKEY, HKEY_CURRENT_USER, KEY_SET_VALUE, REG_OPTION_NON_VOLATILE, REG_SZ,
+    RRF_RT_REG_SZ,
+};
+use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::GetFocus;
+use windows::Win32::UI::Shell::PropertiesSystem::{IInitializeWithFile, IInitializeWithFile_Impl};
+use windows::Win32::UI::Shell::{
+    IPreviewHandler, IPreviewHandler_Impl, IThumbnailProvider, IThumbnailProvider_Impl,
+    SHChangeNotify, SHCNE_ASSOCCHANGED, SHCNF_IDLIST, WTSAT_ARGB, WTS_ALPHATYPE,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GetClientRect, GetMessageW,
+    KillTimer, PostThreadMessageW, RegisterClassExW, SetTimer, SetWindowPos, TranslateMessage,
+    CS_HREDRAW, CS_VREDRAW, MSG, SWP_NOACTIVATE, SWP_NOZORDER, WINDOW_EX_STYLE, WM_DESTROY,
+    WM_ERASEBKGND, WM_PAINT, WM_QUIT, WM_TIMER, WNDCLASSEXW, WS_CHILD, WS_VISIBLE,
+};
+
+/// Files whose preview pane this app draws when registered. Formats Windows
+/// previews itself, and videos, are left to Windows.
+pub const PANE_EXTENSIONS: &[&str] = &[
+    "webp",
+    "dcm",
+    "dicom",
+    "fits",
+    "fit",
+    "fts",
+    "npy",
+    "parquet",
+    "parq",
+    "kra",
+    "clip",
+    "procreate",
+    "blend",
+    "gcode",
+    "gco",
+];
+
+/// Files whose thumbnails this app draws when registered: the pane's
+/// formats that have a picture to show. WebP is left to Windows, which
+/// thumbnails it itself.
+pub const THUMBNAIL_EXTENSIONS: &[&str] = &[
+    "dcm",
+    "dicom",
+    "fits",
+    "fit",
+    "fts",
+    "npy",
+    "kra",
+    "clip",
+    "procreate",
+    "blend",
+    "gcode",
+    "gco",
+];
+
+const CLSID_PANE_HANDLER: GUID = GUID::from_u128(0x5b0e6c8e_3f4a_4d7b_9a61_2c8f1e7d4a93);
+const CLSID_TEXT: &str = "{5b0e6c8e-3f4a-4d7b-9a61-2c8f1e7d4a93}";
+const CLSID_THUMBNAIL_PROVIDER: GUID = GUID::from_u128(0x9d3c2a71_6e0b_4f58_b2d4_7a1e5c8f0b36);
+const THUMBNAIL_CLSID_TEXT: &str = "{9d3c2a71-6e0b-4f58-b2d4-7a1e5c8f0b36}";
+/// The `shellex` key Explorer looks up a file type's preview handler under.
+const PREVIEW_HANDLER_SHELLEX: &str = "{8895b1c6-b41f-4c1c-a562-0d564250836f}";
+/// The `shellex` key Explorer looks up a file type's thumbnail provider under.
+const THUMBNAIL_PROVIDER_SHELLEX: &str = "{e357fccd-a995-4576-b01f-234630154e96}";
+const PREVIEW_HANDLERS_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\PreviewHandlers";
+const HANDLER_NAME: &str = "Rust Hover Preview";
+const THUMBNAIL_PROVIDER_NAME: &str = "Rust Hover Preview Thumbnails";
+/// Thumbnails are decoded at least this large and shrunk to each size asked.
+const THUMBNAIL_DECODE_SIZE: u32 = 256;
+const SERVER_ARGUMENT: &str = "--preview-handler";
+const PANE_CLASS: PCWSTR = w!("RustHoverPreviewPane");
+const FRAME_TIMER: usize = 1;
+const FRAME_INTERVAL_MS: u32 = 15;
+
+static CLASS_REGISTERED: AtomicBool = AtomicBool::new(false);
+/// The server's message loop, told to quit when the last handler goes.
+static SERVER_THREAD: AtomicU32 = AtomicU32::new(0);
+/// What each pane window shows, by window.
+static PANES: Lazy<Mutex<HashMap<isize, MediaData>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Whether COM started the app to serve preview handlers or thumbnails.
+pub fn server_requested(args: impl IntoIterator<Item = OsString>) -> bool {
+    args.into_iter().skip(1).any(|arg| arg == SERVER_ARGUMENT)
+}
+
+/// Serve preview handlers and thumbnails until Explorer releases the last
+/// one.
+pub fn run_server() {
+    unsafe {
+        if CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_err() {
+            return;
+        }
+        SERVER_THREAD.store(GetCurrentThreadId(), Ordering::Release);
+        let cookies: Vec<u32> = [CLSID_PANE_HANDLER, CLSID_THUMBNAIL_PROVIDER]
+            .into_iter()
+            .filter_map(|clsid| {
+                let factory: IClassFactory = HandlerFactory { clsid }.into();
+                CoRegisterClassObject(
+                    &clsid,
+                    &factory,
+                    CLSCTX_LOCAL_SERVER,
+                    REGCLS_MULTIPLEUSE | REGCLS_SUSPENDED,
+                )
+                .ok()
+            })
+            .collect();
+        if !cookies.is_empty() {
+            let _ = CoResumeClassObjects();
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        }
+        for cookie in cookies {
+            let _ = CoRevokeClassObject(cookie);
+        }
+        CoUninitialize();
+    }
+}
+
+/// Register the app as the preview handler of `PANE_EXTENSIONS` for the
+/// current user. Registering again updates the path to the app.
+pub fn register() {
+    if !register_server(CLSID_TEXT, HANDLER_NAME) {
+        return;
+    }
+    for extension in PANE_EXTENSIONS {
+        set_string(
+            &extension_key(extension, PREVIEW_HANDLER_SHELLEX),
+            None,
+            CLSID_TEXT,
+        );
+    }
+    set_string(PREVIEW_HANDLERS_KEY, Some(CLSID_TEXT), HANDLER_NAME);
+    notify_association_change();
+}
+
+/// Register the app as the thumbnail provider of `THUMBNAIL_EXTENSIONS` for
+/// the current user. Registering again updates the path to the app.
+pub fn register_thumbnails() {
+    if !register_server(THUMBNAIL_CLSID_TEXT, THUMBNAIL_PROVIDER_NAME) {
+        return;
+    }
+    for extension in THUMBNAIL_EXTENSIONS {
+        set_string(
+            &extension_key(extension, THUMBNAIL_PROVIDER_SHELLEX),
+            None,
+            THUMBNAIL_CLSID_TEXT,
+        );
+    }
+    notify_association_change();
+}
+
+/// Remove the thumbnail registration. File types whose thumbnail provider
+/// was since set to another one keep it.
+pub fn unregister_thumbnails() {
+    unregister_extensions(
+        THUMBNAIL_EXTENSIONS,
+        THUMBNAIL_PROVIDER_SHELLEX,
+        THUMBNAIL_CLSID_TEXT,
+    );
+    unregister_server(THUMBNAIL_CLSID_TEXT);
+    notify_association_change();
+}
+
+/// Remove the registration. File types whose preview handler was since set
+/// to another one keep it.
+pub fn unregister() {
+    unregister_extensions(PANE_EXTENSIONS, PREVIEW_HANDLER_SHELLEX, CLSID_TEXT);
+    unregister_server(CLSID_TEXT);
+    unsafe {
+        let handlers_key = wide(PREVIEW_HANDLERS_KEY);
+        let mut hkey = HKEY::default();
+        if RegOpenKeyExW(
+            HKEY_CURRENT_USER,
+            PCWSTR(handlers_key.as_ptr()),
+            0,
+            KEY_SET_VALUE,
+            &mut hkey,
+        )
+        .is_ok()
+        {
+            let name = wide(CLSID_TEXT);
+            let _ = RegDeleteValueW(hkey, PCWSTR(name.as_ptr()));
+            let _ = RegCloseKey(hkey);
+        }
+    }
+    notify_association_change();
+}
+
+/// Point the class at this copy of the app as its local server. False when
+/// the app's path is unknown.
+fn register_server(clsid_text: &str, name: &str) -> bool {
+    let Ok(exe) = std::env::current_exe() else {
+        return false;
+    };
+    let clsid_key = format!(r"Software\Classes\CLSID\{}", clsid_text);
+    set_string(&clsid_key, None, name);
+    set_string(
+        &format!(r"{}\LocalServer32", clsid_key),
+        None,
+        &format!("\"{}\" {}", exe.display(), SERVER_ARGUMENT),
+    );
+    true
+}
+
+fn unregister_server(clsid_text: &str) {
+    let clsid_key = wide(&format!(r"Software\Classes\CLSID\{}", clsid_text));
+    unsafe {
+        let _ = RegDeleteTreeW(HKEY_CURRENT_USER, PCWSTR(clsid_key.as_ptr()));
+    }
+}
+
+/// Remove the `shellex` entries of `extensions` that still name the class.
+fn unregister_extensions(extensions: &[&str], shellex: &str, clsid_text: &str) {
+    for extension in extensions {
+        let key = extension_key(extension, shellex);
+        if get_string(&key).as_deref() == Some(clsid_text) {
+            let key = wide(&key);
+            unsafe {
+                let _ = RegDeleteTreeW(HKEY_CURRENT_USER, PCWSTR(key.as_ptr()));
+            }
+        }
+    }
+}
+
+fn extension_key(extension: &str, shellex: &str) -> String {
+    format!(r"Software\Classes\.{}\shellex\{}", extension, shellex)
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Set a string value under `HKEY_CURRENT_USER`, creating the key.
+fn set_string(key: &str, name: Option<&str>, value: &str) {
+    let key = wide(key);
+    let name = name.map(wide);
+    let value = wide(value);
+    unsafe {
+        let mut hkey = HKEY::default();
+        if RegCreateKeyExW(
+            HKEY_CURRENT_USER,
+            PCWSTR(key.as_ptr()),
+            0,
+            PCWSTR::null(),
+            REG_OPTION_NON_VOLATILE,
+            KEY_SET_VALUE,
+            None,
+            &mut hkey,
+            None,
+        )
+        .is_ok()
+        {
+            let name = name
+                .as_ref()
+                .map(|name| PCWSTR(name.as_ptr()))
+                .unwrap_or(PCWSTR::null());
+            let _ = RegSetValueExW(hkey, name, 0, REG_SZ, Some(value.align_to::<u8>().1));
+            let _ = RegCloseKey(hkey);
+        }
+    }
+}
+
+/// The default value of a key under `HKEY_CURRENT_USER`.
+fn get_string(key: &str) -> Option<String> {
+    let key = wide(key);
+    let mut buffer = [0u16; 128];
+    let mut size = std::mem::size_of_val(&buffer) as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            PCWSTR(key.as_ptr()),
+            PCWSTR::null(),
+            RRF_RT_REG_SZ,
+            None,
+            Some(buffer.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+        .ok()
+        .ok()?;
+    }
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    Some(String::from_utf16_lossy(&buffer[..len]))
+}
+
+fn notify_association_change() {
+    unsafe { SHChangeNotify(SHCNE_ASSOCCHANGED, SHCNF_IDLIST, None, None) };
+}
+
+/// Makes preview handlers or thumbnail providers, by the class it was
+/// registered for.
+#[implement(IClassFactory)]
+struct HandlerFactory {
+    clsid: GUID,
+}
+
+impl IClassFactory_Impl for HandlerFactory_Impl {
+    fn CreateInstance(
+        &self,
+        outer: Option<&IUnknown>,
+        iid: *const GUID,
+        object: *mut *mut core::ffi::c_void,
+    ) -> Result<()> {
+        if outer.is_some() {
+            return Err(CLASS_E_NOAGGREGATION.into());
+        }
+        let handler: IUnknown = if self.clsid == CLSID_THUMBNAIL_PROVIDER {
+            ThumbnailProvider::new().into()
+        } else {
+            PaneHandler::new().into()
+        };
+        unsafe { handler.query(iid, object).ok() }
+    }
+
+    fn LockServer(&self, lock: BOOL) -> Result<()> {
+        unsafe {
+            if lock.as_bool() {
+                CoAddRefServerProcess();
+            } else {
+                release_server();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drop a reference to the server, quitting it with the last one.
+unsafe fn release_server() {
+    if CoReleaseServerProcess() == 0 {
+        let _ = PostThreadMessageW(
+            SERVER_THREAD.load(Ordering::Acquire),
+            WM_QUIT,
+            WPARAM(0),
+            LPARAM(0),
+        );
+    }
+}
+
+#[derive(Default)]
+struct PaneState {
+    path: Option<PathBuf>,
+    parent: isize,
+    rect: RECT,
+    window: isize,
+    site: Option<IUnknown>,
+}
+
+#[implement(IPreviewHandler, IInitializeWithFile, IObjectWithSite, IOleWindow)]
+struct PaneHandler {
+    state: RefCell<PaneState>,
+}
+
+impl PaneHandler {
+    fn new() -> Self {
+        unsafe { CoAddRefServerProcess() };
+        PaneHandler {
+            state: RefCell::new(PaneState::default()),
+        }
+    }
+}
+
+impl Drop for PaneHandler {
+    fn drop(&mut self) {
+        close_pane(self.state.get_mut());
+        unsafe { release_server() };
+    }
+}
+
+impl IInitializeWithFile_Impl for PaneHandler_Impl {
+    fn Initialize(&self, path: &PCWSTR, _mode: u32) -> Result<()> {
+        let path = unsafe { path.to_string() }.map_err(|_| Error::from(E_FAIL))?;
+        self.state.borrow_mut().path = Some(PathBuf::from(path));
+        Ok(())
+    }
+}
+
+impl IPreviewHandler_Impl for PaneHandler_Impl {
+    fn SetWindow(&self, parent: HWND, rect: *const RECT) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.parent = parent.0 as isize;
+        if let Some(rect) = unsafe { rect.as_ref() } {
+            state.rect = *rect;
+        }
+        Ok(())
+    }
+
+    fn SetRect(&self, rect: *const RECT) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if let Some(rect) = unsafe { rect.as_ref() } {
+            state.rect = *rect;
+        }
+        if state.window != 0 {
+            let rect = state.rect;
+            unsafe {
+                let _ = SetWindowPos(
+                    HWND(state.window as *mut _),
+                    None,
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn DoPreview(&self) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let path = state
+            .path
+            .clone()
+            .ok_or_else(|| Error::from(E_UNEXPECTED))?;
+        close_pane(&mut state);
+        let rect = state.rect;
+        let width = (rect.right - rect.left).max(1) as u32;
+        let height = (rect.bottom - rect.top).max(1) as u32;
+        let media = preview_window::load_hosted_media(&path, width, height)
+            .ok_or_else(|| Error::from(E_FAIL))?;
+        let window = unsafe { create_pane(HWND(state.parent as *mut _), rect)? };
+        if let Ok(mut panes) = PANES.lock() {
+            panes.insert(window.0 as isize, media);
+        }
+        unsafe {
+            SetTimer(window, FRAME_TIMER, FRAME_INTERVAL_MS, None);
+        }
+        state.window = window.0 as isize;
+        Ok(())
+    }
+
+    fn Unload(&self) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        close_pane(&mut state);
+        state.path = None;
+        state.site = None;
+        Ok(())
+    }
+
+    fn SetFocus(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn QueryFocus(&self) -> Result<HWND> {
+        Ok(unsafe { GetFocus() })
+    }
+
+    fn TranslateAccelerator(&self, _msg: *const MSG) -> Result<()> {
+        // The pane takes no keys; the host handles them all.
+        Err(S_FALSE.into())
+    }
+}
+
+impl IObjectWithSite_Impl for PaneHandler_Impl {
+    fn SetSite(&self, site: Option<&IUnknown>) -> Result<()> {
+        self.state.borrow_mut().site = site.cloned();
+        Ok(())
+    }
+
+    fn GetSite(&self, iid: *const GUID, site: *mut *mut core::ffi::c_void) -> Result<()> {
+        let state = self.state.borrow();
+        let current = state.site.as_ref().ok_or_else(|| Error::from(E_FAIL))?;
+        unsafe { current.query(iid, site).ok() }
+    }
+}
+
+impl IOleWindow_Impl for PaneHandler_Impl {
+    fn GetWindow(&self) -> Result<HWND> {
+        Ok(HWND(self.state.borrow().parent as *mut _))
+    }
+
+    fn ContextSensitiveHelp(&self, _enter: BOOL) -> Result<()> {
+        Err(E_FAIL.into())
+    }
+}
+
+#[implement(IThumbnailProvider, IInitializeWithFile)]
+struct ThumbnailProvider {
+    path: RefCell<Option<PathBuf>>,
+}
+
+impl ThumbnailProvider {
+    fn new() -> Self {
+        unsafe { CoAddRefServerProcess() };
+        ThumbnailProvider {
+            path: RefCell::new(None),
+        }
+    }
+}
+
+impl Drop for ThumbnailProvider {
+    fn drop(&mut self) {
+        unsafe { release_server() };
+    }
+}
+
+impl IInitializeWithFile_Impl for ThumbnailProvider_Impl {
+    fn Initialize(&self, path: &PCWSTR, _mode: u32) -> Result<()> {
+        let path = unsafe { path.to_string() }.map_err(|_| Error::from(E_FAIL))?;
+        *self.path.borrow_mut() = Some(PathBuf::from(path));
+        Ok(())
+    }
+}
+
+impl IThumbnailProvider_Impl for ThumbnailProvider_Impl {
+    fn GetThumbnail(&self, cx: u32, bitmap: *mut HBITMAP, alpha: *mut WTS_ALPHATYPE) -> Result<()> {
+        let path = self
+            .path
+            .borrow()
+            .clone()
+            .ok_or_else(|| Error::from(E_UNEXPECTED))?;
+        // One decode size for every size asked, so the disk cache entry of
+        // the first serves the rest.
+        let decode_size = cx.max(THUMBNAIL_DECODE_SIZE);
+        let media = preview_window::load_hosted_media(&path, decode_size, decode_size)
+            .ok_or_else(|| Error::from(E_FAIL))?;
+        let (pixels, width, height) = media.shown_frame();
+        let (pixels, width, height) =
+            shrink_bgra(pixels, width, height, cx).ok_or_else(|| Error::from(E_FAIL))?;
+        let hbitmap = unsafe { create_bitmap(&pixels, width, height)? };
+        unsafe {
+            *bitmap = hbitmap;
+            *alpha = WTSAT_ARGB;
+        }
+        Ok(())
+    }
+}
+
+/// BGRA pixels shrunk to fit in `size` pixels square; smaller frames are
+/// kept as they are.
+fn shrink_bgra(pixels: &[u8], width: u32, height: u32, size: u32) -> Option<(Vec<u8>, u32, u32)> {
+    if width <= size && height <= size {
+        return Some((pixels.to_vec(), width, height));
+    }
+    let (fit_width, fit_height) =
+        preview_window::scale_dimensions(width, height, size.max(1), size.max(1));
+    // The channel order does not matter to the filter.
+    let frame = image::RgbaImage::from_raw(width, height, pixels.to_vec())?;
+    let shrunk = image::imageops::resize(
+        &frame,
+        fit_width,
+        fit_height,
+        image::imageops::FilterType::Triangle,
+    );
+    Some((shrunk.into_raw(), fit_width, fit_height))
+}
+
+/// A top-down 32-bit DIB section holding the BGRA pixels.
+unsafe fn create_bitmap(pixels: &[u8], width: u32, height: u32) -> Result<HBITMAP> {
+    let bmi = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut bits: *mut core::ffi::c_void = std::ptr::null_mut();
+    let hbitmap = CreateDIBSection(None, &bmi, DIB_RGB_COLORS, &mut bits, None, 0)?;
+    if bits.is_null() {
+        return Err(E_FAIL.into());
+    }
+    let len = pixels.len().min(width as usize * height as usize * 4);
+    std::ptr::copy_nonoverlapping(pixels.as_ptr(), bits as *mut u8, len);
+    Ok(hbitmap)
+}
+
+unsafe fn create_pane(parent: HWND, rect: RECT) -> Result<HWND> {
+    let hinstance = GetModuleHandleW(None)?;
+    if !CLASS_REGISTERED.swap(true, Ordering::AcqRel) {
+        let wc = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(pane_window_proc),
+            hInstance: hinstance.into(),
+            lpszClassName: PANE_CLASS,
+            ..Default::default()
+        };
+        RegisterClassExW(&wc);
+    }
+    CreateWindowExW(
+        WINDOW_EX_STYLE(0),
+        PANE_CLASS,
+        PCWSTR::null(),
+        WS_CHILD | WS_VISIBLE,
+        rect.left,
+        rect.top,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+        parent,
+        None,
+        hinstance,
+        None,
+    )
+}
+
+/// Destroy the pane's window, with what it showed.
+fn close_pane(state: &mut PaneState) {
+    if state.window == 0 {
+        return;
+    }
+    unsafe {
+        let _ = DestroyWindow(HWND(state.window as *mut _));
+    }
+    state.window = 0;
+}
+
+unsafe extern "system" fn pane_window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_TIMER => {
+            let advanced = PANES
+                .lock()
+                .ok()
+                .and_then(|mut panes| Some(panes.get_mut(&(hwnd.0 as isize))?.next_frame()))
+                .unwrap_or(false);
+            if advanced {
+                let _ = InvalidateRect(hwnd, None, false);
+            }
+            LRESULT(0)
+        }
+        WM_PAINT => {
+            paint(hwnd);
+            LRESULT(0)
+        }
+        WM_ERASEBKGND => LRESULT(1),
+        WM_DESTROY => {
+            let _ = KillTimer(hwnd, FRAME_TIMER);
+            if let Ok(mut panes) = PANES.lock() {
+                panes.remove(&(hwnd.0 as isize));
+            }
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+unsafe fn paint(hwnd: HWND) {
+    let mut ps = PAINTSTRUCT::default();
+    let hdc = BeginPaint(hwnd, &mut ps);
+    let mut client = RECT::default();
+    let _ = GetClientRect(hwnd, &mut client);
+    FillRect(hdc, &client, GetSysColorBrush(COLOR_WINDOW));
+    if let Ok(panes) = PANES.lock() {
+        if let Some(media) = panes.get(&(hwnd.0 as isize)) {
+            let (pixels, width, height) = media.shown_frame();
+            let pixels = preview_window::opaque_preview_pixels(pixels, width, height);
+            let target = fit_rect((width, height), (client.right, client.bottom));
+            let bmi = BITMAPINFO {
+                bmiHeader: BITMAPINFOHEADER {
+                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                    biWidth: width as i32,
+                    biHeight: -(height as i32),
+                    biPlanes: 1,
+                    biBitCount: 32,
+                    biCompression: BI_RGB.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            SetStretchBltMode(hdc, HALFTONE);
+            StretchDIBits(
+                hdc,
+                target.left,
+                target.top,
+                target.right - target.left,
+                target.bottom - target.top,
+                0,
+                0,
+                width as i32,
+                height as i32,
+                Some(pixels.as_ptr() as *const _),
+                &bmi,
+                DIB_RGB_COLORS,
+                SRCCOPY,
+            );
+        }
+    }
+    let _ = EndPaint(hwnd, &ps);
+}
+
+/// Where a frame of `size` goes in a client area of `client` pixels: as
+/// large as fits without enlarging it, centered.
+fn fit_rect(size: (u32, u32), client: (i32, i32)) -> RECT {
+    let (width, height) = preview_window::scale_dimensions(
+        size.0,
+        size.1,
+        client.0.max(1) as u32,
+        client.1.max(1) as u32,
+    );
+    let left = (client.0 - width as i32) / 2;
+    let top = (client.1 - height as i32) / 2;
+    RECT {
+        left,
+        top,
+        right: left + width as i32,
+        bottom: top + height as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_shrunk_to_the_pane_and_centered() {
+        let fitted = fit_rect((800, 400), (400, 400));
+        assert_eq!(
+            (fitted.left, fitted.top, fitted.right, fitted.bottom),
+            (0, 100, 400, 300)
+        );
+        // Small files are not enlarged.
+        let small = fit_rect((100, 50), (400, 400));
+        assert_eq!(
+            (small.left, small.top, small.right, small.bottom),
+            (150, 175, 250, 225)
+        );
+
+        assert!(server_requested(
+            ["app.exe", "--preview-handler", "-Embedding"].map(OsString::from)
+        ));
+        assert!(!server_requested(["--preview-handler"].map(OsString::from)));
+    }
+
+    #[test]
+    fn thumbnails_are_shrunk_to_the_size_asked() {
+        let pixels = vec![128u8; 512 * 256 * 4];
+        let (shrunk, width, height) = shrink_bgra(&pixels, 512, 256, 96).unwrap();
+        assert_eq!((width, height), (96, 48));
+        assert_eq!(shrunk.len(), 96 * 48 * 4);
+        // Small frames are not enlarged.
+        let (kept, width, height) = shrink_bgra(&pixels[..16 * 8 * 4], 16, 8, 96).unwrap();
+        assert_eq!((width, height, kept.len()), (16, 8, 16 * 8 * 4));
+        assert!(shrink_bgra(&pixels[..4], 512, 256, 96).is_none());
+
+        assert_eq!(
+            extension_key("kra", THUMBNAIL_PROVIDER_SHELLEX),
+            r"Software\Classes\.kra\shellex\{e357fccd-a995-4576-b01f-234630154e96}"
+        );
+    }
+}
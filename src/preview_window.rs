@@ -1,52 +1,122 @@
-use crate::config::{sanitize_webp_playback_fps, TransparentBackground, DEFAULT_WEBP_PLAYBACK_FPS};
+use crate::automation;
+use crate::codes;
+use crate::color_profile;
+use crate::config::{
+    sanitize_video_speed, sanitize_webp_playback_fps, ClickAction, TransparentBackground,
+    VideoBackend, VideoPreviewMode, DEFAULT_WEBP_PLAYBACK_FPS,
+};
+use crate::dates;
+use crate::exif;
+use crate::favorites;
+use crate::first_pass;
+use crate::gallery;
+use crate::history;
+use crate::image_header;
+use crate::layout::{
+    compute_item_layout, compute_keyboard_layout, compute_mouse_layout,
+    compute_mouse_layout_around, compute_panel_layout, PreviewLayout, ScreenBounds,
+};
+use crate::media_foundation;
+use crate::nsfw;
+use crate::overlay;
+use crate::pinned;
+use crate::prefetch;
+use crate::priority;
+use crate::providers::{self, LoadConstraints};
+use crate::sound;
+use crate::system_load;
+use crate::thumbnail_cache;
+use crate::trace;
+use crate::video::{self, CREATE_NO_WINDOW};
+use crate::warm_start;
+use crate::zoom;
 use crate::{CONFIG, RUNNING};
 use gif::DecodeOptions;
 use image::GenericImageView;
 use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use std::collections::{HashMap, VecDeque};
 use std::env;
+use std::ffi::{OsStr, OsString};
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, Write};
+use std::os::windows::ffi::OsStrExt;
 use std::os::windows::process::CommandExt;
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
 use std::ptr;
-use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU32, Ordering};
-use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::sync::{Arc, Condvar, Mutex};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use windows::core::{w, PCWSTR};
 use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, SIZE, WPARAM};
 use windows::Win32::Graphics::Gdi::{
     BeginPaint, CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, EndPaint,
-    SelectObject, AC_SRC_ALPHA, AC_SRC_OVER, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLENDFUNCTION,
-    DIB_RGB_COLORS, PAINTSTRUCT,
+    GetMonitorInfoW, GetStockObject, MonitorFromPoint, SelectObject, AC_SRC_ALPHA, AC_SRC_OVER,
+    BITMAPINFO, BITMAPINFOHEADER, BI_RGB, BLACK_BRUSH, BLENDFUNCTION, DIB_RGB_COLORS, HBRUSH,
+    MONITORINFO, MONITOR_DEFAULTTONEAREST, PAINTSTRUCT,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::System::Threading::{OpenProcess, TerminateProcess, PROCESS_TERMINATE};
+use windows::Win32::UI::Input::KeyboardAndMouse::{ReleaseCapture, SetCapture};
+use windows::Win32::UI::Shell::ShellExecuteW;
 use windows::Win32::UI::WindowsAndMessaging::{
     CreateWindowExW, DefWindowProcW, DispatchMessageW, EnumWindows, GetSystemMetrics, GetWindow,
-    GetWindowLongPtrW, GetWindowRect, GetWindowThreadProcessId, IsWindowVisible, LoadCursorW,
-    MoveWindow, PeekMessageW, RegisterClassExW, SetWindowLongPtrW, SetWindowPos, ShowWindow,
-    TranslateMessage, UpdateLayeredWindow, CS_HREDRAW, CS_VREDRAW, GWL_EXSTYLE, GW_OWNER,
-    HWND_TOPMOST, IDC_ARROW, MSG, PM_REMOVE, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
-    SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_SHOWWINDOW,
-    SW_HIDE, SW_SHOWNOACTIVATE, ULW_ALPHA, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_POWERBROADCAST,
-    PBT_APMRESUMEAUTOMATIC, PBT_APMRESUMESUSPEND, PBT_APMSUSPEND, PBT_APMSTANDBY, WNDCLASSEXW,
-    WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
+    GetWindowLongPtrW, GetWindowRect, GetWindowThreadProcessId, IsChild, IsWindowVisible,
+    LoadCursorW, MoveWindow, PeekMessageW, RegisterClassExW, SetWindowLongPtrW, SetWindowPos,
+    ShowWindow, TranslateMessage, UpdateLayeredWindow, CS_HREDRAW, CS_VREDRAW, GWL_EXSTYLE,
+    GW_OWNER, HWND_TOPMOST, IDC_ARROW, MA_NOACTIVATE, MSG, PBT_APMRESUMEAUTOMATIC,
+    PBT_APMRESUMESUSPEND, PBT_APMSTANDBY, PBT_APMSUSPEND, PM_REMOVE, SM_CXVIRTUALSCREEN,
+    SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SWP_FRAMECHANGED, SWP_HIDEWINDOW,
+    SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_SHOWWINDOW, SW_HIDE, SW_SHOWNOACTIVATE,
+    SW_SHOWNORMAL, ULW_ALPHA, WINDOWPOS, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_LBUTTONDOWN,
+    WM_LBUTTONUP, WM_MBUTTONUP, WM_MOUSEACTIVATE, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_POWERBROADCAST,
+    WM_RBUTTONUP, WM_WINDOWPOSCHANGING, WM_XBUTTONUP, WNDCLASSEXW, WS_CLIPCHILDREN, WS_EX_LAYERED,
+    WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_POPUP,
 };
 
 const PREVIEW_CLASS: PCWSTR = w!("RustHoverPreviewWindow");
+const VIDEO_HOST_CLASS: PCWSTR = w!("RustHoverPreviewVideoHost");
 
 // Video extensions for detection
-const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "mkv", "avi", "mov", "wmv", "flv", "m4v"];
-const MAX_STREAMED_ANIMATION_FRAMES: usize = 300;
-const MAX_STREAMED_ANIMATION_BYTES: usize = 256 * 1024 * 1024;
+/// Frames decoded ahead of an animation playing from its frame ring.
+const ANIMATION_RING_FRAMES: usize = 8;
 const MIN_GIF_ANIMATION_FRAME_DELAY_MS: u32 = 33;
 const ANIMATION_STARTUP_PREBUFFER_FRAMES: usize = 12;
 const ANIMATION_STARTUP_PREBUFFER_MS: u32 = 500;
 const STREAMING_SPINNER_MAX_MS: u64 = 1500;
 const VIDEO_GEOMETRY_CACHE_MAX_ENTRIES: usize = 512;
+const DIMENSIONS_CACHE_MAX_ENTRIES: usize = 512;
+/// Pixel memory `MEDIA_CACHE` may hold across all of its entries.
+const MEDIA_CACHE_MAX_BYTES: usize = 512 * 1024 * 1024;
+/// How long a prefetched animation may take to finish decoding.
+const PREFETCH_ANIMATION_WAIT_MS: u64 = 5000;
+/// Stills with at least this many pixels show a quick first pass.
+const PROGRESSIVE_MIN_PIXELS: u64 = 12_000_000;
+/// Pixel conversions smaller than this stay on the calling thread.
+const PARALLEL_MIN_BYTES: usize = 1024 * 1024;
+/// Bytes per rayon task when converting a frame; a multiple of 4.
+const PARALLEL_CHUNK_BYTES: usize = 256 * 1024;
+/// Seek positions across the preview width; moving within one does not reseek.
+const VIDEO_SCRUB_STEPS: f32 = 200.0;
+const VIDEO_SCRUB_MIN_INTERVAL_MS: u64 = 120;
+/// How long the cursor rests after scrubbing before the video's sound plays
+/// again from the new position.
+const VIDEO_SCRUB_AUDIO_SETTLE_MS: u64 = 400;
+const STORYBOARD_COLUMNS: u32 = 3;
+const STORYBOARD_ROWS: u32 = 3;
+const STORYBOARD_GAP_PX: u32 = 2;
+const VIDEO_STREAM_FIRST_FRAME_TIMEOUT_MS: u64 = 10000;
+/// A Show this soon after the previous one is part of a sweep across files.
+/// The Explorer hook resolves the hovered file at most every 60 ms.
+const SWEEP_SHOW_INTERVAL: Duration = Duration::from_millis(150);
+/// How long the newest Show of a sweep waits for the cursor to settle before
+/// its file is probed.
+const SWEEP_SHOW_SETTLE: Duration = Duration::from_millis(90);
+/// How far, in pixels, the left button must move on a zoomed preview before
+/// the press pans instead of clicking.
+const PAN_MIN_DRAG_PX: i32 = 4;
 
 // Message passing for thread communication
 pub static PREVIEW_SENDER: Lazy<Mutex<Option<Sender<PreviewMessage>>>> =
@@ -59,20 +129,88 @@ static PREVIEW_HWND: AtomicIsize = AtomicIsize::new(0);
 static VIDEO_HWND: AtomicIsize = AtomicIsize::new(0);
 // Track the ffplay process ID to re-find the window if needed
 static VIDEO_PID: AtomicU32 = AtomicU32::new(0);
+// Plain (non-layered) window that mpv renders into via --wid
+static VIDEO_HOST_HWND: AtomicIsize = AtomicIsize::new(0);
+// External player left running, paused and hidden, after its preview closed.
+static PARKED_PLAYER: Mutex<Option<ParkedPlayer>> = Mutex::new(None);
 // Guard to ensure we only run a single style-monitor thread.
 static NOACTIVATE_MONITOR_STARTED: AtomicBool = AtomicBool::new(false);
 // Flag set when the system resumes from sleep, so the main loop can reset state.
 static RESUME_FROM_SLEEP: AtomicBool = AtomicBool::new(false);
 
+// Set while the visible preview is an in-process video the cursor can scrub.
+static VIDEO_SCRUB_AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+// Set while the visible preview has a click action, can be zoomed or is a paused
+// animation, so it stays open under the cursor.
+static PREVIEW_CLICKABLE: AtomicBool = AtomicBool::new(false);
+// The left-button drag panning a zoomed preview, while the button is held.
+static PAN_DRAG: Mutex<Option<PanDrag>> = Mutex::new(None);
+
+// Set while `reveal_key` is held, showing blurred stills unblurred.
+static REVEAL_HELD: AtomicBool = AtomicBool::new(false);
+// Set while `exif_key` is held, showing the camera details panel.
+static EXIF_HELD: AtomicBool = AtomicBool::new(false);
+
+// Set while the animation on screen is paused with `pause_key`; cleared by the
+// next Show or Hide.
+static ANIMATION_PAUSED: AtomicBool = AtomicBool::new(false);
+
 static CURRENT_MEDIA: Lazy<Mutex<Option<MediaData>>> = Lazy::new(|| Mutex::new(None));
+// Memory held by `CURRENT_MEDIA`, updated each tick, so the cache can be kept
+// under `memory_cap_mb` without taking its lock.
+static CURRENT_MEDIA_BYTES: AtomicUsize = AtomicUsize::new(0);
+// The previous preview's frame while it fades into the current one.
+static CROSSFADE: Mutex<Option<Crossfade>> = Mutex::new(None);
 static VIDEO_GEOMETRY_CACHE: Lazy<Mutex<HashMap<PathBuf, VideoGeometry>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
+static MF_GEOMETRY_CACHE: Lazy<Mutex<HashMap<PathBuf, media_foundation::MfVideoInfo>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Probed media dimensions, with the file's modification time and length when
+/// probed, so files hovered again or loaded after their Show are not probed twice.
+static DIMENSIONS_CACHE: Lazy<Mutex<HashMap<PathBuf, (FileStamp, (u32, u32))>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Recently shown, fully decoded previews, oldest first, so moving back to a
+/// file reuses its frames instead of decoding it again.
+static MEDIA_CACHE: Lazy<Mutex<VecDeque<(MediaCacheKey, MediaData)>>> =
+    Lazy::new(|| Mutex::new(VecDeque::new()));
+/// How far each video played this session, so hovering it again resumes there.
+static VIDEO_RESUME_POSITIONS: Lazy<Mutex<HashMap<PathBuf, f64>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
 
 pub enum PreviewMessage {
-    Show(PathBuf, i32, i32),
+    /// Show a file hovered at a cursor position, clear of the hovered item's
+    /// bounds when given.
+    Show(PathBuf, i32, i32, Option<ScreenBounds>),
     ShowKeyboard(PathBuf, i32, i32, i32, i32),
     Hide,
     Refresh,
+    /// Copy the frame on screen into a pinned window.
+    Pin,
+    /// Star or unstar the previewed file as a favorite.
+    Favorite,
+    /// The preview was clicked; run the action bound to the button.
+    Click(MouseButton),
+    /// The mouse wheel turned over the preview: the rotation and the cursor's
+    /// screen position.
+    Zoom(i32, i32, i32),
+    /// Pause the animation on screen, or resume it.
+    TogglePause,
+    /// Step a paused animation by this many frames; negative steps back.
+    Step(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    X1,
+    X2,
+}
+
+impl MouseButton {
+    const ALL: [MouseButton; 5] = [Self::Left, Self::Middle, Self::Right, Self::X1, Self::X2];
 }
 
 /// Represents different types of media we can display
@@ -80,25 +218,44 @@ enum MediaType {
     StaticImage,
     AnimatedGif,
     AnimatedWebP,
+    /// First frame shown until an external player window (ffplay, mpv) appears
     Video,
+    /// Video decoded in-process and drawn into the preview window
+    VideoStream,
     Loading,
 }
 
 /// A single frame of image data
-struct ImageFrame {
-    pixels: Vec<u8>,
-    width: u32,
-    height: u32,
-    delay_ms: u32, // Delay before next frame (for animations)
+#[derive(Clone)]
+pub struct ImageFrame {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub delay_ms: u32, // Delay before next frame (for animations)
 }
 
 /// Media data that can be either static or animated
-struct MediaData {
-    frames: Vec<ImageFrame>,
+pub struct MediaData {
+    /// Frames are shared with the decoder and the caches rather than copied.
+    frames: Vec<Arc<ImageFrame>>,
     /// Shared frame queue for streaming decode (animated formats append here)
-    shared_frames: Option<Arc<Mutex<VecDeque<ImageFrame>>>>,
+    shared_frames: Option<video::FrameQueue>,
     /// Signal from the background thread that all frames have been decoded
     all_frames_loaded: Option<Arc<AtomicBool>>,
+    /// Set by the decoder once an animation outgrows `animation_memory_mb`.
+    /// From then on `shared_frames` is a ring decoded just ahead of playback,
+    /// and played frames are not kept.
+    frame_ring: Option<Arc<AtomicBool>>,
+    /// How far the decoder has got through an animation still streaming in.
+    decode_progress: Option<Arc<DecodeProgress>>,
+    /// Frames into the animation of the one on screen, once playing from the
+    /// frame ring, where `current_frame` no longer counts them.
+    ring_position: Option<usize>,
+    /// How many times an animation plays before stopping on its final frame,
+    /// from the file's loop count; `None` loops forever.
+    plays: Option<u32>,
+    /// Plays of the animation finished so far.
+    plays_done: u32,
     current_frame: usize,
     last_frame_time: Instant,
     media_type: MediaType,
@@ -107,6 +264,126 @@ struct MediaData {
     // For video playback using ffplay
     video_process: Option<Child>,
     loading_start: Option<Instant>,
+    /// How an in-process video stream was started, for scrubbing.
+    stream_source: Option<VideoStreamSource>,
+    /// When and where video playback started, for resuming it later.
+    playback_clock: Option<PlaybackClock>,
+    /// Video info bar blended over every frame.
+    info_band: Option<overlay::CaptionBand>,
+    /// Source and shown size in the top-right corner, set when the preview is
+    /// put on screen.
+    resolution_badge: Option<overlay::Badge>,
+    /// File name, dimensions, size and date along the bottom, set when the
+    /// preview is put on screen.
+    metadata_band: Option<overlay::CaptionBand>,
+    /// Camera details from the file's EXIF block, drawn while `exif_key` is
+    /// held.
+    exif_band: Option<overlay::CaptionBand>,
+    /// Levels of the still in the top-left corner, set when the preview is
+    /// put on screen.
+    histogram: Option<overlay::HistogramPanel>,
+    /// The file at full size and the part of it shown, while a still is
+    /// zoomed in with the mouse wheel.
+    zoom: Option<Zoom>,
+    /// Shown instead of the still while `reveal_key` is not held.
+    blurred: Option<Vec<u8>>,
+    /// Identifies the decoded file, for keeping this media in `MEDIA_CACHE`.
+    cache_key: Option<MediaCacheKey>,
+    /// A video's still shown instead of playback while the system is busy;
+    /// the preview loads it again to play once the load drops.
+    deferred_playback: bool,
+    /// Hover-scrub's restart of an in-process video stream.
+    scrub: VideoScrub,
+}
+
+/// A stream being restarted at `position` by hover-scrub, whose decoder starts
+/// on a worker so the preview thread never waits for it.
+struct PendingScrub {
+    position: f64,
+    cancel: Arc<AtomicBool>,
+    started: Receiver<Option<video::FrameQueue>>,
+}
+
+#[derive(Default)]
+struct VideoScrub {
+    pending: Option<PendingScrub>,
+    /// When the stream was last moved; its sound restarts once this settles.
+    moved_at: Option<Instant>,
+}
+
+/// How far the background decode of an animation has got, shared with the
+/// decoding thread for the progress bar.
+#[derive(Default)]
+struct DecodeProgress {
+    /// Frames in one play of the animation, counted from the file; 0 until
+    /// known.
+    total: AtomicUsize,
+    /// Frames of the first play decoded so far.
+    decoded: AtomicUsize,
+}
+
+/// A file as it was when decoded, and the size it was decoded at.
+#[derive(Clone, PartialEq)]
+struct MediaCacheKey {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+    width: u32,
+    height: u32,
+}
+
+impl MediaCacheKey {
+    /// Keyed by the preview size the bounds give rather than the bounds, which
+    /// move with the cursor even when the preview comes out the same.
+    fn new(path: &PathBuf, max_width: u32, max_height: u32) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        let (orig_width, orig_height) = get_media_dimensions(path)?;
+        let (width, height) = scale_dimensions(orig_width, orig_height, max_width, max_height);
+        Some(MediaCacheKey {
+            path: path.clone(),
+            modified: meta.modified().ok(),
+            len: meta.len(),
+            width,
+            height,
+        })
+    }
+}
+
+struct PlaybackClock {
+    path: PathBuf,
+    start_secs: f64,
+    started: Instant,
+    speed: f32,
+    paused: Option<Instant>,
+}
+
+impl PlaybackClock {
+    fn new(path: &PathBuf, start_secs: f64, speed: f32) -> Self {
+        PlaybackClock {
+            path: path.clone(),
+            start_secs,
+            started: Instant::now(),
+            speed,
+            paused: None,
+        }
+    }
+
+    /// Seconds of video played since 0:00, counting loops.
+    fn played_secs(&self) -> f64 {
+        let until = self.paused.unwrap_or_else(Instant::now);
+        let elapsed = until.saturating_duration_since(self.started);
+        self.start_secs + elapsed.as_secs_f64() * self.speed as f64
+    }
+
+    fn pause(&mut self) {
+        self.paused.get_or_insert_with(Instant::now);
+    }
+
+    fn resume(&mut self) {
+        if let Some(paused) = self.paused.take() {
+            self.started += paused.elapsed();
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -122,13 +399,129 @@ struct VideoGeometry {
     width: u32,
     height: u32,
     crop: Option<VideoCrop>,
+    frame_rate: Option<f32>,
+}
+
+#[derive(Clone)]
+enum VideoDecoder {
+    Ffmpeg { filter: String },
+    MediaFoundation,
+}
+
+/// Parameters an in-process video stream was started with, kept so the
+/// stream can be restarted at another position.
+#[derive(Clone)]
+struct VideoStreamSource {
+    path: PathBuf,
+    decoder: VideoDecoder,
+    width: u32,
+    height: u32,
+    frame_delay_ms: u32,
+    playback: video::PlaybackOptions,
+    duration_secs: Option<f64>,
+    duration_probed: bool,
+}
+
+impl VideoStreamSource {
+    fn spawn(&self, start_secs: f64, cancel: Arc<AtomicBool>) -> Option<video::FrameQueue> {
+        match &self.decoder {
+            VideoDecoder::Ffmpeg { filter } => video::spawn_ffmpeg_frame_stream(
+                &self.path,
+                filter,
+                self.width,
+                self.height,
+                self.frame_delay_ms,
+                start_secs,
+                self.playback,
+                cancel,
+            ),
+            VideoDecoder::MediaFoundation => media_foundation::spawn_frame_stream(
+                &self.path,
+                self.width,
+                self.height,
+                self.frame_delay_ms,
+                start_secs,
+                self.playback.looping,
+                cancel,
+            ),
+        }
+    }
+
+    fn duration_secs(&mut self) -> Option<f64> {
+        if !self.duration_probed {
+            self.duration_probed = true;
+            self.duration_secs = video::probe_duration(&self.path);
+        }
+        self.duration_secs
+    }
 }
 
 impl MediaData {
+    /// Wrap a single decoded BGRA frame as a static preview.
+    pub fn still(frame: ImageFrame) -> Self {
+        MediaData {
+            frames: vec![Arc::new(frame)],
+            shared_frames: None,
+            all_frames_loaded: None,
+            frame_ring: None,
+            decode_progress: None,
+            ring_position: None,
+            plays: None,
+            plays_done: 0,
+            current_frame: 0,
+            last_frame_time: Instant::now(),
+            media_type: MediaType::StaticImage,
+            stream_cancel: None,
+            video_process: None,
+            loading_start: None,
+            stream_source: None,
+            playback_clock: None,
+            info_band: None,
+            resolution_badge: None,
+            metadata_band: None,
+            exif_band: None,
+            histogram: None,
+            zoom: None,
+            cache_key: None,
+            blurred: None,
+            deferred_playback: false,
+            scrub: VideoScrub::default(),
+        }
+    }
+
+    /// The first decoded frame, for providers that draw on top of it.
+    /// Copied first if a cache still shares it.
+    pub fn first_frame_mut(&mut self) -> &mut ImageFrame {
+        Arc::make_mut(&mut self.frames[0])
+    }
+
+    /// The frame to put on screen now, BGRA, with its width and height.
+    pub fn shown_frame(&self) -> (&[u8], u32, u32) {
+        (
+            self.shown_pixels(),
+            self.current_width(),
+            self.current_height(),
+        )
+    }
+
+    /// Move an animation on for hosts other than the preview window; true
+    /// when a new frame is due on screen.
+    pub fn next_frame(&mut self) -> bool {
+        self.advance_frame()
+    }
+
     fn current_pixels(&self) -> &[u8] {
         &self.frames[self.current_frame].pixels
     }
 
+    /// The pixels to put on screen: blurred unless the reveal key is held.
+    fn shown_pixels(&self) -> &[u8] {
+        match &self.blurred {
+            Some(blurred) if !REVEAL_HELD.load(Ordering::Acquire) => blurred,
+            _ => self.current_pixels(),
+        }
+    }
+
     fn current_width(&self) -> u32 {
         self.frames[self.current_frame].width
     }
@@ -147,24 +540,78 @@ impl MediaData {
 
     /// Pull any newly decoded frames from the shared buffer
     fn sync_shared_frames(&mut self) {
+        if matches!(self.media_type, MediaType::StaticImage) {
+            self.refine_still();
+            return;
+        }
         if let Some(ref shared) = self.shared_frames {
             if let Ok(mut shared_frames) = shared.lock() {
-                if !shared_frames.is_empty() {
+                // The ring flag is raised before the first frame over the
+                // budget is queued, so checking it under the lock never keeps
+                // one of those frames.
+                if !self.in_frame_ring() && !shared_frames.is_empty() {
                     self.frames.extend(shared_frames.drain(..));
                 }
             }
         }
     }
 
+    fn in_frame_ring(&self) -> bool {
+        self.frame_ring
+            .as_ref()
+            .map(|flag| flag.load(Ordering::Acquire))
+            .unwrap_or(false)
+    }
+
+    /// Replace a progressive still's first pass with its full decode once
+    /// that arrives.
+    fn refine_still(&mut self) -> bool {
+        let refined = self
+            .shared_frames
+            .as_ref()
+            .and_then(|shared| shared.lock().ok()?.pop_back());
+        let Some(frame) = refined else {
+            return false;
+        };
+        self.frames = vec![frame];
+        true
+    }
+
     fn advance_frame(&mut self) -> bool {
+        if matches!(self.media_type, MediaType::VideoStream) {
+            return self.advance_video_stream_frame();
+        }
+        if matches!(self.media_type, MediaType::StaticImage) {
+            return self.refine_still();
+        }
+
         // Pull in any new frames from streaming decode
         self.sync_shared_frames();
+        if self.in_frame_ring() && self.current_frame + 1 >= self.frames.len() {
+            return self.advance_ring_frame();
+        }
 
         let frame_count = self.frames.len();
         if frame_count <= 1 {
             return false;
         }
 
+        // Under heavy system load, wait until two frames are due so every
+        // other one is shown, at the animation's normal speed.
+        if system_load::is_high() {
+            let delay_of = |index: usize| {
+                Duration::from_millis(effective_frame_delay_ms(
+                    &self.media_type,
+                    self.frames[index].delay_ms,
+                ) as u64)
+            };
+            let next = (self.current_frame + 1) % frame_count;
+            let due = delay_of(self.current_frame) + delay_of(next);
+            if self.last_frame_time.elapsed() < due {
+                return false;
+            }
+        }
+
         let fully_loaded = self.is_fully_loaded();
         let mut advanced = false;
 
@@ -181,11 +628,15 @@ impl MediaData {
                     self.current_frame = next;
                     self.last_frame_time += delay;
                     advanced = true;
-                } else if fully_loaded {
+                } else if fully_loaded && !self.is_last_play() {
                     // All frames decoded — safe to loop back to start
+                    self.plays_done += 1;
                     self.current_frame = 0;
                     self.last_frame_time += delay;
                     advanced = true;
+                } else if fully_loaded {
+                    // Not meant to repeat: stay on the final frame.
+                    break;
                 } else {
                     // Still streaming — pause on this frame until the next
                     // one arrives. Keep the next streamed frame immediately
@@ -209,6 +660,132 @@ impl MediaData {
         advanced
     }
 
+    /// Whether the animation is in the last of the plays its file asks for.
+    fn is_last_play(&self) -> bool {
+        self.plays
+            .map(|plays| self.plays_done + 1 >= plays)
+            .unwrap_or(false)
+    }
+
+    /// Video frames are consumed from the decode queue instead of being
+    /// retained, so memory stays bounded for arbitrarily long videos.
+    fn advance_video_stream_frame(&mut self) -> bool {
+        let Some(ref shared) = self.shared_frames else {
+            return false;
+        };
+        if self.frames.is_empty() {
+            return false;
+        }
+
+        let delay = Duration::from_millis(self.frames[0].delay_ms.max(1) as u64);
+        if self.last_frame_time.elapsed() < delay {
+            return false;
+        }
+
+        let next = shared.lock().ok().and_then(|mut queue| {
+            // Drop frames we are already late for instead of playing in slow motion.
+            while queue.len() > 1 && self.last_frame_time.elapsed() >= delay * 2 {
+                queue.pop_front();
+                self.last_frame_time += delay;
+            }
+            queue.pop_front()
+        });
+
+        let Some(frame) = next else {
+            return false;
+        };
+        self.frames[0] = frame;
+        self.last_frame_time += delay;
+        if self.last_frame_time.elapsed() > Duration::from_secs(1) {
+            self.last_frame_time = Instant::now();
+        }
+        true
+    }
+
+    /// Play an animation that outgrew `animation_memory_mb` from its frame
+    /// ring once the frames kept before that have played. Only the frame on
+    /// screen is kept, and frames the player is late for are dropped.
+    fn advance_ring_frame(&mut self) -> bool {
+        let Some(ref shared) = self.shared_frames else {
+            return false;
+        };
+        let delay = Duration::from_millis(effective_frame_delay_ms(
+            &self.media_type,
+            self.frames[self.current_frame].delay_ms,
+        ) as u64);
+        // Under heavy system load the queue loop below drops every other frame.
+        let due = if system_load::is_high() {
+            delay * 2
+        } else {
+            delay
+        };
+        if self.last_frame_time.elapsed() < due {
+            return false;
+        }
+
+        let mut dropped = 0;
+        let next = shared.lock().ok().and_then(|mut queue| {
+            while queue.len() > 1 && self.last_frame_time.elapsed() >= delay * 2 {
+                queue.pop_front();
+                self.last_frame_time += delay;
+                dropped += 1;
+            }
+            queue.pop_front()
+        });
+        let Some(frame) = next else {
+            return false;
+        };
+        let position = self.ring_position.unwrap_or(self.current_frame);
+        self.ring_position = Some(position + dropped + 1);
+        self.frames = vec![frame];
+        self.current_frame = 0;
+        self.loading_start = None;
+        self.last_frame_time += delay;
+        if self.last_frame_time.elapsed() > Duration::from_secs(1) {
+            self.last_frame_time = Instant::now();
+        }
+        true
+    }
+
+    /// Step `frames` frames forward, or back when negative, wrapping around
+    /// the frames decoded so far. Frames played from the ring are not kept,
+    /// so there it only steps forward, one frame at a time.
+    fn step_frames(&mut self, frames: i32) -> bool {
+        self.sync_shared_frames();
+        if self.in_frame_ring() && self.current_frame + 1 >= self.frames.len() {
+            if frames <= 0 {
+                return false;
+            }
+            let next = self
+                .shared_frames
+                .as_ref()
+                .and_then(|shared| shared.lock().ok()?.pop_front());
+            let Some(frame) = next else {
+                return false;
+            };
+            self.ring_position = Some(self.ring_position.unwrap_or(self.current_frame) + 1);
+            self.frames = vec![frame];
+            self.current_frame = 0;
+            return true;
+        }
+
+        let frame_count = self.frames.len() as i64;
+        if frame_count <= 1 {
+            return false;
+        }
+        let next = (self.current_frame as i64 + frames as i64).rem_euclid(frame_count) as usize;
+        let stepped = next != self.current_frame;
+        self.current_frame = next;
+        stepped
+    }
+
+    fn is_animation(&self) -> bool {
+        matches!(
+            self.media_type,
+            MediaType::AnimatedGif | MediaType::AnimatedWebP
+        )
+    }
+
     /// Returns true if this media is an animation still being decoded
     fn is_streaming(&self) -> bool {
         matches!(
@@ -217,6 +794,33 @@ impl MediaData {
         ) && !self.is_fully_loaded()
     }
 
+    /// How far through one play of the animation the frame on screen is and,
+    /// while it is still decoding, how much of it has been decoded, both as
+    /// fractions. `None` for stills, videos and animations of unknown length.
+    fn animation_progress(&self) -> Option<(f32, Option<f32>)> {
+        if !self.is_animation() {
+            return None;
+        }
+        let progress = self.decode_progress.as_deref();
+        let counted = progress
+            .map(|progress| progress.total.load(Ordering::Acquire))
+            .filter(|&total| total > 0);
+        let total = match counted {
+            Some(total) => total,
+            None if self.is_fully_loaded() && !self.in_frame_ring() => self.frames.len(),
+            None => return None,
+        };
+        if total <= 1 {
+            return None;
+        }
+        let position = self.ring_position.unwrap_or(self.current_frame) % total;
+        let played = (position + 1) as f32 / total as f32;
+        let decoded = progress
+            .filter(|_| !self.is_fully_loaded())
+            .map(|progress| progress.decoded.load(Ordering::Acquire) as f32 / total as f32);
+        Some((played, decoded))
+    }
+
     fn should_draw_streaming_overlay(&self) -> bool {
         if !self.is_streaming() || self.frames.len() > 1 {
             return false;
@@ -238,7 +842,8 @@ impl MediaData {
                 if let Some(start) = self.loading_start {
                     let elapsed_secs = start.elapsed().as_secs_f32();
                     let angle = elapsed_secs * 2.0 * std::f32::consts::PI * 1.2;
-                    self.frames[0].pixels = render_loading_frame(width, height, angle);
+                    Arc::make_mut(&mut self.frames[0]).pixels =
+                        render_loading_frame(width, height, angle);
                 }
             }
             self.last_frame_time = Instant::now();
@@ -247,843 +852,2581 @@ impl MediaData {
         false
     }
 
-    fn cancel_background_work(&mut self) {
-        if let Some(flag) = self.stream_cancel.take() {
-            flag.store(true, Ordering::Release);
-        }
+    /// Still images and animations whose frames have all been decoded can be
+    /// shown again later. Must be checked before cancelling background work,
+    /// since a cancelled decoder also reports itself as finished.
+    fn is_reusable(&mut self) -> bool {
+        self.sync_shared_frames();
+        matches!(
+            self.media_type,
+            MediaType::StaticImage | MediaType::AnimatedGif | MediaType::AnimatedWebP
+        ) && self.is_fully_loaded()
+            && !self.frames.is_empty()
+            && self.video_process.is_none()
     }
-}
 
-pub fn show_preview(path: &PathBuf, x: i32, y: i32) {
-    if let Ok(sender) = PREVIEW_SENDER.lock() {
-        if let Some(ref tx) = *sender {
-            let _ = tx.send(PreviewMessage::Show(path.clone(), x, y));
+    /// The action bound to `button` for this kind of preview: the left button
+    /// follows the per-type click action, the others their own binding.
+    /// External players and the loading spinner take no clicks.
+    fn click_action(&self, button: MouseButton) -> ClickAction {
+        if matches!(self.media_type, MediaType::Video | MediaType::Loading) {
+            return ClickAction::Nothing;
         }
+        CONFIG
+            .lock()
+            .map(|cfg| match button {
+                MouseButton::Left => match self.media_type {
+                    MediaType::StaticImage => cfg.click_action_image,
+                    MediaType::AnimatedGif | MediaType::AnimatedWebP => cfg.click_action_animation,
+                    _ => cfg.click_action_video,
+                },
+                MouseButton::Middle => cfg.mouse_middle,
+                MouseButton::Right => cfg.mouse_right,
+                MouseButton::X1 => cfg.mouse_x1,
+                MouseButton::X2 => cfg.mouse_x2,
+            })
+            .unwrap_or(ClickAction::Nothing)
     }
-}
 
-pub fn show_preview_keyboard(
-    path: &PathBuf,
-    item_left: i32,
-    item_top: i32,
-    item_right: i32,
-    item_bottom: i32,
-) {
-    if let Ok(sender) = PREVIEW_SENDER.lock() {
-        if let Some(ref tx) = *sender {
-            let _ = tx.send(PreviewMessage::ShowKeyboard(
-                path.clone(),
-                item_left,
-                item_top,
-                item_right,
-                item_bottom,
-            ));
+    /// The frame on screen, composed for painting, to fade out of when the
+    /// next preview replaces this one. Only stills and animations fade.
+    fn crossfade_source(&self, duration: Duration) -> Option<Crossfade> {
+        if !matches!(
+            self.media_type,
+            MediaType::StaticImage | MediaType::AnimatedGif | MediaType::AnimatedWebP
+        ) || self.video_process.is_some()
+        {
+            return None;
+        }
+        let (width, height) = (self.current_width(), self.current_height());
+        if width == 0 || height == 0 {
+            return None;
         }
+        Some(Crossfade {
+            pixels: compose_preview_pixels(
+                self.shown_pixels(),
+                width,
+                height,
+                current_transparent_background(),
+            ),
+            width,
+            height,
+            started: Instant::now(),
+            duration,
+        })
     }
-}
 
-pub fn hide_preview() {
-    unsafe {
-        let hwnd = HWND(PREVIEW_HWND.load(Ordering::SeqCst) as *mut _);
-        if !hwnd.is_invalid() {
-            let _ = ShowWindow(hwnd, SW_HIDE);
-        }
+    fn is_clickable(&self) -> bool {
+        MouseButton::ALL
+            .iter()
+            .any(|&button| self.click_action(button) != ClickAction::Nothing)
     }
 
-    if let Ok(mut current) = CURRENT_MEDIA.try_lock() {
-        if let Some(ref mut media) = *current {
-            media.cancel_background_work();
-            stop_video_playback(media);
-        }
-        *current = None;
-    } else {
-        let pid = VIDEO_PID.load(Ordering::SeqCst);
-        if pid != 0 {
-            unsafe {
-                if let Ok(process) = OpenProcess(PROCESS_TERMINATE, false, pid) {
-                    let _ = TerminateProcess(process, 1);
-                    let _ = windows::Win32::Foundation::CloseHandle(process);
-                }
-            }
-            VIDEO_HWND.store(0, Ordering::SeqCst);
-            VIDEO_PID.store(0, Ordering::SeqCst);
-        }
+    /// Whether the mouse wheel zooms into this preview. Blurred stills are
+    /// not zoomed, since the full-size file is not blurred.
+    fn is_zoomable(&self) -> bool {
+        matches!(self.media_type, MediaType::StaticImage)
+            && self.blurred.is_none()
+            && is_zoom_pan_enabled()
     }
 
-    if let Ok(sender) = PREVIEW_SENDER.lock() {
-        if let Some(ref tx) = *sender {
-            let _ = tx.send(PreviewMessage::Hide);
-        }
+    fn frame_bytes(&self) -> usize {
+        let blurred = self.blurred.as_ref().map(Vec::len).unwrap_or(0);
+        self.frames
+            .iter()
+            .map(|frame| frame.pixels.len())
+            .sum::<usize>()
+            + blurred
     }
-}
 
-pub fn refresh_preview() {
-    if let Ok(sender) = PREVIEW_SENDER.lock() {
-        if let Some(ref tx) = *sender {
-            let _ = tx.send(PreviewMessage::Refresh);
+    /// All the pixel memory this media holds: its frames, the frames queued
+    /// by its decoder or stream, and the full-size decode behind a zoom.
+    fn memory_bytes(&self) -> usize {
+        let queued = self
+            .shared_frames
+            .as_ref()
+            .and_then(|shared| shared.lock().ok())
+            .map(|queue| queue.iter().map(|frame| frame.pixels.len()).sum())
+            .unwrap_or(0);
+        let zoom = self
+            .zoom
+            .as_ref()
+            .map(|zoom| zoom.source.pixels.len())
+            .unwrap_or(0);
+        self.frame_bytes() + queued + zoom
+    }
+
+    fn cancel_background_work(&mut self) {
+        if let Some(flag) = self.stream_cancel.take() {
+            flag.store(true, Ordering::Release);
+        }
+        if let Some(pending) = self.scrub.pending.take() {
+            pending.cancel.store(true, Ordering::Release);
         }
     }
-}
 
-/// Check if cursor is currently over the IMAGE preview window only
-pub fn is_cursor_over_image_preview() -> bool {
-    unsafe {
-        use windows::Win32::Foundation::POINT;
-        use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, WindowFromPoint};
+    fn is_scrubbable(&self) -> bool {
+        matches!(self.media_type, MediaType::VideoStream) && self.stream_source.is_some()
+    }
 
-        let mut cursor_pos = POINT::default();
-        if GetCursorPos(&mut cursor_pos).is_err() {
+    /// Restart an in-process video stream at `fraction` of its duration. The
+    /// decoder starts on a worker and the current frame stays on screen until
+    /// `poll_scrub` swaps the new stream in; no other restart begins before
+    /// then. The sound stops until the cursor settles.
+    fn scrub_video_stream(&mut self, fraction: f32) -> bool {
+        if !matches!(self.media_type, MediaType::VideoStream) || self.scrub.pending.is_some() {
             return false;
         }
-
-        let hwnd_under_cursor = WindowFromPoint(cursor_pos);
-        if hwnd_under_cursor.is_invalid() {
+        let Some(source) = self.stream_source.as_ref() else {
             return false;
-        }
+        };
+        // Probed when the stream was loaded, never on the preview thread.
+        let Some(duration) = source.duration_secs else {
+            return false;
+        };
 
-        let hwnd_ptr = hwnd_under_cursor.0 as isize;
+        let position = video::scrub_position_secs(fraction, duration);
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (started_tx, started) = channel();
+        let worker_source = source.clone();
+        let worker_cancel = Arc::clone(&cancel);
+        std::thread::spawn(move || {
+            let queue = worker_source.spawn(position, Arc::clone(&worker_cancel));
+            if started_tx.send(queue).is_err() {
+                // The preview moved on while the decoder started.
+                worker_cancel.store(true, Ordering::Release);
+            }
+        });
+        self.scrub.pending = Some(PendingScrub {
+            position,
+            cancel,
+            started,
+        });
+        self.scrub.moved_at = Some(Instant::now());
 
-        let preview_hwnd = PREVIEW_HWND.load(Ordering::SeqCst);
-        preview_hwnd != 0 && hwnd_ptr == preview_hwnd
+        if let Some(mut process) = self.video_process.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+        true
     }
-}
 
-/// Check if cursor is currently over the VIDEO preview window (ffplay)
-/// Also checks by process ID to handle the race condition where the ffplay
-/// window exists but VIDEO_HWND hasn't been stored yet.
-pub fn is_cursor_over_video_preview() -> bool {
-    unsafe {
-        use windows::Win32::Foundation::POINT;
-        use windows::Win32::UI::WindowsAndMessaging::{
-            GetCursorPos, GetWindowThreadProcessId, WindowFromPoint,
+    /// Swap in the stream hover-scrub restarted once its decoder is up, and
+    /// play the sound again once the cursor has settled.
+    fn poll_scrub(&mut self) {
+        let started = match self.scrub.pending.as_ref().map(|p| p.started.try_recv()) {
+            Some(Ok(started)) => Some(started),
+            Some(Err(TryRecvError::Disconnected)) => Some(None),
+            Some(Err(TryRecvError::Empty)) | None => None,
         };
-
-        let mut cursor_pos = POINT::default();
-        if GetCursorPos(&mut cursor_pos).is_err() {
-            return false;
+        if let Some(started) = started {
+            let pending = self.scrub.pending.take();
+            if let (Some(pending), Some(queue)) = (pending, started) {
+                self.cancel_background_work();
+                self.stream_cancel = Some(pending.cancel);
+                self.shared_frames = Some(queue);
+                self.last_frame_time = Instant::now();
+                if let Some(source) = self.stream_source.as_ref() {
+                    let speed = source.playback.speed;
+                    self.playback_clock =
+                        Some(PlaybackClock::new(&source.path, pending.position, speed));
+                }
+                self.scrub.moved_at = Some(Instant::now());
+            }
         }
 
-        let hwnd_under_cursor = WindowFromPoint(cursor_pos);
-        if hwnd_under_cursor.is_invalid() {
-            return false;
+        let settled = self.scrub.pending.is_none()
+            && self
+                .scrub
+                .moved_at
+                .map(|at| at.elapsed() >= Duration::from_millis(VIDEO_SCRUB_AUDIO_SETTLE_MS))
+                .unwrap_or(false);
+        if !settled {
+            return;
         }
-
-        let hwnd_ptr = hwnd_under_cursor.0 as isize;
-
-        // Check by stored HWND
-        let video_hwnd = VIDEO_HWND.load(Ordering::SeqCst);
-        if video_hwnd != 0 && hwnd_ptr == video_hwnd {
-            return true;
+        self.scrub.moved_at = None;
+        if let (Some(source), Some(clock)) = (&self.stream_source, &self.playback_clock) {
+            let position = clock.played_secs();
+            self.video_process =
+                video::spawn_audio_player(&source.path, video_volume(), position, source.playback);
         }
+    }
 
-        // Also check by process ID — covers the race window where ffplay's
-        // window exists but VIDEO_HWND hasn't been discovered yet
-        let video_pid = VIDEO_PID.load(Ordering::SeqCst);
-        if video_pid != 0 {
-            let mut window_pid: u32 = 0;
-            GetWindowThreadProcessId(hwnd_under_cursor, Some(&mut window_pid));
-            if window_pid == video_pid {
-                return true;
+    /// Pause a video while it is parked: its player or audio process is
+    /// suspended and the playback clock stopped. An in-process stream's
+    /// decoder blocks on its own once the frame queue is full.
+    fn pause_playback(&mut self) -> bool {
+        if let Some(ref process) = self.video_process {
+            if !video::set_process_suspended(process, true) {
+                return false;
             }
         }
-
-        false
+        if let Some(clock) = self.playback_clock.as_mut() {
+            clock.pause();
+        }
+        true
     }
-}
 
-fn is_video_file(path: &PathBuf) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
-        .unwrap_or(false)
+    fn resume_playback(&mut self) {
+        if let Some(ref process) = self.video_process {
+            video::set_process_suspended(process, false);
+        }
+        if let Some(clock) = self.playback_clock.as_mut() {
+            clock.resume();
+        }
+        // Continue with the next queued frame instead of catching up.
+        self.last_frame_time = Instant::now();
+    }
 }
 
-fn is_gif_file(path: &PathBuf) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_lowercase() == "gif")
-        .unwrap_or(false)
+impl Drop for MediaData {
+    fn drop(&mut self) {
+        // Stale load results and replaced previews must not leave decoders or
+        // player processes (e.g. in-process video audio) running.
+        self.cancel_background_work();
+        if let Some(clock) = self.playback_clock.take() {
+            remember_playback_position(&clock);
+        }
+        if let Some(mut process) = self.video_process.take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
 }
 
-fn is_webp_file(path: &PathBuf) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.to_lowercase() == "webp")
-        .unwrap_or(false)
+/// Stop `media`'s background work and keep it in `MEDIA_CACHE` when it was
+/// fully decoded.
+fn retire_media(mut media: MediaData) {
+    let reusable = media.is_reusable();
+    media.cancel_background_work();
+    // The full-size decode behind a zoom is not worth keeping.
+    media.zoom = None;
+    let Some(key) = media.cache_key.clone().filter(|_| reusable) else {
+        return;
+    };
+    let max_entries = CONFIG
+        .lock()
+        .map(|config| config.memory_cache_entries as usize)
+        .unwrap_or(0);
+    if max_entries == 0 {
+        return;
+    }
+    if let Ok(mut cache) = MEDIA_CACHE.lock() {
+        cache.retain(|(cached, _)| *cached != key);
+        cache.push_back((key, media));
+        let sizes: Vec<usize> = cache.iter().map(|(_, media)| media.frame_bytes()).collect();
+        let max_bytes = memory_cap_bytes()
+            .map(|cap| cap.min(MEDIA_CACHE_MAX_BYTES))
+            .unwrap_or(MEDIA_CACHE_MAX_BYTES);
+        let evicted = media_cache_overflow(&sizes, max_entries, max_bytes);
+        cache.drain(..evicted);
+    }
 }
 
-fn is_confirm_file_type_enabled() -> bool {
-    CONFIG
+/// `memory_cap_mb` in bytes, or `None` when there is no cap.
+fn memory_cap_bytes() -> Option<usize> {
+    let megabytes = CONFIG
         .lock()
-        .map(|cfg| cfg.confirm_file_type)
-        .unwrap_or(false)
+        .map(|config| config.memory_cap_mb)
+        .unwrap_or(0);
+    (megabytes > 0).then(|| megabytes as usize * 1024 * 1024)
 }
 
-/// Guess image format from header bytes instead of file extension.
-fn guessed_image_format(path: &PathBuf) -> Option<image::ImageFormat> {
-    image::ImageReader::open(path)
-        .ok()?
-        .with_guessed_format()
-        .ok()?
-        .format()
+/// Memory held by the previews: the one shown, a parked video and
+/// `MEDIA_CACHE`.
+pub fn memory_usage_bytes() -> usize {
+    let parked = PARKED_PLAYER
+        .lock()
+        .ok()
+        .and_then(|parked| Some(parked.as_ref()?.media.memory_bytes()))
+        .unwrap_or(0);
+    let cached = MEDIA_CACHE
+        .lock()
+        .map(|cache| cache.iter().map(|(_, media)| media.frame_bytes()).sum())
+        .unwrap_or(0);
+    CURRENT_MEDIA_BYTES.load(Ordering::Acquire) + parked + cached
 }
 
-/// Decode an image by sniffing magic bytes instead of trusting the extension.
-fn decode_image_with_header_check(path: &PathBuf) -> Option<image::DynamicImage> {
-    image::ImageReader::open(path)
-        .ok()?
-        .with_guessed_format()
-        .ok()?
-        .decode()
+/// Drop the oldest `MEDIA_CACHE` entries while the previews hold more than
+/// `memory_cap_mb`. They are decoded again when next shown.
+fn trim_media_cache() {
+    let Some(cap) = memory_cap_bytes() else {
+        return;
+    };
+    let parked = PARKED_PLAYER
+        .try_lock()
         .ok()
+        .and_then(|parked| Some(parked.as_ref()?.media.memory_bytes()))
+        .unwrap_or(0);
+    let in_use = CURRENT_MEDIA_BYTES.load(Ordering::Acquire) + parked;
+    if let Ok(mut cache) = MEDIA_CACHE.try_lock() {
+        let sizes: Vec<usize> = cache.iter().map(|(_, media)| media.frame_bytes()).collect();
+        let evicted = memory_cap_overflow(&sizes, cap.saturating_sub(in_use));
+        cache.drain(..evicted);
+    }
 }
 
-/// Read image dimensions by sniffing magic bytes instead of trusting the extension.
-fn image_dimensions_with_header_check(path: &PathBuf) -> Option<(u32, u32)> {
-    image::ImageReader::open(path)
-        .ok()?
-        .with_guessed_format()
-        .ok()?
-        .into_dimensions()
-        .ok()
+/// How many of the oldest entries (sizes given oldest first) to drop so the
+/// rest fit in `budget` bytes, all of them if need be.
+fn memory_cap_overflow(sizes: &[usize], budget: usize) -> usize {
+    let mut total: usize = sizes.iter().sum();
+    sizes
+        .iter()
+        .take_while(|&&size| {
+            let over = total > budget;
+            total -= size;
+            over
+        })
+        .count()
 }
 
-/// Convert RGBA pixels to BGRA for Windows GDI
-fn rgba_to_bgra(rgba: &[u8]) -> Vec<u8> {
-    let mut bgra = Vec::with_capacity(rgba.len());
-    for chunk in rgba.chunks(4) {
-        if chunk.len() == 4 {
-            bgra.push(chunk[2]); // B
-            bgra.push(chunk[1]); // G
-            bgra.push(chunk[0]); // R
-            bgra.push(chunk[3]); // A
+/// How many of the oldest entries (sizes given oldest first) to drop so at
+/// most `max_entries` remain within `max_bytes`. The newest entry is kept even
+/// when it alone is over budget.
+fn media_cache_overflow(sizes: &[usize], max_entries: usize, max_bytes: usize) -> usize {
+    let mut total: usize = sizes.iter().sum();
+    let mut evicted = 0;
+    while evicted + 1 < sizes.len() {
+        if sizes.len() - evicted <= max_entries && total <= max_bytes {
+            break;
         }
+        total -= sizes[evicted];
+        evicted += 1;
     }
-    bgra
+    evicted
 }
 
-fn current_transparent_background() -> TransparentBackground {
-    CONFIG
+/// Take the cached media for `key` out of `MEDIA_CACHE`, rewound to its
+/// first frame.
+fn take_cached_media(key: &MediaCacheKey) -> Option<MediaData> {
+    let mut cache = MEDIA_CACHE.lock().ok()?;
+    let index = cache.iter().position(|(cached, _)| cached == key)?;
+    let (_, mut media) = cache.remove(index)?;
+    media.current_frame = 0;
+    media.plays_done = 0;
+    media.last_frame_time = Instant::now();
+    media.loading_start = None;
+    Some(media)
+}
+
+/// Decode `path` once ahead of its first hover, so a slow still is in the
+/// thumbnail cache by then. A progressive still's full decode is waited for.
+pub fn warm_thumbnail_cache(path: &PathBuf, max_width: u32, max_height: u32) {
+    let Some(key) = MediaCacheKey::new(path, max_width, max_height) else {
+        return;
+    };
+    let cached = MEDIA_CACHE
         .lock()
-        .map(|cfg| cfg.transparent_background)
-        .unwrap_or(TransparentBackground::Transparent)
+        .map(|cache| cache.iter().any(|(cached, _)| *cached == key))
+        .unwrap_or(true);
+    if cached {
+        return;
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    let Some(mut media) = load_media(path, max_width, max_height, cancel) else {
+        return;
+    };
+    let started = Instant::now();
+    while !media.is_reusable()
+        && RUNNING.load(Ordering::Acquire)
+        && started.elapsed() < Duration::from_millis(PREFETCH_ANIMATION_WAIT_MS)
+    {
+        std::thread::sleep(Duration::from_millis(50));
+    }
 }
 
-fn current_webp_playback_fps() -> u32 {
-    CONFIG
+/// Decode `path` for a host other than the preview window, such as Explorer's
+/// preview pane, through the same providers and caches.
+pub fn load_hosted_media(path: &PathBuf, max_width: u32, max_height: u32) -> Option<MediaData> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    load_media(path, max_width, max_height, cancel)
+}
+
+/// Decode `path` into `MEDIA_CACHE` ahead of a hover, waiting for animations
+/// to finish decoding. Gives up when `cancel` is set.
+pub fn prefetch_media(path: &PathBuf, max_width: u32, max_height: u32, cancel: &Arc<AtomicBool>) {
+    let Some(key) = MediaCacheKey::new(path, max_width, max_height) else {
+        return;
+    };
+    let cached = MEDIA_CACHE
         .lock()
-        .map(|cfg| sanitize_webp_playback_fps(cfg.webp_playback_fps))
-        .unwrap_or(DEFAULT_WEBP_PLAYBACK_FPS)
+        .map(|cache| cache.iter().any(|(cached, _)| *cached == key))
+        .unwrap_or(true);
+    if cached {
+        return;
+    }
+    let Some(mut media) = load_media(path, max_width, max_height, Arc::clone(cancel)) else {
+        return;
+    };
+    let started = Instant::now();
+    while !media.is_reusable()
+        && !cancel.load(Ordering::Acquire)
+        && started.elapsed() < Duration::from_millis(PREFETCH_ANIMATION_WAIT_MS)
+    {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    // A cancelled decoder reports itself finished with only part of its frames.
+    if !cancel.load(Ordering::Acquire) {
+        retire_media(media);
+    }
 }
 
-fn effective_frame_delay_ms(media_type: &MediaType, source_delay_ms: u32) -> u32 {
-    match media_type {
-        MediaType::AnimatedWebP => {
-            let fps = current_webp_playback_fps();
-            let min_delay_ms = (1000 / fps).max(1);
-            source_delay_ms.max(min_delay_ms)
+fn remember_playback_position(clock: &PlaybackClock) {
+    if let Ok(mut positions) = VIDEO_RESUME_POSITIONS.lock() {
+        if !positions.contains_key(&clock.path)
+            && positions.len() >= VIDEO_GEOMETRY_CACHE_MAX_ENTRIES
+        {
+            positions.clear();
         }
-        _ => source_delay_ms,
+        positions.insert(clock.path.clone(), clock.played_secs());
     }
 }
 
-fn checkerboard_color(x: u32, y: u32) -> (u8, u8, u8) {
-    if ((x / 16) + (y / 16)) % 2 == 0 {
-        (224, 224, 224)
-    } else {
-        (144, 144, 144)
+/// Where to start `path`: the position it was left at earlier in the session,
+/// or 0:00. `duration` is only asked for when there is a position to resume.
+fn resume_start_secs(path: &PathBuf, duration: impl FnOnce() -> Option<f64>) -> f64 {
+    let (enabled, looping) = CONFIG
+        .lock()
+        .map(|c| (c.video_resume, c.video_loop))
+        .unwrap_or((true, true));
+    if !enabled {
+        return 0.0;
     }
+    let Some(played) = VIDEO_RESUME_POSITIONS
+        .lock()
+        .ok()
+        .and_then(|positions| positions.get(path).copied())
+    else {
+        return 0.0;
+    };
+    duration()
+        .and_then(|duration| video::resume_position_secs(played, duration, looping))
+        .unwrap_or(0.0)
 }
 
-fn compose_preview_pixels(
-    bgra: &[u8],
-    width: u32,
-    height: u32,
-    background: TransparentBackground,
-) -> Vec<u8> {
-    let mut out = Vec::with_capacity(bgra.len());
+pub fn show_preview(path: &PathBuf, x: i32, y: i32) {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Show(path.clone(), x, y, None));
+        }
+    }
+}
 
-    for (idx, px) in bgra.chunks(4).enumerate() {
-        if px.len() != 4 {
-            continue;
+/// Show a file hovered at `x`, `y` over `item`, keeping the preview clear of
+/// the item; with `anchor_to_item`, right beside it.
+pub fn show_preview_at_item(path: &PathBuf, x: i32, y: i32, item: ScreenBounds) {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Show(path.clone(), x, y, Some(item)));
         }
+    }
+}
 
-        let b = px[0] as u32;
-        let g = px[1] as u32;
-        let r = px[2] as u32;
-        let a = px[3] as u32;
+pub fn show_preview_keyboard(
+    path: &PathBuf,
+    item_left: i32,
+    item_top: i32,
+    item_right: i32,
+    item_bottom: i32,
+) {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::ShowKeyboard(
+                path.clone(),
+                item_left,
+                item_top,
+                item_right,
+                item_bottom,
+            ));
+        }
+    }
+}
 
-        match background {
-            TransparentBackground::Transparent => {
-                out.push(((b * a + 127) / 255) as u8);
-                out.push(((g * a + 127) / 255) as u8);
-                out.push(((r * a + 127) / 255) as u8);
-                out.push(a as u8);
-            }
-            TransparentBackground::Black
-            | TransparentBackground::White
-            | TransparentBackground::Checkerboard => {
-                let x = (idx as u32) % width;
-                let y = (idx as u32) / width;
-                let (bg_b, bg_g, bg_r) = match background {
-                    TransparentBackground::Black => (0, 0, 0),
-                    TransparentBackground::White => (255, 255, 255),
-                    TransparentBackground::Checkerboard => checkerboard_color(x, y),
-                    TransparentBackground::Transparent => unreachable!(),
-                };
-                let inv_a = 255 - a;
+pub fn hide_preview() {
+    VIDEO_SCRUB_AVAILABLE.store(false, Ordering::Release);
+    PREVIEW_CLICKABLE.store(false, Ordering::Release);
+    unsafe {
+        // With a fade, the preview thread fades the window out when it
+        // handles Hide.
+        let hwnd = HWND(PREVIEW_HWND.load(Ordering::SeqCst) as *mut _);
+        if !hwnd.is_invalid() && window_fade_duration().is_zero() {
+            let _ = ShowWindow(hwnd, SW_HIDE);
+        }
+    }
+    hide_video_host();
 
-                out.push(((b * a + (bg_b as u32) * inv_a + 127) / 255) as u8);
-                out.push(((g * a + (bg_g as u32) * inv_a + 127) / 255) as u8);
-                out.push(((r * a + (bg_r as u32) * inv_a + 127) / 255) as u8);
-                out.push(255);
+    if let Ok(mut current) = CURRENT_MEDIA.try_lock() {
+        // A video that can be parked is left playing for the preview thread,
+        // which parks it when it handles Hide.
+        let parking = current
+            .as_ref()
+            .map(|media| !parking_time(media).is_zero())
+            .unwrap_or(false);
+        if !parking {
+            if let Some(ref mut media) = *current {
+                media.cancel_background_work();
+                stop_video_playback(media);
+            }
+            *current = None;
+        }
+    } else {
+        let pid = VIDEO_PID.load(Ordering::SeqCst);
+        if pid != 0 {
+            unsafe {
+                if let Ok(process) = OpenProcess(PROCESS_TERMINATE, false, pid) {
+                    let _ = TerminateProcess(process, 1);
+                    let _ = windows::Win32::Foundation::CloseHandle(process);
+                }
             }
+            VIDEO_HWND.store(0, Ordering::SeqCst);
+            VIDEO_PID.store(0, Ordering::SeqCst);
         }
     }
 
-    let expected = width as usize * height as usize * 4;
-    if out.len() < expected {
-        out.resize(expected, 0);
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Hide);
+        }
     }
-
-    out
 }
 
-/// Scale image dimensions to fit within max bounds while maintaining aspect ratio
-fn scale_dimensions(
-    orig_width: u32,
-    orig_height: u32,
-    max_width: u32,
-    max_height: u32,
-) -> (u32, u32) {
-    if orig_width <= max_width && orig_height <= max_height {
-        return (orig_width, orig_height);
+pub fn refresh_preview() {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Refresh);
+        }
     }
+}
+
+/// Track `reveal_key`, redrawing a blurred preview when it changes.
+pub fn set_reveal_held(held: bool) {
+    if REVEAL_HELD.swap(held, Ordering::AcqRel) != held {
+        refresh_preview();
+    }
+}
+
+/// Track `exif_key`, redrawing the preview when it changes.
+pub fn set_exif_held(held: bool) {
+    if EXIF_HELD.swap(held, Ordering::AcqRel) != held {
+        refresh_preview();
+    }
+}
+
+pub fn pin_preview() {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Pin);
+        }
+    }
+}
+
+pub fn favorite_preview() {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Favorite);
+        }
+    }
+}
+
+/// Pause the animation on screen, or resume it where it was paused.
+pub fn pause_or_resume_animation() {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::TogglePause);
+        }
+    }
+}
+
+/// Step the paused animation on screen by `frames` frames.
+pub fn step_animation(frames: i32) {
+    if let Ok(sender) = PREVIEW_SENDER.lock() {
+        if let Some(ref tx) = *sender {
+            let _ = tx.send(PreviewMessage::Step(frames));
+        }
+    }
+}
+
+pub fn is_animation_paused() -> bool {
+    ANIMATION_PAUSED.load(Ordering::Acquire)
+}
+
+/// Pause or resume the animation on screen. Resuming plays on from the frame
+/// shown instead of catching up on the time spent paused.
+fn toggle_animation_pause() {
+    let Ok(mut current) = CURRENT_MEDIA.lock() else {
+        return;
+    };
+    let Some(media) = current.as_mut().filter(|media| media.is_animation()) else {
+        return;
+    };
+    let paused = !ANIMATION_PAUSED.load(Ordering::Acquire);
+    ANIMATION_PAUSED.store(paused, Ordering::Release);
+    media.last_frame_time = Instant::now();
+}
+
+/// Open a pinned window with the frame currently on screen, over the preview.
+unsafe fn pin_current_frame(hwnd: HWND, path: &Path) -> bool {
+    let frame = CURRENT_MEDIA.lock().ok().and_then(|current| {
+        let media = current.as_ref()?;
+        if matches!(media.media_type, MediaType::Loading) {
+            return None;
+        }
+        Some(ImageFrame {
+            pixels: media.shown_pixels().to_vec(),
+            width: media.current_width(),
+            height: media.current_height(),
+            delay_ms: 0,
+        })
+    });
+    let Some(frame) = frame else {
+        return false;
+    };
+
+    let mut rect = RECT::default();
+    let _ = GetWindowRect(hwnd, &mut rect);
+    pinned::open(path, &frame, rect.left, rect.top);
+    true
+}
+
+/// Check if cursor is currently over the IMAGE preview window only
+pub fn is_cursor_over_image_preview() -> bool {
+    unsafe {
+        use windows::Win32::Foundation::POINT;
+        use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, WindowFromPoint};
+
+        let mut cursor_pos = POINT::default();
+        if GetCursorPos(&mut cursor_pos).is_err() {
+            return false;
+        }
+
+        let hwnd_under_cursor = WindowFromPoint(cursor_pos);
+        if hwnd_under_cursor.is_invalid() {
+            return false;
+        }
+
+        let hwnd_ptr = hwnd_under_cursor.0 as isize;
+
+        let preview_hwnd = PREVIEW_HWND.load(Ordering::SeqCst);
+        preview_hwnd != 0 && hwnd_ptr == preview_hwnd
+    }
+}
+
+/// Check if the cursor is over an in-process video preview with hover-scrub
+/// enabled. The Explorer hook keeps such previews open instead of dismissing.
+pub fn is_cursor_over_scrubbable_preview() -> bool {
+    VIDEO_SCRUB_AVAILABLE.load(Ordering::Acquire) && is_cursor_over_image_preview()
+}
+
+/// Check if the cursor is over a preview with a click action or zoom. The
+/// Explorer hook keeps such previews open so they can be clicked or zoomed.
+pub fn is_cursor_over_clickable_preview() -> bool {
+    PREVIEW_CLICKABLE.load(Ordering::Acquire) && is_cursor_over_image_preview()
+}
+
+/// Carry out the action bound to `button` for the preview on screen.
+unsafe fn run_click_action(hwnd: HWND, path: &Path, button: MouseButton) {
+    let action = CURRENT_MEDIA
+        .lock()
+        .ok()
+        .and_then(|current| current.as_ref().map(|media| media.click_action(button)))
+        .unwrap_or(ClickAction::Nothing);
+    match action {
+        ClickAction::Nothing => {}
+        ClickAction::Open => {
+            shell_execute(w!("open"), path.as_os_str(), None);
+            hide_preview();
+        }
+        ClickAction::OpenWith => {
+            shell_execute(w!("openas"), path.as_os_str(), None);
+            hide_preview();
+        }
+        ClickAction::OpenFolder => {
+            show_in_folder(path);
+            hide_preview();
+        }
+        ClickAction::CopyPath => {
+            let _ = pinned::copy_text_to_clipboard(hwnd, &path.to_string_lossy());
+        }
+        ClickAction::CopyImage => {
+            let image = CURRENT_MEDIA.lock().ok().and_then(|current| {
+                let media = current.as_ref()?;
+                // Swapping red and blue back turns the BGRA frame into RGBA.
+                let rgba = rgba_to_bgra(media.shown_pixels());
+                image::RgbaImage::from_raw(media.current_width(), media.current_height(), rgba)
+            });
+            if let Some(image) = image {
+                let _ = pinned::copy_to_clipboard(hwnd, &image);
+            }
+        }
+        ClickAction::Pin => {
+            if pin_current_frame(hwnd, path) {
+                hide_preview();
+            }
+        }
+        ClickAction::Close => hide_preview(),
+        ClickAction::Next | ClickAction::Previous => {
+            let mut cursor = POINT::default();
+            let _ = windows::Win32::UI::WindowsAndMessaging::GetCursorPos(&mut cursor);
+            if let Some(sibling) = prefetch::sibling(path, action == ClickAction::Next) {
+                show_preview(&sibling, cursor.x, cursor.y);
+            }
+        }
+    }
+}
+
+/// Add the preview on screen to the session history.
+fn record_history(path: &Path) {
+    if let Some(entry) = shown_entry(path) {
+        history::record(entry);
+    }
+}
+
+/// Describe the preview on screen to screen readers and automation tools.
+fn publish_preview_info(hwnd: HWND, path: &Path) {
+    let info = CURRENT_MEDIA.lock().ok().and_then(|current| {
+        let media = current.as_ref()?;
+        let frame = media.frames.get(media.current_frame)?;
+        let kind = match media.media_type {
+            MediaType::StaticImage if media.deferred_playback => "video",
+            MediaType::StaticImage => "image",
+            MediaType::AnimatedGif | MediaType::AnimatedWebP => "animation",
+            MediaType::Video | MediaType::VideoStream => "video",
+            MediaType::Loading => return None,
+        };
+        Some(automation::PreviewInfo {
+            path: path.to_path_buf(),
+            kind,
+            width: frame.width,
+            height: frame.height,
+        })
+    });
+    automation::publish(hwnd, info.as_ref());
+}
+
+/// Star or unstar the previewed file, keeping the frame on screen as its
+/// thumbnail.
+fn toggle_favorite(path: &Path) {
+    if let Some(entry) = shown_entry(path) {
+        favorites::toggle(entry);
+    }
+}
+
+/// A gallery entry for `path` with a thumbnail of the frame on screen, or none
+/// for previews without frames of their own. Explicit previews get their
+/// blurred frame whatever the reveal key's state.
+fn shown_entry(path: &Path) -> Option<gallery::Entry> {
+    let current = CURRENT_MEDIA.lock().ok()?;
+    let media = current.as_ref()?;
+    let thumbnail = media.frames.get(media.current_frame).and_then(|frame| {
+        let pixels = media.blurred.as_deref().unwrap_or(&frame.pixels);
+        gallery::Thumbnail::from_frame(pixels, frame.width, frame.height)
+    });
+    Some(gallery::Entry {
+        path: path.to_path_buf(),
+        thumbnail,
+        blurred: media.blurred.is_some(),
+    })
+}
+
+/// Open an Explorer window on the folder of `path` with the file selected.
+pub unsafe fn show_in_folder(path: &Path) {
+    let mut select = OsString::from("/select,\"");
+    select.push(path.as_os_str());
+    select.push("\"");
+    shell_execute(w!("open"), OsStr::new("explorer.exe"), Some(&select));
+}
+
+pub unsafe fn shell_execute(verb: PCWSTR, file: &OsStr, parameters: Option<&OsStr>) {
+    let wide =
+        |value: &OsStr| -> Vec<u16> { value.encode_wide().chain(std::iter::once(0)).collect() };
+    let file = wide(file);
+    let parameters = parameters.map(wide);
+    let _ = ShellExecuteW(
+        HWND(ptr::null_mut()),
+        verb,
+        PCWSTR(file.as_ptr()),
+        parameters
+            .as_ref()
+            .map(|parameters| PCWSTR(parameters.as_ptr()))
+            .unwrap_or(PCWSTR::null()),
+        PCWSTR::null(),
+        SW_SHOWNORMAL,
+    );
+}
+
+/// Horizontal cursor position within the preview window, 0.0 at the left edge.
+fn cursor_fraction_over_preview(hwnd: HWND) -> Option<f32> {
+    unsafe {
+        use windows::Win32::UI::WindowsAndMessaging::{GetCursorPos, WindowFromPoint};
+
+        let mut cursor_pos = POINT::default();
+        GetCursorPos(&mut cursor_pos).ok()?;
+        if WindowFromPoint(cursor_pos) != hwnd {
+            return None;
+        }
+
+        let mut rect = RECT::default();
+        GetWindowRect(hwnd, &mut rect).ok()?;
+        let width = rect.right - rect.left;
+        if width <= 1 {
+            return None;
+        }
+        Some((cursor_pos.x - rect.left) as f32 / (width - 1) as f32)
+    }
+}
+
+fn is_zoom_pan_enabled() -> bool {
+    CONFIG.lock().map(|cfg| cfg.zoom_pan).unwrap_or(false)
+}
+
+fn is_animation_enabled() -> bool {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.preview_animations)
+        .unwrap_or(true)
+}
+
+/// The `video_volume` videos play at, 0 while `play_video_audio` is off.
+fn video_volume() -> u32 {
+    CONFIG
+        .lock()
+        .map(|cfg| {
+            if cfg.play_video_audio {
+                cfg.video_volume
+            } else {
+                0
+            }
+        })
+        .unwrap_or(0)
+}
+
+fn is_video_hover_scrub_enabled() -> bool {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.video_hover_scrub)
+        .unwrap_or(false)
+}
+
+/// Check if cursor is currently over the VIDEO preview window (ffplay)
+/// Also checks by process ID to handle the race condition where the ffplay
+/// window exists but VIDEO_HWND hasn't been stored yet.
+pub fn is_cursor_over_video_preview() -> bool {
+    unsafe {
+        use windows::Win32::Foundation::POINT;
+        use windows::Win32::UI::WindowsAndMessaging::{
+            GetCursorPos, GetWindowThreadProcessId, WindowFromPoint,
+        };
+
+        let mut cursor_pos = POINT::default();
+        if GetCursorPos(&mut cursor_pos).is_err() {
+            return false;
+        }
+
+        let hwnd_under_cursor = WindowFromPoint(cursor_pos);
+        if hwnd_under_cursor.is_invalid() {
+            return false;
+        }
+
+        let hwnd_ptr = hwnd_under_cursor.0 as isize;
+
+        // Check by stored HWND
+        let video_hwnd = VIDEO_HWND.load(Ordering::SeqCst);
+        if video_hwnd != 0 && hwnd_ptr == video_hwnd {
+            return true;
+        }
+
+        // mpv draws into a child window of our host
+        let host = VIDEO_HOST_HWND.load(Ordering::SeqCst);
+        if host != 0
+            && video_hwnd == host
+            && IsChild(HWND(host as *mut _), hwnd_under_cursor).as_bool()
+        {
+            return true;
+        }
+
+        // Also check by process ID — covers the race window where ffplay's
+        // window exists but VIDEO_HWND hasn't been discovered yet
+        let video_pid = VIDEO_PID.load(Ordering::SeqCst);
+        if video_pid != 0 {
+            let mut window_pid: u32 = 0;
+            GetWindowThreadProcessId(hwnd_under_cursor, Some(&mut window_pid));
+            if window_pid == video_pid {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+fn is_video_file(path: &PathBuf) -> bool {
+    providers::is_video(path)
+}
+
+fn is_gif_file(path: &PathBuf) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase() == "gif")
+        .unwrap_or(false)
+}
+
+fn is_webp_file(path: &PathBuf) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase() == "webp")
+        .unwrap_or(false)
+}
+
+fn is_jpeg_file(path: &PathBuf) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_lowercase().as_str(), "jpg" | "jpeg" | "jpe" | "jfif"))
+        .unwrap_or(false)
+}
+
+fn is_confirm_file_type_enabled() -> bool {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.confirm_file_type)
+        .unwrap_or(false)
+}
+
+/// Guess image format from header bytes instead of file extension.
+fn guessed_image_format(path: &PathBuf) -> Option<image::ImageFormat> {
+    image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .format()
+}
+
+/// Decode an image by sniffing magic bytes instead of trusting the extension.
+fn decode_image_with_header_check(path: &PathBuf) -> Option<image::DynamicImage> {
+    image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .decode()
+        .ok()
+}
+
+/// Read image dimensions by sniffing magic bytes instead of trusting the extension.
+fn image_dimensions_with_header_check(path: &PathBuf) -> Option<(u32, u32)> {
+    image::ImageReader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Convert RGBA pixels to BGRA for Windows GDI
+pub fn rgba_to_bgra(rgba: &[u8]) -> Vec<u8> {
+    let mut bgra = rgba[..rgba.len() / 4 * 4].to_vec();
+    if bgra.len() >= PARALLEL_MIN_BYTES {
+        bgra.par_chunks_mut(PARALLEL_CHUNK_BYTES)
+            .for_each(swap_red_blue);
+    } else {
+        swap_red_blue(&mut bgra);
+    }
+    bgra
+}
+
+/// Swap the first and third byte of every pixel. Working on whole `u32`s
+/// lets the compiler turn the loop into vector shuffles.
+fn swap_red_blue(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let value = u32::from_le_bytes([px[0], px[1], px[2], px[3]]);
+        let swapped = (value & 0xFF00_FF00) | ((value & 0xFF) << 16) | ((value >> 16) & 0xFF);
+        px.copy_from_slice(&swapped.to_le_bytes());
+    }
+}
+
+/// Nearest-neighbour scaling of 4-byte pixels in any channel order, one
+/// output row per rayon task.
+fn resize_nearest(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    target_width: u32,
+    target_height: u32,
+) -> Option<Vec<u8>> {
+    if pixels.len() != width as usize * height as usize * 4 || width == 0 || height == 0 {
+        return None;
+    }
+    let source_x: Vec<usize> = (0..target_width)
+        .map(|x| nearest_source(x, width, target_width) * 4)
+        .collect();
+    let row_bytes = target_width as usize * 4;
+    let mut scaled = vec![0u8; row_bytes * target_height as usize];
+    if row_bytes == 0 {
+        return Some(scaled);
+    }
+    scaled
+        .par_chunks_mut(row_bytes)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let source_row = nearest_source(y as u32, height, target_height) * width as usize * 4;
+            let source_row = &pixels[source_row..source_row + width as usize * 4];
+            for (target, &x) in row.chunks_exact_mut(4).zip(&source_x) {
+                target.copy_from_slice(&source_row[x..x + 4]);
+            }
+        });
+    Some(scaled)
+}
+
+/// The source index whose center is nearest to the center of `target`.
+fn nearest_source(target: u32, source_len: u32, target_len: u32) -> usize {
+    let source = (target as u64 * 2 + 1) * source_len as u64 / (target_len as u64 * 2);
+    source.min(source_len as u64 - 1) as usize
+}
+
+fn current_transparent_background() -> TransparentBackground {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.transparent_background)
+        .unwrap_or(TransparentBackground::Transparent)
+}
+
+fn animation_progress_bar_enabled() -> bool {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.animation_progress_bar)
+        .unwrap_or(true)
+}
+
+/// The fixed panel's layout for `orig_dims`, when `fixed_panel` is on.
+fn fixed_panel_layout(orig_dims: (u32, u32), bounds: ScreenBounds) -> Option<PreviewLayout> {
+    let (dock, size) = CONFIG
+        .lock()
+        .ok()
+        .filter(|c| c.fixed_panel)
+        .map(|c| (c.panel_dock, (c.panel_width, c.panel_height)))?;
+    compute_panel_layout(dock, size, orig_dims, bounds)
+}
+
+fn current_matte_color() -> [u8; 3] {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.matte_color)
+        .unwrap_or([0x80, 0x80, 0x80])
+}
+
+fn current_webp_playback_fps() -> u32 {
+    CONFIG
+        .lock()
+        .map(|cfg| sanitize_webp_playback_fps(cfg.webp_playback_fps))
+        .unwrap_or(DEFAULT_WEBP_PLAYBACK_FPS)
+}
+
+fn effective_frame_delay_ms(media_type: &MediaType, source_delay_ms: u32) -> u32 {
+    match media_type {
+        MediaType::AnimatedWebP => {
+            let fps = current_webp_playback_fps();
+            let min_delay_ms = (1000 / fps).max(1);
+            source_delay_ms.max(min_delay_ms)
+        }
+        _ => source_delay_ms,
+    }
+}
+
+/// Scale the color channels of RGBA or BGRA pixels by their alpha.
+fn premultiply_alpha(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let alpha = px[3] as u32;
+        for channel in px.iter_mut().take(3) {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Undo `premultiply_alpha`. Fully transparent pixels become black.
+fn unpremultiply_alpha(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let alpha = px[3] as u32;
+        for channel in px.iter_mut().take(3) {
+            *channel = match alpha {
+                0 => 0,
+                _ => ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8,
+            };
+        }
+    }
+}
+
+/// A frame flattened for windows without per-pixel alpha, such as pinned
+/// previews: over the configured background, or the checkerboard when that is
+/// `transparent`.
+pub fn opaque_preview_pixels(bgra: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let background = match current_transparent_background() {
+        TransparentBackground::Transparent => TransparentBackground::Checkerboard,
+        background => background,
+    };
+    compose_preview_pixels(bgra, width, height, background)
+}
+
+fn checkerboard_color(x: u32, y: u32) -> (u8, u8, u8) {
+    if ((x / 16) + (y / 16)) % 2 == 0 {
+        (224, 224, 224)
+    } else {
+        (144, 144, 144)
+    }
+}
+
+fn compose_preview_pixels(
+    bgra: &[u8],
+    width: u32,
+    height: u32,
+    background: TransparentBackground,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bgra.len());
+    let [matte_r, matte_g, matte_b] = match background {
+        TransparentBackground::Matte => current_matte_color(),
+        _ => [0, 0, 0],
+    };
+
+    for (idx, px) in bgra.chunks(4).enumerate() {
+        if px.len() != 4 {
+            continue;
+        }
+
+        let b = px[0] as u32;
+        let g = px[1] as u32;
+        let r = px[2] as u32;
+        let a = px[3] as u32;
+
+        match background {
+            TransparentBackground::Transparent => {
+                out.push(((b * a + 127) / 255) as u8);
+                out.push(((g * a + 127) / 255) as u8);
+                out.push(((r * a + 127) / 255) as u8);
+                out.push(a as u8);
+            }
+            TransparentBackground::Black
+            | TransparentBackground::White
+            | TransparentBackground::Checkerboard
+            | TransparentBackground::Matte => {
+                let x = (idx as u32) % width;
+                let y = (idx as u32) / width;
+                let (bg_b, bg_g, bg_r) = match background {
+                    TransparentBackground::Black => (0, 0, 0),
+                    TransparentBackground::White => (255, 255, 255),
+                    TransparentBackground::Checkerboard => checkerboard_color(x, y),
+                    TransparentBackground::Matte => (matte_b, matte_g, matte_r),
+                    TransparentBackground::Transparent => unreachable!(),
+                };
+                let inv_a = 255 - a;
+
+                out.push(((b * a + (bg_b as u32) * inv_a + 127) / 255) as u8);
+                out.push(((g * a + (bg_g as u32) * inv_a + 127) / 255) as u8);
+                out.push(((r * a + (bg_r as u32) * inv_a + 127) / 255) as u8);
+                out.push(255);
+            }
+        }
+    }
+
+    let expected = width as usize * height as usize * 4;
+    if out.len() < expected {
+        out.resize(expected, 0);
+    }
+
+    out
+}
+
+/// Scale image dimensions to fit within max bounds while maintaining aspect ratio
+pub fn scale_dimensions(
+    orig_width: u32,
+    orig_height: u32,
+    max_width: u32,
+    max_height: u32,
+) -> (u32, u32) {
+    if orig_width <= max_width && orig_height <= max_height {
+        return (orig_width, orig_height);
+    }
+
+    let scale_x = max_width as f32 / orig_width as f32;
+    let scale_y = max_height as f32 / orig_height as f32;
+    let scale = scale_x.min(scale_y);
+
+    let new_width = (orig_width as f32 * scale).max(1.0) as u32;
+    let new_height = (orig_height as f32 * scale).max(1.0) as u32;
+
+    (new_width, new_height)
+}
+
+/// Decode a single GIF frame from canvas to an ImageFrame
+fn decode_gif_frame_to_image(
+    canvas: &[u8],
+    gif_width: u32,
+    gif_height: u32,
+    target_width: u32,
+    target_height: u32,
+    delay_ms: u32,
+) -> Option<ImageFrame> {
+    let bgra = if target_width != gif_width || target_height != gif_height {
+        let scaled = resize_nearest(canvas, gif_width, gif_height, target_width, target_height)?;
+        rgba_to_bgra(&scaled)
+    } else {
+        rgba_to_bgra(canvas)
+    };
+
+    Some(ImageFrame {
+        pixels: bgra,
+        width: target_width,
+        height: target_height,
+        delay_ms,
+    })
+}
+
+/// The RGBA canvas an animated GIF's frames are drawn on, carrying each
+/// frame's disposal over to the next one.
+struct GifCanvas {
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// How to clean up after the last drawn frame, and the area it covered.
+    disposal: Option<(gif::DisposalMethod, (usize, usize, usize, usize))>,
+    /// The canvas from before the last drawn frame, when it asked to be undone.
+    previous: Option<Vec<u8>>,
+}
+
+impl GifCanvas {
+    fn new(width: u32, height: u32) -> Self {
+        GifCanvas {
+            pixels: vec![0u8; (width * height * 4) as usize],
+            width,
+            height,
+            disposal: None,
+            previous: None,
+        }
+    }
+
+    /// Dispose of the last frame as it asked, then draw `frame`.
+    fn draw(&mut self, frame: &gif::Frame) {
+        match self.disposal.take() {
+            Some((gif::DisposalMethod::Background, area)) => self.clear(area),
+            Some((gif::DisposalMethod::Previous, _)) => {
+                if let Some(previous) = self.previous.take() {
+                    self.pixels = previous;
+                }
+            }
+            _ => {}
+        }
+
+        if frame.dispose == gif::DisposalMethod::Previous {
+            self.previous = Some(self.pixels.clone());
+        }
+        composite_gif_frame(&mut self.pixels, frame, self.width, self.height);
+        let area = (
+            frame.left as usize,
+            frame.top as usize,
+            frame.width as usize,
+            frame.height as usize,
+        );
+        self.disposal = Some((frame.dispose, area));
+    }
+
+    /// Make `(left, top, width, height)` transparent. Viewers clear to
+    /// transparency rather than the background color, and so does this.
+    fn clear(&mut self, (left, top, width, height): (usize, usize, usize, usize)) {
+        let (canvas_w, canvas_h) = (self.width as usize, self.height as usize);
+        let right = (left + width).min(canvas_w);
+        for y in top..(top + height).min(canvas_h) {
+            if left < right {
+                self.pixels[(y * canvas_w + left) * 4..(y * canvas_w + right) * 4].fill(0);
+            }
+        }
+    }
+}
+
+/// Composite a GIF frame onto the canvas
+fn composite_gif_frame(canvas: &mut [u8], frame: &gif::Frame, gif_width: u32, gif_height: u32) {
+    let frame_x = frame.left as usize;
+    let frame_y = frame.top as usize;
+    let frame_w = frame.width as usize;
+    let frame_h = frame.height as usize;
+
+    for y in 0..frame_h {
+        for x in 0..frame_w {
+            let src_idx = (y * frame_w + x) * 4;
+            let dst_x = frame_x + x;
+            let dst_y = frame_y + y;
+            if dst_x < gif_width as usize && dst_y < gif_height as usize {
+                let dst_idx = (dst_y * gif_width as usize + dst_x) * 4;
+                if src_idx + 3 < frame.buffer.len() {
+                    let alpha = frame.buffer[src_idx + 3];
+                    if alpha > 0 {
+                        canvas[dst_idx] = frame.buffer[src_idx];
+                        canvas[dst_idx + 1] = frame.buffer[src_idx + 1];
+                        canvas[dst_idx + 2] = frame.buffer[src_idx + 2];
+                        canvas[dst_idx + 3] = alpha;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Frame memory an animation may keep, from `animation_memory_mb` and no more
+/// than `memory_cap_mb`.
+fn animation_budget_bytes() -> usize {
+    let megabytes = CONFIG
+        .lock()
+        .map(|config| config.animation_memory_mb)
+        .unwrap_or_else(|_| crate::config::AppConfig::default().animation_memory_mb);
+    let budget = megabytes as usize * 1024 * 1024;
+    memory_cap_bytes()
+        .map(|cap| budget.min(cap))
+        .unwrap_or(budget)
+}
+
+/// `size` scaled down, keeping its aspect ratio, until `frames` BGRA frames
+/// of it fit in `budget` bytes.
+fn fit_frame_budget((width, height): (u32, u32), frames: usize, budget: usize) -> (u32, u32) {
+    let bytes = width as f64 * height as f64 * 4.0 * frames as f64;
+    if bytes <= budget as f64 {
+        return (width, height);
+    }
+    let scale = (budget as f64 / bytes).sqrt();
+    (
+        ((width as f64 * scale) as u32).max(1),
+        ((height as f64 * scale) as u32).max(1),
+    )
+}
+
+/// Queue a streamed animation frame for the player. Frames are kept until
+/// they add up to `budget` bytes; the ring flag is then raised, and from
+/// there on the decoder waits for room among `ANIMATION_RING_FRAMES` queued
+/// frames instead. Returns false once cancelled.
+fn queue_animation_frame(
+    queue: &Mutex<VecDeque<Arc<ImageFrame>>>,
+    frame: ImageFrame,
+    kept_bytes: &mut usize,
+    budget: usize,
+    (ring, cancel): (&AtomicBool, &AtomicBool),
+) -> bool {
+    if !ring.load(Ordering::Acquire) {
+        *kept_bytes = kept_bytes.saturating_add(frame.pixels.len());
+        if *kept_bytes > budget {
+            ring.store(true, Ordering::Release);
+        }
+    }
+    if ring.load(Ordering::Acquire) {
+        loop {
+            if cancel.load(Ordering::Acquire) {
+                return false;
+            }
+            let buffered = queue.lock().map(|queue| queue.len()).unwrap_or(0);
+            if buffered < ANIMATION_RING_FRAMES {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(4));
+        }
+    }
+    if let Ok(mut frames) = queue.lock() {
+        frames.push_back(Arc::new(frame));
+    }
+    true
+}
+
+/// Whether to stop animations after the plays their files ask for.
+fn respects_loop_count() -> bool {
+    CONFIG
+        .lock()
+        .map(|config| config.respect_loop_count)
+        .unwrap_or(true)
+}
+
+/// How many times a GIF plays, from its NETSCAPE loop extension: once plus
+/// the repeats it asks for, or forever. Without the extension it plays once.
+fn gif_plays(repeat: gif::Repeat) -> Option<u32> {
+    match repeat {
+        gif::Repeat::Finite(repeats) => Some(repeats as u32 + 1),
+        gif::Repeat::Infinite => None,
+    }
+}
+
+/// How many times an animated WebP plays, from its `ANIM` chunk, where a
+/// loop count of 0 means forever.
+fn webp_plays(buffer: &[u8]) -> Option<u32> {
+    image_header::webp_loop_count(buffer)
+        .filter(|&count| count > 0)
+        .map(u32::from)
+}
+
+pub fn open_gif_decoder(path: &Path) -> Option<gif::Decoder<BufReader<File>>> {
+    let mut options = DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    options
+        .read_info(BufReader::new(File::open(path).ok()?))
+        .ok()
+}
+
+fn load_animated_gif(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+) -> Option<MediaData> {
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let mut decoder = open_gif_decoder(path)?;
+
+    let (gif_width, gif_height) = (decoder.width() as u32, decoder.height() as u32);
+    let budget = animation_budget_bytes();
+    let (target_width, target_height) = fit_frame_budget(
+        scale_dimensions(gif_width, gif_height, max_width, max_height),
+        ANIMATION_STARTUP_PREBUFFER_FRAMES,
+        budget,
+    );
+
+    let mut canvas = GifCanvas::new(gif_width, gif_height);
+    let mut initial_frames = Vec::new();
+    let mut initial_bytes: usize = 0;
+    let mut buffered_ms: u32 = 0;
+    let mut reached_end = false;
+
+    while initial_frames.len() < ANIMATION_STARTUP_PREBUFFER_FRAMES
+        && (initial_frames.len() < 2 || buffered_ms < ANIMATION_STARTUP_PREBUFFER_MS)
+    {
+        if cancel.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let frame = match decoder.read_next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => {
+                reached_end = true;
+                break;
+            }
+            Err(_) => return None,
+        };
+
+        canvas.draw(frame);
+        let delay_ms = (frame.delay as u32 * 10).max(MIN_GIF_ANIMATION_FRAME_DELAY_MS);
+        let img = decode_gif_frame_to_image(
+            &canvas.pixels,
+            gif_width,
+            gif_height,
+            target_width,
+            target_height,
+            delay_ms,
+        )?;
+        initial_bytes = initial_bytes.saturating_add(img.pixels.len());
+        if initial_bytes > budget {
+            return None;
+        }
+        buffered_ms = buffered_ms.saturating_add(delay_ms);
+        initial_frames.push(Arc::new(img));
+    }
+
+    if initial_frames.is_empty() || (reached_end && initial_frames.len() <= 1) {
+        return None;
+    }
+    // The loop extension comes before the first frame, so it has been read.
+    let plays = gif_plays(decoder.repeat()).filter(|_| respects_loop_count());
+
+    if reached_end {
+        return Some(MediaData {
+            frames: initial_frames,
+            shared_frames: None,
+            all_frames_loaded: None,
+            frame_ring: None,
+            decode_progress: None,
+            ring_position: None,
+            plays,
+            plays_done: 0,
+            current_frame: 0,
+            last_frame_time: Instant::now(),
+            media_type: MediaType::AnimatedGif,
+            stream_cancel: Some(cancel),
+            video_process: None,
+            loading_start: None,
+            stream_source: None,
+            playback_clock: None,
+            info_band: None,
+            resolution_badge: None,
+            metadata_band: None,
+            exif_band: None,
+            histogram: None,
+            zoom: None,
+            cache_key: None,
+            blurred: None,
+            deferred_playback: false,
+            scrub: VideoScrub::default(),
+        });
+    }
+
+    let shared = Arc::new(Mutex::new(VecDeque::new()));
+    let shared_clone = Arc::clone(&shared);
+    let loaded_flag = Arc::new(AtomicBool::new(false));
+    let loaded_flag_clone = Arc::clone(&loaded_flag);
+    let ring_flag = Arc::new(AtomicBool::new(false));
+    let ring_flag_clone = Arc::clone(&ring_flag);
+    let progress = Arc::new(DecodeProgress::default());
+    progress
+        .decoded
+        .store(initial_frames.len(), Ordering::Release);
+    let progress_clone = Arc::clone(&progress);
 
-    let scale_x = max_width as f32 / orig_width as f32;
-    let scale_y = max_height as f32 / orig_height as f32;
-    let scale = scale_x.min(scale_y);
+    let path_clone = path.clone();
+    let cancel_clone = Arc::clone(&cancel);
+    std::thread::spawn(move || {
+        priority::use_decode_cores();
+        // GIFs do not say how many frames they have, so count them first; the
+        // walk reads the file without decoding it.
+        if let Some(total) = image_header::gif_frame_count(&path_clone) {
+            progress_clone.total.store(total, Ordering::Release);
+        }
+        let mut kept_bytes = initial_bytes;
+        // The decoder and canvas carry on where the startup frames stopped, so
+        // no frame is decoded twice. Once playing from the ring, each further
+        // pass reopens the file.
+        let mut next_pass = Some((decoder, canvas));
+        let mut passes = 0;
+        while let Some((mut dec, mut canvas)) = next_pass.take() {
+            let mut queued = false;
+
+            while let Ok(Some(frame)) = dec.read_next_frame() {
+                if cancel_clone.load(Ordering::Acquire) {
+                    break;
+                }
 
-    let new_width = (orig_width as f32 * scale).max(1.0) as u32;
-    let new_height = (orig_height as f32 * scale).max(1.0) as u32;
+                canvas.draw(frame);
+                if passes == 0 {
+                    progress_clone.decoded.fetch_add(1, Ordering::AcqRel);
+                }
+                let delay_ms = (frame.delay as u32 * 10).max(MIN_GIF_ANIMATION_FRAME_DELAY_MS);
+                if let Some(img) = decode_gif_frame_to_image(
+                    &canvas.pixels,
+                    gif_width,
+                    gif_height,
+                    target_width,
+                    target_height,
+                    delay_ms,
+                ) {
+                    let ring = (&ring_flag_clone, &cancel_clone);
+                    if !queue_animation_frame(&shared_clone, img, &mut kept_bytes, budget, ring) {
+                        break;
+                    }
+                    queued = true;
+                }
+            }
 
-    (new_width, new_height)
+            passes += 1;
+            if !queued
+                || cancel_clone.load(Ordering::Acquire)
+                || !ring_flag_clone.load(Ordering::Acquire)
+                || plays.map(|plays| passes >= plays).unwrap_or(false)
+            {
+                break;
+            }
+            next_pass = open_gif_decoder(&path_clone)
+                .map(|dec| (dec, GifCanvas::new(gif_width, gif_height)));
+        }
+        loaded_flag_clone.store(true, Ordering::Release);
+    });
+
+    Some(MediaData {
+        frames: initial_frames,
+        shared_frames: Some(shared),
+        all_frames_loaded: Some(loaded_flag),
+        frame_ring: Some(ring_flag),
+        decode_progress: Some(progress),
+        ring_position: None,
+        plays,
+        plays_done: 0,
+        current_frame: 0,
+        last_frame_time: Instant::now(),
+        media_type: MediaType::AnimatedGif,
+        stream_cancel: Some(cancel),
+        video_process: None,
+        loading_start: Some(Instant::now()),
+        stream_source: None,
+        playback_clock: None,
+        info_band: None,
+        resolution_badge: None,
+        metadata_band: None,
+        exif_band: None,
+        histogram: None,
+        zoom: None,
+        cache_key: None,
+        blurred: None,
+        deferred_playback: false,
+        scrub: VideoScrub::default(),
+    })
 }
 
-/// Decode a single GIF frame from canvas to an ImageFrame
-fn decode_gif_frame_to_image(
-    canvas: &[u8],
-    gif_width: u32,
-    gif_height: u32,
+fn decode_webp_animation_frame_to_image(
+    bgra: &[u8],
+    orig_width: u32,
+    orig_height: u32,
     target_width: u32,
     target_height: u32,
     delay_ms: u32,
 ) -> Option<ImageFrame> {
-    let scaled = if target_width != gif_width || target_height != gif_height {
-        let img = image::RgbaImage::from_raw(gif_width, gif_height, canvas.to_vec())?;
-        let resized = image::imageops::resize(
-            &img,
-            target_width,
-            target_height,
-            image::imageops::FilterType::Nearest,
-        );
-        resized.into_raw()
+    let expected_bgra = orig_width as usize * orig_height as usize * 4;
+    if bgra.len() != expected_bgra {
+        return None;
+    }
+
+    let pixels = if target_width == orig_width && target_height == orig_height {
+        bgra.to_vec()
     } else {
-        canvas.to_vec()
+        resize_nearest(bgra, orig_width, orig_height, target_width, target_height)?
     };
 
-    let bgra = rgba_to_bgra(&scaled);
-
     Some(ImageFrame {
-        pixels: bgra,
+        pixels,
         width: target_width,
         height: target_height,
         delay_ms,
     })
 }
 
-/// Composite a GIF frame onto the canvas
-fn composite_gif_frame(canvas: &mut [u8], frame: &gif::Frame, gif_width: u32, gif_height: u32) {
-    let frame_x = frame.left as usize;
-    let frame_y = frame.top as usize;
-    let frame_w = frame.width as usize;
-    let frame_h = frame.height as usize;
+fn load_animated_webp(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+) -> Option<MediaData> {
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
 
-    for y in 0..frame_h {
-        for x in 0..frame_w {
-            let src_idx = (y * frame_w + x) * 4;
-            let dst_x = frame_x + x;
-            let dst_y = frame_y + y;
-            if dst_x < gif_width as usize && dst_y < gif_height as usize {
-                let dst_idx = (dst_y * gif_width as usize + dst_x) * 4;
-                if src_idx + 3 < frame.buffer.len() {
-                    let alpha = frame.buffer[src_idx + 3];
-                    if alpha > 0 {
-                        canvas[dst_idx] = frame.buffer[src_idx];
-                        canvas[dst_idx + 1] = frame.buffer[src_idx + 1];
-                        canvas[dst_idx + 2] = frame.buffer[src_idx + 2];
-                        canvas[dst_idx + 3] = alpha;
-                    }
+    let buffer = std::fs::read(path).ok()?;
+    let plays = webp_plays(&buffer).filter(|_| respects_loop_count());
+    let shared = Arc::new(Mutex::new(VecDeque::new()));
+    let shared_clone = Arc::clone(&shared);
+    let loaded_flag = Arc::new(AtomicBool::new(false));
+    let loaded_flag_clone = Arc::clone(&loaded_flag);
+    let ring_flag = Arc::new(AtomicBool::new(false));
+    let ring_flag_clone = Arc::clone(&ring_flag);
+    let progress = Arc::new(DecodeProgress::default());
+    if let Some(total) = image_header::webp_frame_count(&buffer) {
+        progress.total.store(total, Ordering::Release);
+    }
+    let progress_clone = Arc::clone(&progress);
+    let cancel_clone = Arc::clone(&cancel);
+    let (startup_tx, startup_rx) = channel();
+
+    // The decoder borrows the file's bytes and stays on the thread that made
+    // it, so the startup frames are decoded there too and sent back.
+    std::thread::spawn(move || {
+        priority::use_decode_cores();
+        let flags = (&*ring_flag_clone, &*cancel_clone);
+        let bounds = (max_width, max_height);
+        stream_webp_frames(
+            &buffer,
+            bounds,
+            plays,
+            &startup_tx,
+            &shared_clone,
+            flags,
+            &progress_clone.decoded,
+        );
+        loaded_flag_clone.store(true, Ordering::Release);
+    });
+    let (initial_frames, reached_end) = startup_rx.recv().ok()?;
+
+    if reached_end {
+        return Some(MediaData {
+            frames: initial_frames,
+            shared_frames: None,
+            all_frames_loaded: None,
+            frame_ring: None,
+            decode_progress: None,
+            ring_position: None,
+            plays,
+            plays_done: 0,
+            current_frame: 0,
+            last_frame_time: Instant::now(),
+            media_type: MediaType::AnimatedWebP,
+            stream_cancel: Some(cancel),
+            video_process: None,
+            loading_start: None,
+            stream_source: None,
+            playback_clock: None,
+            info_band: None,
+            resolution_badge: None,
+            metadata_band: None,
+            exif_band: None,
+            histogram: None,
+            zoom: None,
+            cache_key: None,
+            blurred: None,
+            deferred_playback: false,
+            scrub: VideoScrub::default(),
+        });
+    }
+
+    Some(MediaData {
+        frames: initial_frames,
+        shared_frames: Some(shared),
+        all_frames_loaded: Some(loaded_flag),
+        frame_ring: Some(ring_flag),
+        decode_progress: Some(progress),
+        ring_position: None,
+        plays,
+        plays_done: 0,
+        current_frame: 0,
+        last_frame_time: Instant::now(),
+        media_type: MediaType::AnimatedWebP,
+        stream_cancel: Some(cancel),
+        video_process: None,
+        loading_start: Some(Instant::now()),
+        stream_source: None,
+        playback_clock: None,
+        info_band: None,
+        resolution_badge: None,
+        metadata_band: None,
+        exif_band: None,
+        histogram: None,
+        zoom: None,
+        cache_key: None,
+        blurred: None,
+        deferred_playback: false,
+        scrub: VideoScrub::default(),
+    })
+}
+
+/// Decode an animated WebP once. The startup frames go to `startup`, with
+/// whether they are the whole animation; returning without sending means it
+/// cannot be played. The remaining frames go to `queue`, and once playing from
+/// the ring, the animation is decoded again pass after pass, up to `plays`.
+/// Frames of the first pass are counted in `decoded`.
+fn stream_webp_frames(
+    buffer: &[u8],
+    (max_width, max_height): (u32, u32),
+    plays: Option<u32>,
+    startup: &Sender<(Vec<Arc<ImageFrame>>, bool)>,
+    queue: &Mutex<VecDeque<Arc<ImageFrame>>>,
+    (ring, cancel): (&AtomicBool, &AtomicBool),
+    decoded: &AtomicUsize,
+) -> Option<()> {
+    let decoder = open_webp_decoder(buffer)?;
+    let (orig_width, orig_height) = decoder.dimensions();
+    if orig_width == 0 || orig_height == 0 || orig_width > 16384 || orig_height > 16384 {
+        return None;
+    }
+
+    let budget = animation_budget_bytes();
+    let (target_width, target_height) = fit_frame_budget(
+        scale_dimensions(orig_width, orig_height, max_width, max_height),
+        ANIMATION_STARTUP_PREBUFFER_FRAMES,
+        budget,
+    );
+    if target_width == 0 || target_height == 0 {
+        return None;
+    }
+    let to_image = |frame: &webp_animation::Frame, delay_ms: u32| {
+        decode_webp_animation_frame_to_image(
+            frame.data(),
+            orig_width,
+            orig_height,
+            target_width,
+            target_height,
+            delay_ms,
+        )
+    };
+
+    let mut initial_frames = Vec::new();
+    let mut kept_bytes: usize = 0;
+    let mut buffered_ms: u32 = 0;
+    let mut previous_timestamp = 0i32;
+    let mut reached_end = false;
+    let mut frames = decoder.into_iter();
+
+    while initial_frames.len() < ANIMATION_STARTUP_PREBUFFER_FRAMES
+        && (initial_frames.len() < 2 || buffered_ms < ANIMATION_STARTUP_PREBUFFER_MS)
+    {
+        if cancel.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let Some(frame) = frames.next() else {
+            reached_end = true;
+            break;
+        };
+        decoded.fetch_add(1, Ordering::AcqRel);
+
+        let timestamp = frame.timestamp();
+        let delay_ms = (timestamp - previous_timestamp).max(0) as u32;
+        previous_timestamp = timestamp;
+
+        let img = to_image(&frame, delay_ms)?;
+        kept_bytes = kept_bytes.saturating_add(img.pixels.len());
+        if kept_bytes > budget {
+            return None;
+        }
+        buffered_ms = buffered_ms.saturating_add(delay_ms);
+        initial_frames.push(Arc::new(img));
+    }
+
+    if initial_frames.is_empty() || (reached_end && initial_frames.len() <= 1) {
+        return None;
+    }
+    startup.send((initial_frames, reached_end)).ok()?;
+    if reached_end {
+        return Some(());
+    }
+
+    // The first pass carries on where the startup frames stopped.
+    let mut passes = 0;
+    loop {
+        let mut queued = false;
+
+        for frame in frames.by_ref() {
+            if cancel.load(Ordering::Acquire) {
+                break;
+            }
+            if passes == 0 {
+                decoded.fetch_add(1, Ordering::AcqRel);
+            }
+
+            let timestamp = frame.timestamp();
+            let delay_ms = (timestamp - previous_timestamp).max(0) as u32;
+            previous_timestamp = timestamp;
+
+            if let Some(img) = to_image(&frame, delay_ms) {
+                if !queue_animation_frame(queue, img, &mut kept_bytes, budget, (ring, cancel)) {
+                    break;
                 }
+                queued = true;
+            }
+        }
+
+        passes += 1;
+        if !queued
+            || cancel.load(Ordering::Acquire)
+            || !ring.load(Ordering::Acquire)
+            || plays.map(|plays| passes >= plays).unwrap_or(false)
+        {
+            return Some(());
+        }
+        frames = open_webp_decoder(buffer)?.into_iter();
+        previous_timestamp = 0;
+    }
+}
+
+pub fn open_webp_decoder(buffer: &[u8]) -> Option<webp_animation::Decoder<'_>> {
+    let options = webp_animation::DecoderOptions {
+        use_threads: true,
+        color_mode: webp_animation::ColorMode::Bgra,
+    };
+    webp_animation::Decoder::new_with_options(buffer, options).ok()
+}
+
+/// Load a static image (JPG, PNG, BMP, static WebP, etc.)
+fn load_static_image(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: &Arc<AtomicBool>,
+) -> Option<MediaData> {
+    let is_jpeg = if is_confirm_file_type_enabled() {
+        matches!(guessed_image_format(path), Some(image::ImageFormat::Jpeg))
+    } else {
+        is_jpeg_file(path)
+    };
+    if let Some(media) = load_progressive_still(path, max_width, max_height, is_jpeg, cancel) {
+        return Some(media);
+    }
+    let img = decode_still(path, max_width, max_height, is_jpeg)?;
+    // Scaling a large decode is the other slow step.
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
+    Some(static_media_from_image(img, max_width, max_height))
+}
+
+fn decode_still(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    is_jpeg: bool,
+) -> Option<image::DynamicImage> {
+    let scaled = if is_jpeg {
+        decode_scaled_jpeg(path, max_width, max_height)
+    } else {
+        None
+    };
+    match scaled {
+        Some(img) => Some(img),
+        None if is_confirm_file_type_enabled() => decode_image_with_header_check(path),
+        // By content, so files under an extension added to `image_extensions`
+        // decode too.
+        None => image::ImageReader::open(path)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .decode()
+            .ok(),
+    }
+}
+
+/// Show a large still right away from a cheap stand-in stretched to the
+/// preview size, and swap in the full decode when a background thread has it.
+/// The stand-in is the EXIF thumbnail, the Windows thumbnail cache's bitmap,
+/// or for JPEGs a 1/8-scale decode, whichever is found first.
+fn load_progressive_still(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    is_jpeg: bool,
+    cancel: &Arc<AtomicBool>,
+) -> Option<MediaData> {
+    let (orig_width, orig_height) = image_media_dimensions(path)?;
+    if (orig_width as u64) * (orig_height as u64) < PROGRESSIVE_MIN_PIXELS {
+        return None;
+    }
+    let (target_width, target_height) =
+        scale_dimensions(orig_width, orig_height, max_width, max_height);
+    let fits = |img: &image::DynamicImage| {
+        first_pass::same_aspect(img.dimensions(), (orig_width, orig_height))
+    };
+    let exif = if is_jpeg {
+        first_pass::exif_thumbnail(path).filter(fits)
+    } else {
+        None
+    };
+    let first_pass = exif
+        .or_else(|| first_pass::shell_cached(path, target_width, target_height).filter(fits))
+        .or_else(|| {
+            if !is_jpeg {
+                return None;
             }
+            decode_scaled_jpeg(path, (orig_width / 8).max(1), (orig_height / 8).max(1))
+        })?
+        .resize_exact(
+            target_width,
+            target_height,
+            image::imageops::FilterType::Triangle,
+        );
+
+    let queue = Arc::new(Mutex::new(VecDeque::new()));
+    let loaded = Arc::new(AtomicBool::new(false));
+    let mut media = MediaData::still(still_frame(first_pass, max_width, max_height));
+    media.shared_frames = Some(Arc::clone(&queue));
+    media.all_frames_loaded = Some(Arc::clone(&loaded));
+    media.stream_cancel = Some(Arc::clone(cancel));
+
+    let (path, cancel) = (path.clone(), Arc::clone(cancel));
+    std::thread::spawn(move || {
+        priority::use_decode_cores();
+        if cancel.load(Ordering::Acquire) {
+            return;
+        }
+        let Some(img) = decode_still(&path, max_width, max_height, is_jpeg) else {
+            return;
+        };
+        // Left unfinished when cancelled, so the first pass is never cached.
+        if cancel.load(Ordering::Acquire) {
+            return;
+        }
+        let frame = Arc::new(still_frame(img, max_width, max_height));
+        if let Ok(mut queue) = queue.lock() {
+            queue.push_back(Arc::clone(&frame));
+        }
+        loaded.store(true, Ordering::Release);
+        thumbnail_cache::store(&path, frame.width, frame.height, &frame);
+    });
+    Some(media)
+}
+
+/// Decode a JPEG at 1/2, 1/4 or 1/8 scale in the DCT, the smallest that still
+/// covers the preview size, instead of decoding every pixel and resizing.
+/// `None` when the full-size decoder should be used instead.
+pub fn decode_scaled_jpeg(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+) -> Option<image::DynamicImage> {
+    let file = File::open(path).ok()?;
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(file));
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let (orig_width, orig_height) = (info.width as u32, info.height as u32);
+    let (target_width, target_height) =
+        scale_dimensions(orig_width, orig_height, max_width, max_height);
+    // Below half size no DCT scale applies and the full decoder is as fast.
+    if target_width * 2 > orig_width || target_height * 2 > orig_height {
+        return None;
+    }
+    decoder
+        .scale(target_width.max(1) as u16, target_height.max(1) as u16)
+        .ok()?;
+    let pixels = decoder.decode().ok()?;
+    let info = decoder.info()?;
+    let (width, height) = (info.width as u32, info.height as u32);
+    match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => {
+            image::RgbImage::from_raw(width, height, pixels).map(image::DynamicImage::ImageRgb8)
         }
+        jpeg_decoder::PixelFormat::L8 => {
+            image::GrayImage::from_raw(width, height, pixels).map(image::DynamicImage::ImageLuma8)
+        }
+        // CMYK and 16-bit JPEGs are rare; `image` converts those properly.
+        _ => None,
     }
 }
 
-fn load_animated_gif(
-    path: &PathBuf,
+/// Scale a decoded image to fit the preview bounds and wrap it as a still.
+pub fn static_media_from_image(
+    img: image::DynamicImage,
     max_width: u32,
     max_height: u32,
-    cancel: Arc<AtomicBool>,
-) -> Option<MediaData> {
-    if cancel.load(Ordering::Acquire) {
-        return None;
-    }
-
-    let file = File::open(path).ok()?;
-    let mut decoder = DecodeOptions::new();
-    decoder.set_color_output(gif::ColorOutput::RGBA);
-    let mut decoder = decoder.read_info(BufReader::new(file)).ok()?;
+) -> MediaData {
+    MediaData::still(still_frame(img, max_width, max_height))
+}
 
-    let (gif_width, gif_height) = (decoder.width() as u32, decoder.height() as u32);
+/// Scale a decoded image to fit the preview bounds as a BGRA frame.
+fn still_frame(img: image::DynamicImage, max_width: u32, max_height: u32) -> ImageFrame {
+    let (orig_width, orig_height) = img.dimensions();
     let (target_width, target_height) =
-        scale_dimensions(gif_width, gif_height, max_width, max_height);
-
-    let mut canvas = vec![0u8; (gif_width * gif_height * 4) as usize];
-    let mut initial_frames = Vec::new();
-    let mut initial_bytes: usize = 0;
-    let mut buffered_ms: u32 = 0;
-    let mut reached_end = false;
-
-    while initial_frames.len() < MAX_STREAMED_ANIMATION_FRAMES
-        && initial_frames.len() < ANIMATION_STARTUP_PREBUFFER_FRAMES
-        && (initial_frames.len() < 2 || buffered_ms < ANIMATION_STARTUP_PREBUFFER_MS)
-    {
-        if cancel.load(Ordering::Acquire) {
-            return None;
-        }
-
-        let frame = match decoder.read_next_frame() {
-            Ok(Some(frame)) => frame,
-            Ok(None) => {
-                reached_end = true;
-                break;
-            }
-            Err(_) => return None,
-        };
+        scale_dimensions(orig_width, orig_height, max_width, max_height);
 
-        composite_gif_frame(&mut canvas, frame, gif_width, gif_height);
-        let delay_ms = (frame.delay as u32 * 10).max(MIN_GIF_ANIMATION_FRAME_DELAY_MS);
-        let img = decode_gif_frame_to_image(
-            &canvas,
-            gif_width,
-            gif_height,
+    let scale_started = Instant::now();
+    let rgba = if target_width == orig_width && target_height == orig_height {
+        img.to_rgba8()
+    } else if img.color().has_alpha() {
+        // Filter premultiplied so the color of fully transparent pixels does
+        // not bleed into the edges of what is visible.
+        let mut rgba = img.to_rgba8();
+        premultiply_alpha(&mut rgba);
+        let mut resized = image::imageops::resize(
+            &rgba,
             target_width,
             target_height,
-            delay_ms,
-        )?;
-        initial_bytes = initial_bytes.saturating_add(img.pixels.len());
-        if initial_bytes > MAX_STREAMED_ANIMATION_BYTES {
-            return None;
-        }
-        buffered_ms = buffered_ms.saturating_add(delay_ms);
-        initial_frames.push(img);
-    }
+            image::imageops::FilterType::Triangle,
+        );
+        unpremultiply_alpha(&mut resized);
+        resized
+    } else {
+        img.resize_exact(
+            target_width,
+            target_height,
+            image::imageops::FilterType::Triangle,
+        )
+        .to_rgba8()
+    };
+    trace::record("scale", scale_started.elapsed());
 
-    if initial_frames.is_empty() || (reached_end && initial_frames.len() <= 1) {
-        return None;
-    }
+    let bgra = rgba_to_bgra(rgba.as_raw());
 
-    if reached_end {
-        return Some(MediaData {
-            frames: initial_frames,
-            shared_frames: None,
-            all_frames_loaded: None,
-            current_frame: 0,
-            last_frame_time: Instant::now(),
-            media_type: MediaType::AnimatedGif,
-            stream_cancel: Some(cancel),
-            video_process: None,
-            loading_start: None,
-        });
+    ImageFrame {
+        pixels: bgra,
+        width: target_width,
+        height: target_height,
+        delay_ms: 0,
     }
+}
 
-    let shared = Arc::new(Mutex::new(VecDeque::new()));
-    let shared_clone = Arc::clone(&shared);
-    let loaded_flag = Arc::new(AtomicBool::new(false));
-    let loaded_flag_clone = Arc::clone(&loaded_flag);
-    let skip_frames = initial_frames.len();
+fn current_video_backend() -> VideoBackend {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.video_backend)
+        .unwrap_or(VideoBackend::Auto)
+}
 
-    let path_clone = path.clone();
-    let cancel_clone = Arc::clone(&cancel);
-    std::thread::spawn(move || {
-        let file = match File::open(&path_clone) {
-            Ok(f) => f,
-            Err(_) => {
-                loaded_flag_clone.store(true, Ordering::Release);
-                return;
-            }
-        };
-        let mut dec = DecodeOptions::new();
-        dec.set_color_output(gif::ColorOutput::RGBA);
-        let mut dec = match dec.read_info(BufReader::new(file)) {
-            Ok(d) => d,
-            Err(_) => {
-                loaded_flag_clone.store(true, Ordering::Release);
-                return;
-            }
-        };
+fn current_video_preview_mode() -> VideoPreviewMode {
+    CONFIG
+        .lock()
+        .map(|cfg| cfg.video_preview_mode)
+        .unwrap_or(VideoPreviewMode::Play)
+}
 
-        let mut canvas = vec![0u8; (gif_width * gif_height * 4) as usize];
-        let mut frame_idx = 0usize;
-        let mut streamed_count = skip_frames;
-        let mut streamed_bytes = initial_bytes;
+fn current_playback_options() -> video::PlaybackOptions {
+    CONFIG
+        .lock()
+        .map(|cfg| video::PlaybackOptions {
+            speed: sanitize_video_speed(cfg.video_speed),
+            looping: cfg.video_loop,
+            audio_track: cfg.video_audio_track,
+            subtitles: cfg.video_subtitles,
+        })
+        .unwrap_or_default()
+}
 
-        while let Ok(Some(frame)) = dec.read_next_frame() {
-            if cancel_clone.load(Ordering::Acquire)
-                || streamed_count >= MAX_STREAMED_ANIMATION_FRAMES
-            {
-                break;
-            }
+/// Whether videos play in a separate player process instead of the preview window.
+fn uses_external_video_player() -> bool {
+    matches!(
+        current_video_backend(),
+        VideoBackend::Ffplay | VideoBackend::Mpv
+    ) && current_video_preview_mode() == VideoPreviewMode::Play
+}
 
-            composite_gif_frame(&mut canvas, frame, gif_width, gif_height);
-            if frame_idx < skip_frames {
-                frame_idx += 1;
-                continue;
-            }
+/// Whether `path` should be decoded with Media Foundation before trying ffmpeg.
+fn prefers_media_foundation(path: &PathBuf) -> bool {
+    match current_video_backend() {
+        VideoBackend::MediaFoundation => true,
+        VideoBackend::Auto => media_foundation::can_decode(path),
+        VideoBackend::Ffmpeg | VideoBackend::Ffplay | VideoBackend::Mpv => false,
+    }
+}
 
-            let delay_ms = (frame.delay as u32 * 10).max(MIN_GIF_ANIMATION_FRAME_DELAY_MS);
-            if let Some(img) = decode_gif_frame_to_image(
-                &canvas,
-                gif_width,
-                gif_height,
-                target_width,
-                target_height,
-                delay_ms,
-            ) {
-                let frame_bytes = img.pixels.len();
-                if streamed_bytes.saturating_add(frame_bytes) > MAX_STREAMED_ANIMATION_BYTES {
-                    break;
-                }
-                if let Ok(mut frames) = shared_clone.lock() {
-                    frames.push_back(img);
-                }
-                streamed_count += 1;
-                streamed_bytes = streamed_bytes.saturating_add(frame_bytes);
-            }
-            frame_idx += 1;
-        }
-        loaded_flag_clone.store(true, Ordering::Release);
+/// Load a video preview for the configured backend, with the info bar
+pub fn load_video_media(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+) -> Option<MediaData> {
+    // The storyboard already labels its cells; the bar would cover the bottom row.
+    let show_info = CONFIG.lock().map(|c| c.video_info_overlay).unwrap_or(true)
+        && current_video_preview_mode() != VideoPreviewMode::Storyboard;
+    // Probe while the first frame decodes.
+    let info_probe = show_info.then(|| {
+        let path = path.clone();
+        std::thread::spawn(move || video_info(&path))
     });
 
-    Some(MediaData {
-        frames: initial_frames,
-        shared_frames: Some(shared),
-        all_frames_loaded: Some(loaded_flag),
-        current_frame: 0,
-        last_frame_time: Instant::now(),
-        media_type: MediaType::AnimatedGif,
-        stream_cancel: Some(cancel),
-        video_process: None,
-        loading_start: Some(Instant::now()),
+    let mut media = load_video_preview(path, max_width, max_height, Arc::clone(&cancel))?;
+    if let Some(info) = info_probe.and_then(|probe| probe.join().ok().flatten()) {
+        let summary = info.summary();
+        if !summary.is_empty() && !cancel.load(Ordering::Acquire) {
+            media.info_band = overlay::CaptionBand::new(
+                media.current_width(),
+                media.current_height(),
+                &[summary],
+            );
+        }
+    }
+    Some(media)
+}
+
+/// Stream details for the info bar: ffprobe, or Media Foundation (without the
+/// codec) when ffprobe is unavailable.
+fn video_info(path: &PathBuf) -> Option<video::VideoInfo> {
+    if let Some(info) = video::probe_info(path) {
+        return Some(info);
+    }
+    let mf = media_foundation_geometry(path)?;
+    Some(video::VideoInfo {
+        duration_secs: mf.duration_secs,
+        width: Some(mf.width),
+        height: Some(mf.height),
+        frame_rate: mf.frame_rate,
+        codec: None,
     })
 }
 
-fn decode_webp_animation_frame_to_image(
-    bgra: &[u8],
-    orig_width: u32,
-    orig_height: u32,
-    target_width: u32,
-    target_height: u32,
-    delay_ms: u32,
-) -> Option<ImageFrame> {
-    let expected_bgra = orig_width as usize * orig_height as usize * 4;
-    if bgra.len() != expected_bgra {
-        return None;
+fn load_video_preview(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+) -> Option<MediaData> {
+    if uses_external_video_player() {
+        return load_video_thumbnail(path, max_width, max_height, &cancel);
     }
 
-    let pixels = if target_width == orig_width && target_height == orig_height {
-        bgra.to_vec()
-    } else {
-        let mut rgba = Vec::with_capacity(expected_bgra);
-        for chunk in bgra.chunks_exact(4) {
-            rgba.push(chunk[2]);
-            rgba.push(chunk[1]);
-            rgba.push(chunk[0]);
-            rgba.push(chunk[3]);
-        }
-        let img = image::RgbaImage::from_raw(orig_width, orig_height, rgba)?;
-        let resized = image::imageops::resize(
-            &img,
-            target_width,
-            target_height,
-            image::imageops::FilterType::Nearest,
-        );
-        rgba_to_bgra(&resized.into_raw())
+    // Under heavy system load, show a still and leave playback until it drops.
+    let mode = current_video_preview_mode();
+    if mode == VideoPreviewMode::Play && system_load::is_high() {
+        if let Some(mut media) = load_video_still(path, max_width, max_height, &cancel) {
+            media.deferred_playback = true;
+            return Some(media);
+        }
+        if cancel.load(Ordering::Acquire) {
+            return None;
+        }
+    }
+
+    let still = match mode {
+        VideoPreviewMode::Play => None,
+        VideoPreviewMode::Storyboard => load_video_storyboard(path, max_width, max_height, &cancel),
+        VideoPreviewMode::Static => load_video_still(path, max_width, max_height, &cancel),
     };
+    if still.is_some() || cancel.load(Ordering::Acquire) {
+        return still;
+    }
 
-    Some(ImageFrame {
-        pixels,
-        width: target_width,
-        height: target_height,
-        delay_ms,
-    })
+    if prefers_media_foundation(path) {
+        if let Some(media) =
+            load_media_foundation_stream(path, max_width, max_height, Arc::clone(&cancel))
+        {
+            return Some(media);
+        }
+        if cancel.load(Ordering::Acquire) {
+            return None;
+        }
+    }
+
+    load_video_stream(path, max_width, max_height, cancel)
 }
 
-fn load_animated_webp(
+/// Start decoding a video through Media Foundation. Returns `None` when the
+/// file cannot be opened so the caller can fall back to ffmpeg.
+fn load_media_foundation_stream(
     path: &PathBuf,
     max_width: u32,
     max_height: u32,
     cancel: Arc<AtomicBool>,
 ) -> Option<MediaData> {
+    let info = media_foundation_geometry(path)?;
     if cancel.load(Ordering::Acquire) {
         return None;
     }
 
-    let buffer = Arc::new(std::fs::read(path).ok()?);
-    let options = webp_animation::DecoderOptions {
-        use_threads: true,
-        color_mode: webp_animation::ColorMode::Bgra,
+    let (target_width, target_height) =
+        scale_dimensions(info.width, info.height, max_width, max_height);
+    let playback = current_playback_options();
+    let fps = info.frame_rate.unwrap_or(video::DEFAULT_VIDEO_STREAM_FPS);
+    let frame_delay_ms = video::playback_frame_delay_ms(fps, playback.speed);
+
+    let source = VideoStreamSource {
+        path: path.clone(),
+        decoder: VideoDecoder::MediaFoundation,
+        width: target_width,
+        height: target_height,
+        frame_delay_ms,
+        playback,
+        duration_secs: info.duration_secs,
+        duration_probed: info.duration_secs.is_some(),
     };
-    let decoder = webp_animation::Decoder::new_with_options(buffer.as_slice(), options).ok()?;
+    start_video_stream(source, cancel)
+}
 
-    let (orig_width, orig_height) = decoder.dimensions();
-    if orig_width == 0 || orig_height == 0 || orig_width > 16384 || orig_height > 16384 {
-        return None;
+/// Spawn the decoder for `source` and return once the first frame is ready
+fn start_video_stream(mut source: VideoStreamSource, cancel: Arc<AtomicBool>) -> Option<MediaData> {
+    let path = source.path.clone();
+    // Hover-scrub maps the cursor onto the duration, so probe it here on the
+    // loader rather than on the first scrub.
+    if is_video_hover_scrub_enabled() {
+        source.duration_secs();
     }
-
-    let (target_width, target_height) =
-        scale_dimensions(orig_width, orig_height, max_width, max_height);
-    if target_width == 0 || target_height == 0 {
+    let start_secs = resume_start_secs(&path, || source.duration_secs());
+    let queue = source.spawn(start_secs, Arc::clone(&cancel))?;
+    let Some(first_frame) = video::wait_for_first_frame(
+        &queue,
+        Duration::from_millis(VIDEO_STREAM_FIRST_FRAME_TIMEOUT_MS),
+        &cancel,
+    ) else {
+        cancel.store(true, Ordering::Release);
         return None;
-    }
-
-    let mut initial_frames = Vec::new();
-    let mut initial_bytes: usize = 0;
-    let mut buffered_ms: u32 = 0;
-    let mut previous_timestamp = 0i32;
-    let mut reached_end = false;
-    let mut iterator = decoder.into_iter();
+    };
 
-    while initial_frames.len() < MAX_STREAMED_ANIMATION_FRAMES
-        && initial_frames.len() < ANIMATION_STARTUP_PREBUFFER_FRAMES
-        && (initial_frames.len() < 2 || buffered_ms < ANIMATION_STARTUP_PREBUFFER_MS)
-    {
-        if cancel.load(Ordering::Acquire) {
-            return None;
-        }
+    let volume = video_volume();
+    let audio_process = video::spawn_audio_player(&path, volume, start_secs, source.playback);
+    let playback_clock = PlaybackClock::new(&path, start_secs, source.playback.speed);
 
-        let frame = match iterator.next() {
-            Some(frame) => frame,
-            None => {
-                reached_end = true;
-                break;
-            }
-        };
+    Some(MediaData {
+        frames: vec![first_frame],
+        shared_frames: Some(queue),
+        all_frames_loaded: None,
+        frame_ring: None,
+        decode_progress: None,
+        ring_position: None,
+        plays: None,
+        plays_done: 0,
+        current_frame: 0,
+        last_frame_time: Instant::now(),
+        media_type: MediaType::VideoStream,
+        stream_cancel: Some(cancel),
+        video_process: audio_process,
+        loading_start: None,
+        stream_source: Some(source),
+        playback_clock: Some(playback_clock),
+        info_band: None,
+        resolution_badge: None,
+        metadata_band: None,
+        exif_band: None,
+        histogram: None,
+        zoom: None,
+        cache_key: None,
+        blurred: None,
+        deferred_playback: false,
+        scrub: VideoScrub::default(),
+    })
+}
 
-        let timestamp = frame.timestamp();
-        let delay_ms = (timestamp - previous_timestamp).max(0) as u32;
-        previous_timestamp = timestamp;
+fn media_foundation_geometry(path: &PathBuf) -> Option<media_foundation::MfVideoInfo> {
+    if let Ok(cache) = MF_GEOMETRY_CACHE.lock() {
+        if let Some(cached) = cache.get(path) {
+            return Some(*cached);
+        }
+    }
 
-        let img = decode_webp_animation_frame_to_image(
-            frame.data(),
-            orig_width,
-            orig_height,
-            target_width,
-            target_height,
-            delay_ms,
-        )?;
-        initial_bytes = initial_bytes.saturating_add(img.pixels.len());
-        if initial_bytes > MAX_STREAMED_ANIMATION_BYTES {
-            return None;
+    let geometry = media_foundation::probe(path)?;
+    if let Ok(mut cache) = MF_GEOMETRY_CACHE.lock() {
+        if !cache.contains_key(path) && cache.len() >= VIDEO_GEOMETRY_CACHE_MAX_ENTRIES {
+            cache.clear();
         }
-        buffered_ms = buffered_ms.saturating_add(delay_ms);
-        initial_frames.push(img);
+        cache.insert(path.clone(), geometry);
     }
+    Some(geometry)
+}
 
-    if initial_frames.is_empty() || (reached_end && initial_frames.len() <= 1) {
-        return None;
+/// ffmpeg filter that applies the detected crop and scales to the preview size.
+fn ffmpeg_geometry_filter(geometry: Option<&VideoGeometry>, width: u32, height: u32) -> String {
+    let mut filter = String::new();
+    if let Some(crop) = geometry.and_then(|g| g.crop) {
+        filter.push_str(&format!(
+            "crop={}:{}:{}:{},",
+            crop.width, crop.height, crop.x, crop.y
+        ));
     }
+    filter.push_str(&format!(
+        "scale={}:{}:flags=bilinear,setsar=1",
+        width, height
+    ));
+    filter
+}
 
-    if reached_end {
-        return Some(MediaData {
-            frames: initial_frames,
-            shared_frames: None,
-            all_frames_loaded: None,
-            current_frame: 0,
-            last_frame_time: Instant::now(),
-            media_type: MediaType::AnimatedWebP,
-            stream_cancel: Some(cancel),
-            video_process: None,
-            loading_start: None,
-        });
+/// The filter `extract_video_frames` decodes through when no crop is detected.
+pub fn ffmpeg_scale_filter(width: u32, height: u32) -> String {
+    ffmpeg_geometry_filter(None, width, height)
+}
+
+fn video_duration_secs(path: &PathBuf) -> Option<f64> {
+    if prefers_media_foundation(path) {
+        if let Some(duration) = media_foundation_geometry(path).and_then(|info| info.duration_secs)
+        {
+            return Some(duration);
+        }
+    }
+    video::probe_duration(path)
+}
+
+/// Decode single frames of `path` at `times_secs`, through Media Foundation
+/// when it handles the file and otherwise with one ffmpeg seek per frame.
+/// Stops early once `cancel` is set.
+fn extract_video_frames(
+    path: &PathBuf,
+    times_secs: &[f64],
+    (width, height): (u32, u32),
+    cancel: &AtomicBool,
+) -> Vec<Option<ImageFrame>> {
+    if prefers_media_foundation(path) {
+        let frames = media_foundation::extract_frames(path, times_secs, (width, height), cancel);
+        if frames.iter().any(|frame| frame.is_some()) || cancel.load(Ordering::Acquire) {
+            return frames;
+        }
     }
 
-    let shared = Arc::new(Mutex::new(VecDeque::new()));
-    let shared_clone = Arc::clone(&shared);
-    let loaded_flag = Arc::new(AtomicBool::new(false));
-    let loaded_flag_clone = Arc::clone(&loaded_flag);
-    let skip_frames = initial_frames.len();
+    let geometry = get_video_geometry(path);
+    let filter = ffmpeg_geometry_filter(geometry.as_ref(), width, height);
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = times_secs
+            .iter()
+            .map(|&at| {
+                let filter = &filter;
+                scope.spawn(move || {
+                    video::extract_ffmpeg_frame(path, filter, at, (width, height), cancel)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().ok().flatten())
+            .collect()
+    })
+}
 
-    drop(iterator);
-    let buffer_clone = Arc::clone(&buffer);
-    let cancel_clone = Arc::clone(&cancel);
-    std::thread::spawn(move || {
-        let options = webp_animation::DecoderOptions {
-            use_threads: true,
-            color_mode: webp_animation::ColorMode::Bgra,
-        };
-        let decoder =
-            match webp_animation::Decoder::new_with_options(buffer_clone.as_slice(), options) {
-                Ok(decoder) => decoder,
-                Err(_) => {
-                    loaded_flag_clone.store(true, Ordering::Release);
-                    return;
-                }
-            };
+/// Decode one frame at `video_static_seek_percent` of the duration. Without a
+/// known duration the first frame is used.
+fn load_video_still(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: &AtomicBool,
+) -> Option<MediaData> {
+    let percent = CONFIG
+        .lock()
+        .map(|cfg| cfg.video_static_seek_percent)
+        .unwrap_or(10);
+    let at = video_duration_secs(path)
+        .map(|duration| video::scrub_position_secs(percent as f32 / 100.0, duration))
+        .unwrap_or(0.0);
+    let (src_width, src_height) = video_media_dimensions(path)?;
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
 
-        let mut previous_timestamp = 0i32;
-        let mut streamed_count = skip_frames;
-        let mut streamed_bytes = initial_bytes;
+    let (width, height) = scale_dimensions(src_width, src_height, max_width, max_height);
+    let frame = extract_video_frames(path, &[at], (width, height), cancel)
+        .into_iter()
+        .next()
+        .flatten()?;
+    Some(MediaData::still(frame))
+}
 
-        for (frame_idx, frame) in decoder.into_iter().enumerate() {
-            if cancel_clone.load(Ordering::Acquire)
-                || streamed_count >= MAX_STREAMED_ANIMATION_FRAMES
-            {
-                break;
-            }
+/// Build a 3x3 grid of frames sampled across the video, each labelled with
+/// its timestamp.
+fn load_video_storyboard(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: &AtomicBool,
+) -> Option<MediaData> {
+    let duration = video_duration_secs(path)?;
+    let (src_width, src_height) = video_media_dimensions(path)?;
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
 
-            let timestamp = frame.timestamp();
-            let delay_ms = (timestamp - previous_timestamp).max(0) as u32;
-            previous_timestamp = timestamp;
+    let (width, height) = scale_dimensions(src_width, src_height, max_width, max_height);
+    let columns = STORYBOARD_COLUMNS;
+    let rows = STORYBOARD_ROWS;
+    let cell_width = width.saturating_sub(STORYBOARD_GAP_PX * (columns - 1)) / columns;
+    let cell_height = height.saturating_sub(STORYBOARD_GAP_PX * (rows - 1)) / rows;
+    if cell_width < 16 || cell_height < 16 {
+        return None;
+    }
 
-            if frame_idx < skip_frames {
-                continue;
-            }
+    let times = video::storyboard_times(duration, (columns * rows) as usize);
+    let cells = extract_video_frames(path, &times, (cell_width, cell_height), cancel);
+    if cancel.load(Ordering::Acquire) || cells.iter().all(|cell| cell.is_none()) {
+        return None;
+    }
 
-            if let Some(img) = decode_webp_animation_frame_to_image(
-                frame.data(),
-                orig_width,
-                orig_height,
-                target_width,
-                target_height,
-                delay_ms,
-            ) {
-                let frame_bytes = img.pixels.len();
-                if streamed_bytes.saturating_add(frame_bytes) > MAX_STREAMED_ANIMATION_BYTES {
-                    break;
-                }
-                if let Ok(mut frames) = shared_clone.lock() {
-                    frames.push_back(img);
-                }
-                streamed_count += 1;
-                streamed_bytes = streamed_bytes.saturating_add(frame_bytes);
-            }
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    for px in pixels.chunks_exact_mut(4) {
+        px[3] = 255;
+    }
+    for (index, (cell, at)) in cells.into_iter().zip(times.iter()).enumerate() {
+        let Some(mut cell) = cell.filter(|c| c.width == cell_width && c.height == cell_height)
+        else {
+            continue;
+        };
+        overlay::draw_caption(&mut cell, &[video::format_timestamp(*at)]);
+
+        let column = index as u32 % columns;
+        let row = index as u32 / columns;
+        let left = (column * (cell_width + STORYBOARD_GAP_PX)) as usize;
+        let top = (row * (cell_height + STORYBOARD_GAP_PX)) as usize;
+        let row_bytes = cell_width as usize * 4;
+        for y in 0..cell_height as usize {
+            let src = y * row_bytes;
+            let dst = ((top + y) * width as usize + left) * 4;
+            pixels[dst..dst + row_bytes].copy_from_slice(&cell.pixels[src..src + row_bytes]);
         }
-        loaded_flag_clone.store(true, Ordering::Release);
-    });
+    }
 
-    Some(MediaData {
-        frames: initial_frames,
-        shared_frames: Some(shared),
-        all_frames_loaded: Some(loaded_flag),
-        current_frame: 0,
-        last_frame_time: Instant::now(),
-        media_type: MediaType::AnimatedWebP,
-        stream_cancel: Some(cancel),
-        video_process: None,
-        loading_start: Some(Instant::now()),
-    })
+    Some(MediaData::still(ImageFrame {
+        pixels,
+        width,
+        height,
+        delay_ms: 0,
+    }))
 }
 
-/// Load a static image (JPG, PNG, BMP, static WebP, etc.)
-fn load_static_image(path: &PathBuf, max_width: u32, max_height: u32) -> Option<MediaData> {
-    let img = if is_confirm_file_type_enabled() {
-        decode_image_with_header_check(path)?
-    } else {
-        image::open(path).ok()?
-    };
-    let (orig_width, orig_height) = img.dimensions();
-    let (target_width, target_height) =
-        scale_dimensions(orig_width, orig_height, max_width, max_height);
-
-    let resized = if target_width != orig_width || target_height != orig_height {
-        img.resize_exact(
-            target_width,
-            target_height,
-            image::imageops::FilterType::Triangle,
-        )
-    } else {
-        img
-    };
+/// Start decoding a video in-process and return once the first frame is ready
+fn load_video_stream(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+) -> Option<MediaData> {
+    let geometry = get_video_geometry(path)?;
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
 
-    let rgba = resized.to_rgba8();
-    let bgra = rgba_to_bgra(rgba.as_raw());
+    let (target_width, target_height) =
+        scale_dimensions(geometry.width, geometry.height, max_width, max_height);
+    // The rate limit applies to displayed frames, so at higher speeds fewer
+    // source frames are kept.
+    let playback = current_playback_options();
+    let (fps, resample) =
+        video::stream_frame_rate(geometry.frame_rate.map(|fps| fps * playback.speed));
+    let frame_delay_ms = video::playback_frame_delay_ms(fps, 1.0);
+
+    let mut filter = ffmpeg_geometry_filter(Some(&geometry), target_width, target_height);
+    if playback.subtitles {
+        filter.push(',');
+        filter.push_str(&video::subtitles_filter(path));
+    }
+    if resample {
+        filter.push_str(&format!(",fps={}", fps / playback.speed));
+    }
 
-    let frame = ImageFrame {
-        pixels: bgra,
+    let source = VideoStreamSource {
+        path: path.clone(),
+        decoder: VideoDecoder::Ffmpeg { filter },
         width: target_width,
         height: target_height,
-        delay_ms: 0,
+        frame_delay_ms,
+        playback,
+        duration_secs: None,
+        duration_probed: false,
     };
-
-    Some(MediaData {
-        frames: vec![frame],
-        shared_frames: None,
-        all_frames_loaded: None,
-        current_frame: 0,
-        last_frame_time: Instant::now(),
-        media_type: MediaType::StaticImage,
-        stream_cancel: None,
-        video_process: None,
-        loading_start: None,
-    })
+    start_video_stream(source, cancel)
 }
 
 /// Extract video thumbnail using ffmpeg and create frames for preview
-fn load_video_thumbnail(path: &PathBuf, max_width: u32, max_height: u32) -> Option<MediaData> {
+fn load_video_thumbnail(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: &AtomicBool,
+) -> Option<MediaData> {
     let geometry = get_video_geometry(path).unwrap_or(VideoGeometry {
         width: 1920,
         height: 1080,
         crop: None,
+        frame_rate: None,
     });
     let (target_width, target_height) =
         scale_dimensions(geometry.width, geometry.height, max_width, max_height);
 
-    // Create a placeholder frame (dark gray) while video plays
-    let placeholder_pixels = vec![40u8; (target_width * target_height * 4) as usize];
-
-    let frame = ImageFrame {
-        pixels: placeholder_pixels,
-        width: target_width,
-        height: target_height,
-        delay_ms: 0,
-    };
+    // The real first frame, or dark gray if it cannot be decoded
+    let frame = extract_video_frames(path, &[0.0], (target_width, target_height), cancel)
+        .into_iter()
+        .next()
+        .flatten()
+        .filter(|frame| frame.width == target_width && frame.height == target_height)
+        .unwrap_or_else(|| ImageFrame {
+            pixels: vec![40u8; (target_width * target_height * 4) as usize],
+            width: target_width,
+            height: target_height,
+            delay_ms: 0,
+        });
+    if cancel.load(Ordering::Acquire) {
+        return None;
+    }
 
     Some(MediaData {
-        frames: vec![frame],
+        frames: vec![Arc::new(frame)],
         shared_frames: None,
         all_frames_loaded: None,
+        frame_ring: None,
+        decode_progress: None,
+        ring_position: None,
+        plays: None,
+        plays_done: 0,
         current_frame: 0,
         last_frame_time: Instant::now(),
         media_type: MediaType::Video,
         stream_cancel: None,
         video_process: None,
         loading_start: None,
+        stream_source: None,
+        playback_clock: None,
+        info_band: None,
+        resolution_badge: None,
+        metadata_band: None,
+        exif_band: None,
+        histogram: None,
+        zoom: None,
+        cache_key: None,
+        blurred: None,
+        deferred_playback: false,
+        scrub: VideoScrub::default(),
     })
 }
 
-// Windows constant for hiding console window
-const CREATE_NO_WINDOW: u32 = 0x08000000;
 const VIDEO_CROPDETECT_LIMIT: &str = "24";
 const VIDEO_CROPDETECT_ROUND: &str = "16";
 const VIDEO_CROPDETECT_FRAMES: &str = "48";
 const VIDEO_CROP_MAX_AXIS_TRIM_RATIO: f32 = 0.10;
 const VIDEO_CROP_MAX_ASYMMETRY_PX: i32 = 12;
 
-/// Get video dimensions using ffprobe
-fn get_video_dimensions(path: &PathBuf) -> Option<(u32, u32)> {
-    let output = Command::new("ffprobe")
+/// Get video dimensions and average frame rate using ffprobe
+fn get_video_dimensions(path: &PathBuf) -> Option<(u32, u32, Option<f32>)> {
+    let output = video::ffprobe_command()
         .args([
             "-v",
             "error",
@@ -1094,7 +3437,7 @@ fn get_video_dimensions(path: &PathBuf) -> Option<(u32, u32)> {
             "-select_streams",
             "v:0",
             "-show_entries",
-            "stream=width,height",
+            "stream=width,height,avg_frame_rate",
             "-of",
             "csv=s=x:p=0",
         ])
@@ -1109,7 +3452,8 @@ fn get_video_dimensions(path: &PathBuf) -> Option<(u32, u32)> {
     let mut parts = output_str.trim().split('x').filter(|part| !part.is_empty());
     let width = parts.next()?.parse().ok()?;
     let height = parts.next()?.parse().ok()?;
-    Some((width, height))
+    let frame_rate = parts.next().and_then(video::parse_frame_rate);
+    Some((width, height, frame_rate))
 }
 
 fn parse_cropdetect_line(line: &str) -> Option<VideoCrop> {
@@ -1175,7 +3519,7 @@ fn detect_video_crop(path: &PathBuf, src_w: u32, src_h: u32) -> Option<VideoCrop
         VIDEO_CROPDETECT_LIMIT, VIDEO_CROPDETECT_ROUND
     );
 
-    let output = Command::new("ffmpeg")
+    let output = video::ffmpeg_command()
         .args([
             "-v",
             "info",
@@ -1247,7 +3591,7 @@ fn get_video_geometry(path: &PathBuf) -> Option<VideoGeometry> {
         }
     }
 
-    let (src_w, src_h) = get_video_dimensions(path)?;
+    let (src_w, src_h, frame_rate) = get_video_dimensions(path)?;
     let crop = detect_video_crop(path, src_w, src_h);
 
     let geometry = if let Some(crop) = crop {
@@ -1255,12 +3599,14 @@ fn get_video_geometry(path: &PathBuf) -> Option<VideoGeometry> {
             width: crop.width,
             height: crop.height,
             crop: Some(crop),
+            frame_rate,
         }
     } else {
         VideoGeometry {
             width: src_w,
             height: src_h,
             crop: None,
+            frame_rate,
         }
     };
 
@@ -1475,13 +3821,29 @@ fn set_noactivate_for_process(pid: u32) {
     ensure_noactivate_monitor();
 }
 
-/// Start ffplay for video preview with configurable volume
-fn start_video_playback(path: &PathBuf, x: i32, y: i32, width: i32, height: i32) -> Option<Child> {
+/// Start ffplay for video preview with configurable volume, `start_secs` into
+/// the file
+fn start_video_playback(
+    path: &PathBuf,
+    start_secs: f64,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> Option<Child> {
+    if current_video_backend() == VideoBackend::Mpv {
+        return start_mpv_playback(path, start_secs, x, y, width, height);
+    }
+
     // Get volume setting from config (0-100)
-    let volume = CONFIG.lock().map(|c| c.video_volume).unwrap_or(0);
+    let volume = video_volume();
 
     // Use ffplay for video playback - borderless, positioned at preview location
-    let mut cmd = Command::new("ffplay");
+    let mut cmd = video::ffplay_command();
+    let extra = video::FfplayExtraArgs::from_config();
+    cmd.args(&extra.options);
+
+    let playback = current_playback_options();
 
     // If volume is 0, disable audio completely for better performance
     if volume == 0 {
@@ -1489,11 +3851,24 @@ fn start_video_playback(path: &PathBuf, x: i32, y: i32, width: i32, height: i32)
     } else {
         // Convert percentage to ffplay volume filter (0-100 maps to 0.0-1.0)
         let volume_filter = format!("volume={:.2}", volume as f64 / 100.0);
-        cmd.args(["-af", &volume_filter]);
+        let tempo = video::atempo_filter(playback.speed);
+        let audio_filter = video::join_filters(&[
+            Some(volume_filter.as_str()),
+            extra.audio_filter.as_deref(),
+            tempo.as_deref(),
+        ])
+        .unwrap_or_default();
+        cmd.args(["-af", &audio_filter]);
+        if playback.audio_track > 0 {
+            cmd.args(["-ast", &format!("a:{}", playback.audio_track - 1)]);
+        }
+    }
+    if !playback.subtitles {
+        cmd.arg("-sn");
     }
 
     let geometry = get_video_geometry(path);
-    let vf = geometry.map(|geometry| {
+    let frame_filter = geometry.map(|geometry| {
         if let Some(crop) = geometry.crop {
             format!(
                 "crop={}:{}:{}:{},setsar=1",
@@ -1503,10 +3878,22 @@ fn start_video_playback(path: &PathBuf, x: i32, y: i32, width: i32, height: i32)
             "setsar=1".to_string()
         }
     });
+    let setpts =
+        ((playback.speed - 1.0).abs() >= 0.01).then(|| format!("setpts=PTS/{:.3}", playback.speed));
+    // The user's filters, such as subtitles, see the cropped frame at the
+    // file's own timing.
+    let vf = video::join_filters(&[
+        frame_filter.as_deref(),
+        extra.video_filter.as_deref(),
+        setpts.as_deref(),
+    ]);
     log_video_preview(path, x, y, width, height, vf.as_deref(), geometry);
     if let Some(vf) = vf.as_deref() {
         cmd.args(["-vf", &vf]);
     }
+    if start_secs > 0.0 {
+        cmd.args(["-ss", &format!("{:.3}", start_secs)]);
+    }
 
     let child = cmd
         .args([
@@ -1516,8 +3903,8 @@ fn start_video_playback(path: &PathBuf, x: i32, y: i32, width: i32, height: i32)
             "+genpts+discardcorrupt+igndts", // Handle missing timestamps & corrupt data
             "-framedrop",                    // Drop undecodable frames instead of stalling
             "-loop",
-            "0",         // Loop forever
-            "-noborder", // No window border
+            if playback.looping { "0" } else { "1" }, // 0 loops forever
+            "-noborder",                              // No window border
             "-left",
             &x.to_string(),
             "-top",
@@ -1547,16 +3934,308 @@ fn start_video_playback(path: &PathBuf, x: i32, y: i32, width: i32, height: i32)
     child
 }
 
+unsafe extern "system" fn video_host_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_MOUSEACTIVATE {
+        return LRESULT(MA_NOACTIVATE as isize);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+/// The window mpv renders into, created on first use. Unlike the layered
+/// preview window it can host another process's child window.
+fn video_host_window() -> Option<HWND> {
+    let existing = VIDEO_HOST_HWND.load(Ordering::SeqCst);
+    if existing != 0 {
+        return Some(HWND(existing as *mut _));
+    }
+
+    unsafe {
+        let hinstance = GetModuleHandleW(None).ok()?;
+        let wc = WNDCLASSEXW {
+            cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
+            style: CS_HREDRAW | CS_VREDRAW,
+            lpfnWndProc: Some(video_host_proc),
+            hInstance: hinstance.into(),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: HBRUSH(GetStockObject(BLACK_BRUSH).0),
+            lpszClassName: VIDEO_HOST_CLASS,
+            ..Default::default()
+        };
+        RegisterClassExW(&wc);
+
+        let hwnd = CreateWindowExW(
+            WS_EX_TOOLWINDOW | WS_EX_TOPMOST | WS_EX_NOACTIVATE,
+            VIDEO_HOST_CLASS,
+            w!("Video Preview"),
+            WS_POPUP | WS_CLIPCHILDREN,
+            0,
+            0,
+            1,
+            1,
+            None,
+            None,
+            hinstance,
+            None,
+        )
+        .ok()?;
+        VIDEO_HOST_HWND.store(hwnd.0 as isize, Ordering::SeqCst);
+        Some(hwnd)
+    }
+}
+
+fn hide_video_host() {
+    let host = VIDEO_HOST_HWND.load(Ordering::SeqCst);
+    if host != 0 {
+        unsafe {
+            let _ = ShowWindow(HWND(host as *mut _), SW_HIDE);
+        }
+    }
+}
+
+/// Start mpv embedded in the video host window. The host is placed and shown
+/// by `ensure_video_window_topmost`, like the ffplay window.
+fn start_mpv_playback(
+    path: &PathBuf,
+    start_secs: f64,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> Option<Child> {
+    let host = video_host_window()?;
+    let volume = video_volume();
+    let playback = current_playback_options();
+
+    let mut cmd = video::mpv_command();
+    cmd.arg(format!("--wid={}", host.0 as isize));
+    if volume == 0 {
+        cmd.arg("--audio=no");
+    } else {
+        cmd.arg(format!("--volume={}", volume));
+        if playback.audio_track > 0 {
+            cmd.arg(format!("--aid={}", playback.audio_track));
+        }
+    }
+    cmd.arg(if playback.subtitles {
+        "--sid=auto"
+    } else {
+        "--sid=no"
+    });
+
+    let geometry = get_video_geometry(path);
+    let vf = geometry
+        .and_then(|geometry| geometry.crop)
+        .map(|crop| format!("crop={}:{}:{}:{}", crop.width, crop.height, crop.x, crop.y));
+    log_video_preview(path, x, y, width, height, vf.as_deref(), geometry);
+    if let Some(vf) = vf {
+        cmd.arg(format!("--vf={}", vf));
+    }
+    if start_secs > 0.0 {
+        cmd.arg(format!("--start={:.3}", start_secs));
+    }
+
+    let child = cmd
+        .args([
+            &format!("--speed={:.3}", playback.speed),
+            if playback.looping {
+                "--loop-file=inf"
+            } else {
+                // Hold the last frame instead of leaving the host window black
+                "--keep-open=yes"
+            },
+            "--no-border",
+            "--no-osc",
+            "--osd-level=0",
+            "--no-input-default-bindings",
+            "--input-vo-keyboard=no",
+            "--input-cursor=no",
+            "--cursor-autohide=no",
+            "--hwdec=auto-safe",
+            "--no-terminal",
+            "--",
+        ])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()
+        .ok()?;
+
+    // No window of mpv's own to restyle; the host already has the right style.
+    VIDEO_HWND.store(host.0 as isize, Ordering::SeqCst);
+    Some(child)
+}
+
 /// Stop video playback process
 fn stop_video_playback(media: &mut MediaData) {
     if let Some(ref mut process) = media.video_process {
         let _ = process.kill();
         let _ = process.wait();
     }
-    media.video_process = None;
-    // Clear the video window HWND
-    VIDEO_HWND.store(0, Ordering::SeqCst);
-    VIDEO_PID.store(0, Ordering::SeqCst);
+    media.video_process = None;
+    hide_video_host();
+    // Clear the video window HWND
+    VIDEO_HWND.store(0, Ordering::SeqCst);
+    VIDEO_PID.store(0, Ordering::SeqCst);
+}
+
+/// A player closed by its preview stays parked until a preview of the same
+/// video picks it up again or this long has passed.
+fn video_player_keep_alive() -> Duration {
+    CONFIG
+        .lock()
+        .map(|cfg| Duration::from_millis(cfg.video_player_keep_alive_ms))
+        .unwrap_or(Duration::ZERO)
+}
+
+/// How long an in-process video stays paused after its preview closes.
+const PAUSED_STREAM_KEEP_ALIVE: Duration = Duration::from_secs(1);
+
+/// How long `media` stays parked when its preview closes: an external player
+/// for `video_player_keep_alive`, an in-process video for at most
+/// `PAUSED_STREAM_KEEP_ALIVE`, which is enough to cross to a neighbouring item
+/// and back. Zero for previews that are not parked.
+fn parking_time(media: &MediaData) -> Duration {
+    let keep_alive = video_player_keep_alive();
+    if media.is_scrubbable() {
+        keep_alive.min(PAUSED_STREAM_KEEP_ALIVE)
+    } else if media.video_process.is_some()
+        && uses_external_video_player()
+        && VIDEO_HWND.load(Ordering::SeqCst) != 0
+    {
+        keep_alive
+    } else {
+        Duration::ZERO
+    }
+}
+
+/// A video preview parked, paused and hidden, after it closed. The media owns
+/// the player process or decode stream and the playback clock.
+struct ParkedPlayer {
+    path: PathBuf,
+    media: MediaData,
+    /// The external player's window; zero for in-process videos.
+    window: isize,
+    backend: VideoBackend,
+    parked_at: Instant,
+    keep_alive: Duration,
+}
+
+/// Stop the playback of a preview of `path` that is being replaced or closed,
+/// or park it when it is a video that can be picked up again.
+fn release_video_player(mut media: MediaData, path: Option<&PathBuf>) {
+    let keep_alive = parking_time(&media);
+    match path {
+        Some(path) if !keep_alive.is_zero() => park_video_player(path.clone(), media, keep_alive),
+        _ => {
+            media.cancel_background_work();
+            stop_video_playback(&mut media);
+            retire_media(media);
+        }
+    }
+}
+
+/// Hide and pause the video of `media` and keep it for `keep_alive`. A video
+/// parked before is stopped.
+fn park_video_player(path: PathBuf, mut media: MediaData, keep_alive: Duration) {
+    let mut window = 0;
+    if !media.is_scrubbable() {
+        window = VIDEO_HWND.load(Ordering::SeqCst);
+        // Stop the no-activate monitor first so it does not show the window again.
+        VIDEO_PID.store(0, Ordering::SeqCst);
+        unsafe {
+            let _ = ShowWindow(HWND(window as *mut _), SW_HIDE);
+        }
+        hide_video_host();
+        VIDEO_HWND.store(0, Ordering::SeqCst);
+    }
+
+    if !media.pause_playback() {
+        media.cancel_background_work();
+        stop_video_playback(&mut media);
+        return;
+    }
+    if let Ok(mut parked) = PARKED_PLAYER.lock() {
+        *parked = Some(ParkedPlayer {
+            path,
+            media,
+            window,
+            backend: current_video_backend(),
+            parked_at: Instant::now(),
+            keep_alive,
+        });
+    }
+}
+
+/// The parked video of `path`, if it was parked with the current backend.
+fn take_parked_player(path: &PathBuf) -> Option<ParkedPlayer> {
+    let mut parked = PARKED_PLAYER.lock().ok()?;
+    if parked.as_ref()?.path != *path {
+        return None;
+    }
+    parked
+        .take()
+        .filter(|parked| parked.backend == current_video_backend())
+}
+
+/// Resume the parked external player when it is playing `path`, and move its
+/// window to the preview position. The media it was parked with is returned
+/// to become the current preview.
+fn unpark_video_player(
+    path: &PathBuf,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> Option<MediaData> {
+    let parked = take_parked_player(path).filter(|parked| parked.window != 0)?;
+    let mut media = parked.media;
+    let process = media.video_process.as_mut()?;
+    if !matches!(process.try_wait(), Ok(None)) {
+        return None;
+    }
+    let pid = process.id();
+    media.resume_playback();
+
+    VIDEO_HWND.store(parked.window, Ordering::SeqCst);
+    ensure_video_window_topmost(x, y, width, height);
+    if parked.backend != VideoBackend::Mpv {
+        set_noactivate_for_process(pid);
+    }
+    Some(media)
+}
+
+/// Resume the parked in-process video of `path` when its frames fit within
+/// `max_width` x `max_height`.
+fn unpark_video_stream(path: &PathBuf, max_width: u32, max_height: u32) -> Option<MediaData> {
+    let mut media = take_parked_player(path)
+        .filter(|parked| parked.window == 0)?
+        .media;
+    if media.current_width() > max_width || media.current_height() > max_height {
+        return None;
+    }
+    media.resume_playback();
+    Some(media)
+}
+
+/// Stop the parked video once it has waited its keep-alive time.
+fn expire_parked_player() {
+    let keep_alive = video_player_keep_alive();
+    if let Ok(mut parked) = PARKED_PLAYER.try_lock() {
+        if parked
+            .as_ref()
+            .map(|parked| parked.parked_at.elapsed() >= parked.keep_alive.min(keep_alive))
+            .unwrap_or(false)
+        {
+            *parked = None;
+        }
+    }
 }
 
 /// Check if the current ffplay process is still running
@@ -1634,7 +4313,7 @@ fn ensure_video_window_topmost(x: i32, y: i32, width: i32, height: i32) -> bool
     true
 }
 
-/// Load media (image, animated image, or video) with appropriate loader
+/// Load media through the provider registered for the path
 fn load_media(
     path: &PathBuf,
     max_width: u32,
@@ -1645,17 +4324,239 @@ fn load_media(
         return None;
     }
 
-    if is_video_file(path) {
-        return load_video_thumbnail(path, max_width, max_height);
+    // Videos stream, and their stills depend on the video settings, so they
+    // are never cached. Neither are GIFs and WebPs while `preview_animations`
+    // is off, so turning it on or off takes effect on the next hover.
+    let may_animate = is_gif_file(path) || is_webp_file(path);
+    let cacheable = !providers::is_video(path) && (is_animation_enabled() || !may_animate);
+    let cache_key = if cacheable {
+        MediaCacheKey::new(path, max_width, max_height)
+    } else {
+        None
+    };
+    if let Some(media) = cache_key.as_ref().and_then(take_cached_media) {
+        return Some(media);
+    }
+    if let Some(key) = &cache_key {
+        if let Some(frame) = thumbnail_cache::lookup(path, key.width, key.height) {
+            let mut media = MediaData::still(frame);
+            add_code_band(&mut media, &cancel);
+            add_explicit_blur(&mut media, path, &cancel);
+            media.cache_key = cache_key;
+            return Some(media);
+        }
+    }
+
+    let started = Instant::now();
+    let mut media = match providers::find(path) {
+        Some(provider) => provider.load(
+            path,
+            &LoadConstraints {
+                max_width,
+                max_height,
+                cancel: Arc::clone(&cancel),
+            },
+        ),
+        // Unknown extensions still get a best-effort static decode so
+        // confirm_file_type can recover mislabeled images.
+        None => load_image_media(path, max_width, max_height, Arc::clone(&cancel)),
+    }?;
+
+    // Progressive stills store their full decode themselves.
+    let slow_still = matches!(media.media_type, MediaType::StaticImage)
+        && media.shared_frames.is_none()
+        && started.elapsed().as_millis() >= thumbnail_cache::MIN_DECODE_MS;
+    if let Some(key) = cache_key.as_ref().filter(|_| slow_still) {
+        if let Some(frame) = media.frames.first() {
+            let frame = Arc::clone(frame);
+            let (path, width, height) = (path.clone(), key.width, key.height);
+            std::thread::spawn(move || thumbnail_cache::store(&path, width, height, &frame));
+        }
+    }
+    if cacheable {
+        add_code_band(&mut media, &cancel);
+        add_explicit_blur(&mut media, path, &cancel);
+    }
+    media.cache_key = cache_key;
+    Some(media)
+}
+
+/// With `detect_codes`, list the QR codes and barcodes found in a still image
+/// in a bar along its bottom.
+fn add_code_band(media: &mut MediaData, cancel: &AtomicBool) {
+    let enabled = CONFIG.lock().map(|c| c.detect_codes).unwrap_or(false);
+    if !enabled || !matches!(media.media_type, MediaType::StaticImage) {
+        return;
+    }
+    let (width, height) = (media.current_width(), media.current_height());
+    let found = codes::detect_bgra(media.current_pixels(), width, height);
+    if found.is_empty() || cancel.load(Ordering::Acquire) {
+        return;
+    }
+    media.info_band = overlay::CaptionBand::new(width, height, &codes::band_lines(&found));
+}
+
+/// With `show_resolution`, label a raster image preview with the file's size
+/// and the size it is shown at, or with a small "100%" when it is not scaled
+/// down. Provider previews (documents, panels) are left alone.
+fn add_resolution_badge(media: &mut MediaData, path: &PathBuf, source_size: (u32, u32)) {
+    let enabled = CONFIG.lock().map(|c| c.show_resolution).unwrap_or(true);
+    let is_image = providers::find(path).is_none()
+        && matches!(
+            media.media_type,
+            MediaType::StaticImage | MediaType::AnimatedGif | MediaType::AnimatedWebP
+        );
+    let (width, height) = (media.current_width(), media.current_height());
+    media.resolution_badge = resolution_label(source_size, (width, height))
+        .filter(|_| enabled && is_image)
+        .and_then(|label| overlay::Badge::new(width, height, &label));
+}
+
+/// With `metadata_bar`, list the file's name, pixel size, file size and
+/// modified date in a strip along the bottom of the preview.
+fn add_metadata_band(media: &mut MediaData, path: &PathBuf, source_size: (u32, u32)) {
+    let enabled = CONFIG.lock().map(|c| c.metadata_bar).unwrap_or(false);
+    if !enabled {
+        media.metadata_band = None;
+        return;
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let meta = std::fs::metadata(path).ok();
+    let line = metadata_line(
+        &name,
+        source_size,
+        meta.as_ref().map(|meta| meta.len()),
+        meta.and_then(|meta| meta.modified().ok())
+            .and_then(dates::format_local),
+    );
+    let (width, height) = (media.current_width(), media.current_height());
+    media.metadata_band = overlay::CaptionBand::new(width, height, &[line]);
+}
+
+/// With `show_histogram`, chart the levels of still images in the preview's
+/// top-left corner.
+fn add_histogram(media: &mut MediaData) {
+    let enabled = CONFIG.lock().map(|c| c.show_histogram).unwrap_or(false);
+    if !enabled || !matches!(media.media_type, MediaType::StaticImage) {
+        media.histogram = None;
+        return;
+    }
+    let (width, height) = (media.current_width(), media.current_height());
+    media.histogram = overlay::HistogramPanel::new(media.current_pixels(), width, height);
+}
+
+/// With `exif_key` set, prepare the camera details panel shown while the
+/// key is held. Files without EXIF data say so.
+fn add_exif_band(media: &mut MediaData, path: &Path) {
+    let enabled = CONFIG
+        .lock()
+        .map(|c| !c.exif_key.trim().is_empty())
+        .unwrap_or(false);
+    if !enabled {
+        media.exif_band = None;
+        return;
+    }
+    let lines = match exif::read(path) {
+        Some(details) => details.lines(),
+        None => vec!["No camera details".to_string()],
+    };
+    let (width, height) = (media.current_width(), media.current_height());
+    media.exif_band = overlay::CaptionBand::new(width, height, &lines);
+}
+
+/// `photo.jpg  ·  4032×3024  ·  3.2 MB  ·  2024-05-01 14:30`, leaving out
+/// what is unknown.
+fn metadata_line(
+    name: &str,
+    size: (u32, u32),
+    bytes: Option<u64>,
+    modified: Option<String>,
+) -> String {
+    let mut parts = vec![name.to_string()];
+    if size.0 > 0 && size.1 > 0 {
+        parts.push(format!("{}\u{d7}{}", size.0, size.1));
+    }
+    if let Some(bytes) = bytes {
+        parts.push(format_file_size(bytes));
+    }
+    parts.extend(modified);
+    parts.join("  \u{b7}  ")
+}
+
+/// `912 B`, `14.2 KB`, `3.2 MB`, `1.4 GB`, in 1024-byte units as Explorer
+/// counts them.
+fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// `3840×2160 → 1280×720` for a downscaled preview, `100%` for one at the
+/// file's own size. Unknown sizes and enlarged previews get no label.
+fn resolution_label(source: (u32, u32), shown: (u32, u32)) -> Option<String> {
+    if source.0 == 0 || source.1 == 0 || shown.0 == 0 || shown.1 == 0 {
+        return None;
+    }
+    // The header gives the stored size; EXIF rotation may turn it sideways.
+    let sideways =
+        (source.0 > source.1 && shown.0 < shown.1) || (source.0 < source.1 && shown.0 > shown.1);
+    let source = if sideways {
+        (source.1, source.0)
+    } else {
+        source
+    };
+    if source == shown {
+        return Some("100%".to_string());
+    }
+    if source.0 < shown.0 || source.1 < shown.1 {
+        return None;
+    }
+    Some(format!(
+        "{}\u{d7}{} \u{2192} {}\u{d7}{}",
+        source.0, source.1, shown.0, shown.1
+    ))
+}
+
+/// With `blur_explicit`, keep a blurred copy of a still the classifier flags.
+fn add_explicit_blur(media: &mut MediaData, path: &Path, cancel: &AtomicBool) {
+    if !matches!(media.media_type, MediaType::StaticImage) || cancel.load(Ordering::Acquire) {
+        return;
     }
+    let (width, height) = (media.current_width(), media.current_height());
+    if nsfw::should_blur(path, media.current_pixels(), width, height) {
+        media.blurred = nsfw::blur_bgra(media.current_pixels(), width, height);
+    }
+}
 
+/// Load a raster image, dispatching GIF/WebP to the animated loaders
+pub fn load_image_media(
+    path: &PathBuf,
+    max_width: u32,
+    max_height: u32,
+    cancel: Arc<AtomicBool>,
+) -> Option<MediaData> {
     let guessed_format = if is_confirm_file_type_enabled() {
         guessed_image_format(path)
     } else {
         None
     };
 
-    if matches!(guessed_format, Some(image::ImageFormat::Gif)) || is_gif_file(path) {
+    // With `preview_animations` off, GIFs and WebPs show their first frame.
+    let animate = is_animation_enabled();
+
+    let is_gif = matches!(guessed_format, Some(image::ImageFormat::Gif)) || is_gif_file(path);
+    if animate && is_gif {
         // Try animated GIF first
         if let Some(media) = load_animated_gif(path, max_width, max_height, Arc::clone(&cancel)) {
             return Some(media);
@@ -1664,10 +4565,11 @@ fn load_media(
             return None;
         }
         // Fall back to static for single-frame GIFs
-        return load_static_image(path, max_width, max_height);
+        return load_static_image(path, max_width, max_height, &cancel);
     }
 
-    if matches!(guessed_format, Some(image::ImageFormat::WebP)) || is_webp_file(path) {
+    let is_webp = matches!(guessed_format, Some(image::ImageFormat::WebP)) || is_webp_file(path);
+    if animate && is_webp {
         // Try animated WebP first
         if let Some(media) = load_animated_webp(path, max_width, max_height, Arc::clone(&cancel)) {
             return Some(media);
@@ -1676,24 +4578,69 @@ fn load_media(
             return None;
         }
         // Fall back to static for non-animated WebP
-        return load_static_image(path, max_width, max_height);
+        return load_static_image(path, max_width, max_height, &cancel);
     }
 
     // Default to static image
     if cancel.load(Ordering::Acquire) {
         return None;
     }
-    load_static_image(path, max_width, max_height)
+    load_static_image(path, max_width, max_height, &cancel)
 }
 
 /// Get original dimensions of media for positioning calculations
 fn get_media_dimensions(path: &PathBuf) -> Option<(u32, u32)> {
-    if is_video_file(path) {
-        return get_video_geometry(path)
-            .map(|g| (g.width, g.height))
-            .or(Some((1920, 1080)));
+    let stamp = file_stamp(path);
+    if let Some(stamp) = stamp {
+        let cached = DIMENSIONS_CACHE.lock().ok().and_then(|cache| {
+            cache
+                .get(path)
+                .filter(|(at, _)| *at == stamp)
+                .map(|(_, dimensions)| *dimensions)
+        });
+        if cached.is_some() {
+            return cached;
+        }
+    }
+
+    let dimensions = match providers::find(path) {
+        Some(provider) => provider.dimensions(path),
+        None => image_media_dimensions(path),
+    }?;
+    if let (Some(stamp), Ok(mut cache)) = (stamp, DIMENSIONS_CACHE.lock()) {
+        if !cache.contains_key(path) && cache.len() >= DIMENSIONS_CACHE_MAX_ENTRIES {
+            cache.clear();
+        }
+        cache.insert(path.clone(), (stamp, dimensions));
     }
+    Some(dimensions)
+}
+
+/// A file's modification time and length, to notice it changed.
+pub(crate) type FileStamp = (Option<SystemTime>, u64);
+
+pub(crate) fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok(), meta.len()))
+}
+
+pub fn video_media_dimensions(path: &PathBuf) -> Option<(u32, u32)> {
+    if prefers_media_foundation(path) {
+        if let Some(info) = media_foundation_geometry(path) {
+            return Some((info.width, info.height));
+        }
+    }
+
+    get_video_geometry(path)
+        .map(|g| (g.width, g.height))
+        .or(Some((1920, 1080)))
+}
 
+pub fn image_media_dimensions(path: &PathBuf) -> Option<(u32, u32)> {
+    // The size fields of common formats, found by content either way.
+    if let Some(dimensions) = image_header::dimensions(path) {
+        return Some(dimensions);
+    }
     if is_confirm_file_type_enabled() {
         image_dimensions_with_header_check(path)
     } else {
@@ -1785,15 +4732,32 @@ fn create_loading_media(width: u32, height: u32) -> MediaData {
         delay_ms: 33,
     };
     MediaData {
-        frames: vec![frame],
+        frames: vec![Arc::new(frame)],
         shared_frames: None,
         all_frames_loaded: None,
+        frame_ring: None,
+        decode_progress: None,
+        ring_position: None,
+        plays: None,
+        plays_done: 0,
         current_frame: 0,
         last_frame_time: Instant::now(),
         media_type: MediaType::Loading,
         stream_cancel: None,
         video_process: None,
         loading_start: Some(Instant::now()),
+        stream_source: None,
+        playback_clock: None,
+        info_band: None,
+        resolution_badge: None,
+        metadata_band: None,
+        exif_band: None,
+        histogram: None,
+        zoom: None,
+        cache_key: None,
+        blurred: None,
+        deferred_playback: false,
+        scrub: VideoScrub::default(),
     }
 }
 
@@ -1872,15 +4836,25 @@ fn overlay_loading_spinner(pixels: &mut [u8], width: u32, height: u32, angle: f3
 struct LoadResult {
     generation: u64,
     media: Option<MediaData>,
+    /// `queue`, `decode` and `scale` timings for the trace log.
+    stages: trace::Stages,
 }
 
-/// A decode request consumed by the dedicated loader worker.
+/// Loader threads sharing the request slot. A decode that never polls its
+/// cancel flag (`image` on a large RAW or PNG) keeps its worker busy after the
+/// cursor has moved on, so spare workers let the next hover start right away.
+/// The bound caps how many abandoned decodes can pile up during rapid movement;
+/// with every worker busy, only the newest request waits.
+const LOAD_WORKERS: usize = 3;
+
+/// A decode request consumed by the loader workers.
 struct LoadRequest {
     generation: u64,
     path: PathBuf,
     max_width: u32,
     max_height: u32,
     cancel: Arc<AtomicBool>,
+    queued: Instant,
 }
 
 type LoadRequestSlot = Arc<(Mutex<Option<LoadRequest>>, Condvar)>;
@@ -1900,6 +4874,15 @@ fn clear_load_request(slot: &LoadRequestSlot) {
     }
 }
 
+fn spawn_load_workers(
+    request_slot: &LoadRequestSlot,
+    result_tx: Sender<LoadResult>,
+) -> Vec<std::thread::JoinHandle<()>> {
+    (0..LOAD_WORKERS)
+        .map(|_| spawn_load_worker(Arc::clone(request_slot), result_tx.clone()))
+        .collect()
+}
+
 fn spawn_load_worker(
     request_slot: LoadRequestSlot,
     result_tx: Sender<LoadResult>,
@@ -1947,6 +4930,10 @@ fn spawn_load_worker(
                 continue;
             }
 
+            let load_started = Instant::now();
+            let queued = load_started.duration_since(request.queued);
+            trace::take_recorded();
+            priority::use_decode_cores();
             let media = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
                 load_media(
                     &request.path,
@@ -1956,11 +4943,25 @@ fn spawn_load_worker(
                 )
             }))
             .unwrap_or(None);
+            let loaded = load_started.elapsed();
+            let scaled = trace::stage(&trace::take_recorded(), "scale");
+            let loaded_current = media.is_some() && !request.cancel.load(Ordering::Acquire);
+            if loaded_current {
+                prefetch::request(&request.path, request.max_width, request.max_height);
+            }
 
             let _ = result_tx.send(LoadResult {
                 generation: request.generation,
                 media,
+                stages: vec![
+                    ("queue", queued),
+                    ("decode", loaded.saturating_sub(scaled)),
+                    ("scale", scaled),
+                ],
             });
+            if loaded_current {
+                warm_start::record(&request.path, request.max_width, request.max_height);
+            }
         }
     })
 }
@@ -1969,11 +4970,219 @@ fn spawn_load_worker(
 struct PendingLoad {
     generation: u64,
     started: Instant,
-    pos_x: i32,
-    pos_y: i32,
+    layout: PreviewLayout,
+    spinner_shown: bool,
+    /// The file's own dimensions, for the resolution badge.
+    source_size: (u32, u32),
+    /// `resolve` and `probe` timings for the trace log.
+    trace: trace::Stages,
+}
+
+/// How long the previous preview stays on screen while the next one loads, to
+/// fade into it. Slower loads hide it instead of leaving a stale image up.
+const CROSSFADE_HOLD: Duration = Duration::from_millis(250);
+
+/// A composed frame of the previous preview, faded out over the current one
+/// by `render_layered_preview`.
+struct Crossfade {
+    pixels: Vec<u8>,
     width: u32,
     height: u32,
-    spinner_shown: bool,
+    started: Instant,
+    duration: Duration,
+}
+
+fn crossfade_duration() -> Duration {
+    CONFIG
+        .lock()
+        .map(|cfg| Duration::from_millis(cfg.crossfade_ms as u64))
+        .unwrap_or(Duration::ZERO)
+}
+
+fn is_crossfading() -> bool {
+    CROSSFADE.lock().map(|fade| fade.is_some()).unwrap_or(false)
+}
+
+/// The preview window fading in after being shown, or out before being
+/// hidden, by changing only its constant alpha.
+#[derive(Clone, Copy)]
+enum WindowFade {
+    Steady,
+    In(Instant),
+    Out(Instant),
+}
+
+static WINDOW_FADE: Mutex<WindowFade> = Mutex::new(WindowFade::Steady);
+
+fn window_fade_duration() -> Duration {
+    CONFIG
+        .lock()
+        .map(|cfg| Duration::from_millis(cfg.fade_ms as u64))
+        .unwrap_or(Duration::ZERO)
+}
+
+fn window_opacity_percent() -> u32 {
+    CONFIG.lock().map(|cfg| cfg.opacity).unwrap_or(100)
+}
+
+/// How far through a fade of `duration` it is after `elapsed`, from 0 to 1.
+fn fade_progress(elapsed: Duration, duration: Duration) -> f32 {
+    if duration.is_zero() {
+        return 1.0;
+    }
+    (elapsed.as_secs_f32() / duration.as_secs_f32()).min(1.0)
+}
+
+/// Share of the configured opacity the window is at, from 0 to 1.
+fn fade_level(fade: WindowFade, duration: Duration) -> f32 {
+    match fade {
+        WindowFade::Steady => 1.0,
+        WindowFade::In(started) => fade_progress(started.elapsed(), duration),
+        WindowFade::Out(started) => 1.0 - fade_progress(started.elapsed(), duration),
+    }
+}
+
+/// `SourceConstantAlpha` for `opacity_percent` at `level` of a fade.
+fn constant_alpha(opacity_percent: u32, level: f32) -> u8 {
+    let opacity = opacity_percent.min(100) as f32 / 100.0;
+    (255.0 * opacity * level.clamp(0.0, 1.0)).round() as u8
+}
+
+/// When a fade would have started to be `progress` of the way through now,
+/// so a fade can turn around without jumping.
+fn fade_started_at(progress: f32, duration: Duration) -> Instant {
+    let now = Instant::now();
+    now.checked_sub(duration.mul_f32(progress.clamp(0.0, 1.0)))
+        .unwrap_or(now)
+}
+
+/// Start fading in from fully transparent, for a window about to be shown.
+fn begin_fade_in() {
+    let duration = window_fade_duration();
+    if let Ok(mut fade) = WINDOW_FADE.lock() {
+        *fade = if duration.is_zero() {
+            WindowFade::Steady
+        } else {
+            WindowFade::In(Instant::now())
+        };
+    }
+}
+
+/// Start fading out from where the window is. `false` when there is no fade
+/// and the caller should hide the window itself.
+fn begin_fade_out() -> bool {
+    let duration = window_fade_duration();
+    if duration.is_zero() {
+        return false;
+    }
+    let Ok(mut fade) = WINDOW_FADE.lock() else {
+        return false;
+    };
+    let level = fade_level(*fade, duration);
+    *fade = WindowFade::Out(fade_started_at(1.0 - level, duration));
+    true
+}
+
+/// The window's constant alpha for a new frame. A new preview painted while
+/// the previous one fades out fades back in from where it was.
+fn paint_window_alpha() -> u8 {
+    let duration = window_fade_duration();
+    let level = match WINDOW_FADE.lock() {
+        Ok(mut fade) => {
+            let level = fade_level(*fade, duration);
+            if let WindowFade::Out(_) = *fade {
+                *fade = WindowFade::In(fade_started_at(level, duration));
+            }
+            level
+        }
+        Err(_) => 1.0,
+    };
+    constant_alpha(window_opacity_percent(), level)
+}
+
+/// Change only the constant alpha of the layered window, keeping its content.
+unsafe fn set_window_alpha(hwnd: HWND, alpha: u8) {
+    let blend = BLENDFUNCTION {
+        BlendOp: AC_SRC_OVER as u8,
+        BlendFlags: 0,
+        SourceConstantAlpha: alpha,
+        AlphaFormat: AC_SRC_ALPHA as u8,
+    };
+    let _ = UpdateLayeredWindow(
+        hwnd,
+        None,
+        None,
+        None,
+        None,
+        None,
+        COLORREF(0),
+        Some(&blend),
+        ULW_ALPHA,
+    );
+}
+
+/// Move a running fade on; a finished fade-out hides the window.
+unsafe fn step_window_fade(hwnd: HWND) {
+    let duration = window_fade_duration();
+    let (level, fading_out) = {
+        let Ok(mut fade) = WINDOW_FADE.lock() else {
+            return;
+        };
+        let current = *fade;
+        let level = fade_level(current, duration);
+        match current {
+            WindowFade::Steady => return,
+            WindowFade::In(_) if level >= 1.0 => *fade = WindowFade::Steady,
+            WindowFade::Out(_) if level <= 0.0 => *fade = WindowFade::Steady,
+            _ => {}
+        }
+        (level, matches!(current, WindowFade::Out(_)))
+    };
+    if fading_out && level <= 0.0 {
+        let _ = ShowWindow(hwnd, SW_HIDE);
+    } else {
+        set_window_alpha(hwnd, constant_alpha(window_opacity_percent(), level));
+    }
+}
+
+/// Hide the preview window, fading it out when `fade_ms` allows.
+unsafe fn hide_window(hwnd: HWND) {
+    if !IsWindowVisible(hwnd).as_bool() || !begin_fade_out() {
+        let _ = ShowWindow(hwnd, SW_HIDE);
+    }
+}
+
+/// Blend `from`, resampled to the size of `to`, into `to`, keeping `progress`
+/// (0 to 1) of `to`. Both are premultiplied BGRA, so a plain per-channel
+/// blend is correct for transparent pixels too.
+fn blend_crossfade(
+    to: &mut [u8],
+    width: u32,
+    height: u32,
+    from: &[u8],
+    from_width: u32,
+    from_height: u32,
+    progress: f32,
+) {
+    let (w, h) = (width as usize, height as usize);
+    let (from_w, from_h) = (from_width as usize, from_height as usize);
+    if w == 0 || h == 0 || to.len() < w * h * 4 || from.len() < from_w * from_h * 4 {
+        return;
+    }
+    let kept = (progress.clamp(0.0, 1.0) * 256.0) as u32;
+    for y in 0..h {
+        let from_y = y * from_h / h;
+        for x in 0..w {
+            let from_x = x * from_w / w;
+            let dst = (y * w + x) * 4;
+            let src = (from_y * from_w + from_x) * 4;
+            for channel in 0..4 {
+                let blended =
+                    to[dst + channel] as u32 * kept + from[src + channel] as u32 * (256 - kept);
+                to[dst + channel] = (blended >> 8) as u8;
+            }
+        }
+    }
 }
 
 unsafe fn render_layered_preview(hwnd: HWND) {
@@ -1981,10 +5190,6 @@ unsafe fn render_layered_preview(hwnd: HWND) {
         let media_guard = CURRENT_MEDIA.lock().ok()?;
         let media = media_guard.as_ref()?;
 
-        if matches!(media.media_type, MediaType::Video) {
-            return None;
-        }
-
         let width = media.current_width();
         let height = media.current_height();
         let expected_size = width as usize * height as usize * 4;
@@ -1993,7 +5198,10 @@ unsafe fn render_layered_preview(hwnd: HWND) {
         }
 
         let background = current_transparent_background();
-        let pixels = if media.should_draw_streaming_overlay() {
+        // A zoomed still shows part of the full-size file instead.
+        let mut overlaid: Option<Vec<u8>> =
+            media.zoom.as_ref().map(|zoom| zoom.render(width, height));
+        if media.should_draw_streaming_overlay() {
             let elapsed = media
                 .loading_start
                 .map(|s| s.elapsed().as_secs_f32())
@@ -2001,10 +5209,80 @@ unsafe fn render_layered_preview(hwnd: HWND) {
             let angle = elapsed * 2.0 * std::f32::consts::PI * 1.2;
             let mut buf = media.current_pixels().to_vec();
             overlay_loading_spinner(&mut buf, width, height, angle);
-            compose_preview_pixels(&buf, width, height, background)
-        } else {
-            compose_preview_pixels(media.current_pixels(), width, height, background)
+            overlaid = Some(buf);
+        }
+        if let Some(band) = &media.info_band {
+            let buf = overlaid.get_or_insert_with(|| media.shown_pixels().to_vec());
+            band.draw(buf, width, height);
+        }
+        if let Some(band) = &media.metadata_band {
+            let raise = media
+                .info_band
+                .as_ref()
+                .map(|band| band.height())
+                .unwrap_or(0);
+            let buf = overlaid.get_or_insert_with(|| media.shown_pixels().to_vec());
+            band.draw_raised(buf, width, height, raise);
+        }
+        let exif_held = EXIF_HELD.load(Ordering::Acquire);
+        if let Some(band) = media.exif_band.as_ref().filter(|_| exif_held) {
+            let raise = [&media.info_band, &media.metadata_band]
+                .into_iter()
+                .flatten()
+                .map(|band| band.height())
+                .sum();
+            let buf = overlaid.get_or_insert_with(|| media.shown_pixels().to_vec());
+            band.draw_raised(buf, width, height, raise);
+        }
+        let progress = media
+            .animation_progress()
+            .filter(|_| animation_progress_bar_enabled());
+        if let Some((played, decoded)) = progress {
+            let buf = overlaid.get_or_insert_with(|| media.shown_pixels().to_vec());
+            overlay::draw_progress_bar(buf, width, height, played, decoded);
+        }
+        if let Some(histogram) = &media.histogram {
+            let buf = overlaid.get_or_insert_with(|| media.shown_pixels().to_vec());
+            histogram.draw(buf, width, height);
+        }
+        let badge = match &media.zoom {
+            Some(zoom) => zoom.badge.as_ref(),
+            None => media.resolution_badge.as_ref(),
         };
+        if let Some(badge) = badge {
+            let buf = overlaid.get_or_insert_with(|| media.shown_pixels().to_vec());
+            badge.draw(buf, width, height);
+        }
+        let source = overlaid.as_deref().unwrap_or(media.shown_pixels());
+        let mut pixels = compose_preview_pixels(source, width, height, background);
+
+        if let Ok(mut crossfade) = CROSSFADE.lock() {
+            let finished = match crossfade.as_ref() {
+                Some(fade) => {
+                    let progress = fade.started.elapsed().as_secs_f32()
+                        / fade.duration.as_secs_f32().max(0.001);
+                    if progress < 1.0 {
+                        blend_crossfade(
+                            &mut pixels,
+                            width,
+                            height,
+                            &fade.pixels,
+                            fade.width,
+                            fade.height,
+                            progress,
+                        );
+                    }
+                    progress >= 1.0
+                }
+                None => false,
+            };
+            if finished {
+                *crossfade = None;
+            }
+        }
+        if !matches!(media.media_type, MediaType::VideoStream) {
+            color_profile::apply(hwnd, &mut pixels, width, height);
+        }
 
         Some((width, height, pixels))
     })() else {
@@ -2065,31 +5343,189 @@ unsafe fn render_layered_preview(hwnd: HWND) {
     let blend = BLENDFUNCTION {
         BlendOp: AC_SRC_OVER as u8,
         BlendFlags: 0,
-        SourceConstantAlpha: 255,
+        SourceConstantAlpha: paint_window_alpha(),
         AlphaFormat: AC_SRC_ALPHA as u8,
     };
 
-    let _ = UpdateLayeredWindow(
-        hwnd,
-        None,
-        Some(&dst_point),
-        Some(&size),
-        mem_dc,
-        Some(&src_point),
-        COLORREF(0),
-        Some(&blend),
-        ULW_ALPHA,
-    );
+    let _ = UpdateLayeredWindow(
+        hwnd,
+        None,
+        Some(&dst_point),
+        Some(&size),
+        mem_dc,
+        Some(&src_point),
+        COLORREF(0),
+        Some(&blend),
+        ULW_ALPHA,
+    );
+
+    if !old_bitmap.0.is_null() {
+        let _ = SelectObject(mem_dc, old_bitmap);
+    }
+    let _ = DeleteObject(bitmap);
+    let _ = DeleteDC(mem_dc);
+}
+
+/// A still zoomed in past the size it was laid out at.
+struct Zoom {
+    /// The file decoded at full size, BGRA.
+    source: ImageFrame,
+    view: zoom::View,
+    /// The zoom level, shown in place of the resolution badge.
+    badge: Option<overlay::Badge>,
+}
+
+impl Zoom {
+    fn size(&self) -> (u32, u32) {
+        (self.source.width, self.source.height)
+    }
+
+    fn render(&self, width: u32, height: u32) -> Vec<u8> {
+        zoom::render(&self.source.pixels, self.size(), self.view, (width, height))
+    }
+}
+
+/// A left-button press on a zoomed preview, in client coordinates.
+struct PanDrag {
+    start: (i32, i32),
+    last: (i32, i32),
+    panned: bool,
+}
+
+/// Zoom the still on screen by a wheel rotation of `wheel_delta` toward the
+/// screen position `cursor`. The first zoom in decodes the file at full size,
+/// and zooming back out to the fit drops it.
+unsafe fn zoom_preview(hwnd: HWND, path: &PathBuf, wheel_delta: i32, cursor: (i32, i32)) {
+    if providers::find(path).is_some() {
+        return;
+    }
+    let mut rect = RECT::default();
+    if GetWindowRect(hwnd, &mut rect).is_err() {
+        return;
+    }
+    let anchor = ((cursor.0 - rect.left) as f32, (cursor.1 - rect.top) as f32);
+
+    let needs_source = CURRENT_MEDIA
+        .lock()
+        .ok()
+        .and_then(|current| {
+            let media = current.as_ref()?;
+            Some(media.is_zoomable() && media.zoom.is_none())
+        })
+        .unwrap_or(false);
+    let mut source = None;
+    if needs_source {
+        if wheel_delta <= 0 {
+            return;
+        }
+        let Some(img) = decode_still(path, u32::MAX, u32::MAX, false) else {
+            return;
+        };
+        let (width, height) = img.dimensions();
+        source = Some(ImageFrame {
+            pixels: rgba_to_bgra(img.to_rgba8().as_raw()),
+            width,
+            height,
+            delay_ms: 0,
+        });
+    }
+
+    {
+        let Ok(mut current) = CURRENT_MEDIA.lock() else {
+            return;
+        };
+        let Some(media) = current.as_mut().filter(|media| media.is_zoomable()) else {
+            return;
+        };
+        let viewport = (media.current_width(), media.current_height());
+        if let Some(source) = source.filter(|_| media.zoom.is_none()) {
+            let view = zoom::View::fit((source.width, source.height), viewport);
+            media.zoom = Some(Zoom {
+                source,
+                view,
+                badge: None,
+            });
+        }
+        let Some(zoom) = media.zoom.as_mut() else {
+            return;
+        };
+        zoom.view = zoom.view.zoomed(wheel_delta, anchor, zoom.size(), viewport);
+        if zoom.view.is_fit(zoom.size(), viewport) {
+            media.zoom = None;
+        } else {
+            zoom.badge = overlay::Badge::new(viewport.0, viewport.1, &zoom.view.label());
+        }
+    }
+    render_layered_preview(hwnd);
+}
+
+/// Start a left-button press on a zoomed preview, which pans once it moves.
+unsafe fn begin_pan_drag(hwnd: HWND, point: (i32, i32)) {
+    let zoomed = CURRENT_MEDIA
+        .lock()
+        .map(|current| {
+            current
+                .as_ref()
+                .map(|media| media.zoom.is_some())
+                .unwrap_or(false)
+        })
+        .unwrap_or(false);
+    if !zoomed {
+        return;
+    }
+    if let Ok(mut drag) = PAN_DRAG.lock() {
+        *drag = Some(PanDrag {
+            start: point,
+            last: point,
+            panned: false,
+        });
+    }
+    SetCapture(hwnd);
+}
 
-    if !old_bitmap.0.is_null() {
-        let _ = SelectObject(mem_dc, old_bitmap);
+/// Follow a held left button to `point`, panning the zoomed preview.
+unsafe fn continue_pan_drag(hwnd: HWND, point: (i32, i32)) {
+    let delta = PAN_DRAG.lock().ok().and_then(|mut drag| {
+        let drag = drag.as_mut()?;
+        let moved = (point.0 - drag.start.0)
+            .abs()
+            .max((point.1 - drag.start.1).abs());
+        if !drag.panned && moved < PAN_MIN_DRAG_PX {
+            return None;
+        }
+        drag.panned = true;
+        let delta = (point.0 - drag.last.0, point.1 - drag.last.1);
+        drag.last = point;
+        Some(delta)
+    });
+    let Some(delta) = delta.filter(|&delta| delta != (0, 0)) else {
+        return;
+    };
+    if let Ok(mut current) = CURRENT_MEDIA.lock() {
+        let Some(media) = current.as_mut() else {
+            return;
+        };
+        let viewport = (media.current_width(), media.current_height());
+        let Some(zoom) = media.zoom.as_mut() else {
+            return;
+        };
+        zoom.view = zoom.view.panned(delta, zoom.size(), viewport);
     }
-    let _ = DeleteObject(bitmap);
-    let _ = DeleteDC(mem_dc);
+    render_layered_preview(hwnd);
+}
+
+/// Release the left button; whether the press panned rather than clicked.
+unsafe fn end_pan_drag() -> bool {
+    let Some(drag) = PAN_DRAG.lock().ok().and_then(|mut drag| drag.take()) else {
+        return false;
+    };
+    let _ = ReleaseCapture();
+    drag.panned
 }
 
 unsafe fn reset_preview_after_display_change(hwnd: HWND) {
     let _ = ShowWindow(hwnd, SW_HIDE);
+    color_profile::reset();
 
     if let Ok(mut current) = CURRENT_MEDIA.lock() {
         if let Some(ref mut media) = *current {
@@ -2130,6 +5566,24 @@ unsafe extern "system" fn window_proc(
             }
             LRESULT(0)
         }
+        WM_WINDOWPOSCHANGING => {
+            let flags = (*(lparam.0 as *const WINDOWPOS)).flags;
+            if flags.contains(SWP_SHOWWINDOW) && !IsWindowVisible(hwnd).as_bool() {
+                // Start transparent so the last preview's content does not
+                // flash up before the new frame is painted.
+                begin_fade_in();
+                set_window_alpha(hwnd, paint_window_alpha());
+                sound::preview_shown();
+            } else if flags.contains(SWP_HIDEWINDOW) {
+                if let Ok(mut fade) = WINDOW_FADE.lock() {
+                    *fade = WindowFade::Steady;
+                }
+                if IsWindowVisible(hwnd).as_bool() {
+                    sound::preview_hidden();
+                }
+            }
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
         windows::Win32::UI::WindowsAndMessaging::WM_PAINT => {
             let mut ps = PAINTSTRUCT::default();
             let _ = BeginPaint(hwnd, &mut ps);
@@ -2137,33 +5591,60 @@ unsafe extern "system" fn window_proc(
             let _ = EndPaint(hwnd, &ps);
             LRESULT(0)
         }
+        WM_MOUSEWHEEL => {
+            // The high word of `wparam` is the signed rotation; `lparam` holds
+            // the cursor's screen position.
+            let delta = (wparam.0 >> 16) as u16 as i16 as i32;
+            let (x, y) = point_from_lparam(lparam);
+            if PREVIEW_CLICKABLE.load(Ordering::Acquire) {
+                if let Ok(sender) = PREVIEW_SENDER.lock() {
+                    if let Some(ref tx) = *sender {
+                        let _ = tx.send(PreviewMessage::Zoom(delta, x, y));
+                    }
+                }
+            }
+            LRESULT(0)
+        }
+        WM_LBUTTONDOWN => {
+            begin_pan_drag(hwnd, point_from_lparam(lparam));
+            LRESULT(0)
+        }
+        WM_MOUSEMOVE => {
+            continue_pan_drag(hwnd, point_from_lparam(lparam));
+            LRESULT(0)
+        }
+        WM_LBUTTONUP | WM_MBUTTONUP | WM_RBUTTONUP | WM_XBUTTONUP => {
+            // A press that panned a zoomed preview is not a click.
+            if msg == WM_LBUTTONUP && end_pan_drag() {
+                return LRESULT(0);
+            }
+            let button = match msg {
+                WM_LBUTTONUP => MouseButton::Left,
+                WM_MBUTTONUP => MouseButton::Middle,
+                WM_RBUTTONUP => MouseButton::Right,
+                // The high word of `wparam` says which X button.
+                _ if (wparam.0 >> 16) & 0xFFFF == 1 => MouseButton::X1,
+                _ => MouseButton::X2,
+            };
+            if PREVIEW_CLICKABLE.load(Ordering::Acquire) {
+                if let Ok(sender) = PREVIEW_SENDER.lock() {
+                    if let Some(ref tx) = *sender {
+                        let _ = tx.send(PreviewMessage::Click(button));
+                    }
+                }
+            }
+            // X button messages are handled by returning TRUE.
+            LRESULT((msg == WM_XBUTTONUP) as isize)
+        }
         windows::Win32::UI::WindowsAndMessaging::WM_DESTROY => LRESULT(0),
         _ => DefWindowProcW(hwnd, msg, wparam, lparam),
     }
 }
 
-/// Computed preview window layout
-struct PreviewLayout {
-    pos_x: i32,
-    pos_y: i32,
-    max_width: u32,
-    max_height: u32,
-    preview_w: u32,
-    preview_h: u32,
-}
-
-#[derive(Clone, Copy)]
-struct ScreenBounds {
-    left: i32,
-    top: i32,
-    right: i32,
-    bottom: i32,
-}
-
-impl ScreenBounds {
-    fn height(self) -> i32 {
-        self.bottom - self.top
-    }
+fn point_from_lparam(lparam: LPARAM) -> (i32, i32) {
+    let x = (lparam.0 & 0xFFFF) as u16 as i16 as i32;
+    let y = ((lparam.0 >> 16) & 0xFFFF) as u16 as i16 as i32;
+    (x, y)
 }
 
 fn virtual_screen_bounds() -> ScreenBounds {
@@ -2182,287 +5663,25 @@ fn virtual_screen_bounds() -> ScreenBounds {
     }
 }
 
-/// Compute preview layout for mouse hover (relative to cursor position)
-fn compute_mouse_layout(
-    cursor_x: i32,
-    cursor_y: i32,
-    orig_dims: (u32, u32),
-    follow_cursor: bool,
-    bounds: ScreenBounds,
-) -> Option<PreviewLayout> {
-    let offset = 20;
-    let (orig_w, orig_h) = (orig_dims.0 as i32, orig_dims.1 as i32);
-
-    if follow_cursor {
-        let quadrants = [
-            (
-                bounds.right - cursor_x - offset,
-                bounds.bottom - cursor_y - offset,
-                cursor_x + offset,
-                cursor_y + offset,
-            ), // BR
-            (
-                cursor_x - bounds.left - offset,
-                bounds.bottom - cursor_y - offset,
-                bounds.left,
-                cursor_y + offset,
-            ), // BL
-            (
-                bounds.right - cursor_x - offset,
-                cursor_y - bounds.top - offset,
-                cursor_x + offset,
-                bounds.top,
-            ), // TR
-            (
-                cursor_x - bounds.left - offset,
-                cursor_y - bounds.top - offset,
-                bounds.left,
-                bounds.top,
-            ), // TL
-        ];
-
-        let mut best_quadrant = 0;
-        let mut best_scale: f32 = 0.0;
-
-        for (i, &(avail_w, avail_h, _, _)) in quadrants.iter().enumerate() {
-            if avail_w <= 0 || avail_h <= 0 {
-                continue;
-            }
-            let scale_x = avail_w as f32 / orig_w as f32;
-            let scale_y = avail_h as f32 / orig_h as f32;
-            let scale = scale_x.min(scale_y).min(1.0);
-            if scale > best_scale {
-                best_scale = scale;
-                best_quadrant = i;
-            }
-        }
-
-        if best_scale <= 0.0 {
-            return None;
-        }
-
-        let (avail_w, avail_h, _, _) = quadrants[best_quadrant];
-        let max_width = avail_w.max(1) as u32;
-        let max_height = avail_h.max(1) as u32;
-
-        let (preview_w, preview_h) =
-            scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
-        let media_width = preview_w as i32;
-        let media_height = preview_h as i32;
-
-        if media_width <= 0 || media_height <= 0 {
-            return None;
-        }
-
-        let (pos_x, pos_y) = match best_quadrant {
-            0 => (cursor_x + offset, cursor_y + offset),
-            1 => (cursor_x - offset - media_width, cursor_y + offset),
-            2 => (cursor_x + offset, cursor_y - offset - media_height),
-            3 => (
-                cursor_x - offset - media_width,
-                cursor_y - offset - media_height,
-            ),
-            _ => (cursor_x + offset, cursor_y + offset),
-        };
-
-        Some(PreviewLayout {
-            pos_x,
-            pos_y,
-            max_width,
-            max_height,
-            preview_w,
-            preview_h,
-        })
-    } else {
-        let left_width = cursor_x - bounds.left - offset;
-        let right_width = bounds.right - cursor_x - offset;
-        let full_height = bounds.height();
-
-        let left_scale_x = left_width as f32 / orig_w as f32;
-        let left_scale_y = full_height as f32 / orig_h as f32;
-        let left_scale = left_scale_x.min(left_scale_y).min(1.0);
-
-        let right_scale_x = right_width as f32 / orig_w as f32;
-        let right_scale_y = full_height as f32 / orig_h as f32;
-        let right_scale = right_scale_x.min(right_scale_y).min(1.0);
-
-        let (use_left, max_width, max_height) = if left_scale > right_scale && left_width > 0 {
-            (true, left_width.max(1) as u32, full_height as u32)
-        } else if right_width > 0 {
-            (false, right_width.max(1) as u32, full_height as u32)
-        } else {
-            return None;
-        };
-
-        let (preview_w, preview_h) =
-            scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
-        let media_width = preview_w as i32;
-        let media_height = preview_h as i32;
-
-        if media_width <= 0 || media_height <= 0 {
-            return None;
-        }
-
-        let pos_x = if use_left {
-            cursor_x - offset - media_width
-        } else {
-            cursor_x + offset
+/// Work area (without the taskbar and docked toolbars) of the monitor nearest
+/// to `(x, y)`, so previews stay on the monitor they were requested on. Falls
+/// back to the whole virtual screen.
+fn monitor_work_area(x: i32, y: i32) -> ScreenBounds {
+    unsafe {
+        let monitor = MonitorFromPoint(POINT { x, y }, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
         };
-        let pos_y = bounds.top + (full_height - media_height) / 2;
-
-        Some(PreviewLayout {
-            pos_x,
-            pos_y,
-            max_width,
-            max_height,
-            preview_w,
-            preview_h,
-        })
-    }
-}
-
-/// Compute preview layout for keyboard hover (relative to item bounding rect)
-/// Positions the preview so it doesn't block the selected file item
-fn compute_keyboard_layout(
-    item_left: i32,
-    item_top: i32,
-    item_right: i32,
-    item_bottom: i32,
-    orig_dims: (u32, u32),
-    follow_cursor: bool,
-    bounds: ScreenBounds,
-) -> Option<PreviewLayout> {
-    let gap = 10;
-    let (orig_w, orig_h) = (orig_dims.0 as i32, orig_dims.1 as i32);
-
-    if follow_cursor {
-        // Quadrant-based positioning relative to item rect edges
-        let quadrants = [
-            // Bottom-Right of item
-            (
-                bounds.right - item_right - gap,
-                bounds.bottom - item_bottom - gap,
-                item_right + gap,
-                item_bottom + gap,
-            ),
-            // Bottom-Left of item
-            (
-                item_left - bounds.left - gap,
-                bounds.bottom - item_bottom - gap,
-                bounds.left,
-                item_bottom + gap,
-            ),
-            // Top-Right of item
-            (
-                bounds.right - item_right - gap,
-                item_top - bounds.top - gap,
-                item_right + gap,
-                bounds.top,
-            ),
-            // Top-Left of item
-            (
-                item_left - bounds.left - gap,
-                item_top - bounds.top - gap,
-                bounds.left,
-                bounds.top,
-            ),
-        ];
-
-        let mut best_quadrant = 0;
-        let mut best_scale: f32 = 0.0;
-
-        for (i, &(avail_w, avail_h, _, _)) in quadrants.iter().enumerate() {
-            if avail_w <= 0 || avail_h <= 0 {
-                continue;
-            }
-            let scale_x = avail_w as f32 / orig_w as f32;
-            let scale_y = avail_h as f32 / orig_h as f32;
-            let scale = scale_x.min(scale_y).min(1.0);
-            if scale > best_scale {
-                best_scale = scale;
-                best_quadrant = i;
-            }
-        }
-
-        if best_scale <= 0.0 {
-            return None;
-        }
-
-        let (avail_w, avail_h, _, _) = quadrants[best_quadrant];
-        let max_width = avail_w.max(1) as u32;
-        let max_height = avail_h.max(1) as u32;
-
-        let (preview_w, preview_h) =
-            scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
-        let media_width = preview_w as i32;
-        let media_height = preview_h as i32;
-
-        if media_width <= 0 || media_height <= 0 {
-            return None;
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return virtual_screen_bounds();
         }
-
-        let (pos_x, pos_y) = match best_quadrant {
-            0 => (item_right + gap, item_bottom + gap),
-            1 => (item_left - gap - media_width, item_bottom + gap),
-            2 => (item_right + gap, item_top - gap - media_height),
-            3 => (item_left - gap - media_width, item_top - gap - media_height),
-            _ => (item_right + gap, item_bottom + gap),
-        };
-
-        Some(PreviewLayout {
-            pos_x,
-            pos_y,
-            max_width,
-            max_height,
-            preview_w,
-            preview_h,
-        })
-    } else {
-        // Best spot mode: choose left or right side of item
-        let left_width = item_left - bounds.left - gap;
-        let right_width = bounds.right - item_right - gap;
-        let full_height = bounds.height();
-
-        let left_scale_x = left_width as f32 / orig_w as f32;
-        let left_scale_y = full_height as f32 / orig_h as f32;
-        let left_scale = left_scale_x.min(left_scale_y).min(1.0);
-
-        let right_scale_x = right_width as f32 / orig_w as f32;
-        let right_scale_y = full_height as f32 / orig_h as f32;
-        let right_scale = right_scale_x.min(right_scale_y).min(1.0);
-
-        let (use_left, max_width, max_height) = if left_scale > right_scale && left_width > 0 {
-            (true, left_width.max(1) as u32, full_height as u32)
-        } else if right_width > 0 {
-            (false, right_width.max(1) as u32, full_height as u32)
-        } else {
-            return None;
-        };
-
-        let (preview_w, preview_h) =
-            scale_dimensions(orig_dims.0, orig_dims.1, max_width, max_height);
-        let media_width = preview_w as i32;
-        let media_height = preview_h as i32;
-
-        if media_width <= 0 || media_height <= 0 {
-            return None;
+        ScreenBounds {
+            left: info.rcWork.left,
+            top: info.rcWork.top,
+            right: info.rcWork.right,
+            bottom: info.rcWork.bottom,
         }
-
-        let pos_x = if use_left {
-            item_left - gap - media_width
-        } else {
-            item_right + gap
-        };
-        let pos_y = bounds.top + (full_height - media_height) / 2;
-
-        Some(PreviewLayout {
-            pos_x,
-            pos_y,
-            max_width,
-            max_height,
-            preview_w,
-            preview_h,
-        })
     }
 }
 
@@ -2517,18 +5736,29 @@ pub fn run_preview_window() {
 
         // Track current video path to avoid restarting
         let mut current_video_path: Option<PathBuf> = None;
+        // File shown (or being loaded) in the preview, for pinning
+        let mut current_preview_path: Option<PathBuf> = None;
         // Track video position/size for periodic topmost re-assertion
         let mut video_pos: (i32, i32, i32, i32) = (0, 0, 0, 0); // (x, y, w, h)
         let mut last_topmost_check = Instant::now();
+        // Our window is showing the first frame of a video whose player has not appeared yet
+        let mut video_placeholder_shown = false;
 
         // Background loading support
         let (load_tx, load_rx): (Sender<LoadResult>, Receiver<LoadResult>) = channel();
         let load_request_slot: LoadRequestSlot = Arc::new((Mutex::new(None), Condvar::new()));
-        let load_worker = spawn_load_worker(Arc::clone(&load_request_slot), load_tx);
+        let load_workers = spawn_load_workers(&load_request_slot, load_tx);
         let mut current_generation: u64 = 0;
         let mut pending_load: Option<PendingLoad> = None;
+        let mut deferred_load: Option<PendingLoad> = None;
+        // The previous preview's frame, held on screen while the next one loads.
+        let mut crossfade_from: Option<Crossfade> = None;
         let mut pending_load_cancel: Option<Arc<AtomicBool>> = None;
         let mut last_stream_overlay_repaint = Instant::now();
+        let mut last_scrub: Option<(u32, Instant)> = None;
+        // When the last Show arrived, and a Show of a sweep waiting to settle.
+        let mut last_show_at: Option<Instant> = None;
+        let mut settling_show: Option<(PreviewMessage, Instant)> = None;
 
         // Message loop
         let mut msg = MSG::default();
@@ -2570,6 +5800,9 @@ pub fn run_preview_window() {
                 );
             }
 
+            expire_parked_player();
+            trim_media_cache();
+
             // Periodically re-assert topmost on the video window to prevent it
             // from falling behind Explorer or other windows (Bug 2 fix)
             if current_video_path.is_some()
@@ -2578,13 +5811,68 @@ pub fn run_preview_window() {
                 last_topmost_check = Instant::now();
                 let _ =
                     ensure_video_window_topmost(video_pos.0, video_pos.1, video_pos.2, video_pos.3);
+
+                // The player's window is up; retire the first-frame placeholder.
+                if video_placeholder_shown && VIDEO_HWND.load(Ordering::SeqCst) != 0 {
+                    let _ = ShowWindow(hwnd, SW_HIDE);
+                    video_placeholder_shown = false;
+                }
             }
 
             // Advance animation frames if needed
             let mut needs_repaint = false;
+            let scrub_enabled = is_video_hover_scrub_enabled();
             if let Ok(mut media_guard) = CURRENT_MEDIA.lock() {
+                let in_use = media_guard
+                    .as_ref()
+                    .map(MediaData::memory_bytes)
+                    .unwrap_or(0);
+                CURRENT_MEDIA_BYTES.store(in_use, Ordering::Release);
+                let scrubbable = scrub_enabled
+                    && media_guard
+                        .as_ref()
+                        .map(|m| m.is_scrubbable())
+                        .unwrap_or(false);
+                VIDEO_SCRUB_AVAILABLE.store(scrubbable, Ordering::Release);
+                let clickable = media_guard
+                    .as_ref()
+                    .map(|m| m.is_clickable())
+                    .unwrap_or(false);
+                let zoomable = media_guard
+                    .as_ref()
+                    .map(|m| m.is_zoomable())
+                    .unwrap_or(false)
+                    && current_preview_path
+                        .as_ref()
+                        .map(|path| providers::find(path).is_none())
+                        .unwrap_or(false);
+                // A paused animation takes the wheel to step through frames.
+                let paused = ANIMATION_PAUSED.load(Ordering::Acquire);
+                PREVIEW_CLICKABLE.store(clickable || zoomable || paused, Ordering::Release);
+
                 if let Some(ref mut media) = *media_guard {
-                    if media.advance_frame() {
+                    // Hover-scrub: the cursor's horizontal position within the
+                    // preview selects the seek position.
+                    if scrubbable {
+                        if let Some(fraction) = cursor_fraction_over_preview(hwnd) {
+                            let step = (fraction.clamp(0.0, 1.0) * VIDEO_SCRUB_STEPS) as u32;
+                            let due = last_scrub
+                                .map(|(last_step, at)| {
+                                    last_step != step
+                                        && at.elapsed()
+                                            >= Duration::from_millis(VIDEO_SCRUB_MIN_INTERVAL_MS)
+                                })
+                                .unwrap_or(true);
+                            if due && media.scrub_video_stream(fraction) {
+                                last_scrub = Some((step, Instant::now()));
+                            }
+                        }
+                    } else {
+                        last_scrub = None;
+                    }
+
+                    media.poll_scrub();
+                    if !paused && media.advance_frame() {
                         needs_repaint = true;
                     }
                     if media.update_loading_frame() {
@@ -2599,27 +5887,43 @@ pub fn run_preview_window() {
                     }
                 }
             }
+            if is_crossfading() {
+                needs_repaint = true;
+            }
             if needs_repaint {
                 render_layered_preview(hwnd);
             }
+            step_window_fade(hwnd);
 
             // Check for completed background loads
             while let Ok(result) = load_rx.try_recv() {
                 if result.generation == current_generation {
                     match result.media {
-                        Some(media_data) => {
+                        Some(mut media_data) => {
+                            if let (Some(pl), Some(path)) = (&pending_load, &current_preview_path) {
+                                add_resolution_badge(&mut media_data, path, pl.source_size);
+                                add_metadata_band(&mut media_data, path, pl.source_size);
+                                add_exif_band(&mut media_data, path);
+                                add_histogram(&mut media_data);
+                            }
                             let mw = media_data.current_width() as i32;
                             let mh = media_data.current_height() as i32;
+                            let deferred = media_data.deferred_playback;
+                            let mut stages = pending_load
+                                .as_ref()
+                                .map(|pl| pl.trace.clone())
+                                .unwrap_or_default();
 
                             // If window wasn't shown yet (fast load), show it now
                             if let Some(ref pl) = pending_load {
                                 if pl.generation == result.generation && !pl.spinner_shown {
-                                    let _ = MoveWindow(hwnd, pl.pos_x, pl.pos_y, mw, mh, false);
+                                    let (x, y) = pl.layout.position_for(mw, mh);
+                                    let _ = MoveWindow(hwnd, x, y, mw, mh, false);
                                     let _ = SetWindowPos(
                                         hwnd,
                                         HWND_TOPMOST,
-                                        pl.pos_x,
-                                        pl.pos_y,
+                                        x,
+                                        y,
                                         mw,
                                         mh,
                                         SWP_NOACTIVATE | SWP_SHOWWINDOW,
@@ -2634,13 +5938,31 @@ pub fn run_preview_window() {
                                 }
                                 *current = Some(media_data);
                             }
-                            pending_load = None;
+                            deferred_load = pending_load.take().filter(|_| deferred);
                             pending_load_cancel = None;
+                            if let Some(mut fade) = crossfade_from.take() {
+                                fade.started = Instant::now();
+                                if let Ok(mut crossfade) = CROSSFADE.lock() {
+                                    *crossfade = Some(fade);
+                                }
+                            }
+                            let paint_started = Instant::now();
                             render_layered_preview(hwnd);
+                            stages.extend(result.stages);
+                            stages.push(("paint", paint_started.elapsed()));
+                            if let Some(ref path) = current_preview_path {
+                                if trace::enabled() {
+                                    trace::write(path, &stages);
+                                }
+                                record_history(path);
+                                publish_preview_info(hwnd, path);
+                            }
                         }
                         None => {
                             // Loading failed, hide window
+                            crossfade_from = None;
                             let _ = ShowWindow(hwnd, SW_HIDE);
+                            automation::publish(hwnd, None);
                             if let Ok(mut current) = CURRENT_MEDIA.lock() {
                                 if let Some(ref mut existing) = *current {
                                     existing.cancel_background_work();
@@ -2654,29 +5976,72 @@ pub fn run_preview_window() {
                 }
             }
 
+            // Play a video whose playback waited for the system load to drop,
+            // unless another preview has been requested since.
+            if pending_load.is_none() && !system_load::is_high() {
+                let deferred = deferred_load
+                    .take()
+                    .filter(|deferred| deferred.generation == current_generation);
+                if let (Some(deferred), Some(path)) = (deferred, current_preview_path.clone()) {
+                    current_generation += 1;
+                    let load_cancel = Arc::new(AtomicBool::new(false));
+                    pending_load_cancel = Some(Arc::clone(&load_cancel));
+                    queue_load_request(
+                        &load_request_slot,
+                        LoadRequest {
+                            generation: current_generation,
+                            path,
+                            max_width: deferred.layout.max_width,
+                            max_height: deferred.layout.max_height,
+                            cancel: load_cancel,
+                            queued: Instant::now(),
+                        },
+                    );
+                    // The still stays on screen until playback replaces it.
+                    pending_load = Some(PendingLoad {
+                        generation: current_generation,
+                        started: Instant::now(),
+                        spinner_shown: true,
+                        trace: Vec::new(),
+                        ..deferred
+                    });
+                }
+            }
+
+            // Stop holding the previous preview once the next one is slow to load.
+            if crossfade_from
+                .as_ref()
+                .map(|fade| fade.started.elapsed() >= CROSSFADE_HOLD)
+                .unwrap_or(false)
+            {
+                crossfade_from = None;
+                let _ = ShowWindow(hwnd, SW_HIDE);
+            }
+
             // Show loading spinner if a background load has been pending for 3+ seconds
             if let Some(ref mut pl) = pending_load {
                 if !pl.spinner_shown && pl.started.elapsed() >= Duration::from_secs(2) {
                     pl.spinner_shown = true;
-                    let loading = create_loading_media(pl.width, pl.height);
+                    let layout = pl.layout;
+                    let loading = create_loading_media(layout.preview_w, layout.preview_h);
                     if let Ok(mut current) = CURRENT_MEDIA.lock() {
                         *current = Some(loading);
                     }
                     let _ = MoveWindow(
                         hwnd,
-                        pl.pos_x,
-                        pl.pos_y,
-                        pl.width as i32,
-                        pl.height as i32,
+                        layout.pos_x,
+                        layout.pos_y,
+                        layout.preview_w as i32,
+                        layout.preview_h as i32,
                         false,
                     );
                     let _ = SetWindowPos(
                         hwnd,
                         HWND_TOPMOST,
-                        pl.pos_x,
-                        pl.pos_y,
-                        pl.width as i32,
-                        pl.height as i32,
+                        layout.pos_x,
+                        layout.pos_y,
+                        layout.preview_w as i32,
+                        layout.preview_h as i32,
                         SWP_NOACTIVATE | SWP_SHOWWINDOW,
                     );
                     let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
@@ -2689,8 +6054,28 @@ pub fn run_preview_window() {
             // layouts for files the cursor has already left.
             let mut latest_preview_msg: Option<PreviewMessage> = None;
             let mut refresh_requested = false;
+            let mut pin_requested = false;
+            let mut favorite_requested = false;
+            let mut clicked: Option<MouseButton> = None;
+            let mut zoom_requested: Option<(i32, i32, i32)> = None;
+            let mut pause_toggled = false;
+            let mut frames_stepped = 0;
+            let paused = ANIMATION_PAUSED.load(Ordering::Acquire);
             while let Ok(preview_msg) = rx.try_recv() {
                 match preview_msg {
+                    PreviewMessage::Pin => pin_requested = true,
+                    PreviewMessage::Favorite => favorite_requested = true,
+                    PreviewMessage::Click(button) => clicked = Some(button),
+                    PreviewMessage::TogglePause => pause_toggled = !pause_toggled,
+                    PreviewMessage::Step(frames) => frames_stepped += frames,
+                    // Turning the wheel down steps forward, one frame per notch.
+                    PreviewMessage::Zoom(delta, _, _) if paused => {
+                        frames_stepped -= delta.signum();
+                    }
+                    PreviewMessage::Zoom(delta, x, y) => {
+                        let turned = zoom_requested.map(|(turned, _, _)| turned).unwrap_or(0);
+                        zoom_requested = Some((turned + delta, x, y));
+                    }
                     PreviewMessage::Refresh => {
                         if latest_preview_msg.is_none() {
                             refresh_requested = true;
@@ -2703,48 +6088,104 @@ pub fn run_preview_window() {
                 }
             }
 
+            // Sweeping the cursor across a folder sends a Show for every file
+            // passed over. A Show that closely follows another waits for the
+            // cursor to settle, and is dropped for a newer Show or a Hide, so
+            // files merely passed over are never probed or loaded.
+            if let Some(preview_msg) = latest_preview_msg.take() {
+                let is_show = matches!(
+                    preview_msg,
+                    PreviewMessage::Show(..) | PreviewMessage::ShowKeyboard(..)
+                );
+                let sweeping = last_show_at
+                    .map(|at| at.elapsed() < SWEEP_SHOW_INTERVAL)
+                    .unwrap_or(false);
+                if is_show {
+                    last_show_at = Some(Instant::now());
+                }
+                if is_show && sweeping {
+                    settling_show = Some((preview_msg, Instant::now()));
+                } else {
+                    settling_show = None;
+                    latest_preview_msg = Some(preview_msg);
+                }
+            }
+            if settling_show
+                .as_ref()
+                .map(|(_, at)| at.elapsed() >= SWEEP_SHOW_SETTLE)
+                .unwrap_or(false)
+            {
+                latest_preview_msg = settling_show.take().map(|(preview_msg, _)| preview_msg);
+            }
+
             if let Some(preview_msg) = latest_preview_msg {
+                ANIMATION_PAUSED.store(false, Ordering::Release);
                 // Common variables for Show/ShowKeyboard - set in match, used after
                 let mut show_path: Option<PathBuf> = None;
                 let mut show_layout: Option<PreviewLayout> = None;
                 let mut show_is_video: bool = false;
+                let mut show_source_size = (0, 0);
                 let mut show_requested = false;
+                let mut probe_started = Instant::now();
 
                 match preview_msg {
-                    PreviewMessage::Show(path, x, y) => {
+                    PreviewMessage::Show(path, x, y, item) => {
                         show_requested = true;
-                        let bounds = virtual_screen_bounds();
-                        let follow_cursor = CONFIG.lock().map(|c| c.follow_cursor).unwrap_or(true);
+                        probe_started = Instant::now();
+                        let bounds = monitor_work_area(x, y);
+                        let (follow_cursor, anchor_to_item) = CONFIG
+                            .lock()
+                            .map(|c| (c.follow_cursor, c.anchor_to_item))
+                            .unwrap_or((true, false));
 
                         if let Some(orig_dims) = get_media_dimensions(&path) {
-                            let is_video = is_video_file(&path);
-                            if let Some(layout) =
-                                compute_mouse_layout(x, y, orig_dims, follow_cursor, bounds)
-                            {
+                            let is_video = is_video_file(&path) && uses_external_video_player();
+                            let beside_item = item
+                                .filter(|_| anchor_to_item)
+                                .and_then(|item| compute_item_layout(item, orig_dims, bounds));
+                            let placed = fixed_panel_layout(orig_dims, bounds).or(beside_item);
+                            let layout = placed.or_else(|| match item {
+                                Some(item) => compute_mouse_layout_around(
+                                    x,
+                                    y,
+                                    item,
+                                    orig_dims,
+                                    follow_cursor,
+                                    bounds,
+                                ),
+                                None => {
+                                    compute_mouse_layout(x, y, orig_dims, follow_cursor, bounds)
+                                }
+                            });
+                            if let Some(layout) = layout {
                                 show_is_video = is_video;
                                 show_layout = Some(layout);
+                                show_source_size = orig_dims;
                                 show_path = Some(path);
                             }
                         }
                     }
                     PreviewMessage::ShowKeyboard(path, il, it, ir, ib) => {
                         show_requested = true;
-                        let bounds = virtual_screen_bounds();
+                        probe_started = Instant::now();
+                        let bounds = monitor_work_area((il + ir) / 2, (it + ib) / 2);
                         let follow_cursor = CONFIG.lock().map(|c| c.follow_cursor).unwrap_or(true);
 
                         if let Some(orig_dims) = get_media_dimensions(&path) {
-                            let is_video = is_video_file(&path);
-                            if let Some(layout) = compute_keyboard_layout(
-                                il,
-                                it,
-                                ir,
-                                ib,
-                                orig_dims,
-                                follow_cursor,
-                                bounds,
-                            ) {
+                            let is_video = is_video_file(&path) && uses_external_video_player();
+                            let item = ScreenBounds {
+                                left: il,
+                                top: it,
+                                right: ir,
+                                bottom: ib,
+                            };
+                            let layout = fixed_panel_layout(orig_dims, bounds).or_else(|| {
+                                compute_keyboard_layout(item, orig_dims, follow_cursor, bounds)
+                            });
+                            if let Some(layout) = layout {
                                 show_is_video = is_video;
                                 show_layout = Some(layout);
+                                show_source_size = orig_dims;
                                 show_path = Some(path);
                             }
                         }
@@ -2753,157 +6194,244 @@ pub fn run_preview_window() {
                         // Invalidate any pending background loads
                         current_generation += 1;
                         pending_load = None;
+                        crossfade_from = None;
+                        if let Ok(mut crossfade) = CROSSFADE.lock() {
+                            *crossfade = None;
+                        }
                         clear_load_request(&load_request_slot);
                         if let Some(cancel) = pending_load_cancel.take() {
                             cancel.store(true, Ordering::Release);
                         }
 
-                        let _ = ShowWindow(hwnd, SW_HIDE);
+                        hide_window(hwnd);
+                        automation::publish(hwnd, None);
 
-                        // Stop video playback if any
+                        // Stop video playback if any, or park it
                         if let Ok(mut current) = CURRENT_MEDIA.lock() {
-                            if let Some(ref mut media) = *current {
-                                media.cancel_background_work();
-                                stop_video_playback(media);
+                            if let Some(media) = current.take() {
+                                release_video_player(media, current_preview_path.as_ref());
                             }
-                            *current = None;
                         }
                         current_video_path = None;
+                        current_preview_path = None;
                         video_pos = (0, 0, 0, 0);
+                        video_placeholder_shown = false;
                     }
                     PreviewMessage::Refresh => {
                         render_layered_preview(hwnd);
                     }
+                    PreviewMessage::Pin | PreviewMessage::Favorite => {}
                 }
 
                 // Shared load/display logic for Show and ShowKeyboard
                 if let (Some(path), Some(layout)) = (show_path, show_layout) {
+                    let probed = probe_started.elapsed();
+                    prefetch::cancel();
+                    let previous_preview_path = current_preview_path.replace(path.clone());
                     let pos_x = layout.pos_x;
                     let pos_y = layout.pos_y;
                     let media_width = layout.preview_w as i32;
                     let media_height = layout.preview_h as i32;
                     let max_width = layout.max_width;
                     let max_height = layout.max_height;
-                    let preview_w = layout.preview_w;
-                    let preview_h = layout.preview_h;
 
                     if show_is_video {
                         // Cancel any in-flight image load before switching to video.
                         current_generation += 1;
+                        crossfade_from = None;
                         pending_load = None;
                         clear_load_request(&load_request_slot);
                         if let Some(cancel) = pending_load_cancel.take() {
                             cancel.store(true, Ordering::Release);
                         }
 
-                        let no_cancel = Arc::new(AtomicBool::new(false));
-                        if let Some(media_data) =
-                            load_media(&path, max_width, max_height, no_cancel)
-                        {
-                            // For video, hide our window and use ffplay
-                            let _ = ShowWindow(hwnd, SW_HIDE);
-
-                            let process_running = is_video_process_running();
-                            let should_start =
-                                current_video_path.as_ref() != Some(&path) || !process_running;
+                        let process_running = is_video_process_running();
+                        let should_start =
+                            current_video_path.as_ref() != Some(&path) || !process_running;
 
-                            if should_start {
-                                if let Ok(mut media_guard) = CURRENT_MEDIA.lock() {
-                                    if let Some(ref mut media) = *media_guard {
-                                        media.cancel_background_work();
-                                        stop_video_playback(media);
-                                    }
+                        if should_start {
+                            if let Ok(mut media_guard) = CURRENT_MEDIA.lock() {
+                                if let Some(media) = media_guard.take() {
+                                    release_video_player(media, current_video_path.as_ref());
                                 }
+                            }
 
+                            let resumed =
+                                unpark_video_player(&path, pos_x, pos_y, media_width, media_height);
+                            if let Some(media) = resumed {
+                                if let Ok(mut current) = CURRENT_MEDIA.lock() {
+                                    *current = Some(media);
+                                }
+                            } else {
+                                // Start the player first so its startup overlaps with
+                                // decoding the first-frame placeholder below.
+                                let start_secs =
+                                    resume_start_secs(&path, || video_duration_secs(&path));
                                 let video_process = start_video_playback(
                                     &path,
+                                    start_secs,
                                     pos_x,
                                     pos_y,
                                     media_width,
                                     media_height,
                                 );
 
+                                let no_cancel = Arc::new(AtomicBool::new(false));
+                                let placeholder =
+                                    load_media(&path, max_width, max_height, no_cancel);
                                 if let Ok(mut current) = CURRENT_MEDIA.lock() {
-                                    let mut data = media_data;
-                                    data.video_process = video_process;
-                                    *current = Some(data);
+                                    *current = placeholder.map(|mut data| {
+                                        if video_process.is_some() {
+                                            data.playback_clock = Some(PlaybackClock::new(
+                                                &path,
+                                                start_secs,
+                                                current_playback_options().speed,
+                                            ));
+                                        }
+                                        data.video_process = video_process;
+                                        data
+                                    });
                                 }
+                            }
 
-                                current_video_path = Some(path.clone());
-                                video_pos = (pos_x, pos_y, media_width, media_height);
-                                let _ = ensure_video_window_topmost(
+                            current_video_path = Some(path.clone());
+                            video_pos = (pos_x, pos_y, media_width, media_height);
+
+                            // Show the first frame in our window until the
+                            // player's window has been found.
+                            if VIDEO_HWND.load(Ordering::SeqCst) == 0 {
+                                let _ = MoveWindow(
+                                    hwnd,
                                     pos_x,
                                     pos_y,
                                     media_width,
                                     media_height,
+                                    false,
                                 );
-                            } else {
-                                video_pos = (pos_x, pos_y, media_width, media_height);
-                                let _ = ensure_video_window_topmost(
+                                render_layered_preview(hwnd);
+                                let _ = SetWindowPos(
+                                    hwnd,
+                                    HWND_TOPMOST,
                                     pos_x,
                                     pos_y,
                                     media_width,
                                     media_height,
+                                    SWP_NOACTIVATE | SWP_SHOWWINDOW,
                                 );
+                                video_placeholder_shown = true;
+                            } else {
+                                let _ = ShowWindow(hwnd, SW_HIDE);
                             }
+                            let _ = ensure_video_window_topmost(
+                                pos_x,
+                                pos_y,
+                                media_width,
+                                media_height,
+                            );
+                        } else {
+                            video_pos = (pos_x, pos_y, media_width, media_height);
+                            let _ = ensure_video_window_topmost(
+                                pos_x,
+                                pos_y,
+                                media_width,
+                                media_height,
+                            );
                         }
                     } else {
                         // For images/animations, load async
                         if current_video_path.is_some() {
                             if let Ok(mut media_guard) = CURRENT_MEDIA.lock() {
-                                if let Some(ref mut media) = *media_guard {
-                                    media.cancel_background_work();
-                                    stop_video_playback(media);
+                                if let Some(media) = media_guard.take() {
+                                    release_video_player(media, current_video_path.as_ref());
                                 }
                             }
                             current_video_path = None;
                             video_pos = (0, 0, 0, 0);
                         }
+                        video_placeholder_shown = false;
 
                         if let Some(cancel) = pending_load_cancel.take() {
                             cancel.store(true, Ordering::Release);
                         }
+                        // Clear immediately so old pixels never flash while
+                        // the new target is being decoded, except for an image
+                        // that stays up briefly to fade into the next one. A
+                        // playing video is parked in case the cursor comes right
+                        // back.
+                        let fade_duration = crossfade_duration();
                         if let Ok(mut media_guard) = CURRENT_MEDIA.lock() {
-                            if let Some(ref mut media) = *media_guard {
-                                media.cancel_background_work();
+                            if let Some(media) = media_guard.take() {
+                                if !fade_duration.is_zero() && IsWindowVisible(hwnd).as_bool() {
+                                    crossfade_from = media.crossfade_source(fade_duration);
+                                }
+                                release_video_player(media, previous_preview_path.as_ref());
                             }
-                            // Clear immediately so old pixels never flash while
-                            // the new target is being decoded.
-                            *media_guard = None;
                         }
-                        let _ = ShowWindow(hwnd, SW_HIDE);
-
-                        // Start background load; spinner will appear after 2s
+                        if crossfade_from.is_none() {
+                            let _ = ShowWindow(hwnd, SW_HIDE);
+                        }
                         current_generation += 1;
-                        let gen = current_generation;
-                        let load_cancel = Arc::new(AtomicBool::new(false));
-                        pending_load_cancel = Some(Arc::clone(&load_cancel));
-                        pending_load = Some(PendingLoad {
-                            generation: gen,
-                            started: Instant::now(),
-                            pos_x,
-                            pos_y,
-                            width: preview_w,
-                            height: preview_h,
-                            spinner_shown: false,
-                        });
-
-                        queue_load_request(
-                            &load_request_slot,
-                            LoadRequest {
+
+                        if let Some(media) = unpark_video_stream(&path, max_width, max_height) {
+                            pending_load = None;
+                            crossfade_from = None;
+                            clear_load_request(&load_request_slot);
+                            let width = media.current_width() as i32;
+                            let height = media.current_height() as i32;
+                            if let Ok(mut current) = CURRENT_MEDIA.lock() {
+                                *current = Some(media);
+                            }
+                            let (x, y) = layout.position_for(width, height);
+                            let _ = MoveWindow(hwnd, x, y, width, height, false);
+                            render_layered_preview(hwnd);
+                            let _ = SetWindowPos(
+                                hwnd,
+                                HWND_TOPMOST,
+                                x,
+                                y,
+                                width,
+                                height,
+                                SWP_NOACTIVATE | SWP_SHOWWINDOW,
+                            );
+                            record_history(&path);
+                            publish_preview_info(hwnd, &path);
+                        } else {
+                            // Start background load; spinner will appear after 2s
+                            let gen = current_generation;
+                            let load_cancel = Arc::new(AtomicBool::new(false));
+                            pending_load_cancel = Some(Arc::clone(&load_cancel));
+                            pending_load = Some(PendingLoad {
                                 generation: gen,
-                                path,
-                                max_width,
-                                max_height,
-                                cancel: Arc::clone(&load_cancel),
-                            },
-                        );
+                                started: Instant::now(),
+                                layout,
+                                spinner_shown: false,
+                                source_size: show_source_size,
+                                trace: trace::take_resolved(&path)
+                                    .map(|resolved| ("resolve", resolved))
+                                    .into_iter()
+                                    .chain([("probe", probed)])
+                                    .collect(),
+                            });
+
+                            queue_load_request(
+                                &load_request_slot,
+                                LoadRequest {
+                                    generation: gen,
+                                    path,
+                                    max_width,
+                                    max_height,
+                                    cancel: Arc::clone(&load_cancel),
+                                    queued: Instant::now(),
+                                },
+                            );
+                        }
                     }
                 } else if show_requested {
                     // A newer hover target could not produce a layout/path. Treat it
                     // like a hide so stale async loads cannot resurrect old previews.
                     current_generation += 1;
                     pending_load = None;
+                    crossfade_from = None;
                     clear_load_request(&load_request_slot);
                     if let Some(cancel) = pending_load_cancel.take() {
                         cancel.store(true, Ordering::Release);
@@ -2919,23 +6447,67 @@ pub fn run_preview_window() {
                         *current = None;
                     }
                     current_video_path = None;
+                    current_preview_path = None;
                     video_pos = (0, 0, 0, 0);
                 }
             } else if refresh_requested {
                 render_layered_preview(hwnd);
             }
 
+            if let (Some(button), Some(path)) = (clicked, current_preview_path.clone()) {
+                run_click_action(hwnd, &path, button);
+            }
+
+            if let (Some((delta, x, y)), Some(path)) = (zoom_requested, &current_preview_path) {
+                zoom_preview(hwnd, path, delta, (x, y));
+            }
+
+            if pause_toggled {
+                toggle_animation_pause();
+            }
+            if frames_stepped != 0 && is_animation_paused() {
+                let stepped = CURRENT_MEDIA
+                    .lock()
+                    .ok()
+                    .and_then(|mut current| Some(current.as_mut()?.step_frames(frames_stepped)))
+                    .unwrap_or(false);
+                if stepped {
+                    render_layered_preview(hwnd);
+                }
+            }
+
+            if favorite_requested {
+                if let Some(ref path) = current_preview_path {
+                    toggle_favorite(path);
+                }
+            }
+
+            if pin_requested {
+                let pinned = match current_preview_path {
+                    Some(ref path) => pin_current_frame(hwnd, path),
+                    None => false,
+                };
+                if pinned {
+                    hide_preview();
+                }
+            }
+
             std::thread::sleep(std::time::Duration::from_millis(16)); // ~60fps loop is enough and lowers idle CPU
         }
 
-        // Signal the dedicated loader worker to stop and wait for shutdown.
+        // Signal the loader workers to stop and wait for shutdown.
         if let Some(cancel) = pending_load_cancel.take() {
             cancel.store(true, Ordering::Release);
         }
         clear_load_request(&load_request_slot);
         let (_, cvar) = &*load_request_slot;
         cvar.notify_all();
-        let _ = load_worker.join();
+        for worker in load_workers {
+            let _ = worker.join();
+        }
+        if let Ok(mut parked) = PARKED_PLAYER.lock() {
+            *parked = None;
+        }
     }
 }
 
@@ -2944,38 +6516,245 @@ mod tests {
     use super::*;
 
     #[test]
-    fn mouse_layout_respects_virtual_screen_origin() {
-        let bounds = ScreenBounds {
-            left: -3440,
-            top: 0,
-            right: 3840,
-            bottom: 2160,
+    fn crossfade_blends_the_resampled_previous_frame() {
+        // A 2x1 frame fading out of a 1x1 one.
+        let mut to = vec![200, 200, 200, 255, 0, 0, 0, 0];
+        blend_crossfade(&mut to, 2, 1, &[100, 0, 0, 255], 1, 1, 0.5);
+        assert_eq!(to, vec![150, 100, 100, 255, 50, 0, 0, 127]);
+        let mut done = vec![9, 9, 9, 9];
+        blend_crossfade(&mut done, 1, 1, &[0, 0, 0, 0], 1, 1, 1.0);
+        assert_eq!(done, vec![9, 9, 9, 9]);
+    }
+
+    #[test]
+    fn pixel_conversion_swaps_red_and_blue() {
+        assert_eq!(
+            rgba_to_bgra(&[1, 2, 3, 4, 5, 6, 7, 8, 9]),
+            vec![3, 2, 1, 4, 7, 6, 5, 8]
+        );
+        let large: Vec<u8> = (0..PARALLEL_MIN_BYTES * 2)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let converted = rgba_to_bgra(&large);
+        assert_eq!(rgba_to_bgra(&converted), large);
+        let px = &large[PARALLEL_MIN_BYTES..];
+        assert_eq!(
+            converted[PARALLEL_MIN_BYTES..][..4],
+            [px[2], px[1], px[0], px[3]]
+        );
+    }
+
+    #[test]
+    fn animation_frames_shrink_to_fit_the_budget() {
+        assert_eq!(fit_frame_budget((1000, 500), 4, 8_000_000), (1000, 500));
+        assert_eq!(fit_frame_budget((1000, 500), 4, 2_000_000), (500, 250));
+        assert_eq!(fit_frame_budget((4000, 1), 1, 4), (63, 1));
+
+        let queue = Mutex::new(VecDeque::new());
+        let (ring, cancel) = (AtomicBool::new(false), AtomicBool::new(false));
+        let frame = || ImageFrame {
+            pixels: vec![0; 400],
+            width: 10,
+            height: 10,
+            delay_ms: 0,
         };
+        let mut kept = 0;
+        assert!(queue_animation_frame(
+            &queue,
+            frame(),
+            &mut kept,
+            800,
+            (&ring, &cancel)
+        ));
+        assert!(queue_animation_frame(
+            &queue,
+            frame(),
+            &mut kept,
+            800,
+            (&ring, &cancel)
+        ));
+        assert!(!ring.load(Ordering::Acquire));
+        assert!(queue_animation_frame(
+            &queue,
+            frame(),
+            &mut kept,
+            800,
+            (&ring, &cancel)
+        ));
+        assert!(ring.load(Ordering::Acquire));
+        assert_eq!(queue.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn nearest_resize_picks_pixel_centers() {
+        let pixels: Vec<u8> = (0..4u8).flat_map(|value| [value; 4]).collect();
+        let scaled = resize_nearest(&pixels, 4, 1, 2, 2).unwrap();
+        assert_eq!(scaled, [[1; 4], [3; 4], [1; 4], [3; 4]].concat());
+        assert_eq!(resize_nearest(&pixels, 4, 1, 8, 1).unwrap()[4..8], [0; 4]);
+        assert!(resize_nearest(&pixels, 3, 1, 2, 2).is_none());
+    }
+
+    #[test]
+    fn media_cache_drops_oldest_entries_over_budget() {
+        assert_eq!(media_cache_overflow(&[10, 10, 10], 3, 100), 0);
+        assert_eq!(media_cache_overflow(&[10, 10, 10], 2, 100), 1);
+        assert_eq!(media_cache_overflow(&[60, 30, 30], 8, 70), 1);
+        assert_eq!(media_cache_overflow(&[10, 500], 8, 100), 1);
+    }
+
+    #[test]
+    fn memory_cap_drops_cached_entries_until_the_rest_fit() {
+        assert_eq!(memory_cap_overflow(&[10, 10, 10], 30), 0);
+        assert_eq!(memory_cap_overflow(&[10, 10, 10], 15), 2);
+        // Unlike the cache's own budget, the cap can empty the cache.
+        assert_eq!(memory_cap_overflow(&[10, 500], 100), 2);
+        assert_eq!(memory_cap_overflow(&[], 0), 0);
+    }
+
+    #[test]
+    fn resolution_label_shows_downscaling_or_full_size() {
+        assert_eq!(
+            resolution_label((3840, 2160), (1280, 720)).as_deref(),
+            Some("3840\u{d7}2160 \u{2192} 1280\u{d7}720")
+        );
+        assert_eq!(
+            resolution_label((640, 480), (640, 480)).as_deref(),
+            Some("100%")
+        );
+        // Rotated by EXIF orientation.
+        assert_eq!(
+            resolution_label((4000, 3000), (600, 800)).as_deref(),
+            Some("3000\u{d7}4000 \u{2192} 600\u{d7}800")
+        );
+        assert_eq!(resolution_label((320, 240), (640, 480)), None);
+        assert_eq!(resolution_label((0, 0), (640, 480)), None);
+    }
+
+    #[test]
+    fn metadata_line_lists_what_is_known() {
+        assert_eq!(
+            metadata_line(
+                "photo.jpg",
+                (4032, 3024),
+                Some(3_355_443),
+                Some("2024-05-01 14:30".to_string())
+            ),
+            "photo.jpg  \u{b7}  4032\u{d7}3024  \u{b7}  3.2 MB  \u{b7}  2024-05-01 14:30"
+        );
+        assert_eq!(
+            metadata_line("notes.md", (0, 0), Some(912), None),
+            "notes.md  \u{b7}  912 B"
+        );
+    }
 
-        let layout = compute_mouse_layout(-3300, 1000, (800, 600), true, bounds)
-            .expect("layout should fit on the left monitor");
+    #[test]
+    fn file_sizes_count_in_binary_units() {
+        assert_eq!(format_file_size(0), "0 B");
+        assert_eq!(format_file_size(1023), "1023 B");
+        assert_eq!(format_file_size(1024), "1.0 KB");
+        assert_eq!(format_file_size(14_540), "14.2 KB");
+        assert_eq!(format_file_size(3 << 30), "3.0 GB");
+        assert_eq!(format_file_size(5 << 50), "5120.0 TB");
+    }
+
+    #[test]
+    fn premultiplied_alpha_round_trips_visible_pixels() {
+        let mut pixels = [200u8, 100, 50, 128, 255, 255, 255, 0, 10, 20, 30, 255];
+        premultiply_alpha(&mut pixels);
+        assert_eq!(pixels, [100, 50, 25, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
+        unpremultiply_alpha(&mut pixels);
+        assert_eq!(pixels, [199, 100, 50, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
+    }
 
-        assert!(layout.pos_x >= bounds.left);
-        assert!(layout.pos_x + layout.preview_w as i32 <= bounds.right);
-        assert!(layout.pos_y >= bounds.top);
-        assert!(layout.pos_y + layout.preview_h as i32 <= bounds.bottom);
+    #[test]
+    fn window_fade_scales_the_configured_opacity() {
+        let duration = Duration::from_millis(100);
+        assert_eq!(fade_progress(Duration::from_millis(25), duration), 0.25);
+        assert_eq!(fade_progress(Duration::from_millis(300), duration), 1.0);
+        assert_eq!(fade_progress(Duration::ZERO, Duration::ZERO), 1.0);
+        assert_eq!(constant_alpha(100, 1.0), 255);
+        assert_eq!(constant_alpha(80, 1.0), 204);
+        assert_eq!(constant_alpha(80, 0.5), 102);
+        assert_eq!(constant_alpha(100, 0.0), 0);
     }
 
     #[test]
-    fn keyboard_layout_respects_virtual_screen_origin() {
-        let bounds = ScreenBounds {
-            left: -3440,
-            top: 0,
-            right: 3840,
-            bottom: 2160,
+    fn animations_stop_on_the_final_frame_after_their_plays() {
+        assert_eq!(gif_plays(gif::Repeat::Finite(0)), Some(1));
+        assert_eq!(gif_plays(gif::Repeat::Finite(2)), Some(3));
+        assert_eq!(gif_plays(gif::Repeat::Infinite), None);
+
+        let frame = ImageFrame {
+            pixels: vec![0; 4],
+            width: 1,
+            height: 1,
+            delay_ms: 0,
         };
+        let mut media = MediaData::still(frame.clone());
+        media.frames.push(Arc::new(frame));
+        media.media_type = MediaType::AnimatedGif;
+        media.plays = Some(2);
+        for _ in 0..5 {
+            media.advance_frame();
+        }
+        assert_eq!((media.current_frame, media.plays_done), (1, 1));
+
+        media.plays = None;
+        media.advance_frame();
+        assert_eq!(media.current_frame, 1);
+        assert_eq!(media.plays_done, 2);
+    }
 
-        let layout = compute_keyboard_layout(-3200, 900, -3000, 1100, (800, 600), true, bounds)
-            .expect("layout should fit near the selected item");
+    #[test]
+    fn paused_animations_step_through_decoded_frames_and_wrap() {
+        let frame = |delay_ms| ImageFrame {
+            pixels: vec![0; 4],
+            width: 1,
+            height: 1,
+            delay_ms,
+        };
+        let mut media = MediaData::still(frame(0));
+        media.frames.push(Arc::new(frame(1)));
+        media.frames.push(Arc::new(frame(2)));
+        media.media_type = MediaType::AnimatedGif;
+        assert!(media.is_animation());
+
+        assert!(media.step_frames(1));
+        assert_eq!(media.current_frame, 1);
+        assert!(media.step_frames(-2));
+        assert_eq!(media.current_frame, 2);
+        assert!(media.step_frames(1));
+        assert_eq!(media.current_frame, 0);
+        assert!(!media.step_frames(3));
+
+        let mut still = MediaData::still(frame(0));
+        assert!(!still.is_animation());
+        assert!(!still.step_frames(1));
+    }
 
-        assert!(layout.pos_x >= bounds.left);
-        assert!(layout.pos_x + layout.preview_w as i32 <= bounds.right);
-        assert!(layout.pos_y >= bounds.top);
-        assert!(layout.pos_y + layout.preview_h as i32 <= bounds.bottom);
+    #[test]
+    fn gif_frames_are_disposed_before_the_next_is_drawn() {
+        let frame = |left, dispose, rgba: [u8; 4]| gif::Frame {
+            left,
+            width: 1,
+            height: 1,
+            dispose,
+            buffer: rgba.to_vec().into(),
+            ..Default::default()
+        };
+        let red = [255, 0, 0, 255];
+        let green = [0, 255, 0, 255];
+        let blue = [0, 0, 255, 255];
+        let mut canvas = GifCanvas::new(2, 1);
+
+        canvas.draw(&frame(0, gif::DisposalMethod::Keep, red));
+        canvas.draw(&frame(1, gif::DisposalMethod::Background, green));
+        assert_eq!(canvas.pixels, [255, 0, 0, 255, 0, 255, 0, 255]);
+        // The green frame's area is cleared before blue is drawn beside it.
+        canvas.draw(&frame(0, gif::DisposalMethod::Previous, blue));
+        assert_eq!(canvas.pixels, [0, 0, 255, 255, 0, 0, 0, 0]);
+        // Blue is undone, bringing back the red under it.
+        canvas.draw(&frame(1, gif::DisposalMethod::Keep, [0, 0, 0, 0]));
+        assert_eq!(canvas.pixels, [255, 0, 0, 255, 0, 0, 0, 0]);
     }
 }
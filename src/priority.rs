@@ -0,0 +1,142 @@
+//! Process priority and the cores decodes run on.
+//!
+//! The app runs at the `process_priority` class, below normal by default, so
+//! a heavy decode gives way to foreground work. With
+//! `decode_on_efficiency_cores`, decode threads are also kept to the
+//! efficiency cores of a hybrid CPU through CPU sets; on a CPU with one kind
+//! of core it changes nothing.
+
+use crate::config::ProcessPriority;
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use windows::Win32::System::SystemInformation::{
+    GetSystemCpuSetInformation, SYSTEM_CPU_SET_INFORMATION,
+};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, GetCurrentThread, SetPriorityClass, SetThreadSelectedCpuSets,
+    ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, IDLE_PRIORITY_CLASS,
+    NORMAL_PRIORITY_CLASS,
+};
+
+/// `CpuSetInformation`, the only kind of entry `GetSystemCpuSetInformation`
+/// returns so far.
+const CPU_SET_INFORMATION: u32 = 0;
+
+/// CPU set ids of the efficiency cores, empty unless the CPU is hybrid.
+static EFFICIENCY_CPU_SETS: Lazy<Vec<u32>> = Lazy::new(|| efficiency_cpu_sets(&cpu_set_info()));
+
+/// Set the process priority class to `process_priority`.
+pub fn apply_process_priority() {
+    let priority = CONFIG
+        .lock()
+        .map(|c| c.process_priority)
+        .unwrap_or(ProcessPriority::BelowNormal);
+    let class = match priority {
+        ProcessPriority::Idle => IDLE_PRIORITY_CLASS,
+        ProcessPriority::BelowNormal => BELOW_NORMAL_PRIORITY_CLASS,
+        ProcessPriority::Normal => NORMAL_PRIORITY_CLASS,
+        ProcessPriority::AboveNormal => ABOVE_NORMAL_PRIORITY_CLASS,
+    };
+    unsafe {
+        let _ = SetPriorityClass(GetCurrentProcess(), class);
+    }
+}
+
+/// Keep the calling decode thread to the efficiency cores while
+/// `decode_on_efficiency_cores` is on, or let it run anywhere again.
+pub fn use_decode_cores() {
+    let efficiency_only = CONFIG
+        .lock()
+        .map(|c| c.decode_on_efficiency_cores)
+        .unwrap_or(false);
+    let cpu_sets: &[u32] = if efficiency_only {
+        &EFFICIENCY_CPU_SETS
+    } else {
+        &[]
+    };
+    unsafe {
+        // An empty list clears the thread's selection.
+        let _ = SetThreadSelectedCpuSets(GetCurrentThread(), cpu_sets);
+    }
+}
+
+/// The raw entries of `GetSystemCpuSetInformation`.
+fn cpu_set_info() -> Vec<u8> {
+    unsafe {
+        let process = GetCurrentProcess();
+        let mut length = 0u32;
+        let _ = GetSystemCpuSetInformation(None, 0, &mut length, process, 0);
+        let mut buffer = vec![0u8; length as usize];
+        let ok = GetSystemCpuSetInformation(
+            Some(buffer.as_mut_ptr() as *mut SYSTEM_CPU_SET_INFORMATION),
+            length,
+            &mut length,
+            process,
+            0,
+        );
+        if !ok.as_bool() {
+            return Vec::new();
+        }
+        buffer.truncate(length as usize);
+        buffer
+    }
+}
+
+/// The ids of the CPU sets in the lowest efficiency class, when there is more
+/// than one class. Each entry starts with its size and type, then the id at
+/// byte 8 and the efficiency class at byte 18.
+fn efficiency_cpu_sets(info: &[u8]) -> Vec<u32> {
+    let read_u32 = |at: usize| {
+        let bytes = info.get(at..at + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let mut cpu_sets = Vec::new();
+    let mut pos = 0;
+    while let Some(size) = read_u32(pos) {
+        if size == 0 {
+            break;
+        }
+        if read_u32(pos + 4) == Some(CPU_SET_INFORMATION) {
+            if let (Some(id), Some(&class)) = (read_u32(pos + 8), info.get(pos + 18)) {
+                cpu_sets.push((id, class));
+            }
+        }
+        pos += size as usize;
+    }
+
+    let lowest = cpu_sets.iter().map(|&(_, class)| class).min();
+    let highest = cpu_sets.iter().map(|&(_, class)| class).max();
+    if lowest == highest {
+        return Vec::new();
+    }
+    cpu_sets
+        .into_iter()
+        .filter(|&(_, class)| Some(class) == lowest)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u32, class: u8) -> Vec<u8> {
+        let mut entry = vec![0u8; 32];
+        entry[..4].copy_from_slice(&32u32.to_le_bytes());
+        entry[8..12].copy_from_slice(&id.to_le_bytes());
+        entry[18] = class;
+        entry
+    }
+
+    #[test]
+    fn only_hybrid_cpus_have_efficiency_cores() {
+        let hybrid = [entry(256, 1), entry(257, 1), entry(258, 0), entry(259, 0)].concat();
+        assert_eq!(efficiency_cpu_sets(&hybrid), vec![258, 259]);
+
+        let uniform = [entry(256, 0), entry(257, 0)].concat();
+        assert!(efficiency_cpu_sets(&uniform).is_empty());
+        assert!(efficiency_cpu_sets(&[]).is_empty());
+        // A truncated entry is ignored.
+        assert!(efficiency_cpu_sets(&hybrid[..40]).is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+//! Reading entries out of zip-based container formats (painting projects,
+//! game packs). Providers open the archive once and pull the few entries they
+//! need; nothing is extracted to disk.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Entries larger than this are never read into memory.
+const MAX_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+pub struct Archive {
+    zip: zip::ZipArchive<File>,
+}
+
+impl Archive {
+    pub fn open(path: &Path) -> Option<Self> {
+        let zip = zip::ZipArchive::new(File::open(path).ok()?).ok()?;
+        Some(Self { zip })
+    }
+
+    /// Contents of the entry named exactly `name`.
+    pub fn read(&mut self, name: &str) -> Option<Vec<u8>> {
+        let entry = self.zip.by_name(name).ok()?;
+        if entry.size() > MAX_ENTRY_BYTES {
+            return None;
+        }
+        let mut data = Vec::with_capacity(entry.size() as usize);
+        entry.take(MAX_ENTRY_BYTES).read_to_end(&mut data).ok()?;
+        Some(data)
+    }
+
+    /// Contents of the first of `names` present in the archive.
+    pub fn read_first(&mut self, names: &[&str]) -> Option<Vec<u8>> {
+        names.iter().find_map(|name| self.read(name))
+    }
+
+    /// Full name of the least nested entry whose file name is `file_name`
+    /// (case-insensitive). Packs are often zipped with their top-level folder.
+    pub fn find(&self, file_name: &str) -> Option<String> {
+        self.zip
+            .file_names()
+            .filter(|name| {
+                name.rsplit('/')
+                    .next()
+                    .map(|last| last.eq_ignore_ascii_case(file_name))
+                    .unwrap_or(false)
+            })
+            .min_by_key(|name| name.matches('/').count())
+            .map(str::to_string)
+    }
+
+    /// Contents of the entry found by [`Archive::find`].
+    pub fn read_found(&mut self, file_name: &str) -> Option<Vec<u8>> {
+        let name = self.find(file_name)?;
+        self.read(&name)
+    }
+}
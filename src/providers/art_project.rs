@@ -0,0 +1,178 @@
+//! Painting project files: Krita (`.kra`), Clip Studio Paint (`.clip`) and
+//! Procreate (`.procreate`).
+//!
+//! None of these are decoded. Each format embeds a flattened PNG that its
+//! editor writes on save, and that image is what gets previewed:
+//!
+//! - `.kra` and `.procreate` are zip archives; Krita stores the full-size
+//!   `mergedimage.png` (and a small `preview.png`), Procreate stores
+//!   `QuickLook/Thumbnail.png`.
+//! - `.clip` is a chunked container whose `SQLi` chunk is an SQLite database;
+//!   the `CanvasPreview` table holds the PNG. The chunk is copied to a
+//!   temporary file so SQLite can open it.
+
+use super::archive::Archive;
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::preview_window::{file_stamp, static_media_from_image, FileStamp, MediaData};
+use once_cell::sync::Lazy;
+use std::env;
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+const ART_PROJECT_EXTENSIONS: &[&str] = &["kra", "clip", "procreate"];
+
+const KRITA_ENTRIES: &[&str] = &["mergedimage.png", "preview.png"];
+const PROCREATE_ENTRIES: &[&str] = &["QuickLook/Thumbnail.png"];
+
+const CLIP_MAGIC: &[u8] = b"CSFCHUNK";
+const CLIP_HEADER_BYTES: u64 = 24;
+const CLIP_CHUNK_HEADER_BYTES: usize = 16;
+const CLIP_SQLITE_CHUNK: &[u8; 4] = b"SQLi";
+/// Largest database chunk copied out; real files keep it to a few megabytes.
+const CLIP_MAX_SQLITE_BYTES: u64 = 256 * 1024 * 1024;
+
+static CLIP_TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// The embedded image `dimensions` last extracted, kept for the `load` that
+/// follows so a project is opened once per preview.
+static EXTRACTED: Lazy<Mutex<Option<(PathBuf, FileStamp, Vec<u8>)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+pub struct ArtProjectProvider;
+
+impl PreviewProvider for ArtProjectProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, ART_PROJECT_EXTENSIONS)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let png = embedded_image(path)?;
+        let dimensions = image::ImageReader::new(Cursor::new(&png))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok()?;
+        if let (Some(stamp), Ok(mut extracted)) = (file_stamp(path), EXTRACTED.lock()) {
+            *extracted = Some((path.clone(), stamp, png));
+        }
+        Some(dimensions)
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let png = take_extracted(path).or_else(|| embedded_image(path))?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let image = image::load_from_memory(&png).ok()?;
+        Some(static_media_from_image(
+            image,
+            constraints.max_width,
+            constraints.max_height,
+        ))
+    }
+}
+
+/// The image `dimensions` extracted from `path`, unless the file has changed
+/// since.
+fn take_extracted(path: &Path) -> Option<Vec<u8>> {
+    let stamp = file_stamp(path)?;
+    let (extracted_path, extracted_stamp, png) = EXTRACTED.lock().ok()?.take()?;
+    (extracted_path == path && extracted_stamp == stamp).then_some(png)
+}
+
+fn embedded_image(path: &Path) -> Option<Vec<u8>> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "kra" => Archive::open(path)?.read_first(KRITA_ENTRIES),
+        "procreate" => Archive::open(path)?.read_first(PROCREATE_ENTRIES),
+        "clip" => clip_preview(path),
+        _ => None,
+    }
+}
+
+/// Chunk type and payload length from a `CHNK` chunk header.
+fn parse_clip_chunk_header(header: &[u8]) -> Option<([u8; 4], u64)> {
+    if header.len() < CLIP_CHUNK_HEADER_BYTES || &header[..4] != b"CHNK" {
+        return None;
+    }
+    let mut kind = [0u8; 4];
+    kind.copy_from_slice(&header[4..8]);
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&header[8..16]);
+    Some((kind, u64::from_be_bytes(len)))
+}
+
+/// Copy the SQLite chunk out of a `.clip` file.
+fn read_clip_database(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0u8; 8];
+    file.read_exact(&mut magic).ok()?;
+    if magic != CLIP_MAGIC {
+        return None;
+    }
+
+    file.seek(SeekFrom::Start(CLIP_HEADER_BYTES)).ok()?;
+    let mut header = [0u8; CLIP_CHUNK_HEADER_BYTES];
+    while file.read_exact(&mut header).is_ok() {
+        let (kind, len) = parse_clip_chunk_header(&header)?;
+        if &kind == CLIP_SQLITE_CHUNK {
+            if len > CLIP_MAX_SQLITE_BYTES {
+                return None;
+            }
+            let mut data = Vec::with_capacity(len as usize);
+            file.by_ref().take(len).read_to_end(&mut data).ok()?;
+            return Some(data);
+        }
+        file.seek(SeekFrom::Current(i64::try_from(len).ok()?))
+            .ok()?;
+    }
+    None
+}
+
+fn clip_preview(path: &Path) -> Option<Vec<u8>> {
+    let database = read_clip_database(path)?;
+    let temp_path = env::temp_dir().join(format!(
+        "rust-hover-preview-{}-{}.sqlite",
+        std::process::id(),
+        CLIP_TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&temp_path, database).ok()?;
+
+    let png = rusqlite::Connection::open_with_flags(
+        &temp_path,
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )
+    .ok()
+    .and_then(|connection| {
+        connection
+            .query_row("SELECT ImageData FROM CanvasPreview LIMIT 1", [], |row| {
+                row.get::<_, Vec<u8>>(0)
+            })
+            .ok()
+    });
+
+    let _ = fs::remove_file(&temp_path);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_chunk_header_reads_kind_and_big_endian_length() {
+        let mut header = b"CHNKSQLi".to_vec();
+        header.extend_from_slice(&0x0001_0203u64.to_be_bytes());
+        assert_eq!(
+            parse_clip_chunk_header(&header),
+            Some((*b"SQLi", 0x0001_0203))
+        );
+        assert_eq!(parse_clip_chunk_header(b"CHNKHead"), None);
+        header[0] = b'X';
+        assert_eq!(parse_clip_chunk_header(&header), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Blender (`.blend`) files.
+//!
+//! Blender writes a small preview of the scene into a `TEST` block near the
+//! start of the file (when "Save Preview Images" is on, the default). The file
+//! header gives the pointer size, byte order and the Blender version that
+//! saved it; blocks are walked until `TEST` turns up. Gzip (pre 3.0) and Zstd
+//! (3.0+) compressed files are decompressed as a stream, so only the first few
+//! blocks are ever inflated. The version is drawn as a caption.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+const BLEND_EXTENSIONS: &[&str] = &["blend"];
+
+const BLEND_MAGIC: &[u8] = b"BLENDER";
+const LEGACY_HEADER_BYTES: usize = 12;
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// The preview follows the render settings block; give up well after that.
+const MAX_BLOCKS_SCANNED: usize = 32;
+const MAX_THUMBNAIL_SIDE: u32 = 1024;
+/// Size and RGBA pixels of the largest thumbnail read.
+const MAX_THUMBNAIL_BYTES: u64 = 8 + 4 * (MAX_THUMBNAIL_SIDE as u64).pow(2);
+
+const PANEL_WIDTH: u32 = 360;
+const PANEL_HEIGHT: u32 = 96;
+
+pub struct BlendProvider;
+
+impl PreviewProvider for BlendProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, BLEND_EXTENSIONS)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let blend = read_blend(path)?;
+        Some(
+            blend
+                .thumbnail
+                .map(|image| image.dimensions())
+                .unwrap_or((PANEL_WIDTH, PANEL_HEIGHT)),
+        )
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let blend = read_blend(path)?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let version = format!("Blender {}", format_version(blend.header.version));
+        let Some(thumbnail) = blend.thumbnail else {
+            let lines = vec![version, "No embedded preview".to_string()];
+            let panel = overlay::render_text_panel(PANEL_WIDTH, PANEL_HEIGHT, &lines)?;
+            return Some(static_media_from_image(
+                image::DynamicImage::ImageRgba8(panel),
+                constraints.max_width,
+                constraints.max_height,
+            ));
+        };
+
+        let mut media = static_media_from_image(
+            image::DynamicImage::ImageRgba8(thumbnail),
+            constraints.max_width,
+            constraints.max_height,
+        );
+        overlay::draw_caption(media.first_frame_mut(), &[version]);
+        Some(media)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlendHeader {
+    little_endian: bool,
+    pointer_size: usize,
+    /// Bytes in each block header; depends on the header format.
+    block_header_len: usize,
+    /// `major * 100 + minor`, e.g. 402 for 4.2.
+    version: u32,
+}
+
+impl BlendHeader {
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let raw = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian {
+            u32::from_le_bytes(raw)
+        } else {
+            u32::from_be_bytes(raw)
+        }
+    }
+}
+
+struct BlendFile {
+    header: BlendHeader,
+    thumbnail: Option<image::RgbaImage>,
+}
+
+fn parse_digits(bytes: &[u8]) -> Option<u32> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+/// Length of the file header, known from its first 12 bytes.
+fn file_header_len(prefix: &[u8]) -> Option<usize> {
+    if prefix.len() < LEGACY_HEADER_BYTES || !prefix.starts_with(BLEND_MAGIC) {
+        return None;
+    }
+    if prefix[7].is_ascii_digit() {
+        // Blender 5.0+: "BLENDER" + header size + "-" + format + "v" + version.
+        parse_digits(&prefix[7..9]).map(|len| len as usize)
+    } else {
+        Some(LEGACY_HEADER_BYTES)
+    }
+}
+
+fn parse_file_header(bytes: &[u8]) -> Option<BlendHeader> {
+    let len = file_header_len(bytes)?;
+    let bytes = bytes.get(..len)?;
+    if len == LEGACY_HEADER_BYTES {
+        // "BLENDER" + '_' (4-byte) or '-' (8-byte) pointers + 'v' (little)
+        // or 'V' (big endian) + three version digits.
+        let pointer_size = match bytes[7] {
+            b'_' => 4,
+            b'-' => 8,
+            _ => return None,
+        };
+        let little_endian = match bytes[8] {
+            b'v' => true,
+            b'V' => false,
+            _ => return None,
+        };
+        return Some(BlendHeader {
+            little_endian,
+            pointer_size,
+            block_header_len: 16 + pointer_size,
+            version: parse_digits(&bytes[9..12])?,
+        });
+    }
+
+    if bytes.len() < 17 || bytes[9] != b'-' || bytes[12] != b'v' {
+        return None;
+    }
+    let format = parse_digits(&bytes[10..12])?;
+    if format != 1 {
+        return None;
+    }
+    Some(BlendHeader {
+        little_endian: true,
+        pointer_size: 8,
+        // code, SDNA index, old address, 64-bit length, 64-bit count.
+        block_header_len: 32,
+        version: parse_digits(&bytes[13..17])?,
+    })
+}
+
+/// Block code and payload length.
+fn parse_block_header(header: &BlendHeader, bytes: &[u8]) -> Option<([u8; 4], u64)> {
+    let bytes = bytes.get(..header.block_header_len)?;
+    let mut code = [0u8; 4];
+    code.copy_from_slice(&bytes[..4]);
+    let len = if header.block_header_len == 32 {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&bytes[16..24]);
+        u64::from_le_bytes(raw)
+    } else {
+        header.read_u32(&bytes[4..8]) as u64
+    };
+    Some((code, len))
+}
+
+/// `TEST` payload: width, height, then bottom-up RGBA rows.
+fn parse_thumbnail(header: &BlendHeader, data: &[u8]) -> Option<image::RgbaImage> {
+    let width = header.read_u32(data.get(..4)?);
+    let height = header.read_u32(data.get(4..8)?);
+    if width == 0 || height == 0 || width > MAX_THUMBNAIL_SIDE || height > MAX_THUMBNAIL_SIDE {
+        return None;
+    }
+    let row_bytes = width as usize * 4;
+    let pixels = data.get(8..8 + row_bytes * height as usize)?;
+    let flipped: Vec<u8> = pixels
+        .chunks_exact(row_bytes)
+        .rev()
+        .flatten()
+        .copied()
+        .collect();
+    image::RgbaImage::from_raw(width, height, flipped)
+}
+
+fn open_stream(path: &Path) -> Option<Box<dyn Read>> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).ok()?;
+    let file = io::Cursor::new(magic).chain(file);
+    if magic.starts_with(GZIP_MAGIC) {
+        Some(Box::new(flate2::read::GzDecoder::new(file)))
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Some(Box::new(zstd::stream::read::Decoder::new(file).ok()?))
+    } else {
+        Some(Box::new(file))
+    }
+}
+
+fn read_blend(path: &Path) -> Option<BlendFile> {
+    let mut stream = open_stream(path)?;
+
+    let mut header_bytes = vec![0u8; LEGACY_HEADER_BYTES];
+    stream.read_exact(&mut header_bytes).ok()?;
+    let header_len = file_header_len(&header_bytes)?;
+    if header_len > LEGACY_HEADER_BYTES {
+        header_bytes.resize(header_len, 0);
+        stream
+            .read_exact(&mut header_bytes[LEGACY_HEADER_BYTES..])
+            .ok()?;
+    }
+    let header = parse_file_header(&header_bytes)?;
+
+    let mut block_header = vec![0u8; header.block_header_len];
+    for _ in 0..MAX_BLOCKS_SCANNED {
+        if stream.read_exact(&mut block_header).is_err() {
+            break;
+        }
+        let Some((code, len)) = parse_block_header(&header, &block_header) else {
+            break;
+        };
+        match &code {
+            b"TEST" => {
+                if len > MAX_THUMBNAIL_BYTES {
+                    break;
+                }
+                let mut data = Vec::with_capacity(len as usize);
+                stream.by_ref().take(len).read_to_end(&mut data).ok()?;
+                return Some(BlendFile {
+                    header,
+                    thumbnail: parse_thumbnail(&header, &data),
+                });
+            }
+            b"DNA1" | b"ENDB" => break,
+            _ => {
+                io::copy(&mut stream.by_ref().take(len), &mut io::sink()).ok()?;
+            }
+        }
+    }
+
+    Some(BlendFile {
+        header,
+        thumbnail: None,
+    })
+}
+
+/// "2.79", "3.6", "4.2" from the header's version number.
+fn format_version(version: u32) -> String {
+    format!("{}.{}", version / 100, version % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_and_current_file_headers() {
+        let legacy = parse_file_header(b"BLENDER-v306").expect("legacy header");
+        assert_eq!(legacy.pointer_size, 8);
+        assert!(legacy.little_endian);
+        assert_eq!(legacy.block_header_len, 24);
+        assert_eq!(legacy.version, 306);
+
+        let big = parse_file_header(b"BLENDER_V249").expect("big endian header");
+        assert_eq!(big.pointer_size, 4);
+        assert!(!big.little_endian);
+
+        let current = parse_file_header(b"BLENDER17-01v0500").expect("current header");
+        assert_eq!(current.block_header_len, 32);
+        assert_eq!(current.version, 500);
+        assert_eq!(file_header_len(b"BLENDER17-01v0500"), Some(17));
+
+        assert!(parse_file_header(b"NOTBLENDER00").is_none());
+    }
+
+    #[test]
+    fn block_header_length_follows_header_format() {
+        let legacy = parse_file_header(b"BLENDER_v279").unwrap();
+        let mut block = b"TEST".to_vec();
+        block.extend_from_slice(&1234u32.to_le_bytes());
+        block.extend_from_slice(&[0; 12]);
+        assert_eq!(parse_block_header(&legacy, &block), Some((*b"TEST", 1234)));
+
+        let current = parse_file_header(b"BLENDER17-01v0500").unwrap();
+        let mut block = b"REND".to_vec();
+        block.extend_from_slice(&[0; 12]);
+        block.extend_from_slice(&99u64.to_le_bytes());
+        block.extend_from_slice(&1u64.to_le_bytes());
+        assert_eq!(parse_block_header(&current, &block), Some((*b"REND", 99)));
+    }
+
+    #[test]
+    fn thumbnail_rows_are_flipped() {
+        let header = parse_file_header(b"BLENDER-v402").unwrap();
+        let mut data = Vec::new();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[1, 1, 1, 255, 2, 2, 2, 255]);
+        let image = parse_thumbnail(&header, &data).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [2, 2, 2, 255]);
+        assert_eq!(format_version(402), "4.2");
+        assert_eq!(format_version(279), "2.79");
+    }
+}
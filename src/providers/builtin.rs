@@ -0,0 +1,51 @@
+use super::{is_image, is_video, LoadConstraints, PreviewProvider};
+use crate::preview_window::{
+    image_media_dimensions, load_image_media, load_video_media, video_media_dimensions, MediaData,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Static and animated raster images decoded through `image`, `gif` and libwebp.
+pub struct ImageProvider;
+
+impl PreviewProvider for ImageProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        is_image(path)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        image_media_dimensions(path)
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        load_image_media(
+            path,
+            constraints.max_width,
+            constraints.max_height,
+            Arc::clone(&constraints.cancel),
+        )
+    }
+}
+
+/// Videos. With the in-process backend this starts the frame stream; with an
+/// external player it supplies the placeholder shown while the player starts.
+pub struct VideoProvider;
+
+impl PreviewProvider for VideoProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        is_video(path)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        video_media_dimensions(path)
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        load_video_media(
+            path,
+            constraints.max_width,
+            constraints.max_height,
+            Arc::clone(&constraints.cancel),
+        )
+    }
+}
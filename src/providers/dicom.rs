@@ -0,0 +1,708 @@
+//! DICOM (`.dcm`) medical images.
+//!
+//! A small Part 10 reader that walks the dataset up to Pixel Data and renders
+//! the first frame. Native little-endian pixel data (explicit or implicit VR)
+//! is windowed with the file's Window Center/Width, or the full value range
+//! when those are absent. Baseline JPEG encapsulated pixel data is decoded
+//! through `image`; other compressed transfer syntaxes are not supported.
+//!
+//! A caption with modality, study and patient fields is drawn under the image.
+//! `dicom_redact_patient_data=true` removes the patient fields.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::dates::{self, CivilTime};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use crate::CONFIG;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+const DICOM_EXTENSIONS: &[&str] = &["dcm", "dicom"];
+
+/// Bytes read when only the header is needed. Pixel Data is normally the last
+/// element, so this is plenty for everything in front of it.
+const DICOM_HEADER_PROBE_BYTES: u64 = 1024 * 1024;
+
+const UNDEFINED_LENGTH: u32 = 0xFFFF_FFFF;
+
+/// Largest width or height decoded, as for other images.
+const MAX_DIMENSION: u32 = 16384;
+
+const TS_IMPLICIT_LITTLE: &str = "1.2.840.10008.1.2";
+const TS_EXPLICIT_BIG: &str = "1.2.840.10008.1.2.2";
+const TS_DEFLATED: &str = "1.2.840.10008.1.2.1.99";
+const TS_JPEG_BASELINE: &str = "1.2.840.10008.1.2.4.50";
+const TS_JPEG_EXTENDED: &str = "1.2.840.10008.1.2.4.51";
+
+const TAG_TRANSFER_SYNTAX: (u16, u16) = (0x0002, 0x0010);
+const TAG_STUDY_DATE: (u16, u16) = (0x0008, 0x0020);
+const TAG_MODALITY: (u16, u16) = (0x0008, 0x0060);
+const TAG_STUDY_DESCRIPTION: (u16, u16) = (0x0008, 0x1030);
+const TAG_PATIENT_NAME: (u16, u16) = (0x0010, 0x0010);
+const TAG_PATIENT_ID: (u16, u16) = (0x0010, 0x0020);
+const TAG_PATIENT_BIRTH_DATE: (u16, u16) = (0x0010, 0x0030);
+const TAG_SAMPLES_PER_PIXEL: (u16, u16) = (0x0028, 0x0002);
+const TAG_PHOTOMETRIC: (u16, u16) = (0x0028, 0x0004);
+const TAG_PLANAR_CONFIGURATION: (u16, u16) = (0x0028, 0x0006);
+const TAG_ROWS: (u16, u16) = (0x0028, 0x0010);
+const TAG_COLUMNS: (u16, u16) = (0x0028, 0x0011);
+const TAG_BITS_ALLOCATED: (u16, u16) = (0x0028, 0x0100);
+const TAG_BITS_STORED: (u16, u16) = (0x0028, 0x0101);
+const TAG_PIXEL_REPRESENTATION: (u16, u16) = (0x0028, 0x0103);
+const TAG_WINDOW_CENTER: (u16, u16) = (0x0028, 0x1050);
+const TAG_WINDOW_WIDTH: (u16, u16) = (0x0028, 0x1051);
+const TAG_RESCALE_INTERCEPT: (u16, u16) = (0x0028, 0x1052);
+const TAG_RESCALE_SLOPE: (u16, u16) = (0x0028, 0x1053);
+const TAG_PIXEL_DATA: (u16, u16) = (0x7FE0, 0x0010);
+const TAG_ITEM: (u16, u16) = (0xFFFE, 0xE000);
+const TAG_ITEM_DELIMITATION: (u16, u16) = (0xFFFE, 0xE00D);
+const TAG_SEQUENCE_DELIMITATION: (u16, u16) = (0xFFFE, 0xE0DD);
+
+pub struct DicomProvider;
+
+impl PreviewProvider for DicomProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, DICOM_EXTENSIONS)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let mut data = Vec::new();
+        File::open(path)
+            .ok()?
+            .take(DICOM_HEADER_PROBE_BYTES)
+            .read_to_end(&mut data)
+            .ok()?;
+        let header = parse_header(&data)?;
+        Some((header.columns, header.rows))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let data = std::fs::read(path).ok()?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let header = parse_header(&data)?;
+        let rgba = decode_first_frame(&header, &data)?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let mut media = static_media_from_image(
+            image::DynamicImage::ImageRgba8(rgba),
+            constraints.max_width,
+            constraints.max_height,
+        );
+
+        let redact = CONFIG
+            .lock()
+            .map(|cfg| cfg.dicom_redact_patient_data)
+            .unwrap_or(true);
+        overlay::draw_caption(media.first_frame_mut(), &caption_lines(&header, redact));
+        Some(media)
+    }
+}
+
+#[derive(Debug, Default)]
+struct DicomHeader {
+    transfer_syntax: String,
+    rows: u32,
+    columns: u32,
+    samples_per_pixel: u32,
+    photometric: String,
+    planar_configuration: u32,
+    bits_allocated: u32,
+    bits_stored: u32,
+    pixel_representation: u32,
+    window_center: Option<f64>,
+    window_width: Option<f64>,
+    rescale_slope: Option<f64>,
+    rescale_intercept: Option<f64>,
+    modality: Option<String>,
+    study_date: Option<String>,
+    study_description: Option<String>,
+    patient_name: Option<String>,
+    patient_id: Option<String>,
+    patient_birth_date: Option<String>,
+    /// Offset of the Pixel Data value and its length (`None` = encapsulated).
+    pixel_data: Option<(usize, Option<usize>)>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn u16(&mut self) -> Option<u16> {
+        let bytes = self.data.get(self.pos..self.pos + 2)?;
+        self.pos += 2;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.data.get(self.pos..self.pos + 4)?;
+        self.pos += 4;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn peek_group(&self) -> Option<u16> {
+        let bytes = self.data.get(self.pos..self.pos + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+struct Element<'a> {
+    tag: (u16, u16),
+    vr: [u8; 2],
+    length: u32,
+    value: &'a [u8],
+}
+
+fn has_long_length(vr: &[u8; 2]) -> bool {
+    matches!(
+        vr,
+        b"OB"
+            | b"OD"
+            | b"OF"
+            | b"OL"
+            | b"OV"
+            | b"OW"
+            | b"SQ"
+            | b"SV"
+            | b"UC"
+            | b"UN"
+            | b"UR"
+            | b"UT"
+            | b"UV"
+    )
+}
+
+/// Read one data element header. The value is only consumed when its length
+/// is defined; undefined-length values are left for the caller.
+fn read_element<'a>(reader: &mut Reader<'a>, explicit: bool) -> Option<Element<'a>> {
+    let tag = (reader.u16()?, reader.u16()?);
+    if tag.0 == 0xFFFE {
+        let length = reader.u32()?;
+        return Some(Element {
+            tag,
+            vr: *b"  ",
+            length,
+            value: &[],
+        });
+    }
+
+    let (vr, length) = if explicit {
+        let raw = reader.bytes(2)?;
+        let vr = [raw[0], raw[1]];
+        if has_long_length(&vr) {
+            reader.u16()?;
+            (vr, reader.u32()?)
+        } else {
+            (vr, reader.u16()? as u32)
+        }
+    } else {
+        (*b"  ", reader.u32()?)
+    };
+
+    let value = if length == UNDEFINED_LENGTH {
+        &[]
+    } else {
+        reader.bytes(length as usize)?
+    };
+
+    Some(Element {
+        tag,
+        vr,
+        length,
+        value,
+    })
+}
+
+/// Skip the items of an undefined-length sequence, including nested ones.
+fn skip_undefined_sequence(reader: &mut Reader, explicit: bool) -> Option<()> {
+    loop {
+        let item = read_element(reader, explicit)?;
+        match item.tag {
+            TAG_SEQUENCE_DELIMITATION => return Some(()),
+            TAG_ITEM if item.length == UNDEFINED_LENGTH => loop {
+                let element = read_element(reader, explicit)?;
+                if element.tag == TAG_ITEM_DELIMITATION {
+                    break;
+                }
+                if element.length == UNDEFINED_LENGTH {
+                    skip_undefined_sequence(reader, explicit)?;
+                }
+            },
+            TAG_ITEM => {
+                reader.bytes(item.length as usize)?;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn text_value(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value)
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// First value of a multi-valued decimal string such as `40\400`.
+fn decimal_value(value: &[u8]) -> Option<f64> {
+    text_value(value)?.split('\\').next()?.trim().parse().ok()
+}
+
+fn us_value(element: &Element) -> Option<u32> {
+    if element.vr == *b"  " || element.vr == *b"US" {
+        let bytes = element.value.get(0..2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]) as u32)
+    } else {
+        text_value(element.value)?.parse().ok()
+    }
+}
+
+fn parse_header(data: &[u8]) -> Option<DicomHeader> {
+    let mut reader = Reader { data, pos: 0 };
+    if data.get(128..132) == Some(b"DICM".as_slice()) {
+        reader.pos = 132;
+    }
+
+    let mut header = DicomHeader {
+        transfer_syntax: TS_IMPLICIT_LITTLE.to_string(),
+        samples_per_pixel: 1,
+        ..Default::default()
+    };
+
+    // File meta information is always explicit VR little endian.
+    let mut saw_meta = false;
+    while reader.peek_group() == Some(0x0002) {
+        let element = read_element(&mut reader, true)?;
+        if element.tag == TAG_TRANSFER_SYNTAX {
+            header.transfer_syntax = text_value(element.value)?;
+        }
+        saw_meta = true;
+    }
+    if !saw_meta && reader.pos == 132 {
+        return None;
+    }
+    if header.transfer_syntax == TS_EXPLICIT_BIG || header.transfer_syntax == TS_DEFLATED {
+        return None;
+    }
+    let explicit = header.transfer_syntax != TS_IMPLICIT_LITTLE;
+
+    while let Some(element) = read_element(&mut reader, explicit) {
+        if element.tag == TAG_PIXEL_DATA {
+            header.pixel_data = if element.length == UNDEFINED_LENGTH {
+                Some((reader.pos, None))
+            } else {
+                Some((reader.pos - element.value.len(), Some(element.value.len())))
+            };
+            break;
+        }
+        if element.length == UNDEFINED_LENGTH {
+            if skip_undefined_sequence(&mut reader, explicit).is_none() {
+                break;
+            }
+            continue;
+        }
+
+        match element.tag {
+            TAG_STUDY_DATE => header.study_date = text_value(element.value),
+            TAG_MODALITY => header.modality = text_value(element.value),
+            TAG_STUDY_DESCRIPTION => header.study_description = text_value(element.value),
+            TAG_PATIENT_NAME => {
+                header.patient_name = text_value(element.value).map(|name| name.replace('^', " "))
+            }
+            TAG_PATIENT_ID => header.patient_id = text_value(element.value),
+            TAG_PATIENT_BIRTH_DATE => header.patient_birth_date = text_value(element.value),
+            TAG_SAMPLES_PER_PIXEL => header.samples_per_pixel = us_value(&element).unwrap_or(1),
+            TAG_PHOTOMETRIC => header.photometric = text_value(element.value).unwrap_or_default(),
+            TAG_PLANAR_CONFIGURATION => {
+                header.planar_configuration = us_value(&element).unwrap_or(0)
+            }
+            TAG_ROWS => header.rows = us_value(&element).unwrap_or(0),
+            TAG_COLUMNS => header.columns = us_value(&element).unwrap_or(0),
+            TAG_BITS_ALLOCATED => header.bits_allocated = us_value(&element).unwrap_or(0),
+            TAG_BITS_STORED => header.bits_stored = us_value(&element).unwrap_or(0),
+            TAG_PIXEL_REPRESENTATION => {
+                header.pixel_representation = us_value(&element).unwrap_or(0)
+            }
+            TAG_WINDOW_CENTER => header.window_center = decimal_value(element.value),
+            TAG_WINDOW_WIDTH => header.window_width = decimal_value(element.value),
+            TAG_RESCALE_INTERCEPT => header.rescale_intercept = decimal_value(element.value),
+            TAG_RESCALE_SLOPE => header.rescale_slope = decimal_value(element.value),
+            _ => {}
+        }
+    }
+
+    if header.rows == 0 || header.columns == 0 {
+        return None;
+    }
+    Some(header)
+}
+
+/// DICOM PS3.3 C.11.2.1.2 linear VOI LUT, mapped to 0..=255.
+fn apply_window(value: f64, center: f64, width: f64) -> u8 {
+    let width = width.max(1.0);
+    let lower = center - 0.5 - (width - 1.0) / 2.0;
+    let upper = center - 0.5 + (width - 1.0) / 2.0;
+    if value <= lower {
+        0
+    } else if value > upper {
+        255
+    } else {
+        (((value - (center - 0.5)) / (width - 1.0).max(1.0) + 0.5) * 255.0).clamp(0.0, 255.0) as u8
+    }
+}
+
+fn decode_first_frame(header: &DicomHeader, data: &[u8]) -> Option<image::RgbaImage> {
+    let (offset, length) = header.pixel_data?;
+    match length {
+        Some(length) => {
+            let end = offset.checked_add(length)?.min(data.len());
+            decode_native_frame(header, data.get(offset..end)?)
+        }
+        None => decode_encapsulated_frame(header, data, offset),
+    }
+}
+
+fn decode_native_frame(header: &DicomHeader, pixels: &[u8]) -> Option<image::RgbaImage> {
+    let width = header.columns;
+    let height = header.rows;
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return None;
+    }
+    let count = width as usize * height as usize;
+    // Check the samples are there before allocating for them, since the size
+    // comes straight from the header.
+    let bytes_per_sample = (header.bits_allocated as usize + 7) / 8;
+    let needed = count
+        .checked_mul(header.samples_per_pixel as usize)?
+        .checked_mul(bytes_per_sample)?;
+    if needed == 0 || pixels.len() < needed {
+        return None;
+    }
+    let mut rgba = Vec::with_capacity(count * 4);
+
+    match header.samples_per_pixel {
+        1 => {
+            let values = monochrome_values(header, pixels, count)?;
+            let (center, window) = match (header.window_center, header.window_width) {
+                (Some(center), Some(width)) if width > 0.0 => (center, width),
+                _ => {
+                    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+                    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+                    ((min + max) / 2.0, (max - min).max(1.0))
+                }
+            };
+            let invert = header.photometric == "MONOCHROME1";
+            for value in values {
+                let mut level = apply_window(value, center, window);
+                if invert {
+                    level = 255 - level;
+                }
+                rgba.extend_from_slice(&[level, level, level, 255]);
+            }
+        }
+        3 if header.bits_allocated == 8 => {
+            if pixels.len() < count * 3 {
+                return None;
+            }
+            let ybr = header.photometric.starts_with("YBR_FULL");
+            for index in 0..count {
+                let (a, b, c) = if header.planar_configuration == 1 {
+                    (
+                        pixels[index],
+                        pixels[count + index],
+                        pixels[count * 2 + index],
+                    )
+                } else {
+                    (
+                        pixels[index * 3],
+                        pixels[index * 3 + 1],
+                        pixels[index * 3 + 2],
+                    )
+                };
+                let (r, g, b) = if ybr { ybr_to_rgb(a, b, c) } else { (a, b, c) };
+                rgba.extend_from_slice(&[r, g, b, 255]);
+            }
+        }
+        _ => return None,
+    }
+
+    image::RgbaImage::from_raw(width, height, rgba)
+}
+
+/// Modality values (after Rescale Slope/Intercept) for a monochrome frame.
+fn monochrome_values(header: &DicomHeader, pixels: &[u8], count: usize) -> Option<Vec<f64>> {
+    let slope = header.rescale_slope.unwrap_or(1.0);
+    let intercept = header.rescale_intercept.unwrap_or(0.0);
+    let signed = header.pixel_representation == 1;
+    let stored = match header.bits_stored {
+        0 => header.bits_allocated,
+        bits => bits.min(header.bits_allocated),
+    };
+
+    let raw: Vec<i64> = match header.bits_allocated {
+        8 => pixels
+            .get(..count)?
+            .iter()
+            .map(|&v| sign_extend(v as u32, stored, signed))
+            .collect(),
+        16 => pixels
+            .get(..count * 2)?
+            .chunks_exact(2)
+            .map(|v| sign_extend(u16::from_le_bytes([v[0], v[1]]) as u32, stored, signed))
+            .collect(),
+        32 => pixels
+            .get(..count * 4)?
+            .chunks_exact(4)
+            .map(|v| sign_extend(u32::from_le_bytes([v[0], v[1], v[2], v[3]]), stored, signed))
+            .collect(),
+        _ => return None,
+    };
+
+    Some(
+        raw.into_iter()
+            .map(|v| v as f64 * slope + intercept)
+            .collect(),
+    )
+}
+
+fn sign_extend(value: u32, bits: u32, signed: bool) -> i64 {
+    if bits == 0 || bits >= 32 {
+        return if signed {
+            value as i32 as i64
+        } else {
+            value as i64
+        };
+    }
+    let masked = value & ((1u32 << bits) - 1);
+    if signed && masked & (1u32 << (bits - 1)) != 0 {
+        masked as i64 - (1i64 << bits)
+    } else {
+        masked as i64
+    }
+}
+
+fn ybr_to_rgb(y: u8, cb: u8, cr: u8) -> (u8, u8, u8) {
+    let y = y as f32;
+    let cb = cb as f32 - 128.0;
+    let cr = cr as f32 - 128.0;
+    let r = y + 1.402 * cr;
+    let g = y - 0.344_136 * cb - 0.714_136 * cr;
+    let b = y + 1.772 * cb;
+    (
+        r.clamp(0.0, 255.0) as u8,
+        g.clamp(0.0, 255.0) as u8,
+        b.clamp(0.0, 255.0) as u8,
+    )
+}
+
+/// Decode the first fragment of encapsulated baseline JPEG pixel data.
+fn decode_encapsulated_frame(
+    header: &DicomHeader,
+    data: &[u8],
+    offset: usize,
+) -> Option<image::RgbaImage> {
+    if header.transfer_syntax != TS_JPEG_BASELINE && header.transfer_syntax != TS_JPEG_EXTENDED {
+        return None;
+    }
+
+    let mut reader = Reader { data, pos: offset };
+    // The first item is the Basic Offset Table.
+    let offset_table = read_element(&mut reader, false)?;
+    if offset_table.tag != TAG_ITEM {
+        return None;
+    }
+    reader.bytes(offset_table.length as usize)?;
+
+    let fragment = read_element(&mut reader, false)?;
+    if fragment.tag != TAG_ITEM {
+        return None;
+    }
+    let jpeg = reader.bytes(fragment.length as usize)?;
+    image::load_from_memory_with_format(jpeg, image::ImageFormat::Jpeg)
+        .ok()
+        .map(|img| img.to_rgba8())
+}
+
+/// A DICOM date, `YYYYMMDD` or `YYYY.MM.DD` in older files, written as
+/// `date_format` asks. Anything else is shown as the file has it.
+fn format_date(text: &str) -> String {
+    parse_date(text)
+        .map(dates::format_civil)
+        .unwrap_or_else(|| text.to_string())
+}
+
+fn parse_date(text: &str) -> Option<CivilTime> {
+    let digits: String = text.chars().filter(|&c| c != '.').collect();
+    if digits.len() != 8 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| digits[range].parse::<u16>().ok();
+    CivilTime::new(field(0..4)?, field(4..6)?, field(6..8)?, None)
+}
+
+fn caption_lines(header: &DicomHeader, redact_patient_data: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    let study_date = header.study_date.as_deref().map(format_date);
+    let study: Vec<&str> = [
+        header.modality.as_deref(),
+        study_date.as_deref(),
+        header.study_description.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if !study.is_empty() {
+        lines.push(study.join("  ·  "));
+    }
+
+    if !redact_patient_data {
+        let birth_date = header.patient_birth_date.as_deref().map(format_date);
+        let patient: Vec<&str> = [
+            header.patient_name.as_deref(),
+            header.patient_id.as_deref(),
+            birth_date.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !patient.is_empty() {
+            lines.push(patient.join("  ·  "));
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explicit_element(out: &mut Vec<u8>, tag: (u16, u16), vr: &[u8; 2], value: &[u8]) {
+        out.extend_from_slice(&tag.0.to_le_bytes());
+        out.extend_from_slice(&tag.1.to_le_bytes());
+        out.extend_from_slice(vr);
+        if has_long_length(vr) {
+            out.extend_from_slice(&[0, 0]);
+            out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        } else {
+            out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        }
+        out.extend_from_slice(value);
+    }
+
+    fn sample_file() -> Vec<u8> {
+        let mut data = vec![0u8; 128];
+        data.extend_from_slice(b"DICM");
+        explicit_element(
+            &mut data,
+            TAG_TRANSFER_SYNTAX,
+            b"UI",
+            b"1.2.840.10008.1.2.1\0",
+        );
+        explicit_element(&mut data, TAG_MODALITY, b"CS", b"CT");
+        explicit_element(&mut data, TAG_PATIENT_NAME, b"PN", b"DOE^JANE");
+        explicit_element(&mut data, TAG_SAMPLES_PER_PIXEL, b"US", &1u16.to_le_bytes());
+        explicit_element(&mut data, TAG_PHOTOMETRIC, b"CS", b"MONOCHROME2 ");
+        explicit_element(&mut data, TAG_ROWS, b"US", &2u16.to_le_bytes());
+        explicit_element(&mut data, TAG_COLUMNS, b"US", &2u16.to_le_bytes());
+        explicit_element(&mut data, TAG_BITS_ALLOCATED, b"US", &16u16.to_le_bytes());
+        explicit_element(&mut data, TAG_BITS_STORED, b"US", &12u16.to_le_bytes());
+        explicit_element(&mut data, TAG_WINDOW_CENTER, b"DS", b"40\\50");
+        explicit_element(&mut data, TAG_WINDOW_WIDTH, b"DS", b"400 ");
+        let pixels: Vec<u8> = [0u16, 100, 200, 4095]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        explicit_element(&mut data, TAG_PIXEL_DATA, b"OW", &pixels);
+        data
+    }
+
+    #[test]
+    fn parses_explicit_little_endian_header() {
+        let data = sample_file();
+        let header = parse_header(&data).expect("header");
+        assert_eq!((header.columns, header.rows), (2, 2));
+        assert_eq!(header.bits_allocated, 16);
+        assert_eq!(header.window_center, Some(40.0));
+        assert_eq!(header.window_width, Some(400.0));
+        assert_eq!(header.modality.as_deref(), Some("CT"));
+        assert_eq!(header.patient_name.as_deref(), Some("DOE JANE"));
+        assert!(header.pixel_data.is_some());
+    }
+
+    #[test]
+    fn decodes_windowed_monochrome_frame() {
+        let data = sample_file();
+        let header = parse_header(&data).expect("header");
+        let image = decode_first_frame(&header, &data).expect("frame");
+        let levels: Vec<u8> = [(0, 0), (1, 0), (0, 1), (1, 1)]
+            .iter()
+            .map(|&(x, y)| image.get_pixel(x, y).0[0])
+            .collect();
+        assert!(levels.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(image.get_pixel(1, 1).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn frames_larger_than_their_pixel_data_are_rejected() {
+        let data = sample_file();
+        let mut header = parse_header(&data).expect("header");
+        let (offset, _) = header.pixel_data.expect("pixel data");
+        let pixels = &data[offset..];
+        header.rows = 60_000;
+        header.columns = 60_000;
+        assert!(decode_native_frame(&header, pixels).is_none());
+        header.rows = 2;
+        header.columns = 3;
+        assert!(decode_native_frame(&header, pixels).is_none());
+    }
+
+    #[test]
+    fn window_follows_linear_voi_function() {
+        assert_eq!(apply_window(-1000.0, 40.0, 400.0), 0);
+        assert_eq!(apply_window(1000.0, 40.0, 400.0), 255);
+        let mid = apply_window(40.0, 40.0, 400.0);
+        assert!((126..=129).contains(&mid));
+    }
+
+    #[test]
+    fn sign_extension_respects_bits_stored() {
+        assert_eq!(sign_extend(0x0FFF, 12, true), -1);
+        assert_eq!(sign_extend(0x0FFF, 12, false), 4095);
+        assert_eq!(sign_extend(0xF800, 12, false), 0x800);
+    }
+
+    #[test]
+    fn dates_are_read_in_current_and_old_forms() {
+        assert_eq!(parse_date("20240501"), CivilTime::new(2024, 5, 1, None));
+        assert_eq!(parse_date("2024.05.01"), CivilTime::new(2024, 5, 1, None));
+        assert_eq!(parse_date("2024-05"), None);
+        assert_eq!(format_date("unknown"), "unknown");
+    }
+
+    #[test]
+    fn redaction_drops_patient_line() {
+        let data = sample_file();
+        let header = parse_header(&data).expect("header");
+        assert_eq!(caption_lines(&header, false).len(), 2);
+        assert_eq!(caption_lines(&header, true), vec!["CT".to_string()]);
+    }
+}
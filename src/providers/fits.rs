@@ -0,0 +1,451 @@
+//! FITS (`.fits`, `.fit`, `.fts`) astronomy images.
+//!
+//! Reads the primary HDU: 8/16/32-bit integer or 32/64-bit float data, one
+//! plane (grayscale) or three planes (RGB). Raw values are clipped to the
+//! 0.25th-99.75th percentile and then asinh-stretched to 8 bits, which keeps
+//! faint detail visible without blowing out stars. Rows are flipped because
+//! FITS stores the bottom row first.
+//!
+//! Object, exposure, filter and instrument keywords are drawn as a caption.
+//! Tile-compressed (`.fz`) files are not supported.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::dates::{self, CivilTime};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+const FITS_EXTENSIONS: &[&str] = &["fits", "fit", "fts"];
+
+const FITS_BLOCK_BYTES: usize = 2880;
+const FITS_CARD_BYTES: usize = 80;
+/// Header blocks read before giving up on finding `END`.
+const FITS_MAX_HEADER_BLOCKS: usize = 64;
+
+const STRETCH_LOW_PERCENTILE: f64 = 0.0025;
+const STRETCH_HIGH_PERCENTILE: f64 = 0.9975;
+/// Larger values lift faint signal more aggressively.
+const ASINH_STRETCH_FACTOR: f64 = 10.0;
+/// Upper bound on samples used to estimate percentiles.
+const STRETCH_SAMPLE_LIMIT: usize = 1 << 20;
+
+pub struct FitsProvider;
+
+impl PreviewProvider for FitsProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, FITS_EXTENSIONS)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let mut data = Vec::new();
+        File::open(path)
+            .ok()?
+            .take((FITS_BLOCK_BYTES * FITS_MAX_HEADER_BLOCKS) as u64)
+            .read_to_end(&mut data)
+            .ok()?;
+        let header = parse_header(&data)?;
+        Some((header.width, header.height))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let data = std::fs::read(path).ok()?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let header = parse_header(&data)?;
+        let rgba = render_image(&header, &data)?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let mut media = static_media_from_image(
+            image::DynamicImage::ImageRgba8(rgba),
+            constraints.max_width,
+            constraints.max_height,
+        );
+        overlay::draw_caption(media.first_frame_mut(), &caption_lines(&header));
+        Some(media)
+    }
+}
+
+#[derive(Debug, Default)]
+struct FitsHeader {
+    bitpix: i32,
+    width: u32,
+    height: u32,
+    planes: u32,
+    bzero: f64,
+    bscale: f64,
+    blank: Option<i64>,
+    object: Option<String>,
+    exposure: Option<f64>,
+    filter: Option<String>,
+    instrument: Option<String>,
+    date_obs: Option<String>,
+    data_offset: usize,
+}
+
+/// Split a header card into keyword and value, dropping `/ comment` text.
+fn parse_card(card: &[u8]) -> Option<(String, String)> {
+    let card = String::from_utf8_lossy(card);
+    let keyword = card.get(..8)?.trim().to_string();
+    if card.get(8..10) != Some("= ") {
+        return Some((keyword, String::new()));
+    }
+
+    let raw = card[10..].trim();
+    let value = if let Some(quoted) = raw.strip_prefix('\'') {
+        // Quoted strings escape a single quote by doubling it.
+        let mut value = String::new();
+        let mut chars = quoted.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    value.push('\'');
+                    continue;
+                }
+                break;
+            }
+            value.push(c);
+        }
+        value.trim_end().to_string()
+    } else {
+        raw.split('/').next().unwrap_or_default().trim().to_string()
+    };
+    Some((keyword, value))
+}
+
+fn parse_header(data: &[u8]) -> Option<FitsHeader> {
+    if !data.starts_with(b"SIMPLE  =") {
+        return None;
+    }
+
+    let mut header = FitsHeader {
+        bscale: 1.0,
+        planes: 1,
+        ..Default::default()
+    };
+    let mut naxis = 0u32;
+
+    for (index, card) in data.chunks(FITS_CARD_BYTES).enumerate() {
+        if card.len() < FITS_CARD_BYTES || index >= FITS_MAX_HEADER_BLOCKS * 36 {
+            return None;
+        }
+        let (keyword, value) = parse_card(card)?;
+        match keyword.as_str() {
+            "END" => {
+                let header_bytes = (index + 1) * FITS_CARD_BYTES;
+                header.data_offset =
+                    (header_bytes + FITS_BLOCK_BYTES - 1) / FITS_BLOCK_BYTES * FITS_BLOCK_BYTES;
+                break;
+            }
+            "BITPIX" => header.bitpix = value.parse().ok()?,
+            "NAXIS" => naxis = value.parse().ok()?,
+            "NAXIS1" => header.width = value.parse().ok()?,
+            "NAXIS2" => header.height = value.parse().ok()?,
+            "NAXIS3" => header.planes = value.parse().ok()?,
+            "BZERO" => header.bzero = value.parse().unwrap_or(0.0),
+            "BSCALE" => header.bscale = value.parse().unwrap_or(1.0),
+            "BLANK" => header.blank = value.parse().ok(),
+            "OBJECT" => header.object = Some(value).filter(|v| !v.is_empty()),
+            "EXPTIME" | "EXPOSURE" => header.exposure = value.parse().ok(),
+            "FILTER" => header.filter = Some(value).filter(|v| !v.is_empty()),
+            "INSTRUME" => header.instrument = Some(value).filter(|v| !v.is_empty()),
+            "DATE-OBS" => header.date_obs = Some(value).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+    }
+
+    if header.data_offset == 0 || naxis < 2 || header.width == 0 || header.height == 0 {
+        return None;
+    }
+    if naxis < 3 {
+        header.planes = 1;
+    }
+    if !matches!(header.bitpix, 8 | 16 | 32 | -32 | -64) {
+        return None;
+    }
+    Some(header)
+}
+
+/// Physical values (`BZERO + BSCALE * raw`) of one plane; blank pixels are NaN.
+fn plane_values(header: &FitsHeader, data: &[u8], plane: u32) -> Option<Vec<f64>> {
+    let bytes_per_value = (header.bitpix.unsigned_abs() / 8) as usize;
+    let plane_bytes = (header.width as usize)
+        .checked_mul(header.height as usize)?
+        .checked_mul(bytes_per_value)?;
+    let start = (plane as usize)
+        .checked_mul(plane_bytes)?
+        .checked_add(header.data_offset)?;
+    let raw = data.get(start..start.checked_add(plane_bytes)?)?;
+
+    let scale = |value: f64| header.bzero + header.bscale * value;
+    let integer = |value: i64| {
+        if header.blank == Some(value) {
+            f64::NAN
+        } else {
+            scale(value as f64)
+        }
+    };
+
+    let values = match header.bitpix {
+        8 => raw.iter().map(|&v| integer(v as i64)).collect(),
+        16 => raw
+            .chunks_exact(2)
+            .map(|v| integer(i16::from_be_bytes([v[0], v[1]]) as i64))
+            .collect(),
+        32 => raw
+            .chunks_exact(4)
+            .map(|v| integer(i32::from_be_bytes([v[0], v[1], v[2], v[3]]) as i64))
+            .collect(),
+        -32 => raw
+            .chunks_exact(4)
+            .map(|v| scale(f32::from_be_bytes([v[0], v[1], v[2], v[3]]) as f64))
+            .collect(),
+        -64 => raw
+            .chunks_exact(8)
+            .map(|v| {
+                scale(f64::from_be_bytes([
+                    v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7],
+                ]))
+            })
+            .collect(),
+        _ => return None,
+    };
+    Some(values)
+}
+
+/// Low and high clip levels from a sample of the finite values.
+fn percentile_bounds(values: &[f64]) -> Option<(f64, f64)> {
+    let stride = (values.len() / STRETCH_SAMPLE_LIMIT).max(1);
+    let mut sample: Vec<f64> = values
+        .iter()
+        .step_by(stride)
+        .copied()
+        .filter(|v| v.is_finite())
+        .collect();
+    if sample.is_empty() {
+        return None;
+    }
+
+    sample.sort_unstable_by(|a, b| a.total_cmp(b));
+    let last = sample.len() - 1;
+    let low = sample[(last as f64 * STRETCH_LOW_PERCENTILE) as usize];
+    let high = sample[(last as f64 * STRETCH_HIGH_PERCENTILE).round() as usize];
+    if high > low {
+        Some((low, high))
+    } else {
+        Some((low, low + 1.0))
+    }
+}
+
+/// Map a physical value into 0..=255 with percentile clipping and asinh.
+fn stretch(value: f64, low: f64, high: f64) -> u8 {
+    if !value.is_finite() {
+        return 0;
+    }
+    let normalized = ((value - low) / (high - low)).clamp(0.0, 1.0);
+    let stretched = (ASINH_STRETCH_FACTOR * normalized).asinh() / ASINH_STRETCH_FACTOR.asinh();
+    (stretched * 255.0).round() as u8
+}
+
+fn render_image(header: &FitsHeader, data: &[u8]) -> Option<image::RgbaImage> {
+    let width = header.width as usize;
+    let height = header.height as usize;
+    let channels: Vec<Vec<u8>> = if header.planes >= 3 {
+        (0..3)
+            .map(|plane| stretch_plane(&plane_values(header, data, plane)?))
+            .collect::<Option<_>>()?
+    } else {
+        vec![stretch_plane(&plane_values(header, data, 0)?)?]
+    };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    // FITS stores the bottom row first.
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let index = y * width + x;
+            let r = channels[0][index];
+            let (g, b) = if channels.len() == 3 {
+                (channels[1][index], channels[2][index])
+            } else {
+                (r, r)
+            };
+            rgba.extend_from_slice(&[r, g, b, 255]);
+        }
+    }
+    image::RgbaImage::from_raw(header.width, header.height, rgba)
+}
+
+fn stretch_plane(values: &[f64]) -> Option<Vec<u8>> {
+    let (low, high) = percentile_bounds(values)?;
+    Some(values.iter().map(|&v| stretch(v, low, high)).collect())
+}
+
+fn caption_lines(header: &FitsHeader) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(ref object) = header.object {
+        parts.push(object.clone());
+    }
+    if let Some(exposure) = header.exposure {
+        parts.push(format!("{} s", exposure));
+    }
+    if let Some(ref filter) = header.filter {
+        parts.push(filter.clone());
+    }
+
+    let mut lines = Vec::new();
+    if !parts.is_empty() {
+        lines.push(parts.join("  ·  "));
+    }
+    let date_obs = header.date_obs.as_deref().map(format_date_obs);
+    let details: Vec<&str> = [header.instrument.as_deref(), date_obs.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect();
+    if !details.is_empty() {
+        lines.push(details.join("  ·  "));
+    }
+    lines
+}
+
+/// `DATE-OBS` written as `date_format` asks: a time of observation, which is
+/// in UTC, in the local time zone. Anything else is shown as the file has it.
+fn format_date_obs(text: &str) -> String {
+    let Some(date) = parse_date_obs(text) else {
+        return text.to_string();
+    };
+    if date.time.is_none() {
+        return dates::format_civil(date);
+    }
+    date.to_system_time(0)
+        .and_then(dates::format_local)
+        .unwrap_or_else(|| dates::format_civil(date))
+}
+
+/// `2024-05-01`, `2024-05-01T21:30:00.5` or, in files older than 1998,
+/// `01/05/94`.
+fn parse_date_obs(text: &str) -> Option<CivilTime> {
+    let number = |part: &str| part.trim().parse::<u16>().ok();
+    let text = text.trim();
+    if let [day, month, year] = text.split('/').collect::<Vec<_>>()[..] {
+        return CivilTime::new(1900 + number(year)?, number(month)?, number(day)?, None);
+    }
+    let (date, time) = match text.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let [year, month, day] = date.split('-').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let time = match time {
+        Some(time) => {
+            let mut parts = time.split(':');
+            Some((number(parts.next()?)?, number(parts.next()?)?))
+        }
+        None => None,
+    };
+    CivilTime::new(number(year)?, number(month)?, number(day)?, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(text: &str) -> Vec<u8> {
+        let mut card = text.as_bytes().to_vec();
+        card.resize(FITS_CARD_BYTES, b' ');
+        card
+    }
+
+    fn sample_file() -> Vec<u8> {
+        let mut data = Vec::new();
+        for text in [
+            "SIMPLE  =                    T",
+            "BITPIX  =                   16",
+            "NAXIS   =                    2",
+            "NAXIS1  =                    2",
+            "NAXIS2  =                    2",
+            "BZERO   =                32768",
+            "OBJECT  = 'M 42    '           / Orion Nebula",
+            "EXPTIME =                300.0 / seconds",
+            "END",
+        ] {
+            data.extend(card(text));
+        }
+        data.resize(FITS_BLOCK_BYTES, b' ');
+        // Bottom row first: 0, 100 then 200, 65535 after BZERO.
+        for value in [-32768i16, -32668, -32568, 32767] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.resize(FITS_BLOCK_BYTES * 2, 0);
+        data
+    }
+
+    #[test]
+    fn parses_primary_header_keywords() {
+        let header = parse_header(&sample_file()).expect("header");
+        assert_eq!((header.width, header.height), (2, 2));
+        assert_eq!(header.bitpix, 16);
+        assert_eq!(header.data_offset, FITS_BLOCK_BYTES);
+        assert_eq!(header.object.as_deref(), Some("M 42"));
+        assert_eq!(header.exposure, Some(300.0));
+    }
+
+    #[test]
+    fn applies_bzero_and_flips_rows() {
+        let data = sample_file();
+        let header = parse_header(&data).expect("header");
+        let values = plane_values(&header, &data, 0).expect("values");
+        assert_eq!(values, vec![0.0, 100.0, 200.0, 65535.0]);
+
+        let image = render_image(&header, &data).expect("image");
+        // The brightest value is in the last stored row, which is drawn on top.
+        assert_eq!(image.get_pixel(1, 0).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(0, 1).0[0], 0);
+    }
+
+    #[test]
+    fn observation_dates_are_read_in_every_form() {
+        assert_eq!(
+            parse_date_obs("2024-05-01T21:30:00.5"),
+            CivilTime::new(2024, 5, 1, Some((21, 30)))
+        );
+        assert_eq!(
+            parse_date_obs("2024-05-01"),
+            CivilTime::new(2024, 5, 1, None)
+        );
+        assert_eq!(parse_date_obs("01/05/94"), CivilTime::new(1994, 5, 1, None));
+        assert_eq!(parse_date_obs("May 1"), None);
+        assert_eq!(format_date_obs("May 1"), "May 1");
+    }
+
+    #[test]
+    fn planes_past_the_data_are_rejected() {
+        let data = sample_file();
+        let mut header = parse_header(&data).expect("header");
+        assert!(plane_values(&header, &data, u32::MAX).is_none());
+        header.width = u32::MAX;
+        header.height = u32::MAX;
+        assert!(plane_values(&header, &data, 0).is_none());
+    }
+
+    #[test]
+    fn quoted_values_keep_escaped_quotes() {
+        let (keyword, value) = parse_card(&card("OBJECT  = 'O''Brien' / x")).expect("card");
+        assert_eq!(keyword, "OBJECT");
+        assert_eq!(value, "O'Brien");
+    }
+
+    #[test]
+    fn stretch_is_monotonic_and_clipped() {
+        assert_eq!(stretch(-5.0, 0.0, 1.0), 0);
+        assert_eq!(stretch(5.0, 0.0, 1.0), 255);
+        assert!(stretch(0.1, 0.0, 1.0) > 25);
+        assert_eq!(stretch(f64::NAN, 0.0, 1.0), 0);
+    }
+}
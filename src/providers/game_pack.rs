@@ -0,0 +1,263 @@
+//! Minecraft packs and worlds.
+//!
+//! - Bedrock `.mcpack` (resource/behavior packs): `pack_icon.png` and the
+//!   name/description from `manifest.json`.
+//! - Bedrock `.mcworld`: `world_icon.jpeg` and `levelname.txt`.
+//! - Java resource packs and mods shipped as `.jar`: `pack.png` and the
+//!   description from `pack.mcmeta`.
+//!
+//! Entries are found at any depth because packs are often zipped with their
+//! top-level folder. Icons are small pixel art, so they are enlarged with
+//! nearest-neighbour scaling before the pack text is drawn as a caption.
+
+use super::archive::Archive;
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::path::{Path, PathBuf};
+
+const GAME_PACK_EXTENSIONS: &[&str] = &["mcpack", "mcworld", "jar"];
+
+/// Icons are enlarged by whole factors up to at least this size.
+const ICON_MIN_SIDE: u32 = 256;
+const MAX_DESCRIPTION_LINES: usize = 2;
+const MAX_CAPTION_CHARS: usize = 60;
+
+const PANEL_WIDTH: u32 = 420;
+const PANEL_HEIGHT: u32 = 120;
+
+pub struct GamePackProvider;
+
+impl PreviewProvider for GamePackProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, GAME_PACK_EXTENSIONS)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let pack = read_pack(path)?;
+        Some(
+            pack.icon
+                .map(|icon| enlarged_size(icon.width(), icon.height()))
+                .unwrap_or((PANEL_WIDTH, PANEL_HEIGHT)),
+        )
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let pack = read_pack(path)?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let lines = pack.caption_lines();
+        let Some(icon) = pack.icon else {
+            let panel = overlay::render_text_panel(PANEL_WIDTH, PANEL_HEIGHT, &lines)?;
+            return Some(static_media_from_image(
+                image::DynamicImage::ImageRgba8(panel),
+                constraints.max_width,
+                constraints.max_height,
+            ));
+        };
+
+        let (width, height) = enlarged_size(icon.width(), icon.height());
+        let icon = icon.resize_exact(width, height, image::imageops::FilterType::Nearest);
+        let mut media =
+            static_media_from_image(icon, constraints.max_width, constraints.max_height);
+        overlay::draw_caption(media.first_frame_mut(), &lines);
+        Some(media)
+    }
+}
+
+struct GamePack {
+    name: String,
+    description: Option<String>,
+    icon: Option<image::DynamicImage>,
+}
+
+impl GamePack {
+    fn caption_lines(&self) -> Vec<String> {
+        let mut lines = vec![truncate(&self.name)];
+        if let Some(description) = &self.description {
+            lines.extend(
+                description
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .take(MAX_DESCRIPTION_LINES)
+                    .map(truncate),
+            );
+        }
+        lines
+    }
+}
+
+fn read_pack(path: &Path) -> Option<GamePack> {
+    let mut archive = Archive::open(path)?;
+    let file_stem = path.file_stem()?.to_string_lossy().to_string();
+    let decode = |data: Option<Vec<u8>>| data.and_then(|d| image::load_from_memory(&d).ok());
+
+    let pack = if let Some(manifest) = archive.read_found("manifest.json") {
+        // Bedrock pack (worlds may carry a manifest too).
+        let manifest = String::from_utf8_lossy(&manifest);
+        let header = json_object(&manifest, "header").unwrap_or(&manifest);
+        let icon = decode(
+            archive
+                .read_found("pack_icon.png")
+                .or_else(|| archive.read_found("world_icon.jpeg")),
+        );
+        GamePack {
+            name: json_string(header, "name").unwrap_or(file_stem),
+            description: json_string(header, "description"),
+            icon,
+        }
+    } else if let Some(level_name) = archive.read_found("levelname.txt") {
+        GamePack {
+            name: String::from_utf8_lossy(&level_name).trim().to_string(),
+            description: None,
+            icon: decode(archive.read_found("world_icon.jpeg")),
+        }
+    } else {
+        // Java resource pack. Plain jars without a pack.mcmeta are skipped.
+        let meta = archive.read_found("pack.mcmeta")?;
+        let meta = String::from_utf8_lossy(&meta);
+        GamePack {
+            name: file_stem,
+            description: json_string(&meta, "description"),
+            icon: decode(archive.read_found("pack.png")),
+        }
+    };
+
+    (pack.icon.is_some() || pack.description.is_some()).then_some(pack)
+}
+
+/// Whole-factor enlargement so tiny pixel-art icons stay crisp.
+fn enlarged_size(width: u32, height: u32) -> (u32, u32) {
+    let longest = width.max(height).max(1);
+    let factor = (ICON_MIN_SIDE / longest).max(1);
+    (width * factor, height * factor)
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_CAPTION_CHARS {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(MAX_CAPTION_CHARS - 1).collect();
+    out.push('…');
+    out
+}
+
+/// Text after `"key":`, with leading whitespace removed.
+fn json_value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let quoted = format!("\"{}\"", key);
+    let mut search = json;
+    loop {
+        let start = search.find(&quoted)? + quoted.len();
+        let rest = search[start..].trim_start();
+        if let Some(value) = rest.strip_prefix(':') {
+            return Some(value.trim_start());
+        }
+        search = &search[start..];
+    }
+}
+
+/// The object following `"key":`, still unparsed.
+fn json_object<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    json_value(json, key).filter(|value| value.starts_with('{'))
+}
+
+/// String value of `"key"`. For Minecraft text components (objects or arrays
+/// with `"text"` fields) the first `text` is used. Formatting codes such as
+/// `§a` are removed.
+fn json_string(json: &str, key: &str) -> Option<String> {
+    let value = json_value(json, key)?;
+    let text = if value.starts_with('"') {
+        parse_json_string(value)?
+    } else if value.starts_with('{') || value.starts_with('[') {
+        parse_json_string(json_value(value, "text")?)?
+    } else {
+        return None;
+    };
+    let text = strip_formatting_codes(&text);
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Decode the JSON string literal at the start of `value`.
+fn parse_json_string(value: &str) -> Option<String> {
+    let mut chars = value.strip_prefix('"')?.chars();
+    let mut out = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(out),
+            '\\' => match chars.next()? {
+                'n' => out.push('\n'),
+                't' => out.push('\t'),
+                'r' => {}
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16).ok()?;
+                    out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                }
+                other => out.push(other),
+            },
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+fn strip_formatting_codes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bedrock_manifest_header_fields() {
+        let manifest = r#"{
+            "format_version": 2,
+            "header": {
+                "description": "Smooth \"HD\" textures\nby someone",
+                "name": "§6Faithful",
+                "uuid": "x"
+            },
+            "modules": [{ "description": "module", "type": "resources" }]
+        }"#;
+        let header = json_object(manifest, "header").unwrap();
+        assert_eq!(json_string(header, "name").as_deref(), Some("Faithful"));
+        assert_eq!(
+            json_string(header, "description").as_deref(),
+            Some("Smooth \"HD\" textures\nby someone")
+        );
+    }
+
+    #[test]
+    fn java_description_accepts_text_components() {
+        let plain = r#"{"pack": {"pack_format": 15, "description": "Vanilla édit"}}"#;
+        assert_eq!(
+            json_string(plain, "description").as_deref(),
+            Some("Vanilla édit")
+        );
+        let component = r#"{"pack":{"description":[{"text":"Fancy","color":"gold"}]}}"#;
+        assert_eq!(
+            json_string(component, "description").as_deref(),
+            Some("Fancy")
+        );
+    }
+
+    #[test]
+    fn icons_are_enlarged_by_whole_factors() {
+        assert_eq!(enlarged_size(16, 16), (256, 256));
+        assert_eq!(enlarged_size(100, 50), (200, 100));
+        assert_eq!(enlarged_size(512, 512), (512, 512));
+    }
+}
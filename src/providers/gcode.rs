@@ -0,0 +1,457 @@
+//! 3D printer G-code (`.gcode`, `.gco`) rendered as a top-down plot.
+//!
+//! The extruding `G0`/`G1` (and, approximated as straight lines, `G2`/`G3`)
+//! moves of the first few layers are drawn from above, lower layers darker,
+//! which is usually enough to recognise the part's footprint. Absolute and
+//! relative positioning (`G90`/`G91`) and extrusion (`M82`/`M83`, `G92`) are
+//! tracked. The estimated print time and slicer come from the comments that
+//! PrusaSlicer, Cura, OrcaSlicer/Bambu Studio and Simplify3D write at the
+//! start or end of the file.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const GCODE_EXTENSIONS: &[&str] = &["gcode", "gco"];
+
+const PLOT_LAYERS: usize = 5;
+/// Bytes parsed for moves; the first layers are well within this.
+const MAX_MOVE_BYTES: u64 = 16 * 1024 * 1024;
+/// Bytes searched for metadata comments at each end of the file.
+const COMMENT_SCAN_BYTES: u64 = 64 * 1024;
+
+const PLOT_SIZE: u32 = 512;
+const PLOT_MARGIN: f32 = 16.0;
+const PLOT_BACKGROUND: [u8; 4] = [0x1e, 0x1e, 0x1e, 0xff];
+/// Colours from the first layer to the last plotted one.
+const LAYER_COLORS: [[u8; 4]; PLOT_LAYERS] = [
+    [0x6d, 0x3a, 0x0c, 0xff],
+    [0x9a, 0x52, 0x10, 0xff],
+    [0xc8, 0x6b, 0x15, 0xff],
+    [0xf0, 0x8a, 0x24, 0xff],
+    [0xff, 0xb3, 0x5c, 0xff],
+];
+
+pub struct GcodeProvider;
+
+impl PreviewProvider for GcodeProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, GCODE_EXTENSIONS)
+    }
+
+    fn dimensions(&self, _path: &PathBuf) -> Option<(u32, u32)> {
+        Some((PLOT_SIZE, PLOT_SIZE))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let segments = read_segments(path, constraints)?;
+        if segments.is_empty() {
+            return None;
+        }
+        let metadata = read_metadata(path);
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let plot = render_plot(&segments)?;
+        let mut media = static_media_from_image(
+            image::DynamicImage::ImageRgba8(plot),
+            constraints.max_width,
+            constraints.max_height,
+        );
+        overlay::draw_caption(media.first_frame_mut(), &metadata.caption_lines());
+        Some(media)
+    }
+}
+
+/// An extruding move on one of the plotted layers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    from: (f32, f32),
+    to: (f32, f32),
+    layer: usize,
+}
+
+#[derive(Debug)]
+struct MachineState {
+    position: [f32; 4],
+    relative_xyz: bool,
+    relative_e: bool,
+    layer_z: Option<f32>,
+    layer: usize,
+}
+
+impl Default for MachineState {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 4],
+            relative_xyz: false,
+            relative_e: false,
+            layer_z: None,
+            layer: 0,
+        }
+    }
+}
+
+impl MachineState {
+    /// Apply one line. Returns the segment when the line is an extruding move.
+    fn apply(&mut self, line: &str) -> Option<Segment> {
+        let code = line.split(';').next().unwrap_or("").trim();
+        let mut words = code.split_whitespace();
+        let command = words.next()?.to_ascii_uppercase();
+        match command.as_str() {
+            "G90" => self.relative_xyz = false,
+            "G91" => self.relative_xyz = true,
+            "M82" => self.relative_e = false,
+            "M83" => self.relative_e = true,
+            "G92" => {
+                for (axis, value) in words.filter_map(parse_word) {
+                    self.position[axis] = value;
+                }
+            }
+            "G0" | "G1" | "G2" | "G3" => {
+                let from = self.position;
+                let mut extrusion = 0.0;
+                for (axis, value) in words.filter_map(parse_word) {
+                    let relative = if axis == 3 {
+                        self.relative_e
+                    } else {
+                        self.relative_xyz
+                    };
+                    let target = if relative { from[axis] + value } else { value };
+                    if axis == 3 {
+                        extrusion = target - from[3];
+                    }
+                    self.position[axis] = target;
+                }
+
+                let moved = from[0] != self.position[0] || from[1] != self.position[1];
+                if extrusion <= 0.0 || !moved {
+                    return None;
+                }
+                let z = self.position[2];
+                match self.layer_z {
+                    None => self.layer_z = Some(z),
+                    Some(layer_z) if z > layer_z + 1e-4 => {
+                        self.layer_z = Some(z);
+                        self.layer += 1;
+                    }
+                    _ => {}
+                }
+                return Some(Segment {
+                    from: (from[0], from[1]),
+                    to: (self.position[0], self.position[1]),
+                    layer: self.layer,
+                });
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Axis index (X, Y, Z, E) and value of a word such as `X12.5`.
+fn parse_word(word: &str) -> Option<(usize, f32)> {
+    let mut chars = word.chars();
+    let axis = match chars.next()?.to_ascii_uppercase() {
+        'X' => 0,
+        'Y' => 1,
+        'Z' => 2,
+        'E' => 3,
+        _ => return None,
+    };
+    let value: f32 = chars.as_str().parse().ok()?;
+    value.is_finite().then_some((axis, value))
+}
+
+fn read_segments(path: &Path, constraints: &LoadConstraints) -> Option<Vec<Segment>> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file.take(MAX_MOVE_BYTES));
+    let mut state = MachineState::default();
+    let mut segments = Vec::new();
+    let mut line = String::new();
+    let mut lines_read = 0u32;
+
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        lines_read += 1;
+        if lines_read % 10_000 == 0 && constraints.is_cancelled() {
+            return None;
+        }
+        if let Some(segment) = state.apply(&line) {
+            if segment.layer >= PLOT_LAYERS {
+                break;
+            }
+            segments.push(segment);
+        }
+    }
+    Some(segments)
+}
+
+#[derive(Debug, Default)]
+struct GcodeMetadata {
+    print_time_secs: Option<u64>,
+    generator: Option<String>,
+}
+
+impl GcodeMetadata {
+    fn apply_comment(&mut self, line: &str) {
+        let Some(comment) = line.trim().strip_prefix(';') else {
+            return;
+        };
+        let comment = comment.trim();
+        let lower = comment.to_ascii_lowercase();
+
+        if self.generator.is_none() {
+            for prefix in ["generated by ", "generated with "] {
+                if let Some(rest) = lower.strip_prefix(prefix) {
+                    let name = &comment[comment.len() - rest.len()..];
+                    let name = name.split(" on ").next().unwrap_or(name);
+                    self.generator = Some(name.trim().to_string());
+                }
+            }
+        }
+
+        if self.print_time_secs.is_none() {
+            self.print_time_secs = parse_print_time(comment, &lower);
+        }
+    }
+
+    fn caption_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(secs) = self.print_time_secs {
+            lines.push(format!("Estimated print time {}", format_duration(secs)));
+        }
+        if let Some(generator) = &self.generator {
+            lines.push(generator.clone());
+        }
+        lines
+    }
+}
+
+/// Print time from a slicer comment (`lower` is `comment` lowercased).
+fn parse_print_time(comment: &str, lower: &str) -> Option<u64> {
+    if let Some(secs) = lower.strip_prefix("time:") {
+        // Cura: ";TIME:5025"
+        return secs.trim().parse::<f64>().ok().map(|s| s as u64);
+    }
+    let is_time_comment = lower.starts_with("estimated printing time")
+        || lower.starts_with("total estimated time")
+        || lower.starts_with("build time");
+    if !is_time_comment {
+        return None;
+    }
+    // "estimated printing time (normal mode) = 1h 2m 3s", "Build time: 1 hours 2 minutes"
+    let value = comment.split(|c| c == '=' || c == ':').nth(1)?;
+    parse_duration_text(value)
+}
+
+/// Sum a duration such as `1d 2h 3m 4s` or `1 hours 2 minutes`.
+fn parse_duration_text(text: &str) -> Option<u64> {
+    let mut total = 0u64;
+    let mut number: Option<u64> = None;
+    let mut found = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            let digit = c.to_digit(10)? as u64;
+            number = Some(number.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+        let Some(value) = number.take() else {
+            continue;
+        };
+        let unit = match c.to_ascii_lowercase() {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => continue,
+        };
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        found = true;
+        // Skip the rest of a long unit name ("hours", "minutes").
+        while chars.peek().map(|c| c.is_alphabetic()).unwrap_or(false) {
+            chars.next();
+        }
+    }
+    found.then_some(total)
+}
+
+fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, (secs / 3_600) % 24, (secs / 60) % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, secs % 60)
+    }
+}
+
+/// Metadata comments from the head and tail of the file.
+fn read_metadata(path: &Path) -> GcodeMetadata {
+    let mut metadata = GcodeMetadata::default();
+    let Ok(mut file) = File::open(path) else {
+        return metadata;
+    };
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+
+    let mut chunks = Vec::new();
+    let mut head = Vec::new();
+    if file
+        .by_ref()
+        .take(COMMENT_SCAN_BYTES)
+        .read_to_end(&mut head)
+        .is_ok()
+    {
+        chunks.push(head);
+    }
+    let tail_start = SeekFrom::End(-(COMMENT_SCAN_BYTES as i64));
+    if len > COMMENT_SCAN_BYTES * 2 && file.seek(tail_start).is_ok() {
+        let mut tail = Vec::new();
+        if file.read_to_end(&mut tail).is_ok() {
+            chunks.push(tail);
+        }
+    }
+
+    for chunk in chunks {
+        for line in String::from_utf8_lossy(&chunk).lines() {
+            metadata.apply_comment(line);
+        }
+    }
+    metadata
+}
+
+fn render_plot(segments: &[Segment]) -> Option<image::RgbaImage> {
+    let points = segments.iter().flat_map(|s| [s.from, s.to]);
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for (x, y) in points {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+
+    let size = PLOT_SIZE as f32;
+    let span = (max_x - min_x).max(max_y - min_y).max(1e-3);
+    let scale = (size - PLOT_MARGIN * 2.0) / span;
+    // Centre the part; printer Y grows upwards.
+    let offset_x = (size - (max_x - min_x) * scale) / 2.0;
+    let offset_y = (size - (max_y - min_y) * scale) / 2.0;
+    let to_pixel = |(x, y): (f32, f32)| {
+        (
+            offset_x + (x - min_x) * scale,
+            size - (offset_y + (y - min_y) * scale),
+        )
+    };
+
+    let mut rgba: Vec<u8> = PLOT_BACKGROUND
+        .iter()
+        .copied()
+        .cycle()
+        .take(PLOT_SIZE as usize * PLOT_SIZE as usize * 4)
+        .collect();
+    for segment in segments {
+        let color = LAYER_COLORS[segment.layer.min(PLOT_LAYERS - 1)];
+        draw_line(
+            &mut rgba,
+            to_pixel(segment.from),
+            to_pixel(segment.to),
+            color,
+        );
+    }
+    image::RgbaImage::from_raw(PLOT_SIZE, PLOT_SIZE, rgba)
+}
+
+fn draw_line(rgba: &mut [u8], from: (f32, f32), to: (f32, f32), color: [u8; 4]) {
+    let steps = (to.0 - from.0)
+        .abs()
+        .max((to.1 - from.1).abs())
+        .ceil()
+        .max(1.0) as usize;
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let x = (from.0 + (to.0 - from.0) * t).round();
+        let y = (from.1 + (to.1 - from.1) * t).round();
+        if x < 0.0 || y < 0.0 || x >= PLOT_SIZE as f32 || y >= PLOT_SIZE as f32 {
+            continue;
+        }
+        let offset = (y as usize * PLOT_SIZE as usize + x as usize) * 4;
+        rgba[offset..offset + 4].copy_from_slice(&color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extruding_moves_are_tracked_across_layers() {
+        let mut state = MachineState::default();
+        assert_eq!(state.apply("G1 Z0.2 F3000"), None);
+        assert_eq!(state.apply("G1 X10 Y0"), None);
+        let segment = state.apply("G1 X20 Y0 E1.5 ; perimeter").unwrap();
+        assert_eq!(
+            (segment.from, segment.to, segment.layer),
+            ((10.0, 0.0), (20.0, 0.0), 0)
+        );
+
+        state.apply("G92 E0");
+        state.apply("G1 Z0.4");
+        let segment = state.apply("G1 X20 Y10 E0.5").unwrap();
+        assert_eq!(segment.layer, 1);
+
+        state.apply("M83");
+        state.apply("G91");
+        let segment = state.apply("G1 X-5 E0.2").unwrap();
+        assert_eq!(segment.to, (15.0, 10.0));
+        assert_eq!(state.apply("G1 X-5 E-0.8"), None);
+    }
+
+    #[test]
+    fn print_time_from_common_slicers() {
+        let parse = |comment: &str| {
+            let mut metadata = GcodeMetadata::default();
+            metadata.apply_comment(comment);
+            metadata.print_time_secs
+        };
+        assert_eq!(parse(";TIME:5025"), Some(5025));
+        assert_eq!(
+            parse("; estimated printing time (normal mode) = 1d 2h 3m 4s"),
+            Some(93_784)
+        );
+        assert_eq!(parse(";   Build time: 1 hours 23 minutes"), Some(4_980));
+        assert_eq!(parse("; total estimated time: 42m 10s"), Some(2_530));
+        assert_eq!(parse("; layer_height = 0.2"), None);
+        assert_eq!(
+            parse("; estimated printing time = 99999999999999999999m"),
+            None
+        );
+        assert_eq!(format_duration(4_980), "1h 23m");
+    }
+
+    #[test]
+    fn generator_comment_drops_timestamp() {
+        let mut metadata = GcodeMetadata::default();
+        metadata
+            .apply_comment("; generated by PrusaSlicer 2.6.0+win64 on 2023-09-01 at 10:00:00 UTC");
+        assert_eq!(
+            metadata.generator.as_deref(),
+            Some("PrusaSlicer 2.6.0+win64")
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Preview providers.
+//!
+//! Every file format family the preview window can render is handled by a
+//! `PreviewProvider`. Providers are kept in a process-wide registry that the
+//! preview thread consults when decoding and the Explorer hook consults when
+//! deciding whether a hovered file is previewable. New formats are added as
+//! self-contained modules in this directory and registered in `builtin_providers`.
+
+mod archive;
+mod art_project;
+mod blend;
+mod builtin;
+mod certificate;
+mod dicom;
+mod fits;
+mod game_pack;
+mod gcode;
+mod npy;
+mod parquet;
+mod plugin;
+mod reg_file;
+mod shortcut;
+
+use crate::preview_window::MediaData;
+use crate::CONFIG;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+pub use plugin::HpFrame;
+
+static REGISTRY: Lazy<RwLock<Vec<Arc<dyn PreviewProvider>>>> =
+    Lazy::new(|| RwLock::new(builtin_providers()));
+
+/// Limits a provider must respect while producing a preview.
+pub struct LoadConstraints {
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Set when the preview is superseded; long decodes should poll it.
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl LoadConstraints {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Acquire)
+    }
+}
+
+pub trait PreviewProvider: Send + Sync {
+    /// Cheap check, usually extension based. Called on the hover hot path.
+    fn can_handle(&self, path: &PathBuf) -> bool;
+
+    /// Original media dimensions, used to lay out the preview before decoding.
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)>;
+
+    /// Decode the preview, scaled to fit the constraints.
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData>;
+}
+
+fn builtin_providers() -> Vec<Arc<dyn PreviewProvider>> {
+    vec![
+        Arc::new(builtin::VideoProvider),
+        Arc::new(builtin::ImageProvider),
+        Arc::new(dicom::DicomProvider),
+        Arc::new(fits::FitsProvider),
+        Arc::new(npy::NpyProvider),
+        Arc::new(parquet::ParquetProvider),
+        Arc::new(art_project::ArtProjectProvider),
+        Arc::new(blend::BlendProvider),
+        Arc::new(game_pack::GamePackProvider),
+        Arc::new(gcode::GcodeProvider),
+        Arc::new(certificate::CertificateProvider),
+        Arc::new(shortcut::ShortcutProvider),
+        Arc::new(reg_file::RegFileProvider),
+    ]
+}
+
+pub fn has_extension(path: &PathBuf, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Whether `path` has one of the `image_extensions`.
+pub fn is_image(path: &PathBuf) -> bool {
+    CONFIG
+        .lock()
+        .map(|c| has_listed_extension(path, &c.image_extensions))
+        .unwrap_or(false)
+}
+
+/// Whether `path` has one of the `video_extensions`.
+pub fn is_video(path: &PathBuf) -> bool {
+    CONFIG
+        .lock()
+        .map(|c| has_listed_extension(path, &c.video_extensions))
+        .unwrap_or(false)
+}
+
+/// Whether previews are on for `path`'s type; `preview_videos` turns them
+/// off for videos.
+pub fn is_type_enabled(path: &PathBuf) -> bool {
+    !is_video(path) || CONFIG.lock().map(|c| c.preview_videos).unwrap_or(true)
+}
+
+/// Whether `path`'s extension is in `list`, separated by commas, with or
+/// without leading dots.
+fn has_listed_extension(path: &PathBuf, list: &str) -> bool {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return false;
+    };
+    list.split(',')
+        .map(|listed| listed.trim().trim_start_matches('.'))
+        .any(|listed| !listed.is_empty() && listed.eq_ignore_ascii_case(ext))
+}
+
+/// Register an additional provider. Later registrations take precedence over
+/// earlier ones, so a provider can override a built-in format.
+pub fn register(provider: Arc<dyn PreviewProvider>) {
+    if let Ok(mut registry) = REGISTRY.write() {
+        registry.insert(0, provider);
+    }
+}
+
+/// Register the third-party providers found in the plugins folder. Called once
+/// at startup, before the preview and Explorer hook threads start.
+pub fn register_plugins() {
+    for provider in plugin::load_plugins() {
+        register(provider);
+    }
+}
+
+/// Find the provider responsible for `path`.
+pub fn find(path: &PathBuf) -> Option<Arc<dyn PreviewProvider>> {
+    REGISTRY
+        .read()
+        .ok()?
+        .iter()
+        .find(|provider| provider.can_handle(path))
+        .cloned()
+}
+
+pub fn is_supported(path: &PathBuf) -> bool {
+    REGISTRY
+        .read()
+        .map(|registry| registry.iter().any(|provider| provider.can_handle(path)))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listed_extensions_match_with_or_without_dots_in_any_case() {
+        let list = " jpg, .HEIF ,, ts";
+        assert!(has_listed_extension(&PathBuf::from("C:\\a.JPG"), list));
+        assert!(has_listed_extension(&PathBuf::from("C:\\b.heif"), list));
+        assert!(has_listed_extension(&PathBuf::from("C:\\c.ts"), list));
+        assert!(!has_listed_extension(&PathBuf::from("C:\\d.png"), list));
+        assert!(!has_listed_extension(&PathBuf::from("C:\\none"), list));
+        assert!(!has_listed_extension(&PathBuf::from("C:\\e.jpg"), ""));
+    }
+}
@@ -0,0 +1,570 @@
+//! NumPy (`.npy`) arrays rendered as quick plots.
+//!
+//! 1D arrays (and 2D arrays with a single row or column) are drawn as a line
+//! plot with a min/max envelope where there are more values than pixels. 2D
+//! arrays are drawn as a heatmap; for higher dimensions the first 2D slice is
+//! shown. Integer, unsigned, float and bool dtypes in either byte order are
+//! supported. The dtype, shape and value range are drawn as a caption.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+const NPY_EXTENSIONS: &[&str] = &["npy"];
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+/// Bytes read to find the header; NumPy pads headers to 64 bytes and they are
+/// rarely longer than a few hundred.
+const NPY_MAX_HEADER_BYTES: u64 = 64 * 1024;
+/// Upper bound on array data read for one preview.
+const NPY_MAX_DATA_BYTES: usize = 64 * 1024 * 1024;
+
+const LINE_PLOT_WIDTH: u32 = 640;
+const LINE_PLOT_HEIGHT: u32 = 360;
+const LINE_PLOT_MARGIN: u32 = 16;
+const HEATMAP_MIN_SIDE: usize = 256;
+const HEATMAP_MAX_SIDE: usize = 1024;
+
+const PLOT_BACKGROUND: [u8; 4] = [0x1e, 0x1e, 0x1e, 0xff];
+const PLOT_AXIS: [u8; 4] = [0x50, 0x50, 0x50, 0xff];
+const PLOT_LINE: [u8; 4] = [0x4f, 0xc3, 0xf7, 0xff];
+const HEATMAP_MISSING: [u8; 4] = [0x80, 0x80, 0x80, 0xff];
+
+/// Viridis control points, evenly spaced from low to high.
+const VIRIDIS: [[u8; 3]; 5] = [
+    [0x44, 0x01, 0x54],
+    [0x3b, 0x52, 0x8b],
+    [0x21, 0x91, 0x8c],
+    [0x5e, 0xc9, 0x62],
+    [0xfd, 0xe7, 0x25],
+];
+
+pub struct NpyProvider;
+
+impl PreviewProvider for NpyProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, NPY_EXTENSIONS)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let header = read_header(path)?;
+        Some(plot_size(&header))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let header = read_header(path)?;
+        let layout = plot_layout(&header)?;
+        let values = read_values(path, &header, layout.value_count()?)?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let image = match layout {
+            PlotLayout::Line { .. } => render_line_plot(&values),
+            PlotLayout::Heatmap { rows, cols } => {
+                render_heatmap(&values, rows, cols, header.fortran_order)
+            }
+        }?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let mut media = static_media_from_image(
+            image::DynamicImage::ImageRgba8(image),
+            constraints.max_width,
+            constraints.max_height,
+        );
+        overlay::draw_caption(
+            media.first_frame_mut(),
+            &caption_lines(&header, &layout, &values),
+        );
+        Some(media)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DtypeKind {
+    Bool,
+    Int,
+    Uint,
+    Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Dtype {
+    kind: DtypeKind,
+    size: usize,
+    big_endian: bool,
+}
+
+impl Dtype {
+    fn parse(descr: &str) -> Option<Self> {
+        let mut chars = descr.chars();
+        let big_endian = match chars.next()? {
+            '>' => true,
+            '<' | '|' | '=' => false,
+            _ => return None,
+        };
+        let kind = match chars.next()? {
+            'b' => DtypeKind::Bool,
+            'i' => DtypeKind::Int,
+            'u' => DtypeKind::Uint,
+            'f' => DtypeKind::Float,
+            _ => return None,
+        };
+        let size: usize = chars.as_str().parse().ok()?;
+        let supported = match kind {
+            DtypeKind::Bool => size == 1,
+            DtypeKind::Int | DtypeKind::Uint => matches!(size, 1 | 2 | 4 | 8),
+            DtypeKind::Float => matches!(size, 4 | 8),
+        };
+        supported.then_some(Self {
+            kind,
+            size,
+            big_endian,
+        })
+    }
+
+    fn name(&self) -> String {
+        match self.kind {
+            DtypeKind::Bool => "bool".to_string(),
+            DtypeKind::Int => format!("int{}", self.size * 8),
+            DtypeKind::Uint => format!("uint{}", self.size * 8),
+            DtypeKind::Float => format!("float{}", self.size * 8),
+        }
+    }
+
+    fn read(&self, bytes: &[u8]) -> f64 {
+        let mut raw = [0u8; 8];
+        if self.big_endian {
+            raw[8 - self.size..].copy_from_slice(bytes);
+            raw.reverse();
+        } else {
+            raw[..self.size].copy_from_slice(bytes);
+        }
+        let bits = u64::from_le_bytes(raw);
+        match (self.kind, self.size) {
+            (DtypeKind::Bool, _) => (bits != 0) as u8 as f64,
+            (DtypeKind::Uint, _) => bits as f64,
+            (DtypeKind::Int, size) => {
+                let shift = 64 - size as u32 * 8;
+                (((bits << shift) as i64) >> shift) as f64
+            }
+            (DtypeKind::Float, 4) => f32::from_bits(bits as u32) as f64,
+            (DtypeKind::Float, _) => f64::from_bits(bits),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NpyHeader {
+    dtype: Dtype,
+    fortran_order: bool,
+    shape: Vec<usize>,
+    data_offset: usize,
+}
+
+impl NpyHeader {
+    /// `None` when the shape's product overflows, which no real file has.
+    fn element_count(&self) -> Option<usize> {
+        self.shape
+            .iter()
+            .try_fold(1usize, |count, &dim| count.checked_mul(dim))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PlotLayout {
+    Line { len: usize },
+    Heatmap { rows: usize, cols: usize },
+}
+
+impl PlotLayout {
+    fn value_count(&self) -> Option<usize> {
+        match *self {
+            PlotLayout::Line { len } => Some(len),
+            PlotLayout::Heatmap { rows, cols } => rows.checked_mul(cols),
+        }
+    }
+}
+
+fn read_header(path: &PathBuf) -> Option<NpyHeader> {
+    let mut data = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(NPY_MAX_HEADER_BYTES)
+        .read_to_end(&mut data)
+        .ok()?;
+    parse_header(&data)
+}
+
+fn parse_header(data: &[u8]) -> Option<NpyHeader> {
+    if data.len() < 10 || !data.starts_with(NPY_MAGIC) {
+        return None;
+    }
+    let (header_len, header_start) = match data[6] {
+        1 => (u16::from_le_bytes([data[8], data[9]]) as usize, 10),
+        2 | 3 => {
+            let bytes = data.get(8..12)?;
+            (
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+                12,
+            )
+        }
+        _ => return None,
+    };
+    let data_offset = header_start + header_len;
+    let text = std::str::from_utf8(data.get(header_start..data_offset)?).ok()?;
+
+    let descr = dict_value(text, "descr")?;
+    let is_quote = |c: char| c == '\'' || c == '"';
+    let descr = descr.strip_prefix(is_quote)?;
+    let descr = &descr[..descr.find(is_quote)?];
+    let fortran_order = dict_value(text, "fortran_order")?.starts_with("True");
+    let shape_text = dict_value(text, "shape")?.strip_prefix('(')?;
+    let shape_text = &shape_text[..shape_text.find(')')?];
+    let shape = shape_text
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.trim_end_matches('L').parse().ok())
+        .collect::<Option<Vec<usize>>>()?;
+
+    let header = NpyHeader {
+        dtype: Dtype::parse(descr)?,
+        fortran_order,
+        shape,
+        data_offset,
+    };
+    header.element_count()?;
+    Some(header)
+}
+
+/// Text following `'key':` in the header's Python dict literal.
+fn dict_value<'a>(text: &'a str, key: &str) -> Option<&'a str> {
+    let quoted = format!("'{}'", key);
+    let start = text.find(&quoted)? + quoted.len();
+    let rest = text[start..].trim_start().strip_prefix(':')?;
+    Some(rest.trim_start())
+}
+
+fn plot_layout(header: &NpyHeader) -> Option<PlotLayout> {
+    let dims: Vec<usize> = header.shape.iter().copied().filter(|&d| d != 1).collect();
+    match dims.as_slice() {
+        [] if !header.shape.is_empty() => Some(PlotLayout::Line { len: 1 }),
+        [len] => Some(PlotLayout::Line { len: *len }),
+        // Slicing only makes sense for C order, where the last two axes of the
+        // first slice are contiguous at the start of the data.
+        [rows, cols] => Some(PlotLayout::Heatmap {
+            rows: *rows,
+            cols: *cols,
+        }),
+        [.., rows, cols] if !header.fortran_order => Some(PlotLayout::Heatmap {
+            rows: *rows,
+            cols: *cols,
+        }),
+        _ => None,
+    }
+}
+
+fn plot_size(header: &NpyHeader) -> (u32, u32) {
+    match plot_layout(header) {
+        Some(PlotLayout::Heatmap { rows, cols }) => heatmap_size(rows, cols),
+        _ => (LINE_PLOT_WIDTH, LINE_PLOT_HEIGHT),
+    }
+}
+
+/// Output size for a heatmap: small arrays are enlarged by a whole factor so
+/// cells stay square, large ones are reduced to fit `HEATMAP_MAX_SIDE`.
+fn heatmap_size(rows: usize, cols: usize) -> (u32, u32) {
+    let longest = rows.max(cols).max(1);
+    let (width, height) = if longest < HEATMAP_MIN_SIDE {
+        let factor = HEATMAP_MIN_SIDE / longest;
+        (cols * factor, rows * factor)
+    } else if longest > HEATMAP_MAX_SIDE {
+        (
+            cols * HEATMAP_MAX_SIDE / longest,
+            rows * HEATMAP_MAX_SIDE / longest,
+        )
+    } else {
+        (cols, rows)
+    };
+    (width.max(1) as u32, height.max(1) as u32)
+}
+
+/// Read the first `count` values, capped at `NPY_MAX_DATA_BYTES`.
+fn read_values(path: &PathBuf, header: &NpyHeader, count: usize) -> Option<Vec<f64>> {
+    let size = header.dtype.size;
+    let count = count
+        .min(header.element_count()?)
+        .min(NPY_MAX_DATA_BYTES / size);
+    if count == 0 {
+        return None;
+    }
+
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::Start(header.data_offset as u64)).ok()?;
+    let mut bytes = Vec::new();
+    file.take((count * size) as u64)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    let values: Vec<f64> = bytes
+        .chunks_exact(size)
+        .map(|chunk| header.dtype.read(chunk))
+        .collect();
+    (!values.is_empty()).then_some(values)
+}
+
+fn finite_range(values: &[f64]) -> Option<(f64, f64)> {
+    values
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold(None, |range, v| match range {
+            None => Some((v, v)),
+            Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+        })
+}
+
+fn put_pixel(rgba: &mut [u8], width: u32, x: u32, y: u32, color: [u8; 4]) {
+    let offset = (y as usize * width as usize + x as usize) * 4;
+    if let Some(px) = rgba.get_mut(offset..offset + 4) {
+        px.copy_from_slice(&color);
+    }
+}
+
+fn render_line_plot(values: &[f64]) -> Option<image::RgbaImage> {
+    let (width, height) = (LINE_PLOT_WIDTH, LINE_PLOT_HEIGHT);
+    let mut rgba: Vec<u8> = PLOT_BACKGROUND
+        .iter()
+        .copied()
+        .cycle()
+        .take(width as usize * height as usize * 4)
+        .collect();
+
+    let (mut lo, mut hi) = finite_range(values)?;
+    if hi - lo < f64::EPSILON {
+        lo -= 1.0;
+        hi += 1.0;
+    }
+
+    let margin = LINE_PLOT_MARGIN;
+    let plot_width = width - margin * 2;
+    let plot_height = height - margin * 2;
+    let to_y = |v: f64| {
+        let t = ((v - lo) / (hi - lo)).clamp(0.0, 1.0);
+        margin + ((1.0 - t) * (plot_height - 1) as f64).round() as u32
+    };
+
+    if lo < 0.0 && hi > 0.0 {
+        let zero = to_y(0.0);
+        for x in margin..margin + plot_width {
+            put_pixel(&mut rgba, width, x, zero, PLOT_AXIS);
+        }
+    }
+
+    let mut previous: Option<u32> = None;
+    for column in 0..plot_width as usize {
+        let Some((column_lo, column_hi, last)) = column_values(values, column, plot_width as usize)
+        else {
+            previous = None;
+            continue;
+        };
+        let mut top = to_y(column_hi);
+        let mut bottom = to_y(column_lo);
+        // Join to the previous column so steep changes stay connected.
+        if let Some(previous) = previous {
+            top = top.min(previous);
+            bottom = bottom.max(previous);
+        }
+        let x = margin + column as u32;
+        for y in top..=bottom {
+            put_pixel(&mut rgba, width, x, y, PLOT_LINE);
+        }
+        previous = Some(to_y(last));
+    }
+
+    image::RgbaImage::from_raw(width, height, rgba)
+}
+
+/// Min, max and last finite value of the samples that fall in `column`. With
+/// fewer values than columns, the nearest value is repeated.
+fn column_values(values: &[f64], column: usize, columns: usize) -> Option<(f64, f64, f64)> {
+    let len = values.len();
+    let start = column * len / columns;
+    let end = ((column + 1) * len / columns).max(start + 1).min(len);
+    let mut finite = values[start.min(len - 1)..end]
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite());
+    let first = finite.next()?;
+    Some(finite.fold((first, first, first), |(lo, hi, _), v| {
+        (lo.min(v), hi.max(v), v)
+    }))
+}
+
+fn colormap(t: f64) -> [u8; 4] {
+    let scaled = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f64;
+    let index = (scaled.floor() as usize).min(VIRIDIS.len() - 2);
+    let frac = scaled - index as f64;
+    let (a, b) = (VIRIDIS[index], VIRIDIS[index + 1]);
+    let mix = |i: usize| (a[i] as f64 + (b[i] as f64 - a[i] as f64) * frac).round() as u8;
+    [mix(0), mix(1), mix(2), 0xff]
+}
+
+fn render_heatmap(
+    values: &[f64],
+    rows: usize,
+    cols: usize,
+    fortran_order: bool,
+) -> Option<image::RgbaImage> {
+    if values.len() < rows.checked_mul(cols)? {
+        return None;
+    }
+    let (lo, hi) = finite_range(values)?;
+    let span = if hi - lo < f64::EPSILON { 1.0 } else { hi - lo };
+
+    let (width, height) = heatmap_size(rows, cols);
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height as usize {
+        let row = y * rows / height as usize;
+        for x in 0..width as usize {
+            let col = x * cols / width as usize;
+            let index = if fortran_order {
+                col * rows + row
+            } else {
+                row * cols + col
+            };
+            let value = values[index];
+            if value.is_finite() {
+                rgba.extend_from_slice(&colormap((value - lo) / span));
+            } else {
+                rgba.extend_from_slice(&HEATMAP_MISSING);
+            }
+        }
+    }
+    image::RgbaImage::from_raw(width, height, rgba)
+}
+
+fn caption_lines(header: &NpyHeader, layout: &PlotLayout, values: &[f64]) -> Vec<String> {
+    let shape = header
+        .shape
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>()
+        .join(" x ");
+    let mut first = format!("{}  [{}]", header.dtype.name(), shape);
+    if header
+        .element_count()
+        .is_some_and(|count| values.len() < count)
+    {
+        match layout {
+            PlotLayout::Heatmap { .. } if layout.value_count() == Some(values.len()) => {
+                first.push_str("  (first slice)");
+            }
+            _ => first.push_str(&format!("  (first {} values)", values.len())),
+        }
+    }
+
+    let mut lines = vec![first];
+    if let Some((lo, hi)) = finite_range(values) {
+        lines.push(format!(
+            "min {}  max {}",
+            format_value(lo),
+            format_value(hi)
+        ));
+    }
+    lines
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else if value.abs() >= 1e5 || value.abs() < 1e-3 {
+        format!("{:.3e}", value)
+    } else {
+        format!("{:.4}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn npy_bytes(header: &str) -> Vec<u8> {
+        let mut data = NPY_MAGIC.to_vec();
+        data.extend_from_slice(&[1, 0]);
+        data.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        data.extend_from_slice(header.as_bytes());
+        data
+    }
+
+    #[test]
+    fn header_parses_dtype_order_and_shape() {
+        let data =
+            npy_bytes("{'descr': '<f8', 'fortran_order': False, 'shape': (3, 4), }          \n");
+        let header = parse_header(&data).expect("header");
+        assert_eq!(header.dtype.name(), "float64");
+        assert!(!header.fortran_order);
+        assert_eq!(header.shape, vec![3, 4]);
+        assert_eq!(header.data_offset, data.len());
+
+        let vector = npy_bytes("{'descr': '>i2', 'fortran_order': True, 'shape': (5,), }\n");
+        let header = parse_header(&vector).expect("header");
+        assert!(header.dtype.big_endian);
+        assert_eq!(header.shape, vec![5]);
+        assert_eq!(plot_layout(&header), Some(PlotLayout::Line { len: 5 }));
+
+        let huge = npy_bytes(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (4294967296, 4294967296), }\n",
+        );
+        assert!(parse_header(&huge).is_none());
+    }
+
+    #[test]
+    fn dtype_reads_both_byte_orders_and_signed_values() {
+        let little = Dtype::parse("<i2").unwrap();
+        assert_eq!(little.read(&(-300i16).to_le_bytes()), -300.0);
+        let big = Dtype::parse(">u4").unwrap();
+        assert_eq!(big.read(&70_000u32.to_be_bytes()), 70_000.0);
+        let float = Dtype::parse("<f4").unwrap();
+        assert_eq!(float.read(&1.5f32.to_le_bytes()), 1.5);
+        assert!(Dtype::parse("<c16").is_none());
+    }
+
+    #[test]
+    fn layout_collapses_unit_axes_and_slices_higher_dimensions() {
+        let mut header = parse_header(&npy_bytes(
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (1, 200), }\n",
+        ))
+        .unwrap();
+        assert_eq!(plot_layout(&header), Some(PlotLayout::Line { len: 200 }));
+
+        header.shape = vec![10, 20, 30];
+        assert_eq!(
+            plot_layout(&header),
+            Some(PlotLayout::Heatmap { rows: 20, cols: 30 })
+        );
+        header.fortran_order = true;
+        assert_eq!(plot_layout(&header), None);
+    }
+
+    #[test]
+    fn heatmap_size_enlarges_small_and_reduces_large_arrays() {
+        assert_eq!(heatmap_size(8, 16), (256, 128));
+        assert_eq!(heatmap_size(500, 300), (300, 500));
+        assert_eq!(heatmap_size(4096, 2048), (512, 1024));
+    }
+
+    #[test]
+    fn column_values_envelope_and_repeat() {
+        let values = [1.0, 5.0, -2.0, 3.0];
+        assert_eq!(column_values(&values, 0, 2), Some((1.0, 5.0, 5.0)));
+        assert_eq!(column_values(&values, 1, 2), Some((-2.0, 3.0, 3.0)));
+        assert_eq!(column_values(&values, 7, 8), Some((3.0, 3.0, 3.0)));
+        assert_eq!(column_values(&[f64::NAN], 0, 4), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Apache Parquet (`.parquet`) files rendered as a text panel.
+//!
+//! Shows the row, column and row group counts from the footer, the top-level
+//! schema with physical and logical types, and the first rows as a table.
+//! Only the footer and the first row group's pages are read.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::schema::types::Type;
+use std::fs::File;
+use std::path::PathBuf;
+
+const PARQUET_EXTENSIONS: &[&str] = &["parquet", "parq"];
+
+const PANEL_WIDTH: u32 = 760;
+const PANEL_HEIGHT: u32 = 520;
+/// Characters per line that fit in the panel at the panel font size.
+const PANEL_COLUMNS: usize = 96;
+const MAX_SCHEMA_LINES: usize = 12;
+const HEAD_ROWS: usize = 10;
+const MAX_CELL_CHARS: usize = 16;
+
+pub struct ParquetProvider;
+
+impl PreviewProvider for ParquetProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, PARQUET_EXTENSIONS)
+    }
+
+    fn dimensions(&self, _path: &PathBuf) -> Option<(u32, u32)> {
+        Some((PANEL_WIDTH, PANEL_HEIGHT))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let reader = SerializedFileReader::new(File::open(path).ok()?).ok()?;
+        let metadata = reader.metadata();
+        let file_metadata = metadata.file_metadata();
+        let fields = file_metadata.schema_descr().root_schema().get_fields();
+
+        let mut lines = vec![format!(
+            "{} rows  {} columns  {} row groups",
+            file_metadata.num_rows(),
+            fields.len(),
+            metadata.num_row_groups()
+        )];
+        if let Some(created_by) = file_metadata.created_by() {
+            lines.push(truncate(created_by, PANEL_COLUMNS));
+        }
+
+        lines.push(String::new());
+        lines.push("Schema".to_string());
+        for field in fields.iter().take(MAX_SCHEMA_LINES) {
+            lines.push(truncate(
+                &format!("  {:<24} {}", field.name(), type_label(field)),
+                PANEL_COLUMNS,
+            ));
+        }
+        if fields.len() > MAX_SCHEMA_LINES {
+            lines.push(format!("  ... {} more", fields.len() - MAX_SCHEMA_LINES));
+        }
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let mut table = vec![fields.iter().map(|f| f.name().to_string()).collect()];
+        if let Ok(rows) = reader.get_row_iter(None) {
+            for row in rows.take(HEAD_ROWS) {
+                let Ok(row) = row else {
+                    break;
+                };
+                table.push(
+                    row.get_column_iter()
+                        .map(|(_, value)| value.to_string())
+                        .collect(),
+                );
+                if constraints.is_cancelled() {
+                    return None;
+                }
+            }
+        }
+        if table.len() > 1 {
+            lines.push(String::new());
+            lines.push("First rows".to_string());
+            lines.extend(format_table(&table, PANEL_COLUMNS));
+        }
+
+        let image = overlay::render_text_panel(PANEL_WIDTH, PANEL_HEIGHT, &lines)?;
+        Some(static_media_from_image(
+            image::DynamicImage::ImageRgba8(image),
+            constraints.max_width,
+            constraints.max_height,
+        ))
+    }
+}
+
+fn type_label(field: &Type) -> String {
+    if !field.is_primitive() {
+        return "group".to_string();
+    }
+    match field.get_basic_info().logical_type() {
+        Some(logical) => format!("{} ({:?})", field.get_physical_type(), logical),
+        None => field.get_physical_type().to_string(),
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max_chars.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Lay out `table` (header row first) as fixed-width columns separated by
+/// `" | "`, clipped to `max_chars` per line.
+fn format_table(table: &[Vec<String>], max_chars: usize) -> Vec<String> {
+    let columns = table.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            table
+                .iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count().min(MAX_CELL_CHARS))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    table
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = widths
+                .iter()
+                .enumerate()
+                .map(|(column, width)| {
+                    let cell = row.get(column).map(String::as_str).unwrap_or("");
+                    format!("{:<width$}", truncate(cell, MAX_CELL_CHARS), width = width)
+                })
+                .collect();
+            truncate(cells.join(" | ").trim_end(), max_chars)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_pads_columns_and_truncates_long_cells() {
+        let table = vec![
+            vec!["id".to_string(), "name".to_string()],
+            vec!["1".to_string(), "a very long value indeed".to_string()],
+        ];
+        let lines = format_table(&table, 80);
+        assert_eq!(lines[0], "id | name");
+        assert_eq!(lines[1], "1  | a very long val…");
+        assert_eq!(format_table(&table, 6)[1], "1  | …");
+    }
+}
@@ -0,0 +1,264 @@
+//! Third-party providers loaded from DLLs in the `plugins` folder next to
+//! `config.ini`.
+//!
+//! A plugin is a C ABI DLL exporting:
+//!
+//! ```c
+//! typedef struct {
+//!     uint32_t width;
+//!     uint32_t height;
+//!     uint32_t stride;   /* bytes from one row to the next, >= width * 4 */
+//!     uint8_t *pixels;   /* RGBA8, owned by the plugin */
+//!     void *user_data;   /* free for the plugin to use */
+//! } HpFrame;
+//!
+//! uint32_t    hp_plugin_api_version(void);   /* must return 1 */
+//! const char *hp_plugin_extensions(void);    /* e.g. "dcm,dicom" */
+//! int32_t     hp_plugin_dimensions(const wchar_t *path, uint32_t *width, uint32_t *height);
+//! int32_t     hp_plugin_load(const wchar_t *path, uint32_t max_width, uint32_t max_height, HpFrame *out);
+//! void        hp_plugin_free(HpFrame *frame);
+//! ```
+//!
+//! `hp_plugin_dimensions` and `hp_plugin_load` return non-zero on success.
+//! Every frame returned by `hp_plugin_load` is handed back to `hp_plugin_free`
+//! once it has been copied. Plugins are loaded once at startup and stay loaded
+//! for the lifetime of the process.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::config::AppConfig;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::ffi::{c_char, c_void, CStr};
+use std::fs;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use windows::core::{PCSTR, PCWSTR};
+use windows::Win32::Foundation::{FreeLibrary, HMODULE};
+use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+
+const PLUGIN_API_VERSION: u32 = 1;
+
+/// A frame passed across the C ABI, by plugins and by `hp_load_preview`.
+#[repr(C)]
+pub struct HpFrame {
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub pixels: *mut u8,
+    pub user_data: *mut c_void,
+}
+
+type ApiVersionFn = unsafe extern "C" fn() -> u32;
+type ExtensionsFn = unsafe extern "C" fn() -> *const c_char;
+type DimensionsFn = unsafe extern "C" fn(*const u16, *mut u32, *mut u32) -> i32;
+type LoadFn = unsafe extern "C" fn(*const u16, u32, u32, *mut HpFrame) -> i32;
+type FreeFn = unsafe extern "C" fn(*mut HpFrame);
+
+pub struct PluginProvider {
+    extensions: Vec<String>,
+    dimensions_fn: DimensionsFn,
+    load_fn: LoadFn,
+    free_fn: FreeFn,
+}
+
+impl PreviewProvider for PluginProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                self.extensions
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(ext))
+            })
+            .unwrap_or(false)
+    }
+
+    fn dimensions(&self, path: &PathBuf) -> Option<(u32, u32)> {
+        let wide = to_wide(path);
+        let mut width = 0u32;
+        let mut height = 0u32;
+        let ok = unsafe { (self.dimensions_fn)(wide.as_ptr(), &mut width, &mut height) };
+        if ok != 0 && width > 0 && height > 0 {
+            Some((width, height))
+        } else {
+            None
+        }
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let wide = to_wide(path);
+        let mut frame = HpFrame {
+            width: 0,
+            height: 0,
+            stride: 0,
+            pixels: std::ptr::null_mut(),
+            user_data: std::ptr::null_mut(),
+        };
+        let ok = unsafe {
+            (self.load_fn)(
+                wide.as_ptr(),
+                constraints.max_width,
+                constraints.max_height,
+                &mut frame,
+            )
+        };
+        if ok == 0 {
+            return None;
+        }
+
+        // The plugin may clear the frame when it frees it.
+        let (width, height) = (frame.width, frame.height);
+        let rgba = copy_frame_pixels(&frame);
+        unsafe { (self.free_fn)(&mut frame) };
+
+        let image = image::RgbaImage::from_raw(width, height, rgba?)?;
+        Some(static_media_from_image(
+            image::DynamicImage::ImageRgba8(image),
+            constraints.max_width,
+            constraints.max_height,
+        ))
+    }
+}
+
+/// Copy the plugin-owned rows into a tightly packed RGBA buffer. Only
+/// `stride * (height - 1) + width * 4` bytes are read, so the padding after
+/// the last row need not be there.
+fn copy_frame_pixels(frame: &HpFrame) -> Option<Vec<u8>> {
+    let row_bytes = (frame.width as usize).checked_mul(4)?;
+    let stride = frame.stride as usize;
+    if frame.pixels.is_null() || row_bytes == 0 || frame.height == 0 || stride < row_bytes {
+        return None;
+    }
+
+    let height = frame.height as usize;
+    let len = stride.checked_mul(height - 1)?.checked_add(row_bytes)?;
+    let source = unsafe { std::slice::from_raw_parts(frame.pixels, len) };
+    let mut rgba = Vec::with_capacity(row_bytes * height);
+    for row in source.chunks(stride).take(height) {
+        rgba.extend_from_slice(&row[..row_bytes]);
+    }
+    Some(rgba)
+}
+
+fn to_wide(path: &Path) -> Vec<u16> {
+    path.as_os_str().encode_wide().chain(Some(0)).collect()
+}
+
+/// Split the plugin's comma separated extension list.
+fn parse_extension_list(list: &str) -> Vec<String> {
+    list.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
+/// Resolve an export. `name` must be NUL terminated.
+unsafe fn load_symbol<T: Copy>(module: HMODULE, name: &[u8]) -> Option<T> {
+    let proc = GetProcAddress(module, PCSTR(name.as_ptr()))?;
+    Some(std::mem::transmute_copy(&proc))
+}
+
+fn load_plugin(path: &Path) -> Option<PluginProvider> {
+    let wide = to_wide(path);
+    unsafe {
+        let module = LoadLibraryW(PCWSTR(wide.as_ptr())).ok()?;
+        let provider = provider_from_module(module);
+        if provider.is_none() {
+            let _ = FreeLibrary(module);
+        }
+        provider
+    }
+}
+
+/// The provider implemented by a loaded plugin DLL, if it has every export
+/// and speaks this API version.
+unsafe fn provider_from_module(module: HMODULE) -> Option<PluginProvider> {
+    let api_version: ApiVersionFn = load_symbol(module, b"hp_plugin_api_version\0")?;
+    if api_version() != PLUGIN_API_VERSION {
+        return None;
+    }
+
+    let extensions_fn: ExtensionsFn = load_symbol(module, b"hp_plugin_extensions\0")?;
+    let raw_extensions = extensions_fn();
+    if raw_extensions.is_null() {
+        return None;
+    }
+    let extensions = parse_extension_list(&CStr::from_ptr(raw_extensions).to_string_lossy());
+    if extensions.is_empty() {
+        return None;
+    }
+
+    Some(PluginProvider {
+        extensions,
+        dimensions_fn: load_symbol(module, b"hp_plugin_dimensions\0")?,
+        load_fn: load_symbol(module, b"hp_plugin_load\0")?,
+        free_fn: load_symbol(module, b"hp_plugin_free\0")?,
+    })
+}
+
+/// Folder scanned for plugin DLLs.
+pub fn plugins_dir() -> Option<PathBuf> {
+    AppConfig::config_path().and_then(|path| path.parent().map(|dir| dir.join("plugins")))
+}
+
+/// Load every plugin DLL in the plugins folder. Files that fail to load or do
+/// not implement the expected exports are skipped.
+pub fn load_plugins() -> Vec<Arc<dyn PreviewProvider>> {
+    let Some(dir) = plugins_dir() else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| has_extension(path, &["dll"]))
+        .collect();
+    // Deterministic precedence when two plugins claim the same extension.
+    paths.sort();
+
+    paths
+        .iter()
+        .filter_map(|path| load_plugin(path))
+        .map(|provider| Arc::new(provider) as Arc<dyn PreviewProvider>)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_list_accepts_common_separators() {
+        assert_eq!(
+            parse_extension_list("dcm, .DICOM;raw  nef"),
+            vec!["dcm", "dicom", "raw", "nef"]
+        );
+        assert!(parse_extension_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn frame_rows_are_copied_without_their_padding() {
+        // Two 1-pixel rows 8 bytes apart, with no padding after the last.
+        let mut pixels = vec![1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8];
+        let frame = HpFrame {
+            width: 1,
+            height: 2,
+            stride: 8,
+            pixels: pixels.as_mut_ptr(),
+            user_data: std::ptr::null_mut(),
+        };
+        assert_eq!(
+            copy_frame_pixels(&frame),
+            Some(vec![1, 2, 3, 4, 5, 6, 7, 8])
+        );
+
+        let narrow = HpFrame { stride: 2, ..frame };
+        assert_eq!(copy_frame_pixels(&narrow), None);
+    }
+}
@@ -0,0 +1,181 @@
+//! Registry exports (`.reg`) rendered as a text panel.
+//!
+//! Shows the export format, how many keys and values the file touches, and
+//! its first keys and values. Regedit writes UTF-16 (`Windows Registry Editor
+//! Version 5.00`) while older tools write ANSI (`REGEDIT4`); both are read.
+//! Values continued over several lines with a trailing `\` are joined before
+//! being shortened, so long `hex:` blobs take one line.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+const REG_EXTENSIONS: &[&str] = &["reg"];
+
+/// Only the start of the file is shown; large exports are not read in full.
+const MAX_READ_BYTES: u64 = 256 * 1024;
+const MAX_LISTED_LINES: usize = 22;
+
+const PANEL_WIDTH: u32 = 760;
+const PANEL_HEIGHT: u32 = 460;
+const PANEL_COLUMNS: usize = 96;
+
+pub struct RegFileProvider;
+
+impl PreviewProvider for RegFileProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, REG_EXTENSIONS)
+    }
+
+    fn dimensions(&self, _path: &PathBuf) -> Option<(u32, u32)> {
+        Some((PANEL_WIDTH, PANEL_HEIGHT))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let mut data = Vec::new();
+        File::open(path)
+            .ok()?
+            .take(MAX_READ_BYTES)
+            .read_to_end(&mut data)
+            .ok()?;
+        let truncated = data.len() as u64 == MAX_READ_BYTES;
+        let summary = summarize(&decode_text(&data))?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let mut lines = vec![
+            summary.header.clone(),
+            format!(
+                "{}{} keys, {} values",
+                if truncated { "At least " } else { "" },
+                summary.keys,
+                summary.values
+            ),
+            String::new(),
+        ];
+        lines.extend(summary.entries.iter().map(|entry| truncate(entry)));
+        let panel = overlay::render_text_panel(PANEL_WIDTH, PANEL_HEIGHT, &lines)?;
+        Some(static_media_from_image(
+            image::DynamicImage::ImageRgba8(panel),
+            constraints.max_width,
+            constraints.max_height,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct RegSummary {
+    header: String,
+    keys: usize,
+    values: usize,
+    /// First keys (as written) and values (indented).
+    entries: Vec<String>,
+}
+
+/// UTF-16LE with a BOM, otherwise UTF-8/ANSI read lossily.
+fn decode_text(data: &[u8]) -> String {
+    if let Some(utf16) = data.strip_prefix(&[0xff, 0xfe]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    let data = data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data);
+    String::from_utf8_lossy(data).into_owned()
+}
+
+/// `None` unless the text starts with a registry export header.
+fn summarize(text: &str) -> Option<RegSummary> {
+    let mut lines = text.lines().map(str::trim_end);
+    let header = lines
+        .find(|line| !line.trim().is_empty())?
+        .trim()
+        .to_string();
+    if header != "Windows Registry Editor Version 5.00" && header != "REGEDIT4" {
+        return None;
+    }
+
+    let mut summary = RegSummary {
+        header,
+        keys: 0,
+        values: 0,
+        entries: Vec::new(),
+    };
+    let mut pending = String::new();
+    for line in lines {
+        // A trailing backslash continues the value on the next line.
+        if let Some(continued) = line.strip_suffix('\\') {
+            pending.push_str(continued.trim_start());
+            continue;
+        }
+        pending.push_str(line.trim_start());
+        let entry = std::mem::take(&mut pending);
+
+        if entry.is_empty() || entry.starts_with(';') {
+            continue;
+        }
+        let listed = if entry.starts_with('[') {
+            summary.keys += 1;
+            entry
+        } else {
+            summary.values += 1;
+            format!("  {}", entry)
+        };
+        if summary.entries.len() < MAX_LISTED_LINES {
+            summary.entries.push(listed);
+        }
+    }
+    Some(summary)
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= PANEL_COLUMNS {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(PANEL_COLUMNS - 1).collect();
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_exports_are_decoded() {
+        let mut data = vec![0xff, 0xfe];
+        for unit in "REGEDIT4".encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert_eq!(decode_text(&data), "REGEDIT4");
+        assert_eq!(decode_text(b"\xef\xbb\xbfREGEDIT4"), "REGEDIT4");
+    }
+
+    #[test]
+    fn keys_and_joined_values_are_listed() {
+        let text = "Windows Registry Editor Version 5.00\r\n\r\n\
+            [HKEY_CURRENT_USER\\Software\\Demo]\r\n\
+            \"Enabled\"=dword:00000001\r\n\
+            \"Blob\"=hex:01,02,\\\r\n  03,04\r\n\
+            ; comment\r\n\
+            [-HKEY_CURRENT_USER\\Software\\Old]\r\n";
+        let summary = summarize(text).unwrap();
+        assert_eq!(summary.keys, 2);
+        assert_eq!(summary.values, 2);
+        assert_eq!(
+            summary.entries,
+            vec![
+                "[HKEY_CURRENT_USER\\Software\\Demo]".to_string(),
+                "  \"Enabled\"=dword:00000001".to_string(),
+                "  \"Blob\"=hex:01,02,03,04".to_string(),
+                "[-HKEY_CURRENT_USER\\Software\\Old]".to_string(),
+            ]
+        );
+        assert!(summarize("not a registry file").is_none());
+    }
+}
@@ -0,0 +1,222 @@
+//! Windows shortcuts (`.lnk`) whose target is not previewable, rendered as a
+//! text panel.
+//!
+//! Shortcuts to supported media are resolved to their target by the Explorer
+//! hook and never reach this provider. For everything else (programs, folders,
+//! documents) the panel shows what the shortcut runs: target, arguments,
+//! working directory, icon location, window state and comment, read through
+//! `IShellLinkW` without touching the target.
+
+use super::{has_extension, LoadConstraints, PreviewProvider};
+use crate::overlay;
+use crate::preview_window::{static_media_from_image, MediaData};
+use std::os::windows::ffi::OsStrExt;
+use std::path::PathBuf;
+use windows::core::{Interface, PCWSTR};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoUninitialize, IPersistFile, CLSCTX_INPROC_SERVER,
+    COINIT_APARTMENTTHREADED, STGM_READ,
+};
+use windows::Win32::UI::Shell::{IShellLinkW, ShellLink, SLGP_RAWPATH};
+use windows::Win32::UI::WindowsAndMessaging::{SW_SHOWMAXIMIZED, SW_SHOWMINNOACTIVE};
+
+const SHORTCUT_EXTENSIONS: &[&str] = &["lnk"];
+
+/// Arguments and comments may be longer than `MAX_PATH` (up to `INFOTIPSIZE`).
+const FIELD_CHARS: usize = 1024;
+
+const PANEL_WIDTH: u32 = 760;
+const PANEL_HEIGHT: u32 = 260;
+const PANEL_COLUMNS: usize = 96;
+const LABEL_WIDTH: usize = 11;
+/// Long values wrap onto at most this many lines.
+const MAX_VALUE_LINES: usize = 3;
+
+pub struct ShortcutProvider;
+
+impl PreviewProvider for ShortcutProvider {
+    fn can_handle(&self, path: &PathBuf) -> bool {
+        has_extension(path, SHORTCUT_EXTENSIONS)
+    }
+
+    fn dimensions(&self, _path: &PathBuf) -> Option<(u32, u32)> {
+        Some((PANEL_WIDTH, PANEL_HEIGHT))
+    }
+
+    fn load(&self, path: &PathBuf, constraints: &LoadConstraints) -> Option<MediaData> {
+        let shortcut = read_shortcut(path)?;
+        if constraints.is_cancelled() {
+            return None;
+        }
+
+        let panel = overlay::render_text_panel(PANEL_WIDTH, PANEL_HEIGHT, &shortcut.lines())?;
+        Some(static_media_from_image(
+            image::DynamicImage::ImageRgba8(panel),
+            constraints.max_width,
+            constraints.max_height,
+        ))
+    }
+}
+
+struct Shortcut {
+    target: String,
+    arguments: String,
+    working_dir: String,
+    icon: String,
+    show: &'static str,
+    comment: String,
+}
+
+impl Shortcut {
+    fn lines(&self) -> Vec<String> {
+        let target = if self.target.is_empty() {
+            "(shell item, not a file system path)"
+        } else {
+            &self.target
+        };
+        let mut lines = vec!["Shortcut".to_string(), String::new()];
+        for (label, value) in [
+            ("Target", target),
+            ("Arguments", self.arguments.as_str()),
+            ("Start in", self.working_dir.as_str()),
+            ("Icon", self.icon.as_str()),
+            ("Run", self.show),
+            ("Comment", self.comment.as_str()),
+        ] {
+            if !value.is_empty() {
+                lines.extend(labelled_lines(label, value));
+            }
+        }
+        lines
+    }
+}
+
+/// `label: value`, with long values wrapped under the value column.
+fn labelled_lines(label: &str, value: &str) -> Vec<String> {
+    let width = PANEL_COLUMNS - LABEL_WIDTH;
+    let chars: Vec<char> = value.chars().collect();
+    let chunks: Vec<String> = chars
+        .chunks(width)
+        .map(|chunk| chunk.iter().collect())
+        .collect();
+
+    let mut lines = Vec::new();
+    for (index, chunk) in chunks.iter().take(MAX_VALUE_LINES).enumerate() {
+        let mut text = chunk.clone();
+        if index + 1 == MAX_VALUE_LINES && chunks.len() > MAX_VALUE_LINES {
+            text.pop();
+            text.push('…');
+        }
+        let prefix = if index == 0 {
+            format!("{}:", label)
+        } else {
+            String::new()
+        };
+        lines.push(format!("{:<width$}{}", prefix, text, width = LABEL_WIDTH));
+    }
+    lines
+}
+
+fn wide_field(buffer: &[u16]) -> String {
+    let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+    String::from_utf16_lossy(&buffer[..len])
+}
+
+fn read_shortcut(path: &PathBuf) -> Option<Shortcut> {
+    unsafe {
+        // Provider loads run on worker threads that may not have joined COM yet.
+        let com_initialized = CoInitializeEx(None, COINIT_APARTMENTTHREADED).is_ok();
+        let shortcut = read_shell_link(path);
+        if com_initialized {
+            CoUninitialize();
+        }
+        shortcut
+    }
+}
+
+unsafe fn read_shell_link(path: &PathBuf) -> Option<Shortcut> {
+    let shell_link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER).ok()?;
+    let persist_file = shell_link.cast::<IPersistFile>().ok()?;
+    let wide_path: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    persist_file
+        .Load(PCWSTR(wide_path.as_ptr()), STGM_READ)
+        .ok()?;
+
+    // Raw paths keep environment variables such as %SystemRoot% as stored.
+    let mut buffer = vec![0u16; FIELD_CHARS];
+    let target = shell_link
+        .GetPath(&mut buffer, std::ptr::null_mut(), SLGP_RAWPATH.0 as u32)
+        .map(|_| wide_field(&buffer))
+        .unwrap_or_default();
+
+    let mut buffer = vec![0u16; FIELD_CHARS];
+    let arguments = shell_link
+        .GetArguments(&mut buffer)
+        .map(|_| wide_field(&buffer))
+        .unwrap_or_default();
+
+    let mut buffer = vec![0u16; FIELD_CHARS];
+    let working_dir = shell_link
+        .GetWorkingDirectory(&mut buffer)
+        .map(|_| wide_field(&buffer))
+        .unwrap_or_default();
+
+    let mut buffer = vec![0u16; FIELD_CHARS];
+    let mut icon_index = 0i32;
+    let icon = shell_link
+        .GetIconLocation(&mut buffer, &mut icon_index)
+        .map(|_| wide_field(&buffer))
+        .unwrap_or_default();
+    let icon = if icon.is_empty() {
+        icon
+    } else {
+        format!("{},{}", icon, icon_index)
+    };
+
+    let mut buffer = vec![0u16; FIELD_CHARS];
+    let comment = shell_link
+        .GetDescription(&mut buffer)
+        .map(|_| wide_field(&buffer))
+        .unwrap_or_default();
+
+    let show = match shell_link.GetShowCmd() {
+        Ok(cmd) if cmd == SW_SHOWMAXIMIZED => "Maximized",
+        Ok(cmd) if cmd == SW_SHOWMINNOACTIVE => "Minimized",
+        _ => "Normal window",
+    };
+
+    Some(Shortcut {
+        target,
+        arguments,
+        working_dir,
+        icon,
+        show,
+        comment,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_values_wrap_under_the_value_column() {
+        assert_eq!(
+            labelled_lines("Target", "C:\\Tools\\app.exe"),
+            vec!["Target:    C:\\Tools\\app.exe".to_string()]
+        );
+
+        let long = "x".repeat(PANEL_COLUMNS * 4);
+        let lines = labelled_lines("Arguments", &long);
+        assert_eq!(lines.len(), MAX_VALUE_LINES);
+        assert!(lines[1].starts_with(&" ".repeat(LABEL_WIDTH)));
+        assert!(lines[2].ends_with('…'));
+        assert!(lines
+            .iter()
+            .all(|line| line.chars().count() <= PANEL_COLUMNS));
+    }
+}
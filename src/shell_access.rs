@@ -0,0 +1,182 @@
+//! Whether the Explorer hook can reach Explorer through COM, checked when the
+//! hook starts and again after Explorer restarts.
+//!
+//! The hook asks `ShellWindows` for each Explorer window's folder and UI
+//! Automation for the focused item. When COM cannot be initialized, or on a
+//! custom shell that registers no `ShellWindows`, those calls fail quietly and
+//! previews stop with no sign why. What is missing is written to
+//! `rust-hover-preview-com.log` in the temp folder and shown in the tray, and
+//! the hook falls back to what accessibility alone offers: MSAA for the item
+//! under the cursor and each window's address bar for its folder.
+
+use crate::{tray, RUNNING};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use windows::core::HRESULT;
+use windows::Win32::Foundation::RPC_E_CHANGED_MODE;
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_ALL};
+use windows::Win32::UI::Shell::{IShellWindows, ShellWindows};
+
+/// Tries at creating `ShellWindows`, which fails while Explorer is still
+/// starting at sign-in.
+const SHELL_WINDOWS_ATTEMPTS: u32 = 5;
+const RETRY_INTERVAL: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShellAccess {
+    /// Why `CoInitializeEx` failed on the hook's thread, if it did.
+    pub com_error: Option<HRESULT>,
+    pub shell_windows: bool,
+    pub ui_automation: bool,
+}
+
+impl ShellAccess {
+    /// What is missing, such as `ShellWindows and UI Automation are
+    /// unavailable`, or `None` when nothing is.
+    pub fn problem(&self) -> Option<String> {
+        let missing = match self.com_error {
+            Some(error) => vec![format!("COM (0x{:08X})", error.0 as u32)],
+            None => [
+                (!self.shell_windows).then(|| "ShellWindows".to_string()),
+                (!self.ui_automation).then(|| "UI Automation".to_string()),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+        };
+        match missing.len() {
+            0 => None,
+            1 => Some(format!("{} is unavailable", missing[0])),
+            _ => Some(format!("{} are unavailable", missing.join(" and "))),
+        }
+    }
+}
+
+static ACCESS: Mutex<Option<ShellAccess>> = Mutex::new(None);
+/// Whether there is a problem the tray has yet to show.
+static UNREPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Check what of COM the hook can use, given what `CoInitializeEx` returned
+/// on its thread and whether it could create UI Automation, then log and
+/// report anything missing.
+pub fn check(com_init: HRESULT, ui_automation: bool) {
+    // A thread already in another apartment can still use COM.
+    let com_error = (com_init.is_err() && com_init != RPC_E_CHANGED_MODE).then_some(com_init);
+    let mut shell_windows = false;
+    if com_error.is_none() {
+        for attempt in 0..SHELL_WINDOWS_ATTEMPTS {
+            if attempt > 0 {
+                std::thread::sleep(RETRY_INTERVAL);
+            }
+            shell_windows = unsafe {
+                CoCreateInstance::<_, IShellWindows>(&ShellWindows, None, CLSCTX_ALL).is_ok()
+            };
+            if shell_windows || !RUNNING.load(Ordering::Acquire) {
+                break;
+            }
+        }
+    }
+    let access = ShellAccess {
+        com_error,
+        shell_windows,
+        ui_automation,
+    };
+    let previous = ACCESS
+        .lock()
+        .ok()
+        .and_then(|mut current| current.replace(access));
+
+    if let Some(problem) = access.problem() {
+        log(&problem);
+        UNREPORTED.store(true, Ordering::Release);
+        tray::shell_access_checked();
+    } else if previous.and_then(|previous| previous.problem()).is_some() {
+        // Let the tray take back its warning.
+        tray::shell_access_checked();
+    }
+}
+
+/// Whether `ShellWindows` can be used; assumed so until the check says not.
+pub fn shell_windows_available() -> bool {
+    ACCESS
+        .lock()
+        .ok()
+        .and_then(|access| *access)
+        .map(|access| access.shell_windows)
+        .unwrap_or(true)
+}
+
+/// What the check found missing, if anything.
+pub fn problem() -> Option<String> {
+    ACCESS.lock().ok().and_then(|access| (*access)?.problem())
+}
+
+/// The problem, the first time it is asked for after the check.
+pub fn take_unreported() -> Option<String> {
+    if UNREPORTED.swap(false, Ordering::AcqRel) {
+        problem()
+    } else {
+        None
+    }
+}
+
+pub fn log_path() -> PathBuf {
+    env::temp_dir().join("rust-hover-preview-com.log")
+}
+
+fn log(problem: &str) {
+    let Ok(mut file) = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path())
+    else {
+        return;
+    };
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let _ = writeln!(
+        file,
+        "time={} problem=\"{}\" fallback=\"accessibility only\"",
+        time, problem
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problem_names_what_is_missing() {
+        let mut access = ShellAccess {
+            com_error: None,
+            shell_windows: true,
+            ui_automation: true,
+        };
+        assert_eq!(access.problem(), None);
+
+        access.shell_windows = false;
+        assert_eq!(
+            access.problem().as_deref(),
+            Some("ShellWindows is unavailable")
+        );
+        access.ui_automation = false;
+        assert_eq!(
+            access.problem().as_deref(),
+            Some("ShellWindows and UI Automation are unavailable")
+        );
+
+        // Without COM, nothing built on it is listed separately.
+        access.com_error = Some(HRESULT(0x8007_000E_u32 as i32));
+        assert_eq!(
+            access.problem().as_deref(),
+            Some("COM (0x8007000E) is unavailable")
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Optional sound cues when a preview appears and when it goes, so the hover
+//! registering can be heard as well as seen.
+//!
+//! `show_sound` and `hide_sound` name WAV files, played asynchronously with
+//! `PlaySound`. It has no volume of its own, so `sound_volume` scales the
+//! samples of 16-bit PCM files before they play; other WAV formats play at
+//! their recorded volume.
+
+use crate::CONFIG;
+use std::sync::Mutex;
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::HMODULE;
+use windows::Win32::Media::Audio::{PlaySoundW, SND_ASYNC, SND_FLAGS, SND_MEMORY, SND_NODEFAULT};
+
+/// The cue playing, which `PlaySound` reads from until it finishes.
+static PLAYING: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+
+const WAVE_FORMAT_PCM: u16 = 1;
+
+/// Play `show_sound`, if one is set, for a preview that just appeared.
+pub fn preview_shown() {
+    let (path, volume) = CONFIG
+        .lock()
+        .map(|c| (c.show_sound.clone(), c.sound_volume))
+        .unwrap_or_default();
+    play(&path, volume);
+}
+
+/// Play `hide_sound`, if one is set, for a preview that just went.
+pub fn preview_hidden() {
+    let (path, volume) = CONFIG
+        .lock()
+        .map(|c| (c.hide_sound.clone(), c.sound_volume))
+        .unwrap_or_default();
+    play(&path, volume);
+}
+
+fn play(path: &str, volume: u32) {
+    let path = path.trim();
+    if path.is_empty() || volume == 0 {
+        return;
+    }
+    let Ok(mut data) = std::fs::read(path) else {
+        return;
+    };
+    scale_volume(&mut data, volume);
+    let Ok(mut playing) = PLAYING.lock() else {
+        return;
+    };
+    unsafe {
+        // Stop the last cue before the buffer it plays from is replaced.
+        let _ = PlaySoundW(PCWSTR::null(), HMODULE::default(), SND_FLAGS(0));
+        *playing = data;
+        let _ = PlaySoundW(
+            PCWSTR(playing.as_ptr() as *const u16),
+            HMODULE::default(),
+            SND_MEMORY | SND_ASYNC | SND_NODEFAULT,
+        );
+    }
+}
+
+/// Scale the samples of a 16-bit PCM WAV file to `volume` percent in place.
+/// Other files, and volumes of 100 or more, are left as they are.
+fn scale_volume(wav: &mut [u8], volume: u32) {
+    if volume >= 100 {
+        return;
+    }
+    let Some(samples) = pcm16_samples(wav) else {
+        return;
+    };
+    for sample in wav[samples].chunks_exact_mut(2) {
+        let value = i16::from_le_bytes([sample[0], sample[1]]) as i32;
+        let scaled = (value * volume as i32 / 100) as i16;
+        sample.copy_from_slice(&scaled.to_le_bytes());
+    }
+}
+
+/// The byte range of the `data` chunk of a 16-bit PCM WAV file.
+fn pcm16_samples(wav: &[u8]) -> Option<std::ops::Range<usize>> {
+    if wav.get(..4)? != b"RIFF" || wav.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut pcm16 = false;
+    let mut pos = 12;
+    while let Some(header) = wav.get(pos..pos + 8) {
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let body = pos + 8;
+        match &header[..4] {
+            b"fmt " => {
+                let format = wav.get(body..body + 16)?;
+                let tag = u16::from_le_bytes([format[0], format[1]]);
+                let bits = u16::from_le_bytes([format[14], format[15]]);
+                pcm16 = tag == WAVE_FORMAT_PCM && bits == 16;
+            }
+            b"data" if pcm16 => return Some(body..body.checked_add(size)?.min(wav.len())),
+            b"data" => return None,
+            _ => {}
+        }
+        // Chunks are padded to an even length.
+        pos = body.checked_add(size)?.checked_add(size % 2)?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav(bits: u16, samples: &[i16]) -> Vec<u8> {
+        let mut data = b"RIFF\0\0\0\0WAVE".to_vec();
+        data.extend_from_slice(b"fmt ");
+        data.extend_from_slice(&16u32.to_le_bytes());
+        for value in [WAVE_FORMAT_PCM, 1] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [22_050u32, 44_100] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [2u16, bits] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        // An odd-sized chunk before the samples, padded to even.
+        data.extend_from_slice(b"LIST");
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(b"abc\0");
+        data.extend_from_slice(b"data");
+        data.extend_from_slice(&(samples.len() as u32 * 2).to_le_bytes());
+        for sample in samples {
+            data.extend_from_slice(&sample.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn pcm16_samples_are_scaled_to_the_volume() {
+        let mut data = wav(16, &[1000, -2000, i16::MAX]);
+        scale_volume(&mut data, 50);
+        let samples = pcm16_samples(&data).unwrap();
+        assert_eq!(
+            &data[samples],
+            [500i16, -1000, i16::MAX / 2]
+                .iter()
+                .flat_map(|sample| sample.to_le_bytes())
+                .collect::<Vec<u8>>()
+        );
+
+        let original = wav(16, &[1000]);
+        let mut full = original.clone();
+        scale_volume(&mut full, 100);
+        assert_eq!(full, original);
+
+        // Only 16-bit PCM is understood.
+        let eight_bit = wav(8, &[1000]);
+        let mut untouched = eight_bit.clone();
+        scale_volume(&mut untouched, 50);
+        assert_eq!(untouched, eight_bit);
+        assert!(pcm16_samples(b"RIFF").is_none());
+    }
+}
@@ -0,0 +1,151 @@
+//! Adaptive quality under heavy system load.
+//!
+//! A background thread samples overall CPU usage once a second. When it stays
+//! at or above `high_load_cpu_percent` for `SAMPLES_TO_ENTER` samples, previews
+//! cut back until it has stayed `RECOVERY_MARGIN_PERCENT` below that for
+//! `SAMPLES_TO_LEAVE` samples: animations show every other frame at their
+//! normal speed, videos open as a still and start playing once the load has
+//! dropped, and neighbours are not prefetched. The margin keeps quality from
+//! flapping while usage hovers around the threshold.
+
+use crate::{CONFIG, RUNNING};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use windows::Win32::Foundation::FILETIME;
+use windows::Win32::System::Threading::GetSystemTimes;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const SAMPLES_TO_ENTER: u32 = 2;
+const SAMPLES_TO_LEAVE: u32 = 3;
+const RECOVERY_MARGIN_PERCENT: f32 = 15.0;
+
+static HIGH: AtomicBool = AtomicBool::new(false);
+
+/// Whether previews should currently cut back.
+pub fn is_high() -> bool {
+    HIGH.load(Ordering::Acquire)
+}
+
+/// Idle, kernel (including idle) and user time of all processors, in 100 ns
+/// units.
+#[derive(Clone, Copy)]
+struct CpuTimes {
+    idle: u64,
+    kernel: u64,
+    user: u64,
+}
+
+#[derive(Default)]
+struct LoadState {
+    high: bool,
+    /// Consecutive samples arguing for leaving the current state.
+    streak: u32,
+}
+
+impl LoadState {
+    fn update(&mut self, usage_percent: f32, threshold_percent: u32) -> bool {
+        let threshold = threshold_percent as f32;
+        let crossing = if self.high {
+            usage_percent < threshold - RECOVERY_MARGIN_PERCENT
+        } else {
+            usage_percent >= threshold
+        };
+        self.streak = if crossing { self.streak + 1 } else { 0 };
+        let needed = if self.high {
+            SAMPLES_TO_LEAVE
+        } else {
+            SAMPLES_TO_ENTER
+        };
+        if self.streak >= needed {
+            self.high = !self.high;
+            self.streak = 0;
+        }
+        self.high
+    }
+}
+
+/// Sample CPU usage until the app exits.
+pub fn run_monitor() {
+    let mut state = LoadState::default();
+    let mut previous = cpu_times();
+    while RUNNING.load(Ordering::Acquire) {
+        std::thread::sleep(SAMPLE_INTERVAL);
+        let threshold = CONFIG.lock().map(|c| c.high_load_cpu_percent).unwrap_or(0);
+        let current = cpu_times();
+        let usage = previous.zip(current).and_then(|(a, b)| usage_percent(a, b));
+        previous = current;
+        let high = match usage {
+            Some(usage) if threshold > 0 => state.update(usage, threshold),
+            _ => {
+                state = LoadState::default();
+                false
+            }
+        };
+        HIGH.store(high, Ordering::Release);
+    }
+}
+
+fn cpu_times() -> Option<CpuTimes> {
+    let mut idle = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    unsafe {
+        GetSystemTimes(
+            Some(&mut idle as *mut _),
+            Some(&mut kernel as *mut _),
+            Some(&mut user as *mut _),
+        )
+        .ok()?
+    };
+    let ticks = |time: FILETIME| ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    Some(CpuTimes {
+        idle: ticks(idle),
+        kernel: ticks(kernel),
+        user: ticks(user),
+    })
+}
+
+/// Busy share of the processor time between two samples.
+fn usage_percent(before: CpuTimes, after: CpuTimes) -> Option<f32> {
+    let idle = after.idle.checked_sub(before.idle)?;
+    let total = after.kernel.checked_sub(before.kernel)? + after.user.checked_sub(before.user)?;
+    if total == 0 {
+        return None;
+    }
+    Some(100.0 * total.saturating_sub(idle) as f32 / total as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_counts_kernel_time_without_idle() {
+        let before = CpuTimes {
+            idle: 1_000,
+            kernel: 2_000,
+            user: 500,
+        };
+        let after = CpuTimes {
+            idle: 1_250,
+            kernel: 2_500,
+            user: 1_000,
+        };
+        assert_eq!(usage_percent(before, after), Some(75.0));
+        assert_eq!(usage_percent(before, before), None);
+    }
+
+    #[test]
+    fn load_state_needs_a_streak_each_way() {
+        let mut state = LoadState::default();
+        assert!(!state.update(90.0, 85));
+        assert!(!state.update(50.0, 85));
+        assert!(!state.update(90.0, 85));
+        assert!(state.update(95.0, 85));
+        // Within the margin below the threshold still counts as high.
+        assert!(state.update(75.0, 85));
+        assert!(state.update(60.0, 85));
+        assert!(state.update(60.0, 85));
+        assert!(!state.update(60.0, 85));
+    }
+}
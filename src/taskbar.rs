@@ -0,0 +1,147 @@
+//! Previews from the taskbar thumbnails of Explorer windows.
+//!
+//! Hovering a taskbar button shows its windows' thumbnails, drawn by DWM in a
+//! `TaskListThumbnailWnd` popup. The hovered thumbnail's accessible name is
+//! its window's title, which finds the Explorer window; the file selected in
+//! that window is previewed beside the popup, laid out like a keyboard preview
+//! of the popup's bounds.
+
+use crate::excluded_paths;
+use crate::explorer_hook::get_selected_media_file_in_window;
+use crate::network_drives;
+use crate::preview_window::{hide_preview, show_preview_keyboard};
+use crate::providers;
+use crate::{CONFIG, RUNNING};
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use windows::Win32::Foundation::{HWND, POINT, RECT};
+use windows::Win32::Graphics::Dwm::{DwmGetWindowAttribute, DWMWA_EXTENDED_FRAME_BOUNDS};
+use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED};
+use windows::Win32::System::Variant::VARIANT;
+use windows::Win32::UI::Accessibility::{AccessibleObjectFromPoint, IAccessible};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetAncestor, GetClassNameW, GetCursorPos, GetWindowRect, WindowFromPoint, GA_ROOT,
+};
+
+/// Class of the popup that holds a taskbar button's thumbnails.
+const THUMBNAIL_CLASS: &str = "TaskListThumbnailWnd";
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Polling while the feature is off, to notice it being turned on.
+const IDLE_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Watch the taskbar's thumbnails until the app exits.
+pub fn run() {
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+    }
+
+    // Title of the hovered thumbnail, and whether its file is being previewed.
+    let mut hovered: Option<String> = None;
+    let mut previewing = false;
+
+    while RUNNING.load(Ordering::Acquire) {
+        let enabled = CONFIG
+            .lock()
+            .map(|c| c.preview_enabled && c.taskbar_thumbnails)
+            .unwrap_or(false);
+        if !enabled {
+            if previewing {
+                hide_preview();
+                previewing = false;
+            }
+            hovered = None;
+            std::thread::sleep(IDLE_INTERVAL);
+            continue;
+        }
+
+        match hovered_thumbnail() {
+            Some((name, popup)) => {
+                if hovered.as_deref() != Some(name.as_str()) {
+                    let selected = get_selected_media_file_in_window(&name).filter(|path| {
+                        providers::is_type_enabled(path)
+                            && !excluded_paths::is_excluded(path)
+                            && network_drives::is_previewable(path)
+                    });
+                    match selected {
+                        Some(path) => {
+                            show_preview_keyboard(
+                                &path,
+                                popup.left,
+                                popup.top,
+                                popup.right,
+                                popup.bottom,
+                            );
+                            previewing = true;
+                        }
+                        None if previewing => {
+                            hide_preview();
+                            previewing = false;
+                        }
+                        None => {}
+                    }
+                    hovered = Some(name);
+                }
+            }
+            None => {
+                if previewing {
+                    hide_preview();
+                    previewing = false;
+                }
+                hovered = None;
+            }
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+
+    unsafe {
+        CoUninitialize();
+    }
+}
+
+/// The title of the thumbnail under the cursor and the bounds of the popup
+/// holding it, when the cursor is over a taskbar thumbnail.
+fn hovered_thumbnail() -> Option<(String, RECT)> {
+    unsafe {
+        let mut cursor = POINT::default();
+        GetCursorPos(&mut cursor).ok()?;
+        let popup = GetAncestor(WindowFromPoint(cursor), GA_ROOT);
+        if popup.is_invalid() {
+            return None;
+        }
+        let mut class = [0u16; 64];
+        let len = GetClassNameW(popup, &mut class).max(0) as usize;
+        if String::from_utf16_lossy(&class[..len]) != THUMBNAIL_CLASS {
+            return None;
+        }
+
+        let mut accessible: Option<IAccessible> = None;
+        let mut child = VARIANT::default();
+        AccessibleObjectFromPoint(cursor, &mut accessible, &mut child).ok()?;
+        let name = accessible?.get_accName(&child).ok()?.to_string();
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        Some((name.to_string(), popup_bounds(popup)?))
+    }
+}
+
+/// The popup's visible bounds, without the invisible resize borders DWM
+/// adds around windows.
+fn popup_bounds(popup: HWND) -> Option<RECT> {
+    unsafe {
+        let mut rect = RECT::default();
+        let extended = DwmGetWindowAttribute(
+            popup,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut rect as *mut RECT as *mut _,
+            std::mem::size_of::<RECT>() as u32,
+        );
+        if extended.is_ok() {
+            return Some(rect);
+        }
+        GetWindowRect(popup, &mut rect).ok()?;
+        Some(rect)
+    }
+}
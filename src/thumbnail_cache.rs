@@ -0,0 +1,268 @@
+//! Persistent cache of decoded still previews.
+//!
+//! Stills that were slow to decode (large photos, RAW files, project files)
+//! are stored scaled, as zstd-compressed BGRA, under
+//! `%LOCALAPPDATA%\rust-hover-preview\thumbnails`. Entries are keyed by path,
+//! modification time, file size and the preview size, so an edited file
+//! misses and its old entry ages out. The folder is kept under
+//! `thumbnail_cache_mb` by evicting the least recently used entries; a hit
+//! refreshes its entry's modification time to mark it as used.
+
+use crate::preview_window::ImageFrame;
+use crate::CONFIG;
+use directories::BaseDirs;
+use std::ffi::OsStr;
+use std::fs::{self, OpenOptions};
+use std::os::windows::io::AsRawHandle;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use windows::Win32::Foundation::{FILETIME, HANDLE};
+use windows::Win32::Storage::FileSystem::SetFileTime;
+
+const CACHE_DIR_NAME: &str = "thumbnails";
+const ENTRY_EXTENSION: &str = "thumb";
+const MAGIC: &[u8; 4] = b"RHPT";
+const FORMAT_VERSION: u32 = 1;
+/// Magic, version, width, height and key length.
+const HEADER_LEN: usize = 20;
+const COMPRESSION_LEVEL: i32 = 1;
+/// Decodes faster than this are not worth a disk round trip.
+pub const MIN_DECODE_MS: u128 = 40;
+/// Seconds between the `FILETIME` epoch (1601) and the Unix epoch.
+const FILETIME_UNIX_OFFSET_SECS: u64 = 11_644_473_600;
+
+/// The cached `width`x`height` preview of `path`, if there is a current one.
+pub fn lookup(path: &Path, width: u32, height: u32) -> Option<ImageFrame> {
+    if cap_bytes() == 0 {
+        return None;
+    }
+    let key = source_key(path, width, height)?;
+    let entry = cache_dir()?.join(entry_name(&key));
+    let frame = decode_entry(&fs::read(&entry).ok()?, &key)?;
+    touch(&entry);
+    Some(frame)
+}
+
+/// Cache `frame` as the `width`x`height` preview of `path`, then evict the
+/// least recently used entries over the size cap.
+pub fn store(path: &Path, width: u32, height: u32, frame: &ImageFrame) {
+    let cap = cap_bytes();
+    if cap == 0 {
+        return;
+    }
+    let (Some(key), Some(dir)) = (source_key(path, width, height), cache_dir()) else {
+        return;
+    };
+    let Some(data) = encode_entry(&key, frame) else {
+        return;
+    };
+    if data.len() as u64 > cap || fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+
+    // Write then rename, so a concurrent lookup never reads a partial entry.
+    let entry = dir.join(entry_name(&key));
+    let partial = entry.with_extension("partial");
+    if fs::write(&partial, &data).is_err() || fs::rename(&partial, &entry).is_err() {
+        let _ = fs::remove_file(&partial);
+        return;
+    }
+    evict(&dir, cap);
+}
+
+fn cap_bytes() -> u64 {
+    CONFIG
+        .lock()
+        .map(|config| config.thumbnail_cache_mb as u64 * 1024 * 1024)
+        .unwrap_or(0)
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| {
+        dirs.data_local_dir()
+            .join("rust-hover-preview")
+            .join(CACHE_DIR_NAME)
+    })
+}
+
+fn source_key(path: &Path, width: u32, height: u32) -> Option<String> {
+    let meta = fs::metadata(path).ok()?;
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(entry_key(
+        &path.to_string_lossy(),
+        modified.as_nanos(),
+        meta.len(),
+        width,
+        height,
+    ))
+}
+
+/// Paths are compared case-insensitively, like the file system does.
+fn entry_key(path: &str, modified_nanos: u128, size: u64, width: u32, height: u32) -> String {
+    format!(
+        "{}|{}|{}|{}x{}",
+        path.to_lowercase(),
+        modified_nanos,
+        size,
+        width,
+        height
+    )
+}
+
+/// 64-bit FNV-1a of the key; the full key is stored in the entry to catch
+/// collisions.
+fn entry_name(key: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}.{}", hash, ENTRY_EXTENSION)
+}
+
+fn encode_entry(key: &str, frame: &ImageFrame) -> Option<Vec<u8>> {
+    let payload = zstd::encode_all(frame.pixels.as_slice(), COMPRESSION_LEVEL).ok()?;
+    let mut data = Vec::with_capacity(HEADER_LEN + key.len() + payload.len());
+    data.extend_from_slice(MAGIC);
+    for value in [FORMAT_VERSION, frame.width, frame.height, key.len() as u32] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(key.as_bytes());
+    data.extend_from_slice(&payload);
+    Some(data)
+}
+
+fn decode_entry(data: &[u8], key: &str) -> Option<ImageFrame> {
+    let read_u32 = |offset: usize| {
+        let bytes = data.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if data.get(..4)? != MAGIC || read_u32(4)? != FORMAT_VERSION {
+        return None;
+    }
+    let (width, height) = (read_u32(8)?, read_u32(12)?);
+    let key_end = HEADER_LEN + read_u32(16)? as usize;
+    if data.get(HEADER_LEN..key_end)? != key.as_bytes() {
+        return None;
+    }
+    let pixels = zstd::decode_all(&data[key_end..]).ok()?;
+    if pixels.len() != width as usize * height as usize * 4 {
+        return None;
+    }
+    Some(ImageFrame {
+        pixels,
+        width,
+        height,
+        delay_ms: 0,
+    })
+}
+
+fn evict(dir: &Path, cap: u64) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    let entries = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new(ENTRY_EXTENSION)) {
+                return None;
+            }
+            let meta = entry.metadata().ok()?;
+            Some((path, meta.len(), meta.modified().ok()?))
+        })
+        .collect();
+    for path in least_recently_used(entries, cap) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Oldest entries to remove so the rest fit in `cap` bytes.
+fn least_recently_used(mut entries: Vec<(PathBuf, u64, SystemTime)>, cap: u64) -> Vec<PathBuf> {
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort_by_key(|(_, _, used)| *used);
+    let mut evicted = Vec::new();
+    for (path, size, _) in entries {
+        if total <= cap {
+            break;
+        }
+        total -= size;
+        evicted.push(path);
+    }
+    evicted
+}
+
+/// Mark an entry as just used by bumping its modification time.
+fn touch(entry: &Path) {
+    let Ok(file) = OpenOptions::new().write(true).open(entry) else {
+        return;
+    };
+    let now = to_filetime(SystemTime::now());
+    unsafe {
+        let _ = SetFileTime(HANDLE(file.as_raw_handle()), None, None, Some(&now));
+    }
+}
+
+fn to_filetime(time: SystemTime) -> FILETIME {
+    let since_unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let intervals = (since_unix.as_secs() + FILETIME_UNIX_OFFSET_SECS) * 10_000_000
+        + since_unix.subsec_nanos() as u64 / 100;
+    FILETIME {
+        dwLowDateTime: intervals as u32,
+        dwHighDateTime: (intervals >> 32) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn entries_round_trip_and_check_their_key() {
+        let frame = ImageFrame {
+            pixels: (0..2 * 3 * 4).map(|value| value as u8).collect(),
+            width: 2,
+            height: 3,
+            delay_ms: 0,
+        };
+        let key = entry_key("C:\\Photos\\A.CR2", 1, 2, 800, 600);
+        assert_eq!(key, "c:\\photos\\a.cr2|1|2|800x600");
+
+        let data = encode_entry(&key, &frame).unwrap();
+        let decoded = decode_entry(&data, &key).unwrap();
+        assert_eq!((decoded.width, decoded.height), (2, 3));
+        assert_eq!(decoded.pixels, frame.pixels);
+
+        let other = entry_key("C:\\Photos\\A.CR2", 1, 2, 400, 300);
+        assert!(decode_entry(&data, &other).is_none());
+        assert!(decode_entry(&data[..10], &key).is_none());
+        assert_ne!(entry_name(&key), entry_name(&other));
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_first() {
+        let at = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        let entries = vec![
+            (PathBuf::from("new"), 40, at(300)),
+            (PathBuf::from("old"), 40, at(100)),
+            (PathBuf::from("mid"), 40, at(200)),
+        ];
+        assert_eq!(
+            least_recently_used(entries.clone(), 80),
+            vec![PathBuf::from("old")]
+        );
+        assert_eq!(
+            least_recently_used(entries.clone(), 40),
+            vec![PathBuf::from("old"), PathBuf::from("mid")]
+        );
+        assert!(least_recently_used(entries, 120).is_empty());
+    }
+
+    #[test]
+    fn filetimes_count_from_1601() {
+        let epoch = to_filetime(UNIX_EPOCH);
+        let intervals = (epoch.dwHighDateTime as u64) << 32 | epoch.dwLowDateTime as u64;
+        assert_eq!(intervals, 116_444_736_000_000_000);
+    }
+}
@@ -0,0 +1,128 @@
+//! Per-stage timings of each preview, for attributing latency to a stage.
+//!
+//! With `trace_previews` on, every image or animation preview appends one line
+//! to `rust-hover-preview-trace.log` in the temp folder once its first frame is
+//! on screen:
+//!
+//! ```text
+//! path="C:\a.jpg" resolve=14.2 probe=0.4 queue=0.1 decode=48.0 scale=6.5 paint=2.1 total=71.3
+//! ```
+//!
+//! Times are in milliseconds:
+//!
+//! - `resolve`: finding the file under the cursor through UI Automation (mouse
+//!   hovers only)
+//! - `probe`: reading the file's dimensions and laying out the preview
+//! - `queue`: waiting for a loader worker
+//! - `decode`: the provider's load, without the time spent in `scale`
+//! - `scale`: resizing still images to the preview size (animation frames are
+//!   scaled as they decode and count as `decode`)
+//! - `paint`: composing and presenting the first frame
+//!
+//! `total` is the sum of the stages; the hover delay is not included.
+
+use crate::CONFIG;
+use std::cell::RefCell;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub type Stages = Vec<(&'static str, Duration)>;
+
+/// The last path the Explorer hook resolved and how long that took.
+static RESOLVED: Mutex<Option<(PathBuf, Duration)>> = Mutex::new(None);
+
+thread_local! {
+    /// Stages timed inside a load on this thread, such as `scale`.
+    static RECORDED: RefCell<Stages> = RefCell::new(Vec::new());
+}
+
+pub fn enabled() -> bool {
+    CONFIG.lock().map(|c| c.trace_previews).unwrap_or(false)
+}
+
+/// Note how long resolving `path` under the cursor took.
+pub fn resolved(path: &Path, took: Duration) {
+    if let Ok(mut resolved) = RESOLVED.lock() {
+        *resolved = Some((path.to_path_buf(), took));
+    }
+}
+
+/// The resolve time noted for `path`, if it was the last one resolved.
+pub fn take_resolved(path: &Path) -> Option<Duration> {
+    let mut resolved = RESOLVED.lock().ok()?;
+    match resolved.take() {
+        Some((resolved_path, took)) if resolved_path == path => Some(took),
+        _ => None,
+    }
+}
+
+/// Add `took` to `stage` for the load running on this thread.
+pub fn record(stage: &'static str, took: Duration) {
+    RECORDED.with(|recorded| add(&mut recorded.borrow_mut(), stage, took));
+}
+
+/// The stages recorded on this thread since the last call.
+pub fn take_recorded() -> Stages {
+    RECORDED.with(|recorded| std::mem::take(&mut *recorded.borrow_mut()))
+}
+
+/// Time spent in `stage`, if any was recorded.
+pub fn stage(stages: &[(&'static str, Duration)], stage: &str) -> Duration {
+    stages
+        .iter()
+        .filter(|(name, _)| *name == stage)
+        .map(|(_, took)| *took)
+        .sum()
+}
+
+fn add(stages: &mut Stages, stage: &'static str, took: Duration) {
+    match stages.iter_mut().find(|(name, _)| *name == stage) {
+        Some((_, total)) => *total += took,
+        None => stages.push((stage, took)),
+    }
+}
+
+/// Append the trace line for a preview of `path`.
+pub fn write(path: &Path, stages: &[(&'static str, Duration)]) {
+    let log_path = env::temp_dir().join("rust-hover-preview-trace.log");
+    let Ok(mut file) = OpenOptions::new().create(true).append(true).open(log_path) else {
+        return;
+    };
+    let _ = writeln!(file, "{}", trace_line(path, stages));
+}
+
+fn trace_line(path: &Path, stages: &[(&'static str, Duration)]) -> String {
+    let mut line = format!("path=\"{}\"", path.display());
+    for (name, took) in stages {
+        line.push_str(&format!(" {}={}", name, milliseconds(*took)));
+    }
+    let total: Duration = stages.iter().map(|(_, took)| *took).sum();
+    line.push_str(&format!(" total={}", milliseconds(total)));
+    line
+}
+
+fn milliseconds(took: Duration) -> String {
+    format!("{:.1}", took.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_line_lists_stages_and_their_sum() {
+        let mut stages = vec![("probe", Duration::from_micros(400))];
+        add(&mut stages, "scale", Duration::from_millis(4));
+        add(&mut stages, "scale", Duration::from_micros(2_500));
+        assert_eq!(stage(&stages, "scale"), Duration::from_micros(6_500));
+        assert_eq!(stage(&stages, "paint"), Duration::ZERO);
+        assert_eq!(
+            trace_line(Path::new("C:\\a.jpg"), &stages),
+            "path=\"C:\\a.jpg\" probe=0.4 scale=6.5 total=6.9"
+        );
+    }
+}
@@ -1,43 +1,81 @@
 use crate::config::TransparentBackground;
-use crate::preview_window::refresh_preview;
+use crate::explorer_hook;
+use crate::favorites;
+use crate::ffmpeg_setup;
+use crate::gallery::{self, Gallery};
+use crate::preview_handler;
+use crate::preview_window::{memory_usage_bytes, refresh_preview};
+use crate::shell_access;
 use crate::{startup, CONFIG, RUNNING};
 use std::os::windows::ffi::OsStrExt;
-use std::sync::atomic::Ordering;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 use windows::core::{w, PCWSTR};
 use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Shell::{
-    ShellExecuteW, Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE,
-    NOTIFYICONDATAW,
+    ShellExecuteW, Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO,
+    NIIF_WARNING, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIN_BALLOONUSERCLICK, NOTIFYICONDATAW,
+    NOTIFY_ICON_INFOTIP_FLAGS,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
     AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DispatchMessageW,
-    GetCursorPos, LoadImageW, PeekMessageW, PostQuitMessage, RegisterClassExW,
+    GetCursorPos, LoadImageW, PeekMessageW, PostMessageW, PostQuitMessage, RegisterClassExW,
     RegisterWindowMessageW, SetForegroundWindow, TrackPopupMenu, TranslateMessage, CS_HREDRAW,
-    CS_VREDRAW, HICON, IMAGE_ICON, LR_DEFAULTSIZE, LR_SHARED, MF_CHECKED, MF_POPUP, MF_STRING,
-    MF_UNCHECKED, MSG, PM_REMOVE, SW_SHOWNORMAL, TPM_BOTTOMALIGN, TPM_LEFTALIGN, WM_COMMAND,
-    WM_DESTROY, WM_LBUTTONUP, WM_POWERBROADCAST, WM_RBUTTONUP, WM_USER, WNDCLASSEXW,
-    WS_EX_TOOLWINDOW, WS_POPUP, PBT_APMRESUMEAUTOMATIC, PBT_APMRESUMESUSPEND,
+    CS_VREDRAW, HICON, IMAGE_ICON, LR_DEFAULTSIZE, LR_SHARED, MF_CHECKED, MF_GRAYED, MF_POPUP,
+    MF_SEPARATOR, MF_STRING, MF_UNCHECKED, MSG, PBT_APMRESUMEAUTOMATIC, PBT_APMRESUMESUSPEND,
+    PM_REMOVE, SW_SHOWNORMAL, TPM_BOTTOMALIGN, TPM_LEFTALIGN, WM_COMMAND, WM_DESTROY, WM_LBUTTONUP,
+    WM_POWERBROADCAST, WM_RBUTTONUP, WM_USER, WNDCLASSEXW, WS_EX_TOOLWINDOW, WS_POPUP,
 };
 
 const WM_TRAYICON: u32 = WM_USER + 1;
+/// Posted by the FFmpeg check and install threads; `wparam` is a status below.
+const WM_FFMPEG_STATUS: u32 = WM_USER + 2;
+const FFMPEG_STATUS_MISSING: usize = 0;
+const FFMPEG_STATUS_INSTALLED: usize = 1;
+const FFMPEG_STATUS_FAILED: usize = 2;
+/// Posted by the Explorer hook when it finds part of COM missing.
+const WM_SHELL_ACCESS: u32 = WM_USER + 3;
 const ID_TRAY_EXIT: u16 = 1001;
 const ID_TRAY_STARTUP: u16 = 1002;
 const ID_TRAY_ENABLE: u16 = 1003;
 const ID_TRAY_CONFIRM_FILE_TYPE: u16 = 1004;
 const ID_TRAY_ENABLE_OFF_TRIGGER_KEY: u16 = 1005;
+const ID_TRAY_DETECT_CODES: u16 = 1006;
+const ID_TRAY_ZOOM_PAN: u16 = 1050;
+const ID_TRAY_TASKBAR_THUMBNAILS: u16 = 1051;
+const ID_TRAY_PREVIEW_PANE: u16 = 1052;
+const ID_TRAY_METADATA_BAR: u16 = 1053;
+const ID_TRAY_EXPLORER_THUMBNAILS: u16 = 1054;
+const ID_TRAY_SHOW_HISTOGRAM: u16 = 1055;
+const ID_TRAY_ANCHOR_TO_ITEM: u16 = 1056;
+const ID_TRAY_BLUR_EXPLICIT: u16 = 1017;
 const ID_TRAY_BG_TRANSPARENT: u16 = 1007;
 const ID_TRAY_BG_BLACK: u16 = 1008;
 const ID_TRAY_BG_WHITE: u16 = 1009;
 const ID_TRAY_BG_CHECKERBOARD: u16 = 1016;
+const ID_TRAY_BG_MATTE: u16 = 1049;
 const ID_TRAY_VOLUME_MAX: u16 = 1010; // 100%
 const ID_TRAY_VOLUME_HIGH: u16 = 1011; // 80%
 const ID_TRAY_VOLUME_MEDIUM: u16 = 1012; // 50%
 const ID_TRAY_VOLUME_LOW: u16 = 1013; // 25%
 const ID_TRAY_VOLUME_VERY_LOW: u16 = 1014; // 10%
 const ID_TRAY_VOLUME_MUTE: u16 = 1015; // 0%
+const ID_TRAY_SPEED_HALF: u16 = 1022; // 0.5x
+const ID_TRAY_SPEED_NORMAL: u16 = 1023; // 1x
+const ID_TRAY_SPEED_FAST: u16 = 1024; // 1.5x
+const ID_TRAY_SPEED_DOUBLE: u16 = 1025; // 2x
+const ID_TRAY_VIDEO_LOOP: u16 = 1026;
+const ID_TRAY_VIDEO_INFO: u16 = 1042;
+const ID_TRAY_VIDEO_SUBTITLES: u16 = 1043;
+const ID_TRAY_AUDIO_TRACK_DEFAULT: u16 = 1044;
+const ID_TRAY_AUDIO_TRACK_1: u16 = 1045;
+const ID_TRAY_AUDIO_TRACK_2: u16 = 1046;
+const ID_TRAY_AUDIO_TRACK_3: u16 = 1047;
+const ID_TRAY_AUDIO_TRACK_4: u16 = 1048;
 const ID_TRAY_POSITION_FOLLOW: u16 = 1020; // Follow cursor
 const ID_TRAY_POSITION_BEST: u16 = 1021; // Best position
+const ID_TRAY_POSITION_PANEL: u16 = 1057; // Fixed panel
 const ID_TRAY_DELAY_INSTANT: u16 = 1030; // 0ms
 const ID_TRAY_DELAY_VERY_FAST: u16 = 1031; // 200ms
 const ID_TRAY_DELAY_MEDIUM: u16 = 1032; // 500ms
@@ -49,11 +87,21 @@ const ID_TRAY_REHOVER_DELAY_SLOW: u16 = 1037; // 1000ms
 const ID_TRAY_DELAY_FAST_PLUS: u16 = 1038; // 750ms
 const ID_TRAY_REHOVER_DELAY_FAST_PLUS: u16 = 1039; // 750ms
 const ID_TRAY_OPEN_CONFIG: u16 = 1040;
+const ID_TRAY_HISTORY: u16 = 1018;
+const ID_TRAY_FAVORITES: u16 = 1019;
+const ID_TRAY_INSTALL_FFMPEG: u16 = 1041;
+const ID_TRAY_SHELL_ACCESS_LOG: u16 = 1058;
+const ID_TRAY_PREVIEW_VIDEOS: u16 = 1059;
+const ID_TRAY_PREVIEW_ANIMATIONS: u16 = 1060;
+const ID_TRAY_PLAY_VIDEO_AUDIO: u16 = 1061;
+const ID_TRAY_PREVIEW_NETWORK_DRIVES: u16 = 1062;
 
 const TRAY_CLASS: PCWSTR = w!("RustHoverPreviewTrayClass");
 
 static mut TRAY_HWND: HWND = HWND(std::ptr::null_mut());
 static mut TASKBAR_CREATED: u32 = 0;
+static MISSING_FFMPEG_TOOLS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+static FFMPEG_INSTALLING: AtomicBool = AtomicBool::new(false);
 
 unsafe extern "system" fn tray_window_proc(
     hwnd: HWND,
@@ -66,15 +114,63 @@ unsafe extern "system" fn tray_window_proc(
             // Explorer (taskbar) restarted; re-add tray icon
             remove_tray_icon(hwnd);
             let _ = add_tray_icon(hwnd);
+            explorer_hook::shell_restarted();
             LRESULT(0)
         }
         WM_TRAYICON => {
             let event = lparam.0 as u32;
             if event == WM_RBUTTONUP || event == WM_LBUTTONUP {
                 show_context_menu(hwnd);
+            } else if event == NIN_BALLOONUSERCLICK && is_ffmpeg_missing() {
+                start_ffmpeg_install(hwnd);
             }
             LRESULT(0)
         }
+        WM_FFMPEG_STATUS => {
+            match wparam.0 {
+                FFMPEG_STATUS_MISSING => {
+                    let tools = MISSING_FFMPEG_TOOLS
+                        .lock()
+                        .map(|tools| ffmpeg_setup::list_tools(&tools))
+                        .unwrap_or_default();
+                    show_balloon(
+                        hwnd,
+                        "FFmpeg not found",
+                        &format!(
+                            "Some video previews need {}. Click here or use \"Install FFmpeg\" in the tray menu to download it.",
+                            tools
+                        ),
+                        NIIF_WARNING,
+                    );
+                }
+                FFMPEG_STATUS_INSTALLED => {
+                    if let Ok(mut tools) = MISSING_FFMPEG_TOOLS.lock() {
+                        tools.clear();
+                    }
+                    show_balloon(
+                        hwnd,
+                        "FFmpeg installed",
+                        "Video previews are ready to use.",
+                        NIIF_INFO,
+                    );
+                }
+                FFMPEG_STATUS_FAILED => {
+                    show_balloon(
+                        hwnd,
+                        "FFmpeg download failed",
+                        "The FFmpeg download page has been opened instead.",
+                        NIIF_WARNING,
+                    );
+                    open_url(ffmpeg_setup::DOWNLOAD_PAGE_URL);
+                }
+                _ => {}
+            }
+            LRESULT(0)
+        }
+        WM_SHELL_ACCESS => {
+            report_shell_access(hwnd);
+            LRESULT(0)
+        }
         WM_COMMAND => {
             let cmd = (wparam.0 & 0xFFFF) as u16;
             match cmd {
@@ -88,9 +184,21 @@ unsafe extern "system" fn tray_window_proc(
                 ID_TRAY_ENABLE => {
                     toggle_preview_enabled();
                 }
+                ID_TRAY_PREVIEW_VIDEOS => toggle_preview_videos(),
+                ID_TRAY_PREVIEW_ANIMATIONS => toggle_preview_animations(),
+                ID_TRAY_PLAY_VIDEO_AUDIO => toggle_play_video_audio(),
+                ID_TRAY_PREVIEW_NETWORK_DRIVES => toggle_preview_network_drives(),
                 ID_TRAY_CONFIRM_FILE_TYPE => {
                     toggle_confirm_file_type();
                 }
+                ID_TRAY_DETECT_CODES => toggle_detect_codes(),
+                ID_TRAY_ZOOM_PAN => toggle_zoom_pan(),
+                ID_TRAY_TASKBAR_THUMBNAILS => toggle_taskbar_thumbnails(),
+                ID_TRAY_PREVIEW_PANE => toggle_preview_pane(),
+                ID_TRAY_EXPLORER_THUMBNAILS => toggle_explorer_thumbnails(),
+                ID_TRAY_METADATA_BAR => toggle_metadata_bar(),
+                ID_TRAY_SHOW_HISTOGRAM => toggle_show_histogram(),
+                ID_TRAY_BLUR_EXPLICIT => toggle_blur_explicit(),
                 ID_TRAY_ENABLE_OFF_TRIGGER_KEY => {
                     toggle_enable_off_trigger_key();
                 }
@@ -102,14 +210,29 @@ unsafe extern "system" fn tray_window_proc(
                 ID_TRAY_BG_CHECKERBOARD => {
                     set_transparent_background(TransparentBackground::Checkerboard)
                 }
+                ID_TRAY_BG_MATTE => set_transparent_background(TransparentBackground::Matte),
                 ID_TRAY_VOLUME_MAX => set_volume(100),
                 ID_TRAY_VOLUME_HIGH => set_volume(80),
                 ID_TRAY_VOLUME_MEDIUM => set_volume(50),
                 ID_TRAY_VOLUME_LOW => set_volume(25),
                 ID_TRAY_VOLUME_VERY_LOW => set_volume(10),
                 ID_TRAY_VOLUME_MUTE => set_volume(0),
+                ID_TRAY_SPEED_HALF => set_video_speed(0.5),
+                ID_TRAY_SPEED_NORMAL => set_video_speed(1.0),
+                ID_TRAY_SPEED_FAST => set_video_speed(1.5),
+                ID_TRAY_SPEED_DOUBLE => set_video_speed(2.0),
+                ID_TRAY_VIDEO_LOOP => toggle_video_loop(),
+                ID_TRAY_VIDEO_INFO => toggle_video_info_overlay(),
+                ID_TRAY_VIDEO_SUBTITLES => toggle_video_subtitles(),
+                ID_TRAY_AUDIO_TRACK_DEFAULT => set_audio_track(0),
+                ID_TRAY_AUDIO_TRACK_1 => set_audio_track(1),
+                ID_TRAY_AUDIO_TRACK_2 => set_audio_track(2),
+                ID_TRAY_AUDIO_TRACK_3 => set_audio_track(3),
+                ID_TRAY_AUDIO_TRACK_4 => set_audio_track(4),
                 ID_TRAY_POSITION_FOLLOW => set_follow_cursor(true),
                 ID_TRAY_POSITION_BEST => set_follow_cursor(false),
+                ID_TRAY_POSITION_PANEL => set_fixed_panel(),
+                ID_TRAY_ANCHOR_TO_ITEM => toggle_anchor_to_item(),
                 ID_TRAY_DELAY_INSTANT => set_hover_delay(0),
                 ID_TRAY_DELAY_VERY_FAST => set_hover_delay(200),
                 ID_TRAY_DELAY_MEDIUM => set_hover_delay(500),
@@ -121,6 +244,12 @@ unsafe extern "system" fn tray_window_proc(
                 ID_TRAY_REHOVER_DELAY_FAST_PLUS => set_same_file_rehover_delay(750),
                 ID_TRAY_REHOVER_DELAY_SLOW => set_same_file_rehover_delay(1000),
                 ID_TRAY_OPEN_CONFIG => open_config_file(),
+                ID_TRAY_HISTORY => gallery::open(Gallery::History),
+                ID_TRAY_FAVORITES => favorites::open(),
+                ID_TRAY_INSTALL_FFMPEG => start_ffmpeg_install(hwnd),
+                ID_TRAY_SHELL_ACCESS_LOG => {
+                    open_url(&shell_access::log_path().to_string_lossy());
+                }
                 _ => {}
             }
             LRESULT(0)
@@ -167,6 +296,41 @@ unsafe fn show_context_menu(hwnd: HWND) {
         w!("Enable Preview"),
     );
 
+    // Add a checkable item for each media type that can be turned off alone
+    let (preview_videos, preview_animations, play_video_audio) = CONFIG
+        .lock()
+        .map(|c| (c.preview_videos, c.preview_animations, c.play_video_audio))
+        .unwrap_or((true, true, true));
+    let type_flag = |enabled: bool| MF_STRING | if enabled { MF_CHECKED } else { MF_UNCHECKED };
+    let _ = AppendMenuW(
+        menu,
+        type_flag(preview_videos),
+        ID_TRAY_PREVIEW_VIDEOS as usize,
+        w!("Preview Videos"),
+    );
+    let _ = AppendMenuW(
+        menu,
+        type_flag(preview_animations),
+        ID_TRAY_PREVIEW_ANIMATIONS as usize,
+        w!("Play Animations"),
+    );
+    let _ = AppendMenuW(
+        menu,
+        type_flag(play_video_audio),
+        ID_TRAY_PLAY_VIDEO_AUDIO as usize,
+        w!("Play Video Sound"),
+    );
+    let preview_network_drives = CONFIG
+        .lock()
+        .map(|c| c.preview_network_drives)
+        .unwrap_or(true);
+    let _ = AppendMenuW(
+        menu,
+        type_flag(preview_network_drives),
+        ID_TRAY_PREVIEW_NETWORK_DRIVES as usize,
+        w!("Preview Network Drives"),
+    );
+
     // Add "Enable Off Trigger Key" with checkmark
     let (enable_off_trigger_key, off_trigger_key) = CONFIG
         .lock()
@@ -210,6 +374,130 @@ unsafe fn show_context_menu(hwnd: HWND) {
         w!("Confirm File Type"),
     );
 
+    // Add "Detect QR Codes" with checkmark (QR code and barcode bar on images)
+    let detect_codes = CONFIG.lock().map(|c| c.detect_codes).unwrap_or(false);
+    let detect_codes_flags = MF_STRING
+        | if detect_codes {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        menu,
+        detect_codes_flags,
+        ID_TRAY_DETECT_CODES as usize,
+        w!("Detect QR Codes"),
+    );
+
+    // Add "Zoom and Pan Images" with checkmark (wheel zoom, drag pan on stills)
+    let zoom_pan = CONFIG.lock().map(|c| c.zoom_pan).unwrap_or(false);
+    let zoom_pan_flags = MF_STRING | if zoom_pan { MF_CHECKED } else { MF_UNCHECKED };
+    let _ = AppendMenuW(
+        menu,
+        zoom_pan_flags,
+        ID_TRAY_ZOOM_PAN as usize,
+        w!("Zoom and Pan Images"),
+    );
+
+    // Add "Preview Taskbar Thumbnails" with checkmark (Explorer's selected file)
+    let taskbar_thumbnails = CONFIG.lock().map(|c| c.taskbar_thumbnails).unwrap_or(false);
+    let taskbar_flags = MF_STRING
+        | if taskbar_thumbnails {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        menu,
+        taskbar_flags,
+        ID_TRAY_TASKBAR_THUMBNAILS as usize,
+        w!("Preview Taskbar Thumbnails"),
+    );
+
+    // Add "Draw Explorer's Preview Pane" with checkmark (registers the handler)
+    let preview_pane = CONFIG.lock().map(|c| c.preview_pane).unwrap_or(false);
+    let preview_pane_flags = MF_STRING
+        | if preview_pane {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        menu,
+        preview_pane_flags,
+        ID_TRAY_PREVIEW_PANE as usize,
+        w!("Draw Explorer's Preview Pane"),
+    );
+
+    // Add "Draw Explorer's Thumbnails" with checkmark (registers the provider)
+    let explorer_thumbnails = CONFIG
+        .lock()
+        .map(|c| c.explorer_thumbnails)
+        .unwrap_or(false);
+    let explorer_thumbnails_flags = MF_STRING
+        | if explorer_thumbnails {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        menu,
+        explorer_thumbnails_flags,
+        ID_TRAY_EXPLORER_THUMBNAILS as usize,
+        w!("Draw Explorer's Thumbnails"),
+    );
+
+    // Add "Show File Details Bar" with checkmark
+    let metadata_bar = CONFIG.lock().map(|c| c.metadata_bar).unwrap_or(false);
+    let metadata_bar_flags = MF_STRING
+        | if metadata_bar {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        menu,
+        metadata_bar_flags,
+        ID_TRAY_METADATA_BAR as usize,
+        w!("Show File Details Bar"),
+    );
+
+    // Add "Show Histogram" with checkmark
+    let show_histogram = CONFIG.lock().map(|c| c.show_histogram).unwrap_or(false);
+    let show_histogram_flags = MF_STRING
+        | if show_histogram {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        menu,
+        show_histogram_flags,
+        ID_TRAY_SHOW_HISTOGRAM as usize,
+        w!("Show Histogram"),
+    );
+
+    // Add "Blur Explicit Images" with checkmark, grayed out without a model
+    let (blur_explicit, has_nsfw_model) = CONFIG
+        .lock()
+        .map(|c| (c.blur_explicit, !c.nsfw_model.trim().is_empty()))
+        .unwrap_or((false, false));
+    let mut blur_explicit_flags = MF_STRING
+        | if blur_explicit {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    if !has_nsfw_model {
+        blur_explicit_flags |= MF_GRAYED;
+    }
+    let _ = AppendMenuW(
+        menu,
+        blur_explicit_flags,
+        ID_TRAY_BLUR_EXPLICIT as usize,
+        w!("Blur Explicit Images"),
+    );
+
     // Add Transparent Background submenu
     let transparent_background = CONFIG
         .lock()
@@ -249,6 +537,12 @@ unsafe fn show_context_menu(hwnd: HWND) {
         ID_TRAY_BG_CHECKERBOARD as usize,
         w!("Checkerboard"),
     );
+    let _ = AppendMenuW(
+        background_menu,
+        bg_flag(TransparentBackground::Matte),
+        ID_TRAY_BG_MATTE as usize,
+        w!("Matte Color"),
+    );
     let _ = AppendMenuW(
         menu,
         MF_STRING | MF_POPUP,
@@ -413,13 +707,144 @@ unsafe fn show_context_menu(hwnd: HWND) {
         w!("Video Volume"),
     );
 
+    // Add Video submenu (playback speed, looping, the info bar and tracks)
+    let (current_speed, video_loop, video_info_overlay, audio_track, subtitles) = CONFIG
+        .lock()
+        .map(|c| {
+            (
+                c.video_speed,
+                c.video_loop,
+                c.video_info_overlay,
+                c.video_audio_track,
+                c.video_subtitles,
+            )
+        })
+        .unwrap_or((1.0, true, true, 0, false));
+    let video_menu = CreatePopupMenu().unwrap();
+
+    let speed_flag = |speed: f32| {
+        MF_STRING
+            | if (current_speed - speed).abs() < 0.01 {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            }
+    };
+    let _ = AppendMenuW(
+        video_menu,
+        speed_flag(0.5),
+        ID_TRAY_SPEED_HALF as usize,
+        w!("Speed 0.5x"),
+    );
+    let _ = AppendMenuW(
+        video_menu,
+        speed_flag(1.0),
+        ID_TRAY_SPEED_NORMAL as usize,
+        w!("Speed 1x"),
+    );
+    let _ = AppendMenuW(
+        video_menu,
+        speed_flag(1.5),
+        ID_TRAY_SPEED_FAST as usize,
+        w!("Speed 1.5x"),
+    );
+    let _ = AppendMenuW(
+        video_menu,
+        speed_flag(2.0),
+        ID_TRAY_SPEED_DOUBLE as usize,
+        w!("Speed 2x"),
+    );
+    let _ = AppendMenuW(video_menu, MF_SEPARATOR, 0, PCWSTR::null());
+    let loop_flags = MF_STRING | if video_loop { MF_CHECKED } else { MF_UNCHECKED };
+    let _ = AppendMenuW(
+        video_menu,
+        loop_flags,
+        ID_TRAY_VIDEO_LOOP as usize,
+        w!("Loop Videos"),
+    );
+    let info_flags = MF_STRING
+        | if video_info_overlay {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        video_menu,
+        info_flags,
+        ID_TRAY_VIDEO_INFO as usize,
+        w!("Show Video Info"),
+    );
+    let subtitle_flags = MF_STRING | if subtitles { MF_CHECKED } else { MF_UNCHECKED };
+    let _ = AppendMenuW(
+        video_menu,
+        subtitle_flags,
+        ID_TRAY_VIDEO_SUBTITLES as usize,
+        w!("Show Subtitles"),
+    );
+
+    let audio_track_menu = CreatePopupMenu().unwrap();
+    let track_flag = |track: u32| {
+        MF_STRING
+            | if audio_track == track {
+                MF_CHECKED
+            } else {
+                MF_UNCHECKED
+            }
+    };
+    let _ = AppendMenuW(
+        audio_track_menu,
+        track_flag(0),
+        ID_TRAY_AUDIO_TRACK_DEFAULT as usize,
+        w!("Default"),
+    );
+    let _ = AppendMenuW(
+        audio_track_menu,
+        track_flag(1),
+        ID_TRAY_AUDIO_TRACK_1 as usize,
+        w!("Track 1"),
+    );
+    let _ = AppendMenuW(
+        audio_track_menu,
+        track_flag(2),
+        ID_TRAY_AUDIO_TRACK_2 as usize,
+        w!("Track 2"),
+    );
+    let _ = AppendMenuW(
+        audio_track_menu,
+        track_flag(3),
+        ID_TRAY_AUDIO_TRACK_3 as usize,
+        w!("Track 3"),
+    );
+    let _ = AppendMenuW(
+        audio_track_menu,
+        track_flag(4),
+        ID_TRAY_AUDIO_TRACK_4 as usize,
+        w!("Track 4"),
+    );
+    let _ = AppendMenuW(
+        video_menu,
+        MF_STRING | MF_POPUP,
+        audio_track_menu.0 as usize,
+        w!("Audio Track"),
+    );
+
+    let _ = AppendMenuW(
+        menu,
+        MF_STRING | MF_POPUP,
+        video_menu.0 as usize,
+        w!("Video"),
+    );
+
     // Add Cursor Position submenu
-    let follow_cursor = CONFIG.lock().map(|c| c.follow_cursor).unwrap_or(false);
+    let (follow_cursor, fixed_panel) = CONFIG
+        .lock()
+        .map(|c| (c.follow_cursor, c.fixed_panel))
+        .unwrap_or((false, false));
     let position_menu = CreatePopupMenu().unwrap();
 
     let pos_flag = |follow: bool| {
         MF_STRING
-            | if follow_cursor == follow {
+            | if !fixed_panel && follow_cursor == follow {
                 MF_CHECKED
             } else {
                 MF_UNCHECKED
@@ -437,6 +862,32 @@ unsafe fn show_context_menu(hwnd: HWND) {
         ID_TRAY_POSITION_BEST as usize,
         w!("Best Position"),
     );
+    let panel_flags = MF_STRING
+        | if fixed_panel {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        position_menu,
+        panel_flags,
+        ID_TRAY_POSITION_PANEL as usize,
+        w!("Fixed Panel"),
+    );
+    let _ = AppendMenuW(position_menu, MF_SEPARATOR, 0, PCWSTR::null());
+    let anchor_to_item = CONFIG.lock().map(|c| c.anchor_to_item).unwrap_or(false);
+    let anchor_to_item_flags = MF_STRING
+        | if anchor_to_item {
+            MF_CHECKED
+        } else {
+            MF_UNCHECKED
+        };
+    let _ = AppendMenuW(
+        position_menu,
+        anchor_to_item_flags,
+        ID_TRAY_ANCHOR_TO_ITEM as usize,
+        w!("Beside Hovered Item"),
+    );
 
     let _ = AppendMenuW(
         menu,
@@ -445,6 +896,35 @@ unsafe fn show_context_menu(hwnd: HWND) {
         w!("Preview Position"),
     );
 
+    // Point at the log while the hook cannot reach Explorer through COM
+    if shell_access::problem().is_some() {
+        let _ = AppendMenuW(
+            menu,
+            MF_STRING,
+            ID_TRAY_SHELL_ACCESS_LOG as usize,
+            w!("Explorer Access Limited (Open Log)..."),
+        );
+    }
+
+    // Offer FFmpeg while it is missing
+    if is_ffmpeg_missing() {
+        if FFMPEG_INSTALLING.load(Ordering::Acquire) {
+            let _ = AppendMenuW(
+                menu,
+                MF_STRING | MF_GRAYED,
+                ID_TRAY_INSTALL_FFMPEG as usize,
+                w!("Installing FFmpeg..."),
+            );
+        } else {
+            let _ = AppendMenuW(
+                menu,
+                MF_STRING,
+                ID_TRAY_INSTALL_FFMPEG as usize,
+                w!("Install FFmpeg..."),
+            );
+        }
+    }
+
     // Add "Run at Startup" with checkmark
     let startup_enabled = CONFIG.lock().map(|c| c.run_at_startup).unwrap_or(false);
     let flags = MF_STRING
@@ -455,6 +935,36 @@ unsafe fn show_context_menu(hwnd: HWND) {
         };
     let _ = AppendMenuW(menu, flags, ID_TRAY_STARTUP as usize, w!("Run at Startup"));
 
+    // Add "Preview History..."
+    let _ = AppendMenuW(
+        menu,
+        MF_STRING,
+        ID_TRAY_HISTORY as usize,
+        w!("Preview History..."),
+    );
+
+    // Add "Favorites..."
+    let _ = AppendMenuW(
+        menu,
+        MF_STRING,
+        ID_TRAY_FAVORITES as usize,
+        w!("Favorites..."),
+    );
+
+    // Show the memory the previews hold, against `memory_cap_mb`
+    let memory_cap_mb = CONFIG.lock().map(|c| c.memory_cap_mb).unwrap_or(0);
+    let memory_label = memory_label(memory_usage_bytes(), memory_cap_mb);
+    let memory_label_wide: Vec<u16> = memory_label
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let _ = AppendMenuW(
+        menu,
+        MF_STRING | MF_GRAYED,
+        0,
+        PCWSTR(memory_label_wide.as_ptr()),
+    );
+
     // Add "Edit Config.ini"
     let _ = AppendMenuW(
         menu,
@@ -484,6 +994,16 @@ unsafe fn show_context_menu(hwnd: HWND) {
     let _ = DestroyMenu(menu);
 }
 
+/// `Preview Memory: 312 MB of 1024 MB`, or without the cap when there is none.
+fn memory_label(in_use_bytes: usize, cap_mb: u32) -> String {
+    let in_use_mb = in_use_bytes / (1024 * 1024);
+    if cap_mb == 0 {
+        format!("Preview Memory: {} MB", in_use_mb)
+    } else {
+        format!("Preview Memory: {} MB of {} MB", in_use_mb, cap_mb)
+    }
+}
+
 fn toggle_startup() {
     if let Ok(mut config) = CONFIG.lock() {
         config.run_at_startup = !config.run_at_startup;
@@ -504,6 +1024,34 @@ fn toggle_preview_enabled() {
     }
 }
 
+fn toggle_preview_videos() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.preview_videos = !config.preview_videos;
+        config.save();
+    }
+}
+
+fn toggle_preview_animations() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.preview_animations = !config.preview_animations;
+        config.save();
+    }
+}
+
+fn toggle_play_video_audio() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.play_video_audio = !config.play_video_audio;
+        config.save();
+    }
+}
+
+fn toggle_preview_network_drives() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.preview_network_drives = !config.preview_network_drives;
+        config.save();
+    }
+}
+
 fn toggle_enable_off_trigger_key() {
     if let Ok(mut config) = CONFIG.lock() {
         config.enable_off_trigger_key = !config.enable_off_trigger_key;
@@ -518,6 +1066,74 @@ fn toggle_confirm_file_type() {
     }
 }
 
+fn toggle_detect_codes() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.detect_codes = !config.detect_codes;
+        config.save();
+    }
+}
+
+fn toggle_zoom_pan() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.zoom_pan = !config.zoom_pan;
+        config.save();
+    }
+}
+
+fn toggle_taskbar_thumbnails() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.taskbar_thumbnails = !config.taskbar_thumbnails;
+        config.save();
+    }
+}
+
+fn toggle_preview_pane() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.preview_pane = !config.preview_pane;
+        config.save();
+
+        if config.preview_pane {
+            preview_handler::register();
+        } else {
+            preview_handler::unregister();
+        }
+    }
+}
+
+fn toggle_explorer_thumbnails() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.explorer_thumbnails = !config.explorer_thumbnails;
+        config.save();
+
+        if config.explorer_thumbnails {
+            preview_handler::register_thumbnails();
+        } else {
+            preview_handler::unregister_thumbnails();
+        }
+    }
+}
+
+fn toggle_metadata_bar() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.metadata_bar = !config.metadata_bar;
+        config.save();
+    }
+}
+
+fn toggle_show_histogram() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.show_histogram = !config.show_histogram;
+        config.save();
+    }
+}
+
+fn toggle_blur_explicit() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.blur_explicit = !config.blur_explicit;
+        config.save();
+    }
+}
+
 fn set_transparent_background(background: TransparentBackground) {
     if let Ok(mut config) = CONFIG.lock() {
         config.transparent_background = background;
@@ -533,9 +1149,59 @@ fn set_volume(volume: u32) {
     }
 }
 
+fn set_video_speed(speed: f32) {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.video_speed = speed;
+        config.save();
+    }
+}
+
+fn toggle_video_loop() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.video_loop = !config.video_loop;
+        config.save();
+    }
+}
+
+fn toggle_video_info_overlay() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.video_info_overlay = !config.video_info_overlay;
+        config.save();
+    }
+}
+
+fn toggle_video_subtitles() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.video_subtitles = !config.video_subtitles;
+        config.save();
+    }
+}
+
+fn set_audio_track(track: u32) {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.video_audio_track = track;
+        config.save();
+    }
+}
+
 fn set_follow_cursor(follow: bool) {
     if let Ok(mut config) = CONFIG.lock() {
         config.follow_cursor = follow;
+        config.fixed_panel = false;
+        config.save();
+    }
+}
+
+fn set_fixed_panel() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.fixed_panel = true;
+        config.save();
+    }
+}
+
+fn toggle_anchor_to_item() {
+    if let Ok(mut config) = CONFIG.lock() {
+        config.anchor_to_item = !config.anchor_to_item;
         config.save();
     }
 }
@@ -554,6 +1220,72 @@ fn set_same_file_rehover_delay(delay_ms: u64) {
     }
 }
 
+/// Look for the FFmpeg tools off the UI thread and report back if any are missing.
+fn check_ffmpeg_tools(hwnd: HWND) {
+    let hwnd_value = hwnd.0 as isize;
+    std::thread::spawn(move || {
+        let missing = ffmpeg_setup::missing_tools();
+        if missing.is_empty() {
+            return;
+        }
+        if let Ok(mut tools) = MISSING_FFMPEG_TOOLS.lock() {
+            *tools = missing;
+        }
+        unsafe {
+            let _ = PostMessageW(
+                HWND(hwnd_value as *mut _),
+                WM_FFMPEG_STATUS,
+                WPARAM(FFMPEG_STATUS_MISSING),
+                LPARAM(0),
+            );
+        }
+    });
+}
+
+fn is_ffmpeg_missing() -> bool {
+    MISSING_FFMPEG_TOOLS
+        .lock()
+        .map(|tools| !tools.is_empty())
+        .unwrap_or(false)
+}
+
+fn start_ffmpeg_install(hwnd: HWND) {
+    if FFMPEG_INSTALLING.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let hwnd_value = hwnd.0 as isize;
+    std::thread::spawn(move || {
+        let status = if ffmpeg_setup::install().is_some() {
+            FFMPEG_STATUS_INSTALLED
+        } else {
+            FFMPEG_STATUS_FAILED
+        };
+        FFMPEG_INSTALLING.store(false, Ordering::Release);
+        unsafe {
+            let _ = PostMessageW(
+                HWND(hwnd_value as *mut _),
+                WM_FFMPEG_STATUS,
+                WPARAM(status),
+                LPARAM(0),
+            );
+        }
+    });
+}
+
+pub fn open_url(url: &str) {
+    let wide_url: Vec<u16> = url.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let _ = ShellExecuteW(
+            HWND(std::ptr::null_mut()),
+            w!("open"),
+            PCWSTR(wide_url.as_ptr()),
+            PCWSTR(std::ptr::null()),
+            PCWSTR(std::ptr::null()),
+            SW_SHOWNORMAL,
+        );
+    }
+}
+
 fn open_config_file() {
     if let Ok(config) = CONFIG.lock() {
         config.save();
@@ -625,15 +1357,76 @@ unsafe fn add_tray_icon(hwnd: HWND) -> bool {
         ..Default::default()
     };
 
-    // Set tooltip
-    let tip = "Rust Hover Preview";
-    let tip_wide: Vec<u16> = tip.encode_utf16().chain(std::iter::once(0)).collect();
-    let len = tip_wide.len().min(nid.szTip.len());
-    nid.szTip[..len].copy_from_slice(&tip_wide[..len]);
+    copy_wide(&mut nid.szTip, &tray_tip());
 
     Shell_NotifyIconW(NIM_ADD, &nid).as_bool()
 }
 
+/// The tooltip, which notes when Explorer access is limited.
+fn tray_tip() -> String {
+    match shell_access::problem() {
+        Some(_) => "Rust Hover Preview\nExplorer access limited".to_string(),
+        None => "Rust Hover Preview".to_string(),
+    }
+}
+
+/// Let the Explorer hook's COM check reach the tray, once it is up.
+pub fn shell_access_checked() {
+    unsafe {
+        let hwnd = TRAY_HWND;
+        if !hwnd.0.is_null() {
+            let _ = PostMessageW(hwnd, WM_SHELL_ACCESS, WPARAM(0), LPARAM(0));
+        }
+    }
+}
+
+/// Show what the COM check found in the tooltip, and a problem not yet
+/// reported in a balloon.
+unsafe fn report_shell_access(hwnd: HWND) {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: 1,
+        uFlags: NIF_TIP,
+        ..Default::default()
+    };
+    copy_wide(&mut nid.szTip, &tray_tip());
+    let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+    let Some(problem) = shell_access::take_unreported() else {
+        return;
+    };
+    show_balloon(
+        hwnd,
+        "Explorer access limited",
+        &format!(
+            "{}. Previews fall back to accessibility and may miss files in libraries and search results.",
+            problem
+        ),
+        NIIF_WARNING,
+    );
+}
+
+/// Copy `text` into a fixed, NUL-terminated UTF-16 field, truncating if needed.
+fn copy_wide(field: &mut [u16], text: &str) {
+    let wide: Vec<u16> = text.encode_utf16().take(field.len() - 1).collect();
+    field[..wide.len()].copy_from_slice(&wide);
+    field[wide.len()] = 0;
+}
+
+unsafe fn show_balloon(hwnd: HWND, title: &str, text: &str, icon: NOTIFY_ICON_INFOTIP_FLAGS) {
+    let mut nid = NOTIFYICONDATAW {
+        cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: 1,
+        uFlags: NIF_INFO,
+        dwInfoFlags: icon,
+        ..Default::default()
+    };
+    copy_wide(&mut nid.szInfoTitle, title);
+    copy_wide(&mut nid.szInfo, text);
+    let _ = Shell_NotifyIconW(NIM_MODIFY, &nid);
+}
+
 unsafe fn remove_tray_icon(hwnd: HWND) {
     let nid = NOTIFYICONDATAW {
         cbSize: std::mem::size_of::<NOTIFYICONDATAW>() as u32,
@@ -707,6 +1500,16 @@ pub fn run_tray() {
             return;
         }
 
+        if CONFIG
+            .lock()
+            .map(|c| c.ffmpeg_startup_check)
+            .unwrap_or(true)
+        {
+            check_ffmpeg_tools(hwnd);
+        }
+        // The hook may have checked COM before the tray was up.
+        report_shell_access(hwnd);
+
         // Message loop
         let mut msg = MSG::default();
         while RUNNING.load(Ordering::SeqCst) {
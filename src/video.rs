@@ -0,0 +1,825 @@
+//! In-process video decoding.
+//!
+//! Frames are piped from `ffmpeg -f rawvideo` as BGRA into a small bounded
+//! queue that the preview window drains like a streamed animation, so videos
+//! share the preview HWND, its topmost handling and its no-activate style.
+
+use crate::preview_window::ImageFrame;
+use crate::CONFIG;
+use std::collections::VecDeque;
+use std::io::Read;
+use std::os::windows::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+};
+use windows::Win32::System::Threading::{
+    OpenThread, ResumeThread, SuspendThread, THREAD_SUSPEND_RESUME,
+};
+
+// Windows constant for hiding console window
+pub const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+/// Decoded frames kept ahead of playback. ffmpeg blocks on the pipe once the
+/// queue is full, so this also bounds decode work for paused or slow previews.
+const VIDEO_STREAM_BUFFER_FRAMES: usize = 8;
+pub const DEFAULT_VIDEO_STREAM_FPS: f32 = 30.0;
+pub const MAX_VIDEO_STREAM_FPS: f32 = 60.0;
+/// Videos stopped earlier than this start over instead of resuming.
+const RESUME_MIN_SECS: f64 = 2.0;
+/// Videos stopped this close to their end start over instead of resuming.
+const RESUME_END_MARGIN_SECS: f64 = 1.0;
+
+pub type FrameQueue = Arc<Mutex<VecDeque<Arc<ImageFrame>>>>;
+
+/// `Command` for an FFmpeg tool: the configured executable, or the bare name
+/// resolved through `PATH` when none is set.
+fn tool_command(configured: Option<String>, name: &str) -> Command {
+    match configured.filter(|path| !path.is_empty()) {
+        Some(path) => Command::new(path),
+        None => Command::new(name),
+    }
+}
+
+pub fn ffmpeg_command() -> Command {
+    tool_command(CONFIG.lock().ok().map(|c| c.ffmpeg_path.clone()), "ffmpeg")
+}
+
+pub fn ffprobe_command() -> Command {
+    tool_command(
+        CONFIG.lock().ok().map(|c| c.ffprobe_path.clone()),
+        "ffprobe",
+    )
+}
+
+/// `ffplay`. Callers that play a file add `FfplayExtraArgs` to it.
+pub fn ffplay_command() -> Command {
+    tool_command(CONFIG.lock().ok().map(|c| c.ffplay_path.clone()), "ffplay")
+}
+
+/// The user's `ffplay_extra_args`. A `-vf` or `-af` in them is kept apart so
+/// callers can join it to the filter chain they build themselves, since
+/// ffplay only applies the last `-vf` and `-af` it is given.
+#[derive(Debug, Default, PartialEq)]
+pub struct FfplayExtraArgs {
+    pub video_filter: Option<String>,
+    pub audio_filter: Option<String>,
+    /// Every other argument, passed before the app's own options.
+    pub options: Vec<String>,
+}
+
+impl FfplayExtraArgs {
+    pub fn from_config() -> Self {
+        let line = CONFIG
+            .lock()
+            .map(|c| c.ffplay_extra_args.clone())
+            .unwrap_or_default();
+        Self::parse(&line)
+    }
+
+    fn parse(line: &str) -> Self {
+        let mut extra = Self::default();
+        let mut args = split_args(line).into_iter();
+        while let Some(arg) = args.next() {
+            let filter = match arg.as_str() {
+                "-vf" => &mut extra.video_filter,
+                "-af" => &mut extra.audio_filter,
+                _ => {
+                    extra.options.push(arg);
+                    continue;
+                }
+            };
+            *filter = args.next();
+        }
+        extra
+    }
+}
+
+/// Filter chains joined in order into one, leaving out the missing ones.
+pub fn join_filters(chains: &[Option<&str>]) -> Option<String> {
+    let chains: Vec<&str> = chains
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|chain| !chain.is_empty())
+        .collect();
+    (!chains.is_empty()).then(|| chains.join(","))
+}
+
+pub fn mpv_command() -> Command {
+    tool_command(CONFIG.lock().ok().map(|c| c.mpv_path.clone()), "mpv")
+}
+
+/// Split a command line on whitespace, keeping double-quoted runs together.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// How in-process playback runs: speed multiplier and whether it restarts at
+/// the end. Without looping the last frame stays on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackOptions {
+    pub speed: f32,
+    pub looping: bool,
+    /// 1-based audio track to play; 0 keeps the file's default track.
+    pub audio_track: u32,
+    /// Render the first embedded subtitle track into the video.
+    pub subtitles: bool,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            looping: true,
+            audio_track: 0,
+            subtitles: false,
+        }
+    }
+}
+
+/// ffmpeg `subtitles` filter drawing the first subtitle track of `path`.
+pub fn subtitles_filter(path: &Path) -> String {
+    format!("subtitles={}", escape_filter_path(path))
+}
+
+/// Escape a path for use as a filter option value inside a filtergraph: once
+/// for the option parser (`:` and `'`) and once for the graph (`\`, `'`,
+/// brackets, `,` and `;`). Backslashes become forward slashes first, which
+/// ffmpeg accepts on Windows.
+fn escape_filter_path(path: &Path) -> String {
+    let mut option = String::new();
+    for c in path.to_string_lossy().replace('\\', "/").chars() {
+        if c == ':' || c == '\'' {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+
+    let mut graph = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+/// Display delay per decoded frame at `fps` source frames per second.
+pub fn playback_frame_delay_ms(fps: f32, speed: f32) -> u32 {
+    ((1000.0 / (fps * speed)).round() as u32).max(1)
+}
+
+/// `atempo` filter chain for `speed`. A single `atempo` only accepts 0.5-2.0,
+/// so larger changes are split into several stages.
+pub fn atempo_filter(speed: f32) -> Option<String> {
+    if (speed - 1.0).abs() < 0.01 || !speed.is_finite() || speed <= 0.0 {
+        return None;
+    }
+    let mut stages = Vec::new();
+    let mut remaining = speed;
+    while remaining > 2.0 {
+        stages.push("atempo=2.0".to_string());
+        remaining /= 2.0;
+    }
+    while remaining < 0.5 {
+        stages.push("atempo=0.5".to_string());
+        remaining /= 0.5;
+    }
+    stages.push(format!("atempo={:.3}", remaining));
+    Some(stages.join(","))
+}
+
+/// Parse an ffprobe rational such as `30000/1001` or `25`.
+pub fn parse_frame_rate(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let rate = match value.split_once('/') {
+        Some((num, den)) => {
+            let num: f32 = num.trim().parse().ok()?;
+            let den: f32 = den.trim().parse().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num / den
+        }
+        None => value.parse().ok()?,
+    };
+
+    if rate.is_finite() && rate > 0.0 {
+        Some(rate)
+    } else {
+        None
+    }
+}
+
+/// Playback rate for the in-process stream, and whether ffmpeg must resample to it.
+pub fn stream_frame_rate(source_fps: Option<f32>) -> (f32, bool) {
+    match source_fps {
+        Some(fps) if fps <= MAX_VIDEO_STREAM_FPS => (fps, false),
+        Some(_) => (MAX_VIDEO_STREAM_FPS, true),
+        None => (DEFAULT_VIDEO_STREAM_FPS, true),
+    }
+}
+
+/// Spawn ffmpeg decoding `path` through `filter` into `width`x`height` BGRA
+/// frames and a reader thread that feeds them into a bounded queue.
+///
+/// Decoding starts `start_secs` into the file. When `playback` loops the
+/// stream restarts at the end; either way it stops when `cancel` is set.
+pub fn spawn_ffmpeg_frame_stream(
+    path: &PathBuf,
+    filter: &str,
+    width: u32,
+    height: u32,
+    frame_delay_ms: u32,
+    start_secs: f64,
+    playback: PlaybackOptions,
+    cancel: Arc<AtomicBool>,
+) -> Option<FrameQueue> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut command = ffmpeg_command();
+    command.args([
+        "-v",
+        "quiet",
+        "-nostdin",
+        "-err_detect",
+        "ignore_err",
+        "-fflags",
+        "+genpts+discardcorrupt+igndts",
+    ]);
+    if playback.looping {
+        command.args(["-stream_loop", "-1"]);
+    }
+    if start_secs > 0.0 {
+        command.args(["-ss", &format!("{:.3}", start_secs)]);
+    }
+    if playback.subtitles {
+        // Keep source timestamps after the seek so subtitles stay in sync.
+        command.arg("-copyts");
+    }
+    let mut child = command
+        .arg("-i")
+        .arg(path)
+        .args([
+            "-an", "-sn", "-vf", filter, "-pix_fmt", "bgra", "-f", "rawvideo", "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()
+        .ok()?;
+
+    let mut stdout = child.stdout.take()?;
+    let queue: FrameQueue = Arc::new(Mutex::new(VecDeque::new()));
+    let queue_clone = Arc::clone(&queue);
+    let frame_bytes = width as usize * height as usize * 4;
+
+    std::thread::spawn(move || {
+        loop {
+            if cancel.load(Ordering::Acquire) {
+                break;
+            }
+
+            let buffered = queue_clone.lock().map(|q| q.len()).unwrap_or(0);
+            if buffered >= VIDEO_STREAM_BUFFER_FRAMES {
+                std::thread::sleep(Duration::from_millis(4));
+                continue;
+            }
+
+            let mut pixels = vec![0u8; frame_bytes];
+            if stdout.read_exact(&mut pixels).is_err() {
+                break;
+            }
+
+            if let Ok(mut q) = queue_clone.lock() {
+                q.push_back(Arc::new(ImageFrame {
+                    pixels,
+                    width,
+                    height,
+                    delay_ms: frame_delay_ms,
+                }));
+            }
+        }
+
+        stop_child(&mut child);
+    });
+
+    Some(queue)
+}
+
+/// Decode one `width`x`height` BGRA frame at `at_secs` through `filter`.
+/// Seeking before `-i` jumps by keyframe index, so this stays fast on long files.
+/// `ffmpeg` is killed as soon as `cancel` is set.
+pub fn extract_ffmpeg_frame(
+    path: &PathBuf,
+    filter: &str,
+    at_secs: f64,
+    (width, height): (u32, u32),
+    cancel: &AtomicBool,
+) -> Option<ImageFrame> {
+    if width == 0 || height == 0 || cancel.load(Ordering::Acquire) {
+        return None;
+    }
+
+    let mut child = ffmpeg_command()
+        .args([
+            "-v",
+            "quiet",
+            "-nostdin",
+            "-ss",
+            &format!("{:.3}", at_secs.max(0.0)),
+            "-i",
+        ])
+        .arg(path)
+        .args([
+            "-an",
+            "-sn",
+            "-frames:v",
+            "1",
+            "-vf",
+            filter,
+            "-pix_fmt",
+            "bgra",
+            "-f",
+            "rawvideo",
+            "-",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()
+        .ok()?;
+    let mut stdout = child.stdout.take()?;
+    let child = Mutex::new(child);
+    let read_done = AtomicBool::new(false);
+
+    let pixels = std::thread::scope(|scope| {
+        // Killing ffmpeg closes the pipe, which ends the blocking read below.
+        scope.spawn(|| {
+            while !read_done.load(Ordering::Acquire) {
+                if cancel.load(Ordering::Acquire) {
+                    if let Ok(mut child) = child.lock() {
+                        let _ = child.kill();
+                    }
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        let read = stdout.read_exact(&mut pixels).is_ok();
+        read_done.store(true, Ordering::Release);
+        read.then_some(pixels)
+    });
+    if let Ok(mut child) = child.into_inner() {
+        stop_child(&mut child);
+    }
+    let pixels = pixels.filter(|_| !cancel.load(Ordering::Acquire))?;
+    Some(ImageFrame {
+        pixels,
+        width,
+        height,
+        delay_ms: 0,
+    })
+}
+
+/// Wait until the stream has produced its first frame.
+pub fn wait_for_first_frame(
+    queue: &FrameQueue,
+    timeout: Duration,
+    cancel: &AtomicBool,
+) -> Option<Arc<ImageFrame>> {
+    let started = std::time::Instant::now();
+    while started.elapsed() < timeout {
+        if cancel.load(Ordering::Acquire) {
+            return None;
+        }
+        if let Ok(mut q) = queue.lock() {
+            if let Some(frame) = q.pop_front() {
+                return Some(frame);
+            }
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    None
+}
+
+/// Audio for in-process playback comes from a display-less ffplay. With
+/// `-nodisp` it never creates a window, so there is nothing to steal focus.
+pub fn spawn_audio_player(
+    path: &PathBuf,
+    volume: u32,
+    start_secs: f64,
+    playback: PlaybackOptions,
+) -> Option<Child> {
+    if volume == 0 {
+        return None;
+    }
+
+    // The user's `-vf` is left out, as there is no video to apply it to.
+    let extra = FfplayExtraArgs::from_config();
+    let volume_filter = format!("volume={:.2}", volume.min(100) as f64 / 100.0);
+    let tempo = atempo_filter(playback.speed);
+    let audio_filter = join_filters(&[
+        Some(volume_filter.as_str()),
+        extra.audio_filter.as_deref(),
+        tempo.as_deref(),
+    ])
+    .unwrap_or_default();
+    let mut command = ffplay_command();
+    command.args(&extra.options);
+    command.args(["-nodisp", "-vn"]);
+    if playback.looping {
+        command.args(["-loop", "0"]);
+    } else {
+        command.arg("-autoexit");
+    }
+    command.args(["-af", &audio_filter, "-loglevel", "quiet"]);
+    if playback.audio_track > 0 {
+        command.args(["-ast", &format!("a:{}", playback.audio_track - 1)]);
+    }
+    if start_secs > 0.0 {
+        command.args(["-ss", &format!("{:.3}", start_secs)]);
+    }
+    command
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()
+        .ok()
+}
+
+/// Pause or continue a player process by suspending or resuming all of its
+/// threads. Players without a pause command of their own (ffplay only takes
+/// keys typed into its window) stop decoding and playing audio while
+/// suspended. False when its threads could not be listed.
+pub fn set_process_suspended(process: &Child, suspended: bool) -> bool {
+    let pid = process.id();
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) else {
+            return false;
+        };
+        let mut entry = THREADENTRY32 {
+            dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+            ..Default::default()
+        };
+        let mut more = Thread32First(snapshot, &mut entry).is_ok();
+        while more {
+            if entry.th32OwnerProcessID == pid {
+                if let Ok(thread) = OpenThread(THREAD_SUSPEND_RESUME, false, entry.th32ThreadID) {
+                    if suspended {
+                        SuspendThread(thread);
+                    } else {
+                        ResumeThread(thread);
+                    }
+                    let _ = CloseHandle(thread);
+                }
+            }
+            more = Thread32Next(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    true
+}
+
+/// Container duration in seconds, from ffprobe.
+pub fn probe_duration(path: &PathBuf) -> Option<f64> {
+    let output = ffprobe_command()
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+
+    let duration: f64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    if duration.is_finite() && duration > 0.0 {
+        Some(duration)
+    } else {
+        None
+    }
+}
+
+/// Stream details shown in the video info bar. Fields ffprobe does not
+/// report are left out of the summary.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VideoInfo {
+    pub duration_secs: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f32>,
+    pub codec: Option<String>,
+}
+
+impl VideoInfo {
+    /// `01:23  ·  1920x1080  ·  29.97 fps  ·  H264`
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(duration) = self.duration_secs {
+            parts.push(format_timestamp(duration));
+        }
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+        if let Some(fps) = self.frame_rate {
+            if (fps - fps.round()).abs() < 0.01 {
+                parts.push(format!("{} fps", fps.round()));
+            } else {
+                parts.push(format!("{:.2} fps", fps));
+            }
+        }
+        if let Some(codec) = &self.codec {
+            parts.push(codec.to_uppercase());
+        }
+        parts.join("  \u{b7}  ")
+    }
+}
+
+/// Duration, size, frame rate and codec of the first video stream, from ffprobe.
+pub fn probe_info(path: &PathBuf) -> Option<VideoInfo> {
+    let output = ffprobe_command()
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=codec_name,width,height,avg_frame_rate:format=duration",
+            "-of",
+            "default=noprint_wrappers=1",
+        ])
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_video_info(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse ffprobe's `key=value` lines; `N/A` and unknown keys are skipped.
+fn parse_video_info(text: &str) -> VideoInfo {
+    let mut info = VideoInfo::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "codec_name" if !value.is_empty() && value != "N/A" => {
+                info.codec = Some(value.to_string())
+            }
+            "width" => info.width = value.parse().ok().filter(|w| *w > 0),
+            "height" => info.height = value.parse().ok().filter(|h| *h > 0),
+            "avg_frame_rate" => info.frame_rate = parse_frame_rate(value),
+            "duration" => {
+                info.duration_secs = value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|d| d.is_finite() && *d > 0.0)
+            }
+            _ => {}
+        }
+    }
+    info
+}
+
+/// Evenly spaced sample times for `count` storyboard cells, each taken from
+/// the middle of its slice of the video so the first cell skips intros/black.
+pub fn storyboard_times(duration_secs: f64, count: usize) -> Vec<f64> {
+    (0..count)
+        .map(|index| duration_secs * (index as f64 + 0.5) / count as f64)
+        .collect()
+}
+
+/// `mm:ss`, or `h:mm:ss` from one hour on.
+pub fn format_timestamp(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, (total / 60) % 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+/// Seek position for a cursor at `fraction` (0.0 = left edge) of the preview.
+/// Stops short of the very end so the seek still lands on a decodable frame.
+pub fn scrub_position_secs(fraction: f32, duration_secs: f64) -> f64 {
+    let fraction = fraction.clamp(0.0, 1.0) as f64;
+    (duration_secs * fraction).min((duration_secs - 0.5).max(0.0))
+}
+
+/// Where to resume a video that was stopped after playing `played_secs`
+/// (counted from 0:00, including loops), or `None` to start from the beginning.
+pub fn resume_position_secs(played_secs: f64, duration_secs: f64, looping: bool) -> Option<f64> {
+    if duration_secs <= 0.0 {
+        return None;
+    }
+    let position = if looping {
+        played_secs % duration_secs
+    } else {
+        played_secs
+    };
+    (position >= RESUME_MIN_SECS && position < duration_secs - RESUME_END_MARGIN_SECS)
+        .then_some(position)
+}
+
+fn stop_child(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extra_args_split_on_whitespace_and_respect_quotes() {
+        assert_eq!(
+            split_args(r#"-vf "subtitles=C:/My Subs/a.srt"  -audio_device_index 2"#),
+            vec![
+                "-vf",
+                "subtitles=C:/My Subs/a.srt",
+                "-audio_device_index",
+                "2"
+            ]
+        );
+        assert_eq!(
+            split_args(r#"-metadata title="""#),
+            vec!["-metadata", "title="]
+        );
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn extra_filters_join_the_app_filter_chain() {
+        let extra = FfplayExtraArgs::parse(r#"-sync video -vf "subtitles=a.srt" -af"#);
+        assert_eq!(extra.options, vec!["-sync", "video"]);
+        assert_eq!(extra.video_filter.as_deref(), Some("subtitles=a.srt"));
+        assert_eq!(extra.audio_filter, None);
+
+        let vf = join_filters(&[
+            Some("crop=640:360:0:60,setsar=1"),
+            extra.video_filter.as_deref(),
+            Some("setpts=PTS/2.000"),
+        ]);
+        assert_eq!(
+            vf.as_deref(),
+            Some("crop=640:360:0:60,setsar=1,subtitles=a.srt,setpts=PTS/2.000")
+        );
+        assert_eq!(join_filters(&[None, Some("")]), None);
+    }
+
+    #[test]
+    fn atempo_is_split_into_supported_stages() {
+        assert_eq!(atempo_filter(1.0), None);
+        assert_eq!(atempo_filter(1.5).as_deref(), Some("atempo=1.500"));
+        assert_eq!(
+            atempo_filter(4.0).as_deref(),
+            Some("atempo=2.0,atempo=2.000")
+        );
+        assert_eq!(
+            atempo_filter(0.25).as_deref(),
+            Some("atempo=0.5,atempo=0.500")
+        );
+    }
+
+    #[test]
+    fn frame_delay_shrinks_with_speed() {
+        assert_eq!(playback_frame_delay_ms(25.0, 1.0), 40);
+        assert_eq!(playback_frame_delay_ms(25.0, 2.0), 20);
+        assert_eq!(playback_frame_delay_ms(30.0, 0.5), 67);
+    }
+
+    #[test]
+    fn frame_rate_parses_ffprobe_rationals() {
+        let ntsc = parse_frame_rate("30000/1001").expect("rational rate");
+        assert!((ntsc - 29.97).abs() < 0.01);
+        assert_eq!(parse_frame_rate("25"), Some(25.0));
+        assert_eq!(parse_frame_rate("0/0"), None);
+        assert_eq!(parse_frame_rate(""), None);
+    }
+
+    #[test]
+    fn stream_frame_rate_caps_and_defaults() {
+        assert_eq!(stream_frame_rate(Some(24.0)), (24.0, false));
+        assert_eq!(stream_frame_rate(Some(120.0)), (MAX_VIDEO_STREAM_FPS, true));
+        assert_eq!(stream_frame_rate(None), (DEFAULT_VIDEO_STREAM_FPS, true));
+    }
+
+    #[test]
+    fn storyboard_samples_centers_of_equal_slices() {
+        assert_eq!(storyboard_times(90.0, 9)[0], 5.0);
+        assert_eq!(storyboard_times(90.0, 9)[8], 85.0);
+        assert!(storyboard_times(0.0, 3).iter().all(|t| *t == 0.0));
+    }
+
+    #[test]
+    fn timestamps_switch_to_hours_when_needed() {
+        assert_eq!(format_timestamp(65.4), "01:05");
+        assert_eq!(format_timestamp(3725.0), "1:02:05");
+        assert_eq!(format_timestamp(-3.0), "00:00");
+    }
+
+    #[test]
+    fn scrub_position_maps_fraction_and_clamps_to_end() {
+        assert_eq!(scrub_position_secs(0.0, 60.0), 0.0);
+        assert_eq!(scrub_position_secs(0.5, 60.0), 30.0);
+        assert_eq!(scrub_position_secs(1.0, 60.0), 59.5);
+        assert_eq!(scrub_position_secs(-1.0, 60.0), 0.0);
+        assert_eq!(scrub_position_secs(1.0, 0.2), 0.0);
+    }
+
+    #[test]
+    fn resume_position_wraps_loops_and_restarts_finished_videos() {
+        assert_eq!(resume_position_secs(42.0, 60.0, false), Some(42.0));
+        assert_eq!(resume_position_secs(130.0, 60.0, true), Some(10.0));
+        assert_eq!(resume_position_secs(130.0, 60.0, false), None);
+        assert_eq!(resume_position_secs(59.5, 60.0, false), None);
+        assert_eq!(resume_position_secs(1.0, 60.0, false), None);
+        assert_eq!(resume_position_secs(42.0, 0.0, true), None);
+    }
+
+    #[test]
+    fn video_info_parses_ffprobe_output_and_summarizes() {
+        let info = parse_video_info(
+            "codec_name=h264\nwidth=1920\nheight=1080\navg_frame_rate=30000/1001\nduration=83.4\n",
+        );
+        assert_eq!(
+            info.summary(),
+            "01:23  \u{b7}  1920x1080  \u{b7}  29.97 fps  \u{b7}  H264"
+        );
+
+        let partial = parse_video_info("codec_name=vp9\navg_frame_rate=0/0\nduration=N/A\n");
+        assert_eq!(partial.frame_rate, None);
+        assert_eq!(partial.duration_secs, None);
+        assert_eq!(partial.summary(), "VP9");
+
+        let integral = VideoInfo {
+            frame_rate: Some(25.0),
+            ..Default::default()
+        };
+        assert_eq!(integral.summary(), "25 fps");
+    }
+
+    #[test]
+    fn subtitle_paths_are_escaped_for_filtergraphs() {
+        assert_eq!(
+            subtitles_filter(Path::new("C:\\Videos\\a,b's [x].mkv")),
+            "subtitles=C\\\\:/Videos/a\\,b\\\\\\'s \\[x\\].mkv"
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Warming the thumbnail cache at startup from the folders previewed most.
+//!
+//! With `warm_start`, each loaded preview counts towards its folder in
+//! `warm_start.txt` next to `config.ini`, together with the bounds it was
+//! decoded to fit. When the app starts, a thread at idle priority waits a
+//! little, then decodes the newest stills of the most previewed folders at
+//! those bounds, so the slow ones are already in the thumbnail cache when
+//! first hovered. Videos and animations are skipped, as the thumbnail cache
+//! only holds stills.
+
+use crate::config::AppConfig;
+use crate::excluded_paths;
+use crate::explorer_hook::same_path;
+use crate::network_drives;
+use crate::preview_window;
+use crate::priority;
+use crate::providers;
+use crate::system_load;
+use crate::{CONFIG, RUNNING};
+use once_cell::sync::Lazy;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use windows::Win32::System::Threading::{
+    GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_IDLE,
+};
+
+const WARM_START_FILE: &str = "warm_start.txt";
+/// Folders warmed at startup, most previewed first.
+const WARMED_FOLDERS: usize = 5;
+/// Newest stills decoded per folder.
+const WARMED_FILES_PER_FOLDER: usize = 20;
+/// Folders remembered; the least previewed are forgotten beyond this.
+const MAX_TRACKED_FOLDERS: usize = 200;
+/// Recorded previews between saves; the rest are saved on exit.
+const SAVE_EVERY: u32 = 10;
+/// Time left to the rest of sign-in before warming starts.
+const STARTUP_DELAY: Duration = Duration::from_secs(30);
+const BUSY_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// Folders with more entries than this are not listed.
+const MAX_LISTED_ENTRIES: usize = 20_000;
+
+/// How often a folder was previewed from, and the bounds of the last preview.
+#[derive(Debug, Clone, PartialEq)]
+struct FolderUse {
+    folder: PathBuf,
+    previews: u32,
+    max_width: u32,
+    max_height: u32,
+}
+
+static FOLDERS: Lazy<Mutex<Vec<FolderUse>>> = Lazy::new(|| {
+    let folders = warm_start_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| parse(&text))
+        .unwrap_or_default();
+    Mutex::new(folders)
+});
+/// Previews recorded since the last save.
+static UNSAVED: Mutex<u32> = Mutex::new(0);
+
+fn is_enabled() -> bool {
+    CONFIG.lock().map(|c| c.warm_start).unwrap_or(false)
+}
+
+/// Count a preview of `path`, decoded to fit `max_width` x `max_height`,
+/// towards its folder.
+pub fn record(path: &Path, max_width: u32, max_height: u32) {
+    if !is_enabled() {
+        return;
+    }
+    let Some(folder) = path.parent() else {
+        return;
+    };
+    let Ok(mut folders) = FOLDERS.lock() else {
+        return;
+    };
+    let recorded = folders
+        .iter_mut()
+        .find(|used| same_path(&used.folder, folder));
+    match recorded {
+        Some(used) => {
+            used.previews = used.previews.saturating_add(1);
+            used.max_width = max_width;
+            used.max_height = max_height;
+        }
+        None => folders.push(FolderUse {
+            folder: folder.to_path_buf(),
+            previews: 1,
+            max_width,
+            max_height,
+        }),
+    }
+    folders.sort_by(|a, b| b.previews.cmp(&a.previews));
+    folders.truncate(MAX_TRACKED_FOLDERS);
+
+    let due = UNSAVED
+        .lock()
+        .map(|mut unsaved| {
+            *unsaved += 1;
+            *unsaved >= SAVE_EVERY
+        })
+        .unwrap_or(false);
+    if due {
+        write(&folders);
+    }
+}
+
+/// Write the counts recorded since the last save.
+pub fn save() {
+    if UNSAVED.lock().map(|unsaved| *unsaved == 0).unwrap_or(true) {
+        return;
+    }
+    if let Ok(folders) = FOLDERS.lock() {
+        write(&folders);
+    }
+}
+
+/// Start warming the thumbnail cache in the background.
+pub fn start() {
+    let enabled = CONFIG
+        .lock()
+        .map(|c| c.warm_start && c.thumbnail_cache_mb > 0)
+        .unwrap_or(false);
+    if enabled {
+        std::thread::spawn(run);
+    }
+}
+
+fn run() {
+    unsafe {
+        let _ = SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_IDLE);
+    }
+    priority::use_decode_cores();
+    std::thread::sleep(STARTUP_DELAY);
+
+    let folders: Vec<FolderUse> = FOLDERS
+        .lock()
+        .map(|folders| folders.iter().take(WARMED_FOLDERS).cloned().collect())
+        .unwrap_or_default();
+    for used in folders {
+        if !network_drives::is_previewable(&used.folder) {
+            continue;
+        }
+        for path in newest_stills(&used.folder) {
+            while system_load::is_high() && RUNNING.load(Ordering::Acquire) {
+                std::thread::sleep(BUSY_RETRY_INTERVAL);
+            }
+            if !RUNNING.load(Ordering::Acquire) {
+                return;
+            }
+            preview_window::warm_thumbnail_cache(&path, used.max_width, used.max_height);
+        }
+    }
+}
+
+/// The newest previewable stills of `folder`, newest first.
+fn newest_stills(folder: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut stills: Vec<(SystemTime, PathBuf)> = entries
+        .take(MAX_LISTED_ENTRIES)
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .filter(|(_, path)| {
+            providers::is_supported(path)
+                && !providers::is_video(path)
+                && !excluded_paths::is_excluded(path)
+        })
+        .collect();
+    stills.sort_by(|a, b| b.0.cmp(&a.0));
+    stills
+        .into_iter()
+        .take(WARMED_FILES_PER_FOLDER)
+        .map(|(_, path)| path)
+        .collect()
+}
+
+fn warm_start_path() -> Option<PathBuf> {
+    Some(AppConfig::config_path()?.parent()?.join(WARM_START_FILE))
+}
+
+fn write(folders: &[FolderUse]) {
+    if let Ok(mut unsaved) = UNSAVED.lock() {
+        *unsaved = 0;
+    }
+    let Some(path) = warm_start_path() else {
+        return;
+    };
+    let text: String = folders
+        .iter()
+        .map(|used| {
+            format!(
+                "{}\t{}x{}\t{}\n",
+                used.previews,
+                used.max_width,
+                used.max_height,
+                used.folder.display()
+            )
+        })
+        .collect();
+    if let Some(folder) = path.parent() {
+        let _ = fs::create_dir_all(folder);
+    }
+    let _ = fs::write(path, text);
+}
+
+/// Lines of `warm_start.txt`: the preview count, the bounds as `WxH` and the
+/// folder, separated by tabs. Malformed lines are skipped.
+fn parse(text: &str) -> Vec<FolderUse> {
+    let mut folders: Vec<FolderUse> = text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().splitn(3, '\t');
+            let previews = fields.next()?.parse().ok()?;
+            let (max_width, max_height) = fields.next()?.split_once('x')?;
+            let folder = fields.next()?.trim();
+            if folder.is_empty() {
+                return None;
+            }
+            Some(FolderUse {
+                folder: PathBuf::from(folder),
+                previews,
+                max_width: max_width.parse().ok()?,
+                max_height: max_height.parse().ok()?,
+            })
+        })
+        .collect();
+    folders.sort_by(|a, b| b.previews.cmp(&a.previews));
+    folders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_are_read_most_previewed_first() {
+        let text = "3\t800x600\tC:\\Photos\n\
+                    bad line\n\
+                    12\t1280x720\tD:\\Renders\\Final\n\
+                    4\t800x\tC:\\Broken\n";
+        let folders = parse(text);
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[0].folder, PathBuf::from("D:\\Renders\\Final"));
+        assert_eq!(folders[0].previews, 12);
+        assert_eq!((folders[0].max_width, folders[0].max_height), (1280, 720));
+        assert_eq!(folders[1].folder, PathBuf::from("C:\\Photos"));
+        assert!(parse("").is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! Zooming into still previews with the mouse wheel and panning by dragging.
+//!
+//! A zoomed preview keeps its window size and shows part of the file decoded
+//! at full size. `View` says which part: the scale, in preview pixels per file
+//! pixel, and the file position at the preview's top-left corner. Scales run
+//! from the fit the preview was laid out at up to `MAX_SCALE`, and the view is
+//! kept on the file; a file smaller than the preview stays centered.
+
+use rayon::prelude::*;
+
+/// Furthest zoom, in preview pixels per file pixel.
+const MAX_SCALE: f32 = 8.0;
+/// Zoom change per wheel notch.
+const NOTCH_FACTOR: f32 = 1.25;
+/// `WHEEL_DELTA`, one notch of a standard mouse wheel.
+const WHEEL_DELTA: f32 = 120.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    /// Preview pixels per file pixel.
+    pub scale: f32,
+    /// File position shown at the preview's top-left corner.
+    pub origin: (f32, f32),
+}
+
+impl View {
+    /// The whole file fitted to `viewport`, as the unzoomed preview shows it.
+    pub fn fit(source: (u32, u32), viewport: (u32, u32)) -> Self {
+        View {
+            scale: fit_scale(source, viewport),
+            origin: (0.0, 0.0),
+        }
+        .clamped(source, viewport)
+    }
+
+    /// Zoom by a wheel rotation of `wheel_delta`, keeping the file position
+    /// under `anchor` (a position in the viewport) in place.
+    pub fn zoomed(
+        self,
+        wheel_delta: i32,
+        anchor: (f32, f32),
+        source: (u32, u32),
+        viewport: (u32, u32),
+    ) -> Self {
+        let fit = fit_scale(source, viewport);
+        let factor = NOTCH_FACTOR.powf(wheel_delta as f32 / WHEEL_DELTA);
+        let scale = (self.scale * factor).clamp(fit, MAX_SCALE.max(fit));
+        let point = (
+            self.origin.0 + anchor.0 / self.scale,
+            self.origin.1 + anchor.1 / self.scale,
+        );
+        View {
+            scale,
+            origin: (point.0 - anchor.0 / scale, point.1 - anchor.1 / scale),
+        }
+        .clamped(source, viewport)
+    }
+
+    /// Move the file with a drag of `delta` viewport pixels.
+    pub fn panned(self, delta: (i32, i32), source: (u32, u32), viewport: (u32, u32)) -> Self {
+        View {
+            scale: self.scale,
+            origin: (
+                self.origin.0 - delta.0 as f32 / self.scale,
+                self.origin.1 - delta.1 as f32 / self.scale,
+            ),
+        }
+        .clamped(source, viewport)
+    }
+
+    /// Whether the view is back to showing the whole file.
+    pub fn is_fit(self, source: (u32, u32), viewport: (u32, u32)) -> bool {
+        self.scale <= fit_scale(source, viewport) * 1.001
+    }
+
+    /// The scale as a label, such as `250%`.
+    pub fn label(self) -> String {
+        format!("{}%", (self.scale * 100.0).round() as u32)
+    }
+
+    fn clamped(self, source: (u32, u32), viewport: (u32, u32)) -> Self {
+        let clamp = |origin: f32, source: u32, viewport: u32| {
+            let max = source as f32 - viewport as f32 / self.scale;
+            if max <= 0.0 {
+                max / 2.0
+            } else {
+                origin.clamp(0.0, max)
+            }
+        };
+        View {
+            scale: self.scale,
+            origin: (
+                clamp(self.origin.0, source.0, viewport.0),
+                clamp(self.origin.1, source.1, viewport.1),
+            ),
+        }
+    }
+}
+
+fn fit_scale(source: (u32, u32), viewport: (u32, u32)) -> f32 {
+    let scale_x = viewport.0 as f32 / source.0.max(1) as f32;
+    let scale_y = viewport.1 as f32 / source.1.max(1) as f32;
+    scale_x.min(scale_y)
+}
+
+/// The part of `source` (BGRA, `size`) that `view` shows, as a BGRA frame of
+/// `viewport` size. Enlarged file pixels are repeated and reduced ones are
+/// averaged; the preview outside the file is transparent.
+pub fn render(source: &[u8], size: (u32, u32), view: View, viewport: (u32, u32)) -> Vec<u8> {
+    let (width, height) = (viewport.0 as usize, viewport.1 as usize);
+    let mut pixels = vec![0u8; width * height * 4];
+    if width == 0 || height == 0 || source.len() < size.0 as usize * size.1 as usize * 4 {
+        return pixels;
+    }
+    let step = 1.0 / view.scale;
+    let columns: Vec<Option<(usize, usize)>> = (0..width)
+        .map(|x| span(view.origin.0 + x as f32 * step, step, size.0))
+        .collect();
+    let stride = size.0 as usize * 4;
+
+    pixels
+        .par_chunks_mut(width * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let Some((top, bottom)) = span(view.origin.1 + y as f32 * step, step, size.1) else {
+                return;
+            };
+            for (px, column) in row.chunks_exact_mut(4).zip(&columns) {
+                let Some((left, right)) = *column else {
+                    continue;
+                };
+                if right - left == 1 && bottom - top == 1 {
+                    let at = top * stride + left * 4;
+                    px.copy_from_slice(&source[at..at + 4]);
+                    continue;
+                }
+                // Weight colors by alpha so transparent pixels do not darken
+                // the edges of what is visible.
+                let mut sums = [0u64; 4];
+                for line in top..bottom {
+                    let from = line * stride + left * 4;
+                    for src in source[from..line * stride + right * 4].chunks_exact(4) {
+                        let alpha = src[3] as u64;
+                        sums[0] += src[0] as u64 * alpha;
+                        sums[1] += src[1] as u64 * alpha;
+                        sums[2] += src[2] as u64 * alpha;
+                        sums[3] += alpha;
+                    }
+                }
+                let count = ((right - left) * (bottom - top)) as u64;
+                if sums[3] > 0 {
+                    for (channel, sum) in px[..3].iter_mut().zip(&sums[..3]) {
+                        *channel = (sum / sums[3]) as u8;
+                    }
+                }
+                px[3] = (sums[3] / count) as u8;
+            }
+        });
+    pixels
+}
+
+/// File pixels `[first, last)` covered by a preview pixel starting at file
+/// position `start` and `step` file pixels wide.
+fn span(start: f32, step: f32, len: u32) -> Option<(usize, usize)> {
+    if start < 0.0 || start >= len as f32 {
+        return None;
+    }
+    let first = start as usize;
+    let last = if step <= 1.0 {
+        first + 1
+    } else {
+        ((start + step).ceil() as usize).min(len as usize)
+    };
+    Some((first, last.max(first + 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_keeps_the_anchor_in_place_and_the_view_on_the_file() {
+        let (source, viewport) = ((4000, 2000), (400, 200));
+        let fit = View::fit(source, viewport);
+        assert_eq!(fit.scale, 0.1);
+        assert!(fit.is_fit(source, viewport));
+
+        // Four notches in over the middle of the preview.
+        let zoomed = fit.zoomed(480, (200.0, 100.0), source, viewport);
+        assert!((zoomed.scale - 0.1 * 1.25f32.powi(4)).abs() < 1e-6);
+        let under_anchor = zoomed.origin.0 + 200.0 / zoomed.scale;
+        assert!((under_anchor - 2000.0).abs() < 0.01);
+
+        // Zoom is capped, and zooming out stops at the fit.
+        assert_eq!(
+            fit.zoomed(120 * 40, (0.0, 0.0), source, viewport).scale,
+            MAX_SCALE
+        );
+        assert!(zoomed
+            .zoomed(-120 * 40, (0.0, 0.0), source, viewport)
+            .is_fit(source, viewport));
+
+        // Panning stops at the file's edges.
+        let panned = zoomed.panned((100_000, -100_000), source, viewport);
+        assert_eq!(panned.origin.0, 0.0);
+        assert!((panned.origin.1 - (2000.0 - 200.0 / zoomed.scale)).abs() < 0.01);
+        assert_eq!(zoomed.label(), "24%");
+    }
+
+    #[test]
+    fn render_repeats_enlarged_pixels_and_averages_reduced_ones() {
+        // Two opaque pixels side by side, then a transparent one under each.
+        let source = [10, 20, 30, 255, 50, 60, 70, 255, 0, 0, 0, 0, 0, 0, 0, 0];
+        let doubled = View {
+            scale: 2.0,
+            origin: (0.0, 0.0),
+        };
+        let pixels = render(&source, (2, 2), doubled, (4, 1));
+        assert_eq!(
+            pixels,
+            [10, 20, 30, 255, 10, 20, 30, 255, 50, 60, 70, 255, 50, 60, 70, 255]
+        );
+
+        let halved = View {
+            scale: 0.5,
+            origin: (0.0, 0.0),
+        };
+        assert_eq!(render(&source, (2, 2), halved, (1, 1)), [30, 40, 50, 127]);
+        // Outside the file is transparent.
+        assert_eq!(render(&source, (2, 2), halved, (2, 1))[4..], [0, 0, 0, 0]);
+    }
+}